@@ -20,10 +20,11 @@ OPTIONS:
 
 DESCRIPTION:
     This script monitors and automatically restarts cryptocurrency data collection
-    processes. It runs 7 processes for different exchanges and market types:
+    processes. It runs 8 processes for different exchanges and market types:
     - Bybit: spot, linear, inverse
     - Binance: spot, linear, inverse  
     - Hyperliquid: linear
+    - Bitstamp: spot
 
     Logs are automatically rotated when they exceed size limits.
 
@@ -85,6 +86,7 @@ PROCESSES=(
     "binance_linear:pids/binance_linear.pid:./target/debug/binance             --raw-freq 100 -t 5,10,60 --linear  $UPDATE_FLAG --symbols BTCUSDT,ETHUSDT,XRPUSDT,BNBUSDT,SOLUSDT"
     "binance_inverse:pids/binance_inverse.pid:./target/debug/binance           --raw-freq 100 -t 5,10,60 --inverse $UPDATE_FLAG --symbols BTCUSD_PERP,ETHUSD_PERP,XRPUSD_PERP,BNBUSD_PERP,SOLUSD_PERP"
     "hyperliquid_linear:pids/hyperliquid_linear.pid:./target/debug/hyperliquid --raw-freq 100 -t 5,10,60 --linear  $UPDATE_FLAG --symbols BTC,ETH,XRP,BNB,SOL,HYPE"
+    "bitstamp_spot:pids/bitstamp_spot.pid:./target/debug/bitstamp             --raw-freq 100 -t 5,10,60 --spot    $UPDATE_FLAG --symbols BTCEUR,ETHEUR,XRPEUR,SOLEUR"
 )
 
 restart_process() {
@@ -106,7 +108,7 @@ restart_process() {
 # ログローテーション実行
 cat > /tmp/kkcrypto_logrotate.conf << EOF
 # プロセスログ（monitor.log以外）
-$PWD/logs/bybit_*.log $PWD/logs/binance_*.log $PWD/logs/hyperliquid_*.log {
+$PWD/logs/bybit_*.log $PWD/logs/binance_*.log $PWD/logs/hyperliquid_*.log $PWD/logs/bitstamp_*.log {
     size 50M
     rotate 5
     compress
@@ -0,0 +1,15 @@
+use super::{build_query, hmac_sha256_hex, Credentials};
+
+pub const API_KEY_HEADER: &str = "X-MBX-APIKEY";
+
+/// Binance SIGNED エンドポイント用の署名
+/// timestamp / recvWindow を付与したクエリ文字列の末尾に signature を付けて返す
+pub fn sign_query(credentials: &Credentials, params: &[(&str, String)], timestamp_ms: i64, recv_window_ms: u64) -> String {
+    let mut params = params.to_vec();
+    params.push(("recvWindow", recv_window_ms.to_string()));
+    params.push(("timestamp", timestamp_ms.to_string()));
+
+    let query = build_query(&params);
+    let signature = hmac_sha256_hex(&credentials.api_secret, &query);
+    format!("{}&signature={}", query, signature)
+}
@@ -0,0 +1,32 @@
+use super::{hmac_sha256_hex, Credentials};
+
+pub const API_KEY_HEADER: &str = "X-BAPI-API-KEY";
+pub const SIGN_HEADER: &str = "X-BAPI-SIGN";
+pub const TIMESTAMP_HEADER: &str = "X-BAPI-TIMESTAMP";
+pub const RECV_WINDOW_HEADER: &str = "X-BAPI-RECV-WINDOW";
+
+/// Bybit v5 REST の署名
+/// payload は GET ならクエリ文字列, POST なら JSON ボディ
+pub fn sign_request(credentials: &Credentials, timestamp_ms: i64, recv_window_ms: u64, payload: &str) -> String {
+    let message = format!("{}{}{}{}", timestamp_ms, credentials.api_key, recv_window_ms, payload);
+    hmac_sha256_hex(&credentials.api_secret, &message)
+}
+
+/// REST リクエストに付与するヘッダー一式
+pub fn request_headers(credentials: &Credentials, timestamp_ms: i64, recv_window_ms: u64, payload: &str) -> Vec<(&'static str, String)> {
+    vec![
+        (API_KEY_HEADER, credentials.api_key.clone()),
+        (SIGN_HEADER, sign_request(credentials, timestamp_ms, recv_window_ms, payload)),
+        (TIMESTAMP_HEADER, timestamp_ms.to_string()),
+        (RECV_WINDOW_HEADER, recv_window_ms.to_string()),
+    ]
+}
+
+/// Private WebSocket の auth メッセージ ({"op":"auth","args":[key, expires, signature]})
+pub fn websocket_auth_message(credentials: &Credentials, expires_ms: i64) -> serde_json::Value {
+    let signature = hmac_sha256_hex(&credentials.api_secret, &format!("GET/realtime{}", expires_ms));
+    serde_json::json!({
+        "op": "auth",
+        "args": [credentials.api_key, expires_ms, signature],
+    })
+}
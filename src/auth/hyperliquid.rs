@@ -0,0 +1,51 @@
+use super::Credentials;
+use anyhow::Result;
+use ed25519_dalek::{Signer, SigningKey, Verifier, VerifyingKey, Signature};
+
+/// Ed25519 署名器. api_secret に 32 byte の秘密鍵 (hex, 0x 接頭辞可) を設定する
+pub struct Ed25519Signer {
+    signing_key: SigningKey,
+}
+
+impl Ed25519Signer {
+    pub fn from_secret_hex(secret: &str) -> Result<Self> {
+        let bytes = hex::decode(secret.trim().trim_start_matches("0x"))?;
+        let seed: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Ed25519 secret key must be 32 bytes"))?;
+        Ok(Self {
+            signing_key: SigningKey::from_bytes(&seed),
+        })
+    }
+
+    pub fn from_credentials(credentials: &Credentials) -> Result<Self> {
+        Self::from_secret_hex(&credentials.api_secret)
+    }
+
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.signing_key.verifying_key().to_bytes())
+    }
+
+    /// 署名 (hex)
+    pub fn sign(&self, message: &[u8]) -> String {
+        hex::encode(self.signing_key.sign(message).to_bytes())
+    }
+
+    /// アクションの JSON を署名する (nonce はミリ秒時刻)
+    pub fn sign_action(&self, action: &serde_json::Value, nonce_ms: i64) -> Result<String> {
+        let payload = format!("{}{}", serde_json::to_string(action)?, nonce_ms);
+        Ok(self.sign(payload.as_bytes()))
+    }
+}
+
+/// 公開鍵 (hex) で署名 (hex) を検証する
+pub fn verify(public_key_hex: &str, message: &[u8], signature_hex: &str) -> Result<bool> {
+    let key_bytes: [u8; 32] = hex::decode(public_key_hex.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Ed25519 public key must be 32 bytes"))?;
+    let sig_bytes: [u8; 64] = hex::decode(signature_hex.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Ed25519 signature must be 64 bytes"))?;
+    let key = VerifyingKey::from_bytes(&key_bytes)?;
+    Ok(key.verify(message, &Signature::from_bytes(&sig_bytes)).is_ok())
+}
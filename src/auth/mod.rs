@@ -0,0 +1,89 @@
+pub mod binance;
+pub mod bybit;
+pub mod hyperliquid;
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+
+/// API キーとシークレット
+/// 読み込み順: {PREFIX}_KEY_FILE のファイル -> 環境変数 {PREFIX}_API_KEY / {PREFIX}_API_SECRET
+#[derive(Clone)]
+pub struct Credentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // シークレットはログに出さない
+        f.debug_struct("Credentials")
+            .field("api_key", &self.api_key)
+            .field("api_secret", &"***")
+            .finish()
+    }
+}
+
+impl Credentials {
+    pub fn new(api_key: &str, api_secret: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        }
+    }
+
+    /// 取引所名 (e.g. "binance") から認証情報を読み込む
+    pub fn load(exchange: &str) -> Result<Self> {
+        let prefix = exchange.to_uppercase();
+        match std::env::var(format!("{}_KEY_FILE", prefix)) {
+            Ok(path) => Self::from_file(&path),
+            Err(_) => Self::from_env(&prefix),
+        }
+    }
+
+    pub fn from_env(prefix: &str) -> Result<Self> {
+        let api_key = std::env::var(format!("{}_API_KEY", prefix))
+            .map_err(|_| anyhow::anyhow!("{}_API_KEY must be set", prefix))?;
+        let api_secret = std::env::var(format!("{}_API_SECRET", prefix))
+            .map_err(|_| anyhow::anyhow!("{}_API_SECRET must be set", prefix))?;
+        Ok(Self { api_key, api_secret })
+    }
+
+    /// API_KEY=... / API_SECRET=... 形式のファイルから読み込む
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let values: HashMap<&str, &str> = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+            .map(|(k, v)| (k.trim(), v.trim().trim_matches('"')))
+            .collect();
+
+        let api_key = values
+            .get("API_KEY")
+            .ok_or_else(|| anyhow::anyhow!("API_KEY not found in {}", path))?;
+        let api_secret = values
+            .get("API_SECRET")
+            .ok_or_else(|| anyhow::anyhow!("API_SECRET not found in {}", path))?;
+        Ok(Self::new(api_key, api_secret))
+    }
+}
+
+/// HMAC-SHA256 の hex 署名
+pub fn hmac_sha256_hex(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// クエリ文字列を組み立てる (パラメータの順序は保持)
+pub fn build_query(params: &[(&str, String)]) -> String {
+    params
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&")
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::account;
+
+// 互換のための薄いラッパー (kkcrypto account と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    account::run(account::Args::parse()).await
+}
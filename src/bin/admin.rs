@@ -0,0 +1,7 @@
+use clap::Parser;
+use kkcrypto::cli::admin;
+
+// 互換のための薄いラッパー (kkcrypto admin と同じ)
+fn main() -> anyhow::Result<()> {
+    admin::run(admin::Args::parse())
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::backfill;
+
+// 互換のための薄いラッパー (kkcrypto backfill と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    backfill::run(backfill::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::backpack;
+
+// 互換のための薄いラッパー (kkcrypto collect backpack と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    backpack::run(backpack::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::bitstamp;
+
+// 互換のための薄いラッパー (kkcrypto collect bitstamp と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    bitstamp::run(bitstamp::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::collector;
+
+// 互換のための薄いラッパー (kkcrypto collect config と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    collector::run(collector::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::downsample;
+
+// 互換のための薄いラッパー (kkcrypto downsample と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    downsample::run(downsample::Args::parse()).await
+}
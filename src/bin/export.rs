@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::export;
+
+// 互換のための薄いラッパー (kkcrypto export と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    export::run(export::Args::parse()).await
+}
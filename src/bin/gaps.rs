@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::gaps;
+
+// 互換のための薄いラッパー (kkcrypto gaps と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    gaps::run(gaps::Args::parse()).await
+}
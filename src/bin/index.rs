@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::index;
+
+// 互換のための薄いラッパー (kkcrypto index と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    index::run(index::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::pairs;
+
+// 互換のための薄いラッパー (kkcrypto pairs と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pairs::run(pairs::Args::parse()).await
+}
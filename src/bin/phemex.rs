@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::phemex;
+
+// 互換のための薄いラッパー (kkcrypto collect phemex と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    phemex::run(phemex::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::quality;
+
+// 互換のための薄いラッパー (kkcrypto quality と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    quality::run(quality::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::replay;
+
+// 互換のための薄いラッパー (kkcrypto replay と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    replay::run(replay::Args::parse()).await
+}
@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::trades;
+
+// 互換のための薄いラッパー (kkcrypto trades と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    trades::run(trades::Args::parse()).await
+}
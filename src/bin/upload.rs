@@ -0,0 +1,8 @@
+use clap::Parser;
+use kkcrypto::cli::upload;
+
+// 互換のための薄いラッパー (kkcrypto upload と同じ)
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    upload::run(upload::Args::parse()).await
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::LogArgs;
+use crate::{
+    auth::Credentials,
+    db::Database,
+    execution::{
+        account::{AccountTracker, AccountUpdate},
+        binance::BinanceExecutionClient,
+        bybit::BybitExecutionClient,
+        ExecutionClient,
+    },
+    models::market_type::MarketType,
+};
+use std::env;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[command(name = "account")]
+#[command(about = "Track account balances and positions and store them as time series", long_about = None)]
+pub struct Args {
+    /// Exchange (bybit or binance)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Database URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Update database (if not set, only print data)
+    #[arg(long)]
+    update: bool,
+
+    /// Use the testnet REST endpoints with {EXCHANGE}_TESTNET_* credentials (stored under the "testnet" namespace unless --namespace is given)
+    #[arg(long)]
+    testnet: bool,
+
+    /// Use spot market
+    #[arg(long)]
+    spot: bool,
+
+    /// Use linear futures market
+    #[arg(long)]
+    linear: bool,
+
+    /// Use inverse futures market
+    #[arg(long)]
+    inverse: bool,
+
+    /// Polling interval in seconds
+    #[arg(short, long, default_value = "10")]
+    interval: u64,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("account")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    // Determine market type
+    let market_type = match (args.spot, args.linear, args.inverse) {
+        (true, false, false) => MarketType::Spot,
+        (false, true, false) => MarketType::Linear,
+        (false, false, true) => MarketType::Inverse,
+        (false, false, false) => {
+            error!("Must specify one of --spot, --linear, or --inverse");
+            std::process::exit(1);
+        },
+        _ => {
+            error!("Can only specify one market type at a time");
+            std::process::exit(1);
+        }
+    };
+
+    // テストネットの API キーは本番と別 (e.g. BYBIT_TESTNET_API_KEY)
+    let credentials = if args.testnet {
+        Credentials::load(&format!("{}_testnet", args.exchange))?
+    } else {
+        Credentials::load(&args.exchange)?
+    };
+    let client: Box<dyn ExecutionClient> = match args.exchange.as_str() {
+        "bybit" => Box::new(BybitExecutionClient::new(credentials).with_testnet(args.testnet)),
+        "binance" => Box::new(BinanceExecutionClient::new(credentials).with_testnet(args.testnet)),
+        other => {
+            error!("Unsupported exchange: {}", other);
+            std::process::exit(1);
+        }
+    };
+
+    info!("Starting {} {} account tracker (interval: {}s)",
+          args.exchange, market_type.as_str().to_uppercase(), args.interval);
+
+    // Create channels
+    let (update_tx, mut update_rx) = mpsc::channel::<AccountUpdate>(1000);
+
+    let db = if args.update {
+        // Get database URL
+        let database_url = args
+            .database_url
+            .or_else(|| env::var("MONGODB_URL").ok())
+            .expect("MONGODB_URL must be set when using --update");
+
+        Database::new(&database_url, true).await?
+    } else {
+        Database::new("", false).await?
+    }
+    .with_namespace(
+        args.namespace
+            .or_else(|| env::var("MONGODB_NAMESPACE").ok())
+            .or_else(|| args.testnet.then(|| "testnet".to_string())),
+    )?;
+
+    // Start account tracker
+    let tracker = AccountTracker::new(client, market_type, update_tx, Duration::from_secs(args.interval));
+    tokio::spawn(async move {
+        tracker.start().await;
+    });
+
+    // Database writer
+    while let Some(update) = update_rx.recv().await {
+        let result = match update {
+            AccountUpdate::Balance(balance) => {
+                info!(
+                    exchange = %balance.exchange, asset = %balance.asset, wallet = balance.wallet_balance, available = balance.available_balance,
+                    "[ACCOUNT-BALANCE] {} {} | Wallet:{:.8} Available:{:.8}",
+                    balance.exchange, balance.asset, balance.wallet_balance, balance.available_balance
+                );
+                db.insert_balance(&balance).await
+            }
+            AccountUpdate::Position(position) => {
+                info!(
+                    exchange = %position.exchange, symbol = %position.symbol, size = position.size, entry = ?position.entry_price,
+                    mark = ?position.mark_price, unrealized_pnl = position.unrealized_pnl,
+                    "[ACCOUNT-POSITION] {} {} | Size:{} Entry:{} Mark:{} uPnL:{:.4}",
+                    position.exchange, position.symbol, position.size,
+                    position.entry_price.map_or("-".to_string(), |v| format!("{:.2}", v)),
+                    position.mark_price.map_or("-".to_string(), |v| format!("{:.2}", v)),
+                    position.unrealized_pnl
+                );
+                db.insert_position(&position).await
+            }
+        };
+        if let Err(e) = result {
+            error!("Failed to insert account update: {}", e);
+        }
+    }
+
+    Ok(())
+}
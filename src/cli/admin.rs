@@ -0,0 +1,79 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use super::common::LogArgs;
+use crate::utils::snapshot::CollectorSnapshot;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+#[command(name = "admin")]
+#[command(about = "Operate running collectors (snapshot for planned restarts, candle audits)", long_about = None)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Ask a collector started with --snapshot-file to write its state (SIGUSR1, or SIGTERM with --stop)
+    Snapshot {
+        /// Collector process id
+        #[arg(long)]
+        pid: u32,
+
+        /// Stop the collector after writing the snapshot (restart it with --restore <file>)
+        #[arg(long)]
+        stop: bool,
+    },
+    /// Ask a collector started with --audit to recompute its recent candles (SIGUSR2; the result goes to the collector's log)
+    Audit {
+        /// Collector process id
+        #[arg(long)]
+        pid: u32,
+    },
+    /// Print the contents of a snapshot file
+    Show {
+        file: PathBuf,
+    },
+}
+
+pub fn run(args: Args) -> Result<()> {
+    // stdout は show の出力先になるので, ログは stderr に出す
+    args.logs.init_stderr("admin")?;
+    match args.command {
+        Command::Snapshot { pid, stop } => {
+            let signal = if stop { "-TERM" } else { "-USR1" };
+            let status = std::process::Command::new("kill").arg(signal).arg(pid.to_string()).status()?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to signal process {}", pid));
+            }
+            info!(pid, signal = &signal[1..], "Sent {} to {} (the collector writes its --snapshot-file{})", &signal[1..], pid, if stop { " and exits" } else { "" });
+        }
+        Command::Audit { pid } => {
+            let status = std::process::Command::new("kill").arg("-USR2").arg(pid.to_string()).status()?;
+            if !status.success() {
+                return Err(anyhow::anyhow!("Failed to signal process {}", pid));
+            }
+            info!(pid, signal = "USR2", "Sent USR2 to {} (the collector logs the audit report)", pid);
+        }
+        Command::Show { file } => {
+            let snapshot = CollectorSnapshot::load(&file)?;
+            println!("{}", snapshot.summary());
+            for (timeframe, until) in &snapshot.candles.flushed_until {
+                println!("  {}s flushed until {}", timeframe, until.format("%Y-%m-%d %H:%M:%S"));
+            }
+            for buffer in &snapshot.candles.buffers {
+                println!(
+                    "  {} {}s @ {} | Ask: V:{:.4} Cnt:{} | Bid: V:{:.4} Cnt:{}",
+                    buffer.symbol, buffer.timeframe, buffer.candle_end.format("%H:%M:%S"),
+                    buffer.ask_volume, buffer.ask_count, buffer.bid_volume, buffer.bid_count
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
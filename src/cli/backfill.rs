@@ -0,0 +1,177 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::{self, DatabaseArgs, LogArgs, MarketArgs, TimeframeArgs};
+use crate::{
+    exchanges::{binance::BinanceClient, bybit::BybitClient},
+    models::{market_event::MarketEvent, market_type::MarketType, trade::Trade},
+    utils::{candle_fields::CandleFieldSelection, endpoint::{Endpoint, ProxyConfig}, event_writer::EventWriter, mongo_sink::MongoCandleSink, history::{self, HistorySource}, symbol_manager::SYMBOL_MANAGER, trade_candle_builder::TradeCandleBuilder},
+};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+#[derive(Parser, Debug)]
+#[command(name = "backfill")]
+#[command(about = "Build candles from historical trades (Binance / Bybit archives and REST) and store them like the collectors", long_about = None)]
+pub struct Args {
+    /// Exchange: binance or bybit
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Symbols to backfill (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    #[command(flatten)]
+    database: DatabaseArgs,
+
+    #[command(flatten)]
+    market: MarketArgs,
+
+    #[command(flatten)]
+    candles: TimeframeArgs,
+
+    /// Start date in UTC (YYYY-MM-DD, inclusive)
+    #[arg(long)]
+    start: NaiveDate,
+
+    /// End date in UTC (YYYY-MM-DD, exclusive, default: today; candles are only stored up to now)
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Where to get trades: archive (daily files from data.binance.vision / public.bybit.com, REST for days not published yet) or rest
+    #[arg(long, default_value = "archive")]
+    source: String,
+
+    /// Candle fields to persist per timeframe in seconds (e.g., 1=ask_price,bid_price;60=*;*=*)
+    #[arg(long)]
+    candle_fields: Option<String>,
+
+    /// Wait between REST requests in milliseconds (Binance aggTrades pages)
+    #[arg(long, default_value = "200")]
+    request_interval_ms: u64,
+
+    /// REST host[:port] replacing the default one (e.g., api.binance.us)
+    #[arg(long)]
+    rest_host: Option<String>,
+
+    /// Send REST requests through this HTTP proxy (or use PROXY_URL env var)
+    #[arg(long)]
+    proxy: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+/// 1 日分 ([start, end)) の約定を古い順に取得する
+pub(super) async fn fetch_trades(
+    exchange: &str,
+    source: HistorySource,
+    endpoint: &Endpoint,
+    market_type: &MarketType,
+    symbol: &str,
+    (start, end): (DateTime<Utc>, DateTime<Utc>),
+    request_interval: std::time::Duration,
+) -> Result<Vec<Trade>> {
+    // アーカイブは 1 日単位なので, 今日の途中までは REST で取得する
+    if source == HistorySource::Archive && end - start == Duration::days(1) {
+        let archived = match exchange {
+            "binance" => BinanceClient::fetch_archive_trades(endpoint, market_type, symbol, start.date_naive()).await?,
+            _ => BybitClient::fetch_archive_trades(endpoint, market_type, symbol, start.date_naive()).await?,
+        };
+        match archived {
+            Some(trades) => return Ok(trades),
+            None => warn!("No {} archive of {} for {} yet; using REST", exchange, symbol, start.date_naive()),
+        }
+    }
+    let mut trades = match exchange {
+        "binance" => BinanceClient::fetch_rest_trades(endpoint, market_type, symbol, start, end, request_interval).await?,
+        _ => BybitClient::fetch_rest_trades(endpoint, market_type, symbol, start, end).await?,
+    };
+    trades.sort_by_key(|trade| trade.timestamp);
+    Ok(trades)
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("backfill")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let exchange = args.exchange.to_lowercase();
+    let market_type = match exchange.as_str() {
+        "binance" | "bybit" => args.market.market_type(&exchange, &[MarketType::Spot, MarketType::Linear, MarketType::Inverse])?,
+        _ => return Err(anyhow::anyhow!("Backfill supports binance and bybit, not {}", args.exchange)),
+    };
+    let source = HistorySource::parse(&args.source)?;
+    let symbols = common::parse_symbols(&args.symbols);
+    for symbol in &symbols {
+        if SYMBOL_MANAGER.get_symbol_id(&exchange, symbol, market_type.as_str()).is_none() {
+            return Err(anyhow::anyhow!("{} {} ({}) is not in master.csv", exchange, symbol, market_type));
+        }
+    }
+    let timeframes = args.candles.timeframes()?;
+    let session_offset = args.candles.session_offset()?;
+    let candle_fields = match args.candle_fields.as_deref() {
+        Some(spec) => CandleFieldSelection::parse(spec)?,
+        None => CandleFieldSelection::default(),
+    };
+
+    // 今日の途中までなら, 今の時点で終わっている足だけを書き込む
+    let end = args.end.unwrap_or_else(|| Utc::now().date_naive());
+    let start_time = args.start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end_time = end.and_hms_opt(0, 0, 0).unwrap().and_utc().min(Utc::now());
+    if start_time >= end_time {
+        return Err(anyhow::anyhow!("Nothing to backfill between {} and {}", args.start, end));
+    }
+    info!("Backfilling {} {} {:?} from {} to {} ({}), timeframes: {:?}",
+          exchange, market_type, symbols, start_time, end_time, source.as_str(), timeframes);
+
+    let endpoint = Endpoint::new()
+        .with_rest_host(args.rest_host.clone())
+        .with_proxy(ProxyConfig::from_arg_or_env(args.proxy.as_deref())?)?;
+    let request_interval = std::time::Duration::from_millis(args.request_interval_ms);
+
+    // Start database writer (same collections as the collectors)
+    let db = Arc::new(args.database.open(candle_fields, None).await?);
+    let sinks = MongoCandleSink::fanout(&db, &exchange);
+    let (output_tx, output_rx) = mpsc::channel::<MarketEvent>(1000);
+    let (sinks_tx, sinks_rx) = mpsc::channel::<MarketEvent>(1000);
+    tokio::spawn(sinks.clone().run(output_rx, sinks_tx));
+    let writer = tokio::spawn(EventWriter::new(db, &exchange).run(sinks_rx));
+
+    // 足の境界は約定時刻で進むので, symbol ごとに別の集計で古い順に流す
+    for symbol in &symbols {
+        let (trade_tx, trade_rx) = mpsc::channel::<MarketEvent>(1000);
+        let builder = tokio::spawn(
+            TradeCandleBuilder::new(trade_rx, output_tx.clone(), timeframes.clone())
+                .with_session_offset(session_offset)
+                .run_batch(end_time),
+        );
+        let mut total = 0;
+        for day in history::days(args.start, end) {
+            let day_start = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            let day_end = (day_start + Duration::days(1)).min(end_time);
+            if day_start >= day_end {
+                break;
+            }
+            let trades = fetch_trades(&exchange, source, &endpoint, &market_type, symbol, (day_start, day_end), request_interval).await?;
+            info!("[{}-BACKFILL] {} {}: {} trades", exchange.to_uppercase(), symbol, day, trades.len());
+            total += trades.len();
+            for trade in trades {
+                trade_tx.send(MarketEvent::Trade(trade)).await?;
+            }
+        }
+        drop(trade_tx);
+        builder.await?;
+        info!("Backfilled {} {} trades of {}", total, exchange, symbol);
+    }
+
+    // 書き込みが終わるまで待つ
+    drop(output_tx);
+    writer.await?;
+    sinks.finish().await;
+    Ok(())
+}
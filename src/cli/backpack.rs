@@ -0,0 +1,36 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::{ClientOptions, CollectorArgs};
+use crate::{
+    exchanges::backpack::BackpackClient,
+    models::market_type::MarketType,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "backpack")]
+#[command(about = "Collect real-time cryptocurrency trade data from Backpack", long_about = None)]
+pub struct Args {
+    /// Symbols to subscribe (comma-separated, e.g., SOL_USDC for spot, SOL_USDC_PERP for linear)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    #[command(flatten)]
+    collector: CollectorArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let collector = args.collector.start("Backpack", &[MarketType::Spot, MarketType::Linear])?;
+
+    // Start Backpack client
+    collector.run(&args.symbols, |options: &ClientOptions| {
+        let mut client = BackpackClient::new(options.events.clone(), options.raw_freq).with_endpoint(options.endpoint.clone());
+        if let Some(timeout) = options.watchdog {
+            client = client.with_watchdog(timeout);
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(config) = options.chaos.clone() {
+            client = client.with_chaos(config);
+        }
+        client
+    }).await
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::{ClientOptions, CollectorArgs};
+use crate::{
+    exchanges::binance::BinanceClient,
+    models::market_type::MarketType,
+    utils::stablecoin::StablecoinMerge,
+};
+use std::time::Duration;
+use tracing::error;
+
+#[derive(Parser, Debug)]
+#[command(name = "binance")]
+#[command(about = "Collect real-time cryptocurrency trade data from Binance", long_about = None)]
+pub struct Args {
+    /// Symbols to subscribe (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    #[command(flatten)]
+    collector: CollectorArgs,
+
+    /// Also aggregate stablecoin pairs of the same base into one {BASE}-USD series (default: USDT,USDC,FDUSD,BUSD)
+    #[arg(long, num_args = 0..=1, default_missing_value = StablecoinMerge::DEFAULT_QUOTES)]
+    merge_stablecoins: Option<String>,
+
+    /// Also subscribe to {symbol}@bookTicker and store best bid/ask quotes
+    #[arg(long)]
+    book_ticker: bool,
+
+    /// Quote sampling interval in milliseconds (latest quote per symbol is stored)
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(100..))]
+    quote_interval_ms: u64,
+
+    /// Also collect liquidations into the liquidations collection (linear/inverse only)
+    #[arg(long)]
+    liquidations: bool,
+
+    /// Also collect mark/index prices from {symbol}@markPrice@1s into the mark_prices collection (linear/inverse only)
+    #[arg(long)]
+    mark_prices: bool,
+
+    /// Connect to the testnet endpoints (stored under the "testnet" namespace unless --namespace is given)
+    #[arg(long)]
+    testnet: bool,
+
+    /// REST host[:port] for the reconnect backfill replacing the default one (e.g., api.binance.us)
+    #[arg(long)]
+    rest_host: Option<String>,
+
+    /// Symbols per WebSocket connection; more symbols open more connections feeding the same pipeline (0: as many as the stream limit allows, spot 1024 / futures 200 streams)
+    #[arg(long, default_value = "0")]
+    symbols_per_connection: usize,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let collector = args.collector.start("Binance", &[MarketType::Spot, MarketType::Linear, MarketType::Inverse])?;
+    let market_type = collector.market_type().clone();
+
+    if args.liquidations && market_type == MarketType::Spot {
+        error!("--liquidations is only available for --linear or --inverse");
+        std::process::exit(1);
+    }
+    
+    if args.mark_prices && market_type == MarketType::Spot {
+        error!("--mark-prices is only available for --linear or --inverse");
+        std::process::exit(1);
+    }
+
+    let collector = collector
+        .with_namespace(args.testnet.then_some("testnet"))
+        .with_stablecoin_merge(args.merge_stablecoins.as_deref().map(StablecoinMerge::parse).transpose()?)
+        .with_quote_interval(Duration::from_millis(args.quote_interval_ms))
+        .with_rest_host(args.rest_host.clone())
+        .with_symbols_per_connection(args.symbols_per_connection);
+
+    // Start Binance clients (one per slice of symbols, by default as many as the stream limit allows)
+    collector.run(&args.symbols, |options: &ClientOptions| {
+        let mut client = BinanceClient::new(options.events.clone(), options.raw_freq)
+            .with_testnet(args.testnet)
+            .with_endpoint(options.endpoint.clone());
+        if args.book_ticker {
+            client = client.with_quotes();
+        }
+        if args.liquidations {
+            client = client.with_liquidations();
+        }
+        if args.mark_prices {
+            client = client.with_mark_prices();
+        }
+        if let Some(timeout) = options.watchdog {
+            client = client.with_watchdog(timeout);
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(config) = options.chaos.clone() {
+            client = client.with_chaos(config);
+        }
+        client
+    }).await
+}
\ No newline at end of file
@@ -0,0 +1,123 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::{ClientOptions, CollectorArgs};
+use crate::{
+    exchanges::bybit::BybitClient,
+    models::market_type::MarketType,
+    utils::stablecoin::StablecoinMerge,
+};
+use std::time::Duration;
+use tracing::error;
+
+#[derive(Parser, Debug)]
+#[command(name = "bybit")]
+#[command(about = "Collect real-time cryptocurrency trade data from Bybit", long_about = None)]
+pub struct Args {
+    /// Symbols to subscribe (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    #[command(flatten)]
+    collector: CollectorArgs,
+
+    /// Also aggregate stablecoin pairs of the same base into one {BASE}-USD series (default: USDT,USDC,FDUSD,BUSD)
+    #[arg(long, num_args = 0..=1, default_missing_value = StablecoinMerge::DEFAULT_QUOTES)]
+    merge_stablecoins: Option<String>,
+
+    /// Also subscribe to orderbook.{depth}.{symbol} and store quotes (spot: 1,50,200 / linear,inverse: 1,50,200,500)
+    #[arg(long)]
+    orderbook_depth: Option<u32>,
+
+    /// Number of book levels used for the quote imbalance
+    #[arg(long, default_value = "5", value_parser = clap::value_parser!(u32).range(1..))]
+    imbalance_levels: u32,
+
+    /// Quote / mark price sampling interval in milliseconds (latest value per symbol is stored)
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(100..))]
+    quote_interval_ms: u64,
+
+    /// Also collect liquidations into the liquidations collection (linear/inverse only)
+    #[arg(long)]
+    liquidations: bool,
+
+    /// Also collect mark/index prices from tickers.{symbol} into the mark_prices collection (linear/inverse only)
+    #[arg(long)]
+    mark_prices: bool,
+
+    /// Store block trades (BT=true prints) into the block_trades collection and exclude them from candles
+    #[arg(long)]
+    block_trades: bool,
+
+    /// Connect to the testnet endpoints (stored under the "testnet" namespace unless --namespace is given)
+    #[arg(long)]
+    testnet: bool,
+
+    /// REST host[:port] for the reconnect backfill replacing the default one (e.g., api.bybit.nl)
+    #[arg(long)]
+    rest_host: Option<String>,
+
+    /// Symbols per WebSocket connection; more symbols open more connections feeding the same pipeline (0: one connection)
+    #[arg(long, default_value = "0")]
+    symbols_per_connection: usize,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let collector = args.collector.start("Bybit", &[MarketType::Spot, MarketType::Linear, MarketType::Inverse])?;
+    let market_type = collector.market_type().clone();
+
+    // Validate orderbook depth
+    if let Some(depth) = args.orderbook_depth {
+        let valid_depths: &[u32] = match market_type {
+            MarketType::Spot => &[1, 50, 200],
+            MarketType::Linear | MarketType::Inverse => &[1, 50, 200, 500],
+        };
+        if !valid_depths.contains(&depth) {
+            error!("Invalid orderbook depth for {}: {}. Use one of {:?}", market_type.as_str(), depth, valid_depths);
+            std::process::exit(1);
+        }
+    }
+    
+    if args.liquidations && market_type == MarketType::Spot {
+        error!("--liquidations is only available for --linear or --inverse");
+        std::process::exit(1);
+    }
+    
+    if args.mark_prices && market_type == MarketType::Spot {
+        error!("--mark-prices is only available for --linear or --inverse");
+        std::process::exit(1);
+    }
+
+    let collector = collector
+        .with_namespace(args.testnet.then_some("testnet"))
+        .with_stablecoin_merge(args.merge_stablecoins.as_deref().map(StablecoinMerge::parse).transpose()?)
+        .with_quote_interval(Duration::from_millis(args.quote_interval_ms))
+        .with_rest_host(args.rest_host.clone())
+        .with_symbols_per_connection(args.symbols_per_connection);
+
+    // Start Bybit clients (one per slice of symbols)
+    collector.run(&args.symbols, |options: &ClientOptions| {
+        let mut client = BybitClient::new(options.events.clone(), options.raw_freq)
+            .with_testnet(args.testnet)
+            .with_endpoint(options.endpoint.clone());
+        if let Some(depth) = args.orderbook_depth {
+            client = client.with_quotes(depth, args.imbalance_levels as usize);
+        }
+        if args.liquidations {
+            client = client.with_liquidations();
+        }
+        if args.mark_prices {
+            client = client.with_mark_prices();
+        }
+        if args.block_trades {
+            client = client.with_block_trades();
+        }
+        if let Some(timeout) = options.watchdog {
+            client = client.with_watchdog(timeout);
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(config) = options.chaos.clone() {
+            client = client.with_chaos(config);
+        }
+        client
+    }).await
+}
\ No newline at end of file
@@ -0,0 +1,241 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::{self, CollectorOutputs, DatabaseArgs, LogArgs, SinkArgs, SinkDefaults};
+use crate::{
+    db::{shard_urls, Database},
+    exchanges::{backpack::BackpackClient, binance::BinanceClient, bitstamp::BitstampClient, bybit::BybitClient, hyperliquid::HyperliquidClient, phemex::PhemexClient},
+    models::market_event::MarketEvent,
+    utils::{collector_config::{CandleTimeframeFilter, CollectorConfig, FeedConfig}, connection_shards, dedup::TradeDedup, endpoint::{Endpoint, ProxyConfig}, dashboard::Dashboard, retention::RetentionManager, latency::{LatencyMetrics, DEFAULT_LATENCY_WINDOW_SECONDS}, ops_events::{self, OpsEventKind}, supervisor::Supervisor, timeframe, trade_candle_builder::TradeCandleBuilder},
+};
+use std::env;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[command(name = "collector")]
+#[command(about = "Collect trades from several exchanges and markets in one process, driven by a TOML / YAML config", long_about = None)]
+pub struct Args {
+    /// Config file (.toml, .yaml or .yml) listing the feeds: exchange, market_type, symbols and optional timeframes
+    #[arg(short, long)]
+    config: PathBuf,
+
+    /// Update database (overrides `update` in the config)
+    #[arg(long)]
+    update: bool,
+
+    /// Serve /healthz and /status on this address (overrides `health_addr` in the config, e.g., 0.0.0.0:8080)
+    #[arg(long)]
+    health_addr: Option<String>,
+
+    /// Show a live terminal dashboard (per-symbol price, 1m volume, trades/s, candles, WebSocket and DB status) instead of printing every candle; q quits
+    #[arg(long)]
+    tui: bool,
+
+    /// Re-broadcast trades and candles over WebSocket on this address (overrides `broadcast_addr` in the config, e.g., 0.0.0.0:9001)
+    #[arg(long)]
+    broadcast_addr: Option<String>,
+
+    #[command(flatten)]
+    sinks: SinkArgs,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+/// 1 つの feed の接続 (symbols_per_connection ごとに分けて) を維持する
+async fn run_feed(feed: FeedConfig, config: Arc<CollectorConfig>, event_tx: mpsc::Sender<MarketEvent>) -> Result<()> {
+    let market_type = feed.market_type()?;
+    let endpoint = Endpoint::new()
+        .with_ws_host(feed.ws_host.clone())
+        .with_rest_host(feed.rest_host.clone())
+        .with_proxy(ProxyConfig::from_arg_or_env(config.proxy.as_deref())?)?;
+    let watchdog = (config.watchdog_secs > 0).then(|| Duration::from_secs(config.watchdog_secs));
+    let raw_freq = config.raw_freq;
+    info!("Starting {} {} feed with symbols: {:?}", feed.exchange, market_type.as_str().to_uppercase(), feed.symbols);
+
+    match feed.exchange.as_str() {
+        "bybit" => {
+            let new_client = || {
+                let client = BybitClient::new(event_tx.clone(), raw_freq).with_testnet(feed.testnet).with_endpoint(endpoint.clone());
+                match watchdog {
+                    Some(timeout) => client.with_watchdog(timeout),
+                    None => client,
+                }
+            };
+            connection_shards::run_clients(new_client, &feed.symbols, feed.symbols_per_connection, market_type).await
+        }
+        "binance" => {
+            let new_client = || {
+                let client = BinanceClient::new(event_tx.clone(), raw_freq).with_testnet(feed.testnet).with_endpoint(endpoint.clone());
+                match watchdog {
+                    Some(timeout) => client.with_watchdog(timeout),
+                    None => client,
+                }
+            };
+            connection_shards::run_clients(new_client, &feed.symbols, feed.symbols_per_connection, market_type).await
+        }
+        "hyperliquid" => {
+            let new_client = || {
+                let client = HyperliquidClient::new(event_tx.clone(), raw_freq).with_endpoint(endpoint.clone());
+                match watchdog {
+                    Some(timeout) => client.with_watchdog(timeout),
+                    None => client,
+                }
+            };
+            connection_shards::run_clients(new_client, &feed.symbols, feed.symbols_per_connection, market_type).await
+        }
+        "bitstamp" => {
+            let new_client = || {
+                let client = BitstampClient::new(event_tx.clone(), raw_freq).with_endpoint(endpoint.clone());
+                match watchdog {
+                    Some(timeout) => client.with_watchdog(timeout),
+                    None => client,
+                }
+            };
+            connection_shards::run_clients(new_client, &feed.symbols, feed.symbols_per_connection, market_type).await
+        }
+        "phemex" => {
+            let new_client = || {
+                let client = PhemexClient::new(event_tx.clone(), raw_freq).with_endpoint(endpoint.clone());
+                match watchdog {
+                    Some(timeout) => client.with_watchdog(timeout),
+                    None => client,
+                }
+            };
+            connection_shards::run_clients(new_client, &feed.symbols, feed.symbols_per_connection, market_type).await
+        }
+        "backpack" => {
+            let new_client = || {
+                let client = BackpackClient::new(event_tx.clone(), raw_freq).with_endpoint(endpoint.clone());
+                match watchdog {
+                    Some(timeout) => client.with_watchdog(timeout),
+                    None => client,
+                }
+            };
+            connection_shards::run_clients(new_client, &feed.symbols, feed.symbols_per_connection, market_type).await
+        }
+        exchange => Err(anyhow::anyhow!("Unknown exchange: {}", exchange)),
+    }
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    let dashboard = args.tui.then(|| Dashboard::new("collector").qualified());
+    args.logs.init_dashboard("collector", dashboard.as_ref())?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let config = Arc::new(CollectorConfig::load(&args.config)?);
+    let timeframes = config.all_timeframes()?;
+    let session_offset = timeframe::parse_session_offset(&config.session)?;
+    info!("Starting collector with {} feeds ({}), timeframes: {:?}", config.feeds.len(),
+          config.feeds.iter().map(|feed| format!("{} {}", feed.exchange, feed.market_type)).collect::<Vec<_>>().join(", "), timeframes);
+
+    // Handle database operations or print
+    let db = if args.update || config.update {
+        let database_url = config
+            .database_url
+            .clone()
+            .or_else(|| env::var("MONGODB_URL").ok())
+            .expect("MONGODB_URL must be set when using --update");
+        Database::new(&database_url, true).await?
+    } else {
+        Database::new("", false).await?
+    }
+    .with_namespace(config.namespace.clone().or_else(|| env::var("MONGODB_NAMESPACE").ok()))?
+    .with_shards(&shard_urls(None))
+    .await?
+    .with_batching(config.batch_size, Duration::from_millis(config.batch_ms));
+    let db = Arc::new(db);
+
+    // Write candles (MongoDB and the other sinks) and the trades the sinks keep
+    let database = DatabaseArgs {
+        database_url: config.database_url.clone(),
+        shard_urls: None,
+        update: args.update || config.update,
+        namespace: config.namespace.clone(),
+        db_batch_size: config.batch_size,
+        db_batch_ms: config.batch_ms,
+    };
+    let defaults = SinkDefaults {
+        redis_url: config.redis_url.as_deref(),
+        nats_url: config.nats_url.as_deref(),
+        clickhouse_url: config.clickhouse_url.as_deref(),
+        questdb_addr: config.questdb_addr.as_deref(),
+        archive_dir: config.archive_dir.as_deref(),
+        sqlite_dir: config.sqlite_dir.as_deref(),
+        database: Some(&database),
+        namespace: None,
+    };
+    let candles = common::candle_sink(&db, "collector", config.write_ahead_file.as_deref(), dashboard.as_ref())?;
+    let sinks = args.sinks.open(defaults, candles).await?;
+    let health_addr = args.health_addr.clone().or_else(|| config.health_addr.clone());
+    let broadcast_addr = args.broadcast_addr.clone().or_else(|| config.broadcast_addr.clone());
+    let outputs = CollectorOutputs::new("collector", db.clone(), sinks, dashboard, health_addr, broadcast_addr);
+    if let Some(dashboard) = outputs.dashboard() {
+        dashboard.track(&config.feeds.iter().flat_map(|feed| feed.symbols.iter().map(move |symbol| format!("{}:{}:{}", feed.exchange, feed.market_type, symbol))).collect::<Vec<_>>());
+    }
+
+    // Create channels (all feeds share one candle builder and one DB writer)
+    let (event_tx, event_rx) = mpsc::channel::<MarketEvent>(1000);
+    let (output_tx, output_rx) = mpsc::channel::<MarketEvent>(1000);
+    let event_rx = outputs.watch(event_rx);
+    let latency = LatencyMetrics::new(chrono::Duration::seconds(DEFAULT_LATENCY_WINDOW_SECONDS as i64));
+    tokio::spawn(latency.clone().log_every("COLLECTOR".to_string(), Duration::from_secs(DEFAULT_LATENCY_WINDOW_SECONDS)));
+    let mut event_rx = common::pipe_stage(event_rx, |receiver, sender| latency.run(receiver, sender));
+    if config.dedup_window > 0 {
+        let dedup = TradeDedup::new(config.dedup_window);
+        event_rx = common::pipe_stage(event_rx, |receiver, sender| dedup.run(receiver, sender));
+    }
+    let event_rx = outputs.publish(event_rx);
+
+    // Start the shared trade candle builder (builds the union of the feeds' timeframes)
+    let new_candle_builder = move |event_rx: mpsc::Receiver<MarketEvent>| {
+        TradeCandleBuilder::new(event_rx, output_tx.clone(), timeframes.clone())
+            .with_session_offset(session_offset)
+            .start()
+    };
+    tokio::spawn(Supervisor::new("candle builder").run_stage(event_rx, new_candle_builder));
+    let filter = CandleTimeframeFilter::new(&config)?;
+    let filtered_rx = common::pipe_stage(output_rx, |receiver, sender| filter.run(receiver, sender));
+
+    // Start operational event writer, the TUI and the servers
+    let ops_writer = outputs.spawn_ops_writer(ops_events::install_as("collector", "mixed"));
+    ops_events::record(OpsEventKind::Start, format!("config={} feeds={}", args.config.display(),
+        config.feeds.iter().map(|feed| format!("{}:{}:{}", feed.exchange, feed.market_type, feed.symbols.join("/"))).collect::<Vec<_>>().join(",")));
+    outputs.serve().await?;
+
+    // Enforce the candle retention of the config
+    if let (Some(retention), true) = (&config.retention, db.is_enabled()) {
+        tokio::spawn(RetentionManager::new(db.clone(), retention)?.run());
+    }
+
+    // Start event writer (a panicking writer is restarted)
+    outputs.write(filtered_rx, None, None);
+
+    // Start the feeds; when one gives up reconnecting, stop the others too (same as run_sharded)
+    let mut feeds = JoinSet::new();
+    for feed in config.feeds.iter().cloned() {
+        feeds.spawn(run_feed(feed, config.clone(), event_tx.clone()));
+    }
+    drop(event_tx);
+    let result = match feeds.join_next().await {
+        Some(Ok(result)) => result,
+        Some(Err(e)) => Err(anyhow::anyhow!("Feed task failed: {}", e)),
+        None => Ok(()),
+    };
+    if let Err(e) = &result {
+        error!("Feed stopped: {}; closing the others", e);
+    }
+    feeds.shutdown().await;
+
+    // 切断までのイベントを書き込んでから終了する
+    ops_events::uninstall();
+    let _ = ops_writer.await;
+    result
+}
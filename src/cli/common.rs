@@ -0,0 +1,1253 @@
+use crate::db::{self, shard_urls, Database};
+use crate::models::{bar::BarType, market_event::MarketEvent, market_type::MarketType, trade::TimestampSource, ExchangeClient};
+use crate::utils::alert::{AlertEngine, Watchlist};
+use crate::utils::audit;
+use crate::utils::backpressure::{BackpressurePolicy, BackpressureQueue, DEFAULT_BACKPRESSURE_CAPACITY};
+use crate::utils::bar_builder::BarBuilder;
+use crate::utils::candle_cache::CandleCache;
+use crate::utils::candle_fields::CandleFieldSelection;
+use crate::utils::broadcast::{self, EventBroadcaster, BROADCAST_CAPACITY};
+#[cfg(feature = "chaos")]
+use crate::utils::chaos::ChaosConfig;
+use crate::utils::connection_shards;
+use crate::utils::dashboard::Dashboard;
+use crate::utils::dedup::{TradeDedup, DEFAULT_DEDUP_WINDOW};
+use crate::utils::endpoint::{Endpoint, ProxyConfig};
+use crate::utils::event_writer::EventWriter;
+use crate::utils::heikin_ashi::HeikinAshiBuilder;
+use crate::utils::imbalance_bar_builder::{ImbalanceBarBuilder, ImbalanceBarConfig};
+use crate::utils::latency::{LatencyMetrics, DEFAULT_LATENCY_WINDOW_SECONDS};
+use crate::utils::clickhouse_sink::{ClickHouseConfig, ClickHouseSink, DEFAULT_BATCH_SIZE, DEFAULT_FLUSH_SECONDS};
+use crate::utils::health::{self, HealthState};
+use crate::utils::mongo_sink::MongoCandleSink;
+use crate::utils::nats_sink::NatsSink;
+use crate::utils::notify::Notifier;
+use crate::utils::ops_events::{self, OpsEvent, OpsEventKind};
+use crate::utils::parquet_archive::{self, ArchiveStage, ParquetArchive, DEFAULT_ROTATE_SECONDS};
+use crate::utils::quality::{QualityReport, QualityTracker};
+use crate::utils::questdb_sink::{QuestDbConfig, QuestDbSink};
+use crate::utils::sqlite_sink::{SqliteStage, SqliteStore};
+use crate::utils::redis_sink::{RedisSink, DEFAULT_STREAM_MAX_LEN};
+use crate::utils::renko_builder::{BrickConfig, RenkoBuilder};
+use crate::utils::snapshot::{self, CollectorSnapshot};
+use crate::utils::stablecoin::StablecoinMerge;
+use crate::utils::stale_feed::{StaleFeedConfig, StaleFeedMonitor};
+use crate::utils::storage_sink::{SinkFanout, StorageSink};
+use crate::utils::supervisor::Supervisor;
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use crate::utils::throttle::{ThrottleConfig, TradeThrottle};
+use crate::utils::timeframe;
+use crate::utils::trade_blob::{TradeBlobSink, TradeCodec};
+use crate::utils::trade_candle_builder::{AuditControl, BufferMetrics, SnapshotControl, TradeCandleBuilder, WarmupMode};
+use crate::utils::write_ahead::WriteAheadQueue;
+use std::env;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::{Layer, Registry};
+
+/// ログの出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,  // 1 行 1 つの JSON (Loki / Elastic に送る)
+}
+
+impl LogFormat {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            s => Err(anyhow::anyhow!("Invalid log format: {}. Use text or json", s)),
+        }
+    }
+}
+
+/// コマンドのログの出力先 (--log-format, --log-dir)
+#[derive(clap::Args, Debug, Clone)]
+pub struct LogArgs {
+    /// Log format: text or json (one JSON object per line with timestamp, level, target and fields, for Loki / Elastic)
+    #[arg(long, default_value = "text")]
+    pub log_format: String,
+
+    /// Also write logs to daily rotated files in this directory (e.g., bybit-linear.2024-06-01.log)
+    #[arg(long)]
+    pub log_dir: Option<PathBuf>,
+
+    /// Number of daily log files kept in --log-dir (older files are deleted)
+    #[arg(long, default_value_t = DEFAULT_LOG_RETENTION_DAYS)]
+    pub log_retention_days: usize,
+}
+
+/// --log-dir に残す日数の既定値
+pub const DEFAULT_LOG_RETENTION_DAYS: usize = 14;
+
+impl LogArgs {
+    /// 標準出力 (と --log-dir) に出す. name は --log-dir のファイル名
+    pub fn init(&self, name: &str) -> anyhow::Result<()> {
+        self.init_with(name, BoxMakeWriter::new(std::io::stdout), true)
+    }
+
+    /// stdout を出力 (CSV など) に使うコマンド: ログは標準エラー出力 (と --log-dir) に出す
+    pub fn init_stderr(&self, name: &str) -> anyhow::Result<()> {
+        self.init_with(name, BoxMakeWriter::new(std::io::stderr), true)
+    }
+
+    /// --tui: 端末ではなくダッシュボードのログ欄 (と --log-dir) に出す (ダッシュボードがなければ標準出力)
+    pub fn init_dashboard(&self, name: &str, dashboard: Option<&Dashboard>) -> anyhow::Result<()> {
+        match dashboard {
+            Some(dashboard) => self.init_with(name, BoxMakeWriter::new(dashboard.log_writer()), false),
+            None => self.init(name),
+        }
+    }
+
+    /// console に出し, --log-dir があれば同じ形式で {name}.YYYY-MM-DD.log にも書く (UTC の日付で切り替え, log_retention_days 日分を残す)
+    pub fn init_with(&self, name: &str, console: BoxMakeWriter, ansi: bool) -> anyhow::Result<()> {
+        use tracing_subscriber::util::SubscriberInitExt;
+        self.subscriber(name, console, ansi)?.try_init()?;
+        Ok(())
+    }
+
+    /// init_with が global に設定する subscriber (テストでは tracing::subscriber::set_default で使う)
+    pub fn subscriber(&self, name: &str, console: BoxMakeWriter, ansi: bool) -> anyhow::Result<impl tracing::Subscriber + Send + Sync> {
+        use tracing_subscriber::layer::SubscriberExt;
+        let format = LogFormat::parse(&self.log_format)?;
+        let mut layers = vec![log_layer(format, console, ansi)];
+        if let Some(dir) = &self.log_dir {
+            if self.log_retention_days == 0 {
+                return Err(anyhow::anyhow!("--log-retention-days must be positive"));
+            }
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(name)
+                .filename_suffix("log")
+                .max_log_files(self.log_retention_days)
+                .build(dir)
+                .map_err(|e| anyhow::anyhow!("Failed to open log directory {}: {}", dir.display(), e))?;
+            layers.push(log_layer(format, BoxMakeWriter::new(appender), false));
+        }
+        Ok(tracing_subscriber::registry().with(layers))
+    }
+}
+
+/// RUST_LOG がなければ kkcrypto=info (layer ごとに同じ filter を付ける)
+fn log_layer(format: LogFormat, writer: BoxMakeWriter, ansi: bool) -> Box<dyn Layer<Registry> + Send + Sync> {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "kkcrypto=info".into());
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.with_ansi(ansi).with_filter(filter).boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).with_current_span(false).with_filter(filter).boxed(),
+    }
+}
+
+/// --health-addr の HTTP サーバーを起動する (bind できなければエラー)
+pub async fn serve_health(addr: &str, state: HealthState) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tokio::spawn(async move {
+        if let Err(e) = health::serve(listener, state).await {
+            tracing::error!("Health endpoint stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// --broadcast-addr の WebSocket サーバーを起動する (bind できなければエラー)
+pub async fn serve_broadcast(addr: &str, broadcaster: EventBroadcaster) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tokio::spawn(async move {
+        if let Err(e) = broadcast::serve(listener, broadcaster).await {
+            tracing::error!("Broadcast server stopped: {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// カンマ区切りの symbol のリスト
+pub fn parse_symbols(spec: &str) -> Vec<String> {
+    spec.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+}
+
+/// 市場の指定 (どれか 1 つ)
+#[derive(clap::Args, Debug, Clone)]
+pub struct MarketArgs {
+    /// Use spot market
+    #[arg(long)]
+    pub spot: bool,
+
+    /// Use linear futures market
+    #[arg(long)]
+    pub linear: bool,
+
+    /// Use inverse futures market
+    #[arg(long)]
+    pub inverse: bool,
+}
+
+impl MarketArgs {
+    /// 指定された市場 (exchange が supported 以外の市場に対応していなければエラー)
+    pub fn market_type(&self, exchange: &str, supported: &[MarketType]) -> anyhow::Result<MarketType> {
+        let flags = supported.iter().map(|m| format!("--{}", m.as_str())).collect::<Vec<_>>().join(" or ");
+        let market_type = match (self.spot, self.linear, self.inverse) {
+            (true, false, false) => MarketType::Spot,
+            (false, true, false) => MarketType::Linear,
+            (false, false, true) => MarketType::Inverse,
+            (false, false, false) => return Err(anyhow::anyhow!("Must specify {}", flags)),
+            _ => return Err(anyhow::anyhow!("Can only specify one market type at a time")),
+        };
+        if !supported.contains(&market_type) {
+            return Err(anyhow::anyhow!("{} does not support {} markets (use {})", exchange, market_type, flags));
+        }
+        Ok(market_type)
+    }
+}
+
+/// MongoDB の接続先と書き込みの有無
+#[derive(clap::Args, Debug, Clone)]
+pub struct DatabaseArgs {
+    /// Database URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    pub database_url: Option<String>,
+
+    /// Extra MongoDB URLs to shard writes across by symbol hash, comma-separated (or use MONGODB_SHARD_URLS env var; MONGODB_URL is shard 0)
+    #[arg(long)]
+    pub shard_urls: Option<String>,
+
+    /// Update database (if not set, only print data)
+    #[arg(long)]
+    pub update: bool,
+
+    /// Collection namespace for sharing one MongoDB cluster (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    pub namespace: Option<String>,
+
+    /// Number of candles written to MongoDB in one insert_many
+    #[arg(long, default_value_t = db::DEFAULT_BATCH_SIZE)]
+    pub db_batch_size: usize,
+
+    /// Write buffered candles at least this often even if the batch is not full (milliseconds)
+    #[arg(long, default_value_t = db::DEFAULT_BATCH_LATENCY_MS)]
+    pub db_batch_ms: u64,
+}
+
+impl DatabaseArgs {
+    /// --database-url, なければ MONGODB_URL
+    pub fn url(&self) -> Option<String> {
+        self.database_url.clone().or_else(|| env::var("MONGODB_URL").ok())
+    }
+
+    /// --namespace, MONGODB_NAMESPACE, default_namespace (e.g. testnet) の順
+    pub fn namespace(&self, default_namespace: Option<&str>) -> Option<String> {
+        self.namespace
+            .clone()
+            .or_else(|| env::var("MONGODB_NAMESPACE").ok())
+            .or_else(|| default_namespace.map(str::to_string))
+    }
+
+    /// --update なら MongoDB に接続し, なければ表示だけの Database を返す
+    pub async fn open(&self, candle_fields: CandleFieldSelection, default_namespace: Option<&str>) -> anyhow::Result<Database> {
+        let db = if self.update {
+            let database_url = self.url().ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set when using --update"))?;
+            Database::new(&database_url, true).await?
+        } else {
+            Database::new("", false).await?
+        };
+        Ok(db.with_candle_fields(candle_fields)
+            .with_namespace(self.namespace(default_namespace))?
+            .with_shards(&shard_urls(self.shard_urls.as_deref()))
+            .await?
+            .with_batching(self.db_batch_size, std::time::Duration::from_millis(self.db_batch_ms)))
+    }
+}
+
+/// 足を db に書き込むシンク (--update なしなら None)
+/// write_ahead_file があれば MongoDB 障害中に書き込めなかった足をそこに溜める
+pub fn candle_sink(db: &Arc<Database>, exchange: &str, write_ahead_file: Option<&str>, dashboard: Option<&Dashboard>) -> anyhow::Result<Option<MongoCandleSink>> {
+    if !db.is_enabled() {
+        return Ok(None);
+    }
+    let mut sink = MongoCandleSink::new(db.clone(), exchange);
+    if let Some(path) = write_ahead_file {
+        sink = sink.with_write_ahead(WriteAheadQueue::open(Path::new(path))?);
+    }
+    if let Some(dashboard) = dashboard {
+        sink = sink.with_dashboard(dashboard.clone());
+    }
+    Ok(Some(sink))
+}
+
+/// Redis への送信 (--redis-url がなければ送らない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct RedisArgs {
+    /// Also XADD candles to per-symbol Redis streams and PUBLISH trades on per-symbol channels (e.g., redis://localhost:6379, or use REDIS_URL env var)
+    #[arg(long)]
+    pub redis_url: Option<String>,
+
+    /// Approximate number of candles kept per Redis stream (0 = unbounded)
+    #[arg(long, default_value_t = DEFAULT_STREAM_MAX_LEN)]
+    pub redis_max_len: usize,
+
+    /// Prefix of the Redis stream and channel names ({prefix}:candles:{exchange}:{market}:{symbol}, {prefix}:trades:...)
+    #[arg(long, default_value = "kkcrypto")]
+    pub redis_prefix: String,
+}
+
+impl RedisArgs {
+    /// --redis-url, default_url (設定ファイル), REDIS_URL の順で接続する (どれもなければ None)
+    pub async fn open(&self, default_url: Option<&str>) -> anyhow::Result<Option<RedisSink>> {
+        let url = self.redis_url.clone().or_else(|| default_url.map(str::to_string)).or_else(|| env::var("REDIS_URL").ok());
+        match url {
+            Some(url) => Ok(Some(RedisSink::connect(&url, &self.redis_prefix, self.redis_max_len).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// NATS JetStream への送信 (--nats-url がなければ送らない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct NatsArgs {
+    /// Also publish trades (trades.{exchange}.{symbol}) and candles (candles.{timeframe}.{symbol}) to NATS JetStream with at-least-once delivery (e.g., nats://localhost:4222, or use NATS_URL env var); without --update this replaces MongoDB
+    #[arg(long)]
+    pub nats_url: Option<String>,
+
+    /// JetStream stream capturing trades.> and candles.> (created if missing)
+    #[arg(long, default_value = "KKCRYPTO")]
+    pub nats_stream: String,
+}
+
+impl NatsArgs {
+    /// --nats-url, default_url (設定ファイル), NATS_URL の順で接続する (どれもなければ None)
+    pub async fn open(&self, default_url: Option<&str>) -> anyhow::Result<Option<NatsSink>> {
+        let url = self.nats_url.clone().or_else(|| default_url.map(str::to_string)).or_else(|| env::var("NATS_URL").ok());
+        match url {
+            Some(url) => Ok(Some(NatsSink::connect(&url, &self.nats_stream).await?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// ClickHouse への書き込み (--clickhouse-url がなければ書かない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct ClickHouseArgs {
+    /// Also write candles to ClickHouse over HTTP in batches (e.g., http://localhost:8123, or use CLICKHOUSE_URL env var; CLICKHOUSE_USER / CLICKHOUSE_PASSWORD for auth)
+    #[arg(long)]
+    pub clickhouse_url: Option<String>,
+
+    /// ClickHouse database for the candles / trades tables (created if missing)
+    #[arg(long, default_value = "kkcrypto")]
+    pub clickhouse_database: String,
+
+    /// Also write raw trades to ClickHouse (table trades, partitioned by day)
+    #[arg(long)]
+    pub clickhouse_trades: bool,
+
+    /// Rows per ClickHouse INSERT
+    #[arg(long, default_value_t = DEFAULT_BATCH_SIZE)]
+    pub clickhouse_batch_size: usize,
+
+    /// Insert buffered rows at least this often (seconds)
+    #[arg(long, default_value_t = DEFAULT_FLUSH_SECONDS)]
+    pub clickhouse_flush_secs: u64,
+}
+
+impl ClickHouseArgs {
+    /// --clickhouse-url, default_url (設定ファイル), CLICKHOUSE_URL の順で接続する (どれもなければ None)
+    pub async fn open(&self, default_url: Option<&str>) -> anyhow::Result<Option<ClickHouseSink>> {
+        let url = self.clickhouse_url.clone().or_else(|| default_url.map(str::to_string)).or_else(|| env::var("CLICKHOUSE_URL").ok());
+        let Some(url) = url else {
+            return Ok(None);
+        };
+        if self.clickhouse_batch_size == 0 || self.clickhouse_flush_secs == 0 {
+            return Err(anyhow::anyhow!("--clickhouse-batch-size and --clickhouse-flush-secs must be positive"));
+        }
+        let config = ClickHouseConfig {
+            url,
+            database: self.clickhouse_database.clone(),
+            trades: self.clickhouse_trades,
+            batch_size: self.clickhouse_batch_size,
+            flush_interval: std::time::Duration::from_secs(self.clickhouse_flush_secs),
+        };
+        Ok(Some(ClickHouseSink::connect(config).await?))
+    }
+}
+
+/// QuestDB への書き込み (--questdb-addr がなければ書かない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct QuestDbArgs {
+    /// Also write trades and candles to QuestDB over InfluxDB line protocol (TCP) at this address (e.g., localhost:9009, or use QUESTDB_ADDR env var)
+    #[arg(long)]
+    pub questdb_addr: Option<String>,
+
+    /// QuestDB REST API used to create the trades / candles tables with their schemas at startup (e.g., http://localhost:9000; without it ILP creates them)
+    #[arg(long)]
+    pub questdb_http_url: Option<String>,
+
+    /// Write only candles to QuestDB (no tick-level trades)
+    #[arg(long)]
+    pub questdb_skip_trades: bool,
+}
+
+impl QuestDbArgs {
+    /// --questdb-addr, default_addr (設定ファイル), QUESTDB_ADDR の順で接続する (どれもなければ None)
+    pub async fn open(&self, default_addr: Option<&str>) -> anyhow::Result<Option<QuestDbSink>> {
+        let addr = self.questdb_addr.clone().or_else(|| default_addr.map(str::to_string)).or_else(|| env::var("QUESTDB_ADDR").ok());
+        let Some(addr) = addr else {
+            return Ok(None);
+        };
+        let config = QuestDbConfig { addr, http_url: self.questdb_http_url.clone(), trades: !self.questdb_skip_trades };
+        Ok(Some(QuestDbSink::connect(config).await?))
+    }
+}
+
+/// 日付ごとの Parquet ファイルへの書き込み (--archive-dir がなければ書かない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct ArchiveArgs {
+    /// Also write candles to date-partitioned Parquet files under this directory ({dir}/exchange=.../symbol=.../date=YYYY-MM-DD/*.parquet)
+    #[arg(long)]
+    pub archive_dir: Option<String>,
+
+    /// Also archive raw trades (trades-*.parquet next to candles-*.parquet)
+    #[arg(long)]
+    pub archive_trades: bool,
+
+    /// Write the buffered rows to new files this often (seconds; rows since the last file are lost on a crash)
+    #[arg(long, default_value_t = DEFAULT_ROTATE_SECONDS)]
+    pub archive_rotate_secs: u64,
+
+    /// Parquet compression: zstd, snappy, gzip, lz4 or none
+    #[arg(long, default_value = "zstd")]
+    pub archive_compression: String,
+}
+
+impl ArchiveArgs {
+    /// --archive-dir, なければ default_dir (設定ファイル) に書く (どちらもなければ None)
+    pub fn open(&self, default_dir: Option<&str>) -> anyhow::Result<Option<ArchiveStage>> {
+        let Some(dir) = self.archive_dir.as_deref().or(default_dir) else {
+            return Ok(None);
+        };
+        if self.archive_rotate_secs == 0 {
+            return Err(anyhow::anyhow!("--archive-rotate-secs must be positive"));
+        }
+        let compression = parquet_archive::parse_compression(&self.archive_compression)?;
+        std::fs::create_dir_all(dir)?;
+        tracing::info!("Archiving candles{} to Parquet under {} every {}s", if self.archive_trades { " and trades" } else { "" }, dir, self.archive_rotate_secs);
+        let archive = ParquetArchive::new(dir.into(), compression);
+        Ok(Some(archive.start(std::time::Duration::from_secs(self.archive_rotate_secs), self.archive_trades)))
+    }
+}
+
+/// 月ごとの SQLite ファイルへの書き込み (--sqlite-dir がなければ書かない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct SqliteArgs {
+    /// Also write candles to monthly SQLite files in this directory (kkcrypto-YYYY-MM.sqlite, WAL mode), e.g. to run without a MongoDB server
+    #[arg(long)]
+    pub sqlite_dir: Option<String>,
+
+    /// Also write raw trades to the SQLite files
+    #[arg(long)]
+    pub sqlite_trades: bool,
+}
+
+impl SqliteArgs {
+    /// --sqlite-dir, なければ default_dir (設定ファイル) に書く (どちらもなければ None)
+    pub fn open(&self, default_dir: Option<&str>) -> anyhow::Result<Option<SqliteStage>> {
+        let Some(dir) = self.sqlite_dir.as_deref().or(default_dir) else {
+            return Ok(None);
+        };
+        std::fs::create_dir_all(dir)?;
+        tracing::info!("Writing candles{} to SQLite files under {}", if self.sqlite_trades { " and trades" } else { "" }, dir);
+        Ok(Some(SqliteStore::new(dir.into()).start(self.sqlite_trades)))
+    }
+}
+
+/// MongoDB への約定の保存 (--db-trades がなければ保存しない)
+#[derive(clap::Args, Debug, Clone)]
+pub struct MongoTradeArgs {
+    /// Also store raw trades in MongoDB (trade_blobs: one column batch per symbol and minute, written a few seconds after the minute ends; needs --update)
+    #[arg(long)]
+    pub db_trades: bool,
+
+    /// Encoding of the stored trade batches: zstd or none
+    #[arg(long, default_value = "zstd")]
+    pub db_trades_codec: String,
+}
+
+impl MongoTradeArgs {
+    /// database の接続先・namespace で MongoDB に接続する (足の書き込みとは別の接続)
+    pub async fn open(&self, database: Option<&DatabaseArgs>, default_namespace: Option<&str>) -> anyhow::Result<Option<TradeBlobSink>> {
+        if !self.db_trades {
+            return Ok(None);
+        }
+        let codec = TradeCodec::parse(&self.db_trades_codec)?;
+        let database = database.filter(|database| database.update).ok_or_else(|| anyhow::anyhow!("--db-trades needs --update"))?;
+        let db = database.open(CandleFieldSelection::default(), default_namespace).await?;
+        tracing::info!("Storing trades in MongoDB ({})", codec.as_str());
+        Ok(Some(TradeBlobSink::start(Arc::new(db), codec)))
+    }
+}
+
+/// 設定ファイルでの書き込み先 (コマンドラインの指定がなければ使う)
+/// database は約定を MongoDB に保存するときの接続先
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SinkDefaults<'a> {
+    pub redis_url: Option<&'a str>,
+    pub nats_url: Option<&'a str>,
+    pub clickhouse_url: Option<&'a str>,
+    pub questdb_addr: Option<&'a str>,
+    pub archive_dir: Option<&'a str>,
+    pub sqlite_dir: Option<&'a str>,
+    pub database: Option<&'a DatabaseArgs>,
+    pub namespace: Option<&'a str>,  // --namespace, MONGODB_NAMESPACE がなければ使う (e.g. testnet)
+}
+
+/// 足・約定の書き込み先 (いくつでも同時に指定できる)
+#[derive(clap::Args, Debug, Clone)]
+pub struct SinkArgs {
+    #[command(flatten)]
+    pub redis: RedisArgs,
+
+    #[command(flatten)]
+    pub nats: NatsArgs,
+
+    #[command(flatten)]
+    pub clickhouse: ClickHouseArgs,
+
+    #[command(flatten)]
+    pub questdb: QuestDbArgs,
+
+    #[command(flatten)]
+    pub archive: ArchiveArgs,
+
+    #[command(flatten)]
+    pub sqlite: SqliteArgs,
+
+    #[command(flatten)]
+    pub mongo_trades: MongoTradeArgs,
+}
+
+impl SinkArgs {
+    /// 指定された全ての書き込み先に接続し, それぞれのキューとタスクを持つ SinkFanout にまとめる (どれかに接続できなければエラー)
+    /// candles は足を MongoDB に書き込むシンク (--update なしなら None)
+    pub async fn open(&self, defaults: SinkDefaults<'_>, candles: Option<MongoCandleSink>) -> anyhow::Result<SinkFanout> {
+        let mut sinks: Vec<Arc<dyn StorageSink>> = Vec::new();
+        if let Some(sink) = candles {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.redis.open(defaults.redis_url).await? {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.nats.open(defaults.nats_url).await? {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.clickhouse.open(defaults.clickhouse_url).await? {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.questdb.open(defaults.questdb_addr).await? {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.archive.open(defaults.archive_dir)? {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.sqlite.open(defaults.sqlite_dir)? {
+            sinks.push(Arc::new(sink));
+        }
+        if let Some(sink) = self.mongo_trades.open(defaults.database, defaults.namespace).await? {
+            sinks.push(Arc::new(sink));
+        }
+        let fanout = sinks.into_iter().fold(SinkFanout::new(), SinkFanout::with_sink);
+        if !fanout.is_empty() {
+            tracing::info!("Storage sinks: {}", fanout.names().join(", "));
+        }
+        Ok(fanout)
+    }
+}
+
+/// ローソク足の時間枠と境界の時差
+#[derive(clap::Args, Debug, Clone)]
+pub struct TimeframeArgs {
+    /// Timeframes to generate candles (comma-separated seconds or Ns/Nm/Nh/Nd, e.g., 1m,5m,90s,4h)
+    #[arg(short = 't', long, default_value = "1m")]
+    pub timeframes: String,
+
+    /// Candle boundary time zone: utc / funding (00/08/16 UTC), jst or utc+H[:MM] (e.g., utc+9 starts daily candles at 00:00 JST)
+    #[arg(long, default_value = "utc")]
+    pub session: String,
+}
+
+impl TimeframeArgs {
+    pub fn timeframes(&self) -> anyhow::Result<Vec<u32>> {
+        timeframe::parse_list(&self.timeframes)
+    }
+
+    pub fn session_offset(&self) -> anyhow::Result<i64> {
+        timeframe::parse_session_offset(&self.session)
+    }
+}
+
+/// stage (run(self, receiver, sender)) を起動して receiver の後ろにつなぎ, その出力の受信側を返す
+pub fn pipe_stage<F, Fut>(receiver: mpsc::Receiver<MarketEvent>, run: F) -> mpsc::Receiver<MarketEvent>
+where
+    F: FnOnce(mpsc::Receiver<MarketEvent>, mpsc::Sender<MarketEvent>) -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let (sender, next) = mpsc::channel::<MarketEvent>(1000);
+    tokio::spawn(run(receiver, sender));
+    next
+}
+
+/// 運用イベント (接続, 切断, 購読, DB 障害) をログに出し, TUI・/healthz に反映して ops_events に書き込む
+/// TUI のログ欄には tracing のログとして届く (--tui では LogArgs::init_dashboard がログをダッシュボードに流す)
+pub async fn write_ops_events(
+    label: String,
+    mut receiver: mpsc::UnboundedReceiver<OpsEvent>,
+    db: Arc<Database>,
+    dashboard: Option<Dashboard>,
+    health: Option<HealthState>,
+) {
+    while let Some(event) = receiver.recv().await {
+        tracing::info!("[{}-OPS] {}", label, event);
+        if let Some(dashboard) = &dashboard {
+            dashboard.record_ops(&event);
+        }
+        if let Some(health) = &health {
+            health.record_ops(&event);
+        }
+        if let Err(e) = db.insert_ops_event(&event).await {
+            tracing::error!("Failed to insert ops event: {}", e);
+        }
+    }
+}
+
+/// collector の出力 (DB, ストレージのシンク, /healthz, WebSocket 配信, TUI)
+/// 取引所ごとの collector と設定ファイルの collector が共有する
+pub struct CollectorOutputs {
+    exchange: String,  // EventWriter とログに出す名前 (e.g. bybit, collector)
+    db: Arc<Database>,
+    sinks: SinkFanout,
+    dashboard: Option<Dashboard>,
+    health: Option<HealthState>,
+    health_addr: Option<String>,
+    broadcaster: Option<EventBroadcaster>,
+    broadcast_addr: Option<String>,
+}
+
+impl CollectorOutputs {
+    /// health_addr があれば /healthz を, broadcast_addr があれば WebSocket 配信を用意する (serve で起動する)
+    pub fn new(exchange: &str, db: Arc<Database>, sinks: SinkFanout, dashboard: Option<Dashboard>, health_addr: Option<String>, broadcast_addr: Option<String>) -> Self {
+        Self {
+            exchange: exchange.to_string(),
+            db,
+            sinks,
+            dashboard,
+            health: health_addr.is_some().then(HealthState::new),
+            health_addr,
+            broadcaster: broadcast_addr.is_some().then(|| EventBroadcaster::new(BROADCAST_CAPACITY)),
+            broadcast_addr,
+        }
+    }
+
+    /// /status に足の集計のバッファの状況も出す
+    pub fn with_buffer_metrics(mut self, buffer_metrics: BufferMetrics) -> Self {
+        self.health = self.health.map(|health| health.with_buffer_metrics(buffer_metrics));
+        self
+    }
+
+    pub fn dashboard(&self) -> Option<&Dashboard> {
+        self.dashboard.as_ref()
+    }
+
+    /// 受信したイベントを /healthz と TUI に数える stage をつなぐ (取引所から受け取った直後に挟む)
+    pub fn watch(&self, mut receiver: mpsc::Receiver<MarketEvent>) -> mpsc::Receiver<MarketEvent> {
+        if let Some(health) = self.health.clone() {
+            receiver = pipe_stage(receiver, |receiver, sender| health.run(receiver, sender));
+        }
+        if let Some(dashboard) = self.dashboard.clone() {
+            receiver = pipe_stage(receiver, |receiver, sender| dashboard.run(receiver, sender));
+        }
+        receiver
+    }
+
+    /// 約定・足を WebSocket 配信とストレージのシンクに送る stage をつなぐ
+    /// 約定は足の集計の前に, 足は EventWriter の前に (write で) 送る
+    pub fn publish(&self, mut receiver: mpsc::Receiver<MarketEvent>) -> mpsc::Receiver<MarketEvent> {
+        if let Some(broadcaster) = self.broadcaster.clone() {
+            receiver = pipe_stage(receiver, |receiver, sender| broadcaster.run(receiver, sender));
+        }
+        if !self.sinks.is_empty() {
+            let sinks = self.sinks.clone();
+            receiver = pipe_stage(receiver, |receiver, sender| sinks.run(receiver, sender));
+        }
+        receiver
+    }
+
+    /// 足の集計の出力を配信・シンクに送り, EventWriter で表示と他のイベントの書き込みをする (panic した EventWriter は再起動する)
+    pub fn write(&self, receiver: mpsc::Receiver<MarketEvent>, sample_interval: Option<Duration>, price_decimals: Option<usize>) {
+        let receiver = self.publish(receiver);
+        let db = self.db.clone();
+        let exchange = self.exchange.clone();
+        let dashboard = self.dashboard.clone();
+        let new_event_writer = move |receiver: mpsc::Receiver<MarketEvent>| {
+            let mut event_writer = EventWriter::new(db.clone(), &exchange);
+            if let Some(sample_interval) = sample_interval {
+                event_writer = event_writer.with_sample_interval(sample_interval);
+            }
+            if let Some(price_decimals) = price_decimals {
+                event_writer = event_writer.with_price_decimals(price_decimals);
+            }
+            if let Some(dashboard) = dashboard.clone() {
+                event_writer = event_writer.with_dashboard(dashboard);
+            }
+            event_writer.run(receiver)
+        };
+        tokio::spawn(Supervisor::new("event writer").run_stage(receiver, new_event_writer));
+    }
+
+    /// 運用イベントを書き込むタスクを起動する (終了時は ops_events::uninstall の後にこのタスクを待つ)
+    pub fn spawn_ops_writer(&self, receiver: mpsc::UnboundedReceiver<OpsEvent>) -> JoinHandle<()> {
+        let label = self.exchange.to_uppercase();
+        tokio::spawn(write_ops_events(label, receiver, self.db.clone(), self.dashboard.clone(), self.health.clone()))
+    }
+
+    /// TUI と --health-addr, --broadcast-addr のサーバーを起動する (bind できなければエラー)
+    pub async fn serve(&self) -> anyhow::Result<()> {
+        if let Some(dashboard) = self.dashboard.clone() {
+            dashboard.with_database(self.db.clone()).spawn_tui();
+        }
+        if let (Some(addr), Some(health)) = (self.health_addr.as_deref(), self.health.clone()) {
+            serve_health(addr, health.with_database(self.db.clone()).with_sinks(self.sinks.clone())).await?;
+        }
+        if let (Some(addr), Some(broadcaster)) = (self.broadcast_addr.as_deref(), self.broadcaster.clone()) {
+            serve_broadcast(addr, broadcaster).await?;
+        }
+        Ok(())
+    }
+}
+
+/// 取引所ごとの collector (bybit, binance, ...) に共通のオプション
+#[derive(clap::Args, Debug)]
+pub struct CollectorArgs {
+    #[command(flatten)]
+    pub database: DatabaseArgs,
+
+    #[command(flatten)]
+    pub sinks: SinkArgs,
+
+    #[command(flatten)]
+    pub market: MarketArgs,
+
+    #[command(flatten)]
+    pub candles: TimeframeArgs,
+
+    #[command(flatten)]
+    pub logs: LogArgs,
+
+    /// Raw message print frequency (default: 100, minimum: 2)
+    #[arg(long, default_value = "100", value_parser = clap::value_parser!(u32).range(2..))]
+    raw_freq: u32,
+
+    /// Candle fields to persist per timeframe in seconds (e.g., 1=ask_price,bid_price;60=*;*=*)
+    #[arg(long)]
+    candle_fields: Option<String>,
+
+    /// Warm-up seconds after connect: candles starting within this period are discarded or flagged (0 = only the partial first candles)
+    #[arg(long)]
+    warmup: Option<u64>,
+
+    /// Warm-up handling: discard or flag (stored with warmup: true)
+    #[arg(long, default_value = "discard")]
+    warmup_mode: String,
+
+    /// Keep the last N hours of candles in an in-memory cache (0 = disabled)
+    #[arg(long, default_value = "0")]
+    cache_hours: u32,
+
+    /// Thin out trades per symbol before candle building (e.g., BTCUSDT=50ms,ETHUSDT=1/10,*=20ms; <N>ms conflates, 1/<N> samples)
+    #[arg(long)]
+    throttle: Option<String>,
+
+    /// Also build event-driven bars (e.g., tick:500,volume:10,dollar:1000000), stored in bars_{type}_{threshold}
+    #[arg(long)]
+    bar_type: Option<String>,
+
+    /// Also build tick/volume imbalance bars (e.g., tick:100,volume:200/20; initial expected trades per bar / EWMA span), stored in imbalance_bars_{tick|volume}
+    #[arg(long)]
+    imbalance_bar: Option<String>,
+
+    /// Also build Renko bricks / range bars with a fixed or ATR-based size (e.g., renko:100,range:atr14/60; ATR over 60s bars), stored in renko_{size} / range_{size}
+    #[arg(long)]
+    renko: Option<String>,
+
+    /// Also derive Heikin-Ashi candles for these timeframes (e.g., 1m,5m or * for all), stored in ha_candles_{timeframe}
+    #[arg(long)]
+    heikin_ashi: Option<String>,
+
+    /// Wait this long after each candle boundary before flushing; later trades for the previous candle emit a correction with revision + 1 (0 = disabled)
+    #[arg(long, default_value = "0")]
+    grace_ms: u64,
+
+    /// Also emit zero-volume candles (last price carried forward) for periods without trades
+    #[arg(long)]
+    emit_empty: bool,
+
+    /// Maximum number of open candle buffers (exchange/market/symbol/timeframe/period); beyond it the least recently traded symbol's open candles are dropped (0 = unlimited)
+    #[arg(long, default_value = "0")]
+    max_buffers: usize,
+
+    /// Maximum trade sizes kept per candle for the median trade size; beyond it the sample is thinned (0 = unlimited)
+    #[arg(long, default_value = "10000")]
+    max_trades_per_buffer: usize,
+
+    /// Keep the trades of the last N completed candles and recompute them on SIGUSR2, logging any difference from what was emitted (see `admin audit`)
+    #[arg(long)]
+    audit: Option<usize>,
+
+    /// Alert on live candles of the smallest timeframe (e.g., BTCUSDT:price>70000,ETHUSDT:move>2%/5m,*:volume>3x/20); notifies ALERT_WEBHOOK_URL / TELEGRAM_BOT_TOKEN + TELEGRAM_CHAT_ID
+    #[arg(long)]
+    watchlist: Option<String>,
+
+    /// Write open candles and subscriptions to this file on SIGUSR1 (keep running) or SIGTERM/SIGINT (stop; see `admin snapshot`)
+    #[arg(long)]
+    snapshot_file: Option<String>,
+
+    /// Restore open candles and subscriptions from a snapshot file written by --snapshot-file
+    #[arg(long)]
+    restore: Option<String>,
+
+    /// Timestamp used for candle bucketing: exchange (matching engine), gateway (message push time, where the exchange provides it) or receipt (local)
+    #[arg(long, default_value = "exchange")]
+    timestamp_source: String,
+
+    /// Reconnect when no frame (trades or heartbeat replies) has been received for this many seconds (0: disabled)
+    #[arg(long, default_value = "60")]
+    watchdog_secs: u64,
+
+    /// WebSocket host[:port] replacing the default one, keeping the path (e.g., a regional mirror)
+    #[arg(long)]
+    ws_host: Option<String>,
+
+    /// Connect through this proxy: http://[user:pass@]host:port or socks5://[user:pass@]host:port (or use PROXY_URL env var)
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Drop trades whose (exchange, symbol, trade_id) was seen among the last N trades, e.g. overlaps after reconnects or REST backfill (0: disabled; exchanges without trade ids are never deduplicated)
+    #[arg(long, default_value_t = DEFAULT_DEDUP_WINDOW)]
+    dedup_window: usize,
+
+    /// Warn (ops_events stale_feed, ALERT_WEBHOOK_URL / Telegram) when a symbol has had no trade for at least this many seconds and --stale-factor times its usual trade interval
+    #[arg(long)]
+    stale_secs: Option<u64>,
+
+    /// Multiple of a symbol's average trade interval that counts as stale (quiet symbols get a longer threshold)
+    #[arg(long, default_value = "20")]
+    stale_factor: f64,
+
+    /// Log rolling min/p50/p99/max latency (local receive time - exchange trade time) over this many seconds, every as many seconds (0: disabled)
+    #[arg(long, default_value_t = DEFAULT_LATENCY_WINDOW_SECONDS)]
+    latency_window_secs: u64,
+
+    /// Also store each candle's mean trade latency in milliseconds (latency_ms)
+    #[arg(long)]
+    candle_latency: bool,
+
+    /// What to do when the pipeline falls behind the WebSocket feed: block (stall the read loop), drop-oldest or drop-newest (dropped events are counted and logged every minute)
+    #[arg(long, default_value = "block")]
+    backpressure: String,
+
+    /// Events held for the pipeline before drop-oldest / drop-newest start dropping
+    #[arg(long, default_value_t = DEFAULT_BACKPRESSURE_CAPACITY)]
+    backpressure_capacity: usize,
+
+    /// Queue candles that could not be written during a MongoDB outage in this JSONL file and write them once it recovers (kept across restarts)
+    #[arg(long)]
+    write_ahead_file: Option<String>,
+
+    /// Serve /healthz (200 when connected, receiving and writing, 503 otherwise) and /status (JSON) on this address for liveness / readiness probes (e.g., 0.0.0.0:8080)
+    #[arg(long)]
+    health_addr: Option<String>,
+
+    /// Show a live terminal dashboard (per-symbol price, 1m volume, trades/s, candles, WebSocket and DB status) instead of printing every candle; q quits
+    #[arg(long)]
+    tui: bool,
+
+    /// Re-broadcast trades and candles as JSON over WebSocket on this address; clients filter with ?symbols=BTCUSDT&types=trade,candle or {"op":"subscribe",...} messages (e.g., 0.0.0.0:9001)
+    #[arg(long)]
+    broadcast_addr: Option<String>,
+
+    /// Inject connection faults for resilience testing (e.g., disconnect=0.001,delay=0.01,malform=0.01,seed=42)
+    #[cfg(feature = "chaos")]
+    #[arg(long)]
+    chaos: Option<String>,
+}
+
+impl CollectorArgs {
+    /// 市場を決めてログを初期化する (--tui ならダッシュボードのログ欄, --log-dir のファイルは取引所と市場ごと)
+    /// name は取引所の表示名 (e.g. Bybit), supported はその取引所の市場
+    pub fn start(self, name: &str, supported: &[MarketType]) -> anyhow::Result<ExchangeCollector> {
+        let market_type = self.market.market_type(name, supported)?;
+        let exchange = name.to_lowercase();
+        let dashboard = self.tui.then(|| Dashboard::new(&exchange));
+        self.logs.init_dashboard(&format!("{}-{}", exchange, market_type.as_str()), dashboard.as_ref())?;
+        dotenv::dotenv().ok();
+        Ok(ExchangeCollector {
+            args: self,
+            name: name.to_string(),
+            exchange,
+            market_type,
+            dashboard,
+            namespace: None,
+            gateway_time: true,
+            stablecoin_merge: None,
+            quote_interval: None,
+            price_decimals: None,
+            rest_host: None,
+            symbols_per_connection: 0,
+        })
+    }
+}
+
+/// 取引所のクライアントを作るときに使う, 全ての取引所に共通の設定
+#[derive(Clone)]
+pub struct ClientOptions {
+    pub events: mpsc::Sender<MarketEvent>,
+    pub raw_freq: u32,
+    pub endpoint: Endpoint,
+    pub watchdog: Option<Duration>,
+    #[cfg(feature = "chaos")]
+    pub chaos: Option<ChaosConfig>,
+}
+
+/// 1 つの取引所・市場の collector (CollectorArgs::start で作り, 取引所固有の設定を加えて run する)
+pub struct ExchangeCollector {
+    args: CollectorArgs,
+    name: String,      // 表示名 (e.g. Bybit)
+    exchange: String,  // e.g. bybit
+    market_type: MarketType,
+    dashboard: Option<Dashboard>,
+    namespace: Option<&'static str>,  // --namespace, MONGODB_NAMESPACE がなければ使う (e.g. testnet)
+    gateway_time: bool,               // 約定にゲートウェイの時刻があるか
+    stablecoin_merge: Option<StablecoinMerge>,
+    quote_interval: Option<Duration>,
+    price_decimals: Option<usize>,
+    rest_host: Option<String>,
+    symbols_per_connection: usize,
+}
+
+impl ExchangeCollector {
+    pub fn market_type(&self) -> &MarketType {
+        &self.market_type
+    }
+
+    /// --namespace, MONGODB_NAMESPACE がなければこの namespace に書き込む (e.g. --testnet)
+    pub fn with_namespace(mut self, namespace: Option<&'static str>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// 約定にゲートウェイの時刻がない取引所 (--timestamp-source gateway はエラー)
+    pub fn without_gateway_time(mut self) -> Self {
+        self.gateway_time = false;
+        self
+    }
+
+    /// ステーブルコイン建ての銘柄を 1 つの {BASE}-USD の系列にも集計する
+    pub fn with_stablecoin_merge(mut self, merge: Option<StablecoinMerge>) -> Self {
+        self.stablecoin_merge = merge;
+        self
+    }
+
+    /// 気配・マーク価格を symbol ごとにこの間隔で間引いて書き込む
+    pub fn with_quote_interval(mut self, interval: Duration) -> Self {
+        self.quote_interval = Some(interval);
+        self
+    }
+
+    /// 足を表示するときの価格の小数点以下の桁数
+    pub fn with_price_decimals(mut self, price_decimals: usize) -> Self {
+        self.price_decimals = Some(price_decimals);
+        self
+    }
+
+    /// 再接続時の REST 補完の接続先を既定のホストから変える
+    pub fn with_rest_host(mut self, rest_host: Option<String>) -> Self {
+        self.rest_host = rest_host;
+        self
+    }
+
+    /// 1 接続で購読する symbol 数 (0 ならクライアントの上限ごと, 上限がなければ 1 接続)
+    pub fn with_symbols_per_connection(mut self, symbols_per_connection: usize) -> Self {
+        self.symbols_per_connection = symbols_per_connection;
+        self
+    }
+
+    /// symbols (カンマ区切り) を new_client で作ったクライアントで購読し, 約定を足に集計して書き込む
+    /// 接続が再接続の上限を超えて止まるまで続け, 切断までのイベントを書き込んでから返る
+    pub async fn run<C, F>(self, symbols: &str, new_client: F) -> anyhow::Result<()>
+    where
+        C: ExchangeClient + 'static,
+        F: Fn(&ClientOptions) -> C,
+    {
+        let ExchangeCollector {
+            args,
+            name,
+            exchange,
+            market_type,
+            dashboard,
+            namespace,
+            gateway_time,
+            stablecoin_merge,
+            quote_interval,
+            price_decimals,
+            rest_host,
+            symbols_per_connection,
+        } = self;
+        let label = exchange.to_uppercase();
+        let symbols = parse_symbols(symbols);
+        let timeframes = args.candles.timeframes()?;
+
+        // Parse candle field selection
+        let candle_fields = match args.candle_fields.as_deref() {
+            Some(spec) => CandleFieldSelection::parse(spec).unwrap_or_else(|e| {
+                tracing::error!("{}", e);
+                std::process::exit(1);
+            }),
+            None => CandleFieldSelection::default(),
+        };
+
+        let timestamp_source = TimestampSource::parse(&args.timestamp_source)?;
+        if !gateway_time && timestamp_source == TimestampSource::Gateway {
+            tracing::error!("--timestamp-source gateway is not available for {} (use exchange or receipt)", exchange);
+            std::process::exit(1);
+        }
+
+        tracing::info!("Starting {} {} trade collector with symbols: {:?}, timeframes: {:?}",
+              name, market_type.as_str().to_uppercase(), symbols, timeframes);
+
+        // Restore state written before a planned restart
+        let restored = args.restore.as_deref().map(|path| CollectorSnapshot::load(Path::new(path))).transpose()?;
+        let symbols = match &restored {
+            Some(snapshot) => {
+                snapshot.check(&exchange, &market_type)?;
+                snapshot.merge_symbols(symbols)
+            }
+            None => symbols,
+        };
+        let restore_detail = restored.as_ref().zip(args.restore.as_deref()).map(|(snapshot, path)| format!("{} ({})", path, snapshot.summary()));
+
+        // Create channels
+        let (event_tx, event_rx) = mpsc::channel::<MarketEvent>(1000);
+        let (output_tx, mut output_rx) = mpsc::channel::<MarketEvent>(1000);
+        let watchlist = args.watchlist.as_deref().map(Watchlist::parse).transpose()?;
+        let snapshot_timeframes = timeframes.clone();
+        let alert_timeframe = timeframes.iter().copied().min().unwrap_or(60);
+        let heikin_ashi = args.heikin_ashi.as_deref().map(|spec| HeikinAshiBuilder::parse(spec, &timeframes)).transpose()?;
+        // Track feed quality (daily report on the smallest timeframe)
+        let quality = Arc::new(Mutex::new(QualityTracker::new(
+            &exchange, market_type.clone(), timeframes.iter().copied().min().unwrap_or(60),
+        )));
+
+        // Handle database operations or print, and write candles and trades to the storage sinks
+        // (MongoDB, Redis, NATS, ClickHouse, QuestDB, Parquet, SQLite)
+        let db = Arc::new(args.database.open(candle_fields, namespace).await?);
+        let candles = candle_sink(&db, &exchange, args.write_ahead_file.as_deref(), dashboard.as_ref())?;
+        let sinks = args.sinks.open(SinkDefaults { database: Some(&args.database), namespace, ..Default::default() }, candles).await?;
+        let buffer_metrics = BufferMetrics::default();
+        let outputs = CollectorOutputs::new(&exchange, db.clone(), sinks, dashboard, args.health_addr.clone(), args.broadcast_addr.clone())
+            .with_buffer_metrics(buffer_metrics.clone());
+        if let Some(dashboard) = outputs.dashboard() {
+            dashboard.track(&symbols);
+        }
+
+        let mut event_rx = outputs.watch(event_rx);
+        let backpressure = BackpressurePolicy::parse(&args.backpressure)?;
+        if backpressure != BackpressurePolicy::Block {
+            let queue = BackpressureQueue::new(backpressure, args.backpressure_capacity);
+            tokio::spawn(queue.metrics().log_every(label.clone(), Duration::from_secs(60)));
+            event_rx = pipe_stage(event_rx, |receiver, sender| queue.run(receiver, sender));
+        }
+        if args.latency_window_secs > 0 {
+            let window = Duration::from_secs(args.latency_window_secs);
+            let latency = LatencyMetrics::new(chrono::Duration::seconds(args.latency_window_secs as i64));
+            tokio::spawn(latency.clone().log_every(label.clone(), window));
+            event_rx = pipe_stage(event_rx, |receiver, sender| latency.run(receiver, sender));
+        }
+        if let Some(seconds) = args.stale_secs {
+            let config = StaleFeedConfig::new(chrono::Duration::seconds(seconds as i64), args.stale_factor);
+            let monitor = StaleFeedMonitor::new(&exchange, &symbols, config).with_notifier(Notifier::from_env());
+            event_rx = pipe_stage(event_rx, |receiver, sender| monitor.run(receiver, sender));
+        }
+        if args.dedup_window > 0 {
+            let dedup = TradeDedup::new(args.dedup_window).with_quality(quality.clone());
+            event_rx = pipe_stage(event_rx, |receiver, sender| dedup.run(receiver, sender));
+        }
+        if let Some(spec) = args.throttle.as_deref() {
+            let throttle = TradeThrottle::new(ThrottleConfig::parse(spec)?);
+            event_rx = pipe_stage(event_rx, |receiver, sender| throttle.run(receiver, sender));
+        }
+        if let Some(spec) = args.bar_type.as_deref() {
+            let bar_builder = BarBuilder::new(BarType::parse_list(spec)?);
+            event_rx = pipe_stage(event_rx, |receiver, sender| bar_builder.run(receiver, sender));
+        }
+        if let Some(spec) = args.imbalance_bar.as_deref() {
+            let imbalance_bar_builder = ImbalanceBarBuilder::new(ImbalanceBarConfig::parse_list(spec)?);
+            event_rx = pipe_stage(event_rx, |receiver, sender| imbalance_bar_builder.run(receiver, sender));
+        }
+        if let Some(spec) = args.renko.as_deref() {
+            let renko_builder = RenkoBuilder::new(BrickConfig::parse_list(spec)?);
+            event_rx = pipe_stage(event_rx, |receiver, sender| renko_builder.run(receiver, sender));
+        }
+        // Re-broadcast trades and write them to the sinks that keep them (candles are sent before the event writer)
+        let event_rx = outputs.publish(event_rx);
+
+        // Start trade candle builder (a panicking builder is restarted with the same options and handles; its open candles are lost)
+        let session_offset = args.candles.session_offset()?;
+        let warmup = match args.warmup {
+            Some(seconds) => Some((Duration::from_secs(seconds), WarmupMode::parse(&args.warmup_mode)?)),
+            None => None,
+        };
+        if let Some(merge) = &stablecoin_merge {
+            for symbol in &symbols {
+                if let Some(logical) = merge.logical_symbol(symbol) {
+                    if SYMBOL_MANAGER.get_symbol_id(&exchange, &logical, market_type.as_str()).is_none() {
+                        tracing::warn!("{} ({}) is not in master.csv; merged candles cannot be stored", logical, symbol);
+                    }
+                }
+            }
+        }
+        let snapshot_control = args.snapshot_file.is_some().then(SnapshotControl::new);
+        let audit_control = args.audit.map(|_| AuditControl::new());
+        if let Some(control) = audit_control.clone() {
+            let audit_handler = audit::serve(label.clone(), control);
+            tokio::spawn(async move {
+                if let Err(e) = audit_handler.await {
+                    tracing::error!("Audit handler stopped: {}", e);
+                }
+            });
+        }
+        let logged_metrics = buffer_metrics.clone();
+        let buffers_label = label.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(600));
+            loop {
+                ticker.tick().await;
+                tracing::info!("[{}-BUFFERS] {}", buffers_label, logged_metrics);
+            }
+        });
+        let candle_cache = (args.cache_hours > 0).then(|| Arc::new(Mutex::new(CandleCache::new(chrono::Duration::hours(args.cache_hours as i64)))));
+        if let Some(candle_cache) = candle_cache.clone() {
+            let cache_hours = args.cache_hours;
+            let cache_label = label.clone();
+            tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(Duration::from_secs(600));
+                loop {
+                    ticker.tick().await;
+                    tracing::info!("[{}-CACHE] {} candles cached (last {}h)", cache_label, candle_cache.lock().unwrap().len(), cache_hours);
+                }
+            });
+        }
+        let mut restored_candles = restored.map(|snapshot| snapshot.candles);
+        let builder_quality = quality.clone();
+        let builder_snapshot_control = snapshot_control.clone();
+        let (max_buffers, max_trades_per_buffer) = (args.max_buffers, args.max_trades_per_buffer);
+        let (candle_latency, emit_empty, grace_ms, audit_candles) = (args.candle_latency, args.emit_empty, args.grace_ms, args.audit);
+        let new_candle_builder = move |event_rx: mpsc::Receiver<MarketEvent>| {
+            let mut candle_builder = TradeCandleBuilder::new(event_rx, output_tx.clone(), timeframes.clone())
+                .with_quality(builder_quality.clone())
+                .with_timestamp_source(timestamp_source)
+                .with_session_offset(session_offset)
+                .with_buffer_limits(max_buffers, max_trades_per_buffer)
+                .with_buffer_metrics(buffer_metrics.clone());
+            if candle_latency {
+                candle_builder = candle_builder.with_latency();
+            }
+            if let Some(merge) = stablecoin_merge.clone() {
+                candle_builder = candle_builder.with_stablecoin_merge(merge);
+            }
+            if let Some((period, mode)) = warmup {
+                candle_builder = candle_builder.with_warmup(period, mode);
+            }
+            // スナップショットの足は最初の起動でだけ引き継ぐ
+            if let Some(candles) = restored_candles.take() {
+                candle_builder = candle_builder.with_snapshot(candles);
+            }
+            if let Some(control) = &builder_snapshot_control {
+                candle_builder = candle_builder.with_snapshot_control(control);
+            }
+            if emit_empty {
+                candle_builder = candle_builder.with_emit_empty();
+            }
+            if grace_ms > 0 {
+                candle_builder = candle_builder.with_grace(Duration::from_millis(grace_ms));
+            }
+            if let (Some(candles), Some(control)) = (audit_candles, &audit_control) {
+                candle_builder = candle_builder.with_audit(candles).with_audit_control(control);
+            }
+            if let Some(candle_cache) = &candle_cache {
+                candle_builder = candle_builder.with_cache(candle_cache.clone());
+            }
+            candle_builder.start()
+        };
+        tokio::spawn(Supervisor::new("candle builder").run_stage(event_rx, new_candle_builder));
+
+        // Start operational event writer (connects, disconnects, subscribes, DB outages), the TUI and the servers
+        let ops_writer = outputs.spawn_ops_writer(ops_events::install(&exchange, &market_type));
+        ops_events::record(OpsEventKind::Start, format!("symbols={}", symbols.join(",")));
+        outputs.serve().await?;
+        if let Some(detail) = restore_detail {
+            ops_events::record(OpsEventKind::Restore, detail);
+        }
+        if let (Some(path), Some(control)) = (args.snapshot_file.as_deref(), snapshot_control) {
+            let snapshot_handler = snapshot::serve(exchange.clone(), market_type.clone(), symbols.clone(), snapshot_timeframes, control, PathBuf::from(path));
+            tokio::spawn(async move {
+                if let Err(e) = snapshot_handler.await {
+                    tracing::error!("Snapshot handler stopped: {}", e);
+                }
+            });
+        }
+
+        // Start daily quality report writer
+        let (report_tx, mut report_rx) = mpsc::channel::<QualityReport>(10);
+        tokio::spawn(QualityTracker::start_daily(quality, report_tx));
+        let report_db = db.clone();
+        tokio::spawn(async move {
+            while let Some(report) = report_rx.recv().await {
+                tracing::info!("{}", report.to_string().trim_end());
+                if let Err(e) = report_db.insert_quality_report(&report).await {
+                    tracing::error!("Failed to insert quality report: {}", e);
+                }
+            }
+        });
+
+        // Start event writer (displays candles and writes the other market events from the builder)
+        if let Some(heikin_ashi) = heikin_ashi {
+            output_rx = pipe_stage(output_rx, |receiver, sender| heikin_ashi.run(receiver, sender));
+        }
+        if let Some(watchlist) = watchlist {
+            let alert_engine = AlertEngine::new(watchlist, alert_timeframe, &exchange).with_notifier(Notifier::from_env());
+            output_rx = pipe_stage(output_rx, |receiver, sender| alert_engine.run(receiver, sender));
+        }
+        outputs.write(output_rx, quote_interval, price_decimals);
+
+        // Start the exchange clients (one per slice of symbols)
+        let options = ClientOptions {
+            events: event_tx,
+            raw_freq: args.raw_freq,
+            endpoint: Endpoint::new()
+                .with_ws_host(args.ws_host.clone())
+                .with_rest_host(rest_host)
+                .with_proxy(ProxyConfig::from_arg_or_env(args.proxy.as_deref())?)?,
+            watchdog: (args.watchdog_secs > 0).then(|| Duration::from_secs(args.watchdog_secs)),
+            #[cfg(feature = "chaos")]
+            chaos: args.chaos.as_deref().map(ChaosConfig::parse).transpose()?,
+        };
+        let result = connection_shards::run_clients(|| new_client(&options), &symbols, symbols_per_connection, market_type.clone()).await;
+
+        // 切断までのイベントを書き込んでから終了する
+        ops_events::uninstall();
+        let _ = ops_writer.await;
+        result
+    }
+}
@@ -0,0 +1,1204 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use clap::Parser;
+use super::common::{self, LogArgs};
+use futures::StreamExt;
+use mongodb::bson::{doc, Document};
+use mongodb::change_stream::{event::{ChangeStreamEvent, ResumeToken}, ChangeStream};
+use mongodb::error::ErrorKind;
+use mongodb::options::FullDocumentType;
+use crate::db::{candle_collection_name, connect_federated, namespaced_collection, shard_urls, validate_namespace};
+use crate::utils::correlation_matrix::{CorrelationMatrix, MatrixFormat};
+use crate::utils::pca::principal_components;
+use crate::utils::clustering::{cluster, Linkage};
+use crate::utils::correlation_alert::{parse_rules, CorrelationAlerts};
+use crate::utils::notify::Notifier;
+use crate::utils::beta::{regress, BetaParams, BetaStore, SymbolBeta, BETA_COLLECTION};
+use crate::utils::ewma::{EwmaCorrelation, SymbolVolatility};
+use crate::utils::price_window::{price_point, PricePoint, PriceWindow};
+use crate::utils::rank_correlation::CorrelationMethod;
+use crate::utils::resample::{resample_long, FillPolicy, TimeGrid};
+use crate::utils::returns::log_returns_frame;
+use crate::utils::correlation_store::{CorrelationParams, CorrelationStore, CORRELATION_COLLECTION};
+use crate::utils::rolling_correlation::{PairCorrelation, RollingCorrelation};
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use crate::utils::timeframe;
+use polars::prelude::*;
+use polars::lazy::dsl::pearson_corr;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+/// 計算を待っている変更の数 (これを超えると change stream の読み出しが待つ)
+const WATCH_QUEUE_CAPACITY: usize = 100_000;
+/// change stream を開き直せなかったときに待つ時間
+const WATCH_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+/// --incremental でバケットを確定させるまでに待つ時間 (足の書き込みの遅れ. interval の方が長ければ interval)
+const SETTLE_MS: i64 = 2000;
+
+
+#[derive(Parser, Debug)]
+#[command(name = "correlation")]
+#[command(about = "Real-time correlation calculator for cryptocurrency data")]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; all shards are read)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Correlation windows in minutes, comma-separated to compute several matrices from one data load (default: 30)
+    #[arg(short = 'w', long, default_value = "30")]
+    window_minutes: String,
+
+    /// Minimum data points required for correlation (default: 300)
+    #[arg(short = 'm', long, default_value = "300")]
+    min_data_points: usize,
+
+    /// Correlation calculation interval in seconds (default: 5)
+    #[arg(short = 'i', long, default_value = "5")]
+    interval: u64,
+
+    /// Use top-of-book quotes (quotes collection) instead of taker VWAP candles for the mid price
+    #[arg(long)]
+    quotes: bool,
+
+    /// Missing bucket policy: ffill, ffill:<buckets> (capped), interpolate, drop or none
+    #[arg(long, default_value = "ffill")]
+    fill: String,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    /// Follow new candles with a MongoDB change stream instead of re-querying the whole window every interval (needs a replica set; falls back to re-querying if the stream cannot be opened)
+    #[arg(long)]
+    watch: bool,
+
+    /// Keep running per-pair sums and only read candles newer than the window each interval (ffill, ffill:<buckets>, none or drop)
+    #[arg(long)]
+    incremental: bool,
+
+    /// Also write each correlation matrix to the correlations collection (one document per pair)
+    #[arg(long)]
+    update: bool,
+
+    /// Correlate log returns ln(p_t / p_{t-horizon}) instead of price levels
+    #[arg(long)]
+    returns: bool,
+
+    /// Return horizon in -i buckets for --returns (default: 1)
+    #[arg(long, default_value = "1")]
+    return_horizon: usize,
+
+    /// Correlation coefficient: pearson, spearman (rank) or kendall (tau-b)
+    #[arg(long, default_value = "pearson")]
+    method: String,
+
+    /// Also report exponentially weighted correlations and per-symbol EWMA volatility with this half-life (seconds or 30s/5m/1h)
+    #[arg(long)]
+    ewma_half_life: Option<String>,
+
+    /// Also report each symbol's beta and R² of returns against this benchmark, e.g. bybit:linear:BTCUSDT (needs --returns)
+    #[arg(long)]
+    beta: Option<String>,
+
+    /// Only correlate these symbols, comma-separated <exchange>:<market_type>:<symbol> (e.g., bybit:linear:BTCUSDT,bybit:linear:ETHUSDT)
+    #[arg(long)]
+    symbols: Option<String>,
+
+    /// Only correlate these symbol ids, comma-separated (together with --symbols)
+    #[arg(long)]
+    symbol_ids: Option<String>,
+
+    /// Drop symbols with a value in less than this share of the window's buckets after fill (0-1, default: 0 keeps all)
+    #[arg(long, default_value = "0")]
+    min_coverage: f64,
+
+    /// Matrix output: table (log lines), json (one JSON line per window and interval) or csv (header and one row per symbol)
+    #[arg(long, default_value = "table")]
+    output: String,
+
+    /// Also report the top-k principal components (explained variance and loadings) of each correlation matrix
+    #[arg(long)]
+    pca: Option<usize>,
+
+    /// Also cluster the symbols of each correlation matrix hierarchically (distance 1 - correlation) and report the clusters cut at this distance (0-2, e.g. 0.5)
+    #[arg(long)]
+    cluster: Option<f64>,
+
+    /// Linkage for --cluster: average, single or complete
+    #[arg(long, default_value = "average")]
+    linkage: String,
+
+    /// Alert when a pair's correlation stays below/above a level for N intervals, e.g. 1/6<0.5x3 or bybit:linear:BTCUSDT/bybit:linear:ETHUSDT<0.5x3@30 (comma-separated); notifies ALERT_WEBHOOK_URL / TELEGRAM_BOT_TOKEN + TELEGRAM_CHAT_ID
+    #[arg(long)]
+    alert: Option<String>,
+
+    /// Append json/csv matrices to this file instead of stdout
+    #[arg(long)]
+    output_file: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let output = MatrixFormat::parse(&args.output)?;
+    if output == MatrixFormat::Table && args.output_file.is_some() {
+        return Err(anyhow::anyhow!("--output-file needs --output json or --output csv"));
+    }
+    // stdout は行列の出力先になるので, 相関などの表示 (tracing) は stderr に出す
+    let status_to_stderr = output != MatrixFormat::Table && args.output_file.is_none();
+
+    // Load .env file (RUST_LOG も読む)
+    dotenv::dotenv().ok();
+
+    // Initialize tracing
+    if status_to_stderr {
+        args.logs.init_stderr("correlation")?;
+    } else {
+        args.logs.init("correlation")?;
+    }
+    info!("Starting correlation: window_minutes={}, min_data_points={}", args.window_minutes, args.min_data_points);
+
+    // Get database URL
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .expect("MONGODB_URL must be set");
+    debug!("Database URL: {}", database_url.replace(|c: char| c.is_alphanumeric() || c == '@' || c == '.' || c == ':', "*"));
+
+    // Connect to MongoDB
+    let databases = connect_federated(&database_url, &shard_urls(args.shard_urls.as_deref())).await?;
+    debug!("Selected databases: {:?}", databases.iter().map(|db| db.name()).collect::<Vec<_>>());
+    // Select collection based on interval (quotes は bid/ask が実際の最良気配)
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let collection_name = if args.quotes {
+        namespaced_collection(namespace.as_deref(), "quotes")
+    } else {
+        namespaced_collection(namespace.as_deref(), &candle_collection_name(args.interval as i32).ok_or_else(|| anyhow::anyhow!("Invalid interval: {}", args.interval))?)
+    };
+    let collections: Vec<mongodb::Collection<Document>> = databases.iter().map(|db| db.collection::<Document>(&collection_name)).collect();
+    info!("Connected to MongoDB, reading {}", collection_name);
+
+    // Verify database connection
+    let test_filter = doc! { 
+        "unixtime": { "$gte": mongodb::bson::DateTime::from_millis(Utc::now().timestamp_millis() - 60000) }
+    };
+    match collections[0].find_one(test_filter).await {
+        Ok(Some(_)) => debug!("Database connection verified"),
+        Ok(None) => warn!("No recent data found in database"),
+        Err(e) => {
+            error!("Failed to connect to database: {}", e);
+            return Err(e.into());
+        }
+    }
+
+    let fill_policy = FillPolicy::parse(&args.fill)?;
+    let method = CorrelationMethod::parse(&args.method)?;
+    let windows = parse_windows(&args.window_minutes)?;
+    let longest_window = windows[windows.len() - 1];
+
+    let mut calculator = CorrelationCalculator::new(
+        collections.clone(),
+        windows,
+        args.interval as i64,
+        fill_policy,
+    )
+    .with_method(method);
+    let ewma_half_life = args.ewma_half_life.as_deref().map(timeframe::parse).transpose()?;
+    if let Some(half_life) = ewma_half_life {
+        calculator = calculator.with_ewma(half_life);
+    }
+    if args.returns {
+        if args.return_horizon == 0 {
+            return Err(anyhow::anyhow!("--return-horizon must be positive"));
+        }
+        calculator = calculator.with_returns(args.return_horizon);
+    }
+    // beta は価格の水準ではなく収益率で取る
+    if let Some(spec) = args.beta.as_deref() {
+        if !args.returns {
+            return Err(anyhow::anyhow!("--beta needs --returns"));
+        }
+        let benchmark = SYMBOL_MANAGER.resolve(spec)?;
+        info!("Beta benchmark: {} (symbol {})", spec, benchmark);
+        calculator = calculator.with_benchmark(benchmark);
+    }
+    // --symbols と --symbol-ids の symbol だけを読む
+    if args.symbols.is_some() || args.symbol_ids.is_some() {
+        let mut universe = Vec::new();
+        for spec in common::parse_symbols(args.symbols.as_deref().unwrap_or_default()) {
+            universe.push(SYMBOL_MANAGER.resolve(&spec)?);
+        }
+        for id in common::parse_symbols(args.symbol_ids.as_deref().unwrap_or_default()) {
+            universe.push(id.parse::<i32>().map_err(|_| anyhow::anyhow!("Invalid symbol id: {}", id))?);
+        }
+        if universe.len() < 2 {
+            return Err(anyhow::anyhow!("--symbols / --symbol-ids need at least 2 symbols"));
+        }
+        universe.sort_unstable();
+        universe.dedup();
+        info!("Symbols: {:?}", universe);
+        calculator = calculator.with_universe(universe);
+    }
+    if !(0.0..=1.0).contains(&args.min_coverage) {
+        return Err(anyhow::anyhow!("--min-coverage must be between 0 and 1: {}", args.min_coverage));
+    }
+    calculator = calculator.with_min_coverage(args.min_coverage);
+    if let Some(top_k) = args.pca {
+        if top_k == 0 {
+            return Err(anyhow::anyhow!("--pca must be positive"));
+        }
+        calculator = calculator.with_pca(top_k);
+    }
+    if let Some(distance) = args.cluster {
+        if !(0.0..=2.0).contains(&distance) {
+            return Err(anyhow::anyhow!("--cluster must be between 0 and 2: {}", distance));
+        }
+        calculator = calculator.with_clusters(distance, Linkage::parse(&args.linkage)?);
+    }
+    // symbol は symbol_id か <exchange>:<market_type>:<symbol>
+    if let Some(spec) = args.alert.as_deref() {
+        let rules = parse_rules(spec, |symbol| symbol.parse::<i32>().or_else(|_| SYMBOL_MANAGER.resolve(symbol)))?;
+        info!("Correlation alerts: {}", rules.iter().map(|rule| rule.spec.as_str()).collect::<Vec<_>>().join(", "));
+        calculator = calculator.with_alerts(CorrelationAlerts::new(rules), Notifier::from_env());
+    }
+    if args.incremental {
+        calculator = calculator.incremental(args.min_data_points)?;
+    }
+    // 結果は MONGODB_URL (シャード 0) に書き込む
+    let mut stores = Stores { correlations: None, betas: None, matrices: None };
+    if output != MatrixFormat::Table {
+        let writer: Box<dyn Write + Send> = match args.output_file.as_deref() {
+            Some(path) => Box::new(std::io::BufWriter::new(std::fs::OpenOptions::new().create(true).append(true).open(path)?)),
+            None => Box::new(std::io::stdout()),
+        };
+        info!("Writing {} matrices to {}", output.as_str(), args.output_file.as_deref().unwrap_or("stdout"));
+        stores.matrices = Some(MatrixOutput { format: output, writer: Mutex::new(writer) });
+    }
+    if args.update {
+        let collection_name = namespaced_collection(namespace.as_deref(), CORRELATION_COLLECTION);
+        let params = CorrelationParams {
+            window_minutes: longest_window,
+            interval_seconds: args.interval as i64,
+            returns: args.returns.then_some(args.return_horizon),
+            method,
+        };
+        stores.correlations = Some(CorrelationStore::open(&databases[0], &collection_name, params).await?);
+        info!("Writing correlations to {}", collection_name);
+        if args.beta.is_some() {
+            let collection_name = namespaced_collection(namespace.as_deref(), BETA_COLLECTION);
+            let params = BetaParams { window_minutes: longest_window, interval_seconds: args.interval as i64, returns: args.return_horizon };
+            stores.betas = Some(BetaStore::open(&databases[0], &collection_name, params).await?);
+            info!("Writing betas to {}", collection_name);
+        }
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(args.interval));
+
+    // change stream を先に開いてから窓を読むので, 読み込み中に書かれた足も取りこぼさない
+    if args.watch {
+        match open_streams(&collections).await {
+            Ok(streams) => {
+                info!("Starting change stream mode ({} second intervals)...", args.interval);
+                let (sender, receiver) = mpsc::channel(WATCH_QUEUE_CAPACITY);
+                for (collection, stream) in collections.iter().zip(streams) {
+                    tokio::spawn(watch_collection(collection.clone(), stream, sender.clone()));
+                }
+                return run_watch(calculator, interval, receiver, stores).await;
+            }
+            Err(e) => warn!("Cannot open a change stream on {} ({}); re-querying the whole window instead", collection_name, e),
+        }
+    }
+
+    // Use interval timer approach
+    info!("Starting interval timer mode ({} second intervals)...", args.interval);
+    loop {
+        // Wait for next tick
+        interval.tick().await;
+        
+        // Load all data for the window period (--incremental なら 2 回目からは新しい足だけ)
+        let start_time = Instant::now();
+        let result = if calculator.is_loaded() {
+            calculator.load_new_data().await
+        } else {
+            calculator.load_initial_data().await
+        };
+        match result {
+            Ok(_) => {
+                let elapsed = start_time.elapsed();
+                debug!("Data load and processing: {:?}", elapsed);
+                calculator.report(&stores).await;
+            }
+            Err(e) => {
+                error!("Error loading data: {}", e);
+            }
+        }
+    }
+}
+
+/// change stream の変更を interval ごとにまとめて窓に足して計算する
+/// resume token を失ったシャードがあれば (oplog から消えた・コレクションが削除された) 窓全体を読み直す
+async fn run_watch(
+    mut calculator: CorrelationCalculator,
+    mut interval: tokio::time::Interval,
+    mut receiver: mpsc::Receiver<WatchUpdate>,
+    stores: Stores,
+) -> Result<()> {
+    let mut reload = true;
+    loop {
+        interval.tick().await;
+
+        let start_time = Instant::now();
+        let mut points = Vec::new();
+        while let Ok(update) = receiver.try_recv() {
+            match update {
+                WatchUpdate::Point(point) => points.push(point),
+                WatchUpdate::Lost => reload = true,
+            }
+        }
+        // 読み直す場合は届いた変更も読み直しに含まれる
+        let result = if reload {
+            calculator.load_initial_data().await
+        } else {
+            calculator.apply_changes(&points)
+        };
+        match result {
+            Ok(()) => {
+                if !reload {
+                    debug!("Applied {} changes: {:?}", points.len(), start_time.elapsed());
+                }
+                reload = false;
+                calculator.report(&stores).await;
+            }
+            Err(e) => error!("Error loading data: {}", e),
+        }
+    }
+}
+
+/// --update と --output の書き込み先
+struct Stores {
+    correlations: Option<CorrelationStore>,
+    betas: Option<BetaStore>,  // --beta のとき
+    matrices: Option<MatrixOutput>,  // --output json|csv のとき
+}
+
+/// --output json|csv の行列の書き出し先 (stdout か --output-file)
+struct MatrixOutput {
+    format: MatrixFormat,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl MatrixOutput {
+    /// 計算ごとに書き出す (読む側がすぐ受け取れるように flush する)
+    fn write(&self, matrix: &CorrelationMatrix) -> Result<()> {
+        let mut writer = self.writer.lock().map_err(|_| anyhow::anyhow!("Matrix output writer is poisoned"))?;
+        matrix.write(self.format, &mut *writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+type CandleStream = ChangeStream<ChangeStreamEvent<Document>>;
+
+/// change stream の watcher から計算側への通知
+enum WatchUpdate {
+    Point(PricePoint),
+    Lost,  // resume token を失ったので窓全体を読み直す
+}
+
+/// 足の追加・書き直し (upsert) の change stream を開く (resume が None なら今から)
+async fn open_stream(collection: &mongodb::Collection<Document>, resume: Option<ResumeToken>) -> mongodb::error::Result<CandleStream> {
+    collection
+        .watch()
+        .pipeline([doc! { "$match": { "operationType": { "$in": ["insert", "replace", "update"] } } }])
+        .full_document(FullDocumentType::UpdateLookup)
+        .resume_after(resume)
+        .await
+}
+
+async fn open_streams(collections: &[mongodb::Collection<Document>]) -> mongodb::error::Result<Vec<CandleStream>> {
+    let mut streams = Vec::with_capacity(collections.len());
+    for collection in collections {
+        streams.push(open_stream(collection, None).await?);
+    }
+    Ok(streams)
+}
+
+/// resume token から再開できない (InvalidResumeToken, ChangeStreamFatalError, ChangeStreamHistoryLost)
+fn is_resume_token_lost(error: &mongodb::error::Error) -> bool {
+    matches!(*error.kind, ErrorKind::Command(ref error) if matches!(error.code, 260 | 280 | 286))
+}
+
+/// 1 つのシャードのコレクションの変更を送り続ける
+/// 切れたら resume token から再開し, token を失っていたら今から開き直して Lost を送る
+async fn watch_collection(collection: mongodb::Collection<Document>, mut stream: CandleStream, sender: mpsc::Sender<WatchUpdate>) {
+    loop {
+        let mut token = match stream.next().await {
+            Some(Ok(event)) => {
+                if let Some(point) = event.full_document.as_ref().and_then(price_point) {
+                    if sender.send(WatchUpdate::Point(point)).await.is_err() {
+                        return;
+                    }
+                }
+                continue;
+            }
+            Some(Err(e)) => {
+                warn!("Change stream on {} failed: {}", collection.namespace(), e);
+                stream.resume_token()
+            }
+            // invalidate (コレクションの削除・名前の変更) で閉じたストリームは再開できない
+            None => {
+                warn!("Change stream on {} was closed", collection.namespace());
+                None
+            }
+        };
+        stream = loop {
+            match open_stream(&collection, token.clone()).await {
+                Ok(stream) if token.is_some() => {
+                    info!("Resumed change stream on {}", collection.namespace());
+                    break stream;
+                }
+                Ok(stream) => {
+                    if sender.send(WatchUpdate::Lost).await.is_err() {
+                        return;
+                    }
+                    break stream;
+                }
+                Err(e) if token.is_some() && is_resume_token_lost(&e) => {
+                    warn!("Change stream on {} cannot be resumed ({}); reloading the window", collection.namespace(), e);
+                    token = None;
+                }
+                Err(e) => {
+                    error!("Failed to reopen change stream on {}: {}", collection.namespace(), e);
+                    tokio::time::sleep(WATCH_RETRY_INTERVAL).await;
+                }
+            }
+        };
+    }
+}
+
+struct CorrelationCalculator {
+    collections: Vec<mongodb::Collection<Document>>,  // シャードごとのコレクション (symbol はいずれか 1 つにある)
+    windows: Vec<u32>,  // 相関を計算する窓の分数 (昇順)
+    window_minutes: u32,  // 読み込む窓 (最も長い窓)
+    interval_seconds: i64,
+    fill_policy: FillPolicy,
+    window: PriceWindow,  // 窓の中の価格 (縦持ち)
+    data_df: Option<DataFrame>, // Single DataFrame with all symbols
+    rolling: Vec<(u32, RollingCorrelation)>,  // --incremental の窓ごとのペアごとの統計
+    min_data_points: usize,
+    returns: Option<usize>,  // --returns の horizon (バケット数)
+    method: CorrelationMethod,
+    ewma_half_life: Option<u32>,  // --ewma-half-life の秒数
+    benchmark: Option<i32>,  // --beta の benchmark の symbol_id
+    universe: Option<Vec<i32>>,  // --symbols / --symbol-ids (None なら全ての symbol)
+    min_coverage: f64,  // 値のあるバケットの割合がこれ未満の symbol は外す
+    pca: Option<usize>,  // --pca の主成分の数
+    clusters: Option<(f64, Linkage)>,  // --cluster で切る距離と --linkage
+    alerts: Option<CorrelationAlerts>,  // --alert のルール
+    notifier: Option<Arc<Notifier>>,  // --alert の送信先 (設定されていなければ表示のみ)
+}
+
+impl CorrelationCalculator {
+    /// windows は昇順 (最も長い窓を読み込み, 短い窓はその終わりの部分から計算する)
+    fn new(
+        collections: Vec<mongodb::Collection<Document>>,
+        windows: Vec<u32>,
+        interval_seconds: i64,
+        fill_policy: FillPolicy,
+    ) -> Self {
+        Self {
+            collections,
+            window_minutes: windows.last().copied().unwrap_or(30),
+            windows,
+            interval_seconds,
+            fill_policy,
+            window: PriceWindow::new(),
+            data_df: None,
+            rolling: Vec::new(),
+            min_data_points: 0,
+            returns: None,
+            method: CorrelationMethod::Pearson,
+            ewma_half_life: None,
+            benchmark: None,
+            universe: None,
+            min_coverage: 0.0,
+            pca: None,
+            clusters: None,
+            alerts: None,
+            notifier: None,
+        }
+    }
+
+    fn with_method(mut self, method: CorrelationMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// 半減期 half_life_seconds の指数加重の相関と symbol ごとのボラティリティも出す (incremental より先に指定する)
+    fn with_ewma(mut self, half_life_seconds: u32) -> Self {
+        self.ewma_half_life = Some(half_life_seconds);
+        self
+    }
+
+    /// benchmark に対する symbol ごとの beta と決定係数も出す
+    fn with_benchmark(mut self, benchmark: i32) -> Self {
+        self.benchmark = Some(benchmark);
+        self
+    }
+
+    /// 読む symbol を限る
+    fn with_universe(mut self, universe: Vec<i32>) -> Self {
+        self.universe = Some(universe);
+        self
+    }
+
+    fn with_min_coverage(mut self, min_coverage: f64) -> Self {
+        self.min_coverage = min_coverage;
+        self
+    }
+
+    /// 相関行列の上位 top_k 個の主成分も出す
+    fn with_pca(mut self, top_k: usize) -> Self {
+        self.pca = Some(top_k);
+        self
+    }
+
+    /// 相関行列の symbol を階層クラスタリングし, 距離 distance で切ったクラスタも出す
+    fn with_clusters(mut self, distance: f64, linkage: Linkage) -> Self {
+        self.clusters = Some((distance, linkage));
+        self
+    }
+
+    /// 相関のアラートのルールを評価し, 発火したら notifier に送る
+    fn with_alerts(mut self, alerts: CorrelationAlerts, notifier: Notifier) -> Self {
+        self.alerts = Some(alerts);
+        self.notifier = (!notifier.is_empty()).then(|| Arc::new(notifier));
+        self
+    }
+
+    fn in_universe(&self, symbol_id: i32) -> bool {
+        self.universe.as_ref().is_none_or(|universe| universe.contains(&symbol_id))
+    }
+
+    /// 半減期のバケット数
+    fn ewma_half_life_buckets(&self) -> Option<f64> {
+        self.ewma_half_life.map(|seconds| seconds as f64 / self.interval_seconds.max(1) as f64)
+    }
+
+    /// 価格の代わりに horizon バケットの対数収益率の相関を取る (incremental より先に指定する)
+    fn with_returns(mut self, horizon: usize) -> Self {
+        self.returns = Some(horizon);
+        self
+    }
+
+    /// 窓全体の DataFrame を作らず, ペアごとの統計を確定したバケットごとに更新する (窓ごとに同じ足を入れる)
+    fn incremental(mut self, min_data_points: usize) -> Result<Self> {
+        self.rolling = self.new_rolling()?;
+        self.min_data_points = min_data_points;
+        Ok(self)
+    }
+
+    /// 窓ごとの統計 (EWMA は窓によらないので最も長い窓だけで更新する)
+    fn new_rolling(&self) -> Result<Vec<(u32, RollingCorrelation)>> {
+        let mut rollings = Vec::with_capacity(self.windows.len());
+        for &window_minutes in &self.windows {
+            let mut rolling = RollingCorrelation::new(self.interval_seconds, Duration::minutes(window_minutes as i64), self.fill_policy)?;
+            if let Some(horizon) = self.returns {
+                rolling = rolling.with_returns(horizon);
+            }
+            if let Some(half_life_buckets) = self.ewma_half_life_buckets().filter(|_| window_minutes == self.window_minutes) {
+                rolling = rolling.with_ewma(half_life_buckets);
+            }
+            rollings.push((window_minutes, rolling));
+        }
+        Ok(rollings)
+    }
+
+    fn rolling_window(&self, window_minutes: u32) -> Option<&RollingCorrelation> {
+        self.rolling.iter().find(|(minutes, _)| *minutes == window_minutes).map(|(_, rolling)| rolling)
+    }
+
+    /// --incremental で窓を読み込み済みか (2 回目からは新しい足だけを読む)
+    fn is_loaded(&self) -> bool {
+        self.rolling.iter().any(|(_, rolling)| rolling.resume_ms().is_some())
+    }
+
+    /// 確定させるバケットの終端 (書き込みの遅れを待つ)
+    fn settled_until(&self) -> i64 {
+        Utc::now().timestamp_millis() - (self.interval_seconds * 1000).max(SETTLE_MS)
+    }
+
+    /// since_ms 以降の足の価格を全てのシャードから読む
+    async fn query(&self, since_ms: i64) -> Result<Vec<PricePoint>> {
+        // Query for all data in the window (using DateTime object)
+        let mut filter = doc! {
+            "unixtime": { "$gte": mongodb::bson::DateTime::from_millis(since_ms) }
+        };
+        if let Some(universe) = self.universe.as_ref() {
+            filter.insert("metadata.symbol", doc! { "$in": universe.clone() });
+        }
+        
+        let mut points = Vec::new();
+        
+        // シャードごとのコレクションを順に読む (symbol はいずれか 1 つのシャードにある)
+        for collection in &self.collections {
+            let query_start = Instant::now();
+            let mut cursor = collection.find(filter.clone()).await?;
+            let query_elapsed = query_start.elapsed();
+            debug!("MongoDB query execution ({}): {:?}", collection.namespace(), query_elapsed);
+            
+            // Collect data by symbol (ask/bid がどちらもない足は飛ばす)
+            while cursor.advance().await? {
+                let doc: Document = cursor.current().try_into()?;
+                points.extend(price_point(&doc));
+            }
+        }
+        Ok(points)
+    }
+
+    async fn load_initial_data(&mut self) -> Result<()> {
+        let timer_start = Instant::now();
+        let now = Utc::now();
+        let start_time = now - Duration::minutes(self.window_minutes as i64);
+        let start_time_ms = start_time.timestamp_millis();
+        
+        debug!("Loading data from {} ({}ms)", start_time.format("%Y-%m-%d %H:%M:%S"), start_time_ms);
+        
+        let points = self.query(start_time_ms).await?;
+        
+        let symbols: HashSet<i32> = points.iter().map(|point| point.symbol_id).collect();
+        info!("Loaded {} documents for {} symbols", points.len(), symbols.len());
+        debug!("Symbols loaded: {:?}", symbols);
+        if symbols.is_empty() {
+            warn!("No data found in the last {} minutes", self.window_minutes);
+        }
+        
+        if !self.rolling.is_empty() {
+            // 統計を作り直してから窓の足を入れる
+            self.rolling = self.new_rolling()?;
+            self.apply_changes(&points)?;
+        } else {
+            // A. MongoDBデータで窓を置き換える
+            self.window.replace(&points)?;
+            
+            // B. 時間軸を作成してjoin + forward fill
+            self.rebuild()?;
+        }
+        
+        let total_elapsed = timer_start.elapsed();
+        debug!("Total initial data load time: {:?}", total_elapsed);
+        
+        Ok(())
+    }
+
+    /// --incremental で, まだ確定していないバケット以降の足だけを読む
+    async fn load_new_data(&mut self) -> Result<()> {
+        let Some(since_ms) = self.rolling.iter().filter_map(|(_, rolling)| rolling.resume_ms()).min() else {
+            return self.load_initial_data().await;
+        };
+        let points = self.query(since_ms).await?;
+        debug!("Loaded {} new documents", points.len());
+        self.apply_changes(&points)
+    }
+
+    /// 届いた足を窓に足して計算し直す (読み直さない)
+    fn apply_changes(&mut self, points: &[PricePoint]) -> Result<()> {
+        let until_ms = self.settled_until();
+        // change stream の変更は全ての symbol の分が届く
+        let points: Vec<PricePoint> = points.iter().filter(|point| self.in_universe(point.symbol_id)).copied().collect();
+        let points = points.as_slice();
+        if !self.rolling.is_empty() {
+            for (window_minutes, rolling) in self.rolling.iter_mut() {
+                for point in points {
+                    rolling.insert(point);
+                }
+                let pushed = rolling.advance(until_ms);
+                debug!("Advanced {} buckets ({} buckets, {} symbols in {}m window)", pushed, rolling.len(), rolling.symbols().len(), window_minutes);
+            }
+            return Ok(());
+        }
+        self.window.append(points)?;
+        self.rebuild()
+    }
+
+    /// 窓より古い行を落とし, 今までの時間軸に揃えた DataFrame を作り直す
+    fn rebuild(&mut self) -> Result<()> {
+        let end_time = Utc::now();
+        let start_time = end_time - Duration::minutes(self.window_minutes as i64);
+        self.window.trim(start_time.timestamp_millis())?;
+        let df = self.create_filled_dataframe_with_timeaxis(self.window.frame(), start_time, end_time, self.interval_seconds)?;
+        self.data_df = Some(match self.returns {
+            Some(horizon) => log_returns_frame(&df, horizon)?,
+            None => df,
+        });
+        
+        debug!("Created unified DataFrame with {} symbols ({} rows in window)", 
+            self.data_df.as_ref().unwrap().width() - 1, self.window.len()); // -1 for timestamp column
+        Ok(())
+    }
+
+    /// 窓ごとの相関を表示し, store があれば書き込む
+    async fn report(&mut self, stores: &Stores) {
+        let mut timestamp = None;
+        for window_minutes in self.windows.clone() {
+            if let Some((end, pairs)) = self.report_window(window_minutes, stores).await {
+                self.report_alerts(end, window_minutes, &pairs);
+                timestamp = Some(end);
+            }
+        }
+        if let Some(timestamp) = timestamp {
+            self.report_ewma(timestamp, stores.correlations.as_ref()).await;
+        }
+    }
+
+    /// 窓が複数なら見出しに付ける窓の長さ
+    fn window_label(&self, window_minutes: u32) -> String {
+        if self.windows.len() > 1 {
+            format!(" ({}m window)", window_minutes)
+        } else {
+            String::new()
+        }
+    }
+
+    /// 1 つの窓の相関と beta を表示し, store があれば書き込む (窓の終わりの時刻と相関を返す)
+    async fn report_window(&self, window_minutes: u32, stores: &Stores) -> Option<(DateTime<Utc>, Vec<PairCorrelation>)> {
+        let (timestamp, pairs) = match self.correlations(window_minutes) {
+            Ok(Some(result)) => result,
+            Ok(None) => return None,
+            Err(e) => {
+                error!("Error calculating correlations: {}", e);
+                return None;
+            }
+        };
+        let dropped = self.low_coverage(window_minutes);
+        let mut symbols: Vec<i32> = pairs.iter().flat_map(|pair| [pair.symbol_a, pair.symbol_b]).collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+        info!(window_minutes, symbols = ?symbols, "[CORRELATION] Correlation matrix{} of {:?}", self.window_label(window_minutes), symbols);
+        if !dropped.is_empty() {
+            info!(window_minutes, dropped = ?dropped, "[CORRELATION] Dropped symbols below {}% coverage: {:?}", self.min_coverage * 100.0, dropped);
+        }
+        for pair in &pairs {
+            let (symbol_a, symbol_b, points) = (pair.symbol_a, pair.symbol_b, pair.count);
+            match pair.correlation {
+                Some(correlation) => info!(window_minutes, symbol_a, symbol_b, correlation, points, "[CORRELATION] {} / {}: {:.4}", symbol_a, symbol_b, correlation),
+                None => info!(window_minutes, symbol_a, symbol_b, points, "[CORRELATION] Failed to calculate correlation for {} and {} ({} points)", symbol_a, symbol_b, points),
+            }
+        }
+        let matrix = CorrelationMatrix::from_pairs(timestamp, window_minutes, &pairs);
+        if let Some(output) = stores.matrices.as_ref() {
+            if let Err(e) = output.write(&matrix) {
+                error!("Failed to write correlation matrix: {}", e);
+            }
+        }
+        self.report_pca(&matrix);
+        self.report_clusters(&matrix);
+        if let Some(store) = stores.correlations.as_ref() {
+            match store.write(timestamp, window_minutes, &pairs).await {
+                Ok(written) => info!("Stored {} correlations at {}", written, timestamp.format("%Y-%m-%d %H:%M:%S")),
+                Err(e) => error!("Failed to store correlations: {}", e),
+            }
+        }
+        self.report_betas(timestamp, window_minutes, stores.betas.as_ref()).await;
+        Some((timestamp, pairs))
+    }
+
+    /// --alert のルールで相関を評価し, 発火したアラートを表示して通知する
+    fn report_alerts(&mut self, timestamp: DateTime<Utc>, window_minutes: u32, pairs: &[PairCorrelation]) {
+        let Some(alerts) = self.alerts.as_mut() else {
+            return;
+        };
+        for alert in alerts.evaluate(timestamp, window_minutes, pairs) {
+            let label = if alert.resolved { "RESOLVED" } else { "ALERT" };
+            warn!("[CORRELATION-{}] {}", label, alert);
+            if let Some(notifier) = self.notifier.clone() {
+                // 通知の遅延で計算を止めない
+                let text = format!("[CORRELATION-{}] {}", label, alert);
+                tokio::spawn(async move {
+                    if let Err(e) = notifier.send(&text).await {
+                        error!("{}", e);
+                    }
+                });
+            }
+        }
+    }
+
+    /// --pca の主成分の説明する分散と loadings を表示する
+    fn report_pca(&self, matrix: &CorrelationMatrix) {
+        let Some(top_k) = self.pca else {
+            return;
+        };
+        let Some(pca) = principal_components(matrix, top_k) else {
+            return;
+        };
+        let window_minutes = matrix.window_minutes;
+        if !pca.dropped.is_empty() {
+            info!(window_minutes, dropped = ?pca.dropped, "[CORRELATION-PCA] Excluded symbols with missing correlations: {:?}", pca.dropped);
+        }
+        let mut cumulative = 0.0;
+        for (index, component) in pca.components.iter().enumerate() {
+            cumulative += component.explained;
+            let loadings: Vec<String> = component.loadings.iter().map(|(symbol_id, loading)| format!("{}: {:.3}", symbol_id, loading)).collect();
+            info!(
+                window_minutes, component = index + 1, eigenvalue = component.eigenvalue, explained = component.explained, cumulative,
+                "[CORRELATION-PCA] PC{}{}: eigenvalue {:.4}, explained {:.1}% (cumulative {:.1}%), loadings {{{}}}",
+                index + 1, self.window_label(window_minutes), component.eigenvalue, component.explained * 100.0, cumulative * 100.0, loadings.join(", ")
+            );
+        }
+    }
+
+    /// --cluster のクラスタと樹形図の並びを表示する
+    fn report_clusters(&self, matrix: &CorrelationMatrix) {
+        let Some((distance, linkage)) = self.clusters else {
+            return;
+        };
+        let Some(dendrogram) = cluster(matrix, linkage) else {
+            return;
+        };
+        let (window_minutes, order) = (matrix.window_minutes, dendrogram.order());
+        info!(
+            window_minutes, distance, linkage = linkage.as_str(), order = ?order,
+            "[CORRELATION-CLUSTER] Dendrogram order{} ({} linkage): {:?}", self.window_label(window_minutes), linkage.as_str(), order
+        );
+        for (index, members) in dendrogram.cut(distance).iter().enumerate() {
+            info!(window_minutes, cluster = index + 1, members = ?members, "[CORRELATION-CLUSTER] Cluster {} (distance <= {}): {:?}", index + 1, distance, members);
+        }
+    }
+
+    /// --ewma-half-life の相関とボラティリティを表示し, store があれば書き込む (窓によらないので 1 回だけ)
+    async fn report_ewma(&self, timestamp: DateTime<Utc>, store: Option<&CorrelationStore>) {
+        let Some(half_life) = self.ewma_half_life else {
+            return;
+        };
+        let (pairs, volatilities) = match self.ewma() {
+            Ok(Some(result)) => result,
+            Ok(None) => return,
+            Err(e) => {
+                error!("Error calculating EWMA correlations: {}", e);
+                return;
+            }
+        };
+        let half_life_label = timeframe::label(half_life);
+        for pair in &pairs {
+            let (symbol_a, symbol_b, points) = (pair.symbol_a, pair.symbol_b, pair.count);
+            match pair.correlation {
+                Some(correlation) => info!(half_life, symbol_a, symbol_b, correlation, points, "[CORRELATION-EWMA] {} / {} (half-life {}): {:.4}", symbol_a, symbol_b, half_life_label, correlation),
+                None => info!(half_life, symbol_a, symbol_b, points, "[CORRELATION-EWMA] Failed to calculate EWMA correlation for {} and {} ({} points)", symbol_a, symbol_b, points),
+            }
+        }
+        for volatility in &volatilities {
+            let (symbol_id, points) = (volatility.symbol_id, volatility.count);
+            match volatility.volatility {
+                Some(value) => info!(half_life, symbol_id, volatility = value, points, "[CORRELATION-EWMA] Volatility of {}: {:.6} per {}s bucket", symbol_id, value, self.interval_seconds),
+                None => info!(half_life, symbol_id, points, "[CORRELATION-EWMA] Failed to calculate EWMA volatility for {} ({} points)", symbol_id, points),
+            }
+        }
+        if let Some(store) = store {
+            match store.write_ewma(timestamp, self.window_minutes, half_life, &pairs).await {
+                Ok(written) => info!("Stored {} EWMA correlations", written),
+                Err(e) => error!("Failed to store EWMA correlations: {}", e),
+            }
+        }
+    }
+
+    /// --beta の benchmark に対する symbol ごとの beta を表示し, store があれば書き込む
+    async fn report_betas(&self, timestamp: DateTime<Utc>, window_minutes: u32, store: Option<&BetaStore>) {
+        let Some(benchmark) = self.benchmark else {
+            return;
+        };
+        let betas = match self.betas(benchmark, window_minutes) {
+            Ok(betas) => betas,
+            Err(e) => {
+                error!("Error calculating betas: {}", e);
+                return;
+            }
+        };
+        for beta in &betas {
+            let (symbol_id, points) = (beta.symbol_id, beta.count);
+            match (beta.beta, beta.r_squared) {
+                (Some(value), Some(r_squared)) => info!(window_minutes, benchmark, symbol_id, beta = value, r_squared, points, "[CORRELATION-BETA] Beta of {} vs {}: {:.4} (R² {:.4}, {} points)", symbol_id, benchmark, value, r_squared, points),
+                (Some(value), None) => info!(window_minutes, benchmark, symbol_id, beta = value, points, "[CORRELATION-BETA] Beta of {} vs {}: {:.4} ({} points)", symbol_id, benchmark, value, points),
+                (None, _) => info!(window_minutes, benchmark, symbol_id, points, "[CORRELATION-BETA] Failed to calculate beta of {} vs {} ({} points)", symbol_id, benchmark, points),
+            }
+        }
+        if let Some(store) = store {
+            match store.write(timestamp, window_minutes, &betas).await {
+                Ok(written) => info!("Stored {} betas", written),
+                Err(e) => error!("Failed to store betas: {}", e),
+            }
+        }
+    }
+
+    /// benchmark に対する symbol ごとの beta (--incremental では窓に入れたバケットの統計から)
+    fn betas(&self, benchmark: i32, window_minutes: u32) -> Result<Vec<SymbolBeta>> {
+        if let Some(rolling) = self.rolling_window(window_minutes) {
+            // benchmark と両方の値があるバケットが min_data_points 未満の symbol は計算しない
+            let dropped = self.low_coverage(window_minutes);
+            if dropped.contains(&benchmark) {
+                return Ok(Vec::new());
+            }
+            return Ok(rolling
+                .betas(benchmark)
+                .into_iter()
+                .filter(|beta| !dropped.contains(&beta.symbol_id))
+                .map(|beta| {
+                    let enough = beta.count >= self.min_data_points;
+                    SymbolBeta { beta: beta.beta.filter(|_| enough), r_squared: beta.r_squared.filter(|_| enough), ..beta }
+                })
+                .collect());
+        }
+        match self.window_frame(window_minutes) {
+            Some(df) => calculate_betas(&df, benchmark),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// --ewma-half-life の指数加重の相関と symbol ごとのボラティリティ
+    /// --incremental では窓に入れたバケットごとに更新したもの, それ以外は時間軸に揃えた (最も長い) 窓の先頭から計算し直したもの
+    fn ewma(&self) -> Result<Option<(Vec<PairCorrelation>, Vec<SymbolVolatility>)>> {
+        if let Some(rolling) = self.rolling_window(self.window_minutes) {
+            let Some(ewma) = rolling.ewma() else {
+                return Ok(None);
+            };
+            let dropped = self.low_coverage(self.window_minutes);
+            let pairs = ewma
+                .correlations()
+                .into_iter()
+                .filter(|pair| !dropped.contains(&pair.symbol_a) && !dropped.contains(&pair.symbol_b))
+                .map(|pair| PairCorrelation { correlation: pair.correlation.filter(|_| pair.count >= self.min_data_points), ..pair })
+                .collect();
+            let volatilities = ewma.volatilities().into_iter().filter(|volatility| !dropped.contains(&volatility.symbol_id)).collect();
+            return Ok(Some((pairs, volatilities)));
+        }
+        match (self.window_frame(self.window_minutes), self.ewma_half_life_buckets()) {
+            (Some(df), Some(half_life_buckets)) => {
+                let ewma = calculate_ewma(&df, half_life_buckets, self.returns.is_none())?;
+                Ok(Some((ewma.correlations(), ewma.volatilities())))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// 読み込んだ窓の DataFrame の, 終わりから window_minutes 分の行 (時間軸は等間隔)
+    fn window_rows(&self, window_minutes: u32) -> Option<DataFrame> {
+        let df = self.data_df.as_ref()?;
+        let rows = (window_minutes as i64 * 60 / self.interval_seconds.max(1)).max(1) as usize;
+        Some(df.tail(Some(rows)))
+    }
+
+    /// window_rows から --min-coverage 未満の symbol の列を外したもの
+    fn window_frame(&self, window_minutes: u32) -> Option<DataFrame> {
+        let df = self.window_rows(window_minutes)?;
+        let dropped = self.low_coverage(window_minutes);
+        Some(df.drop_many(dropped.iter().map(|symbol_id| format!("symbol_{}", symbol_id))))
+    }
+
+    /// 窓のバケットのうち (埋めた後に) 値のある割合が --min-coverage 未満の symbol (昇順)
+    fn low_coverage(&self, window_minutes: u32) -> Vec<i32> {
+        if self.min_coverage <= 0.0 {
+            return Vec::new();
+        }
+        let coverage = match self.rolling_window(window_minutes) {
+            Some(rolling) => rolling.coverage(),
+            None => self.window_rows(window_minutes).map(|df| frame_coverage(&df)).unwrap_or_default(),
+        };
+        coverage.into_iter().filter(|(_, share)| *share < self.min_coverage).map(|(symbol_id, _)| symbol_id).collect()
+    }
+
+    /// 窓の終わりの時刻とペアごとの相関 (2 つ以上の symbol がなければ None)
+    fn correlations(&self, window_minutes: u32) -> Result<Option<(DateTime<Utc>, Vec<PairCorrelation>)>> {
+        if let Some(rolling) = self.rolling_window(window_minutes) {
+            // 両方の値があるバケットが min_data_points 未満のペアは計算しない
+            let dropped = self.low_coverage(window_minutes);
+            let pairs: Vec<PairCorrelation> = rolling
+                .correlations_by(self.method)
+                .into_iter()
+                .filter(|pair| !dropped.contains(&pair.symbol_a) && !dropped.contains(&pair.symbol_b))
+                .map(|pair| PairCorrelation { correlation: pair.correlation.filter(|_| pair.count >= self.min_data_points), ..pair })
+                .collect();
+            let timestamp = rolling.end_ms().and_then(DateTime::from_timestamp_millis);
+            return Ok(timestamp.filter(|_| !pairs.is_empty()).map(|timestamp| (timestamp, pairs)));
+        }
+        match self.window_frame(window_minutes) {
+            Some(df) if df.width() > 2 => { // timestamp + at least 2 price columns
+                let Some(timestamp) = df.column("timestamp")?.i64()?.max().and_then(DateTime::from_timestamp_millis) else {
+                    return Ok(None);
+                };
+                let pairs = match self.method {
+                    CorrelationMethod::Pearson => self.calculate_correlations(&df)?,
+                    method => calculate_rank_correlations(&df, method)?,
+                };
+                Ok(Some((timestamp, pairs)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // B. 時間軸に揃えて欠損を埋める
+    fn create_filled_dataframe_with_timeaxis(
+        &self,
+        data_df: &DataFrame,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: i64,
+    ) -> Result<DataFrame> {
+        let grid = TimeGrid::aligned(start_time, end_time, interval_seconds);
+        let result_df = resample_long(data_df, &grid, self.fill_policy)?;
+        
+        // Show null counts after fill
+        let null_info: Vec<String> = result_df
+            .get_columns()
+            .iter()
+            .filter(|column| column.name().starts_with("symbol_"))
+            .map(|column| format!("{}:{}", column.name(), column.null_count()))
+            .collect();
+        debug!("Null counts after fill ({:?}): {}", self.fill_policy, null_info.join(", "));
+        
+        Ok(result_df)
+    }
+
+    /// 時間軸に揃えた DataFrame の全てのペアの相関 (polars の pearson_corr) と両方の値がある行数
+    fn calculate_correlations(&self, df: &DataFrame) -> Result<Vec<PairCorrelation>> {
+        let symbol_columns: Vec<(String, i32)> = df.get_column_names()
+            .iter()
+            .filter_map(|name| Some((name.to_string(), name.strip_prefix("symbol_")?.parse().ok()?)))
+            .collect();
+        
+        // Generate all pair correlation expressions
+        let mut correlation_exprs = Vec::new();
+        let mut pair_names = Vec::new();
+        
+        for i in 0..symbol_columns.len() {
+            for j in i + 1..symbol_columns.len() {
+                let (col1, symbol_a) = &symbol_columns[i];
+                let (col2, symbol_b) = &symbol_columns[j];
+                let alias_name = format!("corr_{}_{}", symbol_a, symbol_b);
+                let count_name = format!("count_{}_{}", symbol_a, symbol_b);
+                
+                correlation_exprs.push(
+                    pearson_corr(col(col1), col(col2)).alias(&alias_name)
+                );
+                correlation_exprs.push(
+                    col(col1).is_not_null().and(col(col2).is_not_null()).cast(DataType::Int64).sum().alias(&count_name)
+                );
+                pair_names.push((*symbol_a, *symbol_b, alias_name, count_name));
+            }
+        }
+        if correlation_exprs.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        // Calculate all correlations in one lazy operation
+        let correlations = df.clone()
+            .lazy()
+            .select(correlation_exprs)
+            .collect()?;
+        
+        let mut pairs = Vec::with_capacity(pair_names.len());
+        for (symbol_a, symbol_b, alias_name, count_name) in pair_names {
+            pairs.push(PairCorrelation {
+                symbol_a,
+                symbol_b,
+                count: correlations.column(&count_name)?.i64()?.get(0).unwrap_or(0) as usize,
+                correlation: correlations.column(&alias_name)?.f64()?.get(0).filter(|corr| corr.is_finite()),
+            });
+        }
+        Ok(pairs)
+    }
+
+}
+
+/// 時間軸に揃えた DataFrame の全てのペアの順位相関 (両方の値がある行だけ使う)
+fn calculate_rank_correlations(df: &DataFrame, method: CorrelationMethod) -> Result<Vec<PairCorrelation>> {
+    let mut columns: Vec<(i32, Vec<Option<f64>>)> = Vec::new();
+    for column in df.get_columns() {
+        if let Some(symbol_id) = column.name().strip_prefix("symbol_").and_then(|id| id.parse().ok()) {
+            columns.push((symbol_id, column.f64()?.into_iter().collect()));
+        }
+    }
+    let mut pairs = Vec::new();
+    for (i, (symbol_a, values_a)) in columns.iter().enumerate() {
+        for (symbol_b, values_b) in &columns[i + 1..] {
+            let (x, y): (Vec<f64>, Vec<f64>) = values_a.iter().zip(values_b).filter_map(|(a, b)| Some(((*a)?, (*b)?))).unzip();
+            pairs.push(PairCorrelation { symbol_a: *symbol_a, symbol_b: *symbol_b, count: x.len(), correlation: method.correlation(&x, &y) });
+        }
+    }
+    Ok(pairs)
+}
+
+/// 時間軸に揃えた DataFrame の行を古い順に入れた指数加重の相関とボラティリティ (prices は値が価格か)
+fn calculate_ewma(df: &DataFrame, half_life_buckets: f64, prices: bool) -> Result<EwmaCorrelation> {
+    let mut columns: Vec<(i32, Vec<Option<f64>>)> = Vec::new();
+    for column in df.get_columns() {
+        if let Some(symbol_id) = column.name().strip_prefix("symbol_").and_then(|id| id.parse().ok()) {
+            columns.push((symbol_id, column.f64()?.into_iter().collect()));
+        }
+    }
+    let mut ewma = EwmaCorrelation::new(half_life_buckets, prices);
+    for i in 0..df.height() {
+        let row = columns.iter().filter_map(|(symbol_id, values)| Some((*symbol_id, values[i]?))).collect();
+        ewma.update(&row);
+    }
+    Ok(ewma)
+}
+
+/// 時間軸に揃えた DataFrame の benchmark 以外の symbol の beta と決定係数 (benchmark と両方の値がある行だけ使う)
+fn calculate_betas(df: &DataFrame, benchmark: i32) -> Result<Vec<SymbolBeta>> {
+    let Ok(column) = df.column(&format!("symbol_{}", benchmark)) else {
+        return Ok(Vec::new());
+    };
+    let benchmark_values: Vec<Option<f64>> = column.f64()?.into_iter().collect();
+    let mut betas = Vec::new();
+    for column in df.get_columns() {
+        let Some(symbol_id) = column.name().strip_prefix("symbol_").and_then(|id| id.parse::<i32>().ok()) else {
+            continue;
+        };
+        if symbol_id == benchmark {
+            continue;
+        }
+        let (x, y): (Vec<f64>, Vec<f64>) = benchmark_values.iter().zip(column.f64()?).filter_map(|(a, b)| Some(((*a)?, b?))).unzip();
+        let (beta, r_squared) = regress(&x, &y);
+        betas.push(SymbolBeta { symbol_id, benchmark_id: benchmark, count: x.len(), beta, r_squared });
+    }
+    Ok(betas)
+}
+
+/// "5,30,240" を昇順の窓の分数にする
+fn parse_windows(spec: &str) -> Result<Vec<u32>> {
+    let mut windows = Vec::new();
+    for window in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match window.parse::<u32>() {
+            Ok(minutes) if minutes > 0 => windows.push(minutes),
+            _ => return Err(anyhow::anyhow!("Invalid window: {} (use minutes, e.g. 5,30,240)", window)),
+        }
+    }
+    if windows.is_empty() {
+        return Err(anyhow::anyhow!("Empty window list"));
+    }
+    windows.sort_unstable();
+    windows.dedup();
+    Ok(windows)
+}
+
+/// 時間軸に揃えた DataFrame の symbol ごとの値のある行の割合
+fn frame_coverage(df: &DataFrame) -> BTreeMap<i32, f64> {
+    let rows = df.height().max(1) as f64;
+    df.get_columns()
+        .iter()
+        .filter_map(|column| {
+            let symbol_id = column.name().strip_prefix("symbol_")?.parse().ok()?;
+            Some((symbol_id, 1.0 - column.null_count() as f64 / rows))
+        })
+        .collect()
+}
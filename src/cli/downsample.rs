@@ -0,0 +1,156 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::{self, DatabaseArgs, LogArgs};
+use crate::{
+    db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace},
+    models::{market_event::MarketEvent, market_type::MarketType, trade_candle::TradeCandle},
+    utils::{candle_fields::CandleFieldSelection, downsample, event_writer::EventWriter, mongo_sink::MongoCandleSink, symbol_manager::SYMBOL_MANAGER, timeframe},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+#[command(name = "downsample")]
+#[command(about = "Derive higher timeframe candles from stored 1s / 5s candles and store them in their collections", long_about = None)]
+pub struct Args {
+    #[command(flatten)]
+    database: DatabaseArgs,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Symbols as stored in master.csv (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    /// Source candle timeframe in seconds (e.g., 1 for candles_1s, 5 for candles_5s)
+    #[arg(long, default_value = "1")]
+    source: u32,
+
+    /// Timeframes to derive (comma-separated seconds or Ns/Nm/Nh/Nd; multiples of --source)
+    #[arg(short = 't', long, default_value = "1m,1h,1d")]
+    timeframes: String,
+
+    /// Candle boundary time zone, as given to the collectors with --session
+    #[arg(long, default_value = "utc")]
+    session: String,
+
+    /// Start date in UTC (YYYY-MM-DD, inclusive, default: yesterday)
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// End date in UTC (YYYY-MM-DD, exclusive, default: start + 1 day)
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Candle fields to persist per timeframe in seconds (e.g., 60=*;86400=ask_price,bid_price)
+    #[arg(long)]
+    candle_fields: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("downsample")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args.database.url().ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?;
+    let market_type = MarketType::parse(&args.market_type)?;
+    let symbols = common::parse_symbols(&args.symbols);
+    let mut symbol_ids = Vec::new();
+    for symbol in &symbols {
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&args.exchange, symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", args.exchange, symbol, market_type))?;
+        symbol_ids.push((symbol.clone(), symbol_id));
+    }
+    let source_collection = candle_collection_name(args.source as i32)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported source timeframe: {} seconds", args.source))?;
+    let timeframes = timeframe::parse_list(&args.timeframes)?;
+    for &period in &timeframes {
+        if period <= args.source || !period.is_multiple_of(args.source) {
+            return Err(anyhow::anyhow!("Timeframe {}s must be a larger multiple of the source timeframe {}s", period, args.source));
+        }
+        candle_collection_name(period as i32).ok_or_else(|| anyhow::anyhow!("Unsupported timeframe: {} seconds", period))?;
+    }
+    let session_offset = timeframe::parse_session_offset(&args.session)?;
+    let candle_fields = match args.candle_fields.as_deref() {
+        Some(spec) => CandleFieldSelection::parse(spec)?,
+        None => CandleFieldSelection::default(),
+    };
+
+    let start = args.start.unwrap_or_else(|| (Utc::now() - Duration::days(1)).date_naive());
+    let end = args.end.unwrap_or(start + Duration::days(1));
+    let start_time = start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end_time = end.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    info!("Downsampling {} {} {:?} from {} to {}: {}s -> {:?}", args.exchange, market_type, symbols, start, end, args.source, timeframes);
+
+    // symbol を書き込んだシャードから読む
+    let databases = connect_federated(&database_url, &shard_urls(args.database.shard_urls.as_deref())).await?;
+    let namespace = args.database.namespace(None);
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let collection_name = namespaced_collection(namespace.as_deref(), &source_collection);
+
+    // Start database writer (same collections as the collectors)
+    let db = Arc::new(args.database.open(candle_fields, None).await?);
+    let sinks = MongoCandleSink::fanout(&db, &args.exchange);
+    let (output_tx, output_rx) = mpsc::channel::<MarketEvent>(1000);
+    let (sinks_tx, sinks_rx) = mpsc::channel::<MarketEvent>(1000);
+    tokio::spawn(sinks.clone().run(output_rx, sinks_tx));
+    let writer = tokio::spawn(EventWriter::new(db, &args.exchange).run(sinks_rx));
+
+    for (symbol, symbol_id) in &symbol_ids {
+        let collection = databases[shard_index(symbol, databases.len())].collection::<Document>(&collection_name);
+        for &period in &timeframes {
+            // 1 日分ほどずつ読む (時間枠の境界に揃え, 足が 2 回に分かれないようにする)
+            let chunk = Duration::seconds(86_400_i64.div_ceil(period as i64) * period as i64);
+            let mut chunk_start = timeframe::bucket_end(&(start_time - Duration::seconds(1)), period, session_offset);
+            let mut written = 0;
+            while chunk_start < end_time {
+                let chunk_end = (chunk_start + chunk).min(end_time);
+                // 足の時刻は終端なので, 区間 [chunk_start, chunk_end) の足は (chunk_start, chunk_end]
+                let filter = doc! {
+                    "metadata.symbol": *symbol_id,
+                    "unixtime": {
+                        "$gt": mongodb::bson::DateTime::from_millis(chunk_start.timestamp_millis()),
+                        "$lte": mongodb::bson::DateTime::from_millis(chunk_end.timestamp_millis()),
+                    },
+                };
+                let mut cursor = collection.find(filter).sort(doc! { "unixtime": 1 }).await?;
+                let mut candles = Vec::new();
+                while let Some(doc) = cursor.try_next().await? {
+                    candles.push(TradeCandle::from_timeseries_document(
+                        &doc, args.exchange.clone(), market_type.clone(), symbol.clone(), args.source as i32,
+                    )?);
+                }
+                for candle in downsample::downsample(&candles, period as i32, session_offset, chunk_start, chunk_end) {
+                    output_tx.send(MarketEvent::Candle(candle)).await?;
+                    written += 1;
+                }
+                chunk_start = chunk_end;
+            }
+            info!("Downsampled {} {}s candles of {} from {}s candles", written, period, symbol, args.source);
+        }
+    }
+
+    // 書き込みが終わるまで待つ
+    drop(output_tx);
+    writer.await?;
+    sinks.finish().await;
+    Ok(())
+}
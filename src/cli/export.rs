@@ -0,0 +1,180 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::LogArgs;
+use futures::TryStreamExt;
+use crate::db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace};
+use crate::models::{market_type::MarketType, trade_candle::TradeCandle};
+use crate::utils::candle_frame::{self, FrameFormat};
+use crate::utils::downsample;
+use crate::utils::ohlcv::{write_csv, OhlcvBar, OhlcvFormat};
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use mongodb::bson::{doc, Document};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+#[derive(Parser, Debug)]
+#[command(name = "export")]
+#[command(about = "Export stored candles as standard OHLCV CSV (ccxt / TradingView / backtrader) or as Parquet / CSV tables", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; must match the collectors)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Symbol as stored in master.csv (e.g., BTCUSDT; comma-separated for parquet / csv)
+    #[arg(short, long)]
+    symbol: String,
+
+    /// Output bar size in seconds
+    #[arg(short, long, default_value = "60")]
+    timeframe: i64,
+
+    /// Source candle timeframe in seconds (default: same as --timeframe; finer sources give closer OHLC)
+    #[arg(long)]
+    source: Option<i32>,
+
+    /// Start date in UTC (YYYY-MM-DD, inclusive, default: yesterday)
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// End date in UTC (YYYY-MM-DD, exclusive, default: start + 1 day)
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Output format: ccxt (ms timestamp), tradingview (unix seconds) or backtrader (datetime + openinterest) OHLCV CSV, or parquet / csv with all candle columns
+    #[arg(short, long, default_value = "ccxt")]
+    format: String,
+
+    /// Columns to write with parquet / csv (comma-separated, default: all; timestamp is the candle end)
+    #[arg(long)]
+    columns: Option<String>,
+
+    /// Output file (default: stdout)
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing (stdout は CSV / Parquet の出力先になるので stderr に出す)
+    args.logs.init_stderr("export")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .expect("MONGODB_URL must be set");
+
+    // parquet / csv は全ての列の表, それ以外は OHLCV の CSV
+    let frame_format = FrameFormat::parse(&args.format);
+    let format = match frame_format {
+        Some(_) => None,
+        None => Some(OhlcvFormat::parse(&args.format)?),
+    };
+    let columns = candle_frame::parse_columns(args.columns.as_deref())?;
+    let market_type = MarketType::parse(&args.market_type)?;
+    if args.timeframe <= 0 {
+        return Err(anyhow::anyhow!("Timeframe must be positive: {}", args.timeframe));
+    }
+    let source = args.source.unwrap_or(args.timeframe as i32);
+    if source as i64 > args.timeframe || args.timeframe % source as i64 != 0 {
+        return Err(anyhow::anyhow!("Timeframe {}s must be a multiple of the source timeframe {}s", args.timeframe, source));
+    }
+    let collection_name = candle_collection_name(source)
+        .ok_or_else(|| anyhow::anyhow!("Unsupported source timeframe: {} seconds", source))?;
+    let symbols: Vec<&str> = args.symbol.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+    if format.is_some() && symbols.len() != 1 {
+        return Err(anyhow::anyhow!("OHLCV formats take one symbol; use -f parquet or -f csv for several"));
+    }
+    let mut symbol_ids = Vec::new();
+    for symbol in &symbols {
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&args.exchange, symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", args.exchange, symbol, market_type))?;
+        symbol_ids.push((*symbol, symbol_id));
+    }
+
+    let start = args.start.unwrap_or_else(|| (Utc::now() - Duration::days(1)).date_naive());
+    let end = args.end.unwrap_or(start + Duration::days(1));
+    let start_time = start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end_time = end.and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+    // symbol を書き込んだシャードから読む
+    let databases = connect_federated(&database_url, &shard_urls(args.shard_urls.as_deref())).await?;
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let collection_name = namespaced_collection(namespace.as_deref(), &collection_name);
+
+    let mut candles = Vec::new();
+    for (symbol, symbol_id) in &symbol_ids {
+        let collection = databases[shard_index(symbol, databases.len())].collection::<Document>(&collection_name);
+        // 足の時刻は終端なので, 区間 [start, end) に始端が入る足は (start, end]
+        let filter = doc! {
+            "metadata.symbol": *symbol_id,
+            "unixtime": {
+                "$gt": mongodb::bson::DateTime::from_millis(start_time.timestamp_millis()),
+                "$lte": mongodb::bson::DateTime::from_millis(end_time.timestamp_millis()),
+            },
+        };
+        let mut cursor = collection.find(filter).sort(doc! { "unixtime": 1 }).await?;
+        let mut series = Vec::new();
+        while let Some(doc) = cursor.try_next().await? {
+            series.push(TradeCandle::from_timeseries_document(
+                &doc, args.exchange.clone(), market_type.clone(), symbol.to_string(), source,
+            )?);
+        }
+        match format {
+            Some(_) => candles.extend(series),
+            None if source as i64 == args.timeframe => {
+                // 同じ時刻の足は最新の revision を使う
+                let mut latest: BTreeMap<_, TradeCandle> = BTreeMap::new();
+                for candle in series {
+                    if latest.get(&candle.timestamp).is_none_or(|kept| kept.revision < candle.revision) {
+                        latest.insert(candle.timestamp, candle);
+                    }
+                }
+                candles.extend(latest.into_values());
+            }
+            None => candles.extend(downsample::downsample(&series, args.timeframe as i32, 0, start_time, end_time)),
+        }
+    }
+
+    let mut writer: Box<dyn Write> = match args.output.as_deref() {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    if let Some(format) = format {
+        let bars = OhlcvBar::from_candles(&candles, args.timeframe);
+        write_csv(&mut writer, &bars, format)?;
+        tracing::info!("Exported {} bars from {} candles ({} {}s, {} - {})", bars.len(), candles.len(), args.symbol, args.timeframe, start, end);
+    } else if let Some(frame_format) = frame_format {
+        let mut df = candle_frame::to_dataframe(&candles, &columns)?;
+        candle_frame::write_frame(&mut writer, &mut df, frame_format)?;
+        tracing::info!("Exported {} candles ({} {}s, {} - {})", candles.len(), args.symbol, args.timeframe, start, end);
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::LogArgs;
+use crate::db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace};
+use crate::models::{market_type::MarketType, trade_candle::TradeCandle};
+use crate::utils::{integrity::{self, GapScanner, IntegrityIssue, IntegritySummary}, symbol_manager::SYMBOL_MANAGER, timeframe};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use std::io::Write;
+
+#[derive(Parser, Debug)]
+#[command(name = "gaps")]
+#[command(about = "Scan stored candles for missing buckets, duplicate timestamps and impossible values", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; must match the collectors)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Symbols as stored in master.csv (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    /// Timeframes to scan (comma-separated seconds or Ns/Nm/Nh/Nd)
+    #[arg(short = 't', long, default_value = "1m")]
+    timeframes: String,
+
+    /// Candle boundary time zone, as given to the collectors with --session
+    #[arg(long, default_value = "utc")]
+    session: String,
+
+    /// Start date in UTC (YYYY-MM-DD, inclusive, default: yesterday)
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// End date in UTC (YYYY-MM-DD, exclusive, default: start + 1 day)
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Report runs of missing candles spanning at least this many seconds (quiet markets have no candles for seconds without trades)
+    #[arg(long, default_value = "300")]
+    min_gap_secs: i64,
+
+    /// Report format: json (summary per series and issues) or csv (issues)
+    #[arg(short, long, default_value = "json")]
+    format: String,
+
+    /// Output file (default: stdout)
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Write `backfill` commands for the days with missing candles to this shell script (binance / bybit)
+    #[arg(long)]
+    backfill_script: Option<String>,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+#[derive(serde::Serialize)]
+struct Report<'a> {
+    start: NaiveDate,
+    end: NaiveDate,
+    series: &'a [IntegritySummary],
+    issues: &'a [IntegrityIssue],
+}
+
+fn write_csv(writer: &mut impl Write, issues: &[IntegrityIssue]) -> std::io::Result<()> {
+    writeln!(writer, "exchange,market_type,symbol,timeframe,kind,from,to,count,detail")?;
+    for issue in issues {
+        writeln!(writer, "{},{},{},{},{},{},{},{},\"{}\"",
+            issue.exchange, issue.market_type, issue.symbol, issue.timeframe, issue.kind.as_str(),
+            issue.from.to_rfc3339(), issue.to.to_rfc3339(), issue.count, issue.detail.replace('"', "\"\""))?;
+    }
+    Ok(())
+}
+
+fn write_backfill_script(writer: &mut impl Write, issues: &[IntegrityIssue], session: &str) -> std::io::Result<()> {
+    writeln!(writer, "#!/bin/sh")?;
+    writeln!(writer, "# Re-backfill the days with missing candles (generated by gaps)")?;
+    for (issue, start, end) in integrity::backfill_days(issues) {
+        let command = format!("kkcrypto backfill -e {} --{} -s {} -t {} --session {} --start {} --end {} --update",
+            issue.exchange, issue.market_type, issue.symbol, issue.timeframe, session, start, end);
+        if matches!(issue.exchange.as_str(), "binance" | "bybit") {
+            writeln!(writer, "{}", command)?;
+        } else {
+            writeln!(writer, "# no trade history source for {}: {}", issue.exchange, command)?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing (stdout はレポートの出力先になるので stderr に出す)
+    args.logs.init_stderr("gaps")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?;
+    if !matches!(args.format.as_str(), "json" | "csv") {
+        return Err(anyhow::anyhow!("Invalid format: {}. Use json or csv", args.format));
+    }
+    let market_type = MarketType::parse(&args.market_type)?;
+    let timeframes = timeframe::parse_list(&args.timeframes)?;
+    let session_offset = timeframe::parse_session_offset(&args.session)?;
+    let start = args.start.unwrap_or_else(|| (Utc::now() - Duration::days(1)).date_naive());
+    let end = args.end.unwrap_or(start + Duration::days(1));
+    let start_time = start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end_time = end.and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+    let databases = connect_federated(&database_url, &shard_urls(args.shard_urls.as_deref())).await?;
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+
+    let mut series = Vec::new();
+    let mut issues = Vec::new();
+    for symbol in args.symbols.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&args.exchange, symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", args.exchange, symbol, market_type))?;
+        // symbol を書き込んだシャードから読む
+        let database = &databases[shard_index(symbol, databases.len())];
+        for &period in &timeframes {
+            let collection_name = candle_collection_name(period as i32)
+                .ok_or_else(|| anyhow::anyhow!("Unsupported timeframe: {} seconds", period))?;
+            let collection = database.collection::<Document>(&namespaced_collection(namespace.as_deref(), &collection_name));
+            let filter = doc! {
+                "metadata.symbol": symbol_id,
+                "unixtime": {
+                    "$gt": mongodb::bson::DateTime::from_millis(start_time.timestamp_millis()),
+                    "$lte": mongodb::bson::DateTime::from_millis(end_time.timestamp_millis()),
+                },
+            };
+            let mut scanner = GapScanner::new(&args.exchange, market_type.as_str(), symbol, period, session_offset, start_time, Duration::seconds(args.min_gap_secs));
+            let mut cursor = collection.find(filter).sort(doc! { "unixtime": 1 }).await?;
+            while let Some(doc) = cursor.try_next().await? {
+                scanner.push(&TradeCandle::from_timeseries_document(
+                    &doc, args.exchange.clone(), market_type.clone(), symbol.to_string(), period as i32,
+                )?);
+            }
+            let (summary, found) = scanner.finish(end_time);
+            tracing::info!("{} {}s: {} candles, {} missing, {} duplicates, {} invalid",
+                symbol, period, summary.candles, summary.missing, summary.duplicates, summary.invalid);
+            series.push(summary);
+            issues.extend(found);
+        }
+    }
+
+    let mut writer: Box<dyn Write> = match args.output.as_deref() {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    match args.format.as_str() {
+        "csv" => write_csv(&mut writer, &issues)?,
+        _ => {
+            serde_json::to_writer_pretty(&mut writer, &Report { start, end, series: &series, issues: &issues })?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()?;
+    if let Some(path) = args.backfill_script.as_deref() {
+        let mut script = std::io::BufWriter::new(std::fs::File::create(path)?);
+        write_backfill_script(&mut script, &issues, &args.session)?;
+        script.flush()?;
+        tracing::info!("Wrote backfill commands to {}", path);
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::{ClientOptions, CollectorArgs};
+use crate::{
+    exchanges::hyperliquid::{HyperliquidBookChannel, HyperliquidClient},
+    models::market_type::MarketType,
+};
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(name = "hyperliquid")]
+#[command(about = "Collect real-time cryptocurrency trade data from Hyperliquid", long_about = None)]
+pub struct Args {
+    /// Symbols to subscribe (comma-separated, e.g., BTC,ETH)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    #[command(flatten)]
+    collector: CollectorArgs,
+
+    /// Also subscribe to l2Book (top 20 levels) or bbo and store quotes
+    #[arg(long)]
+    book: Option<String>,
+
+    /// Number of book levels used for the quote imbalance (l2Book only)
+    #[arg(long, default_value = "5", value_parser = clap::value_parser!(u32).range(1..=20))]
+    imbalance_levels: u32,
+
+    /// Quote sampling interval in milliseconds (latest quote per symbol is stored)
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(100..))]
+    quote_interval_ms: u64,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let collector = args.collector.start("Hyperliquid", &[MarketType::Spot, MarketType::Linear])?;
+    let book = args.book.as_deref().map(HyperliquidBookChannel::parse).transpose()?;
+    let collector = collector
+        .without_gateway_time()
+        .with_quote_interval(Duration::from_millis(args.quote_interval_ms))
+        .with_price_decimals(4);
+
+    // Start Hyperliquid client
+    collector.run(&args.symbols, |options: &ClientOptions| {
+        let mut client = HyperliquidClient::new(options.events.clone(), options.raw_freq).with_endpoint(options.endpoint.clone());
+        if let Some(book) = book {
+            client = client.with_quotes(book, args.imbalance_levels as usize);
+        }
+        if let Some(timeout) = options.watchdog {
+            client = client.with_watchdog(timeout);
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(config) = options.chaos.clone() {
+            client = client.with_chaos(config);
+        }
+        client
+    }).await
+}
\ No newline at end of file
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::LogArgs;
+use crate::db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace, Database};
+use crate::models::{market_event::MarketEvent, trade_candle::TradeCandle};
+use crate::utils::{event_writer::EventWriter, mongo_sink::MongoCandleSink, index::{IndexBuilder, IndexDefinition, INDEX_EXCHANGE}, symbol_manager::SYMBOL_MANAGER, timeframe};
+use mongodb::bson::{doc, Document};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+#[derive(Parser, Debug)]
+#[command(name = "index")]
+#[command(about = "Compose weighted basket (index) candles from stored constituent candles across exchanges", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; must match the collectors)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Index definitions, one per line (e.g., BTC70-ETH30 = 0.7*bybit:linear:BTCUSDT + 0.3*binance:linear:ETHUSDT)
+    #[arg(short, long)]
+    config: PathBuf,
+
+    /// Timeframes to compose (comma-separated seconds or Ns/Nm/Nh/Nd; the collectors must store them)
+    #[arg(short = 't', long, default_value = "1m")]
+    timeframes: String,
+
+    /// Candle boundary time zone, as given to the collectors with --session
+    #[arg(long, default_value = "utc")]
+    session: String,
+
+    /// Wait this long after each boundary for the collectors to write the constituent candles
+    #[arg(long, default_value = "5000")]
+    delay_ms: u64,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    /// Write the index candles to MongoDB (stored like collector candles under the index symbol id)
+    #[arg(long)]
+    update: bool,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("index")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?;
+    let shard_urls = shard_urls(args.shard_urls.as_deref());
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let timeframes = timeframe::parse_list(&args.timeframes)?;
+    let session_offset = timeframe::parse_session_offset(&args.session)?;
+
+    let definitions = IndexDefinition::load(&args.config)?;
+    for definition in &definitions {
+        if SYMBOL_MANAGER.get_symbol_id(INDEX_EXCHANGE, &definition.name, "spot").is_none() {
+            tracing::warn!("{} ({} spot) is not in master.csv; index candles cannot be stored", definition.name, INDEX_EXCHANGE);
+        }
+    }
+    // 構成銘柄の symbol id (読み出しの条件)
+    let mut constituents = Vec::new();
+    for (exchange, market_type, symbol) in IndexBuilder::new(definitions.clone()).constituents() {
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&exchange, &symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", exchange, symbol, market_type))?;
+        constituents.push((exchange, market_type, symbol, symbol_id));
+    }
+    info!("Starting index composer: {:?}, timeframes: {:?}", definitions.iter().map(|d| &d.name).collect::<Vec<_>>(), timeframes);
+
+    let databases = connect_federated(&database_url, &shard_urls).await?;
+    let db = Arc::new(
+        Database::new(&database_url, args.update)
+            .await?
+            .with_namespace(namespace.clone())?
+            .with_shards(&shard_urls)
+            .await?,
+    );
+    let sinks = MongoCandleSink::fanout(&db, INDEX_EXCHANGE);
+    let (output_tx, output_rx) = mpsc::channel::<MarketEvent>(1000);
+    let (sinks_tx, sinks_rx) = mpsc::channel::<MarketEvent>(1000);
+    tokio::spawn(sinks.clone().run(output_rx, sinks_tx));
+    let writer = tokio::spawn(EventWriter::new(db, INDEX_EXCHANGE).run(sinks_rx));
+
+    let delay = chrono::Duration::milliseconds(args.delay_ms as i64);
+    for period_seconds in timeframes {
+        let collection_name = namespaced_collection(namespace.as_deref(), &candle_collection_name(period_seconds as i32).unwrap());
+        let collections: Vec<mongodb::Collection<Document>> = databases.iter().map(|db| db.collection::<Document>(&collection_name)).collect();
+        let constituents = constituents.clone();
+        let output_tx = output_tx.clone();
+        let mut builder = IndexBuilder::new(definitions.clone());
+        tokio::spawn(async move {
+            loop {
+                // 境界から delay 後に, 終わった足を読む
+                let now = chrono::Utc::now() - delay;
+                let next = timeframe::bucket_end(&now, period_seconds, session_offset);
+                tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+                let timestamp = next;
+                let mut candles = Vec::new();
+                for (exchange, market_type, symbol, symbol_id) in &constituents {
+                    let collection = &collections[shard_index(symbol, collections.len())];
+                    let filter = doc! {
+                        "metadata.symbol": *symbol_id,
+                        "unixtime": mongodb::bson::DateTime::from_millis(timestamp.timestamp_millis()),
+                    };
+                    // 訂正された足は最新の revision を使う
+                    match collection.find_one(filter).sort(doc! { "revision": -1 }).await {
+                        Ok(Some(doc)) => match TradeCandle::from_timeseries_document(&doc, exchange.clone(), market_type.clone(), symbol.clone(), period_seconds as i32) {
+                            Ok(candle) => candles.push(candle),
+                            Err(e) => error!("Invalid {}s candle of {} {}: {}", period_seconds, exchange, symbol, e),
+                        },
+                        Ok(None) => tracing::debug!("No {}s candle of {} {} @ {}", period_seconds, exchange, symbol, timestamp),
+                        Err(e) => error!("Failed to read {}s candle of {} {}: {}", period_seconds, exchange, symbol, e),
+                    }
+                }
+                for candle in builder.compose(timestamp, period_seconds as i32, &candles) {
+                    if output_tx.send(MarketEvent::Candle(candle)).await.is_err() {
+                        return;
+                    }
+                }
+                tracing::debug!("Composed {}s index candles @ {}", period_seconds, timestamp);
+            }
+        });
+    }
+    drop(output_tx);
+    writer.await?;
+    sinks.finish().await;
+    Ok(())
+}
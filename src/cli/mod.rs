@@ -0,0 +1,114 @@
+pub mod common;
+pub mod bybit;
+pub mod binance;
+pub mod hyperliquid;
+pub mod bitstamp;
+pub mod phemex;
+pub mod backpack;
+pub mod collector;
+pub mod correlation;
+pub mod pairs;
+pub mod export;
+pub mod upload;
+pub mod trades;
+pub mod quality;
+pub mod admin;
+pub mod index;
+pub mod symbols;
+pub mod backfill;
+pub mod downsample;
+pub mod gaps;
+pub mod replay;
+#[cfg(feature = "execution")]
+pub mod account;
+
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+/// kkcrypto の全てのコマンド (src/bin の各バイナリは互換のために残している)
+#[derive(Parser, Debug)]
+#[command(name = "kkcrypto")]
+#[command(about = "Collect, store and analyze cryptocurrency market data", long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Collect real-time trades from an exchange (or several, from a config file)
+    Collect {
+        #[command(subcommand)]
+        exchange: CollectCommand,
+    },
+    /// Build candles from historical trades (Binance / Bybit archives and REST)
+    Backfill(backfill::Args),
+    /// Derive higher timeframe candles from stored 1s / 5s candles
+    Downsample(downsample::Args),
+    /// Report missing, duplicate and invalid stored candles
+    Gaps(gaps::Args),
+    /// Replay stored candles (or historical trades) as a live feed
+    Replay(replay::Args),
+    /// Real-time correlation calculator for cryptocurrency data
+    Correlate(correlation::Args),
+    /// Monitor pair spreads, z-scores and cointegration
+    Pairs(pairs::Args),
+    /// Export stored candles as standard OHLCV CSV
+    Export(export::Args),
+    /// Upload completed hours / days of candles as Parquet to S3 / GCS
+    Upload(upload::Args),
+    /// Decode raw trades stored with --db-trades to CSV
+    Trades(trades::Args),
+    /// List symbols registered in master.csv
+    Symbols(symbols::Args),
+    /// Print daily trade feed quality reports
+    Quality(quality::Args),
+    /// Compose weighted basket (index) candles from stored candles
+    Index(index::Args),
+    /// Operate running collectors (snapshot, audit)
+    Admin(admin::Args),
+    /// Track account balances and positions
+    #[cfg(feature = "execution")]
+    Account(account::Args),
+}
+
+#[derive(Subcommand, Debug)]
+enum CollectCommand {
+    Bybit(bybit::Args),
+    Binance(binance::Args),
+    Hyperliquid(hyperliquid::Args),
+    Bitstamp(bitstamp::Args),
+    Phemex(phemex::Args),
+    Backpack(backpack::Args),
+    /// Several exchanges and markets in one process, driven by a TOML / YAML config
+    Config(collector::Args),
+}
+
+pub async fn run(cli: Cli) -> Result<()> {
+    match cli.command {
+        Command::Collect { exchange } => match exchange {
+            CollectCommand::Bybit(args) => bybit::run(args).await,
+            CollectCommand::Binance(args) => binance::run(args).await,
+            CollectCommand::Hyperliquid(args) => hyperliquid::run(args).await,
+            CollectCommand::Bitstamp(args) => bitstamp::run(args).await,
+            CollectCommand::Phemex(args) => phemex::run(args).await,
+            CollectCommand::Backpack(args) => backpack::run(args).await,
+            CollectCommand::Config(args) => collector::run(args).await,
+        },
+        Command::Backfill(args) => backfill::run(args).await,
+        Command::Downsample(args) => downsample::run(args).await,
+        Command::Gaps(args) => gaps::run(args).await,
+        Command::Replay(args) => replay::run(args).await,
+        Command::Correlate(args) => correlation::run(args).await,
+        Command::Pairs(args) => pairs::run(args).await,
+        Command::Export(args) => export::run(args).await,
+        Command::Upload(args) => upload::run(args).await,
+        Command::Trades(args) => trades::run(args).await,
+        Command::Symbols(args) => symbols::run(args),
+        Command::Quality(args) => quality::run(args).await,
+        Command::Index(args) => index::run(args).await,
+        Command::Admin(args) => admin::run(args),
+        #[cfg(feature = "execution")]
+        Command::Account(args) => account::run(args).await,
+    }
+}
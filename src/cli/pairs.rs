@@ -0,0 +1,227 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use clap::Parser;
+use super::common::LogArgs;
+use crate::db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace};
+use crate::models::market_type::MarketType;
+use crate::utils::pair_signal_store::{to_document, PairSignalStore, PAIR_SIGNAL_COLLECTION};
+use crate::utils::pair_spread::{parse_pairs, spread_stats, ZScoreTrigger};
+use crate::utils::price_window::price_point;
+use crate::utils::resample::{resample_series, FillPolicy, TimeGrid};
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use mongodb::bson::{doc, Document};
+use std::collections::{BTreeMap, HashMap};
+use tracing::{error, info, warn};
+
+#[derive(Parser, Debug)]
+#[command(name = "pairs")]
+#[command(about = "Monitor pair spreads: rolling OLS hedge ratio, spread z-score and Engle-Granger cointegration", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; must match the collectors)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Pairs as <symbol>/<hedge symbol>, comma-separated (e.g., ETHUSDT/BTCUSDT; ln(ETH) is regressed on ln(BTC))
+    #[arg(short, long)]
+    pairs: String,
+
+    /// Regression window in minutes (default: 240)
+    #[arg(short = 'w', long, default_value = "240")]
+    window_minutes: u32,
+
+    /// Candle timeframe and calculation interval in seconds (default: 60)
+    #[arg(short = 'i', long, default_value = "60")]
+    interval: u64,
+
+    /// Minimum buckets where both symbols have a price (default: 100)
+    #[arg(long, default_value = "100")]
+    min_data_points: usize,
+
+    /// Enter when |z-score| reaches this (default: 2.0)
+    #[arg(long, default_value = "2.0")]
+    entry_z: f64,
+
+    /// Exit when |z-score| falls back to this (default: 0.5)
+    #[arg(long, default_value = "0.5")]
+    exit_z: f64,
+
+    /// Missing bucket policy: ffill, ffill:<buckets>, interpolate, drop or none
+    #[arg(long, default_value = "ffill")]
+    fill: String,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    /// Also write signals to the pair_signals collection
+    #[arg(long)]
+    update: bool,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+/// 監視するペア (y を x で回帰する)
+struct PairTarget {
+    label: String,
+    y: (String, i32),
+    x: (String, i32),
+    trigger: ZScoreTrigger,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("pairs")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?;
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let market_type = MarketType::parse(&args.market_type)?;
+    let fill_policy = FillPolicy::parse(&args.fill)?;
+    let collection_name = candle_collection_name(args.interval as i32)
+        .ok_or_else(|| anyhow::anyhow!("Invalid interval: {}", args.interval))?;
+    let collection_name = namespaced_collection(namespace.as_deref(), &collection_name);
+
+    let symbol_id = |symbol: &str| {
+        SYMBOL_MANAGER
+            .get_symbol_id(&args.exchange, symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", args.exchange, symbol, market_type))
+    };
+    let mut targets = Vec::new();
+    for (y, x) in parse_pairs(&args.pairs)? {
+        targets.push(PairTarget {
+            label: format!("{}/{}", y, x),
+            y: (y.clone(), symbol_id(&y)?),
+            x: (x.clone(), symbol_id(&x)?),
+            trigger: ZScoreTrigger::new(args.entry_z, args.exit_z)?,
+        });
+    }
+
+    let databases = connect_federated(&database_url, &shard_urls(args.shard_urls.as_deref())).await?;
+    // symbol を書き込んだシャードごとに読む symbol_id
+    let mut symbols_by_shard: BTreeMap<usize, Vec<i32>> = BTreeMap::new();
+    for (symbol, id) in targets.iter().flat_map(|target| [&target.y, &target.x]) {
+        let ids = symbols_by_shard.entry(shard_index(symbol, databases.len())).or_default();
+        if !ids.contains(id) {
+            ids.push(*id);
+        }
+    }
+    // 結果は MONGODB_URL (シャード 0) に書き込む
+    let store = if args.update {
+        let collection_name = namespaced_collection(namespace.as_deref(), PAIR_SIGNAL_COLLECTION);
+        let store = PairSignalStore::open(&databases[0], &collection_name).await?;
+        info!("Writing pair signals to {}", collection_name);
+        Some(store)
+    } else {
+        None
+    };
+    info!("Monitoring {} pairs on {} (window {} minutes)", targets.len(), collection_name, args.window_minutes);
+
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(args.interval));
+    loop {
+        interval.tick().await;
+        let end_time = Utc::now();
+        let start_time = end_time - Duration::minutes(args.window_minutes as i64);
+        let filter_start = mongodb::bson::DateTime::from_millis(start_time.timestamp_millis());
+
+        // 窓の価格を symbol ごとに読む
+        let mut points: HashMap<i32, Vec<(i64, f64)>> = HashMap::new();
+        let mut failed = false;
+        for (shard, ids) in &symbols_by_shard {
+            let collection = databases[*shard].collection::<Document>(&collection_name);
+            let filter = doc! { "metadata.symbol": { "$in": ids.clone() }, "unixtime": { "$gte": filter_start } };
+            let result: Result<()> = async {
+                let mut cursor = collection.find(filter).await?;
+                while cursor.advance().await? {
+                    let doc: Document = cursor.current().try_into()?;
+                    if let Some(point) = price_point(&doc) {
+                        points.entry(point.symbol_id).or_default().push((point.timestamp_ms, point.price));
+                    }
+                }
+                Ok(())
+            }
+            .await;
+            if let Err(e) = result {
+                error!("Failed to read candles from shard {}: {}", shard, e);
+                failed = true;
+            }
+        }
+        if failed {
+            continue;
+        }
+
+        let grid = TimeGrid::aligned(start_time, end_time, args.interval as i64);
+        let Some(timestamp) = grid.timestamps().last().and_then(|ms| chrono::DateTime::from_timestamp_millis(*ms)) else {
+            continue;
+        };
+        let series: HashMap<i32, Vec<Option<f64>>> = points
+            .iter()
+            .map(|(symbol_id, points)| (*symbol_id, resample_series(points, &grid, fill_policy)))
+            .collect();
+        for target in targets.iter_mut() {
+            let (Some(values_y), Some(values_x)) = (series.get(&target.y.1), series.get(&target.x.1)) else {
+                warn!("{}: no candles in the window", target.label);
+                continue;
+            };
+            // 両方の価格があるバケットだけ使う
+            let (x, y): (Vec<f64>, Vec<f64>) = values_x.iter().zip(values_y).filter_map(|(a, b)| Some(((*a)?, (*b)?))).unzip();
+            if x.len() < args.min_data_points {
+                info!(pair = %target.label, buckets = x.len(), "[PAIRS] {}: {} buckets with both prices (need {})", target.label, x.len(), args.min_data_points);
+                continue;
+            }
+            let Some(stats) = spread_stats(&x, &y) else {
+                warn!(pair = %target.label, buckets = x.len(), "[PAIRS] {}: cannot fit the hedge ratio ({} buckets)", target.label, x.len());
+                continue;
+            };
+            let cointegration = match stats.cointegration_level() {
+                Some(level) => format!("cointegrated at {}%", level * 100.0),
+                None => "not cointegrated".to_string(),
+            };
+            info!(
+                pair = %target.label, hedge_ratio = stats.hedge_ratio, spread = stats.spread, zscore = ?stats.zscore, adf = ?stats.adf, buckets = stats.count,
+                "[PAIRS] {}: hedge_ratio={:.4}, spread={:.6}, z={}, adf={} ({}, {} buckets)",
+                target.label,
+                stats.hedge_ratio,
+                stats.spread,
+                stats.zscore.map_or("-".to_string(), |z| format!("{:.3}", z)),
+                stats.adf.map_or("-".to_string(), |adf| format!("{:.3}", adf)),
+                cointegration,
+                stats.count,
+            );
+            let Some(signal) = stats.zscore.and_then(|z| target.trigger.update(z)) else {
+                continue;
+            };
+            let zscore = stats.zscore.unwrap_or_default();
+            info!(
+                pair = %target.label, signal = signal.as_str(), zscore, timestamp = %timestamp,
+                "[PAIRS-SIGNAL] {} {} at {} (z={:.3})", target.label, signal.as_str(), timestamp.format("%Y-%m-%d %H:%M:%S"), zscore
+            );
+            if let Some(store) = store.as_ref() {
+                let document = to_document(timestamp, target.y.1, target.x.1, args.window_minutes, args.interval as i64, signal, &stats);
+                if let Err(e) = store.write(document).await {
+                    error!("Failed to store the {} signal: {}", target.label, e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use clap::Parser;
+use super::common::{ClientOptions, CollectorArgs};
+use crate::{
+    exchanges::phemex::PhemexClient,
+    models::market_type::MarketType,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "phemex")]
+#[command(about = "Collect real-time cryptocurrency trade data from Phemex", long_about = None)]
+pub struct Args {
+    /// Symbols to subscribe (comma-separated, e.g., sBTCUSDT for spot, BTCUSDT for linear, BTCUSD for inverse)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    #[command(flatten)]
+    collector: CollectorArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    let collector = args.collector.start("Phemex", &[MarketType::Spot, MarketType::Linear, MarketType::Inverse])?
+        .without_gateway_time();
+
+    // Start Phemex client
+    collector.run(&args.symbols, |options: &ClientOptions| {
+        let mut client = PhemexClient::new(options.events.clone(), options.raw_freq).with_endpoint(options.endpoint.clone());
+        if let Some(timeout) = options.watchdog {
+            client = client.with_watchdog(timeout);
+        }
+        #[cfg(feature = "chaos")]
+        if let Some(config) = options.chaos.clone() {
+            client = client.with_chaos(config);
+        }
+        client
+    }).await
+}
\ No newline at end of file
@@ -0,0 +1,89 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::LogArgs;
+use futures::TryStreamExt;
+use crate::db::{namespaced_collection, validate_namespace};
+use crate::utils::quality::QualityReport;
+use tracing::info;
+use mongodb::{
+    bson::{doc, Document},
+    Client,
+};
+
+#[derive(Parser, Debug)]
+#[command(name = "quality")]
+#[command(about = "Print daily trade feed quality reports (quality_reports collection)", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Report date in UTC (YYYY-MM-DD, default: yesterday)
+    #[arg(long)]
+    date: Option<NaiveDate>,
+
+    /// Filter by exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: Option<String>,
+
+    /// Filter by market type (spot, linear, inverse)
+    #[arg(short, long)]
+    market_type: Option<String>,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing (stdout はレポートの出力先になるので stderr に出す)
+    args.logs.init_stderr("quality")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .expect("MONGODB_URL must be set");
+
+    let date = args.date.unwrap_or_else(|| (Utc::now() - Duration::days(1)).date_naive());
+
+    let client = Client::with_uri_str(&database_url).await?;
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let collection = client
+        .database("trade")
+        .collection::<Document>(&namespaced_collection(namespace.as_deref(), "quality_reports"));
+
+    let mut filter = doc! { "date": date.format("%Y-%m-%d").to_string() };
+    if let Some(exchange) = args.exchange {
+        filter.insert("exchange", exchange);
+    }
+    if let Some(market_type) = args.market_type {
+        filter.insert("market_type", market_type);
+    }
+
+    let mut cursor = collection
+        .find(filter)
+        .sort(doc! { "exchange": 1, "market_type": 1, "started_at": 1 })
+        .await?;
+
+    let mut count = 0;
+    while let Some(doc) = cursor.try_next().await? {
+        print!("{}", QualityReport::from_document(doc)?);
+        count += 1;
+    }
+
+    if count == 0 {
+        info!(date = %date, "No quality reports found for {}", date);
+    }
+
+    Ok(())
+}
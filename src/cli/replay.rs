@@ -0,0 +1,205 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::{self, DatabaseArgs, LogArgs, TimeframeArgs};
+use crate::{
+    db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace},
+    models::{market_event::MarketEvent, market_type::MarketType, trade_candle::TradeCandle},
+    utils::{candle_fields::CandleFieldSelection, endpoint::{Endpoint, ProxyConfig}, event_writer::EventWriter, mongo_sink::MongoCandleSink, history::{self, HistorySource}, replay::{self, ReplayClock}, symbol_manager::SYMBOL_MANAGER, trade_candle_builder::TradeCandleBuilder},
+};
+use futures::TryStreamExt;
+use mongodb::bson::{doc, Document};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::info;
+
+#[derive(Parser, Debug)]
+#[command(name = "replay")]
+#[command(about = "Replay stored candles (or historical trades) as a live feed at real-time or accelerated speed", long_about = None)]
+pub struct Args {
+    #[command(flatten)]
+    database: DatabaseArgs,
+
+    /// Namespace to read stored candles from (defaults to MONGODB_NAMESPACE; --namespace is where replayed candles are written)
+    #[arg(long)]
+    source_namespace: Option<String>,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Symbols as stored in master.csv (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    /// What to replay: candles (stored candles of the timeframes) or trades (raw trades are not stored, so Binance / Bybit history is fetched and built into candles of the timeframes)
+    #[arg(long, default_value = "candles")]
+    source: String,
+
+    #[command(flatten)]
+    candles: TimeframeArgs,
+
+    /// Start date in UTC (YYYY-MM-DD, inclusive, default: yesterday)
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// End date in UTC (YYYY-MM-DD, exclusive, default: start + 1 day)
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Replay speed (1 = real time, 60 = one hour per minute, 0 = as fast as possible)
+    #[arg(long, default_value = "1")]
+    speed: f64,
+
+    /// Shift event times so the replay starts now (aligned to the largest timeframe), for consumers reading the latest window
+    #[arg(long)]
+    rebase: bool,
+
+    /// Where to get trades with --source trades: archive or rest
+    #[arg(long, default_value = "archive")]
+    history: String,
+
+    /// Send REST / archive requests through this HTTP proxy (or use PROXY_URL env var)
+    #[arg(long)]
+    proxy: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("replay")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let exchange = args.exchange.to_lowercase();
+    let market_type = MarketType::parse(&args.market_type)?;
+    let symbols = common::parse_symbols(&args.symbols);
+    let mut symbol_ids = Vec::new();
+    for symbol in &symbols {
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&exchange, symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", exchange, symbol, market_type))?;
+        symbol_ids.push((symbol.clone(), symbol_id));
+    }
+    let timeframes = args.candles.timeframes()?;
+    let session_offset = args.candles.session_offset()?;
+    for &period in &timeframes {
+        candle_collection_name(period as i32).ok_or_else(|| anyhow::anyhow!("Unsupported timeframe: {} seconds", period))?;
+    }
+    let trades = match args.source.as_str() {
+        "candles" => false,
+        "trades" if matches!(exchange.as_str(), "binance" | "bybit") => true,
+        "trades" => return Err(anyhow::anyhow!("Trades are not stored; --source trades fetches binance / bybit history, not {}", exchange)),
+        _ => return Err(anyhow::anyhow!("Invalid source: {}. Use candles or trades", args.source)),
+    };
+    let history_source = HistorySource::parse(&args.history)?;
+
+    let start = args.start.unwrap_or_else(|| (Utc::now() - Duration::days(1)).date_naive());
+    let end = args.end.unwrap_or(start + Duration::days(1));
+    let start_time = start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end_time = end.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    if start_time >= end_time {
+        return Err(anyhow::anyhow!("Nothing to replay between {} and {}", start, end));
+    }
+
+    // 読んだ足を同じコレクションに書き戻さない (--rebase なら本物の足と混ざる)
+    let source_namespace = args.source_namespace.clone().or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = source_namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    if args.database.update && args.database.namespace(None) == source_namespace {
+        return Err(anyhow::anyhow!("Replay would write into the collections it reads; pass --namespace (e.g., replay) for the replayed data"));
+    }
+
+    let clock = ReplayClock::new(start_time, args.speed)?;
+    let offset = if args.rebase {
+        replay::rebase_offset(start_time, Utc::now(), timeframes.iter().copied().max().unwrap_or(1))
+    } else {
+        Duration::zero()
+    };
+    info!("Replaying {} {} {} {:?} from {} to {} at {}x (shifted by {}s), timeframes: {:?}",
+          exchange, market_type, args.source, symbols, start, end, args.speed, offset.num_seconds(), timeframes);
+
+    // Start output writer (same output as the collectors)
+    let db = Arc::new(args.database.open(CandleFieldSelection::default(), None).await?);
+    let sinks = MongoCandleSink::fanout(&db, &exchange);
+    let (output_tx, output_rx) = mpsc::channel::<MarketEvent>(1000);
+    let (sinks_tx, sinks_rx) = mpsc::channel::<MarketEvent>(1000);
+    tokio::spawn(sinks.clone().run(output_rx, sinks_tx));
+    let writer = tokio::spawn(EventWriter::new(db, &exchange).run(sinks_rx));
+
+    let mut total = 0;
+    if trades {
+        // 全 symbol の約定を時刻順に 1 つの集計に流す (足は約定の時刻で区切られる)
+        let endpoint = Endpoint::new().with_proxy(ProxyConfig::from_arg_or_env(args.proxy.as_deref())?)?;
+        let (trade_tx, trade_rx) = mpsc::channel::<MarketEvent>(1000);
+        let builder = tokio::spawn(
+            TradeCandleBuilder::new(trade_rx, output_tx.clone(), timeframes.clone())
+                .with_session_offset(session_offset)
+                .run_batch(end_time + offset),
+        );
+        for day in history::days(start, end) {
+            let day_start = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            let mut events = Vec::new();
+            for symbol in &symbols {
+                let fetched = super::backfill::fetch_trades(
+                    &exchange, history_source, &endpoint, &market_type, symbol,
+                    (day_start, day_start + Duration::days(1)), std::time::Duration::from_millis(200),
+                ).await?;
+                events.extend(fetched.into_iter().map(MarketEvent::Trade));
+            }
+            events.sort_by_key(|event| event.timestamp());
+            total += replay::replay(events, &clock, offset, &trade_tx).await?;
+            info!("[{}-REPLAY] {} replayed ({} trades so far)", exchange.to_uppercase(), day, total);
+        }
+        drop(trade_tx);
+        builder.await?;
+    } else {
+        let databases = connect_federated(
+            &args.database.url().ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?,
+            &shard_urls(args.database.shard_urls.as_deref()),
+        ).await?;
+        // 1 日ずつ全ての系列を読み, 時刻順に流す (symbol を書き込んだシャードから読む)
+        for day in history::days(start, end) {
+            let day_start = day.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            let mut candles = Vec::new();
+            for (symbol, symbol_id) in &symbol_ids {
+                let database = &databases[shard_index(symbol, databases.len())];
+                for &period in &timeframes {
+                    let collection_name = namespaced_collection(source_namespace.as_deref(), &candle_collection_name(period as i32).unwrap());
+                    let filter = doc! {
+                        "metadata.symbol": *symbol_id,
+                        "unixtime": {
+                            "$gt": mongodb::bson::DateTime::from_millis(day_start.timestamp_millis()),
+                            "$lte": mongodb::bson::DateTime::from_millis((day_start + Duration::days(1)).timestamp_millis()),
+                        },
+                    };
+                    let mut cursor = database.collection::<Document>(&collection_name).find(filter).await?;
+                    while let Some(doc) = cursor.try_next().await? {
+                        candles.push(TradeCandle::from_timeseries_document(
+                            &doc, exchange.clone(), market_type.clone(), symbol.clone(), period as i32,
+                        )?);
+                    }
+                }
+            }
+            // 同じ時刻なら短い時間枠から (collector と同じ順)
+            candles.sort_by_key(|candle| (candle.timestamp, candle.period_seconds));
+            total += replay::replay(candles.into_iter().map(MarketEvent::Candle), &clock, offset, &output_tx).await?;
+            info!("[{}-REPLAY] {} replayed ({} candles so far)", exchange.to_uppercase(), day, total);
+        }
+    }
+
+    // 書き込みが終わるまで待つ
+    drop(output_tx);
+    writer.await?;
+    sinks.finish().await;
+    info!("Replayed {} {} of {:?}", total, args.source, symbols);
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use anyhow::Result;
+use clap::Parser;
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+#[derive(Parser, Debug)]
+#[command(name = "symbols")]
+#[command(about = "List symbols registered in master.csv", long_about = None)]
+pub struct Args {
+    /// Exchange to list (e.g., bybit)
+    #[arg(short, long)]
+    pub exchange: Option<String>,
+
+    /// Market type to list (spot, linear, inverse, ...)
+    #[arg(short, long)]
+    pub market_type: Option<String>,
+
+    /// Only symbols containing this text (case-insensitive, e.g., BTC)
+    pub pattern: Option<String>,
+
+    /// Print the symbols comma-separated for --symbols
+    #[arg(long)]
+    pub join: bool,
+}
+
+pub fn run(args: Args) -> Result<()> {
+    let pattern = args.pattern.as_deref().map(str::to_uppercase);
+    let entries: Vec<_> = SYMBOL_MANAGER
+        .entries()
+        .into_iter()
+        .filter(|(_, exchange, _, _)| args.exchange.as_deref().is_none_or(|e| e == *exchange))
+        .filter(|(_, _, _, market_type)| args.market_type.as_deref().is_none_or(|m| m == *market_type))
+        .filter(|(_, _, symbol, _)| pattern.as_deref().is_none_or(|p| symbol.to_uppercase().contains(p)))
+        .collect();
+    if entries.is_empty() {
+        return Err(anyhow::anyhow!("No symbols found"));
+    }
+
+    if args.join {
+        println!("{}", entries.iter().map(|(_, _, symbol, _)| *symbol).collect::<Vec<_>>().join(","));
+        return Ok(());
+    }
+    println!("{:>6}  {:<12}  {:<8}  symbol", "id", "exchange", "type");
+    for (symbol_id, exchange, symbol, market_type) in entries {
+        println!("{:>6}  {:<12}  {:<8}  {}", symbol_id, exchange, market_type, symbol);
+    }
+    Ok(())
+}
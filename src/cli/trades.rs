@@ -0,0 +1,114 @@
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use clap::Parser;
+use super::common::LogArgs;
+use futures::TryStreamExt;
+use crate::db::{connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace};
+use crate::models::market_type::MarketType;
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use crate::utils::trade_blob::{self, TRADE_BLOB_COLLECTION};
+use mongodb::bson::{doc, Document};
+use std::collections::HashSet;
+use std::io::Write;
+
+#[derive(Parser, Debug)]
+#[command(name = "trades")]
+#[command(about = "Decode raw trades stored with --db-trades (compressed per-minute batches in trade_blobs) to CSV", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; must match the collectors)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Symbol as stored in master.csv (e.g., BTCUSDT)
+    #[arg(short, long)]
+    symbol: String,
+
+    /// Start date in UTC (YYYY-MM-DD, inclusive, default: yesterday)
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// End date in UTC (YYYY-MM-DD, exclusive, default: start + 1 day)
+    #[arg(long)]
+    end: Option<NaiveDate>,
+
+    /// Output file (default: stdout)
+    #[arg(short, long)]
+    output: Option<String>,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing (stdout は CSV の出力先になるので stderr に出す)
+    args.logs.init_stderr("trades")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let database_url = args
+        .database_url
+        .or_else(|| std::env::var("MONGODB_URL").ok())
+        .ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?;
+    let market_type = MarketType::parse(&args.market_type)?;
+    let symbol_id = SYMBOL_MANAGER
+        .get_symbol_id(&args.exchange, &args.symbol, market_type.as_str())
+        .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", args.exchange, args.symbol, market_type))?;
+    let start = args.start.unwrap_or_else(|| (Utc::now() - Duration::days(1)).date_naive());
+    let end = args.end.unwrap_or(start + Duration::days(1));
+    let start_time = start.and_hms_opt(0, 0, 0).unwrap().and_utc();
+    let end_time = end.and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+    // symbol を書き込んだシャードから読む
+    let databases = connect_federated(&database_url, &shard_urls(args.shard_urls.as_deref())).await?;
+    let namespace = args.namespace.or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let collection = databases[shard_index(&args.symbol, databases.len())]
+        .collection::<Document>(&namespaced_collection(namespace.as_deref(), TRADE_BLOB_COLLECTION));
+    // unixtime は分の開始なので [start, end)
+    let filter = doc! {
+        "metadata.symbol": symbol_id,
+        "unixtime": {
+            "$gte": mongodb::bson::DateTime::from_millis(start_time.timestamp_millis()),
+            "$lt": mongodb::bson::DateTime::from_millis(end_time.timestamp_millis()),
+        },
+    };
+    let mut cursor = collection.find(filter).await?;
+    let (mut blobs, mut trades) = (0, Vec::new());
+    while let Some(doc) = cursor.try_next().await? {
+        trades.extend(trade_blob::from_document(&doc, &args.exchange, &market_type, &args.symbol)?);
+        blobs += 1;
+    }
+    // 再起動・再送で同じ約定が別のまとまりに入っていれば 1 つにする
+    trades.sort_by_key(|trade| trade.timestamp);
+    let mut seen = HashSet::new();
+    trades.retain(|trade| seen.insert((trade.trade_id.clone(), trade.timestamp)));
+
+    let mut writer: Box<dyn Write> = match args.output.as_deref() {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    trade_blob::write_csv(&mut writer, &trades)?;
+    writer.flush()?;
+    tracing::info!("Exported {} trades from {} batches ({} {}, {} - {})", trades.len(), blobs, args.exchange, args.symbol, start, end);
+
+    Ok(())
+}
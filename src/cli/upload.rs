@@ -0,0 +1,213 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::Parser;
+use futures::TryStreamExt;
+use super::common::{self, LogArgs};
+use crate::db::{candle_collection_name, connect_federated, namespaced_collection, shard_index, shard_urls, validate_namespace};
+use crate::models::{market_type::MarketType, trade_candle::TradeCandle};
+use crate::utils::{parquet_archive, timeframe};
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use crate::utils::upload::{self, UploadPeriod};
+use mongodb::bson::{doc, Document};
+use mongodb::Database as MongoDatabase;
+use object_store::{path::Path as ObjectPath, ObjectStore};
+use polars::prelude::ParquetCompression;
+use tracing::{info, warn};
+
+#[derive(Parser, Debug)]
+#[command(name = "upload")]
+#[command(about = "Upload each completed hour / day of stored candles as Parquet to S3 / GCS (optionally pruning MongoDB)", long_about = None)]
+pub struct Args {
+    /// MongoDB URL (or use MONGODB_URL env var)
+    #[arg(short, long)]
+    database_url: Option<String>,
+
+    /// Extra MongoDB shard URLs, comma-separated (or use MONGODB_SHARD_URLS env var; must match the collectors)
+    #[arg(long)]
+    shard_urls: Option<String>,
+
+    /// Collection namespace (collections become {namespace}.{collection}; defaults to MONGODB_NAMESPACE)
+    #[arg(long)]
+    namespace: Option<String>,
+
+    /// Exchange (e.g., bybit)
+    #[arg(short, long)]
+    exchange: String,
+
+    /// Market type (spot, linear, inverse)
+    #[arg(short, long, default_value = "linear")]
+    market_type: String,
+
+    /// Symbols as stored in master.csv (comma-separated, e.g., BTCUSDT,ETHUSDT)
+    #[arg(short, long, required = true)]
+    symbols: String,
+
+    /// Timeframes to upload (comma-separated seconds or Ns/Nm/Nh/Nd, e.g., 1s,1m)
+    #[arg(short = 't', long, default_value = "1m")]
+    timeframes: String,
+
+    /// Destination URL: s3://bucket/prefix, gs://bucket/prefix or file:///path (credentials from AWS_* / GOOGLE_* env vars; AWS_ENDPOINT for S3-compatible storage)
+    #[arg(long)]
+    dest: String,
+
+    /// Object layout under the prefix ({exchange} {market} {symbol} {timeframe} {date} {year} {month} {day} {hour}; default depends on --period)
+    #[arg(long)]
+    layout: Option<String>,
+
+    /// Upload period: hour or day (UTC)
+    #[arg(long, default_value = "day")]
+    period: String,
+
+    /// Wait this long after a period closes before uploading it, for late candle revisions (seconds)
+    #[arg(long, default_value = "300")]
+    delay_secs: i64,
+
+    /// First period to upload (YYYY-MM-DD, default: the last completed period)
+    #[arg(long)]
+    start: Option<NaiveDate>,
+
+    /// Upload the completed periods and exit instead of waiting for the next ones
+    #[arg(long)]
+    once: bool,
+
+    /// Parquet compression: zstd, snappy, gzip, lz4 or none
+    #[arg(long, default_value = "zstd")]
+    compression: String,
+
+    /// Upload again even if the object already exists
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Delete the uploaded period's candles from MongoDB once the object exists (time-series deletes need MongoDB 7.0+)
+    #[arg(long)]
+    prune: bool,
+
+    #[command(flatten)]
+    logs: LogArgs,
+}
+
+/// 1 つの系列の 1 期間の足を読んでアップロードし, --prune なら消す
+#[allow(clippy::too_many_arguments)]
+async fn upload_series(
+    args: &Args,
+    database: &MongoDatabase,
+    namespace: Option<&str>,
+    store: &dyn ObjectStore,
+    prefix: &ObjectPath,
+    layout: &str,
+    compression: ParquetCompression,
+    market_type: &MarketType,
+    (symbol, symbol_id): (&str, i32),
+    period_seconds: u32,
+    (start, end): (DateTime<Utc>, DateTime<Utc>),
+) -> Result<()> {
+    let exchange = args.exchange.to_lowercase();
+    let collection_name = namespaced_collection(namespace, &candle_collection_name(period_seconds as i32).unwrap());
+    let collection = database.collection::<Document>(&collection_name);
+    // 足の時刻は終端なので (start, end]
+    let filter = doc! {
+        "metadata.symbol": symbol_id,
+        "unixtime": {
+            "$gt": mongodb::bson::DateTime::from_millis(start.timestamp_millis()),
+            "$lte": mongodb::bson::DateTime::from_millis(end.timestamp_millis()),
+        },
+    };
+    let relative = upload::object_path(layout, &exchange, market_type.as_str(), symbol, &timeframe::label(period_seconds), start);
+    let path = ObjectPath::parse(if prefix.as_ref().is_empty() { relative } else { format!("{}/{}", prefix, relative) })?;
+
+    let exists = store.head(&path).await.is_ok();
+    if exists && !args.overwrite {
+        info!("{} already exists", path);
+    } else {
+        let mut candles = Vec::new();
+        let mut cursor = collection.find(filter.clone()).await?;
+        while let Some(doc) = cursor.try_next().await? {
+            candles.push(TradeCandle::from_timeseries_document(
+                &doc, exchange.clone(), market_type.clone(), symbol.to_string(), period_seconds as i32,
+            )?);
+        }
+        if candles.is_empty() {
+            info!("No {} candles of {} between {} and {}", timeframe::label(period_seconds), symbol, start, end);
+            return Ok(());
+        }
+        let candles = upload::latest_revisions(candles);
+        let bytes = upload::parquet_bytes(&candles, compression)?;
+        let size = bytes.len();
+        store.put(&path, bytes.into()).await?;
+        info!("Uploaded {} candles to {} ({} bytes)", candles.len(), path, size);
+    }
+    if args.prune {
+        let deleted = collection.delete_many(filter).await?;
+        info!("Pruned {} documents of {} {} from {}", deleted.deleted_count, symbol, timeframe::label(period_seconds), collection_name);
+    }
+    Ok(())
+}
+
+pub async fn run(args: Args) -> Result<()> {
+    // Initialize tracing
+    args.logs.init("upload")?;
+
+    // Load .env file
+    dotenv::dotenv().ok();
+
+    let exchange = args.exchange.to_lowercase();
+    let market_type = MarketType::parse(&args.market_type)?;
+    let mut symbols = Vec::new();
+    for symbol in common::parse_symbols(&args.symbols) {
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&exchange, &symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", exchange, symbol, market_type))?;
+        symbols.push((symbol, symbol_id));
+    }
+    let timeframes = timeframe::parse_list(&args.timeframes)?;
+    for &period in &timeframes {
+        candle_collection_name(period as i32).ok_or_else(|| anyhow::anyhow!("Unsupported timeframe: {} seconds", period))?;
+    }
+    let namespace = args.namespace.clone().or_else(|| std::env::var("MONGODB_NAMESPACE").ok());
+    if let Some(namespace) = namespace.as_deref() {
+        validate_namespace(namespace)?;
+    }
+    let period = UploadPeriod::parse(&args.period)?;
+    let layout = args.layout.clone().unwrap_or_else(|| period.default_layout().to_string());
+    let compression = parquet_archive::parse_compression(&args.compression)?;
+    let delay = Duration::seconds(args.delay_secs.max(0));
+
+    // 認証などは環境変数 (AWS_ACCESS_KEY_ID -> aws_access_key_id など) から
+    let url = url::Url::parse(&args.dest)?;
+    let (store, prefix) = object_store::parse_url_opts(&url, std::env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value)))?;
+
+    let database_url = args.database_url.clone().or_else(|| std::env::var("MONGODB_URL").ok())
+        .ok_or_else(|| anyhow::anyhow!("MONGODB_URL must be set"))?;
+    let databases = connect_federated(&database_url, &shard_urls(args.shard_urls.as_deref())).await?;
+
+    let mut next_start = match args.start {
+        Some(start) => start.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        None => period.floor(Utc::now() - delay) - period.duration(),
+    };
+    info!("Uploading {} {} {:?} {:?} by {:?} from {} to {} (layout {})", exchange, market_type, symbols.iter().map(|(s, _)| s).collect::<Vec<_>>(),
+          timeframes, period, next_start, args.dest, layout);
+    loop {
+        for start in upload::completed_periods(next_start, Utc::now(), delay, period) {
+            let end = start + period.duration();
+            for (symbol, symbol_id) in &symbols {
+                let database = &databases[shard_index(symbol, databases.len())];
+                for &period_seconds in &timeframes {
+                    // 失敗した系列は次の期間の後に再試行しない (--start で指定してやり直す)
+                    if let Err(e) = upload_series(
+                        &args, database, namespace.as_deref(), store.as_ref(), &prefix, &layout, compression, &market_type,
+                        (symbol, *symbol_id), period_seconds, (start, end),
+                    ).await {
+                        warn!("Failed to upload {} {} {} - {}: {}", symbol, timeframe::label(period_seconds), start, end, e);
+                    }
+                }
+            }
+            next_start = end;
+        }
+        if args.once {
+            return Ok(());
+        }
+        // 次の期間が終わって delay 経つまで待つ
+        let wake = next_start + period.duration() + delay;
+        tokio::time::sleep((wake - Utc::now()).to_std().unwrap_or_default()).await;
+    }
+}
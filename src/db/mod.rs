@@ -1,10 +1,137 @@
-use mongodb::{Client, Database as MongoDatabase};
+use mongodb::{Client, Database as MongoDatabase, IndexModel};
+use mongodb::options::{TimeseriesGranularity, TimeseriesOptions};
 use anyhow::Result;
+use crate::utils::candle_fields::CandleFieldSelection;
+use crate::utils::ops_events::{self, OpsEventKind};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// 足をまとめて書き込む件数と, 最初の足から書き込むまでの最大の待ち時間 (ミリ秒)
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+pub const DEFAULT_BATCH_LATENCY_MS: u64 = 1000;
+/// 一時的なエラーで書き込みを再試行する回数 (0.5 秒から倍々に待つ)
+const MAX_WRITE_RETRIES: u32 = 5;
+
+/// 待てば成功しうるエラー (接続・サーバー選択の失敗, RetryableWriteError)
+fn is_transient(error: &mongodb::error::Error) -> bool {
+    use mongodb::error::{ErrorKind, RETRYABLE_WRITE_ERROR};
+    matches!(*error.kind, ErrorKind::Io(_) | ErrorKind::ConnectionPoolCleared { .. } | ErrorKind::ServerSelection { .. })
+        || error.contains_label(RETRYABLE_WRITE_ERROR)
+}
+
+/// namespace を指定した場合のコレクション名 ({namespace}.{collection})
+/// 同じクラスタを複数のチーム・環境で共有しても衝突しないようにする
+pub fn namespaced_collection(namespace: Option<&str>, collection: &str) -> String {
+    match namespace {
+        Some(namespace) => format!("{}.{}", namespace, collection),
+        None => collection.to_string(),
+    }
+}
+
+/// 時間枠 (秒) ごとのローソク足のコレクション名 (candles_1s, candles_90s, candles_1m, candles_4h など)
+pub fn candle_collection_name(period_seconds: i32) -> Option<String> {
+    u32::try_from(period_seconds)
+        .ok()
+        .filter(|&seconds| seconds > 0)
+        .map(|seconds| format!("candles_{}", crate::utils::timeframe::label(seconds)))
+}
+
+/// 足のドキュメントの一意のキー (metadata.symbol, unixtime のミリ秒)
+/// 時間枠ごとにコレクションが分かれるので (symbol, 時間枠, 時刻) ごとに 1 つだけ保存され, 再起動・再送・backfill で書き直しても重複しない
+pub fn candle_key(doc: &mongodb::bson::Document) -> Result<(i32, i64)> {
+    Ok((doc.get_document("metadata")?.get_i32("symbol")?, doc.get_datetime("unixtime")?.timestamp_millis()))
+}
+
+/// master.csv にない symbol の足は symbol_id が 0 (bitflyer BTC_JPY) になりキーが重なるので書き込まない
+fn check_keyed_symbol(exchange: &str, symbol: &str, market_type: &crate::models::market_type::MarketType) -> Result<()> {
+    use crate::utils::symbol_manager::SYMBOL_MANAGER;
+    match SYMBOL_MANAGER.get_symbol_id(exchange, symbol, market_type.as_str()) {
+        Some(_) => Ok(()),
+        None => Err(anyhow::anyhow!("{} {} ({}) is not in master.csv; its candles cannot be keyed", exchange, symbol, market_type)),
+    }
+}
+
+/// キーに一致する足を探す条件
+fn candle_filter((symbol, time): (i32, i64)) -> mongodb::bson::Document {
+    mongodb::bson::doc! { "metadata.symbol": symbol, "unixtime": mongodb::bson::DateTime::from_millis(time) }
+}
+
+/// 足の時間枠に合う時系列コレクションの granularity (schema.mongo.js と同じく 1m までは seconds)
+pub fn candle_granularity(period_seconds: i32) -> TimeseriesGranularity {
+    match period_seconds {
+        ..=60 => TimeseriesGranularity::Seconds,
+        ..=3600 => TimeseriesGranularity::Minutes,
+        _ => TimeseriesGranularity::Hours,
+    }
+}
+
+/// symbol の書き込み先のシャード番号 (0 は MONGODB_URL)
+/// FNV-1a なのでプロセスや Rust のバージョンによらず同じ symbol は同じシャードになる
+pub fn shard_index(symbol: &str, shards: usize) -> usize {
+    if shards <= 1 {
+        return 0;
+    }
+    let hash = symbol
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+    (hash % shards as u64) as usize
+}
+
+/// 追加のシャードの接続先 (カンマ区切り, 引数がなければ MONGODB_SHARD_URLS)
+pub fn shard_urls(arg: Option<&str>) -> Vec<String> {
+    arg.map(str::to_string)
+        .or_else(|| std::env::var("MONGODB_SHARD_URLS").ok())
+        .map(|urls| urls.split(',').map(str::trim).filter(|u| !u.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// シャードのデータベースに接続する
+/// URL にデータベース名があればそれを使う (mongodb://host/trade_b, 同じクラスタの別データベースにも分けられる), なければ trade
+pub async fn connect_shard(url: &str) -> Result<MongoDatabase> {
+    let client = Client::with_uri_str(url).await?;
+    let database = client.default_database().unwrap_or_else(|| client.database("trade"));
+    database.run_command(mongodb::bson::doc! {"ping": 1}).await?;
+    Ok(database)
+}
+
+/// 読み出し用: MONGODB_URL (trade) と全シャードのデータベース (シャード番号順)
+pub async fn connect_federated(database_url: &str, shard_urls: &[String]) -> Result<Vec<MongoDatabase>> {
+    let client = Client::with_uri_str(database_url).await?;
+    let mut databases = vec![client.database("trade")];
+    for url in shard_urls {
+        databases.push(connect_shard(url).await?);
+    }
+    Ok(databases)
+}
+
+pub fn validate_namespace(namespace: &str) -> Result<()> {
+    if namespace.is_empty() || namespace.len() > 32 {
+        return Err(anyhow::anyhow!("Namespace must be 1-32 characters: {:?}", namespace));
+    }
+    if !namespace.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        return Err(anyhow::anyhow!("Namespace may only contain [A-Za-z0-9_-]: {:?}", namespace));
+    }
+    // system.* は MongoDB の予約名
+    if namespace == "system" {
+        return Err(anyhow::anyhow!("Namespace \"system\" is reserved"));
+    }
+    Ok(())
+}
 
 pub struct Database {
     _client: Option<Client>,  // 将来使用予定
     database: Option<MongoDatabase>,
     is_dummy: bool,
+    candle_fields: CandleFieldSelection,
+    namespace: Option<String>,
+    healthy: AtomicBool,  // 直近の書き込みが成功したか (障害の開始・復旧を ops_events に記録する)
+    shards: Vec<MongoDatabase>,  // 追加のシャード (symbol のハッシュで振り分ける, 空なら全て database)
+    batch_size: usize,
+    batch_latency: Duration,
+    ensured: Mutex<HashSet<(usize, String)>>,  // 作成・索引を確認済みの (シャード, 足のコレクション)
+    unlisted: Mutex<HashSet<(String, String, String)>>,  // 警告済みの master.csv にない (取引所, 市場, symbol)
 }
 
 impl Database {
@@ -13,25 +140,20 @@ impl Database {
         
         if update_flag {
             info!("Connecting to MongoDB: {}", database_url);
-            let client = Client::with_uri_str(database_url).await?;
-            let database = client.database("trade");
+            let db = Self::connect(database_url).await?;
             
             // 接続テストを実行
-            match database.run_command(mongodb::bson::doc! {"ping": 1}).await {
-                Ok(_) => {
-                    info!("Database initialized (real connection): database={}, status=connected", database.name());
+            match db.ping().await {
+                Ok(()) => {
+                    info!("Database initialized (real connection): database=trade, status=connected");
                 }
                 Err(e) => {
                     tracing::error!("Database ping failed: {}", e);
-                    return Err(e.into());
+                    return Err(e);
                 }
             }
             
-            Ok(Self { 
-                _client: Some(client), 
-                database: Some(database),
-                is_dummy: false,
-            })
+            Ok(db)
         } else {
             // Dummy connection
             info!("Database initialized (dummy connection)");
@@ -40,50 +162,410 @@ impl Database {
                 _client: None,
                 database: None,
                 is_dummy: true,
+                candle_fields: CandleFieldSelection::default(),
+                namespace: None,
+                healthy: AtomicBool::new(true),
+                shards: Vec::new(),
+                batch_size: DEFAULT_BATCH_SIZE,
+                batch_latency: Duration::from_millis(DEFAULT_BATCH_LATENCY_MS),
+                ensured: Mutex::new(HashSet::new()),
+                unlisted: Mutex::new(HashSet::new()),
             })
         }
     }
 
+    /// 接続を確認せずに作る (MongoDB が落ちていれば最初の書き込みが失敗する)
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let client = Client::with_uri_str(database_url).await?;
+        let database = client.database("trade");
+        Ok(Self {
+            _client: Some(client),
+            database: Some(database),
+            is_dummy: false,
+            candle_fields: CandleFieldSelection::default(),
+            namespace: None,
+            healthy: AtomicBool::new(true),
+            shards: Vec::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            batch_latency: Duration::from_millis(DEFAULT_BATCH_LATENCY_MS),
+            ensured: Mutex::new(HashSet::new()),
+            unlisted: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// シャード 0 に ping する (--update なしなら常に成功)
+    pub async fn ping(&self) -> Result<()> {
+        if let Some(database) = self.database.as_ref() {
+            database.run_command(mongodb::bson::doc! {"ping": 1}).await?;
+        }
+        Ok(())
+    }
+
+    pub fn with_candle_fields(mut self, candle_fields: CandleFieldSelection) -> Self {
+        self.candle_fields = candle_fields;
+        self
+    }
+
+    /// 全コレクション名に namespace を付ける (None なら従来通り)
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Result<Self> {
+        if let Some(namespace) = namespace.as_deref() {
+            validate_namespace(namespace)?;
+            tracing::info!("Using collection namespace: {}", namespace);
+        }
+        self.namespace = namespace;
+        Ok(self)
+    }
+
+    /// symbol ごとの書き込みを追加のクラスタ・データベースにも振り分ける (MONGODB_URL がシャード 0)
+    /// 各シャードに schema.mongo.js を適用しておくこと. symbol を持たない ops_events などはシャード 0 に書き込む
+    pub async fn with_shards(mut self, shard_urls: &[String]) -> Result<Self> {
+        if self.is_dummy || shard_urls.is_empty() {
+            return Ok(self);
+        }
+        for url in shard_urls {
+            let database = connect_shard(url).await?;
+            tracing::info!("Connected shard {}: database={}", self.shards.len() + 1, database.name());
+            self.shards.push(database);
+        }
+        Ok(self)
+    }
+
+    /// 足を最大 batch_size 件ずつ, 最初の足から最大 batch_latency 待ってまとめて書き込む (MongoCandleSink が使う. 1 なら 1 件ずつ)
+    pub fn with_batching(mut self, batch_size: usize, batch_latency: Duration) -> Self {
+        self.batch_size = batch_size.max(1);
+        self.batch_latency = batch_latency.max(Duration::from_millis(1));
+        self
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn batch_latency(&self) -> Duration {
+        self.batch_latency
+    }
+
+    /// MongoDB に書き込むか (--update なしの表示だけなら false)
+    pub fn is_enabled(&self) -> bool {
+        !self.is_dummy
+    }
+
+    /// 直近の書き込みが成功したか (失敗していれば DB 障害中)
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    /// 足を書き込める (master.csv にある) symbol か
+    /// ない symbol の足は落とし, 書き込みのたびではなく symbol ごとに 1 回だけ警告する
+    fn is_keyed_symbol(&self, exchange: &str, symbol: &str, market_type: &crate::models::market_type::MarketType) -> bool {
+        let Err(e) = check_keyed_symbol(exchange, symbol, market_type) else {
+            return true;
+        };
+        let key = (exchange.to_string(), market_type.as_str().to_string(), symbol.to_string());
+        if self.unlisted.lock().unwrap().insert(key) {
+            tracing::warn!("{} (logged once per symbol)", e);
+        }
+        false
+    }
+
+    /// symbol の書き込み先 (None はシャード 0)
+    fn database_for(&self, symbol: Option<&str>) -> Option<&MongoDatabase> {
+        self.database_at(symbol.map_or(0, |symbol| shard_index(symbol, self.shards.len() + 1)))
+    }
+
+    /// シャード番号のデータベース (0 は MONGODB_URL)
+    fn database_at(&self, shard: usize) -> Option<&MongoDatabase> {
+        match shard {
+            0 => self.database.as_ref(),
+            index => self.shards.get(index - 1),
+        }
+    }
+
+    /// 足のコレクションがなければ時系列コレクション (timeField unixtime, metaField metadata) として作り,
+    /// (metadata.symbol, unixtime) の索引を張る. シャード・コレクションごとにプロセスで最初の書き込みの前に 1 回だけ行う
+    async fn ensure_candle_collection(&self, shard: usize, database: &MongoDatabase, collection_name: &str, period_seconds: i32) -> mongodb::error::Result<()> {
+        use mongodb::bson::{doc, Document};
+        use mongodb::error::ErrorKind;
+
+        let key = (shard, collection_name.to_string());
+        if self.ensured.lock().unwrap().contains(&key) {
+            return Ok(());
+        }
+        let exists = !database.list_collection_names().filter(doc! { "name": collection_name }).await?.is_empty();
+        if !exists {
+            let granularity = candle_granularity(period_seconds);
+            let options = TimeseriesOptions::builder()
+                .time_field("unixtime")
+                .meta_field("metadata".to_string())
+                .granularity(granularity.clone())
+                .build();
+            match database.create_collection(collection_name).timeseries(options).await {
+                Ok(()) => tracing::info!("Created time-series collection {}.{} (granularity {:?})", database.name(), collection_name, granularity),
+                // 他のプロセスが先に作った (NamespaceExists)
+                Err(e) if matches!(*e.kind, ErrorKind::Command(ref error) if error.code == 48) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        // 同じ索引が既にあれば何もしない
+        let index = IndexModel::builder().keys(doc! { "metadata.symbol": 1, "unixtime": 1 }).build();
+        database.collection::<Document>(collection_name).create_index(index).await?;
+        self.ensured.lock().unwrap().insert(key);
+        Ok(())
+    }
+
+    /// シャード番号と接続中の全てのデータベース
+    fn databases(&self) -> impl Iterator<Item = (usize, &MongoDatabase)> + '_ {
+        (0..=self.shards.len()).filter_map(|shard| self.database_at(shard).map(|database| (shard, database)))
+    }
+
+    /// 時間枠の足のコレクションの expireAfterSeconds を設定する (None なら外す). 全てのシャードに, なければ作ってから設定する
+    /// 時系列コレクションではバケットの全ての足が期限を過ぎてから消える
+    pub async fn set_candle_ttl(&self, period_seconds: i32, keep: Option<Duration>) -> Result<()> {
+        use mongodb::bson::{doc, Bson};
+
+        let collection_name = candle_collection_name(period_seconds)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported period: {} seconds", period_seconds))?;
+        let collection_name = namespaced_collection(self.namespace.as_deref(), &collection_name);
+        if self.is_dummy {
+            return Ok(());
+        }
+        let expire = keep.map_or(Bson::String("off".to_string()), |keep| Bson::Int64(keep.as_secs() as i64));
+        for (shard, database) in self.databases() {
+            self.ensure_candle_collection(shard, database, &collection_name, period_seconds).await?;
+            database.run_command(doc! { "collMod": collection_name.as_str(), "expireAfterSeconds": expire.clone() }).await?;
+            tracing::info!("Set expireAfterSeconds of {}.{} to {}", database.name(), collection_name, expire);
+        }
+        Ok(())
+    }
+
+    /// 時間枠の足のうち before より前のものを全てのシャードから消し, 消した数を返す (時系列コレクションでは MongoDB 7.0+)
+    pub async fn prune_candles(&self, period_seconds: i32, before: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+        use mongodb::bson::{doc, Document};
+
+        let collection_name = candle_collection_name(period_seconds)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported period: {} seconds", period_seconds))?;
+        let collection_name = namespaced_collection(self.namespace.as_deref(), &collection_name);
+        if self.is_dummy {
+            return Ok(0);
+        }
+        let filter = doc! { "unixtime": { "$lt": mongodb::bson::DateTime::from_millis(before.timestamp_millis()) } };
+        let mut deleted = 0;
+        for (_, database) in self.databases() {
+            deleted += database.collection::<Document>(&collection_name).delete_many(filter.clone()).await?.deleted_count;
+        }
+        Ok(deleted)
+    }
 
     pub async fn insert_trade_candle(&self, candle: &crate::models::trade_candle::TradeCandle) -> Result<()> {
-        use mongodb::bson::Document;
-        
-        // Time Series形式に変換
-        let doc = candle.to_timeseries_document();
+        // Time Series形式に変換 (時間枠ごとの保存フィールドを適用)
+        let doc = self.candle_fields.apply(candle.period_seconds, candle.to_timeseries_document());
         
         // コレクション名を決定
-        let collection_name = match candle.period_seconds {
-            1 => "candles_1s",
-            5 => "candles_5s",
-            10 => "candles_10s",
-            30 => "candles_30s",
-            60 => "candles_1m",
-            300 => "candles_5m",
-            900 => "candles_15m",
-            1800 => "candles_30m",
-            3600 => "candles_1h",
-            7200 => "candles_2h",
-            14400 => "candles_4h",
-            86400 => "candles_1d",
-            _ => {
-                return Err(anyhow::anyhow!("Unsupported period: {} seconds", candle.period_seconds));
+        let collection_name = candle_collection_name(candle.period_seconds)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported period: {} seconds", candle.period_seconds))?;
+        if !self.is_keyed_symbol(&candle.exchange, &candle.symbol, &candle.market_type) {
+            return Ok(());
+        }
+        // 同じ symbol・時刻の足 (訂正前の revision, 再送) は置き換える
+        let key = candle_key(&doc)?;
+        if !self.is_dummy {
+            let shard = shard_index(&candle.symbol, self.shards.len() + 1);
+            if let Some(database) = self.database_at(shard) {
+                let namespaced = namespaced_collection(self.namespace.as_deref(), &collection_name);
+                if let Err(e) = self.ensure_candle_collection(shard, database, &namespaced, candle.period_seconds).await {
+                    tracing::error!("Failed to create {}: {}", namespaced, e);
+                    self.record_write(&namespaced, Some(&e));
+                    return Err(e.into());
+                }
             }
-        };
+        }
+        self.write_document(Some(&candle.symbol), &collection_name, doc, Some(candle_filter(key))).await
+    }
+
+    /// 足をまとめて書き込む. シャード・コレクションごとに, 既にある足 (同じ symbol・時刻) を 1 回の find で調べ,
+    /// 新しい足は insert_many, 既にある足 (訂正された revision, 再送) は replace_one で置き換える
+    /// 一時的なエラーは待って再試行する (調べ直すので, 途中まで書けていても重複しない)
+    pub async fn insert_trade_candles(&self, candles: &[crate::models::trade_candle::TradeCandle]) -> Result<()> {
+        // (シャード, コレクション) -> (symbol_id, unixtime) -> ドキュメント (同じ足は後の revision を使う)
+        let mut groups: BTreeMap<(usize, String, i32), BTreeMap<(i32, i64), mongodb::bson::Document>> = BTreeMap::new();
+        for candle in candles {
+            let doc = self.candle_fields.apply(candle.period_seconds, candle.to_timeseries_document());
+            // 書けない足のためにまとめた他の足を落とさない
+            let Some(collection_name) = candle_collection_name(candle.period_seconds) else {
+                tracing::error!("Unsupported period: {} seconds ({})", candle.period_seconds, candle.symbol);
+                continue;
+            };
+            if !self.is_keyed_symbol(&candle.exchange, &candle.symbol, &candle.market_type) {
+                continue;
+            }
+            let key = match candle_key(&doc) {
+                Ok(key) => key,
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    continue;
+                }
+            };
+            let shard = shard_index(&candle.symbol, self.shards.len() + 1);
+            let collection_name = namespaced_collection(self.namespace.as_deref(), &collection_name);
+            groups.entry((shard, collection_name, candle.period_seconds)).or_default().insert(key, doc);
+        }
+        if self.is_dummy {
+            tracing::debug!("Dummy mode, skipping {} candles", candles.len());
+            return Ok(());
+        }
+        let mut result = Ok(());
+        for ((shard, collection_name, period_seconds), docs) in groups {
+            let Some(database) = self.database_at(shard) else {
+                continue;
+            };
+            let collection = database.collection::<mongodb::bson::Document>(&collection_name);
+            let mut delay = Duration::from_millis(500);
+            let mut attempt = 0;
+            let written = loop {
+                let written = match self.ensure_candle_collection(shard, database, &collection_name, period_seconds).await {
+                    Ok(()) => write_candle_batch(&collection, &docs).await,
+                    Err(e) => Err(e),
+                };
+                match written {
+                    Err(e) if is_transient(&e) && attempt < MAX_WRITE_RETRIES => {
+                        tracing::warn!("Failed to write {} candles to {}: {}; retrying in {:?}", docs.len(), collection_name, e, delay);
+                        tokio::time::sleep(delay).await;
+                        delay *= 2;
+                        attempt += 1;
+                    }
+                    written => break written,
+                }
+            };
+            match written {
+                Ok((inserted, replaced)) => {
+                    tracing::info!("Wrote {} candles to {} ({} inserted, {} replaced)", docs.len(), collection_name, inserted, replaced);
+                    self.record_write(&collection_name, None);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to write {} candles to {}: {}", docs.len(), collection_name, e);
+                    self.record_write(&collection_name, Some(&e));
+                    result = Err(e.into());
+                }
+            }
+        }
+        result
+    }
+
+    /// イベント種別ごとのコレクションに書き込む (約定はローソク足に集計して保存するため書き込まない)
+    pub async fn insert_event(&self, event: &crate::models::market_event::MarketEvent) -> Result<()> {
+        use crate::models::market_event::MarketEvent;
+        match event {
+            MarketEvent::Trade(_) => Ok(()),
+            MarketEvent::Candle(candle) => self.insert_trade_candle(candle).await,
+            MarketEvent::Quote(quote) => self.insert_quote(quote).await,
+            MarketEvent::Liquidation(liquidation) => self.insert_liquidation(liquidation).await,
+            MarketEvent::MarkPrice(mark_price) => self.insert_mark_price(mark_price).await,
+            MarketEvent::BlockTrade(block_trade) => self.insert_block_trade(block_trade).await,
+            MarketEvent::Bar(bar) => self.insert_bar(bar).await,
+            MarketEvent::ImbalanceBar(bar) => self.insert_imbalance_bar(bar).await,
+            MarketEvent::Brick(brick) => self.insert_brick(brick).await,
+            MarketEvent::HeikinAshi(candle) => self.insert_heikin_ashi(candle).await,
+        }
+    }
+
+    pub async fn insert_quote(&self, quote: &crate::models::quote::Quote) -> Result<()> {
+        self.insert_document(Some(&quote.symbol), "quotes", quote.to_timeseries_document()).await
+    }
+
+    pub async fn insert_liquidation(&self, liquidation: &crate::models::liquidation::Liquidation) -> Result<()> {
+        self.insert_document(Some(&liquidation.symbol), "liquidations", liquidation.to_timeseries_document()).await
+    }
+
+    pub async fn insert_mark_price(&self, mark_price: &crate::models::mark_price::MarkPrice) -> Result<()> {
+        self.insert_document(Some(&mark_price.symbol), "mark_prices", mark_price.to_timeseries_document()).await
+    }
+
+    pub async fn insert_block_trade(&self, block_trade: &crate::models::block_trade::BlockTrade) -> Result<()> {
+        self.insert_document(Some(&block_trade.symbol), "block_trades", block_trade.to_timeseries_document()).await
+    }
+
+    /// 約定駆動の足は種類と閾値ごとのコレクション (bars_tick_500 など) に書き込む
+    pub async fn insert_bar(&self, bar: &crate::models::bar::Bar) -> Result<()> {
+        self.insert_document(Some(&bar.symbol), &bar.bar_type.collection_name(), bar.to_timeseries_document()).await
+    }
+
+    pub async fn insert_imbalance_bar(&self, bar: &crate::models::imbalance_bar::ImbalanceBar) -> Result<()> {
+        self.insert_document(Some(&bar.symbol), &bar.kind.collection_name(), bar.to_timeseries_document()).await
+    }
+
+    /// ブリックは設定ごとのコレクション (renko_100, range_atr14 など) に書き込む
+    pub async fn insert_brick(&self, brick: &crate::models::brick::Brick) -> Result<()> {
+        self.insert_document(Some(&brick.symbol), brick.collection_name(), brick.to_timeseries_document()).await
+    }
+
+    /// 平均足は元の時間枠のコレクション名に ha_ を付けたコレクション (ha_candles_1m など) に書き込む
+    /// ローソク足と同じく symbol・時刻で置き換える
+    pub async fn insert_heikin_ashi(&self, candle: &crate::models::heikin_ashi::HeikinAshiCandle) -> Result<()> {
+        let collection_name = candle
+            .collection_name()
+            .ok_or_else(|| anyhow::anyhow!("Unsupported period: {} seconds", candle.period_seconds))?;
+        if !self.is_keyed_symbol(&candle.exchange, &candle.symbol, &candle.market_type) {
+            return Ok(());
+        }
+        let doc = candle.to_timeseries_document();
+        let key = candle_key(&doc)?;
+        self.write_document(Some(&candle.symbol), &collection_name, doc, Some(candle_filter(key))).await
+    }
+
+    /// 1 つの symbol の 1 分の約定 (utils::trade_blob のドキュメント) を trade_blobs に書き込む
+    pub async fn insert_trade_blob(&self, symbol: &str, doc: mongodb::bson::Document) -> Result<()> {
+        self.insert_document(Some(symbol), crate::utils::trade_blob::TRADE_BLOB_COLLECTION, doc).await
+    }
+
+    pub async fn insert_ops_event(&self, event: &crate::utils::ops_events::OpsEvent) -> Result<()> {
+        self.insert_document(None, "ops_events", event.to_document()).await
+    }
+
+    pub async fn insert_quality_report(&self, report: &crate::utils::quality::QualityReport) -> Result<()> {
+        self.insert_document(None, "quality_reports", report.to_document()?).await
+    }
+
+    #[cfg(feature = "execution")]
+    pub async fn insert_balance(&self, balance: &crate::execution::account::Balance) -> Result<()> {
+        self.insert_document(None, "balances", balance.to_timeseries_document()).await
+    }
+
+    #[cfg(feature = "execution")]
+    pub async fn insert_position(&self, position: &crate::execution::account::Position) -> Result<()> {
+        self.insert_document(None, "positions", position.to_timeseries_document()).await
+    }
+
+    async fn insert_document(&self, symbol: Option<&str>, collection_name: &str, doc: mongodb::bson::Document) -> Result<()> {
+        self.write_document(symbol, collection_name, doc, None).await
+    }
+
+    /// key があれば key に一致するドキュメントを置き換える (なければ挿入する)
+    async fn write_document(&self, symbol: Option<&str>, collection_name: &str, doc: mongodb::bson::Document, key: Option<mongodb::bson::Document>) -> Result<()> {
+        use mongodb::bson::Document;
+        
+        let collection_name = &namespaced_collection(self.namespace.as_deref(), collection_name);
         
         // 常にJSONを出力
         tracing::debug!("[DB-INSERT-{}] {}", collection_name, serde_json::to_string(&doc)?); 
         
         // リアル接続がある場合のみ実際に挿入
         if !self.is_dummy {
-            if let Some(ref database) = self.database {
+            if let Some(database) = self.database_for(symbol) {
                 let collection = database.collection::<Document>(collection_name);
-                tracing::debug!("Attempting to insert into MongoDB: database=trade, collection={}", collection_name);
-                match collection.insert_one(doc).await {
-                    Ok(result) => {
+                tracing::debug!("Attempting to insert into MongoDB: database={}, collection={}", database.name(), collection_name);
+                let result = match key {
+                    Some(key) => collection.replace_one(key, doc).upsert(true).await.map(|result| {
+                        tracing::info!("Successfully upserted document (matched {}, upserted ID: {:?})", result.matched_count, result.upserted_id);
+                    }),
+                    None => collection.insert_one(doc).await.map(|result| {
                         tracing::info!("Successfully inserted document with ID: {:?}", result.inserted_id);
-                    }
+                    }),
+                };
+                match result {
+                    Ok(()) => self.record_write(collection_name, None),
                     Err(e) => {
                         tracing::error!("Failed to insert document: {}", e);
+                        self.record_write(collection_name, Some(&e));
                         return Err(e.into());
                     }
                 }
@@ -96,4 +578,54 @@ impl Database {
         
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// 書き込みの成否を記録し, 障害の開始・復旧を ops_events に記録する
+    fn record_write(&self, collection_name: &str, error: Option<&mongodb::error::Error>) {
+        match error {
+            None => {
+                if !self.healthy.swap(true, Ordering::Relaxed) {
+                    ops_events::record(OpsEventKind::DbRecovered, format!("insert into {} succeeded", collection_name));
+                }
+            }
+            Some(e) => {
+                if self.healthy.swap(false, Ordering::Relaxed) {
+                    ops_events::record(OpsEventKind::DbOutage, format!("insert into {} failed: {}", collection_name, e));
+                }
+            }
+        }
+    }
+}
+
+/// 既にある足を調べ, 新しい足を insert_many, 既にある足を replace_one で書き込み, (挿入, 置換) の件数を返す
+async fn write_candle_batch(
+    collection: &mongodb::Collection<mongodb::bson::Document>,
+    docs: &BTreeMap<(i32, i64), mongodb::bson::Document>,
+) -> mongodb::error::Result<(usize, usize)> {
+    use futures::TryStreamExt;
+    use mongodb::bson::{doc, DateTime};
+
+    let symbols: BTreeSet<i32> = docs.keys().map(|(symbol, _)| *symbol).collect();
+    let (first, last) = docs.keys().fold((i64::MAX, i64::MIN), |(first, last), (_, time)| (first.min(*time), last.max(*time)));
+    let filter = doc! {
+        "metadata.symbol": { "$in": symbols.into_iter().collect::<Vec<_>>() },
+        "unixtime": { "$gte": DateTime::from_millis(first), "$lte": DateTime::from_millis(last) },
+    };
+    let mut cursor = collection.find(filter).projection(doc! { "_id": 0, "metadata.symbol": 1, "unixtime": 1 }).await?;
+    let mut existing = HashSet::new();
+    while let Some(found) = cursor.try_next().await? {
+        if let (Ok(metadata), Ok(unixtime)) = (found.get_document("metadata"), found.get_datetime("unixtime")) {
+            if let Ok(symbol) = metadata.get_i32("symbol") {
+                existing.insert((symbol, unixtime.timestamp_millis()));
+            }
+        }
+    }
+
+    let (replace, insert): (Vec<_>, Vec<_>) = docs.iter().partition(|(key, _)| existing.contains(*key));
+    if !insert.is_empty() {
+        collection.insert_many(insert.iter().map(|(_, doc)| (*doc).clone())).ordered(false).await?;
+    }
+    for (key, doc) in &replace {
+        collection.replace_one(candle_filter(**key), (*doc).clone()).upsert(true).await?;
+    }
+    Ok((insert.len(), replace.len()))
+}
@@ -1,13 +1,60 @@
+// optional collection namespace: --eval 'var NAMESPACE="team_a"; load(...)' creates team_a.candles_1s, ...
+const NS = (typeof NAMESPACE !== "undefined" && NAMESPACE) ? NAMESPACE + "." : "";
 // metadata: { ym: 202401, symbol: 1 } ym: year-month, symbol: symbol index reffered to master csv file.
-db.getSiblingDB("trade").createCollection("candles_1s",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
-db.getSiblingDB("trade").createCollection("candles_5s",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
-db.getSiblingDB("trade").createCollection("candles_10s", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
-db.getSiblingDB("trade").createCollection("candles_60s", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// other timeframes go to candles_{N}{s|m|h|d} in the largest whole unit (90 -> candles_90s, 14400 -> candles_4h); collectors with --update create missing candles_* the same way (granularity seconds up to 1m, minutes up to 1h, hours above) with the index below
+db.getSiblingDB("trade").createCollection(NS + "candles_1s",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "candles_5s",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "candles_10s", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "candles_1m",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// candles are upserted by (metadata.symbol, unixtime) so corrections (revision > 0) and replays replace the stored candle (updates on time series collections need MongoDB 7.0+)
+["candles_1s", "candles_5s", "candles_10s", "candles_1m"].forEach(name => db.getSiblingDB("trade").getCollection(NS + name).createIndex({ "metadata.symbol": 1, unixtime: 1 }))
+// metadata: { ym: 202401, symbol: 1 } same symbol index as candles
+db.getSiblingDB("trade").createCollection(NS + "quotes",      { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "liquidations", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "mark_prices",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// OTC/block prints, kept out of candles so they do not distort VWAP
+db.getSiblingDB("trade").createCollection(NS + "block_trades", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// event-driven bars (--bar-type tick:500,volume:10,...): one bars_{tick|volume|dollar}_{threshold} per configured type, unixtime is the closing trade time
+db.getSiblingDB("trade").createCollection(NS + "bars_tick_500",  { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "bars_volume_10", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// tick / volume imbalance bars (--imbalance-bar), with imbalance / threshold / expected_ticks at close
+db.getSiblingDB("trade").createCollection(NS + "imbalance_bars_tick",   { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "imbalance_bars_volume", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// Renko bricks / range bars (--renko renko:100,range:atr14): one renko_{size} / range_{size} per configured spec, with size / direction at close
+db.getSiblingDB("trade").createCollection(NS + "renko_100",   { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "range_atr14", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// Heikin-Ashi candles (--heikin-ashi 60,300): ha_ + the candle collection of the timeframe
+db.getSiblingDB("trade").createCollection(NS + "ha_candles_1m", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").createCollection(NS + "ha_candles_5m", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "minutes" }})
+// upserted by (metadata.symbol, unixtime) like the candles
+["ha_candles_1m", "ha_candles_5m"].forEach(name => db.getSiblingDB("trade").getCollection(NS + name).createIndex({ "metadata.symbol": 1, unixtime: 1 }))
+// raw trades (--db-trades): one document per symbol and minute, { count, codec: "zstd", data: binary columns }
+db.getSiblingDB("trade").createCollection(NS + "trade_blobs", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "minutes" }})
+db.getSiblingDB("trade").getCollection(NS + "trade_blobs").createIndex({ "metadata.symbol": 1, unixtime: 1 })
+// correlation --update: one document per pair and matrix, metadata: { ym, symbol_a, symbol_b, window (minutes), interval (seconds), returns (horizon, 0 = prices), method, half_life (seconds, method "ewma" only) }
+db.getSiblingDB("trade").createCollection(NS + "correlations", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").getCollection(NS + "correlations").createIndex({ "metadata.symbol_a": 1, "metadata.symbol_b": 1, unixtime: 1 })
+// correlation --beta --update: one document per symbol and interval, metadata: { ym, symbol, benchmark, window (minutes), interval (seconds), returns (horizon) }
+db.getSiblingDB("trade").createCollection(NS + "betas", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").getCollection(NS + "betas").createIndex({ "metadata.symbol": 1, "metadata.benchmark": 1, unixtime: 1 })
+// pairs --update: one document per z-score signal, metadata: { ym, symbol_a (symbol), symbol_b (hedge symbol), window (minutes), interval (seconds) }
+db.getSiblingDB("trade").createCollection(NS + "pair_signals", { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+db.getSiblingDB("trade").getCollection(NS + "pair_signals").createIndex({ "metadata.symbol_a": 1, "metadata.symbol_b": 1, unixtime: 1 })
+// daily feed quality report per exchange/market (regular collection)
+db.getSiblingDB("trade").createCollection(NS + "quality_reports")
+db.getSiblingDB("trade").getCollection(NS + "quality_reports").createIndex({ date: 1, exchange: 1, market_type: 1 })
+// connects / disconnects / subscribes / DB outages per collector process (regular collection)
+db.getSiblingDB("trade").createCollection(NS + "ops_events")
+db.getSiblingDB("trade").getCollection(NS + "ops_events").createIndex({ unixtime: 1, exchange: 1, market_type: 1 })
+// metadata: { ym: 202401, exchange: "bybit", market_type: "linear", asset: "USDT" }
+db.getSiblingDB("trade").createCollection(NS + "balances",    { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
+// metadata: { ym: 202401, symbol: 1 } same symbol index as candles
+db.getSiblingDB("trade").createCollection(NS + "positions",   { timeseries: {timeField: "unixtime", metaField: "metadata", granularity: "seconds" }})
 
 // db.candles_5s.deleteMany({})
 // db.candles_5s.drop()
 
-sh.shardCollection("trade.candles_1s",  {"metadata": 1});
-sh.shardCollection("trade.candles_5s",  {"metadata": 1});
-sh.shardCollection("trade.candles_10s", {"metadata": 1});
-sh.shardCollection("trade.candles_60s", {"metadata": 1});
\ No newline at end of file
+sh.shardCollection("trade." + NS + "candles_1s",  {"metadata": 1});
+sh.shardCollection("trade." + NS + "candles_5s",  {"metadata": 1});
+sh.shardCollection("trade." + NS + "candles_10s", {"metadata": 1});
+sh.shardCollection("trade." + NS + "candles_1m",  {"metadata": 1});
\ No newline at end of file
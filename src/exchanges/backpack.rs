@@ -0,0 +1,274 @@
+use crate::models::{trade::{Trade, Side}, market_event::MarketEvent, market_type::MarketType, ExchangeClient};
+use crate::utils::keepalive::{self, Keepalive, KeepaliveAction, WatchdogTimeout};
+use crate::utils::endpoint::Endpoint;
+use crate::utils::ops_events::{self, OpsEventKind};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{error, info};
+#[cfg(feature = "chaos")]
+use crate::utils::chaos::{ChaosConfig, ChaosInjector};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const PING_SECONDS: u64 = 30; // サーバーの ping には pong を返す. こちらからも ping を送って pong を受信させる (watchdog)
+
+#[derive(Debug, Serialize)]
+struct BackpackSubscribe {
+    method: String,
+    params: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackpackMessage {
+    stream: String,
+    data: BackpackTradeData,
+}
+
+#[derive(Debug, Deserialize)]
+struct BackpackTradeData {
+    #[serde(rename = "e")]
+    event_type: String,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "m")]
+    is_buyer_maker: bool,
+    #[serde(rename = "T")]
+    timestamp: i64,  // マイクロ秒 (エンジン時刻)
+    #[serde(rename = "E")]
+    event_time: Option<i64>,  // マイクロ秒
+    #[serde(rename = "t")]
+    trade_id: u64,
+}
+
+pub struct BackpackClient {
+    ws_stream: Option<WsStream>,
+    event_sender: mpsc::Sender<MarketEvent>,
+    trade_counter: AtomicU64,
+    market_type: Option<MarketType>,
+    raw_freq: u32,
+    watchdog: Option<Duration>,
+    endpoint: Endpoint,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosInjector>,
+}
+
+impl BackpackClient {
+    pub fn new(event_sender: mpsc::Sender<MarketEvent>, raw_freq: u32) -> Self {
+        Self {
+            ws_stream: None,
+            event_sender,
+            trade_counter: AtomicU64::new(0),
+            market_type: None,
+            raw_freq,
+            watchdog: None,
+            endpoint: Endpoint::new(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// timeout の間フレームを受信しなければ切断し, WatchdogTimeout を返す (呼び出し側で再接続する)
+    pub fn with_watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// 既定の接続先の代わりに別ホスト・プロキシを使う (地域制限の回避)
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, config: ChaosConfig) -> Self {
+        self.chaos = Some(ChaosInjector::new(config));
+        self
+    }
+
+    fn get_websocket_url(&self) -> &'static str {
+        "wss://ws.backpack.exchange"
+    }
+
+    /// テキストフレームを Trade に正規化する (約定以外のフレームは空で返す)
+    pub fn parse_trades(text: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+
+        if let Ok(message) = serde_json::from_str::<BackpackMessage>(text) {
+            if message.stream.starts_with("trade.") && message.data.event_type == "trade" {
+                let data = message.data;
+                let price = data.price.parse::<f64>().unwrap_or(0.0);
+                let quantity = data.quantity.parse::<f64>().unwrap_or(0.0);
+                // 買い手がメイカー = テイカーは売り
+                let side = if data.is_buyer_maker {
+                    Side::Sell
+                } else {
+                    Side::Buy
+                };
+
+                let timestamp = DateTime::from_timestamp_micros(data.timestamp)
+                    .unwrap_or_else(Utc::now);
+
+                trades.push(Trade::new(
+                    "backpack".to_string(),
+                    market_type.clone(),
+                    data.symbol,
+                    data.trade_id.to_string(),
+                    price,
+                    quantity,
+                    side,
+                    timestamp,
+                ).with_gateway_timestamp(data.event_time.and_then(DateTime::from_timestamp_micros)));
+            }
+        }
+        Ok(trades)
+    }
+
+    async fn process_message(
+        msg: Message,
+        event_sender: &mpsc::Sender<MarketEvent>,
+        _trade_counter: &AtomicU64,
+        market_type: &MarketType,
+    ) -> Result<()> {
+        if let Message::Text(text) = msg {
+            for trade in Self::parse_trades(&text, market_type)? {
+                if let Err(e) = event_sender.send(MarketEvent::Trade(trade)).await {
+                    error!("Failed to send trade: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExchangeClient for BackpackClient {
+    async fn connect(&mut self, market_type: MarketType) -> Result<()> {
+        let url = self.endpoint.ws_url(self.get_websocket_url());
+        info!("Connecting to Backpack {} WebSocket: {}", market_type.as_str().to_uppercase(), url);
+
+        let ws_stream = self.endpoint.connect(&url).await?;
+        self.ws_stream = Some(ws_stream);
+        self.market_type = Some(market_type);
+
+        info!("Connected to Backpack {} WebSocket", self.market_type.as_ref().unwrap().as_str().to_uppercase());
+        ops_events::record(OpsEventKind::Connect, url.to_string());
+        Ok(())
+    }
+
+    async fn subscribe_trades(&mut self, symbols: Vec<String>) -> Result<()> {
+        if let Some(ws_stream) = &mut self.ws_stream {
+            let subscribe_msg = BackpackSubscribe {
+                method: "SUBSCRIBE".to_string(),
+                params: symbols
+                    .iter()
+                    .map(|symbol| format!("trade.{}", symbol))
+                    .collect(),
+            };
+
+            let msg = Message::Text(serde_json::to_string(&subscribe_msg)?);
+            ws_stream.send(msg).await?;
+
+            info!("Subscribed to Backpack {} trades", self.market_type.as_ref().unwrap().as_str().to_uppercase());
+            ops_events::record(OpsEventKind::Subscribe, symbols.join(","));
+
+            // メッセージ処理ループ
+            let mut disconnect_reason = "stream closed".to_string();
+            let mut keepalive = Keepalive::new()
+                .with_ping(Duration::from_secs(PING_SECONDS), Message::Ping(Vec::new()))
+                .with_watchdog(self.watchdog);
+            let mut result = Ok(());
+            loop {
+                let msg = tokio::select! {
+                    msg = ws_stream.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                    action = keepalive.next() => match action {
+                        KeepaliveAction::Ping(ping) => match ws_stream.send(ping).await {
+                            Ok(()) => continue,
+                            Err(e) => {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        },
+                        KeepaliveAction::Timeout(timeout) => {
+                            let timeout = WatchdogTimeout(timeout);
+                            error!("Backpack {}", timeout);
+                            disconnect_reason = timeout.to_string();
+                            result = Err(timeout.into());
+                            break;
+                        }
+                    },
+                };
+                match msg {
+                    Ok(msg) => {
+                        keepalive.received();
+                        if let Some(pong) = keepalive::pong(&msg) {
+                            if let Err(e) = ws_stream.send(pong).await {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        }
+                        #[cfg(feature = "chaos")]
+                        let msg = match self.chaos.as_mut() {
+                            Some(chaos) => match chaos.apply(msg).await {
+                                Ok(msg) => msg,
+                                Err(e) => {
+                                    error!("WebSocket error: {}", e);
+                                    disconnect_reason = format!("WebSocket error: {}", e);
+                                    break;
+                                }
+                            },
+                            None => msg,
+                        };
+                        let count = self.trade_counter.fetch_add(1, Ordering::Relaxed);
+                        // 1件目、(raw_freq+1)件目、(raw_freq*2+1)件目...を表示
+                        if count % (self.raw_freq as u64) == 1 {
+                            tracing::debug!("Raw message: {:?}", msg);
+                        }
+                        // カウンターを定期的にリセット (100万件毎)
+                        if count >= 1_000_000 {
+                            self.trade_counter.store(0, Ordering::Relaxed);
+                        }
+                        if let Err(e) = Self::process_message(msg, &self.event_sender, &self.trade_counter, self.market_type.as_ref().unwrap()).await {
+                            error!("Error processing message: {}", e);
+                            crate::utils::quality::record_parse_failure("backpack");
+                        }
+                    }
+                    Err(e) => {
+                        error!("WebSocket error: {}", e);
+                        disconnect_reason = format!("WebSocket error: {}", e);
+                        break;
+                    }
+                }
+            }
+            ops_events::record(OpsEventKind::Disconnect, disconnect_reason);
+            return result;
+        }
+
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if let Some(mut ws_stream) = self.ws_stream.take() {
+            ws_stream.close(None).await?;
+            info!("Disconnected from Backpack {} WebSocket",
+                  self.market_type.as_ref().map_or("Unknown", |mt| mt.as_str()).to_uppercase());
+        }
+        Ok(())
+    }
+}
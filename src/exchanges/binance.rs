@@ -1,17 +1,27 @@
-use crate::models::{trade::{Trade, Side}, market_type::MarketType, ExchangeClient};
+use crate::models::{trade::{Trade, Side}, quote::Quote, liquidation::Liquidation, mark_price::MarkPrice, market_event::MarketEvent, market_type::MarketType, ExchangeClient};
+use crate::utils::backfill::{LastTrades, SequenceGap, MAX_BACKFILL_PAGES};
+use crate::utils::keepalive::{self, Keepalive, KeepaliveAction, WatchdogTimeout};
+use crate::utils::endpoint::Endpoint;
+use crate::utils::history;
+use crate::utils::ops_events::{self, OpsEventKind};
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
-use futures_util::StreamExt;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures_util::{SinkExt, StreamExt};
 use serde::Deserialize;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use tracing::{error, info};
+#[cfg(feature = "chaos")]
+use crate::utils::chaos::{ChaosConfig, ChaosInjector};
 
 type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+const PING_SECONDS: u64 = 30; // サーバーの ping には pong を返す. 先物の ping は3分間隔のため, こちらからも ping を送って pong を受信させる (watchdog)
+
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum BinanceMessage {
@@ -42,40 +52,220 @@ struct BinanceAggTradeData {
     is_buyer_maker: bool,
     #[serde(rename = "T")]
     timestamp: i64,
+    #[serde(rename = "E")]
+    event_time: Option<i64>,
+    #[serde(rename = "a")]
+    trade_id: u64,
+}
+
+/// GET aggTrades (古い順. ストリームの aggTrade から e/E/s を除いた形)
+#[derive(Debug, Deserialize)]
+struct BinanceRestAggTrade {
     #[serde(rename = "a")]
     trade_id: u64,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    quantity: String,
+    #[serde(rename = "T")]
+    timestamp: i64,
+    #[serde(rename = "m")]
+    is_buyer_maker: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BinanceQuoteMessage {
+    Stream { data: BinanceBookTickerData },
+    Direct(BinanceBookTickerData),
+}
+
+// Spot の bookTicker には e/E/T がない
+#[derive(Debug, Deserialize)]
+struct BinanceBookTickerData {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid_price: String,
+    #[serde(rename = "B")]
+    bid_size: String,
+    #[serde(rename = "a")]
+    ask_price: String,
+    #[serde(rename = "A")]
+    ask_size: String,
+    #[serde(rename = "T")]
+    transaction_time: Option<i64>,
+    #[serde(rename = "E")]
+    event_time: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BinanceLiquidationMessage {
+    Stream { data: BinanceForceOrderData },
+    Direct(BinanceForceOrderData),
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceForceOrderData {
+    #[serde(rename = "e")]
+    event_type: String,
+    #[serde(rename = "o")]
+    order: BinanceForceOrder,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceForceOrder {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "S")]
+    side: String,  // 清算注文の方向 (SELL: ロングの清算)
+    #[serde(rename = "ap")]
+    average_price: String,
+    #[serde(rename = "z")]
+    filled_quantity: String,
+    #[serde(rename = "T")]
+    timestamp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum BinanceMarkPriceMessage {
+    Stream { data: BinanceMarkPriceData },
+    Direct(BinanceMarkPriceData),
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceMarkPriceData {
+    #[serde(rename = "e")]
+    event_type: String,
+    #[serde(rename = "E")]
+    event_time: i64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    mark_price: String,
+    #[serde(rename = "i")]
+    index_price: Option<String>,
+    #[serde(rename = "r")]
+    funding_rate: Option<String>,
 }
 
 pub struct BinanceClient {
     ws_stream: Option<WsStream>,
-    trade_sender: mpsc::Sender<Trade>,
+    event_sender: mpsc::Sender<MarketEvent>,
     trade_counter: AtomicU64,
     market_type: Option<MarketType>,
     raw_freq: u32,
+    quotes: bool,
+    liquidations: bool,
+    mark_prices: bool,
+    testnet: bool,
+    last_trades: LastTrades,
+    watchdog: Option<Duration>,
+    endpoint: Endpoint,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosInjector>,
 }
 
 impl BinanceClient {
-    pub fn new(trade_sender: mpsc::Sender<Trade>, raw_freq: u32) -> Self {
+    pub fn new(event_sender: mpsc::Sender<MarketEvent>, raw_freq: u32) -> Self {
         Self {
             ws_stream: None,
-            trade_sender,
+            event_sender,
             trade_counter: AtomicU64::new(0),
             market_type: None,
             raw_freq,
+            quotes: false,
+            liquidations: false,
+            mark_prices: false,
+            testnet: false,
+            last_trades: LastTrades::new(),
+            watchdog: None,
+            endpoint: Endpoint::new(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// {symbol}@bookTicker も購読し, 最良気配の更新ごとに Quote を送信する
+    pub fn with_quotes(mut self) -> Self {
+        self.quotes = true;
+        self
+    }
+
+    /// {symbol}@forceOrder も購読し, 強制決済ごとに Liquidation を送信する (先物のみ)
+    pub fn with_liquidations(mut self) -> Self {
+        self.liquidations = true;
+        self
+    }
+
+    /// {symbol}@markPrice@1s も購読し, 1秒ごとに MarkPrice を送信する (先物のみ)
+    pub fn with_mark_prices(mut self) -> Self {
+        self.mark_prices = true;
+        self
+    }
+
+    /// テストネット (Spot: testnet.binance.vision, 先物: binancefuture.com) に接続する
+    pub fn with_testnet(mut self, testnet: bool) -> Self {
+        self.testnet = testnet;
+        self
+    }
+
+    /// timeout の間フレームを受信しなければ切断し, WatchdogTimeout を返す (呼び出し側で再接続する)
+    pub fn with_watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// 既定の接続先の代わりに別ホスト・プロキシを使う (地域制限の回避)
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, config: ChaosConfig) -> Self {
+        self.chaos = Some(ChaosInjector::new(config));
+        self
+    }
+
+    /// 1 接続で購読できるストリーム数の上限 (Spot 1024, 先物 200)
+    pub fn max_streams_per_connection(market_type: &MarketType) -> usize {
+        match market_type {
+            MarketType::Spot => 1024,
+            MarketType::Linear | MarketType::Inverse => 200,
         }
     }
 
+    /// 1 symbol あたりに購読するストリーム数 (aggTrade と有効にしたストリーム)
+    pub fn streams_per_symbol(&self) -> usize {
+        1 + [self.quotes, self.liquidations, self.mark_prices].iter().filter(|&&enabled| enabled).count()
+    }
+
     fn build_websocket_url(&self, market_type: &MarketType, symbols: &[String]) -> String {
-        let base_url = match market_type {
-            MarketType::Spot => "wss://stream.binance.com:9443",
-            MarketType::Linear => "wss://fstream.binance.com",
-            MarketType::Inverse => "wss://dstream.binance.com",
+        let base_url = match (market_type, self.testnet) {
+            (MarketType::Spot, false) => "wss://stream.binance.com:9443",
+            (MarketType::Linear, false) => "wss://fstream.binance.com",
+            (MarketType::Inverse, false) => "wss://dstream.binance.com",
+            (MarketType::Spot, true) => "wss://stream.testnet.binance.vision",
+            (MarketType::Linear, true) => "wss://fstream.binancefuture.com",
+            (MarketType::Inverse, true) => "wss://dstream.binancefuture.com",
         };
         
-        let streams: Vec<String> = symbols
+        let mut streams: Vec<String> = symbols
             .iter()
             .map(|s| format!("{}@aggTrade", s.to_lowercase()))
             .collect();
+        if self.quotes {
+            streams.extend(symbols.iter().map(|s| format!("{}@bookTicker", s.to_lowercase())));
+        }
+        if self.liquidations {
+            streams.extend(symbols.iter().map(|s| format!("{}@forceOrder", s.to_lowercase())));
+        }
+        if self.mark_prices {
+            streams.extend(symbols.iter().map(|s| format!("{}@markPrice@1s", s.to_lowercase())));
+        }
         
         if streams.len() == 1 {
             format!("{}/ws/{}", base_url, streams[0])
@@ -84,46 +274,343 @@ impl BinanceClient {
         }
     }
 
+    fn get_agg_trades_url(market_type: &MarketType, testnet: bool) -> &'static str {
+        match (market_type, testnet) {
+            (MarketType::Spot, false) => "https://api.binance.com/api/v3/aggTrades",
+            (MarketType::Linear, false) => "https://fapi.binance.com/fapi/v1/aggTrades",
+            (MarketType::Inverse, false) => "https://dapi.binance.com/dapi/v1/aggTrades",
+            (MarketType::Spot, true) => "https://testnet.binance.vision/api/v3/aggTrades",
+            (MarketType::Linear, true) => "https://testnet.binancefuture.com/fapi/v1/aggTrades",
+            (MarketType::Inverse, true) => "https://testnet.binancefuture.com/dapi/v1/aggTrades",
+        }
+    }
+
+    /// テキストフレームを Trade に正規化する (約定以外のフレームは空で返す)
+    pub fn parse_trades(text: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+        
+        if let Ok(message) = serde_json::from_str::<BinanceMessage>(text) {
+            let data = match message {
+                BinanceMessage::Stream(stream_msg) => stream_msg.data,
+                BinanceMessage::Direct(direct_data) => direct_data,
+            };
+            
+            if data.event_type == "aggTrade" {
+                let price = data.price.parse::<f64>().unwrap_or(0.0);
+                let quantity = data.quantity.parse::<f64>().unwrap_or(0.0);
+                // Binanceでは is_buyer_maker が true なら買い、false なら売り
+                let side = if data.is_buyer_maker {
+                    Side::Buy   // 買い手がメイカー = 買い約定 = Ask側
+                } else {
+                    Side::Sell  // 買い手がテイカー = 売り約定 = Bid側
+                };
+                
+                let timestamp = DateTime::from_timestamp_millis(data.timestamp)
+                    .unwrap_or_else(Utc::now);
+                
+                trades.push(Trade::new(
+                    "binance".to_string(),
+                    market_type.clone(),
+                    data.symbol,
+                    data.trade_id.to_string(),
+                    price,
+                    quantity,
+                    side,
+                    timestamp,
+                ).with_gateway_timestamp(data.event_time.and_then(DateTime::from_timestamp_millis)));
+            }
+        }
+        Ok(trades)
+    }
+
+    /// aggTrades の応答を Trade に正規化する (応答に symbol がないため指定する)
+    pub fn parse_agg_trades(text: &str, symbol: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+        for data in serde_json::from_str::<Vec<BinanceRestAggTrade>>(text)? {
+            // ストリームの aggTrade と同じ向きにする
+            let side = if data.is_buyer_maker { Side::Buy } else { Side::Sell };
+            trades.push(Trade::new(
+                "binance".to_string(),
+                market_type.clone(),
+                symbol.to_string(),
+                data.trade_id.to_string(),
+                data.price.parse::<f64>()?,
+                data.quantity.parse::<f64>()?,
+                side,
+                DateTime::from_timestamp_millis(data.timestamp).unwrap_or_else(Utc::now),
+            ));
+        }
+        Ok(trades)
+    }
+
+    async fn fetch_agg_trades(http: &reqwest::Client, url: &str, symbol: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let text = http.get(url).send().await?.error_for_status()?.text().await?;
+        Self::parse_agg_trades(&text, symbol, market_type)
+    }
+
+    /// 再接続時に aggTrades で最後の約定の次の ID から切断中の約定を取得し, ライブの約定より先に送る
+    /// 1000 件ずつ MAX_BACKFILL_PAGES まで遡る
+    async fn backfill(
+        symbols: &[String],
+        market_type: &MarketType,
+        testnet: bool,
+        endpoint: &Endpoint,
+        last_trades: &mut LastTrades,
+        event_sender: &mpsc::Sender<MarketEvent>,
+    ) {
+        for symbol in symbols {
+            let Some(last) = last_trades.get(symbol).cloned() else { continue };
+            let Ok(last_id) = last.trade_id.parse::<u64>() else { continue };
+            let mut from_id = last_id + 1;
+            let mut count = 0;
+            for page in 0..MAX_BACKFILL_PAGES {
+                let url = format!("{}?symbol={}&fromId={}&limit=1000", endpoint.rest_url(Self::get_agg_trades_url(market_type, testnet)), symbol, from_id);
+                let fetched = match Self::fetch_agg_trades(endpoint.http(), &url, symbol, market_type).await {
+                    Ok(fetched) => fetched,
+                    Err(e) => {
+                        error!("Binance backfill of {} failed: {}", symbol, e);
+                        break;
+                    }
+                };
+                let full = fetched.len() == 1000;
+                if let Some(max_id) = fetched.iter().filter_map(|t| t.trade_id.parse::<u64>().ok()).max() {
+                    from_id = from_id.max(max_id + 1);
+                }
+                for trade in last_trades.missed(symbol, fetched) {
+                    last_trades.record(&trade);
+                    count += 1;
+                    if let Err(e) = event_sender.send(MarketEvent::Trade(trade)).await {
+                        error!("Failed to send backfilled trade: {}", e);
+                    }
+                }
+                if !full {
+                    break;
+                }
+                if page + 1 == MAX_BACKFILL_PAGES {
+                    tracing::warn!("Binance backfill of {} stopped after {} pages; later trades before the live stream are missing", symbol, MAX_BACKFILL_PAGES);
+                }
+            }
+            info!("Backfilled {} Binance trades of {} since {}", count, symbol, last.timestamp);
+            ops_events::record(OpsEventKind::Backfill, format!("{}: {} trades since {}", symbol, count, last.timestamp));
+        }
+    }
+
+    fn get_archive_url(market_type: &MarketType, symbol: &str, date: NaiveDate) -> String {
+        let market = match market_type {
+            MarketType::Spot => "spot",
+            MarketType::Linear => "futures/um",
+            MarketType::Inverse => "futures/cm",
+        };
+        format!("https://data.binance.vision/data/{}/daily/aggTrades/{}/{}-aggTrades-{}.zip", market, symbol, symbol, date.format("%Y-%m-%d"))
+    }
+
+    /// data.binance.vision の aggTrades CSV を Trade に正規化する
+    /// (agg_trade_id,price,quantity,first_trade_id,last_trade_id,transact_time,is_buyer_maker[,is_best_match]. 先物はヘッダー行があり, 現物の transact_time は 2025 年からマイクロ秒)
+    pub fn parse_agg_trades_csv(text: &str, symbol: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.trim().split(',').collect();
+            if fields.len() < 7 {
+                continue;
+            }
+            // ヘッダー行は飛ばす
+            let Ok(trade_id) = fields[0].parse::<u64>() else { continue };
+            let time = fields[5].parse::<i64>()?;
+            let timestamp = if time >= 100_000_000_000_000 {
+                DateTime::from_timestamp_micros(time)
+            } else {
+                DateTime::from_timestamp_millis(time)
+            }
+            .ok_or_else(|| anyhow::anyhow!("Invalid transact_time: {}", time))?;
+            // ストリームの aggTrade と同じ向きにする
+            let side = if fields[6].eq_ignore_ascii_case("true") { Side::Buy } else { Side::Sell };
+            trades.push(Trade::new(
+                "binance".to_string(),
+                market_type.clone(),
+                symbol.to_string(),
+                trade_id.to_string(),
+                fields[1].parse::<f64>()?,
+                fields[2].parse::<f64>()?,
+                side,
+                timestamp,
+            ));
+        }
+        Ok(trades)
+    }
+
+    /// 1 日分の aggTrades をアーカイブから取得する (まだ公開されていなければ None)
+    pub async fn fetch_archive_trades(endpoint: &Endpoint, market_type: &MarketType, symbol: &str, date: NaiveDate) -> Result<Option<Vec<Trade>>> {
+        let Some(bytes) = history::download(endpoint.http(), &Self::get_archive_url(market_type, symbol, date)).await? else {
+            return Ok(None);
+        };
+        Ok(Some(Self::parse_agg_trades_csv(&history::unzip(&bytes)?, symbol, market_type)?))
+    }
+
+    /// [start, end) の aggTrades を REST で取得する
+    /// startTime / endTime は 1 時間以内しか指定できないので, 最初の約定 ID を 1 時間ずつ探してから fromId で 1000 件ずつ送る
+    pub async fn fetch_rest_trades(
+        endpoint: &Endpoint,
+        market_type: &MarketType,
+        symbol: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        request_interval: Duration,
+    ) -> Result<Vec<Trade>> {
+        let url = endpoint.rest_url(Self::get_agg_trades_url(market_type, false));
+        let mut from_id = None;
+        let mut cursor = start;
+        while from_id.is_none() && cursor < end {
+            let to = (cursor + chrono::Duration::hours(1)).min(end);
+            let page = format!("{}?symbol={}&startTime={}&endTime={}&limit=1", url, symbol, cursor.timestamp_millis(), to.timestamp_millis() - 1);
+            from_id = Self::fetch_agg_trades(endpoint.http(), &page, symbol, market_type)
+                .await?
+                .first()
+                .and_then(|trade| trade.trade_id.parse::<u64>().ok());
+            cursor = to;
+            tokio::time::sleep(request_interval).await;
+        }
+        let mut trades = Vec::new();
+        while let Some(id) = from_id {
+            let page = format!("{}?symbol={}&fromId={}&limit=1000", url, symbol, id);
+            let fetched = Self::fetch_agg_trades(endpoint.http(), &page, symbol, market_type).await?;
+            let full = fetched.len() == 1000;
+            from_id = fetched.last().and_then(|trade| trade.trade_id.parse::<u64>().ok()).map(|id| id + 1).filter(|_| full);
+            for trade in fetched {
+                if trade.timestamp >= end {
+                    from_id = None;
+                    break;
+                }
+                trades.push(trade);
+            }
+            tokio::time::sleep(request_interval).await;
+        }
+        Ok(trades)
+    }
+
+    /// bookTicker フレームを Quote に正規化する (bookTicker 以外のフレームは None)
+    pub fn parse_quote(text: &str, market_type: &MarketType) -> Result<Option<Quote>> {
+        let Ok(message) = serde_json::from_str::<BinanceQuoteMessage>(text) else {
+            return Ok(None);
+        };
+        let data = match message {
+            BinanceQuoteMessage::Stream { data } => data,
+            BinanceQuoteMessage::Direct(data) => data,
+        };
+
+        let timestamp = data
+            .transaction_time
+            .or(data.event_time)
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or_else(Utc::now);
+
+        Ok(Some(Quote::new(
+            "binance".to_string(),
+            market_type.clone(),
+            data.symbol,
+            data.bid_price.parse::<f64>()?,
+            data.bid_size.parse::<f64>()?,
+            data.ask_price.parse::<f64>()?,
+            data.ask_size.parse::<f64>()?,
+            timestamp,
+        )))
+    }
+
+    /// forceOrder フレームを Liquidation に正規化する (強制決済以外のフレームは空で返す)
+    pub fn parse_liquidations(text: &str, market_type: &MarketType) -> Result<Vec<Liquidation>> {
+        let Ok(message) = serde_json::from_str::<BinanceLiquidationMessage>(text) else {
+            return Ok(Vec::new());
+        };
+        let data = match message {
+            BinanceLiquidationMessage::Stream { data } => data,
+            BinanceLiquidationMessage::Direct(data) => data,
+        };
+        if data.event_type != "forceOrder" {
+            return Ok(Vec::new());
+        }
+
+        let order = data.order;
+        let side = match order.side.as_str() {
+            "BUY" => Side::Buy,
+            _ => Side::Sell,
+        };
+        Ok(vec![Liquidation::new(
+            "binance".to_string(),
+            market_type.clone(),
+            order.symbol,
+            side,
+            order.average_price.parse::<f64>()?,
+            order.filled_quantity.parse::<f64>()?,
+            DateTime::from_timestamp_millis(order.timestamp).unwrap_or_else(Utc::now),
+        )])
+    }
+
+    /// markPriceUpdate フレームを MarkPrice に正規化する (それ以外のフレームは None)
+    pub fn parse_mark_price(text: &str, market_type: &MarketType) -> Result<Option<MarkPrice>> {
+        let Ok(message) = serde_json::from_str::<BinanceMarkPriceMessage>(text) else {
+            return Ok(None);
+        };
+        let data = match message {
+            BinanceMarkPriceMessage::Stream { data } => data,
+            BinanceMarkPriceMessage::Direct(data) => data,
+        };
+        if data.event_type != "markPriceUpdate" {
+            return Ok(None);
+        }
+
+        let mut mark_price = MarkPrice::new(
+            "binance".to_string(),
+            market_type.clone(),
+            data.symbol,
+            data.mark_price.parse::<f64>()?,
+            data.index_price.as_deref().map(str::parse::<f64>).transpose()?,
+            DateTime::from_timestamp_millis(data.event_time).unwrap_or_else(Utc::now),
+        );
+        // 受け渡し期日のある Coin-M 先物では資金調達率は空文字
+        mark_price.funding_rate = data.funding_rate.as_deref().filter(|r| !r.is_empty()).map(str::parse::<f64>).transpose()?;
+        Ok(Some(mark_price))
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn process_message(
         msg: Message,
-        trade_sender: &mpsc::Sender<Trade>,
+        event_sender: &mpsc::Sender<MarketEvent>,
         _trade_counter: &AtomicU64,
         market_type: &MarketType,
+        quotes: bool,
+        liquidations: bool,
+        mark_prices: bool,
+        last_trades: &mut LastTrades,
+        held: &mut Vec<(SequenceGap, Trade)>,
     ) -> Result<()> {
         if let Message::Text(text) = msg {
-            if let Ok(message) = serde_json::from_str::<BinanceMessage>(&text) {
-                let data = match message {
-                    BinanceMessage::Stream(stream_msg) => stream_msg.data,
-                    BinanceMessage::Direct(direct_data) => direct_data,
-                };
-                
-                if data.event_type == "aggTrade" {
-                    let price = data.price.parse::<f64>().unwrap_or(0.0);
-                    let quantity = data.quantity.parse::<f64>().unwrap_or(0.0);
-                    // Binanceでは is_buyer_maker が true なら買い、false なら売り
-                    let side = if data.is_buyer_maker {
-                        Side::Buy   // 買い手がメイカー = 買い約定 = Ask側
-                    } else {
-                        Side::Sell  // 買い手がテイカー = 売り約定 = Bid側
-                    };
-                    
-                    let timestamp = DateTime::from_timestamp_millis(data.timestamp)
-                        .unwrap_or_else(|| Utc::now());
-                    
-                    let trade = Trade::new(
-                        "binance".to_string(),
-                        market_type.clone(),
-                        data.symbol,
-                        data.trade_id.to_string(),
-                        price,
-                        quantity,
-                        side,
-                        timestamp,
-                    );
-                    
-                    if let Err(e) = trade_sender.send(trade).await {
-                        error!("Failed to send trade: {}", e);
+            // 購読したストリームの順に判定し, 該当しなければ約定として扱う
+            let mut events: Vec<MarketEvent> = Vec::new();
+            if mark_prices {
+                events.extend(Self::parse_mark_price(&text, market_type)?.map(MarketEvent::from));
+            }
+            if liquidations && events.is_empty() {
+                events.extend(Self::parse_liquidations(&text, market_type)?.into_iter().map(MarketEvent::from));
+            }
+            if quotes && events.is_empty() {
+                events.extend(Self::parse_quote(&text, market_type)?.map(MarketEvent::from));
+            }
+            if events.is_empty() {
+                events.extend(Self::parse_trades(&text, market_type)?.into_iter().map(MarketEvent::from));
+            }
+            for event in events {
+                if let MarketEvent::Trade(trade) = &event {
+                    // aggTrade ID に欠番があれば約定を止めて, 欠番を REST で補完してから送る (呼び出し側)
+                    if let Some(gap) = last_trades.sequence_gap(trade) {
+                        if let MarketEvent::Trade(trade) = event {
+                            held.push((gap, trade));
+                        }
+                        continue;
                     }
+                    last_trades.record(trade);
+                }
+                let kind = event.kind();
+                if let Err(e) = event_sender.send(event).await {
+                    error!("Failed to send {}: {}", kind, e);
                 }
             }
         }
@@ -141,19 +628,74 @@ impl ExchangeClient for BinanceClient {
 
     async fn subscribe_trades(&mut self, symbols: Vec<String>) -> Result<()> {
         let market_type = self.market_type.as_ref().unwrap();
-        let url = self.build_websocket_url(market_type, &symbols);
+        let url = self.endpoint.ws_url(&self.build_websocket_url(market_type, &symbols));
         info!("Connecting to Binance {} WebSocket: {}", market_type.as_str().to_uppercase(), url);
         
-        let (ws_stream, _) = connect_async(url).await?;
+        let ws_stream = self.endpoint.connect(&url).await?;
         self.ws_stream = Some(ws_stream);
         
         info!("Connected and subscribed to Binance {} trades", market_type.as_str().to_uppercase());
+        ops_events::record(OpsEventKind::Connect, url.to_string());
+        ops_events::record(OpsEventKind::Subscribe, symbols.join(","));
+
+        // 再接続なら切断中の約定を補完してからライブの約定を処理する (補完中のフレームは受信バッファに溜まる)
+        if !self.last_trades.is_empty() {
+            Self::backfill(&symbols, market_type, self.testnet, &self.endpoint, &mut self.last_trades, &self.event_sender).await;
+        }
         
         if let Some(ws_stream) = &mut self.ws_stream {
             // メッセージ処理ループ
-            while let Some(msg) = ws_stream.next().await {
+            let mut disconnect_reason = "stream closed".to_string();
+            let mut keepalive = Keepalive::new()
+                .with_ping(Duration::from_secs(PING_SECONDS), Message::Ping(Vec::new()))
+                .with_watchdog(self.watchdog);
+            let mut result = Ok(());
+            loop {
+                let msg = tokio::select! {
+                    msg = ws_stream.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                    action = keepalive.next() => match action {
+                        KeepaliveAction::Ping(ping) => match ws_stream.send(ping).await {
+                            Ok(()) => continue,
+                            Err(e) => {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        },
+                        KeepaliveAction::Timeout(timeout) => {
+                            let timeout = WatchdogTimeout(timeout);
+                            error!("Binance {}", timeout);
+                            disconnect_reason = timeout.to_string();
+                            result = Err(timeout.into());
+                            break;
+                        }
+                    },
+                };
                 match msg {
                     Ok(msg) => {
+                        keepalive.received();
+                        if let Some(pong) = keepalive::pong(&msg) {
+                            if let Err(e) = ws_stream.send(pong).await {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        }
+                        #[cfg(feature = "chaos")]
+                        let msg = match self.chaos.as_mut() {
+                            Some(chaos) => match chaos.apply(msg).await {
+                                Ok(msg) => msg,
+                                Err(e) => {
+                                    error!("WebSocket error: {}", e);
+                                    disconnect_reason = format!("WebSocket error: {}", e);
+                                    break;
+                                }
+                            },
+                            None => msg,
+                        };
                         let count = self.trade_counter.fetch_add(1, Ordering::Relaxed);
                         // 1件目、(raw_freq+1)件目、(raw_freq*2+1)件目...を表示
                         if count % (self.raw_freq as u64) == 1 {
@@ -163,18 +705,35 @@ impl ExchangeClient for BinanceClient {
                         if count >= 1_000_000 {
                             self.trade_counter.store(0, Ordering::Relaxed);
                         }
-                        if let Err(e) = Self::process_message(msg, &self.trade_sender, &self.trade_counter, self.market_type.as_ref().unwrap()).await {
+                        let mut held = Vec::new();
+                        if let Err(e) = Self::process_message(msg, &self.event_sender, &self.trade_counter, self.market_type.as_ref().unwrap(), self.quotes, self.liquidations, self.mark_prices, &mut self.last_trades, &mut held).await {
                             error!("Error processing message: {}", e);
+                            crate::utils::quality::record_parse_failure("binance");
+                        }
+                        for (gap, trade) in held {
+                            tracing::warn!("Binance {} aggTrade ids {}..={} are missing ({} trades); backfilling", gap.symbol, gap.from, gap.to, gap.missing());
+                            crate::utils::quality::record_sequence_gap("binance", gap.missing());
+                            Self::backfill(std::slice::from_ref(&gap.symbol), self.market_type.as_ref().unwrap(), self.testnet, &self.endpoint, &mut self.last_trades, &self.event_sender).await;
+                            // REST で届いていなければ止めていた約定をそのまま送る
+                            if self.last_trades.is_after_last(&trade) {
+                                self.last_trades.record(&trade);
+                                if let Err(e) = self.event_sender.send(MarketEvent::Trade(trade)).await {
+                                    error!("Failed to send trade: {}", e);
+                                }
+                            }
                         }
                     }
                     Err(e) => {
                         error!("WebSocket error: {}", e);
+                        disconnect_reason = format!("WebSocket error: {}", e);
                         break;
                     }
                 }
             }
+            ops_events::record(OpsEventKind::Disconnect, disconnect_reason);
+            return result;
         }
-        
+
         Ok(())
     }
 
@@ -186,4 +745,8 @@ impl ExchangeClient for BinanceClient {
         }
         Ok(())
     }
+
+    fn max_symbols_per_connection(&self, market_type: &MarketType) -> usize {
+        Self::max_streams_per_connection(market_type) / self.streams_per_symbol()
+    }
 }
\ No newline at end of file
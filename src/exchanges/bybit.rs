@@ -1,17 +1,29 @@
-use crate::models::{trade::{Trade, Side}, market_type::MarketType, ExchangeClient};
+use crate::models::{trade::{Trade, Side}, quote::Quote, liquidation::Liquidation, mark_price::MarkPrice, block_trade::BlockTrade, market_event::MarketEvent, market_type::MarketType, ExchangeClient};
+use crate::utils::backfill::LastTrades;
+use crate::utils::keepalive::{self, Keepalive, KeepaliveAction, WatchdogTimeout};
+use crate::utils::endpoint::Endpoint;
+use crate::utils::history;
+use crate::utils::ops_events::{self, OpsEventKind};
+use crate::utils::order_book::OrderBook;
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
-use tracing::{error, info};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{error, info, warn};
+#[cfg(feature = "chaos")]
+use crate::utils::chaos::{ChaosConfig, ChaosInjector};
 
 type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+const PING_SECONDS: u64 = 20; // 20秒ごとの {"op":"ping"} が推奨 (無通信だと切断される)
+
 #[derive(Debug, Serialize)]
 struct BybitSubscribe {
     op: String,
@@ -21,9 +33,46 @@ struct BybitSubscribe {
 #[derive(Debug, Deserialize)]
 struct BybitResponse {
     topic: Option<String>,
+    #[serde(rename = "type")]
+    message_type: Option<String>,
+    ts: Option<i64>,
     data: Option<serde_json::Value>,
 }
 
+#[derive(Debug, Deserialize)]
+struct BybitOrderBookData {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bids: Vec<(String, String)>,
+    #[serde(rename = "a")]
+    asks: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitLiquidationData {
+    #[serde(rename = "T")]
+    timestamp: i64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "S")]
+    position_side: String,  // Buy: ロングの清算, Sell: ショートの清算
+    #[serde(rename = "v")]
+    quantity: String,
+    #[serde(rename = "p")]
+    price: String,
+}
+
+/// tickers.{symbol} (delta は変化したフィールドのみ)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitTickerData {
+    symbol: String,
+    mark_price: Option<String>,
+    index_price: Option<String>,
+    funding_rate: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct BybitTradeData {
     #[serde(rename = "s")]
@@ -38,80 +87,569 @@ struct BybitTradeData {
     timestamp: i64,
     #[serde(rename = "i")]
     trade_id: String,
+    #[serde(rename = "BT", default)]
+    block_trade: bool,  // ブロックトレード (相対取引) の約定
+}
+
+/// GET /v5/market/recent-trade (新しい順)
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitRecentTradeResponse {
+    ret_code: i64,
+    ret_msg: String,
+    result: Option<BybitRecentTradeResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitRecentTradeResult {
+    list: Vec<BybitRecentTrade>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitRecentTrade {
+    exec_id: String,
+    symbol: String,
+    price: String,
+    size: String,
+    side: String,
+    time: String,
+    #[serde(default)]
+    is_block_trade: bool,
 }
 
 pub struct BybitClient {
     ws_stream: Option<WsStream>,
-    trade_sender: mpsc::Sender<Trade>,
+    event_sender: mpsc::Sender<MarketEvent>,
     trade_counter: AtomicU64,
     market_type: Option<MarketType>,
     raw_freq: u32,
+    quotes: bool,
+    orderbook_depth: u32,
+    imbalance_levels: usize,
+    order_books: HashMap<String, OrderBook>,
+    liquidations: bool,
+    mark_price_stream: bool,
+    mark_prices: HashMap<String, MarkPrice>,
+    block_trades: bool,
+    testnet: bool,
+    last_trades: LastTrades,
+    watchdog: Option<Duration>,
+    endpoint: Endpoint,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosInjector>,
 }
 
 impl BybitClient {
-    pub fn new(trade_sender: mpsc::Sender<Trade>, raw_freq: u32) -> Self {
+    pub fn new(event_sender: mpsc::Sender<MarketEvent>, raw_freq: u32) -> Self {
         Self {
             ws_stream: None,
-            trade_sender,
+            event_sender,
             trade_counter: AtomicU64::new(0),
             market_type: None,
             raw_freq,
+            quotes: false,
+            orderbook_depth: 50,
+            imbalance_levels: 5,
+            order_books: HashMap::new(),
+            liquidations: false,
+            mark_price_stream: false,
+            mark_prices: HashMap::new(),
+            block_trades: false,
+            testnet: false,
+            last_trades: LastTrades::new(),
+            watchdog: None,
+            endpoint: Endpoint::new(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
         }
     }
 
+    /// orderbook.{depth}.{symbol} も購読し, 板更新ごとに Quote を送信する
+    pub fn with_quotes(mut self, orderbook_depth: u32, imbalance_levels: usize) -> Self {
+        self.quotes = true;
+        self.orderbook_depth = orderbook_depth;
+        self.imbalance_levels = imbalance_levels;
+        self
+    }
+
+    /// allLiquidation.{symbol} も購読し, 強制決済ごとに Liquidation を送信する (Spot は非対応)
+    pub fn with_liquidations(mut self) -> Self {
+        self.liquidations = true;
+        self
+    }
+
+    /// tickers.{symbol} も購読し, マーク価格・インデックス価格の更新ごとに MarkPrice を送信する (Spot は非対応)
+    pub fn with_mark_prices(mut self) -> Self {
+        self.mark_price_stream = true;
+        self
+    }
+
+    /// ブロックトレード (BT=true の約定) をローソク足から除外し, BlockTrade として送信する
+    pub fn with_block_trades(mut self) -> Self {
+        self.block_trades = true;
+        self
+    }
+
+    /// テストネット (stream-testnet.bybit.com) に接続する
+    pub fn with_testnet(mut self, testnet: bool) -> Self {
+        self.testnet = testnet;
+        self
+    }
+
+    /// timeout の間フレームを受信しなければ切断し, WatchdogTimeout を返す (呼び出し側で再接続する)
+    pub fn with_watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// 既定の接続先の代わりに別ホスト・プロキシを使う (地域制限の回避)
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, config: ChaosConfig) -> Self {
+        self.chaos = Some(ChaosInjector::new(config));
+        self
+    }
+
     fn get_websocket_url(&self, market_type: &MarketType) -> &'static str {
+        match (market_type, self.testnet) {
+            (MarketType::Spot, false) => "wss://stream.bybit.com/v5/public/spot",
+            (MarketType::Linear, false) => "wss://stream.bybit.com/v5/public/linear",
+            (MarketType::Inverse, false) => "wss://stream.bybit.com/v5/public/inverse",
+            (MarketType::Spot, true) => "wss://stream-testnet.bybit.com/v5/public/spot",
+            (MarketType::Linear, true) => "wss://stream-testnet.bybit.com/v5/public/linear",
+            (MarketType::Inverse, true) => "wss://stream-testnet.bybit.com/v5/public/inverse",
+        }
+    }
+
+    fn get_rest_url(testnet: bool) -> &'static str {
+        if testnet {
+            "https://api-testnet.bybit.com"
+        } else {
+            "https://api.bybit.com"
+        }
+    }
+
+    /// テキストフレームを Trade に正規化する (約定以外のフレームは空で返す)
+    pub fn parse_trades(text: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let response: BybitResponse = serde_json::from_str(text)?;
+        Ok(Self::trades_from_response(response, market_type).into_iter().map(|(trade, _)| trade).collect())
+    }
+
+    /// テキストフレームのうちブロックトレードの約定だけを BlockTrade に正規化する
+    pub fn parse_block_trades(text: &str, market_type: &MarketType) -> Result<Vec<BlockTrade>> {
+        let response: BybitResponse = serde_json::from_str(text)?;
+        Ok(Self::trades_from_response(response, market_type)
+            .into_iter()
+            .filter(|(_, is_block)| *is_block)
+            .map(|(trade, _)| BlockTrade::from(trade))
+            .collect())
+    }
+
+    /// (約定, ブロックトレードか) の組を返す
+    fn trades_from_response(response: BybitResponse, market_type: &MarketType) -> Vec<(Trade, bool)> {
+        let mut trades = Vec::new();
+        
+        if let Some(topic) = &response.topic {
+            if topic.starts_with("publicTrade.") {
+                if let Some(data) = response.data {
+                    if let Ok(trade_datas) = serde_json::from_value::<Vec<BybitTradeData>>(data) {
+                        for trade_data in trade_datas {
+                            let price = trade_data.price.parse::<f64>().unwrap_or(0.0);
+                            let quantity = trade_data.quantity.parse::<f64>().unwrap_or(0.0);
+                            let side = match trade_data.side.as_str() {
+                                "Buy" => Side::Buy,
+                                "Sell" => Side::Sell,
+                                _ => Side::Buy, // デフォルト
+                            };
+                            
+                            let timestamp = DateTime::from_timestamp_millis(trade_data.timestamp)
+                                .unwrap_or_else(Utc::now);
+                            
+                            let trade = Trade::new(
+                                "bybit".to_string(),
+                                market_type.clone(),
+                                trade_data.symbol,
+                                trade_data.trade_id,
+                                price,
+                                quantity,
+                                side,
+                                timestamp,
+                            ).with_gateway_timestamp(response.ts.and_then(DateTime::from_timestamp_millis));
+                            trades.push((trade, trade_data.block_trade));
+                        }
+                    }
+                }
+            }
+        }
+        trades
+    }
+
+    /// recent-trade の応答を Trade に正規化する (古い順)
+    pub fn parse_recent_trades(text: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let response: BybitRecentTradeResponse = serde_json::from_str(text)?;
+        Ok(Self::recent_trades_from_response(response, market_type)?.into_iter().map(|(trade, _)| trade).collect())
+    }
+
+    fn recent_trades_from_response(response: BybitRecentTradeResponse, market_type: &MarketType) -> Result<Vec<(Trade, bool)>> {
+        if response.ret_code != 0 {
+            return Err(anyhow::anyhow!("Bybit recent-trade error {}: {}", response.ret_code, response.ret_msg));
+        }
+        let mut trades = Vec::new();
+        for data in response.result.map(|r| r.list).unwrap_or_default().into_iter().rev() {
+            let side = match data.side.as_str() {
+                "Sell" => Side::Sell,
+                _ => Side::Buy,
+            };
+            let trade = Trade::new(
+                "bybit".to_string(),
+                market_type.clone(),
+                data.symbol,
+                data.exec_id,
+                data.price.parse::<f64>()?,
+                data.size.parse::<f64>()?,
+                side,
+                DateTime::from_timestamp_millis(data.time.parse::<i64>()?).unwrap_or_else(Utc::now),
+            );
+            trades.push((trade, data.is_block_trade));
+        }
+        Ok(trades)
+    }
+
+    async fn fetch_recent_trades(http: &reqwest::Client, url: &str, market_type: &MarketType) -> Result<Vec<(Trade, bool)>> {
+        let response: BybitRecentTradeResponse = http.get(url).send().await?.error_for_status()?.json().await?;
+        Self::recent_trades_from_response(response, market_type)
+    }
+
+    /// 再接続時に recent-trade で切断中の約定を取得し, ライブの約定より先に送る
+    /// recent-trade は直近の約定 (Spot 60 件, 先物 1000 件) しか返さないため, 最後の約定まで遡れなければ警告する
+    async fn backfill(
+        symbols: &[String],
+        market_type: &MarketType,
+        testnet: bool,
+        endpoint: &Endpoint,
+        block_trades: bool,
+        last_trades: &mut LastTrades,
+        event_sender: &mpsc::Sender<MarketEvent>,
+    ) {
+        let limit = if *market_type == MarketType::Spot { 60 } else { 1000 };
+        for symbol in symbols {
+            let Some(last) = last_trades.get(symbol).cloned() else { continue };
+            let url = format!(
+                "{}/v5/market/recent-trade?category={}&symbol={}&limit={}",
+                endpoint.rest_url(Self::get_rest_url(testnet)), market_type.as_str(), symbol, limit
+            );
+            let fetched = match Self::fetch_recent_trades(endpoint.http(), &url, market_type).await {
+                Ok(fetched) => fetched,
+                Err(e) => {
+                    error!("Bybit backfill of {} failed: {}", symbol, e);
+                    continue;
+                }
+            };
+            if let Some(oldest) = fetched.iter().map(|(trade, _)| trade.timestamp).min().filter(|oldest| *oldest > last.timestamp) {
+                warn!("Bybit backfill of {} is incomplete: trades between {} and {} are missing", symbol, last.timestamp, oldest);
+            }
+            let blocks: HashSet<String> = fetched.iter().filter(|(_, is_block)| *is_block).map(|(trade, _)| trade.trade_id.clone()).collect();
+            let missed = last_trades.missed(symbol, fetched.into_iter().map(|(trade, _)| trade).collect());
+            let count = missed.len();
+            for trade in missed {
+                last_trades.record(&trade);
+                let event = if block_trades && blocks.contains(&trade.trade_id) {
+                    MarketEvent::BlockTrade(BlockTrade::from(trade))
+                } else {
+                    MarketEvent::Trade(trade)
+                };
+                if let Err(e) = event_sender.send(event).await {
+                    error!("Failed to send backfilled trade: {}", e);
+                }
+            }
+            info!("Backfilled {} Bybit trades of {} since {}", count, symbol, last.timestamp);
+            ops_events::record(OpsEventKind::Backfill, format!("{}: {} trades since {}", symbol, count, last.timestamp));
+        }
+    }
+
+    fn get_archive_url(market_type: &MarketType, symbol: &str, date: NaiveDate) -> String {
         match market_type {
-            MarketType::Spot => "wss://stream.bybit.com/v5/public/spot",
-            MarketType::Linear => "wss://stream.bybit.com/v5/public/linear",
-            MarketType::Inverse => "wss://stream.bybit.com/v5/public/inverse",
+            MarketType::Spot => format!("https://public.bybit.com/spot/{}/{}_{}.csv.gz", symbol, symbol, date.format("%Y-%m-%d")),
+            _ => format!("https://public.bybit.com/trading/{}/{}{}.csv.gz", symbol, symbol, date.format("%Y-%m-%d")),
+        }
+    }
+
+    /// public.bybit.com の約定 CSV を Trade に正規化する (古い順)
+    /// 先物は timestamp (秒, 小数),symbol,side,size,price,...,trdMatchID,..., 現物は id,timestamp (ミリ秒),price,volume,side なので列はヘッダーで探す
+    pub fn parse_trade_archive_csv(text: &str, symbol: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let mut lines = text.lines();
+        let header: Vec<&str> = lines.next().unwrap_or_default().trim().split(',').collect();
+        let column = |names: &[&str]| {
+            header
+                .iter()
+                .position(|name| names.contains(name))
+                .ok_or_else(|| anyhow::anyhow!("Bybit trade archive has no {} column", names.join("/")))
+        };
+        let (time_col, side_col, size_col, price_col, id_col) = (column(&["timestamp"])?, column(&["side"])?, column(&["size", "volume"])?, column(&["price"])?, column(&["trdMatchID", "id"])?);
+
+        let mut trades = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.trim().split(',').collect();
+            if fields.len() < header.len() {
+                continue;
+            }
+            let timestamp_ms = match fields[time_col].parse::<i64>() {
+                Ok(ms) if ms >= 100_000_000_000 => ms,
+                Ok(seconds) => seconds * 1000,
+                Err(_) => (fields[time_col].parse::<f64>()? * 1000.0).round() as i64,
+            };
+            let side = if fields[side_col].eq_ignore_ascii_case("sell") { Side::Sell } else { Side::Buy };
+            trades.push(Trade::new(
+                "bybit".to_string(),
+                market_type.clone(),
+                symbol.to_string(),
+                fields[id_col].to_string(),
+                fields[price_col].parse::<f64>()?,
+                fields[size_col].parse::<f64>()?,
+                side,
+                DateTime::from_timestamp_millis(timestamp_ms).ok_or_else(|| anyhow::anyhow!("Invalid timestamp: {}", fields[time_col]))?,
+            ));
+        }
+        trades.sort_by_key(|trade| trade.timestamp);
+        Ok(trades)
+    }
+
+    /// 1 日分の約定をアーカイブから取得する (まだ公開されていなければ None)
+    pub async fn fetch_archive_trades(endpoint: &Endpoint, market_type: &MarketType, symbol: &str, date: NaiveDate) -> Result<Option<Vec<Trade>>> {
+        let Some(bytes) = history::download(endpoint.http(), &Self::get_archive_url(market_type, symbol, date)).await? else {
+            return Ok(None);
+        };
+        Ok(Some(Self::parse_trade_archive_csv(&history::gunzip(&bytes)?, symbol, market_type)?))
+    }
+
+    /// [start, end) の約定を recent-trade で取得する (直近の約定しか返らないため, start まで遡れなければ警告する)
+    pub async fn fetch_rest_trades(endpoint: &Endpoint, market_type: &MarketType, symbol: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Trade>> {
+        let limit = if *market_type == MarketType::Spot { 60 } else { 1000 };
+        let url = format!(
+            "{}/v5/market/recent-trade?category={}&symbol={}&limit={}",
+            endpoint.rest_url(Self::get_rest_url(false)), market_type.as_str(), symbol, limit
+        );
+        let fetched = Self::fetch_recent_trades(endpoint.http(), &url, market_type).await?;
+        if let Some(oldest) = fetched.iter().map(|(trade, _)| trade.timestamp).min().filter(|oldest| *oldest > start) {
+            warn!("Bybit recent-trade of {} only reaches back to {}; trades between {} and {} are missing", symbol, oldest, start, oldest.min(end));
+        }
+        Ok(fetched
+            .into_iter()
+            .map(|(trade, _)| trade)
+            .filter(|trade| trade.timestamp >= start && trade.timestamp < end)
+            .collect())
+    }
+
+    /// 強制決済フレームを Liquidation に正規化する (強制決済以外のフレームは空で返す)
+    pub fn parse_liquidations(text: &str, market_type: &MarketType) -> Result<Vec<Liquidation>> {
+        let response: BybitResponse = serde_json::from_str(text)?;
+        Self::liquidations_from_response(response, market_type)
+    }
+
+    fn liquidations_from_response(response: BybitResponse, market_type: &MarketType) -> Result<Vec<Liquidation>> {
+        let is_liquidation = response.topic.as_deref().is_some_and(|t| t.starts_with("allLiquidation."));
+        let Some(data) = response.data.filter(|_| is_liquidation) else {
+            return Ok(Vec::new());
+        };
+
+        let mut liquidations = Vec::new();
+        for data in serde_json::from_value::<Vec<BybitLiquidationData>>(data)? {
+            // 清算されたポジションの方向を清算注文の方向に変換する
+            let side = match data.position_side.as_str() {
+                "Buy" => Side::Sell,
+                _ => Side::Buy,
+            };
+            liquidations.push(Liquidation::new(
+                "bybit".to_string(),
+                market_type.clone(),
+                data.symbol,
+                side,
+                data.price.parse::<f64>()?,
+                data.quantity.parse::<f64>()?,
+                DateTime::from_timestamp_millis(data.timestamp).unwrap_or_else(Utc::now),
+            ));
+        }
+        Ok(liquidations)
+    }
+
+    /// tickers メッセージを直近の値に適用し, MarkPrice を返す (tickers 以外や価格に変化のないフレームは None)
+    pub fn parse_mark_price(
+        text: &str,
+        market_type: &MarketType,
+        mark_prices: &mut HashMap<String, MarkPrice>,
+    ) -> Result<Option<MarkPrice>> {
+        let response: BybitResponse = serde_json::from_str(text)?;
+        Self::mark_price_from_response(response, market_type, mark_prices)
+    }
+
+    fn mark_price_from_response(
+        response: BybitResponse,
+        market_type: &MarketType,
+        mark_prices: &mut HashMap<String, MarkPrice>,
+    ) -> Result<Option<MarkPrice>> {
+        let is_tickers = response.topic.as_deref().is_some_and(|t| t.starts_with("tickers."));
+        let Some(data) = response.data.filter(|_| is_tickers) else {
+            return Ok(None);
+        };
+        let data: BybitTickerData = serde_json::from_value(data)?;
+
+        if response.message_type.as_deref() == Some("snapshot") {
+            mark_prices.remove(&data.symbol);
+        }
+        // 最終価格や出来高のみの delta は無視する
+        if data.mark_price.is_none() && data.index_price.is_none() && data.funding_rate.is_none() {
+            return Ok(None);
+        }
+
+        let timestamp = response
+            .ts
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or_else(Utc::now);
+        let index_price = data.index_price.as_deref().map(str::parse::<f64>).transpose()?;
+        let funding_rate = data.funding_rate.as_deref().filter(|r| !r.is_empty()).map(str::parse::<f64>).transpose()?;
+
+        let mark_price = match (mark_prices.get_mut(&data.symbol), data.mark_price) {
+            (Some(last), mark_price) => {
+                if let Some(mark_price) = mark_price {
+                    last.mark_price = mark_price.parse::<f64>()?;
+                }
+                last.index_price = index_price.or(last.index_price);
+                last.funding_rate = funding_rate.or(last.funding_rate);
+                last.timestamp = timestamp;
+                last.clone()
+            }
+            (None, Some(mark_price)) => {
+                let mut entry = MarkPrice::new(
+                    "bybit".to_string(),
+                    market_type.clone(),
+                    data.symbol.clone(),
+                    mark_price.parse::<f64>()?,
+                    index_price,
+                    timestamp,
+                );
+                entry.funding_rate = funding_rate;
+                mark_prices.insert(data.symbol, entry.clone());
+                entry
+            }
+            // snapshot を受信する前の delta
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(mark_price))
+    }
+
+    /// 板メッセージをローカル板に適用し, 最良気配の Quote を返す (板以外のフレームは None)
+    pub fn parse_quote(
+        text: &str,
+        market_type: &MarketType,
+        order_books: &mut HashMap<String, OrderBook>,
+        imbalance_levels: usize,
+    ) -> Result<Option<Quote>> {
+        let response: BybitResponse = serde_json::from_str(text)?;
+        Self::quote_from_response(response, market_type, order_books, imbalance_levels)
+    }
+
+    fn quote_from_response(
+        response: BybitResponse,
+        market_type: &MarketType,
+        order_books: &mut HashMap<String, OrderBook>,
+        imbalance_levels: usize,
+    ) -> Result<Option<Quote>> {
+        let is_orderbook = response.topic.as_deref().is_some_and(|t| t.starts_with("orderbook."));
+        let Some(data) = response.data.filter(|_| is_orderbook) else {
+            return Ok(None);
+        };
+        let data: BybitOrderBookData = serde_json::from_value(data)?;
+
+        let book = order_books.entry(data.symbol.clone()).or_default();
+        // snapshot は板の再構築 (購読直後やサーバー再起動時)
+        if response.message_type.as_deref() == Some("snapshot") {
+            book.clear();
+        }
+        for (price, size) in &data.bids {
+            book.update_bid(price.parse::<f64>()?, size.parse::<f64>()?);
         }
+        for (price, size) in &data.asks {
+            book.update_ask(price.parse::<f64>()?, size.parse::<f64>()?);
+        }
+
+        let (Some((bid_price, bid_size)), Some((ask_price, ask_size))) = (book.best_bid(), book.best_ask()) else {
+            return Ok(None);
+        };
+
+        let timestamp = response
+            .ts
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or_else(Utc::now);
+
+        let mut quote = Quote::new(
+            "bybit".to_string(),
+            market_type.clone(),
+            data.symbol,
+            bid_price,
+            bid_size,
+            ask_price,
+            ask_size,
+            timestamp,
+        );
+        quote.imbalance = book.imbalance(imbalance_levels);
+        quote.depth_levels = imbalance_levels as u32;
+        Ok(Some(quote))
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn process_message(
         msg: Message,
-        trade_sender: &mpsc::Sender<Trade>,
+        event_sender: &mpsc::Sender<MarketEvent>,
         trade_counter: &AtomicU64,
         market_type: &MarketType,
+        order_books: &mut HashMap<String, OrderBook>,
+        imbalance_levels: usize,
+        mark_prices: &mut HashMap<String, MarkPrice>,
+        block_trades: bool,
+        last_trades: &mut LastTrades,
     ) -> Result<()> {
         if let Message::Text(text) = msg {
             let response: BybitResponse = serde_json::from_str(&text)?;
             
-            if let Some(topic) = &response.topic {
-                if topic.starts_with("publicTrade.") {
-                    if let Some(data) = response.data {
-                        if let Ok(trades) = serde_json::from_value::<Vec<BybitTradeData>>(data) {
-                            for trade_data in trades {
-                                let _count = trade_counter.fetch_add(1, Ordering::Relaxed);
-                                
-                                let price = trade_data.price.parse::<f64>().unwrap_or(0.0);
-                                let quantity = trade_data.quantity.parse::<f64>().unwrap_or(0.0);
-                                let side = match trade_data.side.as_str() {
-                                    "Buy" => Side::Buy,
-                                    "Sell" => Side::Sell,
-                                    _ => Side::Buy, // デフォルト
-                                };
-                                
-                                let timestamp = DateTime::from_timestamp_millis(trade_data.timestamp)
-                                    .unwrap_or_else(|| Utc::now());
-                                
-                                let trade = Trade::new(
-                                    "bybit".to_string(),
-                                    market_type.clone(),
-                                    trade_data.symbol,
-                                    trade_data.trade_id,
-                                    price,
-                                    quantity,
-                                    side,
-                                    timestamp,
-                                );
-                                
-                                
-                                if let Err(e) = trade_sender.send(trade).await {
-                                    error!("Failed to send trade: {}", e);
-                                }
-                            }
+            // 購読したトピックのフレームしか届かないため, トピックで振り分ける
+            let events: Vec<MarketEvent> = match response.topic.as_deref() {
+                Some(t) if t.starts_with("allLiquidation.") => Self::liquidations_from_response(response, market_type)?
+                    .into_iter()
+                    .map(MarketEvent::from)
+                    .collect(),
+                Some(t) if t.starts_with("tickers.") => Self::mark_price_from_response(response, market_type, mark_prices)?
+                    .into_iter()
+                    .map(MarketEvent::from)
+                    .collect(),
+                Some(t) if t.starts_with("orderbook.") => Self::quote_from_response(response, market_type, order_books, imbalance_levels)?
+                    .into_iter()
+                    .map(MarketEvent::from)
+                    .collect(),
+                _ => Self::trades_from_response(response, market_type)
+                    .into_iter()
+                    .map(|(trade, is_block)| {
+                        // ブロックトレードは分離する設定ならローソク足に含めない
+                        if is_block && block_trades {
+                            return MarketEvent::BlockTrade(BlockTrade::from(trade));
                         }
-                    }
+                        let _count = trade_counter.fetch_add(1, Ordering::Relaxed);
+                        MarketEvent::Trade(trade)
+                    })
+                    .collect(),
+            };
+            
+            for event in events {
+                if let MarketEvent::Trade(trade) = &event {
+                    last_trades.record(trade);
+                }
+                let kind = event.kind();
+                if let Err(e) = event_sender.send(event).await {
+                    error!("Failed to send {}: {}", kind, e);
                 }
             }
         }
@@ -122,38 +660,110 @@ impl BybitClient {
 #[async_trait]
 impl ExchangeClient for BybitClient {
     async fn connect(&mut self, market_type: MarketType) -> Result<()> {
-        let url = self.get_websocket_url(&market_type);
+        let url = self.endpoint.ws_url(self.get_websocket_url(&market_type));
         info!("Connecting to Bybit {} WebSocket: {}", market_type.as_str().to_uppercase(), url);
         
-        let (ws_stream, _) = connect_async(url).await?;
+        let ws_stream = self.endpoint.connect(&url).await?;
         self.ws_stream = Some(ws_stream);
         self.market_type = Some(market_type);
         
         info!("Connected to Bybit {} WebSocket", self.market_type.as_ref().unwrap().as_str().to_uppercase());
+        ops_events::record(OpsEventKind::Connect, url.to_string());
         Ok(())
     }
 
     async fn subscribe_trades(&mut self, symbols: Vec<String>) -> Result<()> {
         if let Some(ws_stream) = &mut self.ws_stream {
-            let args: Vec<String> = symbols
-                .into_iter()
+            let mut args: Vec<String> = symbols
+                .iter()
                 .map(|symbol| format!("publicTrade.{}", symbol))
                 .collect();
+            if self.quotes {
+                args.extend(symbols.iter().map(|symbol| format!("orderbook.{}.{}", self.orderbook_depth, symbol)));
+            }
+            // 旧 liquidation.{symbol} は廃止予定のため allLiquidation を使う
+            if self.liquidations {
+                args.extend(symbols.iter().map(|symbol| format!("allLiquidation.{}", symbol)));
+            }
+            if self.mark_price_stream {
+                args.extend(symbols.iter().map(|symbol| format!("tickers.{}", symbol)));
+            }
             
-            let subscribe_msg = BybitSubscribe {
-                op: "subscribe".to_string(),
-                args,
-            };
-            
-            let msg = Message::Text(serde_json::to_string(&subscribe_msg)?);
-            ws_stream.send(msg).await?;
+            // Spot は 1 リクエスト 10 args まで
+            for chunk in args.chunks(10) {
+                let subscribe_msg = BybitSubscribe {
+                    op: "subscribe".to_string(),
+                    args: chunk.to_vec(),
+                };
+                
+                let msg = Message::Text(serde_json::to_string(&subscribe_msg)?);
+                ws_stream.send(msg).await?;
+            }
             
-            info!("Subscribed to Bybit trades");
+            info!("Subscribed to Bybit trades{}{}{}{}",
+                  if self.quotes { ", orderbook" } else { "" },
+                  if self.liquidations { ", liquidations" } else { "" },
+                  if self.mark_price_stream { ", mark prices" } else { "" },
+                  if self.block_trades { " (block trades separated)" } else { "" });
+            ops_events::record(OpsEventKind::Subscribe, symbols.join(","));
+
+            // 再接続なら切断中の約定を補完してからライブの約定を処理する (補完中のフレームは受信バッファに溜まる)
+            if !self.last_trades.is_empty() {
+                Self::backfill(&symbols, self.market_type.as_ref().unwrap(), self.testnet, &self.endpoint, self.block_trades, &mut self.last_trades, &self.event_sender).await;
+            }
             
             // メッセージ処理ループ
-            while let Some(msg) = ws_stream.next().await {
+            let mut disconnect_reason = "stream closed".to_string();
+            let mut keepalive = Keepalive::new()
+                .with_ping(Duration::from_secs(PING_SECONDS), Message::Text(r#"{"op":"ping"}"#.to_string()))
+                .with_watchdog(self.watchdog);
+            let mut result = Ok(());
+            loop {
+                let msg = tokio::select! {
+                    msg = ws_stream.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                    action = keepalive.next() => match action {
+                        KeepaliveAction::Ping(ping) => match ws_stream.send(ping).await {
+                            Ok(()) => continue,
+                            Err(e) => {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        },
+                        KeepaliveAction::Timeout(timeout) => {
+                            let timeout = WatchdogTimeout(timeout);
+                            error!("Bybit {}", timeout);
+                            disconnect_reason = timeout.to_string();
+                            result = Err(timeout.into());
+                            break;
+                        }
+                    },
+                };
                 match msg {
                     Ok(msg) => {
+                        keepalive.received();
+                        if let Some(pong) = keepalive::pong(&msg) {
+                            if let Err(e) = ws_stream.send(pong).await {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        }
+                        #[cfg(feature = "chaos")]
+                        let msg = match self.chaos.as_mut() {
+                            Some(chaos) => match chaos.apply(msg).await {
+                                Ok(msg) => msg,
+                                Err(e) => {
+                                    error!("WebSocket error: {}", e);
+                                    disconnect_reason = format!("WebSocket error: {}", e);
+                                    break;
+                                }
+                            },
+                            None => msg,
+                        };
                         let count = self.trade_counter.fetch_add(1, Ordering::Relaxed);
                         // 1件目、(raw_freq+1)件目、(raw_freq*2+1)件目...を表示
                         if count % (self.raw_freq as u64) == 1 {
@@ -163,18 +773,32 @@ impl ExchangeClient for BybitClient {
                         if count >= 1_000_000 {
                             self.trade_counter.store(0, Ordering::Relaxed);
                         }
-                        if let Err(e) = Self::process_message(msg, &self.trade_sender, &self.trade_counter, self.market_type.as_ref().unwrap()).await {
+                        if let Err(e) = Self::process_message(
+                            msg,
+                            &self.event_sender,
+                            &self.trade_counter,
+                            self.market_type.as_ref().unwrap(),
+                            &mut self.order_books,
+                            self.imbalance_levels,
+                            &mut self.mark_prices,
+                            self.block_trades,
+                            &mut self.last_trades,
+                        ).await {
                             error!("Error processing message: {}", e);
+                            crate::utils::quality::record_parse_failure("bybit");
                         }
                     }
                     Err(e) => {
                         error!("WebSocket error: {}", e);
+                        disconnect_reason = format!("WebSocket error: {}", e);
                         break;
                     }
                 }
             }
+            ops_events::record(OpsEventKind::Disconnect, disconnect_reason);
+            return result;
         }
-        
+
         Ok(())
     }
 
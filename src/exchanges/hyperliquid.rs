@@ -1,17 +1,26 @@
-use crate::models::{trade::{Trade, Side}, market_type::MarketType, ExchangeClient};
+use crate::models::{trade::{Trade, Side}, quote::Quote, market_event::MarketEvent, market_type::MarketType, ExchangeClient};
+use crate::utils::keepalive::{self, Keepalive, KeepaliveAction, WatchdogTimeout};
+use crate::utils::endpoint::Endpoint;
+use crate::utils::ops_events::{self, OpsEventKind};
+use crate::utils::order_book::OrderBook;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
 use tracing::{error, info};
+#[cfg(feature = "chaos")]
+use crate::utils::chaos::{ChaosConfig, ChaosInjector};
 
 type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
+const PING_SECONDS: u64 = 30; // 60秒無送信で切断されるため {"method":"ping"} を送る
+
 #[derive(Debug, Serialize)]
 struct HyperliquidSubscribe {
     method: String,
@@ -38,69 +47,233 @@ struct HyperliquidTradeData {
     px: String,
     sz: String,
     time: u64,
-    hash: String,
+    tid: u64,  // 約定ごとの ID (hash はトランザクション単位で, 同じ注文の複数約定が共有する)
+}
+
+#[derive(Debug, Deserialize)]
+struct HyperliquidBookMessage {
+    channel: String,
+    data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct HyperliquidLevel {
+    px: String,
+    sz: String,
+}
+
+// l2Book は毎回全体のスナップショット (levels: [bids, asks])
+#[derive(Debug, Deserialize)]
+struct HyperliquidL2BookData {
+    coin: String,
+    time: i64,
+    levels: (Vec<HyperliquidLevel>, Vec<HyperliquidLevel>),
+}
+
+// bbo は片側が空のとき null
+#[derive(Debug, Deserialize)]
+struct HyperliquidBboData {
+    coin: String,
+    time: i64,
+    bbo: (Option<HyperliquidLevel>, Option<HyperliquidLevel>),
+}
+
+/// Quote の元にする板チャンネル
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HyperliquidBookChannel {
+    L2Book,  // 上位 20 段 (imbalance を計算できる)
+    Bbo,     // 最良気配のみ (変化時に配信)
+}
+
+impl HyperliquidBookChannel {
+    pub fn parse(channel: &str) -> anyhow::Result<Self> {
+        match channel.to_lowercase().as_str() {
+            "l2book" => Ok(Self::L2Book),
+            "bbo" => Ok(Self::Bbo),
+            c => Err(anyhow::anyhow!("Invalid Hyperliquid book channel: {}. Use l2book or bbo", c)),
+        }
+    }
+
+    fn subscription_type(&self) -> &'static str {
+        match self {
+            Self::L2Book => "l2Book",
+            Self::Bbo => "bbo",
+        }
+    }
 }
 
 pub struct HyperliquidClient {
     ws_stream: Option<WsStream>,
-    trade_sender: mpsc::Sender<Trade>,
+    event_sender: mpsc::Sender<MarketEvent>,
     trade_counter: AtomicU64,
     market_type: Option<MarketType>,
     raw_freq: u32,
+    quotes: bool,
+    book_channel: HyperliquidBookChannel,
+    imbalance_levels: usize,
+    watchdog: Option<Duration>,
+    endpoint: Endpoint,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosInjector>,
 }
 
 impl HyperliquidClient {
-    pub fn new(trade_sender: mpsc::Sender<Trade>, raw_freq: u32) -> Self {
+    pub fn new(event_sender: mpsc::Sender<MarketEvent>, raw_freq: u32) -> Self {
         Self {
             ws_stream: None,
-            trade_sender,
+            event_sender,
             trade_counter: AtomicU64::new(0),
             market_type: None,
             raw_freq,
+            quotes: false,
+            book_channel: HyperliquidBookChannel::L2Book,
+            imbalance_levels: 5,
+            watchdog: None,
+            endpoint: Endpoint::new(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
         }
     }
 
+    /// l2Book または bbo も購読し, 板更新ごとに Quote を送信する
+    pub fn with_quotes(mut self, book_channel: HyperliquidBookChannel, imbalance_levels: usize) -> Self {
+        self.quotes = true;
+        self.book_channel = book_channel;
+        self.imbalance_levels = imbalance_levels;
+        self
+    }
+
+    /// timeout の間フレームを受信しなければ切断し, WatchdogTimeout を返す (呼び出し側で再接続する)
+    pub fn with_watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// 既定の接続先の代わりに別ホスト・プロキシを使う (地域制限の回避)
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, config: ChaosConfig) -> Self {
+        self.chaos = Some(ChaosInjector::new(config));
+        self
+    }
+
     fn get_websocket_url(&self) -> &'static str {
         "wss://api.hyperliquid.xyz/ws"
     }
 
+    /// テキストフレームを Trade に正規化する (約定以外のフレームは空で返す)
+    pub fn parse_trades(text: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+        
+        if let Ok(message) = serde_json::from_str::<HyperliquidMessage>(text) {
+            if message.channel == "trades" {
+                for trade_data in message.data {
+                    let price = trade_data.px.parse::<f64>().unwrap_or(0.0);
+                    let quantity = trade_data.sz.parse::<f64>().unwrap_or(0.0);
+                    
+                    let side = match trade_data.side.as_str() {
+                        "A" => Side::Sell,  // Ask側の約定 = 売り
+                        "B" => Side::Buy,   // Bid側の約定 = 買い
+                        _ => Side::Buy,
+                    };
+                    
+                    let timestamp = DateTime::from_timestamp_millis(trade_data.time as i64)
+                        .unwrap_or_else(Utc::now);
+                    
+                    trades.push(Trade::new(
+                        "hyperliquid".to_string(),
+                        market_type.clone(),
+                        trade_data.coin,
+                        trade_data.tid.to_string(),
+                        price,
+                        quantity,
+                        side,
+                        timestamp,
+                    ));
+                }
+            }
+        }
+        Ok(trades)
+    }
+
+    /// l2Book / bbo フレームを Quote に正規化する (板以外のフレームは None)
+    pub fn parse_quote(text: &str, market_type: &MarketType, imbalance_levels: usize) -> Result<Option<Quote>> {
+        let Ok(message) = serde_json::from_str::<HyperliquidBookMessage>(text) else {
+            return Ok(None);
+        };
+
+        let (coin, time, book) = match message.channel.as_str() {
+            "l2Book" => {
+                let data: HyperliquidL2BookData = serde_json::from_value(message.data)?;
+                let mut book = OrderBook::new();
+                for level in &data.levels.0 {
+                    book.update_bid(level.px.parse::<f64>()?, level.sz.parse::<f64>()?);
+                }
+                for level in &data.levels.1 {
+                    book.update_ask(level.px.parse::<f64>()?, level.sz.parse::<f64>()?);
+                }
+                (data.coin, data.time, book)
+            }
+            "bbo" => {
+                let data: HyperliquidBboData = serde_json::from_value(message.data)?;
+                let mut book = OrderBook::new();
+                if let Some(bid) = &data.bbo.0 {
+                    book.update_bid(bid.px.parse::<f64>()?, bid.sz.parse::<f64>()?);
+                }
+                if let Some(ask) = &data.bbo.1 {
+                    book.update_ask(ask.px.parse::<f64>()?, ask.sz.parse::<f64>()?);
+                }
+                (data.coin, data.time, book)
+            }
+            _ => return Ok(None),
+        };
+
+        let (Some((bid_price, bid_size)), Some((ask_price, ask_size))) = (book.best_bid(), book.best_ask()) else {
+            return Ok(None);
+        };
+
+        let mut quote = Quote::new(
+            "hyperliquid".to_string(),
+            market_type.clone(),
+            coin,
+            bid_price,
+            bid_size,
+            ask_price,
+            ask_size,
+            DateTime::from_timestamp_millis(time).unwrap_or_else(Utc::now),
+        );
+        // bbo は 1 段のみ
+        if message.channel == "l2Book" {
+            quote.imbalance = book.imbalance(imbalance_levels);
+            quote.depth_levels = imbalance_levels as u32;
+        }
+        Ok(Some(quote))
+    }
+
     async fn process_message(
         msg: Message,
-        trade_sender: &mpsc::Sender<Trade>,
+        event_sender: &mpsc::Sender<MarketEvent>,
         _trade_counter: &AtomicU64,
         market_type: &MarketType,
+        quotes: bool,
+        imbalance_levels: usize,
     ) -> Result<()> {
         if let Message::Text(text) = msg {
-            if let Ok(message) = serde_json::from_str::<HyperliquidMessage>(&text) {
-                if message.channel == "trades" {
-                    for trade_data in message.data {
-                        let price = trade_data.px.parse::<f64>().unwrap_or(0.0);
-                        let quantity = trade_data.sz.parse::<f64>().unwrap_or(0.0);
-                        
-                        let side = match trade_data.side.as_str() {
-                            "A" => Side::Sell,  // Ask側の約定 = 売り
-                            "B" => Side::Buy,   // Bid側の約定 = 買い
-                            _ => Side::Buy,
-                        };
-                        
-                        let timestamp = DateTime::from_timestamp_millis(trade_data.time as i64)
-                            .unwrap_or_else(|| Utc::now());
-                        
-                        let trade = Trade::new(
-                            "hyperliquid".to_string(),
-                            market_type.clone(),
-                            trade_data.coin,
-                            trade_data.hash,
-                            price,
-                            quantity,
-                            side,
-                            timestamp,
-                        );
-                        
-                        if let Err(e) = trade_sender.send(trade).await {
-                            error!("Failed to send trade: {}", e);
-                        }
-                    }
+            let mut events: Vec<MarketEvent> = Vec::new();
+            if quotes {
+                events.extend(Self::parse_quote(&text, market_type, imbalance_levels)?.map(MarketEvent::from));
+            }
+            if events.is_empty() {
+                events.extend(Self::parse_trades(&text, market_type)?.into_iter().map(MarketEvent::from));
+            }
+            for event in events {
+                let kind = event.kind();
+                if let Err(e) = event_sender.send(event).await {
+                    error!("Failed to send {}: {}", kind, e);
                 }
             }
         }
@@ -111,38 +284,94 @@ impl HyperliquidClient {
 #[async_trait]
 impl ExchangeClient for HyperliquidClient {
     async fn connect(&mut self, market_type: MarketType) -> Result<()> {
-        let url = self.get_websocket_url();
+        let url = self.endpoint.ws_url(self.get_websocket_url());
         info!("Connecting to Hyperliquid {} WebSocket: {}", market_type.as_str().to_uppercase(), url);
         
-        let (ws_stream, _) = connect_async(url).await?;
+        let ws_stream = self.endpoint.connect(&url).await?;
         self.ws_stream = Some(ws_stream);
         self.market_type = Some(market_type);
         
         info!("Connected to Hyperliquid {} WebSocket", self.market_type.as_ref().unwrap().as_str().to_uppercase());
+        ops_events::record(OpsEventKind::Connect, url.to_string());
         Ok(())
     }
 
     async fn subscribe_trades(&mut self, symbols: Vec<String>) -> Result<()> {
         if let Some(ws_stream) = &mut self.ws_stream {
-            for symbol in symbols {
-                let subscribe_msg = HyperliquidSubscribe {
-                    method: "subscribe".to_string(),
-                    subscription: HyperliquidSubscription {
-                        sub_type: "trades".to_string(),
-                        coin: symbol,
-                    },
-                };
-                
-                let msg = Message::Text(serde_json::to_string(&subscribe_msg)?);
-                ws_stream.send(msg).await?;
+            let mut sub_types = vec!["trades"];
+            if self.quotes {
+                sub_types.push(self.book_channel.subscription_type());
+            }
+            for symbol in &symbols {
+                for sub_type in &sub_types {
+                    let subscribe_msg = HyperliquidSubscribe {
+                        method: "subscribe".to_string(),
+                        subscription: HyperliquidSubscription {
+                            sub_type: sub_type.to_string(),
+                            coin: symbol.clone(),
+                        },
+                    };
+                    
+                    let msg = Message::Text(serde_json::to_string(&subscribe_msg)?);
+                    ws_stream.send(msg).await?;
+                }
             }
             
-            info!("Subscribed to Hyperliquid {} trades", self.market_type.as_ref().unwrap().as_str().to_uppercase());
+            info!("Subscribed to Hyperliquid {} {}", self.market_type.as_ref().unwrap().as_str().to_uppercase(), sub_types.join(", "));
+            ops_events::record(OpsEventKind::Subscribe, symbols.join(","));
             
             // メッセージ処理ループ
-            while let Some(msg) = ws_stream.next().await {
+            let mut disconnect_reason = "stream closed".to_string();
+            let mut keepalive = Keepalive::new()
+                .with_ping(Duration::from_secs(PING_SECONDS), Message::Text(r#"{"method":"ping"}"#.to_string()))
+                .with_watchdog(self.watchdog);
+            let mut result = Ok(());
+            loop {
+                let msg = tokio::select! {
+                    msg = ws_stream.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                    action = keepalive.next() => match action {
+                        KeepaliveAction::Ping(ping) => match ws_stream.send(ping).await {
+                            Ok(()) => continue,
+                            Err(e) => {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        },
+                        KeepaliveAction::Timeout(timeout) => {
+                            let timeout = WatchdogTimeout(timeout);
+                            error!("Hyperliquid {}", timeout);
+                            disconnect_reason = timeout.to_string();
+                            result = Err(timeout.into());
+                            break;
+                        }
+                    },
+                };
                 match msg {
                     Ok(msg) => {
+                        keepalive.received();
+                        if let Some(pong) = keepalive::pong(&msg) {
+                            if let Err(e) = ws_stream.send(pong).await {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        }
+                        #[cfg(feature = "chaos")]
+                        let msg = match self.chaos.as_mut() {
+                            Some(chaos) => match chaos.apply(msg).await {
+                                Ok(msg) => msg,
+                                Err(e) => {
+                                    error!("WebSocket error: {}", e);
+                                    disconnect_reason = format!("WebSocket error: {}", e);
+                                    break;
+                                }
+                            },
+                            None => msg,
+                        };
                         let count = self.trade_counter.fetch_add(1, Ordering::Relaxed);
                         // 1件目、(raw_freq+1)件目、(raw_freq*2+1)件目...を表示
                         if count % (self.raw_freq as u64) == 1 {
@@ -152,18 +381,22 @@ impl ExchangeClient for HyperliquidClient {
                         if count >= 1_000_000 {
                             self.trade_counter.store(0, Ordering::Relaxed);
                         }
-                        if let Err(e) = Self::process_message(msg, &self.trade_sender, &self.trade_counter, self.market_type.as_ref().unwrap()).await {
+                        if let Err(e) = Self::process_message(msg, &self.event_sender, &self.trade_counter, self.market_type.as_ref().unwrap(), self.quotes, self.imbalance_levels).await {
                             error!("Error processing message: {}", e);
+                            crate::utils::quality::record_parse_failure("hyperliquid");
                         }
                     }
                     Err(e) => {
                         error!("WebSocket error: {}", e);
+                        disconnect_reason = format!("WebSocket error: {}", e);
                         break;
                     }
                 }
             }
+            ops_events::record(OpsEventKind::Disconnect, disconnect_reason);
+            return result;
         }
-        
+
         Ok(())
     }
 
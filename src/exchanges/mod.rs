@@ -1,3 +1,6 @@
 pub mod bybit;
 pub mod binance;
-pub mod hyperliquid;
\ No newline at end of file
+pub mod hyperliquid;
+pub mod bitstamp;
+pub mod phemex;
+pub mod backpack;
\ No newline at end of file
@@ -0,0 +1,371 @@
+use crate::models::{trade::{Trade, Side}, market_event::MarketEvent, market_type::MarketType, ExchangeClient};
+use crate::utils::keepalive::{self, Keepalive, KeepaliveAction, WatchdogTimeout};
+use crate::utils::endpoint::Endpoint;
+use crate::utils::ops_events::{self, OpsEventKind};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{error, info};
+#[cfg(feature = "chaos")]
+use crate::utils::chaos::{ChaosConfig, ChaosInjector};
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+const PRODUCTS_URL: &str = "https://api.phemex.com/public/products";
+const HEARTBEAT_SECONDS: u64 = 5; // 30秒以内に server.ping が必要
+
+#[derive(Debug, Serialize)]
+struct PhemexRequest {
+    id: u64,
+    method: String,
+    params: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhemexProductsResponse {
+    data: PhemexProductsData,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhemexProductsData {
+    products: Vec<PhemexProduct>,
+    currencies: Vec<PhemexCurrency>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PhemexProduct {
+    symbol: String,
+    #[serde(rename = "type")]
+    product_type: String,
+    price_scale: Option<u32>,
+    base_currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PhemexCurrency {
+    currency: String,
+    value_scale: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct PhemexTradeMessage {
+    symbol: String,
+    #[serde(rename = "type")]
+    message_type: String,
+    #[serde(default)]
+    trades: Vec<(i64, String, i64, i64)>,  // [timestamp(ns), side, priceEp, qty(Ev or contracts)]
+    #[serde(default)]
+    trades_p: Vec<(i64, String, String, String)>,  // USDT 建て: [timestamp(ns), side, price, qty]
+}
+
+/// Ep/Ev を実数に変換するためのスケール
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhemexScale {
+    pub price_scale: u32,
+    pub qty_scale: u32,  // Spot は base 通貨の valueScale, Contract は 0 (枚数)
+}
+
+impl PhemexScale {
+    pub fn price(&self, price_ep: i64) -> f64 {
+        price_ep as f64 / 10f64.powi(self.price_scale as i32)
+    }
+
+    pub fn quantity(&self, qty_ev: i64) -> f64 {
+        qty_ev as f64 / 10f64.powi(self.qty_scale as i32)
+    }
+}
+
+pub struct PhemexClient {
+    ws_stream: Option<WsStream>,
+    event_sender: mpsc::Sender<MarketEvent>,
+    trade_counter: AtomicU64,
+    market_type: Option<MarketType>,
+    raw_freq: u32,
+    scales: HashMap<String, PhemexScale>,
+    watchdog: Option<Duration>,
+    endpoint: Endpoint,
+    #[cfg(feature = "chaos")]
+    chaos: Option<ChaosInjector>,
+}
+
+impl PhemexClient {
+    pub fn new(event_sender: mpsc::Sender<MarketEvent>, raw_freq: u32) -> Self {
+        Self {
+            ws_stream: None,
+            event_sender,
+            trade_counter: AtomicU64::new(0),
+            market_type: None,
+            raw_freq,
+            scales: HashMap::new(),
+            watchdog: None,
+            endpoint: Endpoint::new(),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+
+    /// timeout の間フレームを受信しなければ切断し, WatchdogTimeout を返す (呼び出し側で再接続する)
+    pub fn with_watchdog(mut self, timeout: Duration) -> Self {
+        self.watchdog = Some(timeout);
+        self
+    }
+
+    /// 既定の接続先の代わりに別ホスト・プロキシを使う (地域制限の回避)
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = endpoint;
+        self
+    }
+
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, config: ChaosConfig) -> Self {
+        self.chaos = Some(ChaosInjector::new(config));
+        self
+    }
+
+    fn get_websocket_url(&self) -> &'static str {
+        "wss://ws.phemex.com"
+    }
+
+    fn subscribe_method(market_type: &MarketType) -> &'static str {
+        match market_type {
+            MarketType::Linear => "trade_p.subscribe",
+            MarketType::Spot | MarketType::Inverse => "trade.subscribe",
+        }
+    }
+
+    /// 商品一覧から symbol ごとのスケールを取得する
+    pub async fn fetch_product_scales() -> Result<HashMap<String, PhemexScale>> {
+        let response: PhemexProductsResponse = reqwest::get(PRODUCTS_URL).await?.error_for_status()?.json().await?;
+
+        let value_scales: HashMap<String, u32> = response
+            .data
+            .currencies
+            .into_iter()
+            .map(|c| (c.currency, c.value_scale))
+            .collect();
+
+        let mut scales = HashMap::new();
+        for product in response.data.products {
+            let Some(price_scale) = product.price_scale else { continue };
+            let qty_scale = match product.product_type.as_str() {
+                "Spot" => product
+                    .base_currency
+                    .as_ref()
+                    .and_then(|c| value_scales.get(c))
+                    .copied()
+                    .unwrap_or(8),
+                _ => 0,
+            };
+            scales.insert(product.symbol, PhemexScale { price_scale, qty_scale });
+        }
+
+        Ok(scales)
+    }
+
+    /// テキストフレームを Trade に正規化する (約定以外, 購読直後の snapshot は空で返す)
+    pub fn parse_trades(text: &str, market_type: &MarketType, scales: &HashMap<String, PhemexScale>) -> Result<Vec<Trade>> {
+        let mut trades = Vec::new();
+
+        if let Ok(message) = serde_json::from_str::<PhemexTradeMessage>(text) {
+            // snapshot は過去の約定履歴なので取り込まない
+            if message.message_type != "incremental" {
+                return Ok(trades);
+            }
+
+            let parse_side = |side: &str| match side {
+                "Buy" => Side::Buy,
+                "Sell" => Side::Sell,
+                _ => Side::Buy, // デフォルト
+            };
+
+            // Phemex の約定には ID がなく, 同じ ns タイムスタンプで複数の約定が届くこともあるため
+            // タイムスタンプと配列内の位置を組み合わせて代用する
+            for (index, (timestamp_ns, side, price_ep, qty)) in message.trades.into_iter().enumerate() {
+                let scale = scales
+                    .get(&message.symbol)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown Phemex scale for symbol: {}", message.symbol))?;
+                trades.push(Trade::new(
+                    "phemex".to_string(),
+                    market_type.clone(),
+                    message.symbol.clone(),
+                    format!("{}-{}", timestamp_ns, index),
+                    scale.price(price_ep),
+                    scale.quantity(qty),
+                    parse_side(&side),
+                    DateTime::from_timestamp_nanos(timestamp_ns),
+                ));
+            }
+
+            for (index, (timestamp_ns, side, price, qty)) in message.trades_p.into_iter().enumerate() {
+                trades.push(Trade::new(
+                    "phemex".to_string(),
+                    market_type.clone(),
+                    message.symbol.clone(),
+                    format!("{}-{}", timestamp_ns, index),
+                    price.parse::<f64>().unwrap_or(0.0),
+                    qty.parse::<f64>().unwrap_or(0.0),
+                    parse_side(&side),
+                    DateTime::from_timestamp_nanos(timestamp_ns),
+                ));
+            }
+        }
+        Ok(trades)
+    }
+
+    async fn process_message(
+        msg: Message,
+        event_sender: &mpsc::Sender<MarketEvent>,
+        _trade_counter: &AtomicU64,
+        market_type: &MarketType,
+        scales: &HashMap<String, PhemexScale>,
+    ) -> Result<()> {
+        if let Message::Text(text) = msg {
+            for trade in Self::parse_trades(&text, market_type, scales)? {
+                if let Err(e) = event_sender.send(MarketEvent::Trade(trade)).await {
+                    error!("Failed to send trade: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExchangeClient for PhemexClient {
+    async fn connect(&mut self, market_type: MarketType) -> Result<()> {
+        self.scales = Self::fetch_product_scales().await?;
+        info!("Loaded {} Phemex product scales", self.scales.len());
+
+        let url = self.endpoint.ws_url(self.get_websocket_url());
+        info!("Connecting to Phemex {} WebSocket: {}", market_type.as_str().to_uppercase(), url);
+
+        let ws_stream = self.endpoint.connect(&url).await?;
+        self.ws_stream = Some(ws_stream);
+        self.market_type = Some(market_type);
+
+        info!("Connected to Phemex {} WebSocket", self.market_type.as_ref().unwrap().as_str().to_uppercase());
+        ops_events::record(OpsEventKind::Connect, url.to_string());
+        Ok(())
+    }
+
+    async fn subscribe_trades(&mut self, symbols: Vec<String>) -> Result<()> {
+        let market_type = self.market_type.clone().unwrap();
+        if let Some(ws_stream) = &mut self.ws_stream {
+            for (i, symbol) in symbols.iter().enumerate() {
+                if market_type != MarketType::Linear && !self.scales.contains_key(symbol) {
+                    return Err(anyhow::anyhow!("Unknown Phemex symbol: {}", symbol));
+                }
+                let subscribe_msg = PhemexRequest {
+                    id: i as u64 + 1,
+                    method: Self::subscribe_method(&market_type).to_string(),
+                    params: vec![symbol.clone()],
+                };
+
+                let msg = Message::Text(serde_json::to_string(&subscribe_msg)?);
+                ws_stream.send(msg).await?;
+            }
+
+            info!("Subscribed to Phemex {} trades", market_type.as_str().to_uppercase());
+            ops_events::record(OpsEventKind::Subscribe, symbols.join(","));
+
+            let mut disconnect_reason = "stream closed".to_string();
+            let ping = PhemexRequest { id: 0, method: "server.ping".to_string(), params: vec![] };
+            let mut keepalive = Keepalive::new()
+                .with_ping(Duration::from_secs(HEARTBEAT_SECONDS), Message::Text(serde_json::to_string(&ping)?))
+                .with_watchdog(self.watchdog);
+            let mut result = Ok(());
+
+            // メッセージ処理ループ
+            loop {
+                let msg = tokio::select! {
+                    msg = ws_stream.next() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
+                    action = keepalive.next() => match action {
+                        KeepaliveAction::Ping(ping) => match ws_stream.send(ping).await {
+                            Ok(()) => continue,
+                            Err(e) => {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        },
+                        KeepaliveAction::Timeout(timeout) => {
+                            let timeout = WatchdogTimeout(timeout);
+                            error!("Phemex {}", timeout);
+                            disconnect_reason = timeout.to_string();
+                            result = Err(timeout.into());
+                            break;
+                        }
+                    },
+                };
+                match msg {
+                    Ok(msg) => {
+                        keepalive.received();
+                        if let Some(pong) = keepalive::pong(&msg) {
+                            if let Err(e) = ws_stream.send(pong).await {
+                                error!("WebSocket error: {}", e);
+                                disconnect_reason = format!("WebSocket error: {}", e);
+                                break;
+                            }
+                        }
+                        #[cfg(feature = "chaos")]
+                        let msg = match self.chaos.as_mut() {
+                            Some(chaos) => match chaos.apply(msg).await {
+                                Ok(msg) => msg,
+                                Err(e) => {
+                                    error!("WebSocket error: {}", e);
+                                    disconnect_reason = format!("WebSocket error: {}", e);
+                                    break;
+                                }
+                            },
+                            None => msg,
+                        };
+                        let count = self.trade_counter.fetch_add(1, Ordering::Relaxed);
+                        // 1件目、(raw_freq+1)件目、(raw_freq*2+1)件目...を表示
+                        if count % (self.raw_freq as u64) == 1 {
+                            tracing::debug!("Raw message: {:?}", msg);
+                        }
+                        // カウンターを定期的にリセット (100万件毎)
+                        if count >= 1_000_000 {
+                            self.trade_counter.store(0, Ordering::Relaxed);
+                        }
+                        if let Err(e) = Self::process_message(msg, &self.event_sender, &self.trade_counter, &market_type, &self.scales).await {
+                            error!("Error processing message: {}", e);
+                            crate::utils::quality::record_parse_failure("phemex");
+                        }
+                    }
+                    Err(e) => {
+                        error!("WebSocket error: {}", e);
+                        disconnect_reason = format!("WebSocket error: {}", e);
+                        break;
+                    }
+                }
+            }
+            ops_events::record(OpsEventKind::Disconnect, disconnect_reason);
+            return result;
+        }
+
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        if let Some(mut ws_stream) = self.ws_stream.take() {
+            ws_stream.close(None).await?;
+            info!("Disconnected from Phemex {} WebSocket",
+                  self.market_type.as_ref().map_or("Unknown", |mt| mt.as_str()).to_uppercase());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::interval;
+use tracing::error;
+use crate::models::market_type::MarketType;
+use super::ExecutionClient;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Balance {
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub asset: String,
+    pub wallet_balance: f64,
+    pub available_balance: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Balance {
+    pub fn to_timeseries_document(&self) -> Document {
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "exchange": &self.exchange,
+                "market_type": self.market_type.as_str(),
+                "asset": &self.asset
+            },
+            "wallet_balance": self.wallet_balance,
+            "available_balance": self.available_balance
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub size: f64,  // ロングは正, ショートは負
+    pub entry_price: Option<f64>,
+    pub mark_price: Option<f64>,
+    pub unrealized_pnl: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Position {
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // マーケットデータと同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "size": self.size,
+            "entry_price": self.entry_price,
+            "mark_price": self.mark_price,
+            "unrealized_pnl": self.unrealized_pnl
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum AccountUpdate {
+    Balance(Balance),
+    Position(Position),
+}
+
+/// 残高とポジションを定期的にポーリングして送信する
+pub struct AccountTracker {
+    client: Box<dyn ExecutionClient>,
+    market_type: MarketType,
+    update_sender: mpsc::Sender<AccountUpdate>,
+    poll_interval: Duration,
+}
+
+impl AccountTracker {
+    pub fn new(
+        client: Box<dyn ExecutionClient>,
+        market_type: MarketType,
+        update_sender: mpsc::Sender<AccountUpdate>,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            client,
+            market_type,
+            update_sender,
+            poll_interval,
+        }
+    }
+
+    pub async fn start(self) {
+        tracing::info!("AccountTracker started for {} {} (interval: {:?})",
+            self.client.exchange(), self.market_type.as_str().to_uppercase(), self.poll_interval);
+
+        let mut interval = interval(self.poll_interval);
+        loop {
+            interval.tick().await;
+
+            match self.client.balances(&self.market_type).await {
+                Ok(balances) => {
+                    for balance in balances {
+                        if self.update_sender.send(AccountUpdate::Balance(balance)).await.is_err() {
+                            error!("Account update receiver dropped");
+                            return;
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to fetch {} balances: {}", self.client.exchange(), e),
+            }
+
+            if self.market_type == MarketType::Spot {
+                continue;
+            }
+
+            match self.client.positions(&self.market_type).await {
+                Ok(positions) => {
+                    for position in positions {
+                        if self.update_sender.send(AccountUpdate::Position(position)).await.is_err() {
+                            error!("Account update receiver dropped");
+                            return;
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to fetch {} positions: {}", self.client.exchange(), e),
+            }
+        }
+    }
+}
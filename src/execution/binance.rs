@@ -0,0 +1,294 @@
+use super::{account::{Balance, Position}, format_decimal, order::{AmendRequest, CancelRequest, OrderAck, OrderRequest, OrderSide, OrderType, TimeInForce}, ExecutionClient};
+use crate::auth::{binance::{sign_query, API_KEY_HEADER}, Credentials};
+use crate::models::market_type::MarketType;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::Method;
+use serde::Deserialize;
+use tracing::info;
+
+const RECV_WINDOW_MS: u64 = 5000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceOrderResponse {
+    symbol: String,
+    order_id: u64,
+    client_order_id: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceSpotAccount {
+    balances: Vec<BinanceSpotBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceSpotBalance {
+    asset: String,
+    free: String,
+    locked: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceFuturesBalance {
+    asset: String,
+    balance: String,
+    available_balance: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinancePositionRisk {
+    symbol: String,
+    position_amt: String,
+    entry_price: String,
+    mark_price: String,
+    un_realized_profit: String,
+}
+
+impl BinanceOrderResponse {
+    fn into_ack(self) -> OrderAck {
+        OrderAck {
+            exchange: "binance".to_string(),
+            symbol: self.symbol,
+            order_id: self.order_id.to_string(),
+            client_order_id: self.client_order_id,
+            status: self.status,
+        }
+    }
+}
+
+pub struct BinanceExecutionClient {
+    http: reqwest::Client,
+    credentials: Credentials,
+    testnet: bool,
+}
+
+impl BinanceExecutionClient {
+    pub fn new(credentials: Credentials) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            credentials,
+            testnet: false,
+        }
+    }
+
+    /// テストネット (Spot: testnet.binance.vision, 先物: testnet.binancefuture.com) に発注する (テストネット用の API キーが必要)
+    pub fn with_testnet(mut self, testnet: bool) -> Self {
+        self.testnet = testnet;
+        self
+    }
+
+    fn get_rest_url(&self, market_type: &MarketType) -> &'static str {
+        match (market_type, self.testnet) {
+            (MarketType::Spot, false) => "https://api.binance.com",
+            (MarketType::Linear, false) => "https://fapi.binance.com",
+            (MarketType::Inverse, false) => "https://dapi.binance.com",
+            (MarketType::Spot, true) => "https://testnet.binance.vision",
+            (MarketType::Linear | MarketType::Inverse, true) => "https://testnet.binancefuture.com",
+        }
+    }
+
+    fn get_order_path(&self, market_type: &MarketType) -> &'static str {
+        match market_type {
+            MarketType::Spot => "/api/v3",
+            MarketType::Linear => "/fapi/v1",
+            MarketType::Inverse => "/dapi/v1",
+        }
+    }
+
+    fn order_path(&self, market_type: &MarketType, endpoint: &str) -> String {
+        format!("{}{}", self.get_order_path(market_type), endpoint)
+    }
+
+    fn side(side: OrderSide) -> &'static str {
+        match side {
+            OrderSide::Buy => "BUY",
+            OrderSide::Sell => "SELL",
+        }
+    }
+
+    async fn signed_request<T: serde::de::DeserializeOwned>(
+        &self,
+        method: Method,
+        market_type: &MarketType,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<T> {
+        let query = sign_query(&self.credentials, params, Utc::now().timestamp_millis(), RECV_WINDOW_MS);
+        let url = format!("{}{}?{}", self.get_rest_url(market_type), path, query);
+
+        let response = self
+            .http
+            .request(method, &url)
+            .header(API_KEY_HEADER, &self.credentials.api_key)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow::anyhow!("Binance {} request failed ({}): {}", path, status, body));
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+}
+
+#[async_trait]
+impl ExecutionClient for BinanceExecutionClient {
+    fn exchange(&self) -> &'static str {
+        "binance"
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderAck> {
+        let mut params = vec![
+            ("symbol", request.symbol.clone()),
+            ("side", Self::side(request.side).to_string()),
+            ("quantity", format_decimal(request.quantity)),
+            ("newClientOrderId", request.client_order_id.clone()),
+        ];
+
+        match (request.order_type, request.time_in_force) {
+            (OrderType::Market, _) => params.push(("type", "MARKET".to_string())),
+            (OrderType::Limit, tif) => {
+                let price = request.price.ok_or_else(|| anyhow::anyhow!("Limit order requires price"))?;
+                params.push(("price", format_decimal(price)));
+                match (tif, &request.market_type) {
+                    // Spot の post-only は LIMIT_MAKER (timeInForce なし)
+                    (TimeInForce::PostOnly, MarketType::Spot) => params.push(("type", "LIMIT_MAKER".to_string())),
+                    (tif, _) => {
+                        params.push(("type", "LIMIT".to_string()));
+                        params.push(("timeInForce", match tif {
+                            TimeInForce::GoodTillCancel => "GTC",
+                            TimeInForce::ImmediateOrCancel => "IOC",
+                            TimeInForce::FillOrKill => "FOK",
+                            TimeInForce::PostOnly => "GTX",
+                        }.to_string()));
+                    }
+                }
+            }
+        }
+
+        if request.reduce_only && request.market_type != MarketType::Spot {
+            params.push(("reduceOnly", "true".to_string()));
+        }
+
+        let response: BinanceOrderResponse = self.signed_request(Method::POST, &request.market_type, &self.order_path(&request.market_type, "/order"), &params).await?;
+        info!("Placed Binance {} order: {} {} {} (order_id: {})",
+              request.market_type.as_str().to_uppercase(), request.symbol, request.side.as_str(), request.quantity, response.order_id);
+        Ok(response.into_ack())
+    }
+
+    async fn amend_order(&self, request: &AmendRequest) -> Result<OrderAck> {
+        if request.market_type == MarketType::Spot {
+            return Err(anyhow::anyhow!("Binance spot does not support amending orders; cancel and re-place instead"));
+        }
+
+        // 先物の修正は quantity と price の両方が必須
+        let quantity = request.quantity.ok_or_else(|| anyhow::anyhow!("Binance amend requires quantity"))?;
+        let price = request.price.ok_or_else(|| anyhow::anyhow!("Binance amend requires price"))?;
+        let params = vec![
+            ("symbol", request.symbol.clone()),
+            ("orderId", request.order_id.clone()),
+            ("side", Self::side(request.side).to_string()),
+            ("quantity", format_decimal(quantity)),
+            ("price", format_decimal(price)),
+        ];
+
+        let response: BinanceOrderResponse = self.signed_request(Method::PUT, &request.market_type, &self.order_path(&request.market_type, "/order"), &params).await?;
+        info!("Amended Binance {} order: {} (order_id: {})",
+              request.market_type.as_str().to_uppercase(), request.symbol, request.order_id);
+        Ok(response.into_ack())
+    }
+
+    async fn cancel_order(&self, request: &CancelRequest) -> Result<OrderAck> {
+        let params = vec![
+            ("symbol", request.symbol.clone()),
+            ("orderId", request.order_id.clone()),
+        ];
+
+        let response: BinanceOrderResponse = self.signed_request(Method::DELETE, &request.market_type, &self.order_path(&request.market_type, "/order"), &params).await?;
+        info!("Canceled Binance {} order: {} (order_id: {})",
+              request.market_type.as_str().to_uppercase(), request.symbol, request.order_id);
+        Ok(response.into_ack())
+    }
+
+    async fn open_orders(&self, market_type: &MarketType, symbol: Option<&str>) -> Result<Vec<OrderAck>> {
+        let params: Vec<(&str, String)> = symbol.map(|s| vec![("symbol", s.to_string())]).unwrap_or_default();
+        let response: Vec<BinanceOrderResponse> = self.signed_request(Method::GET, market_type, &self.order_path(market_type, "/openOrders"), &params).await?;
+        Ok(response.into_iter().map(|r| r.into_ack()).collect())
+    }
+
+    async fn balances(&self, market_type: &MarketType) -> Result<Vec<Balance>> {
+        let timestamp = Utc::now();
+        let parse = |v: &str| v.parse::<f64>().unwrap_or(0.0);
+
+        let balances = match market_type {
+            MarketType::Spot => {
+                let account: BinanceSpotAccount = self.signed_request(Method::GET, market_type, "/api/v3/account", &[]).await?;
+                account
+                    .balances
+                    .into_iter()
+                    .map(|b| (b.asset, parse(&b.free) + parse(&b.locked), parse(&b.free)))
+                    .collect::<Vec<_>>()
+            }
+            MarketType::Linear | MarketType::Inverse => {
+                let path = match market_type {
+                    MarketType::Linear => "/fapi/v2/balance",
+                    _ => "/dapi/v1/balance",
+                };
+                let balances: Vec<BinanceFuturesBalance> = self.signed_request(Method::GET, market_type, path, &[]).await?;
+                balances
+                    .into_iter()
+                    .map(|b| (b.asset, parse(&b.balance), parse(&b.available_balance)))
+                    .collect()
+            }
+        };
+
+        Ok(balances
+            .into_iter()
+            .filter(|(_, wallet, _)| *wallet != 0.0)
+            .map(|(asset, wallet_balance, available_balance)| Balance {
+                exchange: "binance".to_string(),
+                market_type: market_type.clone(),
+                asset,
+                wallet_balance,
+                available_balance,
+                timestamp,
+            })
+            .collect())
+    }
+
+    async fn positions(&self, market_type: &MarketType) -> Result<Vec<Position>> {
+        let path = match market_type {
+            MarketType::Spot => return Ok(Vec::new()),
+            MarketType::Linear => "/fapi/v2/positionRisk",
+            MarketType::Inverse => "/dapi/v1/positionRisk",
+        };
+        let timestamp = Utc::now();
+        let risks: Vec<BinancePositionRisk> = self.signed_request(Method::GET, market_type, path, &[]).await?;
+
+        Ok(risks
+            .into_iter()
+            .filter_map(|r| {
+                let size = r.position_amt.parse::<f64>().unwrap_or(0.0);
+                if size == 0.0 {
+                    return None;
+                }
+                Some(Position {
+                    exchange: "binance".to_string(),
+                    market_type: market_type.clone(),
+                    symbol: r.symbol,
+                    size,
+                    entry_price: r.entry_price.parse::<f64>().ok(),
+                    mark_price: r.mark_price.parse::<f64>().ok(),
+                    unrealized_pnl: r.un_realized_profit.parse::<f64>().unwrap_or(0.0),
+                    timestamp,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,284 @@
+use super::{account::{Balance, Position}, format_decimal, order::{AmendRequest, CancelRequest, OrderAck, OrderRequest, OrderType, TimeInForce}, ExecutionClient};
+use crate::auth::{build_query, bybit::request_headers, Credentials};
+use crate::models::market_type::MarketType;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+const REST_URL: &str = "https://api.bybit.com";
+const TESTNET_REST_URL: &str = "https://api-testnet.bybit.com";
+const RECV_WINDOW_MS: u64 = 5000;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitResponse<T> {
+    ret_code: i64,
+    ret_msg: String,
+    result: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitOrderResult {
+    order_id: String,
+    order_link_id: Option<String>,
+    symbol: Option<String>,
+    order_status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitOrderList {
+    list: Vec<BybitOrderResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitWalletList {
+    list: Vec<BybitWallet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitWallet {
+    coin: Vec<BybitCoinBalance>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitCoinBalance {
+    coin: String,
+    wallet_balance: String,
+    #[serde(default)]
+    available_to_withdraw: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitPosition {
+    symbol: String,
+    side: String,
+    size: String,
+    avg_price: String,
+    mark_price: String,
+    unrealised_pnl: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BybitPositionList {
+    list: Vec<BybitPosition>,
+}
+
+pub struct BybitExecutionClient {
+    http: reqwest::Client,
+    credentials: Credentials,
+    testnet: bool,
+}
+
+impl BybitExecutionClient {
+    pub fn new(credentials: Credentials) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            credentials,
+            testnet: false,
+        }
+    }
+
+    /// テストネット (api-testnet.bybit.com) に発注する (テストネット用の API キーが必要)
+    pub fn with_testnet(mut self, testnet: bool) -> Self {
+        self.testnet = testnet;
+        self
+    }
+
+    fn rest_url(&self) -> &'static str {
+        if self.testnet { TESTNET_REST_URL } else { REST_URL }
+    }
+
+    fn ack(symbol: &str, result: BybitOrderResult) -> OrderAck {
+        OrderAck {
+            exchange: "bybit".to_string(),
+            symbol: result.symbol.unwrap_or_else(|| symbol.to_string()),
+            order_id: result.order_id,
+            client_order_id: result.order_link_id.filter(|s| !s.is_empty()),
+            status: result.order_status,
+        }
+    }
+
+    fn check<T>(endpoint: &str, response: BybitResponse<T>) -> Result<T> {
+        if response.ret_code != 0 {
+            return Err(anyhow::anyhow!("Bybit {} failed ({}): {}", endpoint, response.ret_code, response.ret_msg));
+        }
+        response.result.ok_or_else(|| anyhow::anyhow!("Bybit {} returned no result", endpoint))
+    }
+
+    async fn post<T: serde::de::DeserializeOwned>(&self, endpoint: &str, body: serde_json::Value) -> Result<T> {
+        let body = serde_json::to_string(&body)?;
+        let mut request = self
+            .http
+            .post(format!("{}{}", self.rest_url(), endpoint))
+            .header("Content-Type", "application/json")
+            .body(body.clone());
+        for (name, value) in request_headers(&self.credentials, Utc::now().timestamp_millis(), RECV_WINDOW_MS, &body) {
+            request = request.header(name, value);
+        }
+
+        let response: BybitResponse<T> = request.send().await?.json().await?;
+        Self::check(endpoint, response)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, endpoint: &str, params: &[(&str, String)]) -> Result<T> {
+        let query = build_query(params);
+        let mut request = self.http.get(format!("{}{}?{}", self.rest_url(), endpoint, query));
+        for (name, value) in request_headers(&self.credentials, Utc::now().timestamp_millis(), RECV_WINDOW_MS, &query) {
+            request = request.header(name, value);
+        }
+
+        let response: BybitResponse<T> = request.send().await?.json().await?;
+        Self::check(endpoint, response)
+    }
+}
+
+#[async_trait]
+impl ExecutionClient for BybitExecutionClient {
+    fn exchange(&self) -> &'static str {
+        "bybit"
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderAck> {
+        let mut body = json!({
+            "category": request.market_type.as_str(),
+            "symbol": request.symbol,
+            "side": request.side.as_str(),
+            "qty": format_decimal(request.quantity),
+            "orderLinkId": request.client_order_id,
+        });
+
+        match request.order_type {
+            OrderType::Market => {
+                body["orderType"] = json!("Market");
+            }
+            OrderType::Limit => {
+                let price = request.price.ok_or_else(|| anyhow::anyhow!("Limit order requires price"))?;
+                body["orderType"] = json!("Limit");
+                body["price"] = json!(format_decimal(price));
+                body["timeInForce"] = json!(match request.time_in_force {
+                    TimeInForce::GoodTillCancel => "GTC",
+                    TimeInForce::ImmediateOrCancel => "IOC",
+                    TimeInForce::FillOrKill => "FOK",
+                    TimeInForce::PostOnly => "PostOnly",
+                });
+            }
+        }
+
+        if request.reduce_only && request.market_type != MarketType::Spot {
+            body["reduceOnly"] = json!(true);
+        }
+
+        let result: BybitOrderResult = self.post("/v5/order/create", body).await?;
+        info!("Placed Bybit {} order: {} {} {} (order_id: {})",
+              request.market_type.as_str().to_uppercase(), request.symbol, request.side.as_str(), request.quantity, result.order_id);
+        Ok(Self::ack(&request.symbol, result))
+    }
+
+    async fn amend_order(&self, request: &AmendRequest) -> Result<OrderAck> {
+        let mut body = json!({
+            "category": request.market_type.as_str(),
+            "symbol": request.symbol,
+            "orderId": request.order_id,
+        });
+        if let Some(quantity) = request.quantity {
+            body["qty"] = json!(format_decimal(quantity));
+        }
+        if let Some(price) = request.price {
+            body["price"] = json!(format_decimal(price));
+        }
+
+        let result: BybitOrderResult = self.post("/v5/order/amend", body).await?;
+        info!("Amended Bybit {} order: {} (order_id: {})",
+              request.market_type.as_str().to_uppercase(), request.symbol, request.order_id);
+        Ok(Self::ack(&request.symbol, result))
+    }
+
+    async fn cancel_order(&self, request: &CancelRequest) -> Result<OrderAck> {
+        let body = json!({
+            "category": request.market_type.as_str(),
+            "symbol": request.symbol,
+            "orderId": request.order_id,
+        });
+
+        let result: BybitOrderResult = self.post("/v5/order/cancel", body).await?;
+        info!("Canceled Bybit {} order: {} (order_id: {})",
+              request.market_type.as_str().to_uppercase(), request.symbol, request.order_id);
+        Ok(Self::ack(&request.symbol, result))
+    }
+
+    async fn open_orders(&self, market_type: &MarketType, symbol: Option<&str>) -> Result<Vec<OrderAck>> {
+        let mut params = vec![("category", market_type.as_str().to_string())];
+        match symbol {
+            Some(symbol) => params.push(("symbol", symbol.to_string())),
+            // linear/inverse は symbol か settleCoin が必須
+            None if *market_type == MarketType::Linear => params.push(("settleCoin", "USDT".to_string())),
+            None => {}
+        }
+
+        let result: BybitOrderList = self.get("/v5/order/realtime", &params).await?;
+        Ok(result.list.into_iter().map(|r| Self::ack(symbol.unwrap_or_default(), r)).collect())
+    }
+
+    async fn balances(&self, market_type: &MarketType) -> Result<Vec<Balance>> {
+        // 統合取引口座 (UTA) は spot/linear/inverse で同じウォレット
+        let timestamp = Utc::now();
+        let result: BybitWalletList = self
+            .get("/v5/account/wallet-balance", &[("accountType", "UNIFIED".to_string())])
+            .await?;
+
+        Ok(result
+            .list
+            .into_iter()
+            .flat_map(|wallet| wallet.coin)
+            .map(|c| Balance {
+                exchange: "bybit".to_string(),
+                market_type: market_type.clone(),
+                asset: c.coin,
+                wallet_balance: c.wallet_balance.parse::<f64>().unwrap_or(0.0),
+                available_balance: c.available_to_withdraw.parse::<f64>().unwrap_or(0.0),
+                timestamp,
+            })
+            .filter(|b| b.wallet_balance != 0.0)
+            .collect())
+    }
+
+    async fn positions(&self, market_type: &MarketType) -> Result<Vec<Position>> {
+        let mut params = vec![("category", market_type.as_str().to_string())];
+        match market_type {
+            MarketType::Spot => return Ok(Vec::new()),
+            MarketType::Linear => params.push(("settleCoin", "USDT".to_string())),
+            MarketType::Inverse => {}
+        }
+
+        let timestamp = Utc::now();
+        let result: BybitPositionList = self.get("/v5/position/list", &params).await?;
+
+        Ok(result
+            .list
+            .into_iter()
+            .filter_map(|p| {
+                let size = p.size.parse::<f64>().unwrap_or(0.0);
+                if size == 0.0 {
+                    return None;
+                }
+                let size = if p.side == "Sell" { -size } else { size };
+                Some(Position {
+                    exchange: "bybit".to_string(),
+                    market_type: market_type.clone(),
+                    symbol: p.symbol,
+                    size,
+                    entry_price: p.avg_price.parse::<f64>().ok(),
+                    mark_price: p.mark_price.parse::<f64>().ok(),
+                    unrealized_pnl: p.unrealised_pnl.parse::<f64>().unwrap_or(0.0),
+                    timestamp,
+                })
+            })
+            .collect())
+    }
+}
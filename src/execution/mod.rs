@@ -0,0 +1,57 @@
+pub mod order;
+pub mod account;
+pub mod risk;
+pub mod simulated;
+pub mod binance;
+pub mod bybit;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use crate::models::market_type::MarketType;
+use account::{Balance, Position};
+use order::{AmendRequest, CancelRequest, OrderAck, OrderRequest};
+
+/// 注文の発注・修正・取消を行う取引所共通のインターフェース
+#[async_trait]
+pub trait ExecutionClient: Send + Sync {
+    fn exchange(&self) -> &'static str;
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderAck>;
+    async fn amend_order(&self, request: &AmendRequest) -> Result<OrderAck>;
+    async fn cancel_order(&self, request: &CancelRequest) -> Result<OrderAck>;
+    async fn open_orders(&self, market_type: &MarketType, symbol: Option<&str>) -> Result<Vec<OrderAck>>;
+    async fn balances(&self, market_type: &MarketType) -> Result<Vec<Balance>>;
+    async fn positions(&self, market_type: &MarketType) -> Result<Vec<Position>>;
+}
+
+// 模擬取引所のように, 約定を流す側と発注側で同じクライアントを共有する場合用
+#[async_trait]
+impl<T: ExecutionClient + ?Sized> ExecutionClient for Arc<T> {
+    fn exchange(&self) -> &'static str {
+        (**self).exchange()
+    }
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderAck> {
+        (**self).place_order(request).await
+    }
+    async fn amend_order(&self, request: &AmendRequest) -> Result<OrderAck> {
+        (**self).amend_order(request).await
+    }
+    async fn cancel_order(&self, request: &CancelRequest) -> Result<OrderAck> {
+        (**self).cancel_order(request).await
+    }
+    async fn open_orders(&self, market_type: &MarketType, symbol: Option<&str>) -> Result<Vec<OrderAck>> {
+        (**self).open_orders(market_type, symbol).await
+    }
+    async fn balances(&self, market_type: &MarketType) -> Result<Vec<Balance>> {
+        (**self).balances(market_type).await
+    }
+    async fn positions(&self, market_type: &MarketType) -> Result<Vec<Position>> {
+        (**self).positions(market_type).await
+    }
+}
+
+/// 価格・数量を取引所に送る文字列に変換する (指数表記を避ける)
+pub(crate) fn format_decimal(value: f64) -> String {
+    let s = format!("{:.10}", value);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
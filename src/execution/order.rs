@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use crate::models::market_type::MarketType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderSide::Buy => "Buy",
+            OrderSide::Sell => "Sell",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OrderType {
+    Market,
+    Limit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeInForce {
+    GoodTillCancel,
+    ImmediateOrCancel,
+    FillOrKill,
+    PostOnly,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderRequest {
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub quantity: f64,
+    pub price: Option<f64>,  // Limit のみ
+    pub time_in_force: TimeInForce,
+    pub reduce_only: bool,
+    pub client_order_id: String,
+}
+
+impl OrderRequest {
+    pub fn market(market_type: MarketType, symbol: &str, side: OrderSide, quantity: f64) -> Self {
+        Self {
+            market_type,
+            symbol: symbol.to_string(),
+            side,
+            order_type: OrderType::Market,
+            quantity,
+            price: None,
+            time_in_force: TimeInForce::ImmediateOrCancel,
+            reduce_only: false,
+            client_order_id: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+
+    pub fn limit(market_type: MarketType, symbol: &str, side: OrderSide, quantity: f64, price: f64) -> Self {
+        Self {
+            market_type,
+            symbol: symbol.to_string(),
+            side,
+            order_type: OrderType::Limit,
+            quantity,
+            price: Some(price),
+            time_in_force: TimeInForce::GoodTillCancel,
+            reduce_only: false,
+            client_order_id: uuid::Uuid::new_v4().simple().to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmendRequest {
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub order_id: String,
+    pub side: OrderSide,  // Binance 先物の修正は side が必須
+    pub quantity: Option<f64>,
+    pub price: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelRequest {
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub order_id: String,
+}
+
+/// 取引所からの注文応答 (取引所ごとの状態文字列はそのまま保持)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderAck {
+    pub exchange: String,
+    pub symbol: String,
+    pub order_id: String,
+    pub client_order_id: Option<String>,
+    pub status: Option<String>,
+}
@@ -0,0 +1,247 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::warn;
+use crate::models::{market_type::MarketType, trade_candle::TradeCandle};
+use super::account::{Balance, Position};
+use super::order::{AmendRequest, CancelRequest, OrderAck, OrderRequest, OrderSide};
+use super::ExecutionClient;
+
+/// 発注前チェックの上限値
+/// 書式: "max_notional=1000,max_position=0.5,max_position.BTCUSDT=0.1,collar=0.02,kill=false"
+/// - max_notional: 1注文あたりの想定元本 (quote 通貨, Inverse は契約数)
+/// - max_position: symbol ごとの最大ポジション (数量の絶対値), max_position.{symbol} で個別指定
+/// - collar: 直近ローソク足 VWAP からの指値の乖離上限 (0.02 = 2%)
+/// - kill: true で起動時からキルスイッチ有効
+#[derive(Debug, Clone, Default)]
+pub struct RiskLimits {
+    pub max_order_notional: Option<f64>,
+    pub max_position: Option<f64>,
+    pub max_position_per_symbol: HashMap<String, f64>,
+    pub price_collar: Option<f64>,
+    pub kill_switch: bool,
+}
+
+impl RiskLimits {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut limits = Self::default();
+
+        for entry in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid risk entry: {}. Use <key>=<value>", entry))?;
+            match key.trim() {
+                "max_notional" => limits.max_order_notional = Some(Self::parse_limit(value)?),
+                "max_position" => limits.max_position = Some(Self::parse_limit(value)?),
+                "collar" => limits.price_collar = Some(Self::parse_limit(value)?),
+                "kill" => limits.kill_switch = value.trim().parse()?,
+                k => match k.strip_prefix("max_position.") {
+                    Some(symbol) if !symbol.is_empty() => {
+                        limits.max_position_per_symbol.insert(symbol.to_string(), Self::parse_limit(value)?);
+                    }
+                    _ => return Err(anyhow::anyhow!("Unknown risk key: {}", k)),
+                },
+            }
+        }
+
+        Ok(limits)
+    }
+
+    fn parse_limit(value: &str) -> Result<f64> {
+        let limit: f64 = value.trim().parse()?;
+        if !(limit.is_finite() && limit > 0.0) {
+            return Err(anyhow::anyhow!("Risk limit must be a positive number: {}", limit));
+        }
+        Ok(limit)
+    }
+
+    pub fn max_position_for(&self, symbol: &str) -> Option<f64> {
+        self.max_position_per_symbol.get(symbol).copied().or(self.max_position)
+    }
+}
+
+/// 発注前チェックで拒否された理由 (anyhow::Error から downcast 可能)
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskViolation {
+    KillSwitch,
+    NoReferencePrice { symbol: String },
+    OrderNotional { symbol: String, notional: f64, limit: f64 },
+    Position { symbol: String, projected: f64, limit: f64 },
+    PriceCollar { symbol: String, price: f64, reference: f64, limit: f64 },
+}
+
+impl fmt::Display for RiskViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskViolation::KillSwitch => write!(f, "Kill switch is engaged"),
+            RiskViolation::NoReferencePrice { symbol } => write!(f, "No reference price for {}", symbol),
+            RiskViolation::OrderNotional { symbol, notional, limit } => {
+                write!(f, "Order notional {:.2} exceeds limit {:.2} for {}", notional, limit, symbol)
+            }
+            RiskViolation::Position { symbol, projected, limit } => {
+                write!(f, "Projected position {} exceeds limit {} for {}", projected, limit, symbol)
+            }
+            RiskViolation::PriceCollar { symbol, price, reference, limit } => write!(
+                f,
+                "Price {} deviates more than {:.2}% from reference {} for {}",
+                price, limit * 100.0, reference, symbol
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RiskViolation {}
+
+/// ExecutionClient をラップし, 発注・修正の前にリスク上限をチェックする
+/// 参照価格は update_reference() で直近ローソク足の VWAP を渡す.
+/// 参照価格がない symbol は想定元本・乖離チェックができないため拒否する (fail-closed).
+pub struct RiskGuard {
+    inner: Box<dyn ExecutionClient>,
+    limits: RiskLimits,
+    kill_switch: Arc<AtomicBool>,
+    reference_prices: RwLock<HashMap<String, f64>>,
+}
+
+impl RiskGuard {
+    pub fn new(inner: Box<dyn ExecutionClient>, limits: RiskLimits) -> Self {
+        let kill_switch = Arc::new(AtomicBool::new(limits.kill_switch));
+        Self {
+            inner,
+            limits,
+            kill_switch,
+            reference_prices: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 外部 (シグナルハンドラや監視タスク) から操作するためのキルスイッチ
+    pub fn kill_switch(&self) -> Arc<AtomicBool> {
+        self.kill_switch.clone()
+    }
+
+    pub fn engage_kill_switch(&self) {
+        warn!("Risk kill switch engaged");
+        self.kill_switch.store(true, Ordering::SeqCst);
+    }
+
+    pub fn release_kill_switch(&self) {
+        self.kill_switch.store(false, Ordering::SeqCst);
+    }
+
+    pub fn update_reference(&self, candle: &TradeCandle) {
+        if let Some(vwap) = candle.vwap() {
+            self.set_reference_price(&candle.symbol, vwap);
+        }
+    }
+
+    pub fn set_reference_price(&self, symbol: &str, price: f64) {
+        self.reference_prices.write().unwrap().insert(symbol.to_string(), price);
+    }
+
+    pub fn reference_price(&self, symbol: &str) -> Option<f64> {
+        self.reference_prices.read().unwrap().get(symbol).copied()
+    }
+
+    fn notional(market_type: &MarketType, quantity: f64, price: f64) -> f64 {
+        match market_type {
+            MarketType::Inverse => quantity,  // 数量は USD 建ての契約数
+            MarketType::Spot | MarketType::Linear => quantity * price,
+        }
+    }
+
+    /// 注文を出してよいか判定する (ポジションは取引所から取得した現在値を使う)
+    pub async fn check_order(&self, request: &OrderRequest) -> Result<()> {
+        self.check_price(&request.market_type, &request.symbol, request.quantity, request.price)?;
+
+        if let Some(limit) = self.limits.max_position_for(&request.symbol) {
+            if request.market_type != MarketType::Spot {
+                let current = self
+                    .inner
+                    .positions(&request.market_type)
+                    .await?
+                    .into_iter()
+                    .filter(|p| p.symbol == request.symbol)
+                    .map(|p| p.size)
+                    .sum::<f64>();
+                let signed_quantity = match request.side {
+                    OrderSide::Buy => request.quantity,
+                    OrderSide::Sell => -request.quantity,
+                };
+                let projected = current + signed_quantity;
+                // 上限超過中でもポジションを減らす注文は通す
+                if projected.abs() > limit && projected.abs() > current.abs() {
+                    return Err(RiskViolation::Position { symbol: request.symbol.clone(), projected, limit }.into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// キルスイッチ・想定元本・価格乖離をチェックする
+    fn check_price(&self, market_type: &MarketType, symbol: &str, quantity: f64, price: Option<f64>) -> Result<()> {
+        if self.kill_switch.load(Ordering::SeqCst) {
+            return Err(RiskViolation::KillSwitch.into());
+        }
+
+        if self.limits.max_order_notional.is_none() && self.limits.price_collar.is_none() {
+            return Ok(());
+        }
+
+        let reference = self
+            .reference_price(symbol)
+            .ok_or_else(|| RiskViolation::NoReferencePrice { symbol: symbol.to_string() })?;
+
+        if let Some(limit) = self.limits.max_order_notional {
+            let notional = Self::notional(market_type, quantity, price.unwrap_or(reference));
+            if notional > limit {
+                return Err(RiskViolation::OrderNotional { symbol: symbol.to_string(), notional, limit }.into());
+            }
+        }
+
+        if let (Some(limit), Some(price)) = (self.limits.price_collar, price) {
+            if (price - reference).abs() / reference > limit {
+                return Err(RiskViolation::PriceCollar { symbol: symbol.to_string(), price, reference, limit }.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ExecutionClient for RiskGuard {
+    fn exchange(&self) -> &'static str {
+        self.inner.exchange()
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderAck> {
+        self.check_order(request).await?;
+        self.inner.place_order(request).await
+    }
+
+    async fn amend_order(&self, request: &AmendRequest) -> Result<OrderAck> {
+        // 修正で数量が増える場合のポジション上限は元注文が分からないためここでは見ない
+        self.check_price(&request.market_type, &request.symbol, request.quantity.unwrap_or(0.0), request.price)?;
+        self.inner.amend_order(request).await
+    }
+
+    // 取消と照会はキルスイッチ中でも常に許可する
+    async fn cancel_order(&self, request: &CancelRequest) -> Result<OrderAck> {
+        self.inner.cancel_order(request).await
+    }
+
+    async fn open_orders(&self, market_type: &MarketType, symbol: Option<&str>) -> Result<Vec<OrderAck>> {
+        self.inner.open_orders(market_type, symbol).await
+    }
+
+    async fn balances(&self, market_type: &MarketType) -> Result<Vec<Balance>> {
+        self.inner.balances(market_type).await
+    }
+
+    async fn positions(&self, market_type: &MarketType) -> Result<Vec<Position>> {
+        self.inner.positions(market_type).await
+    }
+}
@@ -0,0 +1,361 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use crate::models::{market_type::MarketType, trade::Trade};
+use super::account::{Balance, Position};
+use super::order::{AmendRequest, CancelRequest, OrderAck, OrderRequest, OrderSide, OrderType, TimeInForce};
+use super::ExecutionClient;
+
+/// 約定価格に加えるスリッページ (常に不利な方向)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlippageModel {
+    None,
+    /// 約定価格の一定割合 (bps)
+    FixedBps(f64),
+    /// 数量に比例する割合 (bps / 1 単位)
+    Linear { bps_per_unit: f64 },
+}
+
+impl SlippageModel {
+    fn apply(&self, price: f64, quantity: f64, side: OrderSide) -> f64 {
+        let bps = match self {
+            SlippageModel::None => 0.0,
+            SlippageModel::FixedBps(bps) => *bps,
+            SlippageModel::Linear { bps_per_unit } => bps_per_unit * quantity,
+        };
+        match side {
+            OrderSide::Buy => price * (1.0 + bps / 10_000.0),
+            OrderSide::Sell => price * (1.0 - bps / 10_000.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulatedConfig {
+    /// 発注から板に届くまでの遅延 (約定ストリームの時刻で計測)
+    pub latency: Duration,
+    pub slippage: SlippageModel,
+    /// 約定代金に対する手数料率 (0.0005 = 5bps)
+    pub fee_rate: f64,
+    pub quote_asset: String,
+    pub initial_balance: f64,
+}
+
+impl Default for SimulatedConfig {
+    fn default() -> Self {
+        Self {
+            latency: Duration::zero(),
+            slippage: SlippageModel::None,
+            fee_rate: 0.0,
+            quote_asset: "USDT".to_string(),
+            initial_balance: 0.0,
+        }
+    }
+}
+
+/// シミュレーション上の約定
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub order_id: String,
+    pub client_order_id: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub price: f64,
+    pub quantity: f64,
+    pub fee: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct SimulatedOrder {
+    order_id: String,
+    request: OrderRequest,
+    active_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct SimulatedPosition {
+    size: f64,
+    entry_price: f64,
+}
+
+#[derive(Debug, Default)]
+struct SimulatedState {
+    next_order_id: u64,
+    clock: Option<DateTime<Utc>>,
+    open_orders: Vec<SimulatedOrder>,
+    positions: HashMap<(MarketType, String), SimulatedPosition>,
+    last_prices: HashMap<(MarketType, String), f64>,
+    cash: f64,
+    fills: Vec<Fill>,
+}
+
+impl SimulatedState {
+    fn now(&self) -> DateTime<Utc> {
+        self.clock.unwrap_or_else(Utc::now)
+    }
+
+    fn order_ack(order: &SimulatedOrder, status: &str) -> OrderAck {
+        OrderAck {
+            exchange: "simulated".to_string(),
+            symbol: order.request.symbol.clone(),
+            order_id: order.order_id.clone(),
+            client_order_id: Some(order.request.client_order_id.clone()),
+            status: Some(status.to_string()),
+        }
+    }
+
+    /// 約定をポジションと残高に反映する (Spot も証拠金取引と同様に扱う)
+    fn apply_fill(&mut self, fill: &Fill) {
+        let position = self
+            .positions
+            .entry((fill.market_type.clone(), fill.symbol.clone()))
+            .or_default();
+        let signed_quantity = match fill.side {
+            OrderSide::Buy => fill.quantity,
+            OrderSide::Sell => -fill.quantity,
+        };
+
+        let mut realized = 0.0;
+        if position.size == 0.0 || position.size.signum() == signed_quantity.signum() {
+            // 建玉の追加: 平均取得価格を更新
+            let size = position.size + signed_quantity;
+            position.entry_price = (position.entry_price * position.size.abs() + fill.price * fill.quantity) / size.abs();
+            position.size = size;
+        } else {
+            // 反対売買: 決済分の損益を確定し, ドテン分は約定価格で建て直す
+            let closed = signed_quantity.abs().min(position.size.abs());
+            realized = closed * (fill.price - position.entry_price) * position.size.signum();
+            position.size += signed_quantity;
+            if position.size.abs() < 1e-12 {
+                position.size = 0.0;
+                position.entry_price = 0.0;
+            } else if position.size.signum() == signed_quantity.signum() {
+                position.entry_price = fill.price;
+            }
+        }
+
+        self.cash += realized - fill.fee;
+    }
+}
+
+/// 約定ストリームに対して注文を約定させる模擬取引所
+/// 時刻は受け取った約定のタイムスタンプで進むため, リプレイでもライブでも同じ挙動になる.
+/// - 成行: 遅延経過後の最初の約定価格 + スリッページで全量約定
+/// - 指値: 遅延経過後, 約定価格が指値に到達した時点で指値で全量約定 (PostOnly 以外は到達済みなら即時)
+pub struct SimulatedExchange {
+    config: SimulatedConfig,
+    state: Mutex<SimulatedState>,
+}
+
+impl SimulatedExchange {
+    pub fn new(config: SimulatedConfig) -> Self {
+        let state = SimulatedState {
+            cash: config.initial_balance,
+            ..Default::default()
+        };
+        Self {
+            config,
+            state: Mutex::new(state),
+        }
+    }
+
+    /// 約定を1件処理し, 発生したシミュレーション約定を返す
+    pub fn on_trade(&self, trade: &Trade) -> Vec<Fill> {
+        let mut state = self.state.lock().unwrap();
+        let key = (trade.market_type.clone(), trade.symbol.clone());
+        state.clock = Some(state.clock.map_or(trade.timestamp, |clock| clock.max(trade.timestamp)));
+        state.last_prices.insert(key, trade.price);
+
+        let mut fills = Vec::new();
+        let mut remaining = Vec::with_capacity(state.open_orders.len());
+        for order in std::mem::take(&mut state.open_orders) {
+            let request = &order.request;
+            let matches = request.market_type == trade.market_type
+                && request.symbol == trade.symbol
+                && trade.timestamp >= order.active_at;
+            let price = match (matches, request.order_type, request.price) {
+                (false, _, _) => None,
+                (true, OrderType::Market, _) => Some(self.config.slippage.apply(trade.price, request.quantity, request.side)),
+                (true, OrderType::Limit, Some(limit)) => match request.side {
+                    OrderSide::Buy if trade.price <= limit => Some(limit),
+                    OrderSide::Sell if trade.price >= limit => Some(limit),
+                    _ => None,
+                },
+                (true, OrderType::Limit, None) => None,
+            };
+
+            match price {
+                Some(price) => {
+                    let fill = Fill {
+                        order_id: order.order_id.clone(),
+                        client_order_id: request.client_order_id.clone(),
+                        market_type: request.market_type.clone(),
+                        symbol: request.symbol.clone(),
+                        side: request.side,
+                        price,
+                        quantity: request.quantity,
+                        fee: price * request.quantity * self.config.fee_rate,
+                        timestamp: trade.timestamp,
+                    };
+                    state.apply_fill(&fill);
+                    state.fills.push(fill.clone());
+                    fills.push(fill);
+                }
+                None if matches && request.order_type == OrderType::Limit
+                    && matches!(request.time_in_force, TimeInForce::ImmediateOrCancel | TimeInForce::FillOrKill) => {
+                    // 最初に到達した約定で約定しなければ取消
+                }
+                None => remaining.push(order),
+            }
+        }
+        state.open_orders = remaining;
+        fills
+    }
+
+    /// 約定ストリームを受け取り続け, シミュレーション約定を送信する
+    pub async fn run(self: Arc<Self>, mut trade_receiver: mpsc::Receiver<Trade>, fill_sender: mpsc::Sender<Fill>) {
+        while let Some(trade) = trade_receiver.recv().await {
+            for fill in self.on_trade(&trade) {
+                if fill_sender.send(fill).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn fills(&self) -> Vec<Fill> {
+        self.state.lock().unwrap().fills.clone()
+    }
+
+    pub fn cash(&self) -> f64 {
+        self.state.lock().unwrap().cash
+    }
+}
+
+#[async_trait]
+impl ExecutionClient for SimulatedExchange {
+    fn exchange(&self) -> &'static str {
+        "simulated"
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderAck> {
+        if request.quantity <= 0.0 {
+            return Err(anyhow::anyhow!("Order quantity must be positive: {}", request.quantity));
+        }
+        if request.order_type == OrderType::Limit && request.price.is_none() {
+            return Err(anyhow::anyhow!("Limit order requires a price"));
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        // PostOnly の指値が即時約定する価格なら拒否
+        if let (TimeInForce::PostOnly, Some(limit)) = (request.time_in_force, request.price) {
+            if let Some(last) = state.last_prices.get(&(request.market_type.clone(), request.symbol.clone())) {
+                let crosses = match request.side {
+                    OrderSide::Buy => *last <= limit,
+                    OrderSide::Sell => *last >= limit,
+                };
+                if crosses {
+                    return Err(anyhow::anyhow!("PostOnly order would cross the book: {} @ {}", request.symbol, limit));
+                }
+            }
+        }
+
+        state.next_order_id += 1;
+        let order = SimulatedOrder {
+            order_id: state.next_order_id.to_string(),
+            request: request.clone(),
+            active_at: state.now() + self.config.latency,
+        };
+        let ack = SimulatedState::order_ack(&order, "New");
+        state.open_orders.push(order);
+        Ok(ack)
+    }
+
+    async fn amend_order(&self, request: &AmendRequest) -> Result<OrderAck> {
+        let mut state = self.state.lock().unwrap();
+        let active_at = state.now() + self.config.latency;
+        let order = state
+            .open_orders
+            .iter_mut()
+            .find(|o| o.order_id == request.order_id && o.request.symbol == request.symbol)
+            .ok_or_else(|| anyhow::anyhow!("Unknown simulated order: {}", request.order_id))?;
+
+        if let Some(quantity) = request.quantity {
+            order.request.quantity = quantity;
+        }
+        if let Some(price) = request.price {
+            order.request.price = Some(price);
+        }
+        // 修正も遅延後に反映される
+        order.active_at = active_at;
+        Ok(SimulatedState::order_ack(order, "New"))
+    }
+
+    async fn cancel_order(&self, request: &CancelRequest) -> Result<OrderAck> {
+        let mut state = self.state.lock().unwrap();
+        let index = state
+            .open_orders
+            .iter()
+            .position(|o| o.order_id == request.order_id && o.request.symbol == request.symbol)
+            .ok_or_else(|| anyhow::anyhow!("Unknown simulated order: {}", request.order_id))?;
+        let order = state.open_orders.remove(index);
+        Ok(SimulatedState::order_ack(&order, "Cancelled"))
+    }
+
+    async fn open_orders(&self, market_type: &MarketType, symbol: Option<&str>) -> Result<Vec<OrderAck>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .open_orders
+            .iter()
+            .filter(|o| &o.request.market_type == market_type && symbol.is_none_or(|s| o.request.symbol == s))
+            .map(|o| SimulatedState::order_ack(o, "New"))
+            .collect())
+    }
+
+    async fn balances(&self, market_type: &MarketType) -> Result<Vec<Balance>> {
+        let state = self.state.lock().unwrap();
+        let unrealized: f64 = state
+            .positions
+            .iter()
+            .filter(|((mt, _), _)| mt == market_type)
+            .map(|(key, p)| state.last_prices.get(key).map_or(0.0, |last| (last - p.entry_price) * p.size))
+            .sum();
+        Ok(vec![Balance {
+            exchange: "simulated".to_string(),
+            market_type: market_type.clone(),
+            asset: self.config.quote_asset.clone(),
+            wallet_balance: state.cash + unrealized,
+            available_balance: state.cash,
+            timestamp: state.now(),
+        }])
+    }
+
+    async fn positions(&self, market_type: &MarketType) -> Result<Vec<Position>> {
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .positions
+            .iter()
+            .filter(|((mt, _), p)| mt == market_type && p.size != 0.0)
+            .map(|(key, p)| {
+                let mark_price = state.last_prices.get(key).copied();
+                Position {
+                    exchange: "simulated".to_string(),
+                    market_type: key.0.clone(),
+                    symbol: key.1.clone(),
+                    size: p.size,
+                    entry_price: Some(p.entry_price),
+                    mark_price,
+                    unrealized_pnl: mark_price.map_or(0.0, |mark| (mark - p.entry_price) * p.size),
+                    timestamp: state.now(),
+                }
+            })
+            .collect())
+    }
+}
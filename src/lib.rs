@@ -1,4 +1,8 @@
+pub mod auth;
+pub mod cli;
 pub mod db;
 pub mod exchanges;
+#[cfg(feature = "execution")]
+pub mod execution;
 pub mod models;
 pub mod utils;
\ No newline at end of file
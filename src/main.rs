@@ -1,8 +1,7 @@
-fn main() {
-    eprintln!("Use specific binaries:");
-    eprintln!("  cargo run --bin bybit -- --symbols BTCUSDT");
-    eprintln!("  cargo run --bin binance -- --symbols BTCUSDT");
-    eprintln!("");
-    eprintln!("Add --update flag to write to database:");
-    eprintln!("  cargo run --bin bybit -- --symbols BTCUSDT --update");
-}
\ No newline at end of file
+use clap::Parser;
+use kkcrypto::cli::{self, Cli};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    cli::run(Cli::parse()).await
+}
@@ -0,0 +1,165 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use super::trade::{Side, Trade};
+use mongodb::bson::{doc, Document};
+
+/// 時間ではなく約定の量で区切る足の種類
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BarType {
+    Tick(u64),    // N 約定ごと
+    Volume(f64),  // base 通貨の出来高 X ごと
+    Dollar(f64),  // quote 通貨建ての約定代金 Y ごと (Inverse は数量が USD 建て)
+}
+
+impl BarType {
+    /// 書式: "tick:500", "volume:10", "dollar:1000000"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let (kind, threshold) = spec
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("Invalid bar type: {}. Use tick:<N>, volume:<X> or dollar:<Y>", spec))?;
+        let invalid = || anyhow::anyhow!("Invalid bar threshold: {}", spec);
+        let bar_type = match kind.trim() {
+            "tick" => Self::Tick(threshold.trim().parse().map_err(|_| invalid())?),
+            "volume" => Self::Volume(threshold.trim().parse().map_err(|_| invalid())?),
+            "dollar" => Self::Dollar(threshold.trim().parse().map_err(|_| invalid())?),
+            k => return Err(anyhow::anyhow!("Invalid bar type: {}. Use tick, volume or dollar", k)),
+        };
+        let positive = match bar_type {
+            Self::Tick(n) => n > 0,
+            Self::Volume(x) | Self::Dollar(x) => x > 0.0 && x.is_finite(),
+        };
+        if !positive {
+            return Err(anyhow::anyhow!("Bar threshold must be positive: {}", spec));
+        }
+        Ok(bar_type)
+    }
+
+    /// 書式: "tick:500,volume:10"
+    pub fn parse_list(spec: &str) -> anyhow::Result<Vec<Self>> {
+        let bar_types = spec
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(Self::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if bar_types.is_empty() {
+            return Err(anyhow::anyhow!("Empty bar type list"));
+        }
+        Ok(bar_types)
+    }
+
+    /// tick_500, volume_10, volume_0_5 (コレクション名に使う)
+    pub fn name(&self) -> String {
+        let (kind, threshold) = match self {
+            Self::Tick(n) => ("tick", n.to_string()),
+            Self::Volume(x) => ("volume", x.to_string()),
+            Self::Dollar(x) => ("dollar", x.to_string()),
+        };
+        format!("{}_{}", kind, threshold.replace('.', "_"))
+    }
+
+    pub fn collection_name(&self) -> String {
+        format!("bars_{}", self.name())
+    }
+
+    /// 足が閉じる量に達したか
+    pub fn is_complete(&self, bar: &Bar) -> bool {
+        match *self {
+            Self::Tick(n) => bar.count as u64 >= n,
+            Self::Volume(x) => bar.volume >= x,
+            Self::Dollar(x) => bar.notional >= x,
+        }
+    }
+}
+
+/// 約定駆動の足 (tick / volume / dollar bar)
+/// 約定を分割しないので, 閉じた足の量は閾値をわずかに超えることがある
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub bar_type: BarType,
+    pub start: DateTime<Utc>,      // 最初の約定時刻
+    pub timestamp: DateTime<Utc>,  // 最後の約定時刻 (足が閉じた時刻)
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub notional: f64,
+    pub count: i32,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+}
+
+impl Bar {
+    pub fn new(bar_type: BarType, trade: &Trade) -> Self {
+        let mut bar = Self {
+            id: Uuid::new_v4(),
+            exchange: trade.exchange.clone(),
+            market_type: trade.market_type.clone(),
+            symbol: trade.symbol.clone(),
+            bar_type,
+            start: trade.timestamp,
+            timestamp: trade.timestamp,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: 0.0,
+            notional: 0.0,
+            count: 0,
+            buy_volume: 0.0,
+            sell_volume: 0.0,
+        };
+        bar.update(trade);
+        bar
+    }
+
+    pub fn update(&mut self, trade: &Trade) {
+        self.timestamp = self.timestamp.max(trade.timestamp);
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.quantity;
+        self.notional += trade.notional();
+        self.count += 1;
+        match trade.side {
+            Side::Buy => self.buy_volume += trade.quantity,
+            Side::Sell => self.sell_volume += trade.quantity,
+        }
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "start": mongodb::bson::DateTime::from_millis(self.start.timestamp_millis()),
+            "open": self.open,
+            "high": self.high,
+            "low": self.low,
+            "close": self.close,
+            "volume": self.volume,
+            "notional": self.notional,
+            "count": self.count,
+            "buy_volume": self.buy_volume,
+            "sell_volume": self.sell_volume
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use super::trade::{Side, Trade};
+use mongodb::bson::{doc, Document};
+
+/// ブロックトレード (相対取引の約定)
+/// 通常の約定と混ぜると VWAP が歪むため, ローソク足には含めず別に保存する
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTrade {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub trade_id: String,
+    pub side: Side,
+    pub price: f64,
+    pub quantity: f64,
+    pub notional: f64,  // quote 通貨建て (Inverse は数量が USD 建て)
+    pub timestamp: DateTime<Utc>,
+}
+
+impl From<Trade> for BlockTrade {
+    fn from(trade: Trade) -> Self {
+        let notional = trade.notional();
+        Self {
+            id: Uuid::new_v4(),
+            exchange: trade.exchange,
+            market_type: trade.market_type,
+            symbol: trade.symbol,
+            trade_id: trade.trade_id,
+            side: trade.side,
+            price: trade.price,
+            quantity: trade.quantity,
+            notional,
+            timestamp: trade.timestamp,
+        }
+    }
+}
+
+impl BlockTrade {
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        let side = match self.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        };
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "trade_id": &self.trade_id,
+            "side": side,
+            "price": self.price,
+            "quantity": self.quantity,
+            "notional": self.notional
+        }
+    }
+}
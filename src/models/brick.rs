@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use mongodb::bson::{doc, Document};
+
+/// Renko のブリック / レンジバー
+/// 価格が一定幅動くごとに区切るので, 時間や出来高による区切りより値動きのノイズが少ない
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Brick {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub name: String,              // 設定名 (renko_100, range_atr14 など, コレクション名にも使う)
+    pub start: DateTime<Utc>,      // 最初の約定時刻
+    pub timestamp: DateTime<Utc>,  // 閉じた約定の時刻
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub size: f64,       // このブリックの幅 (ATR 指定では閉じた時点の ATR)
+    pub direction: i32,  // 1: 上昇, -1: 下落
+    pub volume: f64,
+    pub count: i32,
+}
+
+impl Brick {
+    pub fn collection_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "start": mongodb::bson::DateTime::from_millis(self.start.timestamp_millis()),
+            "open": self.open,
+            "high": self.high,
+            "low": self.low,
+            "close": self.close,
+            "size": self.size,
+            "direction": self.direction,
+            "volume": self.volume,
+            "count": self.count
+        }
+    }
+}
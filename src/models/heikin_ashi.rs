@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use mongodb::bson::{doc, Document};
+
+/// ローソク足の OHLC から作る平均足 (Heikin-Ashi)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeikinAshiCandle {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub timestamp: DateTime<Utc>,  // 元の足と同じ終端
+    pub period_seconds: i32,
+    pub open: f64,   // (前の足の open + close) / 2, 最初の足は (O + C) / 2
+    pub high: f64,   // max(H, open, close)
+    pub low: f64,    // min(L, open, close)
+    pub close: f64,  // (O + H + L + C) / 4
+    pub warmup: bool,
+    pub revision: u32,  // 元の足の revision
+}
+
+impl HeikinAshiCandle {
+    /// ha_candles_1m など (元のローソク足のコレクション名に ha_ を付ける)
+    pub fn collection_name(&self) -> Option<String> {
+        crate::db::candle_collection_name(self.period_seconds).map(|name| format!("ha_{}", name))
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        let mut doc = doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "open": self.open,
+            "high": self.high,
+            "low": self.low,
+            "close": self.close
+        };
+        if self.warmup {
+            doc.insert("warmup", true);
+        }
+        if self.revision > 0 {
+            doc.insert("revision", self.revision as i32);
+        }
+        doc
+    }
+}
@@ -0,0 +1,134 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use super::trade::{Side, Trade};
+use mongodb::bson::{doc, Document};
+
+/// 不均衡を測る量
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImbalanceKind {
+    Tick,    // 約定の向き (買い +1, 売り -1) の累積
+    Volume,  // 向き付きの出来高の累積
+}
+
+impl ImbalanceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tick => "tick",
+            Self::Volume => "volume",
+        }
+    }
+
+    pub fn collection_name(&self) -> String {
+        format!("imbalance_bars_{}", self.as_str())
+    }
+
+    /// 約定 1 件の向き付きの量
+    pub fn signed(&self, trade: &Trade) -> f64 {
+        let sign = match trade.side {
+            Side::Buy => 1.0,
+            Side::Sell => -1.0,
+        };
+        match self {
+            Self::Tick => sign,
+            Self::Volume => sign * trade.quantity,
+        }
+    }
+}
+
+/// tick / volume imbalance bar (López de Prado)
+/// 向き付きの累積 (imbalance) の絶対値が, 期待される足の約定数 x 約定 1 件あたりの期待不均衡 (threshold) を超えたら閉じる
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImbalanceBar {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub kind: ImbalanceKind,
+    pub start: DateTime<Utc>,      // 最初の約定時刻
+    pub timestamp: DateTime<Utc>,  // 最後の約定時刻 (足が閉じた時刻)
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    pub count: i32,
+    pub buy_volume: f64,
+    pub sell_volume: f64,
+    pub imbalance: f64,       // 閉じた時点の累積不均衡
+    pub threshold: f64,       // 閉じた時点の閾値
+    pub expected_ticks: f64,  // 閉じた時点の期待約定数 E[T]
+}
+
+impl ImbalanceBar {
+    pub fn new(kind: ImbalanceKind, trade: &Trade) -> Self {
+        let mut bar = Self {
+            id: Uuid::new_v4(),
+            exchange: trade.exchange.clone(),
+            market_type: trade.market_type.clone(),
+            symbol: trade.symbol.clone(),
+            kind,
+            start: trade.timestamp,
+            timestamp: trade.timestamp,
+            open: trade.price,
+            high: trade.price,
+            low: trade.price,
+            close: trade.price,
+            volume: 0.0,
+            count: 0,
+            buy_volume: 0.0,
+            sell_volume: 0.0,
+            imbalance: 0.0,
+            threshold: 0.0,
+            expected_ticks: 0.0,
+        };
+        bar.update(trade);
+        bar
+    }
+
+    pub fn update(&mut self, trade: &Trade) {
+        self.timestamp = self.timestamp.max(trade.timestamp);
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+        self.volume += trade.quantity;
+        self.count += 1;
+        self.imbalance += self.kind.signed(trade);
+        match trade.side {
+            Side::Buy => self.buy_volume += trade.quantity,
+            Side::Sell => self.sell_volume += trade.quantity,
+        }
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "start": mongodb::bson::DateTime::from_millis(self.start.timestamp_millis()),
+            "open": self.open,
+            "high": self.high,
+            "low": self.low,
+            "close": self.close,
+            "volume": self.volume,
+            "count": self.count,
+            "buy_volume": self.buy_volume,
+            "sell_volume": self.sell_volume,
+            "imbalance": self.imbalance,
+            "threshold": self.threshold,
+            "expected_ticks": self.expected_ticks
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use super::trade::Side;
+use mongodb::bson::{doc, Document};
+
+/// 強制決済注文
+/// side は清算注文の売買方向 (Sell = ロングの清算, Buy = ショートの清算) に揃える
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Liquidation {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub side: Side,
+    pub price: f64,
+    pub quantity: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Liquidation {
+    pub fn new(
+        exchange: String,
+        market_type: MarketType,
+        symbol: String,
+        side: Side,
+        price: f64,
+        quantity: f64,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            exchange,
+            market_type,
+            symbol,
+            side,
+            price,
+            quantity,
+            timestamp,
+        }
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        let side = match self.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        };
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "side": side,
+            "price": self.price,
+            "quantity": self.quantity
+        }
+    }
+}
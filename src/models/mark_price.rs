@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use mongodb::bson::{doc, Document};
+
+/// 先物のマーク価格・インデックス価格 (basis = mark - index)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkPrice {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub mark_price: f64,
+    pub index_price: Option<f64>,
+    pub funding_rate: Option<f64>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl MarkPrice {
+    pub fn new(
+        exchange: String,
+        market_type: MarketType,
+        symbol: String,
+        mark_price: f64,
+        index_price: Option<f64>,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            exchange,
+            market_type,
+            symbol,
+            mark_price,
+            index_price,
+            funding_rate: None,
+            timestamp,
+        }
+    }
+
+    pub fn basis(&self) -> Option<f64> {
+        self.index_price.map(|index| self.mark_price - index)
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // ローソク足と同じ symbol_id を使用
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "mark_price": self.mark_price,
+            "index_price": self.index_price,
+            "basis": self.basis(),
+            "funding_rate": self.funding_rate
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use super::bar::Bar;
+use super::block_trade::BlockTrade;
+use super::brick::Brick;
+use super::heikin_ashi::HeikinAshiCandle;
+use super::imbalance_bar::ImbalanceBar;
+use super::liquidation::Liquidation;
+use super::mark_price::MarkPrice;
+use super::quote::Quote;
+use super::trade::Trade;
+use super::trade_candle::TradeCandle;
+
+/// 取引所クライアント -> TradeCandleBuilder -> DB 書き込みを 1 本のチャネルで流れるイベント
+/// 新しいデータ種別はここに variant を追加し, 各段の match で扱う
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    Trade(Trade),
+    Quote(Quote),
+    Liquidation(Liquidation),
+    MarkPrice(MarkPrice),  // funding rate も含む
+    BlockTrade(BlockTrade),
+    Candle(TradeCandle),  // TradeCandleBuilder が約定から集計した足
+    Bar(Bar),             // BarBuilder が約定から集計した約定駆動の足
+    ImbalanceBar(ImbalanceBar),  // ImbalanceBarBuilder が約定の向きの偏りで区切った足
+    Brick(Brick),                // RenkoBuilder が値幅で区切った Renko のブリック / レンジバー
+    HeikinAshi(HeikinAshiCandle),  // HeikinAshiBuilder がローソク足から作った平均足
+}
+
+impl MarketEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Trade(_) => "trade",
+            Self::Quote(_) => "quote",
+            Self::Liquidation(_) => "liquidation",
+            Self::MarkPrice(_) => "mark_price",
+            Self::BlockTrade(_) => "block_trade",
+            Self::Candle(_) => "candle",
+            Self::Bar(_) => "bar",
+            Self::ImbalanceBar(_) => "imbalance_bar",
+            Self::Brick(_) => "brick",
+            Self::HeikinAshi(_) => "heikin_ashi",
+        }
+    }
+
+    pub fn exchange(&self) -> &str {
+        match self {
+            Self::Trade(e) => &e.exchange,
+            Self::Quote(e) => &e.exchange,
+            Self::Liquidation(e) => &e.exchange,
+            Self::MarkPrice(e) => &e.exchange,
+            Self::BlockTrade(e) => &e.exchange,
+            Self::Candle(e) => &e.exchange,
+            Self::Bar(e) => &e.exchange,
+            Self::ImbalanceBar(e) => &e.exchange,
+            Self::Brick(e) => &e.exchange,
+            Self::HeikinAshi(e) => &e.exchange,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Trade(e) => &e.symbol,
+            Self::Quote(e) => &e.symbol,
+            Self::Liquidation(e) => &e.symbol,
+            Self::MarkPrice(e) => &e.symbol,
+            Self::BlockTrade(e) => &e.symbol,
+            Self::Candle(e) => &e.symbol,
+            Self::Bar(e) => &e.symbol,
+            Self::ImbalanceBar(e) => &e.symbol,
+            Self::Brick(e) => &e.symbol,
+            Self::HeikinAshi(e) => &e.symbol,
+        }
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        match self {
+            Self::Trade(e) => e.timestamp,
+            Self::Quote(e) => e.timestamp,
+            Self::Liquidation(e) => e.timestamp,
+            Self::MarkPrice(e) => e.timestamp,
+            Self::BlockTrade(e) => e.timestamp,
+            Self::Candle(e) => e.timestamp,
+            Self::Bar(e) => e.timestamp,
+            Self::ImbalanceBar(e) => e.timestamp,
+            Self::Brick(e) => e.timestamp,
+            Self::HeikinAshi(e) => e.timestamp,
+        }
+    }
+}
+
+impl From<Trade> for MarketEvent {
+    fn from(trade: Trade) -> Self {
+        Self::Trade(trade)
+    }
+}
+
+impl From<Quote> for MarketEvent {
+    fn from(quote: Quote) -> Self {
+        Self::Quote(quote)
+    }
+}
+
+impl From<Liquidation> for MarketEvent {
+    fn from(liquidation: Liquidation) -> Self {
+        Self::Liquidation(liquidation)
+    }
+}
+
+impl From<MarkPrice> for MarketEvent {
+    fn from(mark_price: MarkPrice) -> Self {
+        Self::MarkPrice(mark_price)
+    }
+}
+
+impl From<BlockTrade> for MarketEvent {
+    fn from(block_trade: BlockTrade) -> Self {
+        Self::BlockTrade(block_trade)
+    }
+}
+
+impl From<TradeCandle> for MarketEvent {
+    fn from(candle: TradeCandle) -> Self {
+        Self::Candle(candle)
+    }
+}
+
+impl From<Bar> for MarketEvent {
+    fn from(bar: Bar) -> Self {
+        Self::Bar(bar)
+    }
+}
+
+impl From<ImbalanceBar> for MarketEvent {
+    fn from(bar: ImbalanceBar) -> Self {
+        Self::ImbalanceBar(bar)
+    }
+}
+
+impl From<Brick> for MarketEvent {
+    fn from(brick: Brick) -> Self {
+        Self::Brick(brick)
+    }
+}
+
+impl From<HeikinAshiCandle> for MarketEvent {
+    fn from(candle: HeikinAshiCandle) -> Self {
+        Self::HeikinAshi(candle)
+    }
+}
@@ -8,6 +8,15 @@ pub enum MarketType {
 }
 
 impl MarketType {
+    pub fn parse(market_type: &str) -> anyhow::Result<Self> {
+        match market_type.trim() {
+            "spot" => Ok(MarketType::Spot),
+            "linear" => Ok(MarketType::Linear),
+            "inverse" => Ok(MarketType::Inverse),
+            m => Err(anyhow::anyhow!("Invalid market type: {}. Use spot, linear or inverse", m)),
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             MarketType::Spot => "spot",
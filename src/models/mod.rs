@@ -1,6 +1,15 @@
 pub mod trade;
 pub mod trade_candle;
 pub mod market_type;
+pub mod quote;
+pub mod liquidation;
+pub mod mark_price;
+pub mod block_trade;
+pub mod bar;
+pub mod imbalance_bar;
+pub mod brick;
+pub mod heikin_ashi;
+pub mod market_event;
 
 use async_trait::async_trait;
 use anyhow::Result;
@@ -17,4 +26,9 @@ pub trait ExchangeClient: Send + Sync {
     async fn connect(&mut self, market_type: MarketType) -> Result<()>;
     async fn subscribe_trades(&mut self, symbols: Vec<String>) -> Result<()>;
     async fn disconnect(&mut self) -> Result<()>;
+
+    /// 1 接続で購読できる symbol 数の上限 (0 なら上限なし). 接続ごとの symbol 数が指定されなければこれで接続を分ける
+    fn max_symbols_per_connection(&self, _market_type: &MarketType) -> usize {
+        0
+    }
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use super::market_type::MarketType;
+use mongodb::bson::{doc, Document};
+
+/// 板の最良気配 (Top of Book) と深さ N の板の偏り
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub id: Uuid,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub bid_price: f64,
+    pub bid_size: f64,
+    pub ask_price: f64,
+    pub ask_size: f64,
+    pub imbalance: Option<f64>,  // (bid - ask) / (bid + ask) の数量比, 上位 depth_levels 段
+    pub depth_levels: u32,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Quote {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        exchange: String,
+        market_type: MarketType,
+        symbol: String,
+        bid_price: f64,
+        bid_size: f64,
+        ask_price: f64,
+        ask_size: f64,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            exchange,
+            market_type,
+            symbol,
+            bid_price,
+            bid_size,
+            ask_price,
+            ask_size,
+            imbalance: None,
+            depth_levels: 1,
+            timestamp,
+        }
+    }
+
+    pub fn mid_price(&self) -> f64 {
+        (self.bid_price + self.ask_price) / 2.0
+    }
+
+    pub fn spread(&self) -> f64 {
+        self.ask_price - self.bid_price
+    }
+
+    pub fn to_timeseries_document(&self) -> Document {
+        use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+        let ym = self.timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+
+        // symbol_idを取得
+        let symbol_id = SYMBOL_MANAGER
+            .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
+            .unwrap_or(0);
+
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": symbol_id
+            },
+            "bid_price": self.bid_price,
+            "bid_size": self.bid_size,
+            "ask_price": self.ask_price,
+            "ask_size": self.ask_size,
+            "mid_price": self.mid_price(),
+            "imbalance": self.imbalance,
+            "depth_levels": self.depth_levels as i32
+        }
+    }
+}
@@ -9,6 +9,35 @@ pub enum Side {
     Sell,
 }
 
+/// ローソク足の集計に使うタイムスタンプ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampSource {
+    #[default]
+    Exchange,  // マッチングエンジンの約定時刻
+    Gateway,   // WebSocket メッセージの配信時刻 (Bybit ts, Binance/Backpack E)
+    Receipt,   // ローカルの受信時刻
+}
+
+impl TimestampSource {
+    pub fn parse(source: &str) -> anyhow::Result<Self> {
+        match source.to_lowercase().as_str() {
+            "exchange" => Ok(Self::Exchange),
+            "gateway" => Ok(Self::Gateway),
+            "receipt" => Ok(Self::Receipt),
+            s => Err(anyhow::anyhow!("Invalid timestamp source: {}. Use exchange, gateway or receipt", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exchange => "exchange",
+            Self::Gateway => "gateway",
+            Self::Receipt => "receipt",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub id: Uuid,
@@ -19,10 +48,15 @@ pub struct Trade {
     pub price: f64,
     pub quantity: f64,
     pub side: Side,
-    pub timestamp: DateTime<Utc>,
+    pub timestamp: DateTime<Utc>,  // 約定時刻 (マッチングエンジン)
+    #[serde(default)]
+    pub gateway_timestamp: Option<DateTime<Utc>>,
+    #[serde(default = "Utc::now")]
+    pub received_at: DateTime<Utc>,
 }
 
 impl Trade {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         exchange: String,
         market_type: MarketType,
@@ -43,6 +77,35 @@ impl Trade {
             quantity,
             side,
             timestamp,
+            gateway_timestamp: None,
+            received_at: Utc::now(),
+        }
+    }
+
+    pub fn with_gateway_timestamp(mut self, gateway_timestamp: Option<DateTime<Utc>>) -> Self {
+        self.gateway_timestamp = gateway_timestamp;
+        self
+    }
+
+    /// quote 通貨建ての約定代金 (Inverse は数量が USD 建て)
+    pub fn notional(&self) -> f64 {
+        match self.market_type {
+            MarketType::Inverse => self.quantity,
+            _ => self.price * self.quantity,
+        }
+    }
+
+    /// 約定時刻からローカルで受信するまでの遅延 (ミリ秒, 時計のずれを含むので負にもなる)
+    pub fn latency_ms(&self) -> f64 {
+        (self.received_at - self.timestamp).num_milliseconds() as f64
+    }
+
+    /// source に対応するタイムスタンプ (配信時刻がない取引所では約定時刻)
+    pub fn timestamp_for(&self, source: TimestampSource) -> DateTime<Utc> {
+        match source {
+            TimestampSource::Exchange => self.timestamp,
+            TimestampSource::Gateway => self.gateway_timestamp.unwrap_or(self.timestamp),
+            TimestampSource::Receipt => self.received_at,
         }
     }
 }
\ No newline at end of file
@@ -2,8 +2,48 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use super::market_type::MarketType;
+use super::trade::TimestampSource;
 use mongodb::bson::{doc, Document};
 
+/// 足の中の約定サイズの分布 (サイズは quote 通貨建ての約定代金, 銘柄をまたいで比較できる)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeSizeStats {
+    pub max: f64,
+    pub median: f64,
+    pub histogram: [i32; 5],  // SIZE_BUCKETS で区切った約定数 (<1k, 1k-10k, 10k-100k, 100k-1M, >=1M)
+}
+
+impl TradeSizeStats {
+    /// ヒストグラムの境界 (quote 通貨)
+    pub const SIZE_BUCKETS: [f64; 4] = [1_000.0, 10_000.0, 100_000.0, 1_000_000.0];
+    pub const BUCKET_NAMES: [&'static str; 5] = ["lt_1k", "1k_10k", "10k_100k", "100k_1m", "ge_1m"];
+
+    /// 約定ごとの約定代金から集計する (約定がなければ None)
+    pub fn from_sizes(sizes: &[f64]) -> Option<Self> {
+        if sizes.is_empty() {
+            return None;
+        }
+        let mut sorted = sizes.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        let median = if sorted.len().is_multiple_of(2) {
+            (sorted[middle - 1] + sorted[middle]) / 2.0
+        } else {
+            sorted[middle]
+        };
+        let mut histogram = [0; 5];
+        for &size in &sorted {
+            histogram[Self::bucket(size)] += 1;
+        }
+        Some(Self { max: sorted[sorted.len() - 1], median, histogram })
+    }
+
+    /// size が入るヒストグラムの位置
+    pub fn bucket(size: f64) -> usize {
+        Self::SIZE_BUCKETS.iter().take_while(|&&bound| size >= bound).count()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeCandle {
     pub id: Uuid,
@@ -16,15 +56,70 @@ pub struct TradeCandle {
     // Ask側データ (売り注文側の約定)
     pub ask_price: Option<f64>,  // 加重平均価格 (VWAP)
     pub ask_volume: f64,
+    #[serde(default)]
+    pub ask_notional: f64,  // quote 通貨建ての約定代金 (Inverse は USD 建ての数量の合計)
     pub ask_count: i32,
     
     // Bid側データ (買い注文側の約定)
     pub bid_price: Option<f64>,  // 加重平均価格 (VWAP)
     pub bid_volume: f64,
+    #[serde(default)]
+    pub bid_notional: f64,
     pub bid_count: i32,
+    
+    // 接続直後のウォームアップ期間に含まれる (部分的な集計の可能性がある) 足
+    #[serde(default)]
+    pub warmup: bool,
+    
+    // 遅延約定による訂正の回数 (0 は最初の出力, 同じ足の最大の revision が最新)
+    #[serde(default)]
+    pub revision: u32,
+    
+    // 約定サイズの最大・中央値とヒストグラム (約定のない足は None)
+    #[serde(default)]
+    pub trade_size: Option<TradeSizeStats>,
+    
+    // 約定価格の始値・高値・安値・終値, 連続する約定間の対数リターンから求めた realized volatility (sqrt(Σ r^2)),
+    // 値動きの向きが変わった約定の数 (約定のない足は None / 0)
+    #[serde(default)]
+    pub open: Option<f64>,
+    #[serde(default)]
+    pub high: Option<f64>,
+    #[serde(default)]
+    pub low: Option<f64>,
+    #[serde(default)]
+    pub close: Option<f64>,
+    #[serde(default)]
+    pub realized_vol: Option<f64>,
+    #[serde(default)]
+    pub direction_changes: i32,
+    
+    // 同じ symbol・時間枠の足の delta (買い - 売りの出来高) の累積 (この足を含む, collector の起動またはスナップショットの復元から)
+    #[serde(default)]
+    pub cvd: f64,
+    
+    // 集計に使ったタイムスタンプの種類と, 最後に含めた約定のローカル受信時刻
+    #[serde(default)]
+    pub timestamp_source: TimestampSource,
+    #[serde(default)]
+    pub received_at: Option<DateTime<Utc>>,
+    
+    // 足に含めた約定の平均の遅延 (受信時刻 - 約定時刻, ミリ秒. TradeCandleBuilder::with_latency のときのみ)
+    #[serde(default)]
+    pub latency_ms: Option<f64>,
 }
 
 impl TradeCandle {
+    // to_timeseries_document() が出力するデータフィールド (unixtime, metadata 以外)
+    pub const FIELDS: [&'static str; 22] = [
+        "ask_price", "ask_volume", "ask_notional", "ask_count",
+        "bid_price", "bid_volume", "bid_notional", "bid_count",
+        "delta", "cvd", "taker_buy_ratio",
+        "trade_size", "size_hist",
+        "open", "high", "low", "close", "range", "realized_vol", "direction_changes",
+        "received_at", "latency_ms",
+    ];
+
     pub fn new(
         exchange: String,
         market_type: MarketType,
@@ -41,11 +136,101 @@ impl TradeCandle {
             period_seconds,
             ask_price: None,
             ask_volume: 0.0,
+            ask_notional: 0.0,
             ask_count: 0,
             bid_price: None,
             bid_volume: 0.0,
+            bid_notional: 0.0,
             bid_count: 0,
+            warmup: false,
+            revision: 0,
+            trade_size: None,
+            open: None,
+            high: None,
+            low: None,
+            close: None,
+            realized_vol: None,
+            direction_changes: 0,
+            cvd: 0.0,
+            timestamp_source: TimestampSource::Exchange,
+            received_at: None,
+            latency_ms: None,
+        }
+    }
+
+    /// 約定価格の高値と安値の差
+    pub fn range(&self) -> Option<f64> {
+        Some(self.high? - self.low?)
+    }
+
+    /// 買い約定 (Ask 側) と売り約定 (Bid 側) の出来高の差
+    pub fn delta(&self) -> f64 {
+        self.ask_volume - self.bid_volume
+    }
+
+    /// 出来高に占める買い約定の割合 (約定のない足は None)
+    pub fn taker_buy_ratio(&self) -> Option<f64> {
+        let volume = self.ask_volume + self.bid_volume;
+        (volume > 0.0).then(|| self.ask_volume / volume)
+    }
+
+    /// 買い・売り両側を合わせた出来高加重平均価格
+    pub fn vwap(&self) -> Option<f64> {
+        let notional = self.ask_price.unwrap_or(0.0) * self.ask_volume + self.bid_price.unwrap_or(0.0) * self.bid_volume;
+        let volume = self.ask_volume + self.bid_volume;
+        if volume > 0.0 {
+            Some(notional / volume)
+        } else {
+            None
+        }
+    }
+    
+    /// to_timeseries_document() で保存したドキュメントから復元する (metadata には symbol id しかないため識別子は呼び出し側が渡す)
+    /// 時間枠ごとの保存フィールドで省かれた値は None / 0 になる
+    pub fn from_timeseries_document(
+        doc: &Document,
+        exchange: String,
+        market_type: MarketType,
+        symbol: String,
+        period_seconds: i32,
+    ) -> anyhow::Result<Self> {
+        let timestamp = DateTime::from_timestamp_millis(doc.get_datetime("unixtime")?.timestamp_millis())
+            .ok_or_else(|| anyhow::anyhow!("Invalid unixtime"))?;
+        let mut candle = Self::new(exchange, market_type, symbol, timestamp, period_seconds);
+        candle.ask_price = doc.get_f64("ask_price").ok();
+        candle.ask_volume = doc.get_f64("ask_volume").unwrap_or(0.0);
+        candle.ask_notional = doc.get_f64("ask_notional").unwrap_or(0.0);
+        candle.ask_count = doc.get_i32("ask_count").unwrap_or(0);
+        candle.bid_price = doc.get_f64("bid_price").ok();
+        candle.bid_volume = doc.get_f64("bid_volume").unwrap_or(0.0);
+        candle.bid_notional = doc.get_f64("bid_notional").unwrap_or(0.0);
+        candle.bid_count = doc.get_i32("bid_count").unwrap_or(0);
+        candle.warmup = doc.get_bool("warmup").unwrap_or(false);
+        candle.revision = doc.get_i32("revision").unwrap_or(0) as u32;
+        candle.open = doc.get_f64("open").ok();
+        candle.high = doc.get_f64("high").ok();
+        candle.low = doc.get_f64("low").ok();
+        candle.close = doc.get_f64("close").ok();
+        candle.realized_vol = doc.get_f64("realized_vol").ok();
+        candle.direction_changes = doc.get_i32("direction_changes").unwrap_or(0);
+        candle.cvd = doc.get_f64("cvd").unwrap_or(0.0);
+        if let (Ok(size), Ok(hist)) = (doc.get_document("trade_size"), doc.get_document("size_hist")) {
+            let mut histogram = [0; 5];
+            for (count, name) in histogram.iter_mut().zip(TradeSizeStats::BUCKET_NAMES) {
+                *count = hist.get_i32(name).unwrap_or(0);
+            }
+            candle.trade_size = Some(TradeSizeStats {
+                max: size.get_f64("max")?,
+                median: size.get_f64("median")?,
+                histogram,
+            });
         }
+        candle.received_at = doc
+            .get_datetime("received_at")
+            .ok()
+            .and_then(|dt| DateTime::from_timestamp_millis(dt.timestamp_millis()));
+        candle.latency_ms = doc.get_f64("latency_ms").ok();
+        Ok(candle)
     }
     
     pub fn to_timeseries_document(&self) -> Document {
@@ -59,7 +244,7 @@ impl TradeCandle {
             .get_symbol_id(&self.exchange, &self.symbol, self.market_type.as_str())
             .unwrap_or(0);
         
-        doc! {
+        let mut doc = doc! {
             "unixtime": mongodb::bson::DateTime::from_millis(unixtime * 1000),
             "metadata": {
                 "ym": ym,
@@ -67,10 +252,54 @@ impl TradeCandle {
             },
             "ask_price": self.ask_price,
             "ask_volume": self.ask_volume,
+            "ask_notional": self.ask_notional,
             "ask_count": self.ask_count,
             "bid_price": self.bid_price,
             "bid_volume": self.bid_volume,
-            "bid_count": self.bid_count
+            "bid_notional": self.bid_notional,
+            "bid_count": self.bid_count,
+            "delta": self.delta(),
+            "cvd": self.cvd,
+            "ts_source": self.timestamp_source.as_str()
+        };
+        if let Some(ratio) = self.taker_buy_ratio() {
+            doc.insert("taker_buy_ratio", ratio);
+        }
+        if let Some(received_at) = self.received_at {
+            doc.insert("received_at", mongodb::bson::DateTime::from_millis(received_at.timestamp_millis()));
+        }
+        if let Some(latency_ms) = self.latency_ms {
+            doc.insert("latency_ms", latency_ms);
+        }
+        if self.warmup {
+            doc.insert("warmup", true);
+        }
+        if self.revision > 0 {
+            doc.insert("revision", self.revision as i32);
+        }
+        if let (Some(high), Some(low)) = (self.high, self.low) {
+            if let (Some(open), Some(close)) = (self.open, self.close) {
+                doc.insert("open", open);
+                doc.insert("close", close);
+            }
+            doc.insert("high", high);
+            doc.insert("low", low);
+            doc.insert("range", high - low);
+            // 合成シンボルの足 (utils::index) は約定の経路がないので realized volatility を持たない
+            if let Some(realized_vol) = self.realized_vol {
+                doc.insert("realized_vol", realized_vol);
+                doc.insert("direction_changes", self.direction_changes);
+            }
+        }
+        if let Some(stats) = &self.trade_size {
+            doc.insert("trade_size", doc! { "max": stats.max, "median": stats.median });
+            let hist: Document = TradeSizeStats::BUCKET_NAMES
+                .iter()
+                .zip(stats.histogram)
+                .map(|(name, count)| (name.to_string(), mongodb::bson::Bson::Int32(count)))
+                .collect();
+            doc.insert("size_hist", hist);
         }
+        doc
     }
 }
\ No newline at end of file
@@ -0,0 +1,267 @@
+use crate::models::market_event::MarketEvent;
+use crate::models::trade_candle::TradeCandle;
+use super::notify::Notifier;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// ウォッチリストの条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertCondition {
+    CrossAbove(f64),                                   // price>X: 価格が X を下から上に抜けた
+    CrossBelow(f64),                                   // price<X: 価格が X を上から下に抜けた
+    Move { percent: f64, window: chrono::Duration },   // move>P%/5m: window 前からの変化率の絶対値が P% 以上
+    VolumeSpike { multiple: f64, lookback: usize },    // volume>3x/20: 直前 lookback 本の平均出来高の multiple 倍以上
+}
+
+impl AlertCondition {
+    /// 書式: "price>70000", "price<60000", "move>2%/5m", "volume>3x/20"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let invalid = || anyhow::anyhow!("Invalid alert condition: {}. Use price>X, price<X, move>P%/<N>m or volume>Kx/<N>", spec);
+        if let Some(price) = spec.strip_prefix("price>") {
+            return Ok(Self::CrossAbove(price.parse().map_err(|_| invalid())?));
+        }
+        if let Some(price) = spec.strip_prefix("price<") {
+            return Ok(Self::CrossBelow(price.parse().map_err(|_| invalid())?));
+        }
+        if let Some(rest) = spec.strip_prefix("move>") {
+            let (percent, window) = rest.split_once("%/").ok_or_else(invalid)?;
+            let percent: f64 = percent.parse().map_err(|_| invalid())?;
+            let window = parse_window(window).ok_or_else(invalid)?;
+            if percent <= 0.0 {
+                return Err(invalid());
+            }
+            return Ok(Self::Move { percent, window });
+        }
+        if let Some(rest) = spec.strip_prefix("volume>") {
+            let (multiple, lookback) = rest.split_once("x/").ok_or_else(invalid)?;
+            let multiple: f64 = multiple.parse().map_err(|_| invalid())?;
+            let lookback: usize = lookback.parse().map_err(|_| invalid())?;
+            if multiple <= 0.0 || lookback == 0 {
+                return Err(invalid());
+            }
+            return Ok(Self::VolumeSpike { multiple, lookback });
+        }
+        Err(invalid())
+    }
+}
+
+// "30s", "5m", "1h"
+fn parse_window(spec: &str) -> Option<chrono::Duration> {
+    let (value, unit) = spec.split_at(spec.len().checked_sub(1)?);
+    let value: i64 = value.parse().ok().filter(|&v| v > 0)?;
+    match unit {
+        "s" => Some(chrono::Duration::seconds(value)),
+        "m" => Some(chrono::Duration::minutes(value)),
+        "h" => Some(chrono::Duration::hours(value)),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertRule {
+    pub symbol: String,  // * は全ての symbol
+    pub condition: AlertCondition,
+    pub spec: String,
+}
+
+/// 監視する symbol と条件のリスト
+#[derive(Debug, Clone, Default)]
+pub struct Watchlist {
+    pub rules: Vec<AlertRule>,
+}
+
+impl Watchlist {
+    /// 書式: "BTCUSDT:price>70000,ETHUSDT:move>2%/5m,*:volume>3x/20"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut rules = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (symbol, condition) = entry
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("Invalid watchlist entry: {}. Use SYMBOL:CONDITION", entry))?;
+            rules.push(AlertRule {
+                symbol: symbol.trim().to_string(),
+                condition: AlertCondition::parse(condition)?,
+                spec: entry.to_string(),
+            });
+        }
+        if rules.is_empty() {
+            return Err(anyhow::anyhow!("Empty watchlist"));
+        }
+        Ok(Self { rules })
+    }
+}
+
+/// 発火したアラート
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub timestamp: DateTime<Utc>,
+    pub exchange: String,
+    pub symbol: String,
+    pub rule: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Alert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} @ {} | {} ({})", self.exchange, self.symbol, self.timestamp.format("%H:%M:%S"), self.message, self.rule)
+    }
+}
+
+// symbol ごとの直近の足 (時刻, 価格, 出来高)
+type History = VecDeque<(DateTime<Utc>, f64, f64)>;
+
+/// 1 つの時間枠のローソク足でウォッチリストを評価し, 発火したアラートを通知する
+/// 約定のない足 (emit_empty) は直前の価格を引き継いだ価格として扱う
+pub struct AlertEngine {
+    watchlist: Watchlist,
+    timeframe: u32,
+    label: String,  // 表示用の取引所名 (e.g. BYBIT)
+    notifier: Option<Arc<Notifier>>,
+    history: HashMap<String, History>,
+    last_price: HashMap<(usize, String), f64>,        // (rule, symbol) -> 前回の価格 (cross 判定用)
+    last_fired: HashMap<(usize, String), DateTime<Utc>>, // (rule, symbol) -> 前回の発火時刻 (move の連続発火を抑える)
+}
+
+impl AlertEngine {
+    pub fn new(watchlist: Watchlist, timeframe: u32, exchange: &str) -> Self {
+        Self {
+            watchlist,
+            timeframe,
+            label: exchange.to_uppercase(),
+            notifier: None,
+            history: HashMap::new(),
+            last_price: HashMap::new(),
+            last_fired: HashMap::new(),
+        }
+    }
+
+    /// 送信先が設定されていない場合は表示のみ
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = (!notifier.is_empty()).then(|| Arc::new(notifier));
+        self
+    }
+
+    // 保持する履歴の本数 (move の window と volume の lookback を満たす)
+    fn capacity(&self) -> usize {
+        self.watchlist
+            .rules
+            .iter()
+            .map(|rule| match rule.condition {
+                AlertCondition::Move { window, .. } => (window.num_seconds() / self.timeframe.max(1) as i64) as usize + 1,
+                AlertCondition::VolumeSpike { lookback, .. } => lookback + 1,
+                _ => 1,
+            })
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// 足を 1 本評価する (対象外の時間枠, ウォームアップ・訂正の足は無視する)
+    pub fn evaluate(&mut self, candle: &TradeCandle) -> Vec<Alert> {
+        if candle.period_seconds != self.timeframe as i32 || candle.warmup || candle.revision > 0 {
+            return Vec::new();
+        }
+        let Some(price) = candle_price(candle) else {
+            return Vec::new();
+        };
+        let volume = candle.ask_volume + candle.bid_volume;
+        let capacity = self.capacity();
+        let history = self.history.entry(candle.symbol.clone()).or_default();
+
+        let mut alerts = Vec::new();
+        for (index, rule) in self.watchlist.rules.iter().enumerate() {
+            if rule.symbol != "*" && rule.symbol != candle.symbol {
+                continue;
+            }
+            let key = (index, candle.symbol.clone());
+            let message = match rule.condition {
+                AlertCondition::CrossAbove(level) => {
+                    let previous = self.last_price.insert(key.clone(), price);
+                    previous
+                        .filter(|&previous| previous < level && price >= level)
+                        .map(|_| format!("price crossed above {} ({})", level, price))
+                }
+                AlertCondition::CrossBelow(level) => {
+                    let previous = self.last_price.insert(key.clone(), price);
+                    previous
+                        .filter(|&previous| previous > level && price <= level)
+                        .map(|_| format!("price crossed below {} ({})", level, price))
+                }
+                AlertCondition::Move { percent, window } => {
+                    if self.last_fired.get(&key).is_some_and(|&fired| candle.timestamp - fired < window) {
+                        continue;
+                    }
+                    // window 前 (以前) の最後の足と比べる
+                    let since = candle.timestamp - window;
+                    history
+                        .iter()
+                        .rev()
+                        .find(|(timestamp, _, _)| *timestamp <= since)
+                        .map(|&(_, base, _)| (price - base) / base * 100.0)
+                        .filter(|change| change.abs() >= percent)
+                        .map(|change| format!("moved {:+.2}% in {}s ({})", change, window.num_seconds(), price))
+                }
+                AlertCondition::VolumeSpike { multiple, lookback } => {
+                    if history.len() < lookback {
+                        continue;
+                    }
+                    let average = history.iter().rev().take(lookback).map(|&(_, _, v)| v).sum::<f64>() / lookback as f64;
+                    (average > 0.0 && volume >= average * multiple)
+                        .then(|| format!("volume {:.4} is {:.1}x the {}-candle average", volume, volume / average, lookback))
+                }
+            };
+            if let Some(message) = message {
+                self.last_fired.insert(key, candle.timestamp);
+                alerts.push(Alert {
+                    timestamp: candle.timestamp,
+                    exchange: candle.exchange.clone(),
+                    symbol: candle.symbol.clone(),
+                    rule: rule.spec.clone(),
+                    message,
+                });
+            }
+        }
+
+        history.push_back((candle.timestamp, price, volume));
+        while history.len() > capacity {
+            history.pop_front();
+        }
+        alerts
+    }
+
+    /// ローソク足を評価しながら全てのイベントを後段にそのまま流す
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        tracing::info!("AlertEngine started with {} rules on {}s candles", self.watchlist.rules.len(), self.timeframe);
+        while let Some(event) = receiver.recv().await {
+            if let MarketEvent::Candle(candle) = &event {
+                for alert in self.evaluate(candle) {
+                    tracing::warn!("[{}-ALERT] {}", self.label, alert);
+                    if let Some(notifier) = self.notifier.clone() {
+                        // 通知の遅延でパイプラインを止めない
+                        let text = format!("[{}] {}", self.label, alert);
+                        tokio::spawn(async move {
+                            if let Err(e) = notifier.send(&text).await {
+                                error!("{}", e);
+                            }
+                        });
+                    }
+                }
+            }
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+}
+
+// 約定のある足は VWAP, 空の足は引き継いだ売り買いの価格
+fn candle_price(candle: &TradeCandle) -> Option<f64> {
+    candle.vwap().or(match (candle.ask_price, candle.bid_price) {
+        (Some(ask), Some(bid)) => Some((ask + bid) / 2.0),
+        (price, None) | (None, price) => price,
+    })
+}
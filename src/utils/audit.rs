@@ -0,0 +1,120 @@
+use crate::models::trade_candle::TradeCandle;
+use super::trade_candle_builder::AuditControl;
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+const RELATIVE_TOLERANCE: f64 = 1e-9;  // 逐次計算と一括計算の浮動小数点誤差として許容する相対差
+
+/// 出力した足と, 保持していた約定から計算し直した足の差
+#[derive(Debug, Clone)]
+pub struct AuditMismatch {
+    pub exchange: String,
+    pub symbol: String,
+    pub period_seconds: i32,
+    pub timestamp: DateTime<Utc>,
+    pub revision: u32,
+    pub field: &'static str,
+    pub emitted: String,
+    pub recomputed: String,
+}
+
+impl fmt::Display for AuditMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}s @ {} (revision {}) {}: emitted {} / recomputed {}",
+            self.exchange, self.symbol, self.period_seconds, self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.revision, self.field, self.emitted, self.recomputed
+        )
+    }
+}
+
+/// 監査の結果
+#[derive(Debug, Clone)]
+pub struct AuditReport {
+    pub taken_at: DateTime<Utc>,
+    pub checked: usize,  // 計算し直した足の数
+    pub mismatches: Vec<AuditMismatch>,
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "checked {} candles, {} mismatches", self.checked, self.mismatches.len())
+    }
+}
+
+/// 出力した足と計算し直した足を比べる (中央値は標本の間引き方で変わるので比べない)
+pub fn compare(emitted: &TradeCandle, recomputed: &TradeCandle) -> Vec<AuditMismatch> {
+    let mut mismatches = Vec::new();
+    let mut check = |field: &'static str, same: bool, a: String, b: String| {
+        if !same {
+            mismatches.push(AuditMismatch {
+                exchange: emitted.exchange.clone(),
+                symbol: emitted.symbol.clone(),
+                period_seconds: emitted.period_seconds,
+                timestamp: emitted.timestamp,
+                revision: emitted.revision,
+                field,
+                emitted: a,
+                recomputed: b,
+            });
+        }
+    };
+    let close = |a: f64, b: f64| (a - b).abs() <= RELATIVE_TOLERANCE * a.abs().max(b.abs()).max(1.0);
+    let close_opt = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) => close(a, b),
+        (a, b) => a == b,
+    };
+
+    check("ask_count", emitted.ask_count == recomputed.ask_count, emitted.ask_count.to_string(), recomputed.ask_count.to_string());
+    check("bid_count", emitted.bid_count == recomputed.bid_count, emitted.bid_count.to_string(), recomputed.bid_count.to_string());
+    for (field, a, b) in [
+        ("ask_volume", emitted.ask_volume, recomputed.ask_volume),
+        ("bid_volume", emitted.bid_volume, recomputed.bid_volume),
+        ("ask_notional", emitted.ask_notional, recomputed.ask_notional),
+        ("bid_notional", emitted.bid_notional, recomputed.bid_notional),
+    ] {
+        check(field, close(a, b), a.to_string(), b.to_string());
+    }
+    for (field, a, b) in [
+        ("ask_price", emitted.ask_price, recomputed.ask_price),
+        ("bid_price", emitted.bid_price, recomputed.bid_price),
+        ("open", emitted.open, recomputed.open),
+        ("high", emitted.high, recomputed.high),
+        ("low", emitted.low, recomputed.low),
+        ("close", emitted.close, recomputed.close),
+        ("realized_vol", emitted.realized_vol, recomputed.realized_vol),
+    ] {
+        check(field, close_opt(a, b), format!("{:?}", a), format!("{:?}", b));
+    }
+    check(
+        "direction_changes",
+        emitted.direction_changes == recomputed.direction_changes,
+        emitted.direction_changes.to_string(),
+        recomputed.direction_changes.to_string(),
+    );
+    let size = |candle: &TradeCandle| candle.trade_size.as_ref().map(|stats| (stats.max, stats.histogram));
+    check("trade_size", size(emitted) == size(recomputed), format!("{:?}", size(emitted)), format!("{:?}", size(recomputed)));
+    mismatches
+}
+
+/// SIGUSR2 を受けたら監査して結果を出力する (admin audit --pid <PID>)
+#[cfg(unix)]
+pub async fn serve(label: String, control: AuditControl) -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut usr2 = signal(SignalKind::user_defined2())?;
+    while usr2.recv().await.is_some() {
+        let report = control.audit().await?;
+        tracing::info!("[{}-AUDIT] {}", label, report);
+        for mismatch in &report.mismatches {
+            tracing::warn!("[{}-AUDIT] {}", label, mismatch);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn serve(_label: String, _control: AuditControl) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("Audits are triggered by unix signals and are not supported on this platform"))
+}
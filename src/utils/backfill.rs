@@ -0,0 +1,93 @@
+use crate::models::trade::Trade;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// 1 回の補完で REST を呼ぶ上限 (ページ送りできる取引所のみ)
+pub const MAX_BACKFILL_PAGES: usize = 10;
+
+/// 約定 ID が連番の取引所で届かなかった ID の範囲 (from..=to)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceGap {
+    pub symbol: String,
+    pub from: u64,
+    pub to: u64,
+}
+
+impl SequenceGap {
+    pub fn missing(&self) -> u64 {
+        self.to - self.from + 1
+    }
+}
+
+/// 銘柄ごとに最後に受信した約定 (再接続時に REST で補完する起点)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastTrade {
+    pub trade_id: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+pub struct LastTrades {
+    trades: HashMap<String, LastTrade>,
+}
+
+impl LastTrades {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 約定時刻が最後の約定より前なら更新しない
+    pub fn record(&mut self, trade: &Trade) {
+        if self.trades.get(&trade.symbol).is_some_and(|last| last.timestamp > trade.timestamp) {
+            return;
+        }
+        self.trades.insert(
+            trade.symbol.clone(),
+            LastTrade {
+                trade_id: trade.trade_id.clone(),
+                timestamp: trade.timestamp,
+            },
+        );
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&LastTrade> {
+        self.trades.get(symbol)
+    }
+
+    /// 約定 ID が連番の取引所 (Binance aggTrade) で, 最後の約定の次の ID より先の約定なら欠番を返す (record の前に呼ぶ)
+    pub fn sequence_gap(&self, trade: &Trade) -> Option<SequenceGap> {
+        let last_id = self.get(&trade.symbol)?.trade_id.parse::<u64>().ok()?;
+        let id = trade.trade_id.parse::<u64>().ok()?;
+        (id > last_id + 1).then(|| SequenceGap {
+            symbol: trade.symbol.clone(),
+            from: last_id + 1,
+            to: id - 1,
+        })
+    }
+
+    /// 連番の約定 ID で, 最後の約定より後の約定か (最後の約定がなければ true)
+    pub fn is_after_last(&self, trade: &Trade) -> bool {
+        let last_id = self.get(&trade.symbol).and_then(|last| last.trade_id.parse::<u64>().ok());
+        match (last_id, trade.trade_id.parse::<u64>().ok()) {
+            (Some(last_id), Some(id)) => id > last_id,
+            _ => true,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    /// REST で取得した約定から, 最後の約定より後のものを古い順に返す (受信済みの約定の前後も含めて返るため)
+    /// 同じミリ秒の約定は最後の約定以外を残す (重複は後段で除く)
+    pub fn missed(&self, symbol: &str, mut trades: Vec<Trade>) -> Vec<Trade> {
+        trades.sort_by_key(|t| t.timestamp);
+        let Some(last) = self.get(symbol) else {
+            return trades;
+        };
+        trades
+            .into_iter()
+            .filter(|t| t.timestamp > last.timestamp || (t.timestamp == last.timestamp && t.trade_id != last.trade_id))
+            .collect()
+    }
+}
@@ -0,0 +1,202 @@
+use crate::models::market_event::MarketEvent;
+use super::ops_events::{self, OpsEventKind};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// 既定で溜めておけるイベント数 (後段が詰まっている間, クライアントの読み込みを止めずに受け取る)
+pub const DEFAULT_BACKPRESSURE_CAPACITY: usize = 10_000;
+
+/// 後段が詰まったときの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    Block,       // 空くまで待つ (WebSocket の読み込みも止まる. 取引所に切断されることがある)
+    DropOldest,  // 一番古いイベントを捨てて受け取る
+    DropNewest,  // 受け取ったイベントを捨てる
+}
+
+impl BackpressurePolicy {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim() {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            "drop-newest" => Ok(Self::DropNewest),
+            _ => Err(anyhow::anyhow!("Invalid backpressure policy: {}. Use block, drop-oldest or drop-newest", spec)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::DropOldest => "drop-oldest",
+            Self::DropNewest => "drop-newest",
+        }
+    }
+}
+
+/// 溜まっているイベント数と捨てたイベント数 (clone して表示と共有する)
+#[derive(Debug, Clone, Default)]
+pub struct BackpressureMetrics {
+    queued: Arc<AtomicU64>,
+    peak: Arc<AtomicU64>,
+    dropped_trades: Arc<AtomicU64>,
+    dropped_other: Arc<AtomicU64>,  // 約定以外 (quote, liquidation など)
+}
+
+impl BackpressureMetrics {
+    pub fn queued(&self) -> u64 {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped_trades(&self) -> u64 {
+        self.dropped_trades.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped_other(&self) -> u64 {
+        self.dropped_other.load(Ordering::Relaxed)
+    }
+
+    fn observe(&self, queued: usize) {
+        self.queued.store(queued as u64, Ordering::Relaxed);
+        self.peak.fetch_max(queued as u64, Ordering::Relaxed);
+    }
+
+    fn record_drop(&self, event: &MarketEvent) {
+        match event {
+            MarketEvent::Trade(_) => self.dropped_trades.fetch_add(1, Ordering::Relaxed),
+            _ => self.dropped_other.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// interval ごとに表示し, 前回から捨てたイベントがあれば警告して ops_events に残す (label は表示用の取引所名)
+    pub async fn log_every(self, label: String, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        let mut reported = (0, 0);
+        loop {
+            ticker.tick().await;
+            tracing::info!("[{}-BACKPRESSURE] {}", label, self);
+            let dropped = (self.dropped_trades(), self.dropped_other());
+            if dropped != reported {
+                let detail = format!("dropped {} trades and {} other events in the last {}s",
+                    dropped.0 - reported.0, dropped.1 - reported.1, interval.as_secs());
+                tracing::warn!("{} {}", label, detail);
+                ops_events::record(OpsEventKind::EventsDropped, detail);
+                reported = dropped;
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BackpressureMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "queued:{} peak:{} dropped_trades:{} dropped_other:{}",
+            self.queued(), self.peak(), self.dropped_trades(), self.dropped_other())
+    }
+}
+
+/// 取引所クライアントの直後に置き, 後段が詰まってもクライアントからは待たずに受け取って capacity まで溜める
+/// 溜まりきったら policy に従って捨てる (Block なら溜まりきった時点で受け取りを止める)
+pub struct BackpressureQueue {
+    policy: BackpressurePolicy,
+    capacity: usize,
+    queue: VecDeque<MarketEvent>,
+    metrics: BackpressureMetrics,
+}
+
+impl BackpressureQueue {
+    pub fn new(policy: BackpressurePolicy, capacity: usize) -> Self {
+        Self {
+            policy,
+            capacity: capacity.max(1),
+            queue: VecDeque::new(),
+            metrics: BackpressureMetrics::default(),
+        }
+    }
+
+    pub fn metrics(&self) -> BackpressureMetrics {
+        self.metrics.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    fn is_full(&self) -> bool {
+        self.queue.len() >= self.capacity
+    }
+
+    /// イベントを溜める (溜まりきっていれば policy に従って 1 件捨て, 捨てたイベントを返す)
+    pub fn push(&mut self, event: MarketEvent) -> Option<MarketEvent> {
+        let dropped = if !self.is_full() {
+            self.queue.push_back(event);
+            None
+        } else {
+            match self.policy {
+                BackpressurePolicy::Block => {
+                    self.queue.push_back(event);
+                    None
+                }
+                BackpressurePolicy::DropOldest => {
+                    let oldest = self.queue.pop_front();
+                    self.queue.push_back(event);
+                    oldest
+                }
+                BackpressurePolicy::DropNewest => Some(event),
+            }
+        };
+        if let Some(event) = &dropped {
+            self.metrics.record_drop(event);
+        }
+        self.metrics.observe(self.queue.len());
+        dropped
+    }
+
+    pub fn pop(&mut self) -> Option<MarketEvent> {
+        let event = self.queue.pop_front();
+        self.metrics.observe(self.queue.len());
+        event
+    }
+
+    /// 受け取りと後段への送信を並行して行う. receiver が閉じたら溜まっている分を送ってから終了する
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        loop {
+            // Block は溜まりきったら受け取りを止め, クライアントの送信を待たせる
+            let accepting = self.policy != BackpressurePolicy::Block || !self.is_full();
+            tokio::select! {
+                event = receiver.recv(), if accepting => match event {
+                    Some(event) => {
+                        if let Some(dropped) = self.push(event) {
+                            tracing::debug!("Dropped {} {} ({})", dropped.kind(), dropped.symbol(), self.policy.as_str());
+                        }
+                    }
+                    None => break,
+                },
+                permit = sender.reserve(), if !self.is_empty() => match permit {
+                    Ok(permit) => permit.send(self.pop().unwrap()),
+                    Err(e) => {
+                        error!("Failed to forward queued events: {}", e);
+                        return;
+                    }
+                },
+            }
+        }
+        while let Some(event) = self.pop() {
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::models::bar::{Bar, BarType};
+use crate::models::market_event::MarketEvent;
+use crate::models::market_type::MarketType;
+use crate::models::trade::Trade;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// 約定から tick / volume / dollar bar を作る (時間の足は TradeCandleBuilder)
+/// 約定はそのまま後段に流し, 閉じた足を MarketEvent::Bar として追加する
+pub struct BarBuilder {
+    bar_types: Vec<BarType>,
+    open: HashMap<(String, MarketType, String, usize), Bar>,  // (exchange, market_type, symbol, bar_types の添字) -> 集計中の足
+}
+
+impl BarBuilder {
+    pub fn new(bar_types: Vec<BarType>) -> Self {
+        Self {
+            bar_types,
+            open: HashMap::new(),
+        }
+    }
+
+    /// 約定を 1 件加え, 閉じた足を返す
+    pub fn push(&mut self, trade: &Trade) -> Vec<Bar> {
+        let mut closed = Vec::new();
+        for (index, bar_type) in self.bar_types.iter().enumerate() {
+            let key = (trade.exchange.clone(), trade.market_type.clone(), trade.symbol.clone(), index);
+            let bar = self
+                .open
+                .entry(key.clone())
+                .and_modify(|bar| bar.update(trade))
+                .or_insert_with(|| Bar::new(*bar_type, trade));
+            if bar_type.is_complete(bar) {
+                closed.extend(self.open.remove(&key));
+            }
+        }
+        closed
+    }
+
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        tracing::info!("BarBuilder started with bar types: {:?}", self.bar_types.iter().map(BarType::name).collect::<Vec<_>>());
+        while let Some(event) = receiver.recv().await {
+            let bars = match &event {
+                MarketEvent::Trade(trade) => self.push(trade),
+                _ => Vec::new(),
+            };
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+            for bar in bars {
+                if let Err(e) = sender.send(MarketEvent::Bar(bar)).await {
+                    error!("Failed to send bar: {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use super::correlation_store::open_timeseries;
+use super::rank_correlation::pearson;
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database as MongoDatabase};
+
+/// beta の計算結果を書き込むコレクション
+pub const BETA_COLLECTION: &str = "betas";
+
+/// benchmark に対する symbol の beta (cov(r, r_benchmark) / var(r_benchmark)) と決定係数
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolBeta {
+    pub symbol_id: i32,
+    pub benchmark_id: i32,
+    pub count: usize,  // benchmark と両方の値があるバケット数
+    pub beta: Option<f64>,
+    pub r_squared: Option<f64>,
+}
+
+/// 両方の値がある組の beta と決定係数 (2 組未満・benchmark が一定なら None)
+pub fn regress(benchmark: &[f64], values: &[f64]) -> (Option<f64>, Option<f64>) {
+    let n = benchmark.len().min(values.len());
+    if n < 2 {
+        return (None, None);
+    }
+    let mean_x = benchmark[..n].iter().sum::<f64>() / n as f64;
+    let mean_y = values[..n].iter().sum::<f64>() / n as f64;
+    let (mut c_xy, mut m2_x) = (0.0, 0.0);
+    for (x, y) in benchmark.iter().zip(values) {
+        c_xy += (x - mean_x) * (y - mean_y);
+        m2_x += (x - mean_x).powi(2);
+    }
+    let beta = (m2_x > 0.0).then(|| c_xy / m2_x);
+    (beta, pearson(benchmark, values).map(|corr| corr * corr))
+}
+
+/// 計算の条件 (metadata に入れる)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BetaParams {
+    pub window_minutes: u32,
+    pub interval_seconds: i64,
+    pub returns: usize,  // 対数収益率の horizon (バケット数)
+}
+
+/// 1 回の計算の beta を symbol ごとのドキュメントにする
+/// metadata は (ym, symbol_id, benchmark の symbol_id, 窓の分数, バケットの秒数, 収益率の horizon), beta と r2 は計算できなければ null
+pub fn to_documents(timestamp: DateTime<Utc>, params: &BetaParams, betas: &[SymbolBeta]) -> Vec<Document> {
+    let ym = timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+    betas
+        .iter()
+        .map(|beta| doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol": beta.symbol_id,
+                "benchmark": beta.benchmark_id,
+                "window": params.window_minutes as i32,
+                "interval": params.interval_seconds,
+                "returns": params.returns as i32,
+            },
+            "beta": beta.beta,
+            "r2": beta.r_squared,
+            "count": beta.count as i64,
+        })
+        .collect()
+}
+
+/// beta を betas (時系列コレクション) に書き込む
+pub struct BetaStore {
+    collection: Collection<Document>,
+    params: BetaParams,
+}
+
+impl BetaStore {
+    pub async fn open(database: &MongoDatabase, collection_name: &str, params: BetaParams) -> anyhow::Result<Self> {
+        let index = doc! { "metadata.symbol": 1, "metadata.benchmark": 1, "unixtime": 1 };
+        Ok(Self { collection: open_timeseries(database, collection_name, index).await?, params })
+    }
+
+    /// window_minutes の窓の beta を書き込み, 書き込んだ symbol の数を返す
+    pub async fn write(&self, timestamp: DateTime<Utc>, window_minutes: u32, betas: &[SymbolBeta]) -> anyhow::Result<usize> {
+        let params = BetaParams { window_minutes, ..self.params };
+        let docs = to_documents(timestamp, &params, betas);
+        if docs.is_empty() {
+            return Ok(0);
+        }
+        let count = docs.len();
+        self.collection.insert_many(docs).ordered(false).await?;
+        Ok(count)
+    }
+}
@@ -0,0 +1,223 @@
+use crate::models::market_event::MarketEvent;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, info, warn};
+
+/// 配信を待たせておけるメッセージ数 (遅いクライアントはこれを超えると古いメッセージを飛ばす)
+pub const BROADCAST_CAPACITY: usize = 10_000;
+
+/// 配信する 1 件 (JSON は 1 度だけ作り, 全てのクライアントで共有する)
+#[derive(Debug)]
+pub struct BroadcastMessage {
+    pub kind: &'static str,  // trade / candle
+    pub exchange: String,
+    pub symbol: String,
+    pub json: String,        // {"type":"trade","data":{...}}
+}
+
+impl BroadcastMessage {
+    /// Trade / Candle だけを配信する
+    pub fn from_event(event: &MarketEvent) -> Option<Self> {
+        let data = match event {
+            MarketEvent::Trade(trade) => serde_json::to_value(trade),
+            MarketEvent::Candle(candle) => serde_json::to_value(candle),
+            _ => return None,
+        };
+        let data = data.map_err(|e| error!("Failed to serialize {}: {}", event.kind(), e)).ok()?;
+        Some(Self {
+            kind: event.kind(),
+            exchange: event.exchange().to_string(),
+            symbol: event.symbol().to_string(),
+            json: serde_json::json!({ "type": event.kind(), "data": data }).to_string(),
+        })
+    }
+}
+
+/// クライアントからの購読の変更 ({"op":"subscribe","symbols":["BTCUSDT"]})
+#[derive(Debug, Deserialize)]
+pub struct ClientCommand {
+    pub op: String,
+    #[serde(default)]
+    pub symbols: Vec<String>,
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub exchanges: Vec<String>,
+}
+
+/// 接続ごとの絞り込み (空なら全て)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BroadcastFilter {
+    pub symbols: HashSet<String>,
+    pub types: HashSet<String>,
+    pub exchanges: HashSet<String>,
+}
+
+impl BroadcastFilter {
+    /// 接続 URL のクエリ (e.g. ?symbols=BTCUSDT,ETHUSDT&types=candle&exchanges=bybit)
+    pub fn from_query(query: &str) -> anyhow::Result<Self> {
+        let mut filter = Self::default();
+        for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+            let values = value.split(',').map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+            match key {
+                "symbols" => filter.symbols.extend(values),
+                "types" => filter.types.extend(values),
+                "exchanges" => filter.exchanges.extend(values.map(|v| v.to_lowercase())),
+                _ => return Err(anyhow::anyhow!("Unknown filter: {}. Use symbols, types or exchanges", key)),
+            }
+        }
+        filter.validate()?;
+        Ok(filter)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        match self.types.iter().find(|t| !matches!(t.as_str(), "trade" | "candle")) {
+            Some(t) => Err(anyhow::anyhow!("Unknown type: {}. Use trade or candle", t)),
+            None => Ok(()),
+        }
+    }
+
+    /// subscribe は追加, unsubscribe は削除 (削除して空になれば全てに戻る)
+    pub fn apply(&mut self, command: &ClientCommand) -> anyhow::Result<()> {
+        let exchanges = command.exchanges.iter().map(|e| e.to_lowercase());
+        match command.op.as_str() {
+            "subscribe" => {
+                self.symbols.extend(command.symbols.iter().cloned());
+                self.types.extend(command.types.iter().cloned());
+                self.exchanges.extend(exchanges);
+            }
+            "unsubscribe" => {
+                for symbol in &command.symbols {
+                    self.symbols.remove(symbol);
+                }
+                for t in &command.types {
+                    self.types.remove(t);
+                }
+                for exchange in exchanges {
+                    self.exchanges.remove(&exchange);
+                }
+            }
+            op => return Err(anyhow::anyhow!("Unknown op: {}. Use subscribe or unsubscribe", op)),
+        }
+        self.validate()
+    }
+
+    pub fn matches(&self, message: &BroadcastMessage) -> bool {
+        (self.symbols.is_empty() || self.symbols.contains(&message.symbol))
+            && (self.types.is_empty() || self.types.contains(message.kind))
+            && (self.exchanges.is_empty() || self.exchanges.contains(&message.exchange))
+    }
+}
+
+/// パイプラインを流れる Trade / Candle を WebSocket のクライアントに配信するハンドル (clone して約定側・足側の両方に挟む)
+#[derive(Clone)]
+pub struct EventBroadcaster {
+    sender: broadcast::Sender<Arc<BroadcastMessage>>,
+}
+
+impl EventBroadcaster {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<BroadcastMessage>> {
+        self.sender.subscribe()
+    }
+
+    /// クライアントがいれば配信する
+    pub fn publish(&self, event: &MarketEvent) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        if let Some(message) = BroadcastMessage::from_event(event) {
+            let _ = self.sender.send(Arc::new(message));
+        }
+    }
+
+    /// 全てのイベントを配信しながら後段にそのまま流す
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            self.publish(&event);
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+}
+
+async fn handle(stream: TcpStream, broadcaster: &EventBroadcaster) -> anyhow::Result<()> {
+    let mut query = String::new();
+    let callback = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        query = request.uri().query().unwrap_or("").to_string();
+        Ok(response)
+    };
+    let websocket = tokio_tungstenite::accept_hdr_async(stream, callback).await?;
+    let (mut write, mut read) = websocket.split();
+    let mut filter = match BroadcastFilter::from_query(&query) {
+        Ok(filter) => filter,
+        Err(e) => {
+            write.send(Message::Text(serde_json::json!({ "error": e.to_string() }).to_string())).await?;
+            return Ok(());
+        }
+    };
+    let mut messages = broadcaster.subscribe();
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Ok(message) if filter.matches(&message) => write.send(Message::Text(message.json.clone())).await?,
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Broadcast client fell behind; skipped {} messages", skipped);
+                    write.send(Message::Text(serde_json::json!({ "type": "lagged", "skipped": skipped }).to_string())).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = read.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let result = serde_json::from_str::<ClientCommand>(&text)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|command| filter.apply(&command));
+                    let reply = match result {
+                        Ok(()) => serde_json::json!({
+                            "type": "subscribed",
+                            "symbols": filter.symbols,
+                            "types": filter.types,
+                            "exchanges": filter.exchanges,
+                        }),
+                        Err(e) => serde_json::json!({ "error": e.to_string() }),
+                    };
+                    write.send(Message::Text(reply.to_string())).await?;
+                }
+                Some(Ok(Message::Ping(payload))) => write.send(Message::Pong(payload)).await?,
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Trade / Candle を JSON で配信する WebSocket サーバー (listener は起動時に失敗するように呼び出し側で bind する)
+pub async fn serve(listener: TcpListener, broadcaster: EventBroadcaster) -> anyhow::Result<()> {
+    info!("Broadcasting trades and candles on ws://{}/ (?symbols=...&types=trade,candle)", listener.local_addr()?);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let broadcaster = broadcaster.clone();
+        tokio::spawn(async move {
+            info!("Broadcast client {} connected", peer);
+            if let Err(e) = handle(stream, &broadcaster).await {
+                debug!("Broadcast client {} failed: {}", peer, e);
+            }
+            info!("Broadcast client {} disconnected", peer);
+        });
+    }
+}
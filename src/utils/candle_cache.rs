@@ -0,0 +1,113 @@
+use crate::models::trade_candle::TradeCandle;
+use crate::utils::symbol_manager::SYMBOL_MANAGER;
+use chrono::Duration;
+use polars::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+/// 直近 retention 分のローソク足を symbol・時間枠ごとに保持するリングキャッシュ
+/// 読み出しは polars の DataFrame で返す (DB を参照せずに相関計算などを初期化する用途)
+pub struct CandleCache {
+    retention: Duration,
+    series: HashMap<(String, i32), VecDeque<TradeCandle>>,
+}
+
+impl CandleCache {
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            series: HashMap::new(),
+        }
+    }
+
+    pub fn push(&mut self, candle: &TradeCandle) {
+        let series = self
+            .series
+            .entry((candle.symbol.clone(), candle.period_seconds))
+            .or_default();
+        // 時系列順に届く前提 (遅れて届いた足は末尾に積まずに挿入する, 訂正された足は置き換える)
+        let position = series.partition_point(|c| c.timestamp < candle.timestamp);
+        if series.get(position).is_some_and(|c| c.timestamp == candle.timestamp) {
+            series[position] = candle.clone();
+        } else {
+            series.insert(position, candle.clone());
+        }
+
+        let Some(latest) = series.back().map(|c| c.timestamp) else {
+            return;
+        };
+        while series.front().is_some_and(|c| c.timestamp <= latest - self.retention) {
+            series.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.series.values().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn symbols(&self, period_seconds: i32) -> Vec<String> {
+        let mut symbols: Vec<String> = self
+            .series
+            .keys()
+            .filter(|(_, period)| *period == period_seconds)
+            .map(|(symbol, _)| symbol.clone())
+            .collect();
+        symbols.sort();
+        symbols
+    }
+
+    pub fn candles(&self, symbol: &str, period_seconds: i32) -> Vec<TradeCandle> {
+        self.series
+            .get(&(symbol.to_string(), period_seconds))
+            .map(|series| series.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// 1 symbol・1 時間枠のローソク足 (timestamp はミリ秒)
+    pub fn to_dataframe(&self, symbol: &str, period_seconds: i32) -> anyhow::Result<DataFrame> {
+        let candles = self.candles(symbol, period_seconds);
+        Ok(DataFrame::new(vec![
+            Series::new("timestamp".into(), candles.iter().map(|c| c.timestamp.timestamp_millis()).collect::<Vec<i64>>()).into(),
+            Series::new("ask_price".into(), candles.iter().map(|c| c.ask_price).collect::<Vec<Option<f64>>>()).into(),
+            Series::new("ask_volume".into(), candles.iter().map(|c| c.ask_volume).collect::<Vec<f64>>()).into(),
+            Series::new("ask_notional".into(), candles.iter().map(|c| c.ask_notional).collect::<Vec<f64>>()).into(),
+            Series::new("ask_count".into(), candles.iter().map(|c| c.ask_count).collect::<Vec<i32>>()).into(),
+            Series::new("bid_price".into(), candles.iter().map(|c| c.bid_price).collect::<Vec<Option<f64>>>()).into(),
+            Series::new("bid_volume".into(), candles.iter().map(|c| c.bid_volume).collect::<Vec<f64>>()).into(),
+            Series::new("bid_notional".into(), candles.iter().map(|c| c.bid_notional).collect::<Vec<f64>>()).into(),
+            Series::new("bid_count".into(), candles.iter().map(|c| c.bid_count).collect::<Vec<i32>>()).into(),
+            Series::new("delta".into(), candles.iter().map(|c| c.delta()).collect::<Vec<f64>>()).into(),
+            Series::new("cvd".into(), candles.iter().map(|c| c.cvd).collect::<Vec<f64>>()).into(),
+            Series::new("vwap".into(), candles.iter().map(|c| c.vwap()).collect::<Vec<Option<f64>>>()).into(),
+        ])?)
+    }
+
+    /// 全 symbol の VWAP を縦持ちで返す (correlation の初期データと同じ timestamp, symbol_id, price の形式)
+    pub fn price_frame(&self, period_seconds: i32) -> anyhow::Result<DataFrame> {
+        let mut rows: Vec<(i64, i32, f64)> = Vec::new();
+        for ((symbol, period), series) in &self.series {
+            if *period != period_seconds {
+                continue;
+            }
+            for candle in series {
+                let Some(price) = candle.vwap() else {
+                    continue;
+                };
+                let symbol_id = SYMBOL_MANAGER
+                    .get_symbol_id(&candle.exchange, symbol, candle.market_type.as_str())
+                    .unwrap_or(0);
+                rows.push((candle.timestamp.timestamp_millis(), symbol_id, price));
+            }
+        }
+        rows.sort_by_key(|(ts, sid, _)| (*ts, *sid));
+
+        Ok(DataFrame::new(vec![
+            Series::new("timestamp".into(), rows.iter().map(|(ts, _, _)| *ts).collect::<Vec<i64>>()).into(),
+            Series::new("symbol_id".into(), rows.iter().map(|(_, sid, _)| *sid).collect::<Vec<i32>>()).into(),
+            Series::new("price".into(), rows.iter().map(|(_, _, p)| *p).collect::<Vec<f64>>()).into(),
+        ])?)
+    }
+}
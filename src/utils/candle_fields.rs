@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use mongodb::bson::Document;
+use crate::models::trade_candle::TradeCandle;
+
+// 常に保存するキー (Time Series の timeField / metaField, ウォームアップ・訂正のフラグ)
+const REQUIRED_KEYS: [&str; 5] = ["unixtime", "metadata", "warmup", "ts_source", "revision"];
+
+/// 時間枠ごとに保存する candle フィールドを選択する設定
+/// 書式: "1=ask_price,bid_price;60=*"  (キーは秒, "*" キーは全時間枠のデフォルト)
+#[derive(Debug, Clone, Default)]
+pub struct CandleFieldSelection {
+    per_timeframe: HashMap<i32, Option<Vec<String>>>, // None = 全フィールド
+    default: Option<Vec<String>>,
+}
+
+impl CandleFieldSelection {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut selection = Self::default();
+
+        for entry in spec.split(';').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (key, fields) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid candle field entry: {}. Use <seconds>=<field,...>", entry))?;
+
+            let fields = Self::parse_fields(fields)?;
+            match key.trim() {
+                "*" => selection.default = fields,
+                k => {
+                    let seconds: i32 = k
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid timeframe in candle fields: {} (use seconds, e.g., 1,60)", k))?;
+                    selection.per_timeframe.insert(seconds, fields);
+                }
+            }
+        }
+
+        Ok(selection)
+    }
+
+    fn parse_fields(fields: &str) -> Result<Option<Vec<String>>> {
+        let fields: Vec<String> = fields
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        if fields.iter().any(|f| f == "*") {
+            return Ok(None);
+        }
+
+        for field in &fields {
+            if !TradeCandle::FIELDS.contains(&field.as_str()) {
+                return Err(anyhow::anyhow!("Unknown candle field: {}. Available: {:?}", field, TradeCandle::FIELDS));
+            }
+        }
+
+        Ok(Some(fields))
+    }
+
+    /// 指定時間枠で保存対象外のフィールドを document から取り除く
+    pub fn apply(&self, period_seconds: i32, doc: Document) -> Document {
+        let fields = match self.per_timeframe.get(&period_seconds) {
+            Some(fields) => fields,
+            None => &self.default,
+        };
+
+        match fields {
+            None => doc,
+            Some(fields) => doc
+                .into_iter()
+                .filter(|(key, _)| REQUIRED_KEYS.contains(&key.as_str()) || fields.contains(key))
+                .collect(),
+        }
+    }
+}
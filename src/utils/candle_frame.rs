@@ -0,0 +1,98 @@
+use crate::models::trade_candle::TradeCandle;
+use polars::prelude::*;
+use std::io::Write;
+
+/// 書き出せる列 (timestamp は保存時と同じ足の終端)
+pub const COLUMNS: &[&str] = &[
+    "timestamp", "exchange", "market_type", "symbol", "period_seconds",
+    "open", "high", "low", "close", "vwap",
+    "ask_price", "ask_volume", "ask_notional", "ask_count",
+    "bid_price", "bid_volume", "bid_notional", "bid_count",
+    "delta", "cvd", "realized_vol", "direction_changes", "revision", "warmup",
+];
+
+/// 表の書式 (全ての列を書ける)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameFormat {
+    Parquet,
+    Csv,
+}
+
+impl FrameFormat {
+    /// 書式: "parquet", "csv" (OHLCV の書式なら None)
+    pub fn parse(spec: &str) -> Option<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "parquet" => Some(Self::Parquet),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// --columns の列 (カンマ区切り, なければ全て)
+pub fn parse_columns(spec: Option<&str>) -> anyhow::Result<Vec<String>> {
+    let Some(spec) = spec else {
+        return Ok(COLUMNS.iter().map(|c| c.to_string()).collect());
+    };
+    let columns: Vec<String> = spec.split(',').map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).collect();
+    if let Some(column) = columns.iter().find(|c| !COLUMNS.contains(&c.as_str())) {
+        return Err(anyhow::anyhow!("Unknown column: {}. Use {}", column, COLUMNS.join(",")));
+    }
+    if columns.is_empty() {
+        return Err(anyhow::anyhow!("No columns selected"));
+    }
+    Ok(columns)
+}
+
+fn column(name: &str, candles: &[TradeCandle]) -> PolarsResult<Column> {
+    let f64s = |f: fn(&TradeCandle) -> f64| Series::new(name.into(), candles.iter().map(f).collect::<Vec<_>>());
+    let prices = |f: fn(&TradeCandle) -> Option<f64>| Series::new(name.into(), candles.iter().map(f).collect::<Vec<_>>());
+    let i32s = |f: fn(&TradeCandle) -> i32| Series::new(name.into(), candles.iter().map(f).collect::<Vec<_>>());
+    let strings = |f: fn(&TradeCandle) -> &str| Series::new(name.into(), candles.iter().map(f).collect::<Vec<_>>());
+    let series = match name {
+        "timestamp" => Series::new(name.into(), candles.iter().map(|c| c.timestamp.timestamp_millis()).collect::<Vec<_>>())
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, Some(TimeZone::UTC)))?,
+        "exchange" => strings(|c| c.exchange.as_str()),
+        "market_type" => strings(|c| c.market_type.as_str()),
+        "symbol" => strings(|c| c.symbol.as_str()),
+        "period_seconds" => i32s(|c| c.period_seconds),
+        "open" => prices(|c| c.open),
+        "high" => prices(|c| c.high),
+        "low" => prices(|c| c.low),
+        "close" => prices(|c| c.close),
+        "vwap" => prices(|c| c.vwap()),
+        "ask_price" => prices(|c| c.ask_price),
+        "ask_volume" => f64s(|c| c.ask_volume),
+        "ask_notional" => f64s(|c| c.ask_notional),
+        "ask_count" => i32s(|c| c.ask_count),
+        "bid_price" => prices(|c| c.bid_price),
+        "bid_volume" => f64s(|c| c.bid_volume),
+        "bid_notional" => f64s(|c| c.bid_notional),
+        "bid_count" => i32s(|c| c.bid_count),
+        "delta" => f64s(|c| c.delta()),
+        "cvd" => f64s(|c| c.cvd),
+        "realized_vol" => prices(|c| c.realized_vol),
+        "direction_changes" => i32s(|c| c.direction_changes),
+        "revision" => Series::new(name.into(), candles.iter().map(|c| c.revision).collect::<Vec<_>>()),
+        "warmup" => Series::new(name.into(), candles.iter().map(|c| c.warmup).collect::<Vec<_>>()),
+        _ => polars_bail!(ColumnNotFound: "{}", name),
+    };
+    Ok(series.into())
+}
+
+/// ローソク足を 1 行 1 本の DataFrame にする
+pub fn to_dataframe(candles: &[TradeCandle], columns: &[String]) -> anyhow::Result<DataFrame> {
+    let columns = columns.iter().map(|name| column(name, candles)).collect::<PolarsResult<Vec<_>>>()?;
+    Ok(DataFrame::new(columns)?)
+}
+
+/// DataFrame を Parquet / CSV で書き出す
+pub fn write_frame<W: Write>(writer: W, df: &mut DataFrame, format: FrameFormat) -> anyhow::Result<()> {
+    match format {
+        FrameFormat::Parquet => {
+            ParquetWriter::new(writer).finish(df)?;
+        }
+        FrameFormat::Csv => CsvWriter::new(writer).include_header(true).finish(df)?,
+    }
+    Ok(())
+}
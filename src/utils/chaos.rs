@@ -0,0 +1,125 @@
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
+
+/// 接続層に障害を注入するための設定 (feature = "chaos" のみ)
+/// 書式: "disconnect=0.001,delay=0.01,max_delay_ms=500,malform=0.01,seed=42"
+#[derive(Debug, Clone, Default)]
+pub struct ChaosConfig {
+    pub disconnect_rate: f64,
+    pub delay_rate: f64,
+    pub max_delay_ms: u64,
+    pub malform_rate: f64,
+    pub seed: Option<u64>,
+}
+
+impl ChaosConfig {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut config = Self {
+            max_delay_ms: 1000,
+            ..Default::default()
+        };
+
+        for entry in spec.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid chaos entry: {}. Use <key>=<value>", entry))?;
+            match key.trim() {
+                "disconnect" => config.disconnect_rate = Self::parse_rate(value)?,
+                "delay" => config.delay_rate = Self::parse_rate(value)?,
+                "malform" => config.malform_rate = Self::parse_rate(value)?,
+                "max_delay_ms" => config.max_delay_ms = value.trim().parse()?,
+                "seed" => config.seed = Some(value.trim().parse()?),
+                k => return Err(anyhow::anyhow!("Unknown chaos key: {}", k)),
+            }
+        }
+
+        Ok(config)
+    }
+
+    fn parse_rate(value: &str) -> Result<f64> {
+        let rate: f64 = value.trim().parse()?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(anyhow::anyhow!("Chaos rate must be between 0 and 1: {}", rate));
+        }
+        Ok(rate)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChaosAction {
+    Pass,
+    Delay(Duration),
+    Malform,
+    Disconnect,
+}
+
+pub struct ChaosInjector {
+    config: ChaosConfig,
+    rng: StdRng,
+}
+
+impl ChaosInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self { config, rng }
+    }
+
+    /// 次のメッセージに対する障害を決める
+    pub fn next_action(&mut self) -> ChaosAction {
+        let roll: f64 = self.rng.gen();
+        let mut threshold = self.config.disconnect_rate;
+        if roll < threshold {
+            return ChaosAction::Disconnect;
+        }
+        threshold += self.config.malform_rate;
+        if roll < threshold {
+            return ChaosAction::Malform;
+        }
+        threshold += self.config.delay_rate;
+        if roll < threshold {
+            let delay_ms = self.rng.gen_range(0..=self.config.max_delay_ms);
+            return ChaosAction::Delay(Duration::from_millis(delay_ms));
+        }
+        ChaosAction::Pass
+    }
+
+    /// 受信メッセージに障害を注入する. Err は切断として扱う
+    pub async fn apply(&mut self, msg: Message) -> Result<Message> {
+        match self.next_action() {
+            ChaosAction::Pass => Ok(msg),
+            ChaosAction::Delay(delay) => {
+                tracing::warn!("[CHAOS] Delaying frame by {:?}", delay);
+                tokio::time::sleep(delay).await;
+                Ok(msg)
+            }
+            ChaosAction::Malform => {
+                tracing::warn!("[CHAOS] Malforming frame");
+                Ok(Self::malform(msg))
+            }
+            ChaosAction::Disconnect => {
+                tracing::warn!("[CHAOS] Injecting disconnect");
+                Err(anyhow::anyhow!("Chaos: injected disconnect"))
+            }
+        }
+    }
+
+    /// JSON として解釈できないようにフレームを壊す
+    pub fn malform(msg: Message) -> Message {
+        match msg {
+            Message::Text(text) => {
+                let cut = text.char_indices().nth(text.chars().count() / 2).map_or(0, |(i, _)| i);
+                Message::Text(format!("{}\u{0}{{", &text[..cut]))
+            }
+            Message::Binary(mut data) => {
+                data.truncate(data.len() / 2);
+                Message::Binary(data)
+            }
+            other => other,
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::models::{market_event::MarketEvent, trade::{Side, Trade}, trade_candle::TradeCandle};
+use super::storage_sink::StorageSink;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+pub const DEFAULT_BATCH_SIZE: usize = 10_000;
+pub const DEFAULT_FLUSH_SECONDS: u64 = 5;
+/// 書き込みを待たせておけるイベント数 (これを超えるとパイプラインが待つ)
+const QUEUE_CAPACITY: usize = 100_000;
+/// INSERT に失敗したときの再試行の回数 (1 秒から倍々に待つ. 尽きたらその分を捨てる)
+const MAX_RETRIES: u32 = 5;
+
+/// 足のテーブル (同じ足の revision は ReplacingMergeTree で最新だけが残る. 読むときは FINAL か argMax(.., revision))
+pub fn candles_table_sql(database: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {}.candles (
+    timestamp DateTime64(3, 'UTC'),
+    exchange LowCardinality(String),
+    market_type LowCardinality(String),
+    symbol LowCardinality(String),
+    period_seconds UInt32,
+    open Nullable(Float64),
+    high Nullable(Float64),
+    low Nullable(Float64),
+    close Nullable(Float64),
+    ask_price Nullable(Float64),
+    ask_volume Float64,
+    ask_notional Float64,
+    ask_count UInt32,
+    bid_price Nullable(Float64),
+    bid_volume Float64,
+    bid_notional Float64,
+    bid_count UInt32,
+    cvd Float64,
+    realized_vol Nullable(Float64),
+    direction_changes UInt32,
+    warmup Bool,
+    revision UInt32
+) ENGINE = ReplacingMergeTree(revision)
+PARTITION BY toYYYYMM(timestamp)
+ORDER BY (exchange, market_type, symbol, period_seconds, timestamp)",
+        database
+    )
+}
+
+/// 約定のテーブル (日ごとのパーティション. 古い日は ALTER TABLE .. DROP PARTITION で消せる)
+pub fn trades_table_sql(database: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {}.trades (
+    timestamp DateTime64(3, 'UTC'),
+    received_at DateTime64(3, 'UTC'),
+    exchange LowCardinality(String),
+    market_type LowCardinality(String),
+    symbol LowCardinality(String),
+    trade_id String,
+    price Float64,
+    quantity Float64,
+    side Enum8('buy' = 1, 'sell' = 2)
+) ENGINE = MergeTree
+PARTITION BY toYYYYMMDD(timestamp)
+ORDER BY (exchange, market_type, symbol, timestamp)",
+        database
+    )
+}
+
+/// DateTime64(3) の既定の書式
+fn datetime(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
+
+/// JSONEachRow の 1 行
+pub fn candle_row(candle: &TradeCandle) -> serde_json::Value {
+    json!({
+        "timestamp": datetime(candle.timestamp),
+        "exchange": candle.exchange,
+        "market_type": candle.market_type.as_str(),
+        "symbol": candle.symbol,
+        "period_seconds": candle.period_seconds,
+        "open": candle.open,
+        "high": candle.high,
+        "low": candle.low,
+        "close": candle.close,
+        "ask_price": candle.ask_price,
+        "ask_volume": candle.ask_volume,
+        "ask_notional": candle.ask_notional,
+        "ask_count": candle.ask_count,
+        "bid_price": candle.bid_price,
+        "bid_volume": candle.bid_volume,
+        "bid_notional": candle.bid_notional,
+        "bid_count": candle.bid_count,
+        "cvd": candle.cvd,
+        "realized_vol": candle.realized_vol,
+        "direction_changes": candle.direction_changes,
+        "warmup": candle.warmup,
+        "revision": candle.revision,
+    })
+}
+
+pub fn trade_row(trade: &Trade) -> serde_json::Value {
+    json!({
+        "timestamp": datetime(trade.timestamp),
+        "received_at": datetime(trade.received_at),
+        "exchange": trade.exchange,
+        "market_type": trade.market_type.as_str(),
+        "symbol": trade.symbol,
+        "trade_id": trade.trade_id,
+        "price": trade.price,
+        "quantity": trade.quantity,
+        "side": match trade.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        },
+    })
+}
+
+/// 行をためて batch_size 行ごと (か flush_interval ごと) にまとめて INSERT する
+pub struct RowBuffer {
+    table: &'static str,
+    rows: String,  // 改行区切りの JSON
+    len: usize,
+}
+
+impl RowBuffer {
+    pub fn new(table: &'static str) -> Self {
+        Self { table, rows: String::new(), len: 0 }
+    }
+
+    pub fn push(&mut self, row: &serde_json::Value) {
+        self.rows.push_str(&row.to_string());
+        self.rows.push('\n');
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// ためた行を取り出して空にする
+    pub fn take(&mut self) -> String {
+        self.len = 0;
+        std::mem::take(&mut self.rows)
+    }
+}
+
+/// ClickHouse の HTTP インターフェース (e.g. http://localhost:8123)
+#[derive(Clone)]
+struct ClickHouseClient {
+    http: reqwest::Client,
+    url: String,
+    user: Option<String>,
+    password: Option<String>,
+}
+
+impl ClickHouseClient {
+    async fn execute(&self, query: &str, body: Option<String>) -> anyhow::Result<()> {
+        let mut request = match body {
+            // データは本文, クエリは URL で送る
+            Some(body) => self.http.post(&self.url).query(&[("query", query)]).body(body),
+            None => self.http.post(&self.url).body(query.to_string()),
+        };
+        if let Some(user) = &self.user {
+            request = request.header("X-ClickHouse-User", user);
+        }
+        if let Some(password) = &self.password {
+            request = request.header("X-ClickHouse-Key", password);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            return Err(anyhow::anyhow!("ClickHouse returned {}: {}", status, response.text().await.unwrap_or_default().trim()));
+        }
+        Ok(())
+    }
+
+    /// 失敗したら 1 秒から倍々に待って再試行し, 尽きたら捨てる
+    async fn insert(&self, database: &str, buffer: &mut RowBuffer) {
+        if buffer.is_empty() {
+            return;
+        }
+        let rows = buffer.len();
+        let body = buffer.take();
+        let query = format!("INSERT INTO {}.{} FORMAT JSONEachRow", database, buffer.table);
+        let mut delay = Duration::from_secs(1);
+        for attempt in 0..=MAX_RETRIES {
+            match self.execute(&query, Some(body.clone())).await {
+                Ok(()) => return,
+                Err(e) if attempt < MAX_RETRIES => {
+                    warn!("Failed to insert {} rows into ClickHouse {}: {}; retrying in {:?}", rows, buffer.table, e, delay);
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+                Err(e) => error!("Dropped {} rows for ClickHouse {}: {}", rows, buffer.table, e),
+            }
+        }
+    }
+}
+
+/// ClickHouse への書き込みの設定
+#[derive(Debug, Clone)]
+pub struct ClickHouseConfig {
+    pub url: String,
+    pub database: String,
+    pub trades: bool,  // 約定も書く
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+}
+
+/// 足 (と約定) を ClickHouse にまとめて書くシンク
+#[derive(Clone)]
+pub struct ClickHouseSink {
+    trades: bool,
+    client: ClickHouseClient,
+    sender: mpsc::Sender<MarketEvent>,
+}
+
+impl ClickHouseSink {
+    /// テーブルを作成し (なければ), 書き込みのタスクを起動する. 接続できなければエラー
+    /// 認証は CLICKHOUSE_USER / CLICKHOUSE_PASSWORD
+    pub async fn connect(config: ClickHouseConfig) -> anyhow::Result<Self> {
+        let client = ClickHouseClient {
+            http: reqwest::Client::new(),
+            url: config.url.clone(),
+            user: std::env::var("CLICKHOUSE_USER").ok(),
+            password: std::env::var("CLICKHOUSE_PASSWORD").ok(),
+        };
+        client.execute(&format!("CREATE DATABASE IF NOT EXISTS {}", config.database), None).await?;
+        client.execute(&candles_table_sql(&config.database), None).await?;
+        if config.trades {
+            client.execute(&trades_table_sql(&config.database), None).await?;
+        }
+        info!("Writing candles{} to ClickHouse {} database {} in batches of {} rows (or every {:?})",
+              if config.trades { " and trades" } else { "" }, config.url, config.database, config.batch_size, config.flush_interval);
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let trades = config.trades;
+        tokio::spawn(write(client.clone(), config, receiver));
+        Ok(Self { trades, client, sender })
+    }
+
+    async fn send(&self, event: MarketEvent) -> anyhow::Result<()> {
+        self.sender.send(event).await.map_err(|_| anyhow::anyhow!("ClickHouse writer stopped"))
+    }
+}
+
+#[async_trait]
+impl StorageSink for ClickHouseSink {
+    fn name(&self) -> &'static str {
+        "clickhouse"
+    }
+
+    /// --clickhouse-trades のときのみ
+    fn stores_trades(&self) -> bool {
+        self.trades
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        self.send(MarketEvent::Candle(candle.clone())).await
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.send(MarketEvent::Trade(trade.clone())).await
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("ClickHouse writer stopped"));
+        }
+        self.client.execute("SELECT 1", None).await
+    }
+}
+
+async fn write(client: ClickHouseClient, config: ClickHouseConfig, mut receiver: mpsc::Receiver<MarketEvent>) {
+    let mut candles = RowBuffer::new("candles");
+    let mut trades = RowBuffer::new("trades");
+    let mut flush = tokio::time::interval(config.flush_interval);
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some(MarketEvent::Candle(candle)) => {
+                    candles.push(&candle_row(&candle));
+                    if candles.len() >= config.batch_size {
+                        client.insert(&config.database, &mut candles).await;
+                    }
+                }
+                Some(MarketEvent::Trade(trade)) => {
+                    trades.push(&trade_row(&trade));
+                    if trades.len() >= config.batch_size {
+                        client.insert(&config.database, &mut trades).await;
+                    }
+                }
+                Some(_) => {}
+                None => break,
+            },
+            _ = flush.tick() => {
+                client.insert(&config.database, &mut candles).await;
+                client.insert(&config.database, &mut trades).await;
+            }
+        }
+    }
+    // 残りを書いてから終了する
+    client.insert(&config.database, &mut candles).await;
+    client.insert(&config.database, &mut trades).await;
+}
@@ -0,0 +1,140 @@
+use super::correlation_matrix::CorrelationMatrix;
+
+/// クラスタ間の距離の取り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Linkage {
+    Single,    // 最も近い symbol の組
+    Complete,  // 最も遠い symbol の組
+    Average,   // 全ての symbol の組の平均 (UPGMA)
+}
+
+impl Linkage {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "single" => Ok(Self::Single),
+            "complete" => Ok(Self::Complete),
+            "average" => Ok(Self::Average),
+            s => Err(anyhow::anyhow!("Invalid linkage: {}. Use single, complete or average", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Single => "single",
+            Self::Complete => "complete",
+            Self::Average => "average",
+        }
+    }
+
+    fn distance(&self, a: &[usize], b: &[usize], distances: &[Vec<f64>]) -> f64 {
+        let pairs = a.iter().flat_map(|&i| b.iter().map(move |&j| distances[i][j]));
+        match self {
+            Self::Single => pairs.fold(f64::INFINITY, f64::min),
+            Self::Complete => pairs.fold(f64::NEG_INFINITY, f64::max),
+            Self::Average => pairs.sum::<f64>() / (a.len() * b.len()) as f64,
+        }
+    }
+}
+
+/// 2 つのクラスタを 1 つにした記録 (ノードの番号は symbol が 0..n, merge が n + i. scipy の linkage と同じ)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Merge {
+    pub left: usize,
+    pub right: usize,
+    pub distance: f64,
+    pub size: usize,  // merge したクラスタの symbol 数
+}
+
+/// 凝集型の階層クラスタリングの結果
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dendrogram {
+    pub symbols: Vec<i32>,  // 葉の symbol (昇順)
+    pub merges: Vec<Merge>,  // 距離の小さい順
+}
+
+impl Dendrogram {
+    /// 樹形図の葉の並び (merge した 2 つのクラスタを左から順に並べる. 近い symbol が隣り合う)
+    pub fn order(&self) -> Vec<i32> {
+        let n = self.symbols.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let mut order = Vec::with_capacity(n);
+        // 最後の merge が根 (symbol が 1 つなら葉)
+        let mut stack = vec![n + self.merges.len() - 1];
+        while let Some(node) = stack.pop() {
+            if node < n {
+                order.push(self.symbols[node]);
+            } else {
+                let merge = &self.merges[node - n];
+                stack.push(merge.right);
+                stack.push(merge.left);
+            }
+        }
+        order
+    }
+
+    /// 距離 threshold 以下の merge だけを行ったクラスタ (樹形図の並びの順, クラスタの中も同じ順)
+    pub fn cut(&self, threshold: f64) -> Vec<Vec<i32>> {
+        let n = self.symbols.len();
+        // ノードごとの根 (threshold 以下の merge で 1 つになったクラスタの番号)
+        let mut parent: Vec<usize> = (0..n + self.merges.len()).collect();
+        for (index, merge) in self.merges.iter().enumerate() {
+            if merge.distance <= threshold {
+                parent[merge.left] = n + index;
+                parent[merge.right] = n + index;
+            }
+        }
+        let find = |mut node: usize| {
+            while parent[node] != node {
+                node = parent[node];
+            }
+            node
+        };
+        let mut clusters: Vec<(usize, Vec<i32>)> = Vec::new();
+        for symbol_id in self.order() {
+            let leaf = self.symbols.binary_search(&symbol_id).unwrap_or_default();
+            let cluster = find(leaf);
+            match clusters.iter_mut().find(|(root, _)| *root == cluster) {
+                Some((_, members)) => members.push(symbol_id),
+                None => clusters.push((cluster, vec![symbol_id])),
+            }
+        }
+        clusters.into_iter().map(|(_, members)| members).collect()
+    }
+}
+
+/// 距離 1 - 相関で相関行列の symbol を階層クラスタリングする (symbol がなければ None)
+/// 相関が計算できないペアは相関 0 (距離 1) として扱う
+pub fn cluster(matrix: &CorrelationMatrix, linkage: Linkage) -> Option<Dendrogram> {
+    let n = matrix.symbols.len();
+    if n == 0 {
+        return None;
+    }
+    let distances: Vec<Vec<f64>> = matrix
+        .values
+        .iter()
+        .map(|row| row.iter().map(|value| 1.0 - value.unwrap_or(0.0)).collect())
+        .collect();
+    // (ノードの番号, 葉の番号)
+    let mut active: Vec<(usize, Vec<usize>)> = (0..n).map(|i| (i, vec![i])).collect();
+    let mut merges = Vec::with_capacity(n.saturating_sub(1));
+    while active.len() > 1 {
+        let mut best = (0, 1, f64::INFINITY);
+        for (a, (_, left)) in active.iter().enumerate() {
+            for (b, (_, right)) in active.iter().enumerate().skip(a + 1) {
+                let distance = linkage.distance(left, right, &distances);
+                if distance < best.2 {
+                    best = (a, b, distance);
+                }
+            }
+        }
+        let (a, b, distance) = best;
+        let (right, mut right_members) = active.remove(b);
+        let (left, mut members) = active.remove(a);
+        members.append(&mut right_members);
+        merges.push(Merge { left, right, distance, size: members.len() });
+        active.push((n + merges.len() - 1, members));
+    }
+    Some(Dendrogram { symbols: matrix.symbols.clone(), merges })
+}
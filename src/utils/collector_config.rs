@@ -0,0 +1,222 @@
+use crate::db::{DEFAULT_BATCH_LATENCY_MS, DEFAULT_BATCH_SIZE};
+use crate::models::market_event::MarketEvent;
+use crate::models::market_type::MarketType;
+use super::dedup::DEFAULT_DEDUP_WINDOW;
+use super::retention::RetentionConfig;
+use super::timeframe;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// collector で扱える取引所と市場
+pub const EXCHANGES: &[(&str, &[&str])] = &[
+    ("bybit", &["spot", "linear", "inverse"]),
+    ("binance", &["spot", "linear", "inverse"]),
+    ("hyperliquid", &["spot", "linear"]),
+    ("bitstamp", &["spot"]),
+    ("phemex", &["spot", "linear", "inverse"]),
+    ("backpack", &["spot", "linear"]),
+];
+
+/// 1 プロセスで複数の取引所・市場を集める collector の設定 (TOML / YAML)
+/// 全ての feed が 1 つのローソク足の集計と DB 書き込みを共有する
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollectorConfig {
+    pub database_url: Option<String>,  // なければ MONGODB_URL
+    pub namespace: Option<String>,     // なければ MONGODB_NAMESPACE
+    #[serde(default)]
+    pub update: bool,                  // false なら表示のみ
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,             // ローソク足をまとめて書き込む件数
+    #[serde(default = "default_batch_ms")]
+    pub batch_ms: u64,                 // 件数に達しなくてもこの間隔で書き込む
+    #[serde(default = "default_timeframes")]
+    pub timeframes: String,            // feed で指定がなければこれを使う (e.g. "1,1m")
+    #[serde(default = "default_session")]
+    pub session: String,
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: usize,
+    #[serde(default = "default_raw_freq")]
+    pub raw_freq: u32,
+    #[serde(default = "default_watchdog_secs")]
+    pub watchdog_secs: u64,
+    pub proxy: Option<String>,         // なければ PROXY_URL
+    pub write_ahead_file: Option<String>,
+    pub health_addr: Option<String>,   // /healthz と /status (e.g. "0.0.0.0:8080")
+    pub broadcast_addr: Option<String>,  // 約定・足の WebSocket 配信 (e.g. "0.0.0.0:9001")
+    pub redis_url: Option<String>,     // 足を Redis Streams, 約定を pub/sub にも送る (--redis-url が優先)
+    pub nats_url: Option<String>,      // 約定・足を NATS JetStream にも送る (--nats-url が優先)
+    pub clickhouse_url: Option<String>,  // 足 (と約定) を ClickHouse にも書く (--clickhouse-url が優先)
+    pub questdb_addr: Option<String>,  // 約定・足を QuestDB (ILP) にも書く (--questdb-addr が優先)
+    pub archive_dir: Option<String>,   // 足 (と約定) を日付ごとの Parquet にも書く (--archive-dir が優先)
+    pub sqlite_dir: Option<String>,    // 足 (と約定) を月ごとの SQLite にも書く (--sqlite-dir が優先)
+    pub retention: Option<RetentionConfig>,  // 時間枠ごとの足の保持期間 (--update のときのみ)
+    pub feeds: Vec<FeedConfig>,
+}
+
+/// 1 つの取引所・市場の購読
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedConfig {
+    pub exchange: String,
+    pub market_type: String,
+    pub symbols: Vec<String>,
+    pub timeframes: Option<String>,
+    #[serde(default)]
+    pub symbols_per_connection: usize,  // 0 なら 1 接続
+    #[serde(default)]
+    pub testnet: bool,                  // bybit / binance のみ
+    pub ws_host: Option<String>,
+    pub rest_host: Option<String>,
+}
+
+fn default_timeframes() -> String {
+    "1m".to_string()
+}
+
+fn default_batch_size() -> usize {
+    DEFAULT_BATCH_SIZE
+}
+
+fn default_batch_ms() -> u64 {
+    DEFAULT_BATCH_LATENCY_MS
+}
+
+fn default_session() -> String {
+    "utc".to_string()
+}
+
+fn default_dedup_window() -> usize {
+    DEFAULT_DEDUP_WINDOW
+}
+
+fn default_raw_freq() -> u32 {
+    100
+}
+
+fn default_watchdog_secs() -> u64 {
+    60
+}
+
+impl CollectorConfig {
+    /// 拡張子で形式を決める (.toml / .yaml / .yml)
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let config = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text)?,
+            Some("yaml" | "yml") => Self::from_yaml(&text)?,
+            _ => return Err(anyhow::anyhow!("Unknown config format: {}. Use .toml, .yaml or .yml", path.display())),
+        };
+        Ok(config)
+    }
+
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        let config: Self = toml::from_str(text).map_err(|e| anyhow::anyhow!("Invalid collector config: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_yaml(text: &str) -> anyhow::Result<Self> {
+        let config: Self = serde_yaml::from_str(text).map_err(|e| anyhow::anyhow!("Invalid collector config: {}", e))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.feeds.is_empty() {
+            return Err(anyhow::anyhow!("No feeds in collector config"));
+        }
+        timeframe::parse_list(&self.timeframes)?;
+        timeframe::parse_session_offset(&self.session)?;
+        if let Some(retention) = &self.retention {
+            retention.validate()?;
+        }
+        let mut seen = BTreeSet::new();
+        for feed in &self.feeds {
+            let market_type = feed.market_type()?;
+            let markets = EXCHANGES
+                .iter()
+                .find(|(exchange, _)| *exchange == feed.exchange)
+                .map(|(_, markets)| *markets)
+                .ok_or_else(|| anyhow::anyhow!("Unknown exchange: {}. Use one of {:?}", feed.exchange,
+                    EXCHANGES.iter().map(|(exchange, _)| *exchange).collect::<Vec<_>>()))?;
+            if !markets.contains(&market_type.as_str()) {
+                return Err(anyhow::anyhow!("{} has no {} market", feed.exchange, market_type));
+            }
+            if feed.symbols.is_empty() {
+                return Err(anyhow::anyhow!("No symbols for {} {}", feed.exchange, market_type));
+            }
+            if feed.testnet && !matches!(feed.exchange.as_str(), "bybit" | "binance") {
+                return Err(anyhow::anyhow!("testnet is only available for bybit and binance"));
+            }
+            // 同じ足が 2 つの feed から出力されないように
+            if !seen.insert((feed.exchange.clone(), market_type.as_str())) {
+                return Err(anyhow::anyhow!("{} {} appears more than once; list all its symbols in one feed", feed.exchange, market_type));
+            }
+            if let Some(spec) = &feed.timeframes {
+                timeframe::parse_list(spec)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 共有するローソク足の集計に渡す時間枠 (全ての feed の時間枠の和)
+    pub fn all_timeframes(&self) -> anyhow::Result<Vec<u32>> {
+        let mut timeframes = BTreeSet::new();
+        for feed in &self.feeds {
+            timeframes.extend(feed.timeframes(&self.timeframes)?);
+        }
+        Ok(timeframes.into_iter().collect())
+    }
+}
+
+impl FeedConfig {
+    pub fn market_type(&self) -> anyhow::Result<MarketType> {
+        MarketType::parse(&self.market_type)
+    }
+
+    /// この feed の時間枠 (指定がなければ default)
+    pub fn timeframes(&self, default: &str) -> anyhow::Result<Vec<u32>> {
+        timeframe::parse_list(self.timeframes.as_deref().unwrap_or(default))
+    }
+}
+
+/// 共有の集計は全ての feed の時間枠の足を作るので, feed が指定していない時間枠の足を捨てる
+pub struct CandleTimeframeFilter {
+    timeframes: HashMap<(String, MarketType), Vec<u32>>,
+}
+
+impl CandleTimeframeFilter {
+    pub fn new(config: &CollectorConfig) -> anyhow::Result<Self> {
+        let mut timeframes = HashMap::new();
+        for feed in &config.feeds {
+            timeframes.insert((feed.exchange.clone(), feed.market_type()?), feed.timeframes(&config.timeframes)?);
+        }
+        Ok(Self { timeframes })
+    }
+
+    /// 後段に流すか (ローソク足以外は全て流す)
+    pub fn keeps(&self, event: &MarketEvent) -> bool {
+        let MarketEvent::Candle(candle) = event else {
+            return true;
+        };
+        self.timeframes
+            .get(&(candle.exchange.clone(), candle.market_type.clone()))
+            .is_none_or(|timeframes| timeframes.contains(&(candle.period_seconds as u32)))
+    }
+
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            if !self.keeps(&event) {
+                continue;
+            }
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::models::{market_type::MarketType, ExchangeClient};
+use super::keepalive::WatchdogTimeout;
+use super::supervisor::{catch_panic, Supervisor};
+use tokio::task::JoinSet;
+use tracing::info;
+
+/// symbol を 1 接続あたり max_per_connection 以下に均等に分ける (順序は保つ, 0 なら 1 接続)
+/// 例: 300 symbol を 200 ずつなら 200 + 100 ではなく 150 + 150 にして, 接続ごとの負荷を揃える
+pub fn split_symbols(symbols: &[String], max_per_connection: usize) -> Vec<Vec<String>> {
+    if symbols.is_empty() {
+        return Vec::new();
+    }
+    if max_per_connection == 0 {
+        return vec![symbols.to_vec()];
+    }
+    let connections = symbols.len().div_ceil(max_per_connection);
+    let base = symbols.len() / connections;
+    let extra = symbols.len() % connections;
+    let mut shards = Vec::with_capacity(connections);
+    let mut start = 0;
+    for i in 0..connections {
+        let size = base + usize::from(i < extra);
+        shards.push(symbols[start..start + size].to_vec());
+        start += size;
+    }
+    shards
+}
+
+/// 1 つの接続を維持する
+/// 無受信による切断はすぐに同じ symbol で再接続し, それ以外の切断・エラー・panic は Supervisor の間隔と上限で再接続する
+pub async fn run_connection<C: ExchangeClient>(client: &mut C, market_type: MarketType, symbols: Vec<String>) -> anyhow::Result<()> {
+    let mut supervisor = Supervisor::new(format!("connection ({} symbols from {})", symbols.len(), symbols.first().map_or("", String::as_str)));
+    loop {
+        let result = catch_panic(async {
+            client.connect(market_type.clone()).await?;
+            client.subscribe_trades(symbols.clone()).await
+        })
+        .await;
+        let cause = match result {
+            Ok(Err(e)) if e.is::<WatchdogTimeout>() => {
+                tracing::warn!("{}; reconnecting", e);
+                continue;
+            }
+            Ok(Ok(())) => "stream closed".to_string(),
+            Ok(Err(e)) => e.to_string(),
+            Err(message) => format!("panicked: {}", message),
+        };
+        if !supervisor.restart(&cause).await {
+            return Err(anyhow::anyhow!("{} stopped: {}", supervisor.name(), cause));
+        }
+    }
+}
+
+/// symbol を分けた複数の接続を並行して維持する (全ての接続が同じイベントチャネルに送る)
+/// 各接続は独立に再接続し, いずれかの接続が再接続の上限を超えて終了したら残りも止めてその結果を返す
+pub async fn run_sharded<C: ExchangeClient + 'static>(shards: Vec<(C, Vec<String>)>, market_type: MarketType) -> anyhow::Result<()> {
+    let total = shards.len();
+    if total > 1 {
+        info!("Opening {} connections ({} symbols)", total, shards.iter().map(|(_, symbols)| symbols.len()).sum::<usize>());
+    }
+    let mut connections = JoinSet::new();
+    for (i, (mut client, symbols)) in shards.into_iter().enumerate() {
+        let market_type = market_type.clone();
+        if total > 1 {
+            info!("Connection {}/{}: {} symbols ({} .. {})", i + 1, total, symbols.len(),
+                  symbols.first().map_or("", String::as_str), symbols.last().map_or("", String::as_str));
+        }
+        connections.spawn(async move {
+            let result = run_connection(&mut client, market_type, symbols).await;
+            (i, result)
+        });
+    }
+    let result = match connections.join_next().await {
+        Some(Ok((i, result))) => {
+            if total > 1 {
+                if let Err(e) = &result {
+                    tracing::error!("Connection {}/{} stopped: {}; closing the others", i + 1, total, e);
+                }
+            }
+            result
+        }
+        Some(Err(e)) => Err(anyhow::anyhow!("Connection task failed: {}", e)),
+        None => Ok(()),
+    };
+    connections.shutdown().await;
+    result
+}
+
+/// new_client で作った接続で symbols を max_per_connection ずつに分けて購読する
+/// max_per_connection が 0 ならクライアントの上限 (Binance のストリーム数など) ごとに, 上限もなければ 1 接続で購読する
+pub async fn run_clients<C: ExchangeClient + 'static>(new_client: impl Fn() -> C, symbols: &[String], max_per_connection: usize, market_type: MarketType) -> anyhow::Result<()> {
+    let max_per_connection = match max_per_connection {
+        0 => new_client().max_symbols_per_connection(&market_type),
+        n => n,
+    };
+    let shards = split_symbols(symbols, max_per_connection)
+        .into_iter()
+        .map(|symbols| (new_client(), symbols))
+        .collect();
+    run_sharded(shards, market_type).await
+}
@@ -0,0 +1,173 @@
+use super::rolling_correlation::PairCorrelation;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// 相関のアラートの条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CorrelationCondition {
+    Below(f64),  // <X: 相関が X 未満
+    Above(f64),  // >X: 相関が X より大きい
+}
+
+impl CorrelationCondition {
+    pub fn holds(&self, correlation: f64) -> bool {
+        match *self {
+            Self::Below(level) => correlation < level,
+            Self::Above(level) => correlation > level,
+        }
+    }
+}
+
+impl std::fmt::Display for CorrelationCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Below(level) => write!(f, "below {}", level),
+            Self::Above(level) => write!(f, "above {}", level),
+        }
+    }
+}
+
+/// ペアの相関が条件を intervals 回続けて満たしたら発火する
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationRule {
+    pub symbol_a: i32,
+    pub symbol_b: i32,
+    pub condition: CorrelationCondition,
+    pub intervals: usize,
+    pub window_minutes: Option<u32>,  // None なら全ての窓
+    pub spec: String,
+}
+
+impl CorrelationRule {
+    /// 書式: "<symbol>/<symbol><X" または ">X", 続けて "x<回数>" (既定 1) と "@<窓の分数>" (既定は全ての窓)
+    /// e.g. "1/6<0.5x3", "bybit:linear:BTCUSDT/bybit:linear:ETHUSDT<0.5x3@30". symbol は resolve で symbol_id にする
+    pub fn parse(spec: &str, resolve: impl Fn(&str) -> anyhow::Result<i32>) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let invalid = || anyhow::anyhow!("Invalid correlation alert: {}. Use <symbol>/<symbol><X or >X, optionally followed by x<intervals> and @<window minutes>", spec);
+        let split = spec.find(['<', '>']).ok_or_else(invalid)?;
+        let (pair, rest) = spec.split_at(split);
+        let (symbol_a, symbol_b) = pair.split_once('/').ok_or_else(invalid)?;
+        let (symbol_a, symbol_b) = (resolve(symbol_a.trim())?, resolve(symbol_b.trim())?);
+        if symbol_a == symbol_b {
+            return Err(invalid());
+        }
+        let (rest, window_minutes) = match rest.split_once('@') {
+            Some((rest, window)) => (rest, Some(window.trim().parse::<u32>().ok().filter(|&w| w > 0).ok_or_else(invalid)?)),
+            None => (rest, None),
+        };
+        let (level, intervals) = match rest[1..].split_once('x') {
+            Some((level, intervals)) => (level, intervals.trim().parse::<usize>().ok().filter(|&n| n > 0).ok_or_else(invalid)?),
+            None => (&rest[1..], 1),
+        };
+        let level: f64 = level.trim().parse().map_err(|_| invalid())?;
+        let condition = if rest.starts_with('<') { CorrelationCondition::Below(level) } else { CorrelationCondition::Above(level) };
+        Ok(Self { symbol_a, symbol_b, condition, intervals, window_minutes, spec: spec.to_string() })
+    }
+
+    fn matches(&self, pair: &PairCorrelation) -> bool {
+        (pair.symbol_a, pair.symbol_b) == (self.symbol_a, self.symbol_b) || (pair.symbol_a, pair.symbol_b) == (self.symbol_b, self.symbol_a)
+    }
+}
+
+/// カンマ区切りのルール (e.g. "1/6<0.5x3,1/9>0.95")
+pub fn parse_rules(spec: &str, resolve: impl Fn(&str) -> anyhow::Result<i32>) -> anyhow::Result<Vec<CorrelationRule>> {
+    let rules = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| CorrelationRule::parse(entry, &resolve))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if rules.is_empty() {
+        return Err(anyhow::anyhow!("Empty correlation alert rules"));
+    }
+    Ok(rules)
+}
+
+/// 発火した (resolved なら条件を満たさなくなった) アラート
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationAlert {
+    pub timestamp: DateTime<Utc>,
+    pub window_minutes: u32,
+    pub symbol_a: i32,
+    pub symbol_b: i32,
+    pub rule: String,
+    pub condition: CorrelationCondition,
+    pub intervals: usize,
+    pub recent: Vec<f64>,  // 直近 intervals 回の相関 (古い順)
+    pub resolved: bool,
+}
+
+impl std::fmt::Display for CorrelationAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let recent: Vec<String> = self.recent.iter().map(|value| format!("{:.4}", value)).collect();
+        let latest = self.recent.last().copied().unwrap_or(f64::NAN);
+        if self.resolved {
+            write!(f, "corr({}, {}) {:.4} is no longer {} ({}m window) @ {}", self.symbol_a, self.symbol_b, latest, self.condition, self.window_minutes, self.timestamp.format("%H:%M:%S"))?;
+        } else {
+            write!(
+                f, "corr({}, {}) {:.4} {} for {} intervals [{}] ({}m window) @ {}",
+                self.symbol_a, self.symbol_b, latest, self.condition, self.intervals, recent.join(", "), self.window_minutes, self.timestamp.format("%H:%M:%S")
+            )?;
+        }
+        write!(f, " | {}", self.rule)
+    }
+}
+
+/// 計算ごとの相関をルールで評価する (ルールと窓ごとに続けて満たした回数を数える)
+/// 発火は満たし始めてから intervals 回目の 1 回だけで, 満たさなくなったら resolved を出して次に備える. 計算できない回は数えない
+#[derive(Debug, Clone, Default)]
+pub struct CorrelationAlerts {
+    rules: Vec<CorrelationRule>,
+    recent: HashMap<(usize, u32), VecDeque<f64>>,  // (rule, 窓) -> 続けて満たした直近の相関
+    active: HashSet<(usize, u32)>,  // 発火して resolved をまだ出していない (rule, 窓)
+}
+
+impl CorrelationAlerts {
+    pub fn new(rules: Vec<CorrelationRule>) -> Self {
+        Self { rules, ..Self::default() }
+    }
+
+    pub fn rules(&self) -> &[CorrelationRule] {
+        &self.rules
+    }
+
+    pub fn evaluate(&mut self, timestamp: DateTime<Utc>, window_minutes: u32, pairs: &[PairCorrelation]) -> Vec<CorrelationAlert> {
+        let mut alerts = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.window_minutes.is_some_and(|window| window != window_minutes) {
+                continue;
+            }
+            let Some(correlation) = pairs.iter().find(|pair| rule.matches(pair)).and_then(|pair| pair.correlation) else {
+                continue;
+            };
+            let key = (index, window_minutes);
+            let alert = |recent: Vec<f64>, resolved| CorrelationAlert {
+                timestamp,
+                window_minutes,
+                symbol_a: rule.symbol_a,
+                symbol_b: rule.symbol_b,
+                rule: rule.spec.clone(),
+                condition: rule.condition,
+                intervals: rule.intervals,
+                recent,
+                resolved,
+            };
+            if !rule.condition.holds(correlation) {
+                self.recent.remove(&key);
+                if self.active.remove(&key) {
+                    alerts.push(alert(vec![correlation], true));
+                }
+                continue;
+            }
+            let recent = self.recent.entry(key).or_default();
+            recent.push_back(correlation);
+            while recent.len() > rule.intervals {
+                recent.pop_front();
+            }
+            if recent.len() == rule.intervals && self.active.insert(key) {
+                alerts.push(alert(recent.iter().copied().collect(), false));
+            }
+        }
+        alerts
+    }
+}
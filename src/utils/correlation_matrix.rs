@@ -0,0 +1,101 @@
+use super::rolling_correlation::PairCorrelation;
+use chrono::{DateTime, Utc};
+use std::io::Write;
+
+/// 相関行列の出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatrixFormat {
+    Table,  // ペアごとの行 (人が読む)
+    Json,   // 1 回の計算を 1 行の JSON (JSON Lines)
+    Csv,    // 見出しの行と symbol ごとの行
+}
+
+impl MatrixFormat {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "table" => Ok(Self::Table),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            s => Err(anyhow::anyhow!("Invalid output format: {}. Use table, json or csv", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Table => "table",
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// ペアごとの相関を並べた対称行列 (symbol は昇順, 対角は 1, 計算できないペアは None)
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorrelationMatrix {
+    pub timestamp: DateTime<Utc>,  // 窓の終わり
+    pub window_minutes: u32,
+    pub symbols: Vec<i32>,
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl CorrelationMatrix {
+    pub fn from_pairs(timestamp: DateTime<Utc>, window_minutes: u32, pairs: &[PairCorrelation]) -> Self {
+        let mut symbols: Vec<i32> = pairs.iter().flat_map(|pair| [pair.symbol_a, pair.symbol_b]).collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+        let mut values: Vec<Vec<Option<f64>>> = (0..symbols.len())
+            .map(|i| (0..symbols.len()).map(|j| (i == j).then_some(1.0)).collect())
+            .collect();
+        for pair in pairs {
+            if let (Ok(a), Ok(b)) = (symbols.binary_search(&pair.symbol_a), symbols.binary_search(&pair.symbol_b)) {
+                if a != b {
+                    values[a][b] = pair.correlation;
+                    values[b][a] = pair.correlation;
+                }
+            }
+        }
+        Self { timestamp, window_minutes, symbols, values }
+    }
+
+    /// symbol_a と symbol_b の相関 (どちらかが行列になければ None)
+    pub fn get(&self, symbol_a: i32, symbol_b: i32) -> Option<f64> {
+        let a = self.symbols.binary_search(&symbol_a).ok()?;
+        let b = self.symbols.binary_search(&symbol_b).ok()?;
+        self.values[a][b]
+    }
+
+    fn timestamp_label(&self) -> String {
+        self.timestamp.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
+    }
+
+    /// {"timestamp", "window", "symbols", "matrix"} (計算できないペアは null)
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": self.timestamp_label(),
+            "window": self.window_minutes,
+            "symbols": self.symbols,
+            "matrix": self.values,
+        })
+    }
+
+    /// timestamp,window,symbol,<symbol_id>... の見出しの行と symbol ごとの行 (計算できないペアは空)
+    pub fn write_csv<W: Write>(&self, writer: &mut W) -> anyhow::Result<()> {
+        let header: Vec<String> = self.symbols.iter().map(|symbol_id| symbol_id.to_string()).collect();
+        writeln!(writer, "timestamp,window,symbol,{}", header.join(","))?;
+        let timestamp = self.timestamp_label();
+        for (symbol_id, row) in self.symbols.iter().zip(&self.values) {
+            let row: Vec<String> = row.iter().map(|value| value.map(|value| value.to_string()).unwrap_or_default()).collect();
+            writeln!(writer, "{},{},{},{}", timestamp, self.window_minutes, symbol_id, row.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// format で書き出す (table は何も書かない)
+    pub fn write<W: Write>(&self, format: MatrixFormat, writer: &mut W) -> anyhow::Result<()> {
+        match format {
+            MatrixFormat::Table => Ok(()),
+            MatrixFormat::Json => Ok(writeln!(writer, "{}", self.to_json())?),
+            MatrixFormat::Csv => self.write_csv(writer),
+        }
+    }
+}
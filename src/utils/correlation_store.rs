@@ -0,0 +1,111 @@
+use super::rank_correlation::CorrelationMethod;
+use super::rolling_correlation::PairCorrelation;
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use mongodb::error::ErrorKind;
+use mongodb::options::{TimeseriesGranularity, TimeseriesOptions};
+use mongodb::{Collection, Database as MongoDatabase, IndexModel};
+
+/// 相関の計算結果を書き込むコレクション
+pub const CORRELATION_COLLECTION: &str = "correlations";
+
+/// 相関の計算の条件 (metadata に入れて, 条件の違う結果を区別する)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CorrelationParams {
+    pub window_minutes: u32,
+    pub interval_seconds: i64,
+    pub returns: Option<usize>,  // 対数収益率の horizon (バケット数). None は価格そのもの
+    pub method: CorrelationMethod,
+}
+
+/// 1 回の計算の相関行列をペアごとのドキュメントにする
+/// unixtime は窓の終わり, metadata は (ym, ペアの symbol_id, 窓の分数, バケットの秒数, 収益率の horizon (価格なら 0), 相関係数の種類), coefficient は計算できなければ null
+pub fn to_documents(timestamp: DateTime<Utc>, params: &CorrelationParams, pairs: &[PairCorrelation]) -> Vec<Document> {
+    let ym = timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+    pairs
+        .iter()
+        .map(|pair| doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(timestamp.timestamp_millis()),
+            "metadata": {
+                "ym": ym,
+                "symbol_a": pair.symbol_a,
+                "symbol_b": pair.symbol_b,
+                "window": params.window_minutes as i32,
+                "interval": params.interval_seconds,
+                "returns": params.returns.unwrap_or(0) as i32,
+                "method": params.method.as_str(),
+            },
+            "coefficient": pair.correlation,
+            "count": pair.count as i64,
+        })
+        .collect()
+}
+
+/// EWMA 相関のドキュメント (metadata の method は ewma, half_life は半減期の秒数)
+pub fn to_ewma_documents(timestamp: DateTime<Utc>, params: &CorrelationParams, half_life_seconds: u32, pairs: &[PairCorrelation]) -> Vec<Document> {
+    let mut docs = to_documents(timestamp, params, pairs);
+    for doc in docs.iter_mut() {
+        if let Ok(metadata) = doc.get_document_mut("metadata") {
+            metadata.insert("method", "ewma");
+            metadata.insert("half_life", half_life_seconds as i64);
+        }
+    }
+    docs
+}
+
+/// correlation の結果を correlations (時系列コレクション) に書き込む
+pub struct CorrelationStore {
+    collection: Collection<Document>,
+    params: CorrelationParams,
+}
+
+impl CorrelationStore {
+    /// コレクションがなければ時系列コレクションとして作り, ペアと時刻の索引を張る
+    pub async fn open(database: &MongoDatabase, collection_name: &str, params: CorrelationParams) -> anyhow::Result<Self> {
+        let index = doc! { "metadata.symbol_a": 1, "metadata.symbol_b": 1, "unixtime": 1 };
+        let collection = open_timeseries(database, collection_name, index).await?;
+        Ok(Self { collection, params })
+    }
+
+    /// window_minutes の窓の相関行列を書き込み, 書き込んだペアの数を返す
+    pub async fn write(&self, timestamp: DateTime<Utc>, window_minutes: u32, pairs: &[PairCorrelation]) -> anyhow::Result<usize> {
+        let params = CorrelationParams { window_minutes, ..self.params };
+        self.insert(to_documents(timestamp, &params, pairs)).await
+    }
+
+    /// EWMA 相関を書き込み, 書き込んだペアの数を返す (window_minutes は読み込んだ窓)
+    pub async fn write_ewma(&self, timestamp: DateTime<Utc>, window_minutes: u32, half_life_seconds: u32, pairs: &[PairCorrelation]) -> anyhow::Result<usize> {
+        let params = CorrelationParams { window_minutes, ..self.params };
+        self.insert(to_ewma_documents(timestamp, &params, half_life_seconds, pairs)).await
+    }
+
+    async fn insert(&self, docs: Vec<Document>) -> anyhow::Result<usize> {
+        if docs.is_empty() {
+            return Ok(0);
+        }
+        let count = docs.len();
+        self.collection.insert_many(docs).ordered(false).await?;
+        Ok(count)
+    }
+}
+
+/// 計算結果の時系列コレクションを開く (なければ作り, index の索引を張る)
+pub async fn open_timeseries(database: &MongoDatabase, collection_name: &str, index: Document) -> anyhow::Result<Collection<Document>> {
+    let exists = !database.list_collection_names().filter(doc! { "name": collection_name }).await?.is_empty();
+    if !exists {
+        let options = TimeseriesOptions::builder()
+            .time_field("unixtime")
+            .meta_field("metadata".to_string())
+            .granularity(TimeseriesGranularity::Seconds)
+            .build();
+        match database.create_collection(collection_name).timeseries(options).await {
+            Ok(()) => tracing::info!("Created time-series collection {}.{}", database.name(), collection_name),
+            // 他のプロセスが先に作った (NamespaceExists)
+            Err(e) if matches!(*e.kind, ErrorKind::Command(ref error) if error.code == 48) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let collection = database.collection::<Document>(collection_name);
+    collection.create_index(IndexModel::builder().keys(index).build()).await?;
+    Ok(collection)
+}
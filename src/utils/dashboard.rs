@@ -0,0 +1,357 @@
+use crate::db::Database;
+use crate::models::market_event::MarketEvent;
+use crate::models::market_type::MarketType;
+use super::ops_events::{OpsEvent, OpsEventKind};
+use chrono::{DateTime, Utc};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// 出来高と約定数を数える直近の秒数
+pub const TRADE_WINDOW_SECONDS: i64 = 60;
+/// 画面を描き直す間隔
+const REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+/// 保持するログの行数
+const LOG_LINES: usize = 200;
+
+#[derive(Debug, Default)]
+struct SymbolStats {
+    last_price: Option<f64>,
+    last_trade: Option<DateTime<Utc>>,  // 最後に受け取った時刻
+    trades: VecDeque<(DateTime<Utc>, f64)>,  // 直近 TRADE_WINDOW_SECONDS 秒の (受け取った時刻, 数量)
+    candles: BTreeMap<i32, (DateTime<Utc>, DateTime<Utc>)>,  // 時間枠 -> (最後の足の時刻, 受け取った時刻)
+}
+
+#[derive(Debug, Default)]
+struct DashboardInner {
+    symbols: BTreeMap<String, SymbolStats>,
+    connects: u64,
+    disconnects: u64,
+    last_disconnect: Option<(DateTime<Utc>, String)>,  // (時刻, 理由)
+    pending_candles: usize,  // MongoCandleSink がまだ書き込んでいない足
+    queued_candles: usize,   // write-ahead キューの足 (DB 障害中)
+    logs: VecDeque<String>,
+    closed: bool,  // 端末を戻した (以降のログは stderr に出す)
+}
+
+impl DashboardInner {
+    fn log(&mut self, line: String) {
+        self.logs.push_back(line);
+        while self.logs.len() > LOG_LINES {
+            self.logs.pop_front();
+        }
+    }
+}
+
+/// 足 1 つの出力の状況
+#[derive(Debug, Clone, PartialEq)]
+pub struct CandleStatus {
+    pub period_seconds: i32,
+    pub timestamp: DateTime<Utc>,
+    pub age_secs: f64,  // 受け取ってからの秒数
+    pub late: bool,     // 時間枠の 2 倍以上次の足が出ていない
+}
+
+/// symbol ごとの表示する値
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolRow {
+    pub symbol: String,
+    pub last_price: Option<f64>,
+    pub volume: f64,  // 直近 TRADE_WINDOW_SECONDS 秒の数量の合計
+    pub trades_per_sec: f64,
+    pub last_trade_age_secs: Option<f64>,
+    pub candles: Vec<CandleStatus>,
+}
+
+/// 1 回の描画の内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct DashboardSnapshot {
+    pub uptime_secs: i64,
+    pub open_connections: u64,
+    pub connects: u64,
+    pub disconnects: u64,
+    pub last_disconnect: Option<(DateTime<Utc>, String)>,
+    pub database: Option<(bool, bool)>,  // (enabled, healthy)
+    pub pending_candles: usize,
+    pub queued_candles: usize,
+    pub symbols: Vec<SymbolRow>,
+    pub logs: Vec<String>,
+}
+
+fn age(now: DateTime<Utc>, time: DateTime<Utc>) -> f64 {
+    (now - time).num_milliseconds().max(0) as f64 / 1000.0
+}
+
+/// --tui の端末のダッシュボード (clone してパイプライン・EventWriter・MongoCandleSink・ログと共有する)
+/// 約定はパイプラインの段 (run) で, 足は EventWriter から, 書き込み待ちは MongoCandleSink から, 接続は ops_events から受け取る
+#[derive(Clone)]
+pub struct Dashboard {
+    label: String,  // 表示用の取引所名 (e.g. BYBIT)
+    started_at: DateTime<Utc>,
+    qualified: bool,  // 行を exchange:market:symbol にする (複数の取引所をまとめる collector)
+    inner: Arc<Mutex<DashboardInner>>,
+    db: Option<Arc<Database>>,
+}
+
+impl Dashboard {
+    pub fn new(exchange: &str) -> Self {
+        Self { label: exchange.to_uppercase(), started_at: Utc::now(), qualified: false, inner: Arc::default(), db: None }
+    }
+
+    /// 行を exchange:market:symbol (e.g. bybit:linear:BTCUSDT) で分ける
+    pub fn qualified(mut self) -> Self {
+        self.qualified = true;
+        self
+    }
+
+    fn key(&self, exchange: &str, market_type: &MarketType, symbol: &str) -> String {
+        if self.qualified {
+            format!("{}:{}:{}", exchange, market_type.as_str(), symbol)
+        } else {
+            symbol.to_string()
+        }
+    }
+
+    /// symbols は約定がまだなくても行を出す (qualified なら exchange:market:symbol)
+    pub fn track(&self, symbols: &[String]) {
+        let mut inner = self.inner.lock().unwrap();
+        for symbol in symbols {
+            inner.symbols.entry(symbol.clone()).or_default();
+        }
+    }
+
+    /// DB の接続状態を表示する
+    pub fn with_database(mut self, db: Arc<Database>) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// 約定と足を記録する (それ以外は無視)
+    pub fn observe(&self, event: &MarketEvent, now: DateTime<Utc>) {
+        let mut inner = self.inner.lock().unwrap();
+        match event {
+            MarketEvent::Trade(trade) => {
+                let stats = inner.symbols.entry(self.key(&trade.exchange, &trade.market_type, &trade.symbol)).or_default();
+                stats.last_price = Some(trade.price);
+                stats.last_trade = Some(now);
+                stats.trades.push_back((now, trade.quantity));
+                while stats.trades.front().is_some_and(|(time, _)| (now - *time).num_seconds() >= TRADE_WINDOW_SECONDS) {
+                    stats.trades.pop_front();
+                }
+            }
+            MarketEvent::Candle(candle) if !candle.warmup => {
+                let stats = inner.symbols.entry(self.key(&candle.exchange, &candle.market_type, &candle.symbol)).or_default();
+                stats.candles.insert(candle.period_seconds, (candle.timestamp, now));
+            }
+            _ => {}
+        }
+    }
+
+    /// 接続・切断を数える (運用イベントの行は tracing からログ欄に入る)
+    pub fn record_ops(&self, event: &OpsEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        match event.kind {
+            OpsEventKind::Connect => inner.connects += 1,
+            OpsEventKind::Disconnect => {
+                inner.disconnects += 1;
+                inner.last_disconnect = Some((event.timestamp, event.detail.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    /// MongoCandleSink の書き込み待ちとキューの足の数
+    pub fn record_writer(&self, pending_candles: usize, queued_candles: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.pending_candles = pending_candles;
+        inner.queued_candles = queued_candles;
+    }
+
+    /// ログの行を足す
+    pub fn log(&self, line: impl Into<String>) {
+        self.inner.lock().unwrap().log(line.into());
+    }
+
+    /// tracing のログを画面の下に出す writer
+    pub fn log_writer(&self) -> DashboardLog {
+        DashboardLog { inner: self.inner.clone() }
+    }
+
+    pub fn snapshot(&self, now: DateTime<Utc>) -> DashboardSnapshot {
+        let inner = self.inner.lock().unwrap();
+        let symbols = inner
+            .symbols
+            .iter()
+            .map(|(symbol, stats)| {
+                let recent: Vec<f64> = stats
+                    .trades
+                    .iter()
+                    .filter(|(time, _)| (now - *time).num_seconds() < TRADE_WINDOW_SECONDS)
+                    .map(|(_, quantity)| *quantity)
+                    .collect();
+                let candles = stats
+                    .candles
+                    .iter()
+                    .map(|(&period_seconds, &(timestamp, received))| {
+                        let age_secs = age(now, received);
+                        CandleStatus { period_seconds, timestamp, age_secs, late: age_secs >= 2.0 * period_seconds as f64 }
+                    })
+                    .collect();
+                SymbolRow {
+                    symbol: symbol.clone(),
+                    last_price: stats.last_price,
+                    volume: recent.iter().sum(),
+                    trades_per_sec: recent.len() as f64 / TRADE_WINDOW_SECONDS as f64,
+                    last_trade_age_secs: stats.last_trade.map(|time| age(now, time)),
+                    candles,
+                }
+            })
+            .collect();
+        DashboardSnapshot {
+            uptime_secs: (now - self.started_at).num_seconds(),
+            open_connections: inner.connects.saturating_sub(inner.disconnects),
+            connects: inner.connects,
+            disconnects: inner.disconnects,
+            last_disconnect: inner.last_disconnect.clone(),
+            database: self.db.as_ref().map(|db| (db.is_enabled(), db.is_healthy())),
+            pending_candles: inner.pending_candles,
+            queued_candles: inner.queued_candles,
+            symbols,
+            logs: inner.logs.iter().cloned().collect(),
+        }
+    }
+
+    /// 約定を記録しながら全てのイベントを後段にそのまま流す
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            self.observe(&event, Utc::now());
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+
+    /// 端末をダッシュボードにして REFRESH_INTERVAL ごとに描き直す
+    /// raw mode では Ctrl-C が SIGINT にならないので, q / Esc / Ctrl-C で端末を戻してプロセスを終了する
+    pub fn spawn_tui(self) -> tokio::task::JoinHandle<()> {
+        tokio::task::spawn_blocking(move || {
+            let mut terminal = ratatui::init();
+            let result = self.run_tui(&mut terminal);
+            ratatui::restore();
+            self.inner.lock().unwrap().closed = true;
+            if let Err(e) = result {
+                error!("[{}-TUI] {}", self.label, e);
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        })
+    }
+
+    fn run_tui(&self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            let snapshot = self.snapshot(Utc::now());
+            terminal.draw(|frame| draw(frame, &self.label, &snapshot))?;
+            if !event::poll(REFRESH_INTERVAL)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                let quit = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+                if key.kind == KeyEventKind::Press && quit {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// 上から接続と DB の状況, symbol ごとの表, ログ
+fn draw(frame: &mut Frame, label: &str, snapshot: &DashboardSnapshot) {
+    let [status_area, table_area, log_area] = Layout::vertical([Constraint::Length(4), Constraint::Min(5), Constraint::Length(8)]).areas(frame.area());
+
+    let red = Style::default().fg(Color::Red);
+    let green = Style::default().fg(Color::Green);
+    let connection = Line::styled(
+        format!(
+            "WebSocket: {} open ({} connects, {} disconnects){}",
+            snapshot.open_connections, snapshot.connects, snapshot.disconnects,
+            snapshot.last_disconnect.as_ref().map_or(String::new(), |(time, reason)| format!(", last disconnect {} {}", time.format("%H:%M:%S"), reason))
+        ),
+        if snapshot.open_connections > 0 { green } else { red },
+    );
+    let database = match snapshot.database {
+        Some((false, _)) => Line::raw(format!("DB: disabled (print only), {} pending", snapshot.pending_candles)),
+        Some((true, healthy)) => Line::styled(
+            format!("DB: {}, {} candles pending, {} queued (write-ahead)", if healthy { "healthy" } else { "FAILING" }, snapshot.pending_candles, snapshot.queued_candles),
+            if healthy && snapshot.queued_candles == 0 { green } else { red },
+        ),
+        None => Line::raw(format!("DB: {} candles pending, {} queued (write-ahead)", snapshot.pending_candles, snapshot.queued_candles)),
+    };
+    let title = format!(" kkcrypto {} | up {}s | q to quit ", label, snapshot.uptime_secs);
+    frame.render_widget(Paragraph::new(vec![connection, database]).block(Block::bordered().title(title)), status_area);
+
+    let header = Row::new(["Symbol", "Last", "Vol 1m", "Trades/s", "Last trade", "Candles"]).style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = snapshot.symbols.iter().map(|row| {
+        let candles: Vec<String> = row
+            .candles
+            .iter()
+            .map(|candle| format!("{}s {}{}", candle.period_seconds, candle.timestamp.format("%H:%M:%S"), if candle.late { " LATE" } else { "" }))
+            .collect();
+        let stale = row.candles.iter().any(|candle| candle.late) || row.last_trade_age_secs.is_none();
+        Row::new([
+            row.symbol.clone(),
+            row.last_price.map_or("-".to_string(), |price| price.to_string()),
+            format!("{:.4}", row.volume),
+            format!("{:.2}", row.trades_per_sec),
+            row.last_trade_age_secs.map_or("-".to_string(), |secs| format!("{:.1}s ago", secs)),
+            candles.join("  "),
+        ])
+        .style(if stale { red } else { Style::default() })
+    });
+    let widths = [Constraint::Length(if snapshot.symbols.iter().any(|row| row.symbol.contains(':')) { 28 } else { 14 }), Constraint::Length(14), Constraint::Length(14), Constraint::Length(9), Constraint::Length(12), Constraint::Min(20)];
+    frame.render_widget(Table::new(rows, widths).header(header).block(Block::bordered().title(" Symbols ")), table_area);
+
+    let visible = log_area.height.saturating_sub(2) as usize;
+    let logs: Vec<Line> = snapshot.logs.iter().skip(snapshot.logs.len().saturating_sub(visible)).map(|line| Line::raw(line.as_str())).collect();
+    frame.render_widget(Paragraph::new(logs).block(Block::bordered().title(" Log ")), log_area);
+}
+
+/// tracing の出力を Dashboard のログに溜める writer (1 回の write が 1 つ以上の行. 端末を戻した後は stderr に出す)
+#[derive(Clone)]
+pub struct DashboardLog {
+    inner: Arc<Mutex<DashboardInner>>,
+}
+
+impl std::io::Write for DashboardLog {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return std::io::Write::write(&mut std::io::stderr(), buf);
+        }
+        for line in text.lines().filter(|line| !line.trim().is_empty()) {
+            inner.log(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for DashboardLog {
+    type Writer = DashboardLog;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
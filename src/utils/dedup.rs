@@ -0,0 +1,105 @@
+use crate::models::market_event::MarketEvent;
+use crate::models::trade::Trade;
+use super::quality::QualityTracker;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// 既定で覚えておく直近の約定キーの数 (再接続・REST 補完で重なる範囲より十分大きく)
+pub const DEFAULT_DEDUP_WINDOW: usize = 100_000;
+
+type TradeKey = (String, String, String);  // (exchange, symbol, trade_id)
+
+/// 約定ごとの ID を配信しない取引所 (trade_id はタイムスタンプ等からの代用なので重複判定に使えない)
+const EXCHANGES_WITHOUT_TRADE_ID: &[&str] = &["phemex"];
+
+/// trade_id で重複を判定できる取引所か
+pub fn has_trade_ids(exchange: &str) -> bool {
+    !EXCHANGES_WITHOUT_TRADE_ID.contains(&exchange)
+}
+
+/// 約定を TradeCandleBuilder に渡す前に trade_id で重複を除く
+/// 直近 capacity 件のキーを LRU で保持し, 最も長く見ていないキーから忘れる
+pub struct TradeDedup {
+    capacity: usize,
+    last_seen: HashMap<TradeKey, u64>,  // キー -> 最後に見た順番
+    order: BTreeMap<u64, TradeKey>,     // 順番 -> キー (古い順に追い出す)
+    counter: u64,
+    duplicates: u64,
+    quality: Option<Arc<Mutex<QualityTracker>>>,
+}
+
+impl TradeDedup {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            last_seen: HashMap::new(),
+            order: BTreeMap::new(),
+            counter: 0,
+            duplicates: 0,
+            quality: None,
+        }
+    }
+
+    /// 捨てた重複を品質レポートの重複数に計上する
+    pub fn with_quality(mut self, quality: Arc<Mutex<QualityTracker>>) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// 初めて見た約定なら true (重複なら false で, キーは最近見たものとして残す)
+    /// 約定 ID のない取引所の約定は判定せず常に通す
+    pub fn push(&mut self, trade: &Trade) -> bool {
+        if !has_trade_ids(&trade.exchange) {
+            return true;
+        }
+        let key = (trade.exchange.clone(), trade.symbol.clone(), trade.trade_id.clone());
+        self.counter += 1;
+        if let Some(seen) = self.last_seen.insert(key.clone(), self.counter) {
+            self.order.remove(&seen);
+            self.order.insert(self.counter, key);
+            self.duplicates += 1;
+            return false;
+        }
+        self.order.insert(self.counter, key);
+        if self.order.len() > self.capacity {
+            if let Some((_, oldest)) = self.order.pop_first() {
+                self.last_seen.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.last_seen.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last_seen.is_empty()
+    }
+
+    /// これまでに捨てた重複の数
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    /// MarketEvent のうち重複した約定だけを捨てて後段に流す
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            if let MarketEvent::Trade(trade) = &event {
+                if !self.push(trade) {
+                    if let Some(quality) = &self.quality {
+                        quality.lock().unwrap().record_duplicate(trade);
+                    }
+                    tracing::debug!("Dropped duplicate trade {} {} {}", trade.exchange, trade.symbol, trade.trade_id);
+                    continue;
+                }
+            }
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to send {}: {}", kind, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::models::trade_candle::{TradeCandle, TradeSizeStats};
+use super::timeframe;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// 同じ系列の連続する足 (古い順) を 1 本の足にまとめる
+/// 価格は出来高加重, 数量・約定代金・件数は合計, OHLC と realized_vol は経路を連結した値 (約定から集計した場合と一致する)
+/// 保存済みの足には約定ごとの情報がないため, 次の値は近似になる
+///   direction_changes: 元の足の合計 (境界をまたぐ向きの変化は数えない)
+///   trade_size.median: 元の足の中央値の約定件数加重の中央値
+pub fn aggregate(candles: &[TradeCandle], timestamp: DateTime<Utc>, period_seconds: i32) -> Option<TradeCandle> {
+    let first = candles.first()?;
+    let mut candle = TradeCandle::new(first.exchange.clone(), first.market_type.clone(), first.symbol.clone(), timestamp, period_seconds);
+    candle.timestamp_source = first.timestamp_source;
+    let vwap = |price: Option<f64>, volume: f64, next_price: Option<f64>, next_volume: f64| {
+        let total = volume + next_volume;
+        if total > 0.0 {
+            Some((price.unwrap_or(0.0) * volume + next_price.unwrap_or(0.0) * next_volume) / total)
+        } else {
+            price.or(next_price)
+        }
+    };
+    let mut squared_returns = 0.0;
+    let mut latency = (0.0, 0);  // (Σ 遅延 × 件数, 件数)
+    let mut medians = Vec::new();  // (中央値, 件数)
+    for source in candles {
+        candle.ask_price = vwap(candle.ask_price, candle.ask_volume, source.ask_price, source.ask_volume);
+        candle.ask_volume += source.ask_volume;
+        candle.ask_notional += source.ask_notional;
+        candle.ask_count += source.ask_count;
+        candle.bid_price = vwap(candle.bid_price, candle.bid_volume, source.bid_price, source.bid_volume);
+        candle.bid_volume += source.bid_volume;
+        candle.bid_notional += source.bid_notional;
+        candle.bid_count += source.bid_count;
+        candle.warmup |= source.warmup;
+
+        // 境界をまたぐ 1 約定分のリターン (直前の足の終値 -> この足の始値)
+        if let (Some(close), Some(open)) = (candle.close, source.open) {
+            if close > 0.0 && open > 0.0 {
+                squared_returns += (open / close).ln().powi(2);
+            }
+        }
+        squared_returns += source.realized_vol.unwrap_or(0.0).powi(2);
+        candle.open = candle.open.or(source.open);
+        candle.close = source.close.or(candle.close);
+        if let Some(high) = source.high {
+            candle.high = Some(candle.high.map_or(high, |h| h.max(high)));
+        }
+        if let Some(low) = source.low {
+            candle.low = Some(candle.low.map_or(low, |l| l.min(low)));
+        }
+        candle.direction_changes += source.direction_changes;
+        candle.cvd = source.cvd;
+        candle.received_at = candle.received_at.max(source.received_at);
+
+        let count = source.ask_count + source.bid_count;
+        if let Some(latency_ms) = source.latency_ms {
+            latency.0 += latency_ms * count as f64;
+            latency.1 += count;
+        }
+        if let Some(size) = &source.trade_size {
+            let stats = candle.trade_size.get_or_insert(TradeSizeStats { max: size.max, median: 0.0, histogram: [0; 5] });
+            stats.max = stats.max.max(size.max);
+            for (total, count) in stats.histogram.iter_mut().zip(size.histogram) {
+                *total += count;
+            }
+            medians.push((size.median, count));
+        }
+    }
+    candle.realized_vol = candle.close.map(|_| squared_returns.sqrt());
+    candle.latency_ms = (latency.1 > 0).then(|| latency.0 / latency.1 as f64);
+    if let Some(stats) = candle.trade_size.as_mut() {
+        stats.median = weighted_median(&mut medians);
+    }
+    Some(candle)
+}
+
+fn weighted_median(values: &mut [(f64, i32)]) -> f64 {
+    values.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let total: i64 = values.iter().map(|(_, weight)| *weight as i64).sum();
+    let mut cumulative = 0;
+    for (value, weight) in values.iter() {
+        cumulative += *weight as i64;
+        if cumulative * 2 >= total {
+            return *value;
+        }
+    }
+    values.last().map_or(0.0, |(value, _)| *value)
+}
+
+/// 小さい時間枠の足 (1 つの系列) を period_seconds の足にまとめる
+/// 同じ時刻の足は最新の revision を使い, [start, end) に収まる足だけを古い順に返す
+pub fn downsample(
+    candles: &[TradeCandle],
+    period_seconds: i32,
+    session_offset: i64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<TradeCandle> {
+    let mut latest: BTreeMap<DateTime<Utc>, &TradeCandle> = BTreeMap::new();
+    for candle in candles {
+        if latest.get(&candle.timestamp).is_none_or(|kept| kept.revision < candle.revision) {
+            latest.insert(candle.timestamp, candle);
+        }
+    }
+    // 元の足の時刻は終端なので, 直前の時刻を含む足にまとめる
+    let mut buckets: BTreeMap<DateTime<Utc>, Vec<TradeCandle>> = BTreeMap::new();
+    for (timestamp, candle) in latest {
+        let bucket_end = timeframe::bucket_end(&(timestamp - chrono::Duration::milliseconds(1)), period_seconds as u32, session_offset);
+        buckets.entry(bucket_end).or_default().push(candle.clone());
+    }
+    buckets
+        .into_iter()
+        .filter(|(bucket_end, _)| *bucket_end - chrono::Duration::seconds(period_seconds as i64) >= start && *bucket_end <= end)
+        .filter_map(|(bucket_end, sources)| aggregate(&sources, bucket_end, period_seconds))
+        .collect()
+}
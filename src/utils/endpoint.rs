@@ -0,0 +1,235 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// 接続に使うプロキシ (http://[user:pass@]host:port は CONNECT, socks5://[user:pass@]host:port は SOCKS5)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyConfig {
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+    pub auth: Option<(String, String)>,  // (user, password)
+    url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+    Http,
+    Socks5,
+}
+
+impl ProxyConfig {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let url = Url::parse(spec.trim()).map_err(|e| anyhow::anyhow!("Invalid proxy URL {}: {}", spec, e))?;
+        let kind = match url.scheme() {
+            "http" => ProxyKind::Http,
+            "socks5" | "socks5h" => ProxyKind::Socks5,
+            scheme => return Err(anyhow::anyhow!("Unsupported proxy scheme: {}. Use http:// or socks5://", scheme)),
+        };
+        let host = url.host_str().ok_or_else(|| anyhow::anyhow!("Proxy URL has no host: {}", spec))?.to_string();
+        let port = url.port().unwrap_or(match kind {
+            ProxyKind::Http => 8080,
+            ProxyKind::Socks5 => 1080,
+        });
+        let auth = (!url.username().is_empty()).then(|| (url.username().to_string(), url.password().unwrap_or("").to_string()));
+        Ok(Self { kind, host, port, auth, url: spec.trim().to_string() })
+    }
+
+    /// 引数がなければ PROXY_URL
+    pub fn from_arg_or_env(arg: Option<&str>) -> anyhow::Result<Option<Self>> {
+        arg.map(str::to_string)
+            .or_else(|| std::env::var("PROXY_URL").ok().filter(|v| !v.is_empty()))
+            .map(|spec| Self::parse(&spec))
+            .transpose()
+    }
+
+    /// プロキシ経由で host:port への TCP 接続を開く
+    pub async fn tunnel(&self, host: &str, port: u16) -> anyhow::Result<TcpStream> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+        match self.kind {
+            ProxyKind::Http => self.http_connect(&mut stream, host, port).await?,
+            ProxyKind::Socks5 => self.socks5_connect(&mut stream, host, port).await?,
+        }
+        Ok(stream)
+    }
+
+    async fn http_connect(&self, stream: &mut TcpStream, host: &str, port: u16) -> anyhow::Result<()> {
+        let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+        if let Some((user, password)) = &self.auth {
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", base64(format!("{}:{}", user, password).as_bytes())));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // ヘッダの終わりまで 1 バイトずつ読む (トンネルのデータを読み過ぎないように)
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            if response.len() > 8192 {
+                return Err(anyhow::anyhow!("Proxy response header is too long"));
+            }
+            response.push(stream.read_u8().await?);
+        }
+        let status_line = String::from_utf8_lossy(&response);
+        let status_line = status_line.lines().next().unwrap_or("");
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            return Err(anyhow::anyhow!("Proxy CONNECT to {}:{} failed: {}", host, port, status_line));
+        }
+        Ok(())
+    }
+
+    async fn socks5_connect(&self, stream: &mut TcpStream, host: &str, port: u16) -> anyhow::Result<()> {
+        let method = if self.auth.is_some() { 0x02 } else { 0x00 };
+        stream.write_all(&[0x05, 0x01, method]).await?;
+        let mut reply = [0u8; 2];
+        stream.read_exact(&mut reply).await?;
+        if reply != [0x05, method] {
+            return Err(anyhow::anyhow!("SOCKS5 proxy rejected the authentication method"));
+        }
+        if let Some((user, password)) = &self.auth {
+            let mut request = vec![0x01, user.len() as u8];
+            request.extend(user.as_bytes());
+            request.push(password.len() as u8);
+            request.extend(password.as_bytes());
+            stream.write_all(&request).await?;
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(anyhow::anyhow!("SOCKS5 proxy authentication failed"));
+            }
+        }
+
+        // 名前解決はプロキシ側で行う (ATYP 3: ドメイン名)
+        let mut request = vec![0x05, 0x01, 0x00, 0x03, host.len() as u8];
+        request.extend(host.as_bytes());
+        request.extend(port.to_be_bytes());
+        stream.write_all(&request).await?;
+        let mut header = [0u8; 4];
+        stream.read_exact(&mut header).await?;
+        if header[1] != 0x00 {
+            return Err(anyhow::anyhow!("SOCKS5 connect to {}:{} failed (reply {})", host, port, header[1]));
+        }
+        let address_len = match header[3] {
+            0x01 => 4,
+            0x04 => 16,
+            0x03 => stream.read_u8().await? as usize,
+            atyp => return Err(anyhow::anyhow!("SOCKS5 reply has unknown address type {}", atyp)),
+        };
+        let mut bound = vec![0u8; address_len + 2];
+        stream.read_exact(&mut bound).await?;
+        Ok(())
+    }
+}
+
+/// 取引所の接続先の差し替え (地域制限のある既定のホストの代わりに別ホスト・プロキシを使う)
+#[derive(Debug, Clone, Default)]
+pub struct Endpoint {
+    ws_host: Option<String>,    // host[:port] (パスは既定の URL のまま)
+    rest_host: Option<String>,
+    proxy: Option<ProxyConfig>,
+    http: reqwest::Client,
+}
+
+impl Endpoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// WebSocket の接続先ホスト (e.g. stream.binance.us:9443, stream.bybit.nl)
+    pub fn with_ws_host(mut self, host: Option<String>) -> Self {
+        self.ws_host = host;
+        self
+    }
+
+    /// REST (再接続時の補完) の接続先ホスト (e.g. api.binance.us, api.bybit.nl)
+    pub fn with_rest_host(mut self, host: Option<String>) -> Self {
+        self.rest_host = host;
+        self
+    }
+
+    /// WebSocket はプロキシ経由で接続する. REST は HTTP プロキシのみ経由する (SOCKS5 では HTTPS_PROXY などの環境変数に従う)
+    pub fn with_proxy(mut self, proxy: Option<ProxyConfig>) -> anyhow::Result<Self> {
+        if let Some(proxy) = &proxy {
+            match proxy.kind {
+                ProxyKind::Http => self.http = reqwest::Client::builder().proxy(reqwest::Proxy::all(&proxy.url)?).build()?,
+                ProxyKind::Socks5 => tracing::warn!("REST requests do not use the SOCKS5 proxy; set HTTPS_PROXY to an HTTP proxy if REST is blocked too"),
+            }
+        }
+        self.proxy = proxy;
+        Ok(self)
+    }
+
+    pub fn ws_url(&self, default: &str) -> String {
+        replace_host(default, self.ws_host.as_deref())
+    }
+
+    pub fn rest_url(&self, default: &str) -> String {
+        replace_host(default, self.rest_host.as_deref())
+    }
+
+    /// REST 用の HTTP クライアント (プロキシ設定済み)
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// WebSocket に接続する (プロキシがあればトンネルを開いてから TLS / WebSocket のハンドシェイクを行う)
+    pub async fn connect(&self, url: &str) -> anyhow::Result<WsStream> {
+        let Some(proxy) = &self.proxy else {
+            let (ws_stream, _) = tokio_tungstenite::connect_async(url).await?;
+            return Ok(ws_stream);
+        };
+        let parsed = Url::parse(url)?;
+        let host = parsed.host_str().ok_or_else(|| anyhow::anyhow!("URL has no host: {}", url))?;
+        let port = parsed.port_or_known_default().ok_or_else(|| anyhow::anyhow!("URL has no port: {}", url))?;
+        let stream = proxy.tunnel(host, port).await?;
+        let (ws_stream, _) = tokio_tungstenite::client_async_tls(url, stream).await?;
+        Ok(ws_stream)
+    }
+}
+
+/// URL のホスト (と指定があればポート) を置き換える. host が None ならそのまま
+pub fn replace_host(url: &str, host: Option<&str>) -> String {
+    let Some(host) = host else {
+        return url.to_string();
+    };
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) => match port.parse::<u16>() {
+            Ok(port) => (name, Some(port)),
+            Err(_) => (host, None),
+        },
+        None => (host, None),
+    };
+    if parsed.set_host(Some(name)).is_err() {
+        return url.to_string();
+    }
+    if let Some(port) = port {
+        let _ = parsed.set_port(Some(port));
+    }
+    // 既定の URL にパスがなければ末尾の / を付けない (wss://host:9443 に /ws/... を足すため)
+    let mut replaced = parsed.to_string();
+    if parsed.path() == "/" && !url.ends_with('/') {
+        replaced.pop();
+    }
+    replaced
+}
+
+fn base64(input: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(TABLE[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
@@ -0,0 +1,155 @@
+use crate::db::Database;
+use crate::models::market_event::MarketEvent;
+use super::dashboard::Dashboard;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+/// TradeCandleBuilder の出力 (MarketEvent) を表示して DB に書き込む
+/// ローソク足は表示するだけで, 書き込みは SinkFanout の MongoCandleSink が行う
+/// 板・マーク価格は高頻度なので, symbol ごとの最新値を sample_interval ごとに書き込む
+pub struct EventWriter {
+    db: Arc<Database>,
+    label: String,  // 表示用の取引所名 (e.g. BYBIT)
+    sample_interval: std::time::Duration,
+    price_decimals: usize,
+    dashboard: Option<Dashboard>,  // --tui なら行を表示せずダッシュボードに出す
+}
+
+impl EventWriter {
+    pub fn new(db: Arc<Database>, exchange: &str) -> Self {
+        Self {
+            db,
+            label: exchange.to_uppercase(),
+            sample_interval: std::time::Duration::from_millis(1000),
+            price_decimals: 2,
+            dashboard: None,
+        }
+    }
+
+    /// Quote / MarkPrice を書き込む間隔
+    pub fn with_sample_interval(mut self, sample_interval: std::time::Duration) -> Self {
+        self.sample_interval = sample_interval;
+        self
+    }
+
+    /// 表示する価格の小数点以下の桁数
+    pub fn with_price_decimals(mut self, price_decimals: usize) -> Self {
+        self.price_decimals = price_decimals;
+        self
+    }
+
+    /// 行を表示する代わりに足をダッシュボードに出す
+    pub fn with_dashboard(mut self, dashboard: Dashboard) -> Self {
+        self.dashboard = Some(dashboard);
+        self
+    }
+
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>) {
+        let mut latest: HashMap<(&'static str, String), MarketEvent> = HashMap::new();
+        let mut ticker = tokio::time::interval(self.sample_interval);
+        loop {
+            tokio::select! {
+                event = receiver.recv() => match event {
+                    Some(event @ (MarketEvent::Quote(_) | MarketEvent::MarkPrice(_))) => {
+                        latest.insert((event.kind(), event.symbol().to_string()), event);
+                    }
+                    Some(event) => self.write(&event).await,
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    for (_, event) in latest.drain() {
+                        self.write(&event).await;
+                    }
+                }
+            }
+        }
+        for (_, event) in latest.drain() {
+            self.write(&event).await;
+        }
+    }
+
+    async fn write(&self, event: &MarketEvent) {
+        match self.dashboard.as_ref() {
+            Some(dashboard) => dashboard.observe(event, chrono::Utc::now()),
+            None => {
+                if let Some(line) = self.format(event) {
+                    info!("{}", line);
+                }
+            }
+        }
+        if matches!(event, MarketEvent::Candle(_)) {
+            return;
+        }
+        if let Err(e) = self.db.insert_event(event).await {
+            error!("Failed to insert {}: {}", event.kind(), e);
+        }
+    }
+
+    fn format(&self, event: &MarketEvent) -> Option<String> {
+        let label = &self.label;
+        let d = self.price_decimals;
+        let price = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.*}", d, v));
+        let line = match event {
+            MarketEvent::Trade(_) => return None,
+            MarketEvent::Candle(candle) => format!(
+                "[{}-CANDLE {}s] {} @ {} | Ask: Price:{} V:{:.4} N:{:.0} Cnt:{} | Bid: Price:{} V:{:.4} N:{:.0} Cnt:{} | Delta:{:+.4} CVD:{:+.4}",
+                label, candle.period_seconds, candle.symbol, candle.timestamp.format("%H:%M:%S"),
+                price(candle.ask_price), candle.ask_volume, candle.ask_notional, candle.ask_count,
+                price(candle.bid_price), candle.bid_volume, candle.bid_notional, candle.bid_count,
+                candle.delta(), candle.cvd
+            ),
+            MarketEvent::Quote(quote) => format!(
+                "[{}-QUOTE] {} @ {} | Bid: {:.*} x {:.4} | Ask: {:.*} x {:.4} | Mid: {:.*}{}",
+                label, quote.symbol, quote.timestamp.format("%H:%M:%S%.3f"),
+                d, quote.bid_price, quote.bid_size,
+                d, quote.ask_price, quote.ask_size,
+                d, quote.mid_price(),
+                quote.imbalance.map_or(String::new(), |v| format!(" | Imb({}): {:+.3}", quote.depth_levels, v))
+            ),
+            MarketEvent::Liquidation(liquidation) => format!(
+                "[{}-LIQUIDATION] {} @ {} | {:?} Price:{:.*} Qty:{:.4}",
+                label, liquidation.symbol, liquidation.timestamp.format("%H:%M:%S%.3f"),
+                liquidation.side, d, liquidation.price, liquidation.quantity
+            ),
+            MarketEvent::MarkPrice(mark_price) => format!(
+                "[{}-MARK] {} @ {} | Mark: {:.*} | Index: {} | Basis: {} | Funding: {}",
+                label, mark_price.symbol, mark_price.timestamp.format("%H:%M:%S%.3f"),
+                d, mark_price.mark_price,
+                price(mark_price.index_price),
+                mark_price.basis().map_or("-".to_string(), |v| format!("{:+.*}", d, v)),
+                mark_price.funding_rate.map_or("-".to_string(), |v| format!("{:.6}", v))
+            ),
+            MarketEvent::BlockTrade(block_trade) => format!(
+                "[{}-BLOCK] {} @ {} | {:?} Price:{:.*} Qty:{:.4} Notional:{:.0}",
+                label, block_trade.symbol, block_trade.timestamp.format("%H:%M:%S%.3f"),
+                block_trade.side, d, block_trade.price, block_trade.quantity, block_trade.notional
+            ),
+            MarketEvent::Bar(bar) => format!(
+                "[{}-BAR {}] {} @ {} | O:{:.*} H:{:.*} L:{:.*} C:{:.*} V:{:.4} Cnt:{} | {:.3}s",
+                label, bar.bar_type.name(), bar.symbol, bar.timestamp.format("%H:%M:%S%.3f"),
+                d, bar.open, d, bar.high, d, bar.low, d, bar.close, bar.volume, bar.count,
+                (bar.timestamp - bar.start).num_milliseconds() as f64 / 1000.0
+            ),
+            MarketEvent::ImbalanceBar(bar) => format!(
+                "[{}-IMBALANCE {}] {} @ {} | O:{:.*} H:{:.*} L:{:.*} C:{:.*} V:{:.4} Cnt:{} | Imb:{:+.4} Thr:{:.4} E[T]:{:.1}",
+                label, bar.kind.as_str(), bar.symbol, bar.timestamp.format("%H:%M:%S%.3f"),
+                d, bar.open, d, bar.high, d, bar.low, d, bar.close, bar.volume, bar.count,
+                bar.imbalance, bar.threshold, bar.expected_ticks
+            ),
+            MarketEvent::Brick(brick) => format!(
+                "[{}-BRICK {}] {} @ {} | {} O:{:.*} C:{:.*} H:{:.*} L:{:.*} Size:{:.*} V:{:.4} Cnt:{}",
+                label, brick.name, brick.symbol, brick.timestamp.format("%H:%M:%S%.3f"),
+                if brick.direction > 0 { "UP" } else { "DOWN" },
+                d, brick.open, d, brick.close, d, brick.high, d, brick.low, d, brick.size, brick.volume, brick.count
+            ),
+            MarketEvent::HeikinAshi(candle) => format!(
+                "[{}-HA {}s] {} @ {} | O:{:.*} H:{:.*} L:{:.*} C:{:.*}",
+                label, candle.period_seconds, candle.symbol, candle.timestamp.format("%H:%M:%S"),
+                d, candle.open, d, candle.high, d, candle.low, d, candle.close
+            ),
+        };
+        Some(line)
+    }
+}
@@ -0,0 +1,144 @@
+use super::returns::ReturnTransform;
+use super::rolling_correlation::PairCorrelation;
+use std::collections::BTreeMap;
+
+/// 指数加重の平均・分散・共分散 (West の重み付きの逐次計算. 古い値の重みを毎回 lambda 倍する)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EwmaPair {
+    weight: f64,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c_xy: f64,
+    pub count: usize,
+}
+
+impl EwmaPair {
+    pub fn update(&mut self, lambda: f64, x: f64, y: f64) {
+        self.weight = lambda * self.weight + 1.0;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / self.weight;
+        self.mean_y += dy / self.weight;
+        self.m2_x = lambda * self.m2_x + dx * (x - self.mean_x);
+        self.m2_y = lambda * self.m2_y + dy * (y - self.mean_y);
+        self.c_xy = lambda * self.c_xy + dx * (y - self.mean_y);
+        self.count += 1;
+    }
+
+    pub fn covariance(&self) -> Option<f64> {
+        (self.count >= 2).then(|| self.c_xy / self.weight)
+    }
+
+    pub fn correlation(&self) -> Option<f64> {
+        if self.count < 2 || self.m2_x <= 0.0 || self.m2_y <= 0.0 {
+            return None;
+        }
+        Some((self.c_xy / (self.m2_x * self.m2_y).sqrt()).clamp(-1.0, 1.0))
+    }
+}
+
+/// symbol ごとの指数加重の分散 (ボラティリティ用)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EwmaVariance {
+    weight: f64,
+    mean: f64,
+    m2: f64,
+    pub count: usize,
+}
+
+impl EwmaVariance {
+    pub fn update(&mut self, lambda: f64, x: f64) {
+        self.weight = lambda * self.weight + 1.0;
+        let dx = x - self.mean;
+        self.mean += dx / self.weight;
+        self.m2 = lambda * self.m2 + dx * (x - self.mean);
+        self.count += 1;
+    }
+
+    /// 指数加重の標準偏差
+    pub fn std(&self) -> Option<f64> {
+        (self.count >= 2).then(|| (self.m2.max(0.0) / self.weight).sqrt())
+    }
+}
+
+/// symbol の EWMA ボラティリティ (1 バケットの対数収益率の標準偏差)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolVolatility {
+    pub symbol_id: i32,
+    pub count: usize,
+    pub volatility: Option<f64>,
+}
+
+/// バケットごとの値 (価格または収益率) から指数加重の相関と symbol ごとのボラティリティを計算する
+/// 半減期 half_life_buckets バケットで重みが半分になるので, 窓の全てのバケットを同じ重みで扱うより変化が早く出る
+/// ボラティリティは値が収益率ならそのまま, 価格なら 1 バケットの対数収益率の標準偏差
+pub struct EwmaCorrelation {
+    lambda: f64,
+    pairs: BTreeMap<(i32, i32), EwmaPair>,
+    volatility: BTreeMap<i32, EwmaVariance>,
+    price_returns: Option<ReturnTransform>,  // 値が価格のときのボラティリティ用の収益率
+}
+
+impl EwmaCorrelation {
+    /// prices は入力が価格 (収益率でない) か
+    pub fn new(half_life_buckets: f64, prices: bool) -> Self {
+        Self {
+            lambda: 0.5f64.powf(1.0 / half_life_buckets.max(f64::MIN_POSITIVE)),
+            pairs: BTreeMap::new(),
+            volatility: BTreeMap::new(),
+            price_returns: prices.then(|| ReturnTransform::new(1)),
+        }
+    }
+
+    pub fn lambda(&self) -> f64 {
+        self.lambda
+    }
+
+    /// 次のバケットの値 (値のない symbol は含まない). 値のあるペア・symbol だけ更新する
+    pub fn update(&mut self, row: &BTreeMap<i32, f64>) {
+        let values: Vec<(i32, f64)> = row.iter().map(|(&symbol_id, &value)| (symbol_id, value)).collect();
+        for (i, &(symbol_a, x)) in values.iter().enumerate() {
+            for &(symbol_b, y) in &values[i + 1..] {
+                self.pairs.entry((symbol_a, symbol_b)).or_default().update(self.lambda, x, y);
+            }
+        }
+        let returns = match self.price_returns.as_mut() {
+            Some(transform) => transform.apply(row),
+            None => row.clone(),
+        };
+        for (symbol_id, value) in returns {
+            self.volatility.entry(symbol_id).or_default().update(self.lambda, value);
+        }
+    }
+
+    /// バケットが途切れたときに最初からやり直す
+    pub fn reset(&mut self) {
+        self.pairs.clear();
+        self.volatility.clear();
+        if let Some(transform) = self.price_returns.as_mut() {
+            transform.reset();
+        }
+    }
+
+    pub fn correlations(&self) -> Vec<PairCorrelation> {
+        self.pairs
+            .iter()
+            .map(|(&(symbol_a, symbol_b), pair)| PairCorrelation { symbol_a, symbol_b, count: pair.count, correlation: pair.correlation() })
+            .collect()
+    }
+
+    /// ペアの指数加重の共分散
+    pub fn covariance(&self, symbol_a: i32, symbol_b: i32) -> Option<f64> {
+        let key = if symbol_a <= symbol_b { (symbol_a, symbol_b) } else { (symbol_b, symbol_a) };
+        self.pairs.get(&key)?.covariance()
+    }
+
+    pub fn volatilities(&self) -> Vec<SymbolVolatility> {
+        self.volatility
+            .iter()
+            .map(|(&symbol_id, variance)| SymbolVolatility { symbol_id, count: variance.count, volatility: variance.std() })
+            .collect()
+    }
+}
@@ -0,0 +1,271 @@
+use crate::db::Database;
+use crate::models::market_event::MarketEvent;
+use super::ops_events::{OpsEvent, OpsEventKind};
+use super::storage_sink::{SinkFanout, SinkStatus};
+use super::trade_candle_builder::BufferMetrics;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
+
+/// 最後のメッセージからこれ以上経つと ready でなくなる (秒, 全ての symbol が止まった場合)
+pub const MAX_SILENCE_SECONDS: i64 = 120;
+
+#[derive(Debug, Default)]
+struct HealthInner {
+    connects: u64,
+    disconnects: u64,
+    last_connect: Option<(DateTime<Utc>, String)>,     // (時刻, URL)
+    last_disconnect: Option<(DateTime<Utc>, String)>,  // (時刻, 理由)
+    last_message: BTreeMap<String, BTreeMap<String, DateTime<Utc>>>,  // exchange -> symbol -> 最後に受け取った時刻
+}
+
+/// /healthz と /status で返す collector の状態 (clone してパイプライン・ops_events の書き込みと共有する)
+#[derive(Clone)]
+pub struct HealthState {
+    started_at: DateTime<Utc>,
+    inner: Arc<Mutex<HealthInner>>,
+    db: Option<Arc<Database>>,
+    sinks: Option<SinkFanout>,
+    buffers: Option<BufferMetrics>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConnectionStatus {
+    pub open: u64,
+    pub connects: u64,
+    pub disconnects: u64,
+    pub last_connect: Option<DateTime<Utc>>,
+    pub last_connect_url: Option<String>,
+    pub last_disconnect: Option<DateTime<Utc>>,
+    pub last_disconnect_reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExchangeStatus {
+    pub last_message_age_secs: f64,
+    pub symbols: BTreeMap<String, f64>,  // symbol -> 最後のメッセージからの秒数
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseStatus {
+    pub enabled: bool,  // --update なし (表示のみ) なら false
+    pub healthy: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BufferStatus {
+    pub active: u64,
+    pub peak: u64,
+    pub evicted: u64,
+    pub thinned: u64,
+}
+
+/// /status の内容 (ready なら /healthz は 200)
+#[derive(Debug, Serialize)]
+pub struct HealthStatus {
+    pub ready: bool,
+    pub reasons: Vec<String>,  // ready でない理由
+    pub started_at: DateTime<Utc>,
+    pub uptime_secs: i64,
+    pub connections: ConnectionStatus,
+    pub exchanges: BTreeMap<String, ExchangeStatus>,
+    pub database: Option<DatabaseStatus>,
+    pub sinks: Vec<SinkStatus>,  // MongoDB 以外の書き込み先
+    pub buffers: Option<BufferStatus>,
+}
+
+fn age(now: DateTime<Utc>, time: DateTime<Utc>) -> f64 {
+    (now - time).num_milliseconds().max(0) as f64 / 1000.0
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self { started_at: Utc::now(), inner: Arc::new(Mutex::new(HealthInner::default())), db: None, sinks: None, buffers: None }
+    }
+
+    /// DB の接続状態を報告する
+    pub fn with_database(mut self, db: Arc<Database>) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    /// MongoDB 以外の書き込み先の状況を報告する
+    pub fn with_sinks(mut self, sinks: SinkFanout) -> Self {
+        self.sinks = Some(sinks);
+        self
+    }
+
+    /// 集計中の足のバッファ数を報告する
+    pub fn with_buffer_metrics(mut self, buffers: BufferMetrics) -> Self {
+        self.buffers = Some(buffers);
+        self
+    }
+
+    /// 取引所から受け取ったイベントの時刻を記録する
+    pub fn observe(&self, event: &MarketEvent, now: DateTime<Utc>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.last_message.entry(event.exchange().to_string()).or_default().insert(event.symbol().to_string(), now);
+    }
+
+    /// 接続・切断の運用イベントを数える (それ以外は無視)
+    pub fn record_ops(&self, event: &OpsEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        match event.kind {
+            OpsEventKind::Connect => {
+                inner.connects += 1;
+                inner.last_connect = Some((event.timestamp, event.detail.clone()));
+            }
+            OpsEventKind::Disconnect => {
+                inner.disconnects += 1;
+                inner.last_disconnect = Some((event.timestamp, event.detail.clone()));
+            }
+            _ => {}
+        }
+    }
+
+    pub fn status(&self, now: DateTime<Utc>) -> HealthStatus {
+        let inner = self.inner.lock().unwrap();
+        let open = inner.connects.saturating_sub(inner.disconnects);
+        let exchanges: BTreeMap<String, ExchangeStatus> = inner
+            .last_message
+            .iter()
+            .map(|(exchange, symbols)| {
+                let latest = symbols.values().max().copied().unwrap_or(self.started_at);
+                let symbols = symbols.iter().map(|(symbol, time)| (symbol.clone(), age(now, *time))).collect();
+                (exchange.clone(), ExchangeStatus { last_message_age_secs: age(now, latest), symbols })
+            })
+            .collect();
+        let database = self.db.as_ref().map(|db| DatabaseStatus { enabled: db.is_enabled(), healthy: db.is_healthy() });
+        let sinks = self.sinks.as_ref().map(SinkFanout::statuses).unwrap_or_default();
+
+        let mut reasons = Vec::new();
+        if open == 0 {
+            reasons.push("no open exchange connection".to_string());
+        }
+        // 起動直後はまだメッセージがなくてもよい
+        let latest = inner.last_message.values().flat_map(|symbols| symbols.values()).max().copied().unwrap_or(self.started_at);
+        if (now - latest).num_seconds() >= MAX_SILENCE_SECONDS {
+            reasons.push(format!("no message for {:.0}s", age(now, latest)));
+        }
+        if database.as_ref().is_some_and(|db| !db.healthy) {
+            reasons.push("database writes failing".to_string());
+        }
+        for sink in sinks.iter().filter(|sink| !sink.healthy) {
+            reasons.push(format!("{} writes failing", sink.name));
+        }
+
+        HealthStatus {
+            ready: reasons.is_empty(),
+            reasons,
+            started_at: self.started_at,
+            uptime_secs: (now - self.started_at).num_seconds(),
+            connections: ConnectionStatus {
+                open,
+                connects: inner.connects,
+                disconnects: inner.disconnects,
+                last_connect: inner.last_connect.as_ref().map(|(time, _)| *time),
+                last_connect_url: inner.last_connect.as_ref().map(|(_, url)| url.clone()),
+                last_disconnect: inner.last_disconnect.as_ref().map(|(time, _)| *time),
+                last_disconnect_reason: inner.last_disconnect.as_ref().map(|(_, reason)| reason.clone()),
+            },
+            exchanges,
+            database,
+            sinks,
+            buffers: self.buffers.as_ref().map(|buffers| BufferStatus {
+                active: buffers.active(),
+                peak: buffers.peak(),
+                evicted: buffers.evicted(),
+                thinned: buffers.thinned(),
+            }),
+        }
+    }
+
+    /// リクエスト行 (e.g. "GET /healthz HTTP/1.1") への応答 (ステータスコード, JSON)
+    pub fn respond(&self, request_line: &str, now: DateTime<Utc>) -> (u16, String) {
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        if method != "GET" && method != "HEAD" {
+            return (405, r#"{"error":"method not allowed"}"#.to_string());
+        }
+        let status = self.status(now);
+        match path.split('?').next().unwrap_or("") {
+            "/healthz" => {
+                let code = if status.ready { 200 } else { 503 };
+                (code, serde_json::json!({ "ready": status.ready, "reasons": status.reasons }).to_string())
+            }
+            "/status" => (200, serde_json::to_string(&status).unwrap_or_default()),
+            _ => (404, r#"{"error":"not found (use /healthz or /status)"}"#.to_string()),
+        }
+    }
+
+    /// 全てのイベントの受信時刻を記録しながら後段にそのまま流す
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            self.observe(&event, Utc::now());
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn handle(mut stream: TcpStream, state: &HealthState) -> std::io::Result<()> {
+    // ヘッダーの終わりまで (最大 8KB) 読む
+    let mut buffer = vec![0u8; 8192];
+    let mut len = 0;
+    while len < buffer.len() {
+        let n = stream.read(&mut buffer[len..]).await?;
+        if n == 0 {
+            break;
+        }
+        len += n;
+        if buffer[..len].windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+    let request = String::from_utf8_lossy(&buffer[..len]);
+    let request_line = request.lines().next().unwrap_or("");
+    let (code, body) = state.respond(request_line, Utc::now());
+    let reason = match code {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Service Unavailable",
+    };
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        code, reason, body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    if !request_line.starts_with("HEAD ") {
+        stream.write_all(body.as_bytes()).await?;
+    }
+    stream.shutdown().await
+}
+
+/// /healthz (ready なら 200, でなければ 503) と /status (JSON) を返す HTTP サーバー
+/// 起動時にポートの使用中などで失敗するように, listener は呼び出し側で bind する
+pub async fn serve(listener: TcpListener, state: HealthState) -> anyhow::Result<()> {
+    info!("Health endpoint listening on http://{}/healthz and /status", listener.local_addr()?);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &state).await {
+                debug!("Health request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
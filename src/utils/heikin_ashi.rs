@@ -0,0 +1,110 @@
+use crate::models::heikin_ashi::HeikinAshiCandle;
+use crate::models::market_event::MarketEvent;
+use crate::models::market_type::MarketType;
+use crate::models::trade_candle::TradeCandle;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// 系列ごとの直近の平均足
+#[derive(Debug, Clone, Copy)]
+struct HeikinAshiState {
+    timestamp: DateTime<Utc>,
+    previous: Option<(f64, f64)>,  // 1 本前の (open, close), 最新の足の訂正で使う
+    current: (f64, f64),
+}
+
+/// TradeCandleBuilder が出力したローソク足から平均足を作る後段
+/// ローソク足はそのまま流し, 対象の時間枠の足の後に MarketEvent::HeikinAshi を送る
+pub struct HeikinAshiBuilder {
+    timeframes: Vec<u32>,
+    states: HashMap<(String, MarketType, String, i32), HeikinAshiState>,
+}
+
+impl HeikinAshiBuilder {
+    pub fn new(timeframes: Vec<u32>) -> Self {
+        Self {
+            timeframes,
+            states: HashMap::new(),
+        }
+    }
+
+    /// 書式: "60,300" や "1m,5m" (utils::timeframe) または "*" (全ての時間枠). active に含まれない時間枠はエラー
+    pub fn parse(spec: &str, active: &[u32]) -> anyhow::Result<Self> {
+        if spec.trim() == "*" {
+            return Ok(Self::new(active.to_vec()));
+        }
+        let mut timeframes = Vec::new();
+        for s in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let seconds = super::timeframe::parse(s)?;
+            if !active.contains(&seconds) {
+                return Err(anyhow::anyhow!("Heikin-Ashi timeframe {} is not in --timeframes {:?}", super::timeframe::label(seconds), active));
+            }
+            timeframes.push(seconds);
+        }
+        if timeframes.is_empty() {
+            return Err(anyhow::anyhow!("Empty Heikin-Ashi timeframe list"));
+        }
+        Ok(Self::new(timeframes))
+    }
+
+    /// 平均足を作る. 対象外の時間枠, 約定のない足 (OHLC なし), 直近より古い足は None
+    /// 最新の足の訂正 (同じ時刻で revision が大きい) は 1 本前の平均足から作り直す
+    pub fn push(&mut self, candle: &TradeCandle) -> Option<HeikinAshiCandle> {
+        if !self.timeframes.contains(&(candle.period_seconds as u32)) {
+            return None;
+        }
+        let (Some(open), Some(high), Some(low), Some(close)) = (candle.open, candle.high, candle.low, candle.close) else {
+            return None;
+        };
+        let key = (candle.exchange.clone(), candle.market_type.clone(), candle.symbol.clone(), candle.period_seconds);
+        let state = self.states.get(&key).copied();
+        let previous = match state {
+            Some(state) if candle.timestamp > state.timestamp => Some(state.current),
+            Some(state) if candle.timestamp == state.timestamp => state.previous,
+            Some(_) => {
+                tracing::debug!("Skipping Heikin-Ashi for older {}s candle: {} @ {}", candle.period_seconds, candle.symbol, candle.timestamp);
+                return None;
+            }
+            None => None,
+        };
+
+        let ha_close = (open + high + low + close) / 4.0;
+        let ha_open = previous.map_or((open + close) / 2.0, |(prev_open, prev_close)| (prev_open + prev_close) / 2.0);
+        self.states.insert(key, HeikinAshiState { timestamp: candle.timestamp, previous, current: (ha_open, ha_close) });
+        Some(HeikinAshiCandle {
+            id: uuid::Uuid::new_v4(),
+            exchange: candle.exchange.clone(),
+            market_type: candle.market_type.clone(),
+            symbol: candle.symbol.clone(),
+            timestamp: candle.timestamp,
+            period_seconds: candle.period_seconds,
+            open: ha_open,
+            high: high.max(ha_open).max(ha_close),
+            low: low.min(ha_open).min(ha_close),
+            close: ha_close,
+            warmup: candle.warmup,
+            revision: candle.revision,
+        })
+    }
+
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        tracing::info!("HeikinAshiBuilder started with timeframes: {:?}", self.timeframes);
+        while let Some(event) = receiver.recv().await {
+            let heikin_ashi = match &event {
+                MarketEvent::Candle(candle) => self.push(candle),
+                _ => None,
+            };
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+            if let Some(candle) = heikin_ashi {
+                if let Err(e) = sender.send(MarketEvent::HeikinAshi(candle)).await {
+                    error!("Failed to send Heikin-Ashi candle: {}", e);
+                }
+            }
+        }
+    }
+}
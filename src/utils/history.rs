@@ -0,0 +1,61 @@
+use chrono::{Duration, NaiveDate};
+use std::io::Read;
+
+/// 過去の約定の取得元
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistorySource {
+    Archive,  // 日次のアーカイブ (data.binance.vision, public.bybit.com). 公開されていない日は REST で取得する
+    Rest,     // REST のみ (Binance aggTrades はページ送り, Bybit recent-trade は直近の約定のみ)
+}
+
+impl HistorySource {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim() {
+            "archive" => Ok(Self::Archive),
+            "rest" => Ok(Self::Rest),
+            _ => Err(anyhow::anyhow!("Invalid history source: {}. Use archive or rest", spec)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Archive => "archive",
+            Self::Rest => "rest",
+        }
+    }
+}
+
+/// [start, end) の日付
+pub fn days(start: NaiveDate, end: NaiveDate) -> Vec<NaiveDate> {
+    let mut days = Vec::new();
+    let mut day = start;
+    while day < end {
+        days.push(day);
+        day += Duration::days(1);
+    }
+    days
+}
+
+/// アーカイブをダウンロードする (まだ公開されていなければ None)
+pub async fn download(http: &reqwest::Client, url: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    let response = http.get(url).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+}
+
+/// zip の最初のファイル (data.binance.vision の CSV) を展開する
+pub fn unzip(bytes: &[u8]) -> anyhow::Result<String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes))?;
+    let mut text = String::new();
+    archive.by_index(0)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// gzip (public.bybit.com の CSV) を展開する
+pub fn gunzip(bytes: &[u8]) -> anyhow::Result<String> {
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(bytes).read_to_string(&mut text)?;
+    Ok(text)
+}
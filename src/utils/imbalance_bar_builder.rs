@@ -0,0 +1,133 @@
+use crate::models::imbalance_bar::{ImbalanceBar, ImbalanceKind};
+use crate::models::market_event::MarketEvent;
+use crate::models::market_type::MarketType;
+use crate::models::trade::Trade;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// imbalance bar の設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImbalanceBarConfig {
+    pub kind: ImbalanceKind,
+    pub initial_ticks: f64,  // 最初の足の期待約定数 E0[T]
+    pub span: u32,           // 期待値を更新する EWMA の span (足の本数)
+}
+
+impl ImbalanceBarConfig {
+    /// 書式: "tick:100", "volume:200/20" (期待約定数の初期値 / EWMA の span, 既定 10)
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let invalid = || anyhow::anyhow!("Invalid imbalance bar: {}. Use tick:<N> or volume:<N>[/<span>]", spec);
+        let (kind, rest) = spec.split_once(':').ok_or_else(invalid)?;
+        let kind = match kind.trim() {
+            "tick" => ImbalanceKind::Tick,
+            "volume" => ImbalanceKind::Volume,
+            _ => return Err(invalid()),
+        };
+        let (ticks, span) = rest.split_once('/').unwrap_or((rest, "10"));
+        let initial_ticks: u32 = ticks.trim().parse().map_err(|_| invalid())?;
+        let span: u32 = span.trim().parse().map_err(|_| invalid())?;
+        if initial_ticks == 0 || span == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { kind, initial_ticks: initial_ticks as f64, span })
+    }
+
+    /// 書式: "tick:100,volume:200/20"
+    pub fn parse_list(spec: &str) -> anyhow::Result<Vec<Self>> {
+        let configs = spec
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(Self::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if configs.is_empty() {
+            return Err(anyhow::anyhow!("Empty imbalance bar list"));
+        }
+        Ok(configs)
+    }
+}
+
+/// symbol ごとの期待値と集計中の足
+#[derive(Debug)]
+struct ImbalanceState {
+    expected_ticks: f64,               // E[T]
+    expected_imbalance: Option<f64>,   // 約定 1 件あたりの期待不均衡 (最初の足が閉じるまでは集計中の平均を使う)
+    bar: Option<ImbalanceBar>,
+}
+
+/// 約定から tick / volume imbalance bar を作る
+/// 期待値は閉じた足の約定数と 1 件あたりの不均衡の EWMA で更新する
+/// 期待約定数は初期値の 1/10 から 10 倍の範囲に収める (閾値が縮み続けて足が細かくなりすぎるのを防ぐ)
+pub struct ImbalanceBarBuilder {
+    configs: Vec<ImbalanceBarConfig>,
+    states: HashMap<(String, MarketType, String, usize), ImbalanceState>,  // (exchange, market_type, symbol, configs の添字)
+}
+
+impl ImbalanceBarBuilder {
+    pub fn new(configs: Vec<ImbalanceBarConfig>) -> Self {
+        Self {
+            configs,
+            states: HashMap::new(),
+        }
+    }
+
+    /// 約定を 1 件加え, 閉じた足を返す
+    pub fn push(&mut self, trade: &Trade) -> Vec<ImbalanceBar> {
+        let mut closed = Vec::new();
+        for (index, config) in self.configs.iter().enumerate() {
+            let key = (trade.exchange.clone(), trade.market_type.clone(), trade.symbol.clone(), index);
+            let state = self.states.entry(key).or_insert_with(|| ImbalanceState {
+                expected_ticks: config.initial_ticks,
+                expected_imbalance: None,
+                bar: None,
+            });
+            let bar = match state.bar.as_mut() {
+                Some(bar) => {
+                    bar.update(trade);
+                    bar
+                }
+                None => state.bar.insert(ImbalanceBar::new(config.kind, trade)),
+            };
+
+            let mean_imbalance = bar.imbalance / bar.count as f64;
+            let threshold = state.expected_ticks * state.expected_imbalance.unwrap_or(mean_imbalance).abs();
+            if threshold <= 0.0 || bar.imbalance.abs() < threshold {
+                continue;
+            }
+
+            // 閉じた足で期待値を更新する
+            let alpha = 2.0 / (config.span as f64 + 1.0);
+            let mut bar = state.bar.take().unwrap();
+            bar.threshold = threshold;
+            bar.expected_ticks = state.expected_ticks;
+            state.expected_ticks = (alpha * bar.count as f64 + (1.0 - alpha) * state.expected_ticks)
+                .clamp(config.initial_ticks / 10.0, config.initial_ticks * 10.0);
+            state.expected_imbalance = Some(match state.expected_imbalance {
+                Some(expected) => alpha * mean_imbalance + (1.0 - alpha) * expected,
+                None => mean_imbalance,
+            });
+            closed.push(bar);
+        }
+        closed
+    }
+
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        tracing::info!("ImbalanceBarBuilder started with {:?}", self.configs);
+        while let Some(event) = receiver.recv().await {
+            let bars = match &event {
+                MarketEvent::Trade(trade) => self.push(trade),
+                _ => Vec::new(),
+            };
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+            for bar in bars {
+                if let Err(e) = sender.send(MarketEvent::ImbalanceBar(bar)).await {
+                    error!("Failed to send imbalance bar: {}", e);
+                }
+            }
+        }
+    }
+}
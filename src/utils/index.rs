@@ -0,0 +1,173 @@
+use crate::models::{market_type::MarketType, trade_candle::TradeCandle};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// 合成シンボルの足の exchange (master.csv に "index" で登録した symbol_id で保存する)
+pub const INDEX_EXCHANGE: &str = "index";
+
+/// 構成銘柄 1 つ (重み付きの取引所・市場・symbol)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constituent {
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub weight: f64,
+}
+
+/// 構成銘柄の重み付きバスケットで定義する合成シンボル
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexDefinition {
+    pub name: String,
+    pub constituents: Vec<Constituent>,
+}
+
+impl IndexDefinition {
+    /// 書式: "BTC70-ETH30 = 0.7*bybit:linear:BTCUSDT + 0.3*binance:linear:ETHUSDT"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let invalid = || anyhow::anyhow!("Invalid index definition: {}. Use <name> = <weight>*<exchange>:<market_type>:<symbol> + ...", spec);
+        let (name, basket) = spec.split_once('=').ok_or_else(invalid)?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(invalid());
+        }
+        let mut constituents = Vec::new();
+        for term in basket.split('+').map(str::trim) {
+            let (weight, target) = term.split_once('*').ok_or_else(invalid)?;
+            let weight: f64 = weight.trim().parse().map_err(|_| invalid())?;
+            if weight <= 0.0 || !weight.is_finite() {
+                return Err(invalid());
+            }
+            let mut parts = target.trim().splitn(3, ':');
+            let (Some(exchange), Some(market_type), Some(symbol)) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(invalid());
+            };
+            let constituent = Constituent {
+                exchange: exchange.to_lowercase(),
+                market_type: MarketType::parse(market_type)?,
+                symbol: symbol.to_string(),
+                weight,
+            };
+            if constituents.iter().any(|c: &Constituent| (&c.exchange, &c.market_type, &c.symbol) == (&constituent.exchange, &constituent.market_type, &constituent.symbol)) {
+                return Err(anyhow::anyhow!("Duplicate constituent {}:{}:{} in {}", constituent.exchange, constituent.market_type.as_str(), constituent.symbol, name));
+            }
+            constituents.push(constituent);
+        }
+        Ok(Self { name: name.to_string(), constituents })
+    }
+
+    /// 1 行に 1 つの定義を書いたファイル (# 以降と空行は無視する)
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Vec<Self>> {
+        let content = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let definitions = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|line| !line.is_empty())
+            .map(Self::parse)
+            .collect::<anyhow::Result<Vec<Self>>>()?;
+        if definitions.is_empty() {
+            return Err(anyhow::anyhow!("No index definitions in {}", path.display()));
+        }
+        Ok(definitions)
+    }
+}
+
+pub type ConstituentKey = (String, MarketType, String);  // (exchange, market_type, symbol)
+
+/// 構成銘柄の同じ時刻の足から合成シンボルの足を作る (時間枠ごとに 1 つ)
+/// 約定のない構成銘柄は直前の終値で横ばいとみなし, 一度も約定のない構成銘柄がある間は出力しない
+pub struct IndexBuilder {
+    definitions: Vec<IndexDefinition>,
+    last_close: HashMap<ConstituentKey, f64>,
+}
+
+impl IndexBuilder {
+    pub fn new(definitions: Vec<IndexDefinition>) -> Self {
+        Self {
+            definitions,
+            last_close: HashMap::new(),
+        }
+    }
+
+    pub fn definitions(&self) -> &[IndexDefinition] {
+        &self.definitions
+    }
+
+    /// 全ての定義の構成銘柄 (重複なし)
+    pub fn constituents(&self) -> Vec<ConstituentKey> {
+        let mut keys: Vec<ConstituentKey> = Vec::new();
+        for constituent in self.definitions.iter().flat_map(|d| &d.constituents) {
+            let key = (constituent.exchange.clone(), constituent.market_type.clone(), constituent.symbol.clone());
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+        keys
+    }
+
+    /// candles は timestamp・period_seconds の構成銘柄の足 (見つかった分だけ)
+    /// 価格は重み付き和 (VWAP, 始値, 終値. 高値・安値は構成銘柄の高値・安値の重み付き和で, 合成値の上限・下限)
+    /// 約定代金と件数は構成銘柄の合計, 出来高は約定代金を合成の VWAP で割った値
+    pub fn compose(&mut self, timestamp: DateTime<Utc>, period_seconds: i32, candles: &[TradeCandle]) -> Vec<TradeCandle> {
+        let traded: HashMap<ConstituentKey, &TradeCandle> = candles
+            .iter()
+            .filter(|c| c.timestamp == timestamp && c.period_seconds == period_seconds && c.ask_count + c.bid_count > 0)
+            .map(|c| ((c.exchange.clone(), c.market_type.clone(), c.symbol.clone()), c))
+            .collect();
+        let mut composites = Vec::new();
+        for definition in &self.definitions {
+            let mut composite = TradeCandle::new(INDEX_EXCHANGE.to_string(), MarketType::Spot, definition.name.clone(), timestamp, period_seconds);
+            let (mut vwap, mut open, mut high, mut low, mut close) = (0.0, 0.0, 0.0, 0.0, 0.0);
+            let mut complete = true;
+            for constituent in &definition.constituents {
+                let key = (constituent.exchange.clone(), constituent.market_type.clone(), constituent.symbol.clone());
+                let w = constituent.weight;
+                match traded.get(&key) {
+                    Some(candle) => {
+                        let price = candle.vwap().unwrap_or_default();
+                        let last = candle.close.unwrap_or(price);
+                        vwap += w * price;
+                        open += w * candle.open.unwrap_or(price);
+                        high += w * candle.high.unwrap_or(price);
+                        low += w * candle.low.unwrap_or(price);
+                        close += w * last;
+                        composite.ask_notional += candle.ask_notional;
+                        composite.bid_notional += candle.bid_notional;
+                        composite.ask_count += candle.ask_count;
+                        composite.bid_count += candle.bid_count;
+                    }
+                    None => match self.last_close.get(&key) {
+                        Some(&last) => {
+                            vwap += w * last;
+                            open += w * last;
+                            high += w * last;
+                            low += w * last;
+                            close += w * last;
+                        }
+                        None => complete = false,
+                    },
+                }
+            }
+            if !complete {
+                tracing::debug!("Skipping {} @ {}: a constituent has not traded yet", definition.name, timestamp);
+                continue;
+            }
+            if vwap > 0.0 {
+                composite.ask_volume = composite.ask_notional / vwap;
+                composite.bid_volume = composite.bid_notional / vwap;
+            }
+            composite.ask_price = (composite.ask_count > 0).then_some(vwap);
+            composite.bid_price = (composite.bid_count > 0).then_some(vwap);
+            composite.open = Some(open);
+            composite.high = Some(high);
+            composite.low = Some(low);
+            composite.close = Some(close);
+            composites.push(composite);
+        }
+        for (key, candle) in traded {
+            if let Some(close) = candle.close.or(candle.vwap()) {
+                self.last_close.insert(key, close);
+            }
+        }
+        composites
+    }
+}
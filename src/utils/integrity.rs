@@ -0,0 +1,212 @@
+use crate::models::trade_candle::TradeCandle;
+use super::timeframe;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+
+/// 保存済みの足の不備の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IssueKind {
+    Missing,    // 足のない区間 (min_gap 以上)
+    Duplicate,  // 同じ時刻の足が複数
+    Invalid,    // ありえない値 (負の出来高, 約定がないのに価格がある, など)
+}
+
+impl IssueKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Duplicate => "duplicate",
+            Self::Invalid => "invalid",
+        }
+    }
+}
+
+/// 1 つの不備 (時刻は足の終端. Missing は欠けている最初と最後の足)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntegrityIssue {
+    pub exchange: String,
+    pub market_type: String,
+    pub symbol: String,
+    pub timeframe: u32,
+    pub kind: IssueKind,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub count: u64,  // 欠けている足 / 重複した足 / 1
+    pub detail: String,
+}
+
+/// 系列ごとの集計
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IntegritySummary {
+    pub exchange: String,
+    pub market_type: String,
+    pub symbol: String,
+    pub timeframe: u32,
+    pub candles: u64,
+    pub missing: u64,
+    pub duplicates: u64,
+    pub invalid: u64,
+}
+
+/// 1 つの系列 (exchange, market_type, symbol, timeframe) の足を古い順に受け取り, 不備を探す
+/// 約定のない時間の足は保存されないので, min_gap 以上続く欠けだけを報告する
+pub struct GapScanner {
+    summary: IntegritySummary,
+    session_offset: i64,
+    min_gap: Duration,
+    expected: DateTime<Utc>,  // 次に来るはずの足の終端
+    last: Option<(DateTime<Utc>, u64)>,  // 直前の足の時刻と同じ時刻の件数
+    issues: Vec<IntegrityIssue>,
+}
+
+impl GapScanner {
+    /// [start, end) の足を調べる (start は境界に揃える)
+    pub fn new(exchange: &str, market_type: &str, symbol: &str, timeframe: u32, session_offset: i64, start: DateTime<Utc>, min_gap: Duration) -> Self {
+        Self {
+            summary: IntegritySummary {
+                exchange: exchange.to_string(),
+                market_type: market_type.to_string(),
+                symbol: symbol.to_string(),
+                timeframe,
+                ..Default::default()
+            },
+            session_offset,
+            min_gap,
+            expected: timeframe::bucket_end(&start, timeframe, session_offset),
+            last: None,
+            issues: Vec::new(),
+        }
+    }
+
+    fn period(&self) -> Duration {
+        Duration::seconds(self.summary.timeframe as i64)
+    }
+
+    fn issue(&mut self, kind: IssueKind, from: DateTime<Utc>, to: DateTime<Utc>, count: u64, detail: String) {
+        match kind {
+            IssueKind::Missing => self.summary.missing += count,
+            IssueKind::Duplicate => self.summary.duplicates += count,
+            IssueKind::Invalid => self.summary.invalid += count,
+        }
+        self.issues.push(IntegrityIssue {
+            exchange: self.summary.exchange.clone(),
+            market_type: self.summary.market_type.clone(),
+            symbol: self.summary.symbol.clone(),
+            timeframe: self.summary.timeframe,
+            kind,
+            from,
+            to,
+            count,
+            detail,
+        });
+    }
+
+    /// expected から until の前までの足が欠けていれば報告する
+    fn check_gap(&mut self, until: DateTime<Utc>) {
+        if until <= self.expected {
+            return;
+        }
+        let period = self.period();
+        let count = ((until - self.expected).num_seconds() / period.num_seconds()) as u64;
+        if count > 0 && period * count as i32 >= self.min_gap {
+            let (from, to) = (self.expected, until - period);
+            self.issue(IssueKind::Missing, from, to, count, format!("{} candles ({}s) missing", count, count * self.summary.timeframe as u64));
+        }
+    }
+
+    pub fn push(&mut self, candle: &TradeCandle) {
+        self.summary.candles += 1;
+        let timestamp = candle.timestamp;
+        match self.last {
+            Some((last, count)) if last == timestamp => {
+                self.last = Some((last, count + 1));
+            }
+            _ => {
+                self.flush_duplicates();
+                if timeframe::bucket_end(&(timestamp - Duration::seconds(1)), self.summary.timeframe, self.session_offset) != timestamp {
+                    self.issue(IssueKind::Invalid, timestamp, timestamp, 1, format!("timestamp not on a {}s boundary", self.summary.timeframe));
+                }
+                self.check_gap(timestamp);
+                self.expected = self.expected.max(timestamp + self.period());
+                self.last = Some((timestamp, 1));
+            }
+        }
+        if let Some(reason) = invalid_reason(candle) {
+            self.issue(IssueKind::Invalid, timestamp, timestamp, 1, reason);
+        }
+    }
+
+    fn flush_duplicates(&mut self) {
+        if let Some((timestamp, count)) = self.last.take().filter(|(_, count)| *count > 1) {
+            self.issue(IssueKind::Duplicate, timestamp, timestamp, count, format!("{} candles at the same time", count));
+        }
+    }
+
+    /// end までの欠けを調べて結果を返す
+    pub fn finish(mut self, end: DateTime<Utc>) -> (IntegritySummary, Vec<IntegrityIssue>) {
+        self.flush_duplicates();
+        // end までに終わる足だけを数える
+        let last_end = end - Duration::seconds((end.timestamp() + self.session_offset).rem_euclid(self.summary.timeframe as i64));
+        self.check_gap(last_end + self.period());
+        (self.summary, self.issues)
+    }
+}
+
+/// ありえない値なら理由を返す (--emit-empty の約定のない足は価格だけを引き継ぐので正常とする)
+pub fn invalid_reason(candle: &TradeCandle) -> Option<String> {
+    let sides = [
+        ("ask", candle.ask_price, candle.ask_volume, candle.ask_notional, candle.ask_count),
+        ("bid", candle.bid_price, candle.bid_volume, candle.bid_notional, candle.bid_count),
+    ];
+    let empty = candle.ask_count == 0 && candle.bid_count == 0 && candle.ask_volume == 0.0 && candle.bid_volume == 0.0 && candle.open.is_none();
+    for (side, price, volume, notional, count) in sides {
+        if volume < 0.0 || notional < 0.0 || count < 0 {
+            return Some(format!("negative {} volume / notional / count", side));
+        }
+        if !volume.is_finite() || !notional.is_finite() {
+            return Some(format!("non-finite {} volume / notional", side));
+        }
+        if let Some(price) = price {
+            if !price.is_finite() || price <= 0.0 {
+                return Some(format!("{} price {}", side, price));
+            }
+            if count == 0 && !empty {
+                return Some(format!("{} price without {} trades", side, side));
+            }
+        }
+        if price.is_none() && count > 0 {
+            return Some(format!("{} trades without {} price", side, side));
+        }
+    }
+    if let (Some(high), Some(low)) = (candle.high, candle.low) {
+        if high < low {
+            return Some(format!("high {} below low {}", high, low));
+        }
+        for (name, value) in [("open", candle.open), ("close", candle.close)] {
+            if value.is_some_and(|value| value > high || value < low) {
+                return Some(format!("{} outside high / low", name));
+            }
+        }
+    }
+    None
+}
+
+/// 欠けた区間を取り直す日付の範囲 ([start, end), 系列ごとに重なりをまとめる)
+pub fn backfill_days(issues: &[IntegrityIssue]) -> Vec<(&IntegrityIssue, NaiveDate, NaiveDate)> {
+    let mut days: Vec<(&IntegrityIssue, NaiveDate, NaiveDate)> = Vec::new();
+    for issue in issues.iter().filter(|issue| issue.kind == IssueKind::Missing) {
+        let start = (issue.from - Duration::seconds(issue.timeframe as i64)).date_naive();
+        let end = (issue.to - Duration::seconds(1)).date_naive() + Duration::days(1);
+        match days.last_mut() {
+            Some((last, _, last_end))
+                if (&last.exchange, &last.market_type, &last.symbol, last.timeframe) == (&issue.exchange, &issue.market_type, &issue.symbol, issue.timeframe)
+                    && start <= *last_end =>
+            {
+                *last_end = (*last_end).max(end);
+            }
+            _ => days.push((issue, start, end)),
+        }
+    }
+    days
+}
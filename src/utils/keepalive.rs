@@ -0,0 +1,99 @@
+use std::fmt;
+use std::time::Duration;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
+
+/// 受信ループで行う接続維持の処理 (Keepalive::next の戻り値)
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeepaliveAction {
+    Ping(Message),      // 取引所が要求する ping を送る
+    Timeout(Duration),  // 無受信が続いたので切断して再接続する
+}
+
+/// 無受信の監視による切断 (subscribe_trades のエラー). 呼び出し側はこのエラーなら再接続する
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchdogTimeout(pub Duration);
+
+impl fmt::Display for WatchdogTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "watchdog: no message for {}s", self.0.as_secs())
+    }
+}
+
+impl std::error::Error for WatchdogTimeout {}
+
+/// 定期的な ping の送信と無受信の監視 (watchdog)
+/// 受信ループの select! で next() を待ち, フレームを受信するたびに received() を呼ぶ
+pub struct Keepalive {
+    ping: Option<(Interval, Message)>,
+    watchdog: Option<Duration>,
+    last_received: Instant,
+}
+
+impl Default for Keepalive {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Keepalive {
+    pub fn new() -> Self {
+        Self {
+            ping: None,
+            watchdog: None,
+            last_received: Instant::now(),
+        }
+    }
+
+    /// period ごとに message を送る (最初の送信は period 後)
+    pub fn with_ping(mut self, period: Duration, message: Message) -> Self {
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        self.ping = Some((interval, message));
+        self
+    }
+
+    /// timeout の間フレーム (pong を含む) を受信しなければ Timeout を返す (None なら監視しない)
+    pub fn with_watchdog(mut self, timeout: Option<Duration>) -> Self {
+        self.watchdog = timeout;
+        self
+    }
+
+    pub fn received(&mut self) {
+        self.last_received = Instant::now();
+    }
+
+    /// 次の ping の送信時刻か無受信の期限まで待つ (どちらもなければ待ち続ける)
+    pub async fn next(&mut self) -> KeepaliveAction {
+        let watchdog = self.watchdog;
+        let deadline = watchdog.map(|timeout| self.last_received + timeout);
+        let ping = async {
+            match self.ping.as_mut() {
+                Some((interval, message)) => {
+                    interval.tick().await;
+                    message.clone()
+                }
+                None => std::future::pending().await,
+            }
+        };
+        let timeout = async {
+            match deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            message = ping => KeepaliveAction::Ping(message),
+            _ = timeout => KeepaliveAction::Timeout(watchdog.unwrap_or_default()),
+        }
+    }
+}
+
+/// プロトコルレベルの ping への pong (ping 以外は None)
+/// tungstenite も自動で応答するが, 次の送信まで送られないため明示的に返す
+pub fn pong(msg: &Message) -> Option<Message> {
+    match msg {
+        Message::Ping(payload) => Some(Message::Pong(payload.clone())),
+        _ => None,
+    }
+}
@@ -0,0 +1,125 @@
+use crate::models::market_event::MarketEvent;
+use crate::models::trade::Trade;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// 既定で集計する直近の期間 (秒)
+pub const DEFAULT_LATENCY_WINDOW_SECONDS: u64 = 300;
+/// これより遅れて届いた約定は REST 補完・再送とみなして集計しない (ミリ秒)
+pub const MAX_LIVE_LATENCY_MS: f64 = 300_000.0;
+
+/// 直近の期間の遅延の分布 (ローカルの受信時刻 - 取引所の約定時刻, ミリ秒)
+/// 時計がずれていれば全体がずれる (min が負ならローカルの時計が取引所より遅れている)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySummary {
+    pub count: usize,
+    pub min_ms: f64,
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl fmt::Display for LatencySummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "trades:{} min:{:.0}ms p50:{:.0}ms p99:{:.0}ms max:{:.0}ms",
+               self.count, self.min_ms, self.p50_ms, self.p99_ms, self.max_ms)
+    }
+}
+
+/// ソート済みの値の q 分位 (nearest-rank)
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = (q.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+}
+
+#[derive(Debug)]
+struct LatencyWindow {
+    window: Duration,
+    samples: VecDeque<(DateTime<Utc>, f64)>,  // (受信時刻, 遅延)
+}
+
+impl LatencyWindow {
+    fn prune(&mut self, now: DateTime<Utc>) {
+        while self.samples.front().is_some_and(|(received_at, _)| now - *received_at > self.window) {
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// 約定ごとの遅延を直近 window の期間だけ保持し, 分位を返すハンドル (clone して集計と表示で共有する)
+#[derive(Debug, Clone)]
+pub struct LatencyMetrics {
+    inner: Arc<Mutex<LatencyWindow>>,
+}
+
+impl LatencyMetrics {
+    pub fn new(window: Duration) -> Self {
+        Self { inner: Arc::new(Mutex::new(LatencyWindow { window, samples: VecDeque::new() })) }
+    }
+
+    /// 約定の遅延を記録する (REST 補完などの古い約定は記録せず false)
+    pub fn record(&self, trade: &Trade) -> bool {
+        let latency = trade.latency_ms();
+        if latency > MAX_LIVE_LATENCY_MS {
+            return false;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        inner.samples.push_back((trade.received_at, latency));
+        inner.prune(trade.received_at);
+        true
+    }
+
+    /// now の時点で直近 window の遅延の分布 (約定がなければ None)
+    pub fn summary(&self, now: DateTime<Utc>) -> Option<LatencySummary> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.prune(now);
+        if inner.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f64> = inner.samples.iter().map(|(_, latency)| *latency).collect();
+        sorted.sort_by(f64::total_cmp);
+        Some(LatencySummary {
+            count: sorted.len(),
+            min_ms: sorted[0],
+            p50_ms: percentile(&sorted, 0.5),
+            p99_ms: percentile(&sorted, 0.99),
+            max_ms: sorted[sorted.len() - 1],
+        })
+    }
+
+    /// 全ての約定の遅延を記録しながら全てのイベントを後段にそのまま流す
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            if let MarketEvent::Trade(trade) = &event {
+                self.record(trade);
+            }
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+
+    /// interval ごとに分布をログに出す (label は表示用の取引所名)
+    pub async fn log_every(self, label: String, interval: std::time::Duration) {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(summary) = self.summary(Utc::now()) else {
+                continue;
+            };
+            tracing::info!("[{}-LATENCY] {}", label, summary);
+            if summary.p50_ms < 0.0 {
+                tracing::warn!("{} trades arrive {:.0}ms before their exchange timestamp (median); the local clock is behind, check NTP", label, -summary.p50_ms);
+            }
+        }
+    }
+}
@@ -1,2 +1,67 @@
 pub mod trade_candle_builder;
-pub mod symbol_manager;
\ No newline at end of file
+pub mod symbol_manager;
+pub mod candle_fields;
+pub mod order_book;
+pub mod quality;
+pub mod candle_cache;
+pub mod resample;
+pub mod price_window;
+pub mod rolling_correlation;
+pub mod returns;
+pub mod rank_correlation;
+pub mod ewma;
+pub mod beta;
+pub mod correlation_store;
+pub mod correlation_matrix;
+pub mod pca;
+pub mod clustering;
+pub mod correlation_alert;
+pub mod pair_spread;
+pub mod pair_signal_store;
+pub mod stablecoin;
+pub mod ops_events;
+pub mod event_writer;
+pub mod throttle;
+pub mod ohlcv;
+pub mod candle_frame;
+pub mod notify;
+pub mod alert;
+pub mod bar_builder;
+pub mod imbalance_bar_builder;
+pub mod renko_builder;
+pub mod heikin_ashi;
+pub mod snapshot;
+pub mod audit;
+pub mod timeframe;
+pub mod index;
+pub mod keepalive;
+pub mod connection_shards;
+pub mod endpoint;
+pub mod backfill;
+pub mod dedup;
+pub mod write_ahead;
+pub mod stale_feed;
+pub mod latency;
+pub mod supervisor;
+pub mod backpressure;
+pub mod collector_config;
+pub mod history;
+pub mod downsample;
+pub mod integrity;
+pub mod replay;
+pub mod health;
+pub mod dashboard;
+pub mod broadcast;
+pub mod redis_sink;
+pub mod nats_sink;
+pub mod clickhouse_sink;
+pub mod questdb_sink;
+pub mod parquet_archive;
+pub mod sqlite_sink;
+pub mod storage_sink;
+pub mod mongo_sink;
+pub mod upload;
+pub mod retention;
+pub mod trade_blob;
+#[cfg(feature = "chaos")]
+pub mod chaos;
\ No newline at end of file
@@ -0,0 +1,194 @@
+use crate::db::Database;
+use crate::models::{trade::Trade, trade_candle::TradeCandle};
+use super::dashboard::Dashboard;
+use super::storage_sink::{SinkFanout, StorageSink};
+use super::write_ahead::WriteAheadQueue;
+use async_trait::async_trait;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// キューに溜まったローソク足を DB に書き直す間隔 (障害中は書き込みのたびにタイムアウトを待つため長めに)
+const REPLAY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// ローソク足を MongoDB の時間枠ごとのコレクションに書き込むシンク (約定はローソク足に集計して保存するため書き込まない)
+/// Database の batch_size 件 (または batch_latency ごと) にまとめて書き込み,
+/// write_ahead があれば書き込めなかった足をディスクに溜めて DB の復旧後に書き直す
+pub struct MongoCandleSink {
+    db: Arc<Database>,
+    label: String,  // ログに出す取引所名 (e.g. BYBIT)
+    dashboard: Option<Dashboard>,
+    state: Mutex<CandleQueue>,
+    written: AtomicU64,  // DB に書き込めたローソク足の数 (受け取っただけの足は数えない)
+}
+
+struct CandleQueue {
+    pending: Vec<TradeCandle>,  // まだ書き込んでいないローソク足
+    write_ahead: Option<WriteAheadQueue>,
+    replayed_at: Option<Instant>,  // 最後にキューの足を書き直した時刻
+}
+
+impl CandleQueue {
+    fn enqueue(&mut self, candle: &TradeCandle) {
+        if let Some(queue) = self.write_ahead.as_mut() {
+            if let Err(e) = queue.push(candle) {
+                error!("Failed to queue candle in {}: {}", queue.path().display(), e);
+            }
+        }
+    }
+
+    fn has_queued(&self) -> bool {
+        self.write_ahead.as_ref().is_some_and(|queue| !queue.is_empty())
+    }
+}
+
+impl MongoCandleSink {
+    pub fn new(db: Arc<Database>, exchange: &str) -> Self {
+        Self {
+            db,
+            label: exchange.to_uppercase(),
+            dashboard: None,
+            state: Mutex::new(CandleQueue { pending: Vec::new(), write_ahead: None, replayed_at: None }),
+            written: AtomicU64::new(0),
+        }
+    }
+
+    /// DB 障害中のローソク足を溜めるキュー
+    pub fn with_write_ahead(mut self, write_ahead: WriteAheadQueue) -> Self {
+        self.state.get_mut().write_ahead = Some(write_ahead);
+        self
+    }
+
+    /// 書き込み待ちとキューの足の数をダッシュボードに出す
+    pub fn with_dashboard(mut self, dashboard: Dashboard) -> Self {
+        self.dashboard = Some(dashboard);
+        self
+    }
+
+    /// 足を MongoDB に書き込むシンクだけの SinkFanout (--update なしなら空. backfill などの一括処理で使う)
+    pub fn fanout(db: &Arc<Database>, exchange: &str) -> SinkFanout {
+        let fanout = SinkFanout::new();
+        if !db.is_enabled() {
+            return fanout;
+        }
+        fanout.with_sink(Arc::new(Self::new(db.clone(), exchange)))
+    }
+
+    /// まだ書き込んでいないローソク足をまとめて書き込む
+    async fn write_pending(&self, queue: &mut CandleQueue) -> anyhow::Result<()> {
+        if queue.pending.is_empty() {
+            return Ok(());
+        }
+        let candles = std::mem::take(&mut queue.pending);
+        let result = self.db.insert_trade_candles(&candles).await;
+        if result.is_ok() {
+            self.written.fetch_add(candles.len() as u64, Ordering::Relaxed);
+        }
+        // DB 障害で書き込めなかった足だけを溜める (足そのものの不備は書き直しても失敗するため)
+        if result.is_err() && !self.db.is_healthy() {
+            for candle in &candles {
+                queue.enqueue(candle);
+            }
+        }
+        result
+    }
+
+    /// 溜まっているローソク足を古い順に書き込み, 失敗したところで止めて残りは次の機会に回す
+    async fn replay(&self, queue: &mut CandleQueue) {
+        queue.replayed_at = Some(Instant::now());
+        let Some(write_ahead) = queue.write_ahead.as_mut() else {
+            return;
+        };
+        let candles = match write_ahead.load() {
+            Ok(candles) => candles,
+            Err(e) => {
+                error!("Failed to read {}: {}", write_ahead.path().display(), e);
+                return;
+            }
+        };
+        let mut written = 0;
+        for batch in candles.chunks(self.db.batch_size()) {
+            if let Err(e) = self.db.insert_trade_candles(batch).await {
+                warn!("Replay of queued candles stopped ({} left): {}", candles.len() - written, e);
+                break;
+            }
+            written += batch.len();
+        }
+        if written > 0 {
+            info!("[{}-WAL] Replayed {} queued candles", self.label, written);
+            self.written.fetch_add(written as u64, Ordering::Relaxed);
+        }
+        if let Err(e) = write_ahead.remove_front(written) {
+            error!("Failed to update {}: {}", write_ahead.path().display(), e);
+        }
+    }
+
+    /// 書き込み待ちとキューの足の数をダッシュボードに出す
+    fn report(&self, queue: &CandleQueue) {
+        if let Some(dashboard) = self.dashboard.as_ref() {
+            dashboard.record_writer(queue.pending.len(), queue.write_ahead.as_ref().map_or(0, WriteAheadQueue::len));
+        }
+    }
+}
+
+#[async_trait]
+impl StorageSink for MongoCandleSink {
+    fn name(&self) -> &'static str {
+        "mongodb"
+    }
+
+    fn stores_trades(&self) -> bool {
+        false
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        let mut queue = self.state.lock().await;
+        // 溜まっている足より先に書かないように, キューが空になるまでは後ろに積む (古い revision で上書きしない)
+        let result = if queue.has_queued() {
+            queue.enqueue(candle);
+            Ok(())
+        } else {
+            queue.pending.push(candle.clone());
+            if queue.pending.len() >= self.db.batch_size() {
+                self.write_pending(&mut queue).await
+            } else {
+                Ok(())
+            }
+        };
+        self.report(&queue);
+        result
+    }
+
+    async fn write_trade(&self, _trade: &Trade) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// batch_latency ごとに書き込み, キューに足があれば REPLAY_INTERVAL ごとに書き直す
+    async fn flush(&self) -> anyhow::Result<()> {
+        let mut queue = self.state.lock().await;
+        let result = self.write_pending(&mut queue).await;
+        if queue.has_queued() && queue.replayed_at.is_none_or(|at| at.elapsed() >= REPLAY_INTERVAL) {
+            self.replay(&mut queue).await;
+        }
+        self.report(&queue);
+        result
+    }
+
+    fn flush_interval(&self) -> Option<Duration> {
+        Some(self.db.batch_latency())
+    }
+
+    fn written(&self) -> Option<u64> {
+        Some(self.written.load(Ordering::Relaxed))
+    }
+
+    /// 直近の書き込みが失敗していれば (DB 障害中) エラー, でなければシャード 0 に ping する
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if !self.db.is_healthy() {
+            return Err(anyhow::anyhow!("Writes to MongoDB are failing"));
+        }
+        self.db.ping().await
+    }
+}
@@ -0,0 +1,154 @@
+use crate::models::{market_event::MarketEvent, trade::Trade, trade_candle::TradeCandle};
+use super::storage_sink::StorageSink;
+use super::timeframe;
+use async_trait::async_trait;
+use async_nats::jetstream::{self, context::Publish};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// JetStream への送信を待たせておけるイベント数 (これを超えるとパイプラインが待つ. 捨てない)
+pub const NATS_QUEUE_CAPACITY: usize = 10_000;
+/// ack を待たずに送る最大件数
+const MAX_IN_FLIGHT: usize = 500;
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// JetStream に送る 1 件
+#[derive(Debug, Clone, PartialEq)]
+pub struct NatsRecord {
+    pub subject: String,
+    pub message_id: String,  // Nats-Msg-Id (再送しても stream の重複排除で 1 件になる)
+    pub payload: String,
+}
+
+/// subject の 1 区切りに使えない文字 (., 空白, ワイルドカード) を _ にする
+fn token(value: &str) -> String {
+    value.chars().map(|c| if c == '.' || c == '*' || c == '>' || c.is_whitespace() { '_' } else { c }).collect()
+}
+
+impl NatsRecord {
+    /// 約定: trades.{exchange}.{symbol}, 足: candles.{timeframe}.{symbol} (e.g. candles.1m.BTCUSDT)
+    pub fn from_event(event: &MarketEvent) -> Option<Self> {
+        let record = match event {
+            MarketEvent::Trade(trade) => Self {
+                subject: format!("trades.{}.{}", token(&trade.exchange), token(&trade.symbol)),
+                message_id: format!("{}:{}:{}:{}", trade.exchange, trade.market_type.as_str(), trade.symbol, trade.trade_id),
+                payload: serde_json::to_string(trade).ok()?,
+            },
+            MarketEvent::Candle(candle) => Self {
+                subject: format!("candles.{}.{}", timeframe::label(candle.period_seconds as u32), token(&candle.symbol)),
+                message_id: format!(
+                    "{}:{}:{}:{}:{}:{}",
+                    candle.exchange, candle.market_type.as_str(), candle.symbol, candle.period_seconds,
+                    candle.timestamp.timestamp_millis(), candle.revision
+                ),
+                payload: serde_json::to_string(candle).ok()?,
+            },
+            _ => return None,
+        };
+        Some(record)
+    }
+}
+
+/// 約定・足を NATS JetStream に送るシンク
+/// ack が返るまで再送する (at-least-once). 送信が追いつかなければパイプラインが待つ
+#[derive(Clone)]
+pub struct NatsSink {
+    sender: mpsc::Sender<NatsRecord>,
+}
+
+impl NatsSink {
+    /// 接続して stream (trades.> と candles.>) を用意する. 接続できなければエラー (接続後の切断は自動で再接続する)
+    pub async fn connect(url: &str, stream: &str) -> anyhow::Result<Self> {
+        let client = async_nats::connect(url).await?;
+        let context = jetstream::new(client);
+        context
+            .get_or_create_stream(jetstream::stream::Config {
+                name: stream.to_string(),
+                subjects: vec!["trades.>".to_string(), "candles.>".to_string()],
+                ..Default::default()
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get or create JetStream stream {}: {}", stream, e))?;
+        let (sender, receiver) = mpsc::channel(NATS_QUEUE_CAPACITY);
+        tokio::spawn(write(context, receiver));
+        info!("Publishing trades.{{exchange}}.{{symbol}} and candles.{{timeframe}}.{{symbol}} to JetStream stream {}", stream);
+        Ok(Self { sender })
+    }
+
+    pub async fn publish(&self, event: &MarketEvent) -> anyhow::Result<()> {
+        if let Some(record) = NatsRecord::from_event(event) {
+            self.sender.send(record).await.map_err(|_| anyhow::anyhow!("NATS writer stopped"))?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageSink for NatsSink {
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+
+    fn stores_trades(&self) -> bool {
+        true
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        self.publish(&MarketEvent::Candle(candle.clone())).await
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.publish(&MarketEvent::Trade(trade.clone())).await
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("NATS writer stopped"));
+        }
+        Ok(())
+    }
+}
+
+/// まとめて送ってから ack を待ち, ack のなかった分を返す
+async fn publish_batch(context: &jetstream::Context, batch: Vec<NatsRecord>) -> (Vec<NatsRecord>, Option<String>) {
+    let mut acks = Vec::with_capacity(batch.len());
+    let mut failed = Vec::new();
+    let mut last_error = None;
+    for record in batch {
+        let publish = Publish::build().payload(record.payload.clone().into()).message_id(record.message_id.as_str());
+        match context.send_publish(record.subject.clone(), publish).await {
+            Ok(ack) => acks.push((record, ack)),
+            Err(e) => {
+                last_error = Some(e.to_string());
+                failed.push(record);
+            }
+        }
+    }
+    for (record, ack) in acks {
+        if let Err(e) = ack.await {
+            last_error = Some(e.to_string());
+            failed.push(record);
+        }
+    }
+    (failed, last_error)
+}
+
+async fn write(context: jetstream::Context, mut receiver: mpsc::Receiver<NatsRecord>) {
+    let mut batch = Vec::with_capacity(MAX_IN_FLIGHT);
+    while receiver.recv_many(&mut batch, MAX_IN_FLIGHT).await > 0 {
+        let mut pending = std::mem::take(&mut batch);
+        let mut delay = Duration::from_secs(1);
+        loop {
+            let (failed, last_error) = publish_batch(&context, pending).await;
+            if failed.is_empty() {
+                break;
+            }
+            warn!("{} messages were not acknowledged by JetStream ({}); retrying in {:?}",
+                  failed.len(), last_error.unwrap_or_default(), delay);
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RETRY_DELAY);
+            pending = failed;
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use serde_json::json;
+
+/// アラートなどの通知を webhook (Slack / Discord 互換) と Telegram に送る
+/// 設定は環境変数: ALERT_WEBHOOK_URL, TELEGRAM_BOT_TOKEN + TELEGRAM_CHAT_ID
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    http: reqwest::Client,
+    webhook_url: Option<String>,
+    telegram: Option<(String, String)>,  // (bot token, chat id)
+}
+
+impl Notifier {
+    pub fn from_env() -> Self {
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let telegram = match (env("TELEGRAM_BOT_TOKEN"), env("TELEGRAM_CHAT_ID")) {
+            (Some(token), Some(chat_id)) => Some((token, chat_id)),
+            _ => None,
+        };
+        Self {
+            http: reqwest::Client::new(),
+            webhook_url: env("ALERT_WEBHOOK_URL"),
+            telegram,
+        }
+    }
+
+    pub fn with_webhook(mut self, url: &str) -> Self {
+        self.webhook_url = Some(url.to_string());
+        self
+    }
+
+    pub fn with_telegram(mut self, token: &str, chat_id: &str) -> Self {
+        self.telegram = Some((token.to_string(), chat_id.to_string()));
+        self
+    }
+
+    /// 送信先が 1 つも設定されていない
+    pub fn is_empty(&self) -> bool {
+        self.webhook_url.is_none() && self.telegram.is_none()
+    }
+
+    /// 設定された全ての送信先に送る (失敗した送信先があればエラーを返す)
+    pub async fn send(&self, text: &str) -> anyhow::Result<()> {
+        let mut errors = Vec::new();
+        if let Some(url) = &self.webhook_url {
+            // Slack は text, Discord は content を読む
+            let body = json!({ "text": text, "content": text });
+            if let Err(e) = self.post(url, &body).await {
+                errors.push(format!("webhook: {}", e));
+            }
+        }
+        if let Some((token, chat_id)) = &self.telegram {
+            let url = format!("https://api.telegram.org/bot{}/sendMessage", token);
+            let body = json!({ "chat_id": chat_id, "text": text });
+            if let Err(e) = self.post(&url, &body).await {
+                errors.push(format!("telegram: {}", e));
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("Failed to send notification: {}", errors.join(", ")))
+        }
+    }
+
+    async fn post(&self, url: &str, body: &serde_json::Value) -> anyhow::Result<()> {
+        self.http
+            .post(url)
+            .json(body)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
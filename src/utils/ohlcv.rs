@@ -0,0 +1,104 @@
+use crate::models::trade_candle::TradeCandle;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// OHLCV CSV の書式 (ツールごとのヘッダーと時刻の表し方)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OhlcvFormat {
+    Ccxt,         // timestamp (ミリ秒), open, high, low, close, volume
+    TradingView,  // time (秒), open, high, low, close, Volume
+    Backtrader,   // datetime (YYYY-MM-DD HH:MM:SS), open, high, low, close, volume, openinterest
+}
+
+impl OhlcvFormat {
+    /// 書式: "ccxt", "tradingview", "backtrader"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "ccxt" => Ok(Self::Ccxt),
+            "tradingview" | "tv" => Ok(Self::TradingView),
+            "backtrader" | "bt" => Ok(Self::Backtrader),
+            s => Err(anyhow::anyhow!("Invalid OHLCV format: {}. Use ccxt, tradingview or backtrader", s)),
+        }
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            Self::Ccxt => "timestamp,open,high,low,close,volume",
+            Self::TradingView => "time,open,high,low,close,Volume",
+            Self::Backtrader => "datetime,open,high,low,close,volume,openinterest",
+        }
+    }
+}
+
+/// 一般的な OHLCV の足 (時刻は足の始端, 各ツールの規約に合わせる)
+#[derive(Debug, Clone, PartialEq)]
+pub struct OhlcvBar {
+    pub timestamp: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl OhlcvBar {
+    /// 保存済みのローソク足 (売り買い別の VWAP) から interval_seconds 足の OHLCV を作る
+    /// open / high / low / close は元の足の約定価格の始値・高値・安値・終値
+    /// (記録のない古い足は 1 本を 1 つの価格点とみなし, 始値・終値は VWAP, 高値・安値は売り買いの VWAP の最大 / 最小)
+    /// 同じ時刻の足が複数あれば revision の大きいもの (遅延約定で訂正された足) を使い, 約定のない区間は出力しない
+    pub fn from_candles(candles: &[TradeCandle], interval_seconds: i64) -> Vec<OhlcvBar> {
+        let interval_ms = interval_seconds.max(1) * 1000;
+        let mut latest: BTreeMap<DateTime<Utc>, &TradeCandle> = BTreeMap::new();
+        for candle in candles {
+            match latest.get(&candle.timestamp) {
+                Some(existing) if existing.revision >= candle.revision => {}
+                _ => {
+                    latest.insert(candle.timestamp, candle);
+                }
+            }
+        }
+
+        let mut bars: Vec<OhlcvBar> = Vec::new();
+        for candle in latest.values() {
+            let Some(vwap) = candle.vwap() else {
+                continue;
+            };
+            let prices = [candle.ask_price, candle.bid_price];
+            let high = candle.high.unwrap_or_else(|| prices.iter().flatten().copied().fold(vwap, f64::max));
+            let low = candle.low.unwrap_or_else(|| prices.iter().flatten().copied().fold(vwap, f64::min));
+            let volume = candle.ask_volume + candle.bid_volume;
+            let open = candle.open.unwrap_or(vwap);
+            let close = candle.close.unwrap_or(vwap);
+
+            // 足の時刻は終端なので, 始端を含む区間に入れる
+            let start_ms = candle.timestamp.timestamp_millis() - candle.period_seconds as i64 * 1000;
+            let bucket_ms = start_ms.div_euclid(interval_ms) * interval_ms;
+            let timestamp = DateTime::from_timestamp_millis(bucket_ms).unwrap_or_default();
+            match bars.last_mut() {
+                Some(bar) if bar.timestamp == timestamp => {
+                    bar.high = bar.high.max(high);
+                    bar.low = bar.low.min(low);
+                    bar.close = close;
+                    bar.volume += volume;
+                }
+                _ => bars.push(OhlcvBar { timestamp, open, high, low, close, volume }),
+            }
+        }
+        bars
+    }
+}
+
+/// OHLCV を CSV で書き出す
+pub fn write_csv<W: Write>(writer: &mut W, bars: &[OhlcvBar], format: OhlcvFormat) -> anyhow::Result<()> {
+    writeln!(writer, "{}", format.header())?;
+    for bar in bars {
+        let values = format!("{},{},{},{},{}", bar.open, bar.high, bar.low, bar.close, bar.volume);
+        match format {
+            OhlcvFormat::Ccxt => writeln!(writer, "{},{}", bar.timestamp.timestamp_millis(), values)?,
+            OhlcvFormat::TradingView => writeln!(writer, "{},{}", bar.timestamp.timestamp(), values)?,
+            OhlcvFormat::Backtrader => writeln!(writer, "{},{},0", bar.timestamp.format("%Y-%m-%d %H:%M:%S"), values)?,
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use crate::models::market_type::MarketType;
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+/// 運用イベントの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpsEventKind {
+    Start,
+    Connect,
+    Disconnect,
+    Subscribe,
+    DbOutage,
+    DbRecovered,
+    ConfigReload,
+    Snapshot,  // 計画的な再起動のための状態の書き出し
+    Restore,   // 起動時のスナップショットの読み込み
+    Backfill,  // 再接続時の REST による約定の補完
+    StaleFeed,    // symbol の約定が普段より長く止まった
+    FeedResumed,  // 止まっていた symbol の約定が再開した
+    TaskRestart,  // panic・終了したコンポーネントの再起動 (utils::supervisor)
+    TaskFailed,   // 再起動の上限を超えて諦めた
+    EventsDropped,  // 後段が詰まってイベントを捨てた (utils::backpressure)
+}
+
+impl OpsEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Connect => "connect",
+            Self::Disconnect => "disconnect",
+            Self::Subscribe => "subscribe",
+            Self::DbOutage => "db_outage",
+            Self::DbRecovered => "db_recovered",
+            Self::ConfigReload => "config_reload",
+            Self::Snapshot => "snapshot",
+            Self::Restore => "restore",
+            Self::Backfill => "backfill",
+            Self::StaleFeed => "stale_feed",
+            Self::FeedResumed => "feed_resumed",
+            Self::TaskRestart => "task_restart",
+            Self::TaskFailed => "task_failed",
+            Self::EventsDropped => "events_dropped",
+        }
+    }
+}
+
+/// 運用イベント (ops_events コレクション)
+/// データ異常の事後調査で, 接続・購読・DB 障害などの履歴と突き合わせるために記録する
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpsEvent {
+    pub timestamp: DateTime<Utc>,
+    pub exchange: String,
+    pub market_type: String,
+    pub kind: OpsEventKind,
+    pub detail: String,
+}
+
+impl OpsEvent {
+    pub fn to_document(&self) -> Document {
+        doc! {
+            "unixtime": mongodb::bson::DateTime::from_millis(self.timestamp.timestamp_millis()),
+            "exchange": &self.exchange,
+            "market_type": &self.market_type,
+            "kind": self.kind.as_str(),
+            "detail": &self.detail,
+            "pid": std::process::id() as i64
+        }
+    }
+}
+
+impl fmt::Display for OpsEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} @ {} | {}", self.market_type, self.kind.as_str(), self.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"), self.detail)
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref OPS_EVENTS: Mutex<Option<(String, String, mpsc::UnboundedSender<OpsEvent>)>> = Mutex::new(None);
+}
+
+/// 以降の record() を受け取る receiver を返す (呼び出し側で ops_events に書き込む)
+/// 1 プロセス 1 取引所・市場なので, exchange / market_type はここで固定する
+/// install() されていない間のイベントは捨てる
+pub fn install(exchange: &str, market_type: &MarketType) -> mpsc::UnboundedReceiver<OpsEvent> {
+    install_as(exchange, market_type.as_str())
+}
+
+/// 複数の取引所・市場を集める collector 用 (exchange / market_type はプロセスを表すラベル, e.g. "collector", "mixed")
+pub fn install_as(exchange: &str, market_type: &str) -> mpsc::UnboundedReceiver<OpsEvent> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    *OPS_EVENTS.lock().unwrap() = Some((exchange.to_string(), market_type.to_string(), tx));
+    rx
+}
+
+pub fn record(kind: OpsEventKind, detail: impl Into<String>) {
+    let guard = OPS_EVENTS.lock().unwrap();
+    let Some((exchange, market_type, tx)) = guard.as_ref() else {
+        return;
+    };
+    let _ = tx.send(OpsEvent {
+        timestamp: Utc::now(),
+        exchange: exchange.clone(),
+        market_type: market_type.clone(),
+        kind,
+        detail: detail.into(),
+    });
+}
+
+/// receiver 側のチャネルを閉じる (書き込みタスクは残りのイベントを処理して終了する)
+pub fn uninstall() {
+    *OPS_EVENTS.lock().unwrap() = None;
+}
@@ -0,0 +1,79 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+// f64 を BTreeMap のキーにするためのラッパー
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PriceKey(f64);
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// snapshot + delta で維持するローカル板
+#[derive(Debug, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    /// 数量 0 はその価格の削除
+    pub fn update_bid(&mut self, price: f64, size: f64) {
+        Self::update_side(&mut self.bids, price, size);
+    }
+
+    pub fn update_ask(&mut self, price: f64, size: f64) {
+        Self::update_side(&mut self.asks, price, size);
+    }
+
+    fn update_side(side: &mut BTreeMap<PriceKey, f64>, price: f64, size: f64) {
+        if size == 0.0 {
+            side.remove(&PriceKey(price));
+        } else {
+            side.insert(PriceKey(price), size);
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.iter().next_back().map(|(p, s)| (p.0, *s))
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.iter().next().map(|(p, s)| (p.0, *s))
+    }
+
+    /// 上位 levels 段の数量による板の偏り (-1: 売り優勢 ~ 1: 買い優勢)
+    pub fn imbalance(&self, levels: usize) -> Option<f64> {
+        let bid_size: f64 = self.bids.values().rev().take(levels).sum();
+        let ask_size: f64 = self.asks.values().take(levels).sum();
+        let total = bid_size + ask_size;
+        if total > 0.0 {
+            Some((bid_size - ask_size) / total)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() || self.asks.is_empty()
+    }
+}
@@ -0,0 +1,48 @@
+use super::correlation_store::open_timeseries;
+use super::pair_spread::{SpreadSignal, SpreadStats};
+use chrono::{DateTime, Utc};
+use mongodb::bson::{doc, Document};
+use mongodb::{Collection, Database as MongoDatabase};
+
+/// pairs のシグナルを書き込むコレクション
+pub const PAIR_SIGNAL_COLLECTION: &str = "pair_signals";
+
+/// シグナル 1 つのドキュメント
+/// metadata は (ym, y の symbol_id, x の symbol_id, 窓の分数, バケットの秒数), adf は計算できなければ null
+pub fn to_document(timestamp: DateTime<Utc>, symbol_a: i32, symbol_b: i32, window_minutes: u32, interval_seconds: i64, signal: SpreadSignal, stats: &SpreadStats) -> Document {
+    let ym = timestamp.format("%Y%m").to_string().parse::<i32>().unwrap_or(0);
+    doc! {
+        "unixtime": mongodb::bson::DateTime::from_millis(timestamp.timestamp_millis()),
+        "metadata": {
+            "ym": ym,
+            "symbol_a": symbol_a,
+            "symbol_b": symbol_b,
+            "window": window_minutes as i32,
+            "interval": interval_seconds,
+        },
+        "signal": signal.as_str(),
+        "zscore": stats.zscore,
+        "spread": stats.spread,
+        "hedge_ratio": stats.hedge_ratio,
+        "intercept": stats.intercept,
+        "adf": stats.adf,
+        "count": stats.count as i64,
+    }
+}
+
+/// pairs のシグナルを pair_signals (時系列コレクション) に書き込む
+pub struct PairSignalStore {
+    collection: Collection<Document>,
+}
+
+impl PairSignalStore {
+    pub async fn open(database: &MongoDatabase, collection_name: &str) -> anyhow::Result<Self> {
+        let index = doc! { "metadata.symbol_a": 1, "metadata.symbol_b": 1, "unixtime": 1 };
+        Ok(Self { collection: open_timeseries(database, collection_name, index).await? })
+    }
+
+    pub async fn write(&self, document: Document) -> anyhow::Result<()> {
+        self.collection.insert_one(document).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+/// Engle-Granger (2 変数, 定数項あり) の残差の ADF 統計量の棄却値 (MacKinnon 2010, 有意水準と値)
+pub const ENGLE_GRANGER_CRITICAL_VALUES: [(f64, f64); 3] = [(0.01, -3.90), (0.05, -3.34), (0.10, -3.04)];
+
+/// "ETHUSDT/BTCUSDT,SOLUSDT/ETHUSDT" を (y, x) の組にする (y を x で回帰する)
+pub fn parse_pairs(spec: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut pairs = Vec::new();
+    for pair in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let invalid = || anyhow::anyhow!("Invalid pair: {}. Use <symbol>/<hedge symbol>, e.g. ETHUSDT/BTCUSDT", pair);
+        let (y, x) = pair.split_once('/').ok_or_else(invalid)?;
+        let (y, x) = (y.trim(), x.trim());
+        if y.is_empty() || x.is_empty() || y == x {
+            return Err(invalid());
+        }
+        pairs.push((y.to_string(), x.to_string()));
+    }
+    if pairs.is_empty() {
+        return Err(anyhow::anyhow!("Empty pair list"));
+    }
+    Ok(pairs)
+}
+
+/// y = intercept + slope * x の最小二乗 (2 点未満・x が一定なら None)
+pub fn ols(x: &[f64], y: &[f64]) -> Option<(f64, f64)> {
+    let n = x.len().min(y.len());
+    if n < 2 {
+        return None;
+    }
+    let mean_x = x[..n].iter().sum::<f64>() / n as f64;
+    let mean_y = y[..n].iter().sum::<f64>() / n as f64;
+    let (mut s_xy, mut s_xx) = (0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        s_xy += (a - mean_x) * (b - mean_y);
+        s_xx += (a - mean_x).powi(2);
+    }
+    if s_xx <= 0.0 {
+        return None;
+    }
+    let slope = s_xy / s_xx;
+    Some((mean_y - slope * mean_x, slope))
+}
+
+/// 残差の Dickey-Fuller の t 値 (Δe_t = gamma * e_{t-1} + u_t の gamma. 定数項なし・ラグなし)
+/// 負に大きいほど残差が平均に戻る (Engle-Granger の棄却値と比べる)
+pub fn dickey_fuller(residuals: &[f64]) -> Option<f64> {
+    let n = residuals.len().checked_sub(1)?;
+    if n < 3 {
+        return None;
+    }
+    let (mut s_lag, mut s_cross) = (0.0, 0.0);
+    for window in residuals.windows(2) {
+        s_lag += window[0] * window[0];
+        s_cross += window[0] * (window[1] - window[0]);
+    }
+    if s_lag <= 0.0 {
+        return None;
+    }
+    let gamma = s_cross / s_lag;
+    let sse: f64 = residuals.windows(2).map(|window| (window[1] - window[0] - gamma * window[0]).powi(2)).sum();
+    let se = (sse / (n - 1) as f64 / s_lag).sqrt();
+    (se > 0.0).then(|| gamma / se)
+}
+
+/// 窓の中のペアのヘッジ比率・スプレッド・z スコア・共和分の統計量
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpreadStats {
+    pub count: usize,
+    pub intercept: f64,
+    pub hedge_ratio: f64,  // ln y = intercept + hedge_ratio * ln x
+    pub spread: f64,  // 最後のバケットの残差
+    pub zscore: Option<f64>,  // spread / 残差の標準偏差
+    pub adf: Option<f64>,  // 残差の Dickey-Fuller の t 値
+}
+
+impl SpreadStats {
+    /// 共和分を棄却できる最も小さい有意水準 (どれでも棄却できなければ None)
+    pub fn cointegration_level(&self) -> Option<f64> {
+        let adf = self.adf?;
+        ENGLE_GRANGER_CRITICAL_VALUES.iter().find(|(_, critical)| adf < *critical).map(|(level, _)| *level)
+    }
+}
+
+/// 時間軸に揃えた両方の価格がある組の対数価格を回帰し, 最後の組のスプレッドを返す (3 組未満・価格が 0 以下なら None)
+pub fn spread_stats(x: &[f64], y: &[f64]) -> Option<SpreadStats> {
+    if x.len() != y.len() || x.len() < 3 || x.iter().chain(y).any(|price| *price <= 0.0) {
+        return None;
+    }
+    let log_x: Vec<f64> = x.iter().map(|price| price.ln()).collect();
+    let log_y: Vec<f64> = y.iter().map(|price| price.ln()).collect();
+    let (intercept, hedge_ratio) = ols(&log_x, &log_y)?;
+    let residuals: Vec<f64> = log_x.iter().zip(&log_y).map(|(a, b)| b - intercept - hedge_ratio * a).collect();
+    let spread = *residuals.last()?;
+    // 定数項があるので残差の平均は 0
+    let std = (residuals.iter().map(|e| e * e).sum::<f64>() / (residuals.len() - 2) as f64).sqrt();
+    Some(SpreadStats {
+        count: residuals.len(),
+        intercept,
+        hedge_ratio,
+        spread,
+        zscore: (std > 0.0).then(|| spread / std),
+        adf: dickey_fuller(&residuals),
+    })
+}
+
+/// z スコアが閾値を越えたときのシグナル
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadSignal {
+    EnterLong,   // スプレッドを買う (y を買い x を hedge_ratio だけ売る)
+    EnterShort,  // スプレッドを売る
+    Exit,
+}
+
+impl SpreadSignal {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::EnterLong => "enter_long",
+            Self::EnterShort => "enter_short",
+            Self::Exit => "exit",
+        }
+    }
+}
+
+/// ペアごとの建玉の状態を持ち, z スコアが閾値を越えたときだけシグナルを出す
+/// |z| >= entry で逆向きに入り, 0 の側に戻って |z| <= exit になったら出る
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZScoreTrigger {
+    entry: f64,
+    exit: f64,
+    position: i8,  // 1: long, -1: short, 0: なし
+}
+
+impl ZScoreTrigger {
+    pub fn new(entry: f64, exit: f64) -> anyhow::Result<Self> {
+        if !(entry > 0.0 && exit >= 0.0 && exit < entry) {
+            return Err(anyhow::anyhow!("Z-score thresholds must satisfy 0 <= exit ({}) < entry ({})", exit, entry));
+        }
+        Ok(Self { entry, exit, position: 0 })
+    }
+
+    pub fn update(&mut self, zscore: f64) -> Option<SpreadSignal> {
+        let signal = if zscore >= self.entry && self.position != -1 {
+            self.position = -1;
+            SpreadSignal::EnterShort
+        } else if zscore <= -self.entry && self.position != 1 {
+            self.position = 1;
+            SpreadSignal::EnterLong
+        } else if self.position != 0 && zscore * self.position as f64 >= -self.exit {
+            self.position = 0;
+            SpreadSignal::Exit
+        } else {
+            return None;
+        };
+        Some(signal)
+    }
+}
@@ -0,0 +1,252 @@
+use crate::models::{market_event::MarketEvent, trade::{Side, Trade}, trade_candle::TradeCandle};
+use super::candle_frame;
+use super::storage_sink::StorageSink;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use polars::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+pub const DEFAULT_ROTATE_SECONDS: u64 = 300;
+/// 書き込みを待たせておけるイベント数 (これを超えるとパイプラインが待つ)
+const QUEUE_CAPACITY: usize = 100_000;
+
+/// 圧縮: zstd (既定), snappy, gzip, lz4, none
+pub fn parse_compression(spec: &str) -> anyhow::Result<ParquetCompression> {
+    match spec.trim().to_lowercase().as_str() {
+        "zstd" => Ok(ParquetCompression::Zstd(None)),
+        "snappy" => Ok(ParquetCompression::Snappy),
+        "gzip" => Ok(ParquetCompression::Gzip(None)),
+        "lz4" => Ok(ParquetCompression::Lz4Raw),
+        "none" => Ok(ParquetCompression::Uncompressed),
+        s => Err(anyhow::anyhow!("Invalid compression: {}. Use zstd, snappy, gzip, lz4 or none", s)),
+    }
+}
+
+/// 1 つのファイルにまとめる単位 (exchange, symbol, 日付, 種類, 市場)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PartitionKey {
+    pub exchange: String,
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub kind: &'static str,  // candles / trades
+    pub market_type: &'static str,
+}
+
+impl PartitionKey {
+    /// {dir}/exchange={exchange}/symbol={symbol}/date={YYYY-MM-DD}
+    pub fn directory(&self, dir: &Path) -> PathBuf {
+        dir.join(format!("exchange={}", self.exchange))
+            .join(format!("symbol={}", self.symbol))
+            .join(format!("date={}", self.date.format("%Y-%m-%d")))
+    }
+
+    /// {kind}-{market_type}-{HHMMSS}.parquet (同じ秒に書いたファイルがあれば -1, -2, ..)
+    pub fn file_path(&self, dir: &Path, written_at: DateTime<Utc>) -> PathBuf {
+        let directory = self.directory(dir);
+        let stem = format!("{}-{}-{}", self.kind, self.market_type, written_at.format("%H%M%S"));
+        let mut path = directory.join(format!("{}.parquet", stem));
+        let mut suffix = 0;
+        while path.exists() {
+            suffix += 1;
+            path = directory.join(format!("{}-{}.parquet", stem, suffix));
+        }
+        path
+    }
+}
+
+/// 書き出す前の行
+enum Rows {
+    Candles(Vec<TradeCandle>),
+    Trades(Vec<Trade>),
+}
+
+/// 約定を 1 行 1 件の DataFrame にする (時刻は UTC のミリ秒)
+pub fn trades_dataframe(trades: &[Trade]) -> anyhow::Result<DataFrame> {
+    let datetime = |name: &str, f: fn(&Trade) -> i64| -> PolarsResult<Column> {
+        Ok(Series::new(name.into(), trades.iter().map(f).collect::<Vec<_>>())
+            .cast(&DataType::Datetime(TimeUnit::Milliseconds, Some(TimeZone::UTC)))?
+            .into())
+    };
+    let strings = |name: &str, f: fn(&Trade) -> &str| -> Column { Series::new(name.into(), trades.iter().map(f).collect::<Vec<_>>()).into() };
+    let floats = |name: &str, f: fn(&Trade) -> f64| -> Column { Series::new(name.into(), trades.iter().map(f).collect::<Vec<_>>()).into() };
+    Ok(DataFrame::new(vec![
+        datetime("timestamp", |t| t.timestamp.timestamp_millis())?,
+        datetime("received_at", |t| t.received_at.timestamp_millis())?,
+        strings("exchange", |t| t.exchange.as_str()),
+        strings("market_type", |t| t.market_type.as_str()),
+        strings("symbol", |t| t.symbol.as_str()),
+        strings("trade_id", |t| t.trade_id.as_str()),
+        floats("price", |t| t.price),
+        floats("quantity", |t| t.quantity),
+        strings("side", |t| match t.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }),
+    ])?)
+}
+
+/// 行を Parquet に書く (途中のファイルが読まれないように .tmp に書いてから名前を変える)
+fn write_file(path: &Path, mut df: DataFrame, compression: ParquetCompression) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("parquet.tmp");
+    let file = std::fs::File::create(&tmp)?;
+    ParquetWriter::new(file).with_compression(compression).finish(&mut df)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 約定・足を日付ごとのディレクトリの Parquet ファイルにためて書くアーカイブ
+pub struct ParquetArchive {
+    dir: PathBuf,
+    compression: ParquetCompression,
+    columns: Vec<String>,
+    pending: BTreeMap<PartitionKey, Rows>,
+}
+
+impl ParquetArchive {
+    pub fn new(dir: PathBuf, compression: ParquetCompression) -> Self {
+        let columns = candle_frame::COLUMNS.iter().map(|c| c.to_string()).collect();
+        Self { dir, compression, columns, pending: BTreeMap::new() }
+    }
+
+    /// 書き出していない行数
+    pub fn pending_rows(&self) -> usize {
+        self.pending.values().map(|rows| match rows {
+            Rows::Candles(candles) => candles.len(),
+            Rows::Trades(trades) => trades.len(),
+        }).sum()
+    }
+
+    /// 約定・足をためる (それ以外は無視)
+    pub fn push(&mut self, event: MarketEvent) {
+        match event {
+            MarketEvent::Candle(candle) => {
+                let key = PartitionKey {
+                    exchange: candle.exchange.clone(),
+                    symbol: candle.symbol.clone(),
+                    date: candle.timestamp.date_naive(),
+                    kind: "candles",
+                    market_type: candle.market_type.as_str(),
+                };
+                if let Rows::Candles(candles) = self.pending.entry(key).or_insert_with(|| Rows::Candles(Vec::new())) {
+                    candles.push(candle);
+                }
+            }
+            MarketEvent::Trade(trade) => {
+                let key = PartitionKey {
+                    exchange: trade.exchange.clone(),
+                    symbol: trade.symbol.clone(),
+                    date: trade.timestamp.date_naive(),
+                    kind: "trades",
+                    market_type: trade.market_type.as_str(),
+                };
+                if let Rows::Trades(trades) = self.pending.entry(key).or_insert_with(|| Rows::Trades(Vec::new())) {
+                    trades.push(trade);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// ためた行をまとめて 1 つのファイルずつ書き出し, 書いたファイルを返す (書けなかった分は捨ててエラーを記録する)
+    pub fn rotate(&mut self, written_at: DateTime<Utc>) -> Vec<PathBuf> {
+        let mut written = Vec::new();
+        for (key, rows) in std::mem::take(&mut self.pending) {
+            let path = key.file_path(&self.dir, written_at);
+            let result = match &rows {
+                Rows::Candles(candles) => candle_frame::to_dataframe(candles, &self.columns),
+                Rows::Trades(trades) => trades_dataframe(trades),
+            }
+            .and_then(|df| write_file(&path, df, self.compression));
+            match result {
+                Ok(()) => written.push(path),
+                Err(e) => error!("Failed to write {}: {}", path.display(), e),
+            }
+        }
+        written
+    }
+
+    /// 書き込みのタスクに送るシンクと, rotate_interval ごとに書き出すタスク
+    pub fn start(self, rotate_interval: Duration, trades: bool) -> ArchiveStage {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(write(self, receiver, rotate_interval));
+        ArchiveStage { trades, sender }
+    }
+}
+
+/// ParquetArchive の書き込みのタスクに送るシンク
+#[derive(Clone)]
+pub struct ArchiveStage {
+    trades: bool,
+    sender: mpsc::Sender<MarketEvent>,
+}
+
+impl ArchiveStage {
+    async fn send(&self, event: MarketEvent) -> anyhow::Result<()> {
+        self.sender.send(event).await.map_err(|_| anyhow::anyhow!("Parquet archive writer stopped"))
+    }
+}
+
+#[async_trait]
+impl StorageSink for ArchiveStage {
+    fn name(&self) -> &'static str {
+        "parquet"
+    }
+
+    fn stores_trades(&self) -> bool {
+        self.trades
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        self.send(MarketEvent::Candle(candle.clone())).await
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.send(MarketEvent::Trade(trade.clone())).await
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("Parquet archive writer stopped"));
+        }
+        Ok(())
+    }
+}
+
+/// Parquet の書き出しはブロックするので spawn_blocking で行う
+async fn rotate(archive: ParquetArchive) -> ParquetArchive {
+    let rows = archive.pending_rows();
+    let (archive, written) = tokio::task::spawn_blocking(move || {
+        let mut archive = archive;
+        let written = archive.rotate(Utc::now());
+        (archive, written)
+    })
+    .await
+    .expect("Parquet archive rotation panicked");
+    if !written.is_empty() {
+        info!("Archived {} rows to {} Parquet files", rows, written.len());
+    }
+    archive
+}
+
+async fn write(mut archive: ParquetArchive, mut receiver: mpsc::Receiver<MarketEvent>, rotate_interval: Duration) {
+    let mut ticker = tokio::time::interval(rotate_interval);
+    ticker.tick().await;
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some(event) => archive.push(event),
+                None => break,
+            },
+            _ = ticker.tick() => archive = rotate(archive).await,
+        }
+    }
+    // 残りを書いてから終了する
+    rotate(archive).await;
+}
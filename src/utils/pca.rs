@@ -0,0 +1,107 @@
+use super::correlation_matrix::CorrelationMatrix;
+
+/// Jacobi 法の回転を繰り返す上限 (1 回で全ての非対角要素を回る)
+const MAX_SWEEPS: usize = 100;
+
+/// 対称行列の固有値と固有ベクトル (Jacobi 法. 固有値の大きい順, 固有ベクトルは単位ベクトル)
+pub fn symmetric_eigen(matrix: &[Vec<f64>]) -> Vec<(f64, Vec<f64>)> {
+    let n = matrix.len();
+    let mut a: Vec<Vec<f64>> = matrix.to_vec();
+    let mut v: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    let scale: f64 = a.iter().flatten().map(|x| x * x).sum::<f64>().max(f64::MIN_POSITIVE);
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..n).flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j))).map(|(i, j)| a[i][j] * a[i][j]).sum();
+        if off <= scale * 1e-24 {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+                // a[p][q] を 0 にする回転
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p][k], a[q][k]);
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let mut pairs: Vec<(f64, Vec<f64>)> = (0..n).map(|i| (a[i][i], v.iter().map(|row| row[i]).collect())).collect();
+    pairs.sort_by(|x, y| y.0.total_cmp(&x.0));
+    pairs
+}
+
+/// 主成分 1 つ
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipalComponent {
+    pub eigenvalue: f64,
+    pub explained: f64,  // 説明する分散の割合 (固有値 / symbol 数)
+    pub loadings: Vec<(i32, f64)>,  // symbol ごとの固有ベクトルの成分 (合計が負にならない向き)
+}
+
+/// 相関行列の上位の主成分
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pca {
+    pub symbols: Vec<i32>,  // 分解に使った symbol (昇順)
+    pub dropped: Vec<i32>,  // 相関が計算できないペアがあるので外した symbol
+    pub components: Vec<PrincipalComponent>,
+}
+
+impl Pca {
+    /// 上位 k 個の主成分で説明する分散の割合
+    pub fn cumulative_explained(&self) -> f64 {
+        self.components.iter().map(|component| component.explained).sum()
+    }
+}
+
+/// 相関行列の上位 top_k 個の主成分 (2 つ以上の symbol がなければ None)
+/// 相関が計算できないペアがあれば, そのようなペアの最も多い symbol から外して全てのペアがある行列にする
+pub fn principal_components(matrix: &CorrelationMatrix, top_k: usize) -> Option<Pca> {
+    let mut keep: Vec<usize> = (0..matrix.symbols.len()).collect();
+    let mut dropped = Vec::new();
+    loop {
+        let missing: Vec<usize> = keep.iter().map(|&i| keep.iter().filter(|&&j| matrix.values[i][j].is_none()).count()).collect();
+        let Some((index, &count)) = missing.iter().enumerate().max_by_key(|(index, count)| (**count, std::cmp::Reverse(*index))) else {
+            break;
+        };
+        if count == 0 {
+            break;
+        }
+        dropped.push(matrix.symbols[keep.remove(index)]);
+    }
+    if keep.len() < 2 {
+        return None;
+    }
+    dropped.sort_unstable();
+    let values: Vec<Vec<f64>> = keep.iter().map(|&i| keep.iter().map(|&j| matrix.values[i][j].unwrap_or(0.0)).collect()).collect();
+    let symbols: Vec<i32> = keep.iter().map(|&i| matrix.symbols[i]).collect();
+    let components = symmetric_eigen(&values)
+        .into_iter()
+        .take(top_k)
+        .map(|(eigenvalue, vector)| {
+            let sign = if vector.iter().sum::<f64>() < 0.0 { -1.0 } else { 1.0 };
+            PrincipalComponent {
+                eigenvalue,
+                explained: eigenvalue / symbols.len() as f64,
+                loadings: symbols.iter().zip(&vector).map(|(&symbol_id, value)| (symbol_id, sign * value)).collect(),
+            }
+        })
+        .collect();
+    Some(Pca { symbols, dropped, components })
+}
@@ -0,0 +1,86 @@
+use mongodb::bson::Document;
+use polars::prelude::*;
+
+/// 足 (または気配) の 1 件の価格
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PricePoint {
+    pub timestamp_ms: i64,
+    pub symbol_id: i32,
+    pub price: f64,
+}
+
+/// 足・気配のドキュメントの価格 (ask/bid の中値, 片方しかなければその値. どちらもなければ None)
+pub fn price_point(doc: &Document) -> Option<PricePoint> {
+    let symbol_id = doc.get_document("metadata").ok()?.get_i32("symbol").ok()?;
+    let timestamp_ms = doc.get_datetime("unixtime").ok()?.timestamp_millis();
+    let price = match (doc.get_f64("ask_price").ok(), doc.get_f64("bid_price").ok()) {
+        (Some(ask), Some(bid)) => (ask + bid) / 2.0,
+        (Some(ask), None) => ask,
+        (None, Some(bid)) => bid,
+        (None, None) => return None,
+    };
+    Some(PricePoint { timestamp_ms, symbol_id, price })
+}
+
+fn points_frame(points: &[PricePoint]) -> PolarsResult<DataFrame> {
+    DataFrame::new(vec![
+        Series::new("timestamp".into(), points.iter().map(|point| point.timestamp_ms).collect::<Vec<_>>()).into(),
+        Series::new("symbol_id".into(), points.iter().map(|point| point.symbol_id).collect::<Vec<_>>()).into(),
+        Series::new("price".into(), points.iter().map(|point| point.price).collect::<Vec<_>>()).into(),
+    ])
+}
+
+/// 窓の中の価格を縦持ち (timestamp, symbol_id, price) の DataFrame で保持する
+/// change stream で届いた分は append で後ろに足し, trim で窓より古い行を落とす
+/// 同じ (symbol, 時刻) の足が書き直された場合は後から足した行が resample_long で使われる
+pub struct PriceWindow {
+    df: DataFrame,
+}
+
+impl Default for PriceWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PriceWindow {
+    pub fn new() -> Self {
+        Self { df: points_frame(&[]).expect("empty price frame") }
+    }
+
+    /// 窓の中身を読み直した価格で置き換える
+    pub fn replace(&mut self, points: &[PricePoint]) -> anyhow::Result<()> {
+        let mut points = points.to_vec();
+        points.sort_by_key(|point| point.timestamp_ms);
+        self.df = points_frame(&points)?;
+        Ok(())
+    }
+
+    pub fn append(&mut self, points: &[PricePoint]) -> anyhow::Result<()> {
+        if !points.is_empty() {
+            self.df.vstack_mut_owned(points_frame(points)?)?;
+        }
+        Ok(())
+    }
+
+    /// start_ms より前の行を落とす
+    pub fn trim(&mut self, start_ms: i64) -> anyhow::Result<()> {
+        let mask = self.df.column("timestamp")?.i64()?.gt_eq(start_ms);
+        self.df = self.df.filter(&mask)?;
+        self.df.as_single_chunk_par();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.df.height()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.df.height() == 0
+    }
+
+    /// 縦持ちの DataFrame (resample_long の入力)
+    pub fn frame(&self) -> &DataFrame {
+        &self.df
+    }
+}
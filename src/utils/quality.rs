@@ -0,0 +1,283 @@
+use crate::models::{market_type::MarketType, trade::Trade, trade_candle::TradeCandle};
+use chrono::{DateTime, Duration, Utc};
+use mongodb::bson::Document;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::time::interval;
+
+const DUPLICATE_WINDOW: usize = 10_000;  // symbol ごとに保持する直近の trade_id 数
+const DEFAULT_GAP_SECONDS: i64 = 60;  // これ以上約定が途切れたら欠損とみなす
+
+// パース失敗はクライアント内部で発生するため取引所ごとのグローバルカウンタで集計する
+lazy_static::lazy_static! {
+    static ref PARSE_FAILURES: Mutex<HashMap<String, u64>> = Mutex::new(HashMap::new());
+    static ref SEQUENCE_GAPS: Mutex<HashMap<String, (u64, u64)>> = Mutex::new(HashMap::new());  // (欠番の回数, 欠けた約定数)
+}
+
+pub fn record_parse_failure(exchange: &str) {
+    *PARSE_FAILURES.lock().unwrap().entry(exchange.to_string()).or_default() += 1;
+}
+
+fn take_parse_failures(exchange: &str) -> u64 {
+    PARSE_FAILURES.lock().unwrap().remove(exchange).unwrap_or(0)
+}
+
+/// 連番の約定 ID の欠番 (missing 件の約定が届かなかった)
+pub fn record_sequence_gap(exchange: &str, missing: u64) {
+    let mut gaps = SEQUENCE_GAPS.lock().unwrap();
+    let (count, total) = gaps.entry(exchange.to_string()).or_default();
+    *count += 1;
+    *total += missing;
+}
+
+fn take_sequence_gaps(exchange: &str) -> (u64, u64) {
+    SEQUENCE_GAPS.lock().unwrap().remove(exchange).unwrap_or((0, 0))
+}
+
+#[derive(Debug, Default)]
+struct SymbolQuality {
+    trades: u64,
+    duplicates: u64,
+    gaps: u64,
+    max_gap: Duration,
+    last_trade: Option<DateTime<Utc>>,
+    candles: u64,
+    recent_ids: HashSet<String>,
+    recent_order: VecDeque<String>,
+}
+
+/// symbol ごとの品質指標 (1日分)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolQualityReport {
+    pub symbol: String,
+    pub trades: u64,
+    pub duplicates: u64,
+    pub duplicate_rate: f64,
+    pub gaps: u64,  // gap_threshold を超えて約定が途切れた回数
+    pub max_gap_seconds: f64,
+    pub candles: u64,
+    pub candle_coverage_pct: f64,  // 最小時間枠のローソク足が出力された割合
+}
+
+/// 取引所・市場ごとの日次品質レポート (quality_reports コレクション)
+/// プロセスが途中で再起動した場合は started_at 以降のみを集計する
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub exchange: String,
+    pub market_type: String,
+    pub date: String,  // UTC の日付 (YYYY-MM-DD)
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub uptime_pct: f64,  // いずれかの symbol で約定を受信していた時間の割合
+    pub parse_failures: u64,
+    #[serde(default)]
+    pub sequence_gaps: u64,  // 連番の約定 ID の欠番を検知した回数 (Binance aggTrade, REST で補完する)
+    #[serde(default)]
+    pub missing_trades: u64,  // 欠番で届かなかった約定数
+    pub candle_period_seconds: u32,
+    pub symbols: Vec<SymbolQualityReport>,
+}
+
+impl QualityReport {
+    pub fn to_document(&self) -> anyhow::Result<Document> {
+        let mut doc = mongodb::bson::to_document(self)?;
+        // 期間は Date 型で保存する
+        doc.insert("started_at", mongodb::bson::DateTime::from_millis(self.started_at.timestamp_millis()));
+        doc.insert("ended_at", mongodb::bson::DateTime::from_millis(self.ended_at.timestamp_millis()));
+        Ok(doc)
+    }
+
+    pub fn from_document(mut doc: Document) -> anyhow::Result<Self> {
+        for key in ["started_at", "ended_at"] {
+            let millis = doc.get_datetime(key)?.timestamp_millis();
+            let value = DateTime::from_timestamp_millis(millis).unwrap_or_default().to_rfc3339();
+            doc.insert(key, value);
+        }
+        Ok(mongodb::bson::from_document(doc)?)
+    }
+}
+
+impl fmt::Display for QualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "[QUALITY] {} {} {} ({} - {}) | Uptime: {:.2}% | Parse failures: {} | Sequence gaps: {} ({} trades)",
+            self.exchange, self.market_type.to_uppercase(), self.date,
+            self.started_at.format("%H:%M:%S"), self.ended_at.format("%H:%M:%S"),
+            self.uptime_pct, self.parse_failures, self.sequence_gaps, self.missing_trades
+        )?;
+        for s in &self.symbols {
+            writeln!(
+                f,
+                "  {:<14} Trades:{:>10} Dup:{:>6} ({:.4}%) Gaps:{:>4} MaxGap:{:>8.1}s Coverage({}s):{:>7.2}%",
+                s.symbol, s.trades, s.duplicates, s.duplicate_rate * 100.0,
+                s.gaps, s.max_gap_seconds, self.candle_period_seconds, s.candle_coverage_pct
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 約定・ローソク足の流れから受信品質を集計する (TradeCandleBuilder から記録される)
+pub struct QualityTracker {
+    exchange: String,
+    market_type: MarketType,
+    candle_period_seconds: u32,
+    gap_threshold: Duration,
+    started_at: DateTime<Utc>,
+    last_message: Option<DateTime<Utc>>,
+    downtime: Duration,
+    symbols: HashMap<String, SymbolQuality>,
+}
+
+impl QualityTracker {
+    pub fn new(exchange: &str, market_type: MarketType, candle_period_seconds: u32) -> Self {
+        Self {
+            exchange: exchange.to_string(),
+            market_type,
+            candle_period_seconds,
+            gap_threshold: Duration::seconds(DEFAULT_GAP_SECONDS),
+            started_at: Utc::now(),
+            last_message: None,
+            downtime: Duration::zero(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn with_gap_threshold(mut self, gap_threshold: Duration) -> Self {
+        self.gap_threshold = gap_threshold;
+        self
+    }
+
+    pub fn record_trade(&mut self, trade: &Trade) {
+        // 受信時刻で計測する (取引所のタイムスタンプは遅延・逆転があるため)
+        let now = Utc::now();
+        let feed_gap = now - self.last_message.unwrap_or(self.started_at);
+        if feed_gap > self.gap_threshold {
+            self.downtime += feed_gap;
+        }
+        self.last_message = Some(now);
+
+        let quality = self.symbols.entry(trade.symbol.clone()).or_default();
+        quality.trades += 1;
+        if let Some(last) = quality.last_trade {
+            let gap = now - last;
+            if gap > self.gap_threshold {
+                quality.gaps += 1;
+            }
+            quality.max_gap = quality.max_gap.max(gap);
+        }
+        quality.last_trade = Some(now);
+
+        if !quality.recent_ids.insert(trade.trade_id.clone()) {
+            quality.duplicates += 1;
+            return;
+        }
+        quality.recent_order.push_back(trade.trade_id.clone());
+        if quality.recent_order.len() > DUPLICATE_WINDOW {
+            if let Some(old) = quality.recent_order.pop_front() {
+                quality.recent_ids.remove(&old);
+            }
+        }
+    }
+
+    /// TradeDedup が捨てた重複 (受信した約定としても数える)
+    pub fn record_duplicate(&mut self, trade: &Trade) {
+        let quality = self.symbols.entry(trade.symbol.clone()).or_default();
+        quality.trades += 1;
+        quality.duplicates += 1;
+    }
+
+    pub fn record_candle(&mut self, candle: &TradeCandle) {
+        if candle.period_seconds as u32 == self.candle_period_seconds {
+            self.symbols.entry(candle.symbol.clone()).or_default().candles += 1;
+        }
+    }
+
+    /// started_at から ended_at までのレポートを作成する (パース失敗数はここで取り出してリセットする)
+    fn report(&self, ended_at: DateTime<Utc>) -> QualityReport {
+        let elapsed = ended_at - self.started_at;
+        let mut downtime = self.downtime;
+        let tail_gap = ended_at - self.last_message.unwrap_or(self.started_at);
+        if tail_gap > self.gap_threshold {
+            downtime += tail_gap;
+        }
+        let uptime_pct = if elapsed > Duration::zero() {
+            (1.0 - downtime.num_milliseconds() as f64 / elapsed.num_milliseconds() as f64).max(0.0) * 100.0
+        } else {
+            0.0
+        };
+        let expected_candles = (elapsed.num_seconds() / self.candle_period_seconds.max(1) as i64).max(1) as f64;
+
+        let mut symbols: Vec<SymbolQualityReport> = self
+            .symbols
+            .iter()
+            .map(|(symbol, q)| SymbolQualityReport {
+                symbol: symbol.clone(),
+                trades: q.trades,
+                duplicates: q.duplicates,
+                duplicate_rate: if q.trades > 0 { q.duplicates as f64 / q.trades as f64 } else { 0.0 },
+                gaps: q.gaps,
+                max_gap_seconds: q.max_gap.num_milliseconds() as f64 / 1000.0,
+                candles: q.candles,
+                candle_coverage_pct: (q.candles as f64 / expected_candles * 100.0).min(100.0),
+            })
+            .collect();
+        symbols.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        let (sequence_gaps, missing_trades) = take_sequence_gaps(&self.exchange);
+
+        QualityReport {
+            exchange: self.exchange.clone(),
+            market_type: self.market_type.as_str().to_string(),
+            date: self.started_at.format("%Y-%m-%d").to_string(),
+            started_at: self.started_at,
+            ended_at,
+            uptime_pct,
+            parse_failures: take_parse_failures(&self.exchange),
+            sequence_gaps,
+            missing_trades,
+            candle_period_seconds: self.candle_period_seconds,
+            symbols,
+        }
+    }
+
+    /// UTC の日付が変わっていれば前日分のレポートを返して集計をリセットする
+    pub fn roll(&mut self, now: DateTime<Utc>) -> Option<QualityReport> {
+        if now.date_naive() == self.started_at.date_naive() {
+            return None;
+        }
+
+        let midnight = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let report = self.report(midnight);
+
+        self.started_at = midnight;
+        self.last_message = self.last_message.map(|t| t.max(midnight));
+        self.downtime = Duration::zero();
+        for quality in self.symbols.values_mut() {
+            quality.trades = 0;
+            quality.duplicates = 0;
+            quality.gaps = 0;
+            quality.max_gap = Duration::zero();
+            quality.candles = 0;
+        }
+        Some(report)
+    }
+
+    /// 日付の切り替わりを監視し, 日次レポートを送信する
+    pub async fn start_daily(tracker: Arc<Mutex<Self>>, report_sender: mpsc::Sender<QualityReport>) {
+        let mut interval = interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let report = tracker.lock().unwrap().roll(Utc::now());
+            if let Some(report) = report {
+                if report_sender.send(report).await.is_err() {
+                    tracing::error!("Quality report receiver dropped");
+                    return;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::models::{trade::{Side, Trade}, trade_candle::TradeCandle};
+use super::storage_sink::StorageSink;
+use super::timeframe;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::fmt::Write as _;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// 書き込みを待たせておけるイベント数 (これを超えるとパイプラインが待つ)
+const QUEUE_CAPACITY: usize = 100_000;
+/// 1 回にまとめて書く最大行数
+const MAX_BATCH: usize = 5_000;
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// 約定のテーブル (ILP でも同じ型で自動作成されるが, SYMBOL の列と日ごとのパーティションを明示する)
+pub const TRADES_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS trades (\
+timestamp TIMESTAMP, exchange SYMBOL, market_type SYMBOL, symbol SYMBOL, side SYMBOL, \
+trade_id VARCHAR, price DOUBLE, quantity DOUBLE, received_at TIMESTAMP\
+) TIMESTAMP(timestamp) PARTITION BY DAY WAL";
+
+/// 足のテーブル (同じ足の revision は最新の行で上書きされる)
+pub const CANDLES_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS candles (\
+timestamp TIMESTAMP, exchange SYMBOL, market_type SYMBOL, symbol SYMBOL, timeframe SYMBOL, period_seconds LONG, \
+open DOUBLE, high DOUBLE, low DOUBLE, close DOUBLE, \
+ask_price DOUBLE, ask_volume DOUBLE, ask_notional DOUBLE, ask_count LONG, \
+bid_price DOUBLE, bid_volume DOUBLE, bid_notional DOUBLE, bid_count LONG, \
+cvd DOUBLE, realized_vol DOUBLE, direction_changes LONG, warmup BOOLEAN, revision LONG\
+) TIMESTAMP(timestamp) PARTITION BY DAY WAL DEDUP UPSERT KEYS(timestamp, exchange, market_type, symbol, timeframe)";
+
+/// タグの値のエスケープ (, = 空白)
+fn tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 文字列のフィールドのエスケープ (" と \)
+fn string_field(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn nanos(time: DateTime<Utc>) -> i64 {
+    time.timestamp_nanos_opt().unwrap_or(time.timestamp_micros() * 1000)
+}
+
+/// 約定 1 件の ILP の行 (末尾の改行を含む)
+pub fn trade_line(trade: &Trade) -> String {
+    let side = match trade.side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    };
+    format!(
+        "trades,exchange={},market_type={},symbol={},side={} trade_id={},price={:?},quantity={:?},received_at={}t {}\n",
+        tag(&trade.exchange), trade.market_type.as_str(), tag(&trade.symbol), side,
+        string_field(&trade.trade_id), trade.price, trade.quantity, trade.received_at.timestamp_micros(), nanos(trade.timestamp)
+    )
+}
+
+/// 足 1 本の ILP の行 (約定のない足の価格は書かない = NULL)
+pub fn candle_line(candle: &TradeCandle) -> String {
+    let mut line = format!(
+        "candles,exchange={},market_type={},symbol={},timeframe={} period_seconds={}i",
+        tag(&candle.exchange), candle.market_type.as_str(), tag(&candle.symbol),
+        timeframe::label(candle.period_seconds as u32), candle.period_seconds
+    );
+    let prices = [
+        ("open", candle.open), ("high", candle.high), ("low", candle.low), ("close", candle.close),
+        ("ask_price", candle.ask_price), ("bid_price", candle.bid_price), ("realized_vol", candle.realized_vol),
+    ];
+    for (name, value) in prices {
+        if let Some(value) = value.filter(|v| v.is_finite()) {
+            let _ = write!(line, ",{}={:?}", name, value);
+        }
+    }
+    let _ = writeln!(
+        line,
+        ",ask_volume={:?},ask_notional={:?},ask_count={}i,bid_volume={:?},bid_notional={:?},bid_count={}i,cvd={:?},direction_changes={}i,warmup={},revision={}i {}",
+        candle.ask_volume, candle.ask_notional, candle.ask_count, candle.bid_volume, candle.bid_notional, candle.bid_count,
+        candle.cvd, candle.direction_changes, candle.warmup, candle.revision, nanos(candle.timestamp)
+    );
+    line
+}
+
+/// QuestDB への書き込みの設定
+#[derive(Debug, Clone)]
+pub struct QuestDbConfig {
+    pub addr: String,              // ILP (TCP) の接続先 (e.g. localhost:9009)
+    pub http_url: Option<String>,  // テーブルを作成する REST API (e.g. http://localhost:9000)
+    pub trades: bool,              // 約定も書く
+}
+
+/// 約定・足を QuestDB に InfluxDB line protocol (TCP) で書くシンク
+#[derive(Clone)]
+pub struct QuestDbSink {
+    trades: bool,
+    sender: mpsc::Sender<String>,
+}
+
+impl QuestDbSink {
+    /// (http_url があれば) テーブルを作成し, ILP で接続する. 接続できなければエラー (接続後の切断は再接続する)
+    pub async fn connect(config: QuestDbConfig) -> anyhow::Result<Self> {
+        if let Some(http_url) = &config.http_url {
+            let http = reqwest::Client::new();
+            let tables = if config.trades { vec![TRADES_TABLE_SQL, CANDLES_TABLE_SQL] } else { vec![CANDLES_TABLE_SQL] };
+            for sql in tables {
+                let response = http.get(format!("{}/exec", http_url.trim_end_matches('/'))).query(&[("query", sql)]).send().await?;
+                if !response.status().is_success() {
+                    let status = response.status();
+                    return Err(anyhow::anyhow!("QuestDB returned {}: {}", status, response.text().await.unwrap_or_default().trim()));
+                }
+            }
+        }
+        let stream = TcpStream::connect(&config.addr).await?;
+        info!("Writing candles{} to QuestDB over ILP at {}", if config.trades { " and trades" } else { "" }, config.addr);
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(write(config.addr.clone(), stream, receiver));
+        Ok(Self { trades: config.trades, sender })
+    }
+
+    async fn send(&self, line: String) -> anyhow::Result<()> {
+        self.sender.send(line).await.map_err(|_| anyhow::anyhow!("QuestDB writer stopped"))
+    }
+}
+
+#[async_trait]
+impl StorageSink for QuestDbSink {
+    fn name(&self) -> &'static str {
+        "questdb"
+    }
+
+    /// --questdb-skip-trades でなければ
+    fn stores_trades(&self) -> bool {
+        self.trades
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        self.send(candle_line(candle)).await
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.send(trade_line(trade)).await
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("QuestDB writer stopped"));
+        }
+        Ok(())
+    }
+}
+
+/// 溜まっている行をまとめて書く. 書けなければ再接続して同じ行をもう一度だけ書く (ILP over TCP には応答がない)
+/// 再接続できるまではパイプラインが待つ
+async fn write(addr: String, stream: TcpStream, mut receiver: mpsc::Receiver<String>) {
+    let mut stream = Some(stream);
+    let mut lines = Vec::with_capacity(MAX_BATCH);
+    while receiver.recv_many(&mut lines, MAX_BATCH).await > 0 {
+        let batch = lines.concat();
+        lines.clear();
+        let mut failures = 0;
+        let mut delay = Duration::from_secs(1);
+        loop {
+            if stream.is_none() {
+                match TcpStream::connect(&addr).await {
+                    Ok(connected) => {
+                        info!("Reconnected to QuestDB at {}", addr);
+                        stream = Some(connected);
+                    }
+                    Err(e) => {
+                        warn!("Failed to reconnect to QuestDB at {}: {}; retrying in {:?}", addr, e, delay);
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                        continue;
+                    }
+                }
+            }
+            let Some(connection) = stream.as_mut() else { continue };
+            match connection.write_all(batch.as_bytes()).await {
+                Ok(()) => break,
+                Err(e) => {
+                    stream = None;
+                    failures += 1;
+                    if failures >= 2 {
+                        error!("Dropped {} bytes for QuestDB: {}", batch.len(), e);
+                        break;
+                    }
+                    warn!("Failed to write to QuestDB: {}; reconnecting", e);
+                }
+            }
+        }
+    }
+    if let Some(mut connection) = stream {
+        let _ = connection.shutdown().await;
+    }
+}
@@ -0,0 +1,142 @@
+/// 相関係数の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorrelationMethod {
+    Pearson,   // 値そのもの (外れ値に弱い)
+    Spearman,  // 順位のピアソン
+    Kendall,   // tau-b (同順位を補正)
+}
+
+impl CorrelationMethod {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "pearson" => Ok(Self::Pearson),
+            "spearman" => Ok(Self::Spearman),
+            "kendall" => Ok(Self::Kendall),
+            s => Err(anyhow::anyhow!("Invalid correlation method: {}. Use pearson, spearman or kendall", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pearson => "pearson",
+            Self::Spearman => "spearman",
+            Self::Kendall => "kendall",
+        }
+    }
+
+    /// 両方の値がある組の相関 (2 組未満・どちらかが一定なら None)
+    pub fn correlation(&self, x: &[f64], y: &[f64]) -> Option<f64> {
+        match self {
+            Self::Pearson => pearson(x, y),
+            Self::Spearman => spearman(x, y),
+            Self::Kendall => kendall(x, y),
+        }
+    }
+}
+
+pub fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len().min(y.len());
+    if n < 2 {
+        return None;
+    }
+    let mean_x = x[..n].iter().sum::<f64>() / n as f64;
+    let mean_y = y[..n].iter().sum::<f64>() / n as f64;
+    let (mut c_xy, mut m2_x, mut m2_y) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        c_xy += (a - mean_x) * (b - mean_y);
+        m2_x += (a - mean_x).powi(2);
+        m2_y += (b - mean_y).powi(2);
+    }
+    if m2_x <= 0.0 || m2_y <= 0.0 {
+        return None;
+    }
+    Some((c_xy / (m2_x * m2_y).sqrt()).clamp(-1.0, 1.0))
+}
+
+/// 1 始まりの順位 (同じ値は平均の順位)
+pub fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[a].total_cmp(&values[b]));
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        // start..end が同じ値 (順位 start + 1 から end の平均)
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &index in &order[start..end] {
+            ranks[index] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+pub fn spearman(x: &[f64], y: &[f64]) -> Option<f64> {
+    pearson(&ranks(x), &ranks(y))
+}
+
+/// 同じ値の組の数 (ソート済みの列の連続する同じ値から n(n-1)/2 を足す)
+fn tied_pairs<T: PartialEq>(sorted: &[T]) -> u64 {
+    let mut ties = 0;
+    let mut start = 0;
+    while start < sorted.len() {
+        let mut end = start + 1;
+        while end < sorted.len() && sorted[end] == sorted[start] {
+            end += 1;
+        }
+        let run = (end - start) as u64;
+        ties += run * (run - 1) / 2;
+        start = end;
+    }
+    ties
+}
+
+/// y で安定なマージソートをし, 入れ替えた回数 (y の逆順の組の数) を返す
+fn merge_sort_swaps(values: &mut [(f64, f64)], buffer: &mut Vec<(f64, f64)>) -> u64 {
+    let n = values.len();
+    if n < 2 {
+        return 0;
+    }
+    let middle = n / 2;
+    let mut swaps = merge_sort_swaps(&mut values[..middle], buffer) + merge_sort_swaps(&mut values[middle..], buffer);
+    buffer.clear();
+    let (mut left, mut right) = (0, middle);
+    while left < middle && right < n {
+        if values[left].1 <= values[right].1 {
+            buffer.push(values[left]);
+            left += 1;
+        } else {
+            buffer.push(values[right]);
+            swaps += (middle - left) as u64;
+            right += 1;
+        }
+    }
+    buffer.extend_from_slice(&values[left..middle]);
+    buffer.extend_from_slice(&values[right..n]);
+    values.copy_from_slice(buffer);
+    swaps
+}
+
+/// Kendall の tau-b (Knight のアルゴリズム, O(n log n))
+pub fn kendall(x: &[f64], y: &[f64]) -> Option<f64> {
+    let mut pairs: Vec<(f64, f64)> = x.iter().copied().zip(y.iter().copied()).collect();
+    let n = pairs.len() as u64;
+    if n < 2 {
+        return None;
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    let total = n * (n - 1) / 2;
+    let ties_x = tied_pairs(&pairs.iter().map(|pair| pair.0).collect::<Vec<_>>());
+    let ties_xy = tied_pairs(&pairs);
+    let swaps = merge_sort_swaps(&mut pairs, &mut Vec::with_capacity(n as usize));
+    let ties_y = tied_pairs(&pairs.iter().map(|pair| pair.1).collect::<Vec<_>>());
+    if ties_x == total || ties_y == total {
+        return None;
+    }
+    let score = total as f64 - ties_x as f64 - ties_y as f64 + ties_xy as f64 - 2.0 * swaps as f64;
+    let norm = ((total - ties_x) as f64 * (total - ties_y) as f64).sqrt();
+    Some((score / norm).clamp(-1.0, 1.0))
+}
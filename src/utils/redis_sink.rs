@@ -0,0 +1,148 @@
+use crate::models::{market_event::MarketEvent, trade::Trade, trade_candle::TradeCandle};
+use super::storage_sink::StorageSink;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Redis への送信を待たせておけるイベント数 (Redis が遅い・落ちている間はこれを超えた分を捨てる)
+pub const REDIS_QUEUE_CAPACITY: usize = 10_000;
+/// stream ごとに残す足の数 (おおよそ, 0 なら削らない)
+pub const DEFAULT_STREAM_MAX_LEN: usize = 10_000;
+/// 1 回のパイプラインで送る最大件数
+const MAX_PIPELINE: usize = 500;
+
+/// Redis に送る 1 件
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedisRecord {
+    /// 足は symbol ごとの stream に XADD する
+    Stream { key: String, fields: Vec<(&'static str, String)> },
+    /// 約定は symbol ごとの channel に PUBLISH する
+    Publish { channel: String, message: String },
+}
+
+impl RedisRecord {
+    /// 足: {prefix}:candles:{exchange}:{market_type}:{symbol} (period, revision, data), 約定: {prefix}:trades:{exchange}:{market_type}:{symbol}
+    pub fn from_event(prefix: &str, event: &MarketEvent) -> Option<Self> {
+        let record = match event {
+            MarketEvent::Trade(trade) => Self::Publish {
+                channel: format!("{}:trades:{}:{}:{}", prefix, trade.exchange, trade.market_type.as_str(), trade.symbol),
+                message: serde_json::to_string(trade).ok()?,
+            },
+            MarketEvent::Candle(candle) => Self::Stream {
+                key: format!("{}:candles:{}:{}:{}", prefix, candle.exchange, candle.market_type.as_str(), candle.symbol),
+                fields: vec![
+                    ("period", candle.period_seconds.to_string()),
+                    ("revision", candle.revision.to_string()),
+                    ("data", serde_json::to_string(candle).ok()?),
+                ],
+            },
+            _ => return None,
+        };
+        Some(record)
+    }
+
+    fn append_to(&self, pipe: &mut redis::Pipeline, max_len: usize) {
+        match self {
+            Self::Stream { key, fields } => {
+                let command = pipe.cmd("XADD").arg(key);
+                if max_len > 0 {
+                    command.arg("MAXLEN").arg("~").arg(max_len);
+                }
+                command.arg("*");
+                for (field, value) in fields {
+                    command.arg(*field).arg(value);
+                }
+                command.ignore();
+            }
+            Self::Publish { channel, message } => {
+                pipe.cmd("PUBLISH").arg(channel).arg(message).ignore();
+            }
+        }
+    }
+}
+
+/// 足を Redis Streams に, 約定を pub/sub に送るシンク
+/// 送信は別のタスクで行い, パイプラインは Redis を待たない
+#[derive(Clone)]
+pub struct RedisSink {
+    prefix: Arc<str>,
+    sender: mpsc::Sender<RedisRecord>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl RedisSink {
+    /// 接続できなければエラー (接続後の切断は自動で再接続する)
+    pub async fn connect(url: &str, prefix: &str, max_len: usize) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        let (sender, receiver) = mpsc::channel(REDIS_QUEUE_CAPACITY);
+        tokio::spawn(write(connection, receiver, max_len));
+        info!("Sending candles to Redis streams {}:candles:* (max length {}) and trades to channels {}:trades:*", prefix, max_len, prefix);
+        Ok(Self { prefix: prefix.into(), sender, dropped: Arc::new(AtomicU64::new(0)) })
+    }
+
+    /// 捨てたイベントの数
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn publish(&self, event: &MarketEvent) {
+        let Some(record) = RedisRecord::from_event(&self.prefix, event) else {
+            return;
+        };
+        if self.sender.try_send(record).is_err() {
+            let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped == 1 || dropped % 1000 == 0 {
+                warn!("Redis sink is behind; dropped {} events so far", dropped);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl StorageSink for RedisSink {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    fn stores_trades(&self) -> bool {
+        true
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        self.publish(&MarketEvent::Candle(candle.clone()));
+        Ok(())
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.publish(&MarketEvent::Trade(trade.clone()));
+        Ok(())
+    }
+
+    /// 捨てたイベントは書き込みの失敗にしない (best-effort). 送信のタスクが止まっていれば失敗
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("Redis writer stopped"));
+        }
+        Ok(())
+    }
+}
+
+/// 溜まっている分をまとめて 1 つのパイプラインで送る (失敗した分は捨てる)
+async fn write(mut connection: ConnectionManager, mut receiver: mpsc::Receiver<RedisRecord>, max_len: usize) {
+    let mut batch = Vec::with_capacity(MAX_PIPELINE);
+    while receiver.recv_many(&mut batch, MAX_PIPELINE).await > 0 {
+        let mut pipe = redis::pipe();
+        for record in &batch {
+            record.append_to(&mut pipe, max_len);
+        }
+        let result: redis::RedisResult<()> = pipe.query_async(&mut connection).await;
+        if let Err(e) = result {
+            error!("Failed to send {} records to Redis: {}", batch.len(), e);
+        }
+        batch.clear();
+    }
+}
@@ -0,0 +1,273 @@
+use crate::models::brick::Brick;
+use crate::models::market_event::MarketEvent;
+use crate::models::market_type::MarketType;
+use crate::models::trade::Trade;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// ブリックの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrickKind {
+    Renko,  // 直前のブリックの終値から size (反転は 2 x size) 動いたら閉じる
+    Range,  // 高値と安値の差が size に達したら閉じる
+}
+
+/// ブリックの幅
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrickSize {
+    Fixed(f64),
+    Atr { period: usize, timeframe: u32 },  // timeframe 秒足の ATR(period), 揃うまではブリックを作らない
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrickConfig {
+    pub kind: BrickKind,
+    pub size: BrickSize,
+}
+
+impl BrickConfig {
+    /// 書式: "renko:100", "range:0.5", "renko:atr14" (1 分足の ATR(14)), "range:atr14/300" や "range:atr14/5m" (5 分足)
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let invalid = || anyhow::anyhow!("Invalid brick spec: {}. Use renko:<size>, range:<size> or renko:atr<N>[/<timeframe>]", spec);
+        let (kind, size) = spec.split_once(':').ok_or_else(invalid)?;
+        let kind = match kind.trim() {
+            "renko" => BrickKind::Renko,
+            "range" => BrickKind::Range,
+            _ => return Err(invalid()),
+        };
+        let size = match size.trim().strip_prefix("atr") {
+            Some(atr) => {
+                let (period, timeframe) = atr.split_once('/').unwrap_or((atr, "60"));
+                let period: usize = period.parse().map_err(|_| invalid())?;
+                let timeframe = super::timeframe::parse(timeframe).map_err(|_| invalid())?;
+                if period == 0 {
+                    return Err(invalid());
+                }
+                BrickSize::Atr { period, timeframe }
+            }
+            None => {
+                let size: f64 = size.trim().parse().map_err(|_| invalid())?;
+                if size <= 0.0 || !size.is_finite() {
+                    return Err(invalid());
+                }
+                BrickSize::Fixed(size)
+            }
+        };
+        Ok(Self { kind, size })
+    }
+
+    /// 書式: "renko:100,range:atr14"
+    pub fn parse_list(spec: &str) -> anyhow::Result<Vec<Self>> {
+        let configs = spec
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(Self::parse)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if configs.is_empty() {
+            return Err(anyhow::anyhow!("Empty brick list"));
+        }
+        Ok(configs)
+    }
+
+    /// renko_100, range_0_5, renko_atr14, range_atr14_300 (コレクション名)
+    pub fn name(&self) -> String {
+        let kind = match self.kind {
+            BrickKind::Renko => "renko",
+            BrickKind::Range => "range",
+        };
+        let size = match self.size {
+            BrickSize::Fixed(size) => size.to_string().replace('.', "_"),
+            BrickSize::Atr { period, timeframe: 60 } => format!("atr{}", period),
+            BrickSize::Atr { period, timeframe } => format!("atr{}_{}", period, timeframe),
+        };
+        format!("{}_{}", kind, size)
+    }
+}
+
+/// 約定から作る timeframe 秒足の ATR (単純平均)
+#[derive(Debug, Default)]
+struct AtrTracker {
+    bucket: Option<i64>,  // 集計中の足 (timestamp / timeframe)
+    high: f64,
+    low: f64,
+    close: f64,
+    previous_close: Option<f64>,
+    ranges: VecDeque<f64>,  // 直近の true range
+}
+
+impl AtrTracker {
+    fn update(&mut self, trade: &Trade, period: usize, timeframe: u32) {
+        let bucket = trade.timestamp.timestamp().div_euclid(timeframe as i64);
+        let Some(current) = self.bucket else {
+            *self = Self { bucket: Some(bucket), high: trade.price, low: trade.price, close: trade.price, ..Self::default() };
+            return;
+        };
+        if bucket > current {
+            // 足が閉じたら true range を記録する
+            let range = match self.previous_close {
+                Some(previous) => (self.high - self.low).max((self.high - previous).abs()).max((self.low - previous).abs()),
+                None => self.high - self.low,
+            };
+            self.ranges.push_back(range);
+            while self.ranges.len() > period {
+                self.ranges.pop_front();
+            }
+            self.previous_close = Some(self.close);
+            self.bucket = Some(bucket);
+            self.high = trade.price;
+            self.low = trade.price;
+        }
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.close = trade.price;
+    }
+
+    fn value(&self, period: usize) -> Option<f64> {
+        if self.ranges.len() < period {
+            return None;
+        }
+        Some(self.ranges.iter().sum::<f64>() / period as f64).filter(|&atr| atr > 0.0)
+    }
+}
+
+/// 集計中のブリック
+#[derive(Debug)]
+struct OpenBrick {
+    start: DateTime<Utc>,
+    high: f64,
+    low: f64,
+    first: f64,
+    volume: f64,
+    count: i32,
+}
+
+impl OpenBrick {
+    fn new(trade: &Trade) -> Self {
+        Self { start: trade.timestamp, high: trade.price, low: trade.price, first: trade.price, volume: 0.0, count: 0 }
+    }
+
+    fn update(&mut self, trade: &Trade) {
+        self.high = self.high.max(trade.price);
+        self.low = self.low.min(trade.price);
+        self.volume += trade.quantity;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct BrickState {
+    atr: AtrTracker,
+    anchor: Option<f64>,  // Renko: 直前のブリックの終値 (最初は最初の約定価格)
+    direction: i32,
+    open: Option<OpenBrick>,
+}
+
+/// 約定から Renko のブリックとレンジバーを作る
+/// 1 つの約定で複数のブリックが閉じる場合, 出来高と件数は最初のブリックに含める
+pub struct RenkoBuilder {
+    configs: Vec<BrickConfig>,
+    states: HashMap<(String, MarketType, String, usize), BrickState>,  // (exchange, market_type, symbol, configs の添字)
+}
+
+impl RenkoBuilder {
+    pub fn new(configs: Vec<BrickConfig>) -> Self {
+        Self {
+            configs,
+            states: HashMap::new(),
+        }
+    }
+
+    /// 約定を 1 件加え, 閉じたブリックを返す
+    pub fn push(&mut self, trade: &Trade) -> Vec<Brick> {
+        let mut closed = Vec::new();
+        for (index, config) in self.configs.iter().enumerate() {
+            let key = (trade.exchange.clone(), trade.market_type.clone(), trade.symbol.clone(), index);
+            let state = self.states.entry(key).or_default();
+            let size = match config.size {
+                BrickSize::Fixed(size) => size,
+                BrickSize::Atr { period, timeframe } => {
+                    state.atr.update(trade, period, timeframe);
+                    match state.atr.value(period) {
+                        Some(atr) => atr,
+                        None => continue,
+                    }
+                }
+            };
+            let open = state.open.get_or_insert_with(|| OpenBrick::new(trade));
+            open.update(trade);
+            let brick = |open: &OpenBrick, from: f64, to: f64, direction: i32, first: bool| Brick {
+                id: uuid::Uuid::new_v4(),
+                exchange: trade.exchange.clone(),
+                market_type: trade.market_type.clone(),
+                symbol: trade.symbol.clone(),
+                name: config.name(),
+                start: open.start,
+                timestamp: trade.timestamp,
+                open: from,
+                high: if first { open.high } else { from.max(to) },
+                low: if first { open.low } else { from.min(to) },
+                close: to,
+                size,
+                direction,
+                volume: if first { open.volume } else { 0.0 },
+                count: if first { open.count } else { 0 },
+            };
+            match config.kind {
+                BrickKind::Range => {
+                    if open.high - open.low >= size {
+                        let direction = if trade.price >= open.first { 1 } else { -1 };
+                        closed.push(brick(open, open.first, trade.price, direction, true));
+                        state.open = None;
+                    }
+                }
+                BrickKind::Renko => {
+                    let mut anchor = *state.anchor.get_or_insert(open.first);
+                    let mut first = true;
+                    loop {
+                        // 同じ向きは size, 反転は直前のブリックの始値から size (終値から 2 x size)
+                        let up = anchor + if state.direction < 0 { 2.0 * size } else { size };
+                        let down = anchor - if state.direction > 0 { 2.0 * size } else { size };
+                        let (from, to, direction) = if trade.price >= up {
+                            (up - size, up, 1)
+                        } else if trade.price <= down {
+                            (down + size, down, -1)
+                        } else {
+                            break;
+                        };
+                        closed.push(brick(open, from, to, direction, first));
+                        first = false;
+                        anchor = to;
+                        state.direction = direction;
+                    }
+                    state.anchor = Some(anchor);
+                    if !first {
+                        state.open = None;
+                    }
+                }
+            }
+        }
+        closed
+    }
+
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        tracing::info!("RenkoBuilder started with {:?}", self.configs.iter().map(BrickConfig::name).collect::<Vec<_>>());
+        while let Some(event) = receiver.recv().await {
+            let bricks = match &event {
+                MarketEvent::Trade(trade) => self.push(trade),
+                _ => Vec::new(),
+            };
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+            for brick in bricks {
+                if let Err(e) = sender.send(MarketEvent::Brick(brick)).await {
+                    error!("Failed to send brick: {}", e);
+                }
+            }
+        }
+    }
+}
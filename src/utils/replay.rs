@@ -0,0 +1,75 @@
+use crate::models::market_event::MarketEvent;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::mpsc;
+
+/// 保存済みのデータを元の間隔 (speed 倍速) で流すための時計
+/// origin (データの開始時刻) を作成した時点に対応させ, 各イベントをその時刻まで待ってから流す
+pub struct ReplayClock {
+    origin: DateTime<Utc>,
+    started: tokio::time::Instant,
+    speed: f64,  // 0 なら待たない
+}
+
+impl ReplayClock {
+    pub fn new(origin: DateTime<Utc>, speed: f64) -> anyhow::Result<Self> {
+        if !speed.is_finite() || speed < 0.0 {
+            return Err(anyhow::anyhow!("Invalid replay speed: {} (use 0 for as fast as possible)", speed));
+        }
+        Ok(Self { origin, started: tokio::time::Instant::now(), speed })
+    }
+
+    /// 開始から elapsed 経った時点で, event_time のイベントを流すまでに待つ時間
+    pub fn delay(&self, event_time: DateTime<Utc>, elapsed: std::time::Duration) -> std::time::Duration {
+        if self.speed == 0.0 || event_time <= self.origin {
+            return std::time::Duration::ZERO;
+        }
+        let offset = (event_time - self.origin).to_std().unwrap_or_default().div_f64(self.speed);
+        offset.saturating_sub(elapsed)
+    }
+
+    pub async fn wait(&self, event_time: DateTime<Utc>) {
+        let delay = self.delay(event_time, self.started.elapsed());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// 再生するデータを今の時刻にずらす幅 (足の境界が変わらないように align_seconds の倍数に切り捨てる)
+pub fn rebase_offset(start: DateTime<Utc>, now: DateTime<Utc>, align_seconds: u32) -> Duration {
+    let align = align_seconds.max(1) as i64;
+    Duration::seconds((now - start).num_seconds().div_euclid(align) * align)
+}
+
+/// 約定・ローソク足の時刻を offset だけずらす (再生するのはこの 2 つだけ)
+pub fn shift(event: &mut MarketEvent, offset: Duration) {
+    match event {
+        MarketEvent::Trade(trade) => {
+            trade.timestamp += offset;
+            trade.gateway_timestamp = trade.gateway_timestamp.map(|t| t + offset);
+            trade.received_at += offset;
+        }
+        MarketEvent::Candle(candle) => {
+            candle.timestamp += offset;
+            candle.received_at = candle.received_at.map(|t| t + offset);
+        }
+        _ => {}
+    }
+}
+
+/// 時刻順のイベントを clock に合わせて (時刻を offset だけずらして) sender に流し, 流した件数を返す
+pub async fn replay(
+    events: impl IntoIterator<Item = MarketEvent>,
+    clock: &ReplayClock,
+    offset: Duration,
+    sender: &mpsc::Sender<MarketEvent>,
+) -> anyhow::Result<usize> {
+    let mut count = 0;
+    for mut event in events {
+        clock.wait(event.timestamp()).await;
+        shift(&mut event, offset);
+        sender.send(event).await?;
+        count += 1;
+    }
+    Ok(count)
+}
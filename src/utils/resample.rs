@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// 値のないバケットの扱い
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillPolicy {
+    None,                            // null のまま
+    Ffill { limit: Option<usize> },  // 直前の値で埋める (limit バケットまで)
+    Interpolate,                     // 前後の値から線形補間 (先頭・末尾は null)
+    Drop,                            // いずれかの系列が null の行を削除
+}
+
+impl FillPolicy {
+    /// 書式: "none", "ffill", "ffill:10", "interpolate", "drop"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim().to_lowercase();
+        match spec.split_once(':') {
+            Some(("ffill", limit)) => Ok(Self::Ffill {
+                limit: Some(limit.parse().map_err(|_| anyhow::anyhow!("Invalid ffill limit: {}", limit))?),
+            }),
+            None if spec == "ffill" => Ok(Self::Ffill { limit: None }),
+            None if spec == "none" => Ok(Self::None),
+            None if spec == "interpolate" => Ok(Self::Interpolate),
+            None if spec == "drop" => Ok(Self::Drop),
+            _ => Err(anyhow::anyhow!("Invalid fill policy: {}. Use none, ffill, ffill:<buckets>, interpolate or drop", spec)),
+        }
+    }
+
+    fn fill(&self, values: &mut [Option<f64>]) {
+        match self {
+            Self::None | Self::Drop => {}
+            Self::Ffill { limit } => {
+                let mut last: Option<(usize, f64)> = None;
+                for (i, value) in values.iter_mut().enumerate() {
+                    match (*value, last) {
+                        (Some(v), _) => last = Some((i, v)),
+                        (None, Some((at, v))) if limit.is_none_or(|limit| i - at <= limit) => *value = Some(v),
+                        _ => {}
+                    }
+                }
+            }
+            Self::Interpolate => {
+                let mut prev: Option<(usize, f64)> = None;
+                for i in 0..values.len() {
+                    let Some(v) = values[i] else {
+                        continue;
+                    };
+                    if let Some((at, pv)) = prev {
+                        for (k, value) in values.iter_mut().enumerate().take(i).skip(at + 1) {
+                            *value = Some(pv + (v - pv) * (k - at) as f64 / (i - at) as f64);
+                        }
+                    }
+                    prev = Some((i, v));
+                }
+            }
+        }
+    }
+}
+
+/// 等間隔の時間軸 (ミリ秒)
+/// 各バケットの時刻はその区間の終端 (ローソク足の unixtime と同じ規約)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeGrid {
+    pub start_ms: i64,
+    pub interval_ms: i64,
+    pub len: usize,
+}
+
+impl TimeGrid {
+    /// start より後の最初の境界から end 以前の最後の境界まで
+    pub fn aligned(start: DateTime<Utc>, end: DateTime<Utc>, interval_seconds: i64) -> Self {
+        let interval_ms = interval_seconds.max(1) * 1000;
+        let start_ms = (start.timestamp_millis() / interval_ms) * interval_ms + interval_ms;
+        let end_ms = (end.timestamp_millis() / interval_ms) * interval_ms;
+        let len = if end_ms >= start_ms { ((end_ms - start_ms) / interval_ms + 1) as usize } else { 0 };
+        Self { start_ms, interval_ms, len }
+    }
+
+    pub fn timestamps(&self) -> Vec<i64> {
+        (0..self.len as i64).map(|i| self.start_ms + i * self.interval_ms).collect()
+    }
+
+    /// timestamp を含むバケット (終端側に切り上げ) の位置
+    pub fn bucket(&self, timestamp_ms: i64) -> Option<usize> {
+        let offset = timestamp_ms - self.start_ms;
+        let index = if offset <= 0 {
+            // 先頭バケットの区間 (start - interval, start]
+            if offset > -self.interval_ms { 0 } else { return None }
+        } else {
+            (offset + self.interval_ms - 1) / self.interval_ms
+        };
+        (index < self.len as i64).then_some(index as usize)
+    }
+}
+
+/// (timestamp ms, value) の系列を時間軸に揃える
+/// 同じバケットに複数の値がある場合は最後 (最新) の値を使う
+pub fn resample_series(points: &[(i64, f64)], grid: &TimeGrid, policy: FillPolicy) -> Vec<Option<f64>> {
+    let mut sorted: Vec<(i64, f64)> = points.to_vec();
+    sorted.sort_by_key(|(ts, _)| *ts);
+
+    let mut values = vec![None; grid.len];
+    for (timestamp, value) in sorted {
+        if let Some(index) = grid.bucket(timestamp) {
+            values[index] = Some(value);
+        }
+    }
+    policy.fill(&mut values);
+    values
+}
+
+/// 縦持ち (timestamp, symbol_id, price) の DataFrame を時間軸に揃えて横持ち (timestamp, symbol_{id}...) にする
+pub fn resample_long(df: &DataFrame, grid: &TimeGrid, policy: FillPolicy) -> anyhow::Result<DataFrame> {
+    let mut series_by_symbol: BTreeMap<i32, Vec<(i64, f64)>> = BTreeMap::new();
+    if !df.is_empty() {
+        let timestamps = df.column("timestamp")?.i64()?;
+        let symbol_ids = df.column("symbol_id")?.i32()?;
+        let prices = df.column("price")?.f64()?;
+        for ((timestamp, symbol_id), price) in timestamps.into_iter().zip(symbol_ids).zip(prices) {
+            if let (Some(timestamp), Some(symbol_id), Some(price)) = (timestamp, symbol_id, price) {
+                series_by_symbol.entry(symbol_id).or_default().push((timestamp, price));
+            }
+        }
+    }
+
+    let mut timestamps = grid.timestamps();
+    let mut columns: Vec<(String, Vec<Option<f64>>)> = series_by_symbol
+        .iter()
+        .map(|(symbol_id, points)| (format!("symbol_{}", symbol_id), resample_series(points, grid, policy)))
+        .collect();
+
+    if policy == FillPolicy::Drop {
+        let keep: Vec<bool> = (0..grid.len)
+            .map(|i| columns.iter().all(|(_, values)| values[i].is_some()))
+            .collect();
+        timestamps = timestamps.into_iter().zip(&keep).filter(|(_, k)| **k).map(|(ts, _)| ts).collect();
+        for (_, values) in columns.iter_mut() {
+            *values = values.iter().zip(&keep).filter(|(_, k)| **k).map(|(v, _)| *v).collect();
+        }
+    }
+
+    let mut result: Vec<Column> = vec![Series::new("timestamp".into(), timestamps).into()];
+    for (name, values) in columns {
+        result.push(Series::new(name.as_str().into(), values).into());
+    }
+    Ok(DataFrame::new(result)?)
+}
@@ -0,0 +1,147 @@
+use crate::db::Database;
+use super::timeframe;
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info};
+
+/// 古い足の消し方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionMode {
+    Ttl,    // 時系列コレクションの expireAfterSeconds (MongoDB がバケットごとに消す)
+    Prune,  // interval_secs ごとに delete_many (時系列コレクションの時刻での削除は MongoDB 7.0+)
+}
+
+impl RetentionMode {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "ttl" => Ok(Self::Ttl),
+            "prune" => Ok(Self::Prune),
+            s => Err(anyhow::anyhow!("Invalid retention mode: {}. Use ttl or prune", s)),
+        }
+    }
+}
+
+/// collector の設定の [retention]
+/// keep は時間枠 -> 保持期間 (e.g. 1s = "7d", 1m = "90d", 1h = "forever"). 書いていない時間枠は消さない
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionConfig {
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,  // prune の間隔
+    pub keep: BTreeMap<String, String>,
+}
+
+fn default_mode() -> String {
+    "ttl".to_string()
+}
+
+fn default_interval_secs() -> u64 {
+    3600
+}
+
+/// 1 つの時間枠の保持期間 (None は消さない)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    pub period_seconds: u32,
+    pub keep: Option<Duration>,
+}
+
+/// 保持期間の指定 ("7d", "12h", "forever") を変換する
+pub fn parse_keep(spec: &str) -> anyhow::Result<Option<Duration>> {
+    match spec.trim() {
+        "forever" => Ok(None),
+        spec => Ok(Some(Duration::from_secs(timeframe::parse(spec)? as u64))),
+    }
+}
+
+impl RetentionConfig {
+    pub fn mode(&self) -> anyhow::Result<RetentionMode> {
+        RetentionMode::parse(&self.mode)
+    }
+
+    /// 時間枠の順の保持期間
+    pub fn policies(&self) -> anyhow::Result<Vec<RetentionPolicy>> {
+        let mut policies = BTreeMap::new();
+        for (period, keep) in &self.keep {
+            let period_seconds = timeframe::parse(period)?;
+            let keep = parse_keep(keep)?;
+            if policies.insert(period_seconds, keep).is_some() {
+                return Err(anyhow::anyhow!("Retention of {} appears more than once", timeframe::label(period_seconds)));
+            }
+        }
+        Ok(policies.into_iter().map(|(period_seconds, keep)| RetentionPolicy { period_seconds, keep }).collect())
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.mode()?;
+        self.policies()?;
+        if self.interval_secs == 0 {
+            return Err(anyhow::anyhow!("Retention interval_secs must be positive"));
+        }
+        Ok(())
+    }
+}
+
+/// 足のコレクションに時間枠ごとの保持期間を適用する (全てのシャード)
+/// ttl なら起動時に expireAfterSeconds を設定し (forever は外す), prune なら interval ごとに保持期間より古い足を消す
+pub struct RetentionManager {
+    db: Arc<Database>,
+    mode: RetentionMode,
+    interval: Duration,
+    policies: Vec<RetentionPolicy>,
+}
+
+impl RetentionManager {
+    pub fn new(db: Arc<Database>, config: &RetentionConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            db,
+            mode: config.mode()?,
+            interval: Duration::from_secs(config.interval_secs.max(1)),
+            policies: config.policies()?,
+        })
+    }
+
+    pub async fn run(self) {
+        info!("Retention ({:?}): {}", self.mode, self.policies.iter()
+            .map(|policy| format!("{}={}", timeframe::label(policy.period_seconds), policy.keep.map_or("forever".to_string(), |keep| timeframe::label(keep.as_secs() as u32))))
+            .collect::<Vec<_>>().join(", "));
+        match self.mode {
+            RetentionMode::Ttl => {
+                for policy in &self.policies {
+                    if let Err(e) = self.db.set_candle_ttl(policy.period_seconds as i32, policy.keep).await {
+                        error!("Failed to set retention of {} candles: {}", timeframe::label(policy.period_seconds), e);
+                    }
+                }
+            }
+            RetentionMode::Prune => {
+                let mut ticker = tokio::time::interval(self.interval);
+                loop {
+                    ticker.tick().await;
+                    self.prune().await;
+                }
+            }
+        }
+    }
+
+    async fn prune(&self) {
+        for policy in &self.policies {
+            let Some(keep) = policy.keep else {
+                continue;
+            };
+            let Ok(keep) = chrono::Duration::from_std(keep) else {
+                continue;
+            };
+            let before = Utc::now() - keep;
+            match self.db.prune_candles(policy.period_seconds as i32, before).await {
+                Ok(0) => {}
+                Ok(deleted) => info!("Pruned {} {} candles before {}", deleted, timeframe::label(policy.period_seconds), before),
+                Err(e) => error!("Failed to prune {} candles: {}", timeframe::label(policy.period_seconds), e),
+            }
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use polars::prelude::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// horizon バケット前との対数収益率 ln(p_t / p_{t-horizon}) (どちらかがない・0 以下なら None)
+pub fn log_return(current: Option<f64>, previous: Option<f64>) -> Option<f64> {
+    match (current, previous) {
+        (Some(current), Some(previous)) if current > 0.0 && previous > 0.0 => Some((current / previous).ln()),
+        _ => None,
+    }
+}
+
+/// 等間隔の価格の系列を horizon バケットの対数収益率にする (先頭の horizon 個は None)
+pub fn log_returns(values: &[Option<f64>], horizon: usize) -> Vec<Option<f64>> {
+    let horizon = horizon.max(1);
+    (0..values.len())
+        .map(|i| i.checked_sub(horizon).and_then(|j| log_return(values[i], values[j])))
+        .collect()
+}
+
+/// 時間軸に揃えた横持ち (timestamp, symbol_{id}...) の価格を対数収益率にする
+pub fn log_returns_frame(df: &DataFrame, horizon: usize) -> anyhow::Result<DataFrame> {
+    let mut columns = Vec::with_capacity(df.width());
+    for column in df.get_columns() {
+        if !column.name().starts_with("symbol_") {
+            columns.push(column.clone());
+            continue;
+        }
+        let values: Vec<Option<f64>> = column.f64()?.into_iter().collect();
+        columns.push(Series::new(column.name().clone(), log_returns(&values, horizon)).into());
+    }
+    Ok(DataFrame::new(columns)?)
+}
+
+/// バケットごとの価格を 1 行ずつ受け取り, horizon バケット前との対数収益率にする (RollingCorrelation 用)
+pub struct ReturnTransform {
+    horizon: usize,
+    history: HashMap<i32, VecDeque<Option<f64>>>,  // symbol ごとの直近 horizon + 1 バケットの価格
+}
+
+impl ReturnTransform {
+    pub fn new(horizon: usize) -> Self {
+        Self { horizon: horizon.max(1), history: HashMap::new() }
+    }
+
+    /// バケットが途切れたときに履歴を捨てる
+    pub fn reset(&mut self) {
+        self.history.clear();
+    }
+
+    /// 次のバケットの価格 (値のない symbol は含まない) の収益率
+    pub fn apply(&mut self, row: &BTreeMap<i32, f64>) -> BTreeMap<i32, f64> {
+        for &symbol_id in row.keys() {
+            self.history.entry(symbol_id).or_default();
+        }
+        let mut returns = BTreeMap::new();
+        for (&symbol_id, history) in self.history.iter_mut() {
+            history.push_back(row.get(&symbol_id).copied());
+            if history.len() > self.horizon + 1 {
+                history.pop_front();
+            }
+            if history.len() == self.horizon + 1 {
+                if let Some(value) = log_return(history[self.horizon], history[0]) {
+                    returns.insert(symbol_id, value);
+                }
+            }
+        }
+        // horizon + 1 バケットの間ずっと値のない symbol は忘れる
+        self.history.retain(|_, history| history.iter().any(Option::is_some));
+        returns
+    }
+}
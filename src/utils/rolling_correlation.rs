@@ -0,0 +1,340 @@
+use super::beta::SymbolBeta;
+use super::ewma::EwmaCorrelation;
+use super::price_window::PricePoint;
+use super::rank_correlation::CorrelationMethod;
+use super::resample::FillPolicy;
+use super::returns::ReturnTransform;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// 1 つのペアの窓の中の平均・偏差平方和・共偏差 (Welford. 追加と削除のどちらも O(1))
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PairStats {
+    pub count: usize,
+    mean_x: f64,
+    mean_y: f64,
+    m2_x: f64,
+    m2_y: f64,
+    c_xy: f64,
+}
+
+impl PairStats {
+    pub fn add(&mut self, x: f64, y: f64) {
+        self.count += 1;
+        let n = self.count as f64;
+        let dx = x - self.mean_x;
+        let dy = y - self.mean_y;
+        self.mean_x += dx / n;
+        self.mean_y += dy / n;
+        self.m2_x += dx * (x - self.mean_x);
+        self.m2_y += dy * (y - self.mean_y);
+        self.c_xy += dx * (y - self.mean_y);
+    }
+
+    /// add の逆 (窓から出たバケット)
+    pub fn remove(&mut self, x: f64, y: f64) {
+        if self.count <= 1 {
+            *self = Self::default();
+            return;
+        }
+        let n = self.count as f64;
+        let mean_x = (n * self.mean_x - x) / (n - 1.0);
+        let mean_y = (n * self.mean_y - y) / (n - 1.0);
+        self.m2_x -= (x - mean_x) * (x - self.mean_x);
+        self.m2_y -= (y - mean_y) * (y - self.mean_y);
+        self.c_xy -= (x - mean_x) * (y - self.mean_y);
+        self.mean_x = mean_x;
+        self.mean_y = mean_y;
+        self.count -= 1;
+    }
+
+    /// ピアソンの相関係数 (2 点未満・分散 0 なら None)
+    pub fn correlation(&self) -> Option<f64> {
+        if self.count < 2 || self.m2_x <= 0.0 || self.m2_y <= 0.0 {
+            return None;
+        }
+        Some((self.c_xy / (self.m2_x * self.m2_y).sqrt()).clamp(-1.0, 1.0))
+    }
+
+    /// y を x で回帰した傾き (x_on_y なら x を y で回帰した傾き. 2 点未満・分散 0 なら None)
+    pub fn slope(&self, x_on_y: bool) -> Option<f64> {
+        let m2 = if x_on_y { self.m2_y } else { self.m2_x };
+        (self.count >= 2 && m2 > 0.0).then(|| self.c_xy / m2)
+    }
+}
+
+/// ペアの相関 (symbol_a < symbol_b)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairCorrelation {
+    pub symbol_a: i32,
+    pub symbol_b: i32,
+    pub count: usize,
+    pub correlation: Option<f64>,
+}
+
+/// 時間軸に揃えた価格の窓とペアごとの Welford の統計
+/// 届いた価格はバケット (終端の時刻, TimeGrid と同じ) ごとに保留し, advance で確定したバケットを 1 行ずつ窓に入れて O(ペア数) で更新する
+/// 窓から出たバケットは同じく O(ペア数) で統計から外すので, 窓全体を読み直さない
+/// 既に窓に入れたバケットに遅れて届いた価格 (書き直し) は使わない
+pub struct RollingCorrelation {
+    interval_ms: i64,
+    capacity: usize,  // 窓のバケット数
+    fill_policy: FillPolicy,
+    rows: VecDeque<(i64, BTreeMap<i32, f64>)>,  // 窓に入れたバケット (埋めた後の値)
+    pairs: BTreeMap<(i32, i32), PairStats>,
+    values: HashMap<i32, usize>,  // symbol ごとの窓の中の値のあるバケット数
+    pending: BTreeMap<i64, HashMap<i32, f64>>,  // まだ窓に入れていないバケット
+    last: HashMap<i32, (f64, usize)>,  // ffill 用の symbol ごとの直近の値と経過バケット数
+    next_bucket: Option<i64>,  // 次に窓に入れるバケット
+    pushed: usize,  // 統計を計算し直してから入れたバケット数
+    returns: Option<ReturnTransform>,  // 価格の代わりに対数収益率の相関を取る
+    ewma: Option<EwmaCorrelation>,  // 窓に入れた行で更新する指数加重の相関とボラティリティ
+}
+
+impl RollingCorrelation {
+    /// interpolate は後のバケットが要るので使えない
+    pub fn new(interval_seconds: i64, window: chrono::Duration, fill_policy: FillPolicy) -> anyhow::Result<Self> {
+        if fill_policy == FillPolicy::Interpolate {
+            return Err(anyhow::anyhow!("Incremental correlation supports ffill, ffill:<buckets>, none or drop, not interpolate"));
+        }
+        let interval_ms = interval_seconds.max(1) * 1000;
+        Ok(Self {
+            interval_ms,
+            capacity: (window.num_milliseconds() / interval_ms).max(2) as usize,
+            fill_policy,
+            rows: VecDeque::new(),
+            pairs: BTreeMap::new(),
+            values: HashMap::new(),
+            pending: BTreeMap::new(),
+            last: HashMap::new(),
+            next_bucket: None,
+            pushed: 0,
+            returns: None,
+            ewma: None,
+        })
+    }
+
+    /// 埋めた後の価格を horizon バケットの対数収益率にしてから窓に入れる
+    pub fn with_returns(mut self, horizon: usize) -> Self {
+        self.returns = Some(ReturnTransform::new(horizon));
+        self
+    }
+
+    /// 窓に入れる行 (価格または with_returns の収益率) で指数加重の相関とボラティリティも更新する (with_returns の後に指定する)
+    /// 窓から出たバケットは引かないので, 窓より前のバケットも重みを減らしながら残る
+    pub fn with_ewma(mut self, half_life_buckets: f64) -> Self {
+        self.ewma = Some(EwmaCorrelation::new(half_life_buckets, self.returns.is_none()));
+        self
+    }
+
+    /// 価格の入るバケットの終端 (区間 (終端 - interval, 終端])
+    fn bucket(&self, timestamp_ms: i64) -> i64 {
+        (timestamp_ms + self.interval_ms - 1).div_euclid(self.interval_ms) * self.interval_ms
+    }
+
+    /// 価格を保留する (同じ symbol・バケットは後の値で上書き. 窓に入れたバケットの分は捨てる)
+    pub fn insert(&mut self, point: &PricePoint) {
+        let bucket = self.bucket(point.timestamp_ms);
+        if self.next_bucket.is_some_and(|next| bucket < next) {
+            return;
+        }
+        self.pending.entry(bucket).or_default().insert(point.symbol_id, point.price);
+    }
+
+    /// 終端が until_ms 以前のバケットを窓に入れ, 入れたバケット数を返す
+    pub fn advance(&mut self, until_ms: i64) -> usize {
+        let Some(mut bucket) = self.next_bucket.or_else(|| self.pending.keys().next().copied()) else {
+            return 0;
+        };
+        // 窓より長く止まっていたら窓の外になるバケットは飛ばす (ffill の値は引き継ぐ)
+        let skip_to = self.bucket(until_ms) - (self.capacity as i64 - 1) * self.interval_ms;
+        if bucket < skip_to {
+            self.clear_rows();
+            while self.pending.first_key_value().is_some_and(|(&first, _)| first < skip_to) {
+                if let Some((_, values)) = self.pending.pop_first() {
+                    for (symbol_id, price) in values {
+                        self.last.insert(symbol_id, (price, 0));
+                    }
+                }
+            }
+            bucket = skip_to;
+        }
+        let mut pushed = 0;
+        while bucket <= until_ms {
+            let values = self.pending.remove(&bucket).unwrap_or_default();
+            self.push(bucket, values);
+            bucket += self.interval_ms;
+            pushed += 1;
+        }
+        self.next_bucket = Some(bucket);
+        pushed
+    }
+
+    /// バケットの値を埋めて窓に入れ, 溢れたバケットを外す
+    fn push(&mut self, bucket: i64, values: HashMap<i32, f64>) {
+        for (symbol_id, (_, age)) in self.last.iter_mut() {
+            if !values.contains_key(symbol_id) {
+                *age += 1;
+            }
+        }
+        for (&symbol_id, &price) in &values {
+            self.last.insert(symbol_id, (price, 0));
+        }
+        // 窓の間ずっと値のない symbol は窓から消える (全体を読み直した場合と同じ)
+        let capacity = self.capacity;
+        self.last.retain(|_, (_, age)| *age < capacity);
+        let row: BTreeMap<i32, f64> = match self.fill_policy {
+            FillPolicy::Ffill { limit } => self.last.iter()
+                .filter(|(_, (_, age))| limit.is_none_or(|limit| *age <= limit))
+                .map(|(&symbol_id, &(price, _))| (symbol_id, price))
+                .collect(),
+            // drop はいずれかの symbol の値がないバケットを空にする
+            FillPolicy::Drop if values.len() < self.last.len() => BTreeMap::new(),
+            _ => values.into_iter().collect(),
+        };
+        let row = match self.returns.as_mut() {
+            Some(returns) => returns.apply(&row),
+            None => row,
+        };
+        if let Some(ewma) = self.ewma.as_mut() {
+            ewma.update(&row);
+        }
+        update_pairs(&mut self.pairs, &row, PairStats::add);
+        for &symbol_id in row.keys() {
+            *self.values.entry(symbol_id).or_default() += 1;
+        }
+        self.rows.push_back((bucket, row));
+        while self.rows.len() > self.capacity {
+            if let Some((_, row)) = self.rows.pop_front() {
+                update_pairs(&mut self.pairs, &row, PairStats::remove);
+                for symbol_id in row.keys() {
+                    if let Some(count) = self.values.get_mut(symbol_id) {
+                        *count -= 1;
+                    }
+                }
+            }
+        }
+        self.values.retain(|_, count| *count > 0);
+        self.pairs.retain(|_, stats| stats.count > 0);
+        // 足し引きの誤差が溜まらないように窓 1 つ分ごとに計算し直す
+        self.pushed += 1;
+        if self.pushed >= self.capacity {
+            self.recompute();
+        }
+    }
+
+    fn clear_rows(&mut self) {
+        self.rows.clear();
+        self.pairs.clear();
+        self.values.clear();
+        self.pushed = 0;
+        if let Some(returns) = self.returns.as_mut() {
+            returns.reset();
+        }
+        if let Some(ewma) = self.ewma.as_mut() {
+            ewma.reset();
+        }
+    }
+
+    /// 窓の行からペアの統計を計算し直す
+    fn recompute(&mut self) {
+        self.pairs.clear();
+        for (_, row) in &self.rows {
+            update_pairs(&mut self.pairs, row, PairStats::add);
+        }
+        self.pushed = 0;
+    }
+
+    pub fn ewma(&self) -> Option<&EwmaCorrelation> {
+        self.ewma.as_ref()
+    }
+
+    /// 窓に入れたバケット数
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// 窓に入れた最後のバケットの終端
+    pub fn end_ms(&self) -> Option<i64> {
+        self.rows.back().map(|(bucket, _)| *bucket)
+    }
+
+    /// 次に読む価格の開始時刻 (まだ窓に入れていないバケットの区間の始まり)
+    pub fn resume_ms(&self) -> Option<i64> {
+        self.next_bucket.map(|bucket| bucket - self.interval_ms + 1)
+    }
+
+    /// symbol ごとの窓のバケットのうち値のある (埋めた後) 割合
+    pub fn coverage(&self) -> BTreeMap<i32, f64> {
+        let rows = self.rows.len().max(1) as f64;
+        self.values.iter().map(|(&symbol_id, &count)| (symbol_id, count as f64 / rows)).collect()
+    }
+
+    /// 窓の中の symbol (昇順)
+    pub fn symbols(&self) -> Vec<i32> {
+        let mut symbols: Vec<i32> = self.rows.iter().flat_map(|(_, row)| row.keys().copied()).collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+        symbols
+    }
+
+    /// ペアごとの相関 (両方の値があるバケットが 1 つもないペアは含まない)
+    pub fn correlations(&self) -> Vec<PairCorrelation> {
+        self.pairs
+            .iter()
+            .map(|(&(symbol_a, symbol_b), stats)| PairCorrelation { symbol_a, symbol_b, count: stats.count, correlation: stats.correlation() })
+            .collect()
+    }
+
+    /// benchmark に対する symbol ごとの beta と決定係数 (benchmark との両方の値があるバケットが 1 つもない symbol は含まない)
+    pub fn betas(&self, benchmark: i32) -> Vec<SymbolBeta> {
+        self.pairs
+            .iter()
+            .filter_map(|(&(symbol_a, symbol_b), stats)| {
+                // (benchmark, symbol) の順のペアなら symbol (y) を benchmark (x) で回帰する
+                let (symbol_id, x_on_y) = match (symbol_a == benchmark, symbol_b == benchmark) {
+                    (true, _) => (symbol_b, false),
+                    (_, true) => (symbol_a, true),
+                    _ => return None,
+                };
+                Some(SymbolBeta {
+                    symbol_id,
+                    benchmark_id: benchmark,
+                    count: stats.count,
+                    beta: stats.slope(x_on_y),
+                    r_squared: stats.correlation().map(|corr| corr * corr),
+                })
+            })
+            .collect()
+    }
+
+    /// method のペアごとの相関. 順位相関 (spearman, kendall) は足し引きできないので窓の行から計算する (DB は読み直さない)
+    pub fn correlations_by(&self, method: CorrelationMethod) -> Vec<PairCorrelation> {
+        if method == CorrelationMethod::Pearson {
+            return self.correlations();
+        }
+        self.pairs
+            .iter()
+            .map(|(&(symbol_a, symbol_b), stats)| {
+                let (x, y): (Vec<f64>, Vec<f64>) = self.rows
+                    .iter()
+                    .filter_map(|(_, row)| Some((*row.get(&symbol_a)?, *row.get(&symbol_b)?)))
+                    .unzip();
+                PairCorrelation { symbol_a, symbol_b, count: stats.count, correlation: method.correlation(&x, &y) }
+            })
+            .collect()
+    }
+}
+
+/// 1 行の値がある全てのペアの統計を更新する (O(ペア数))
+fn update_pairs(pairs: &mut BTreeMap<(i32, i32), PairStats>, row: &BTreeMap<i32, f64>, update: fn(&mut PairStats, f64, f64)) {
+    let values: Vec<(i32, f64)> = row.iter().map(|(&symbol_id, &price)| (symbol_id, price)).collect();
+    for (i, &(symbol_a, x)) in values.iter().enumerate() {
+        for &(symbol_b, y) in &values[i + 1..] {
+            update(pairs.entry((symbol_a, symbol_b)).or_default(), x, y);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::models::market_type::MarketType;
+use super::ops_events::{self, OpsEventKind};
+use super::trade_candle_builder::{CandleBuilderSnapshot, SnapshotControl};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 計画的な再起動で引き継ぐコレクタの状態 (JSON ファイル)
+/// 新しい状態を追加するときは #[serde(default)] を付けて古いファイルも読めるようにする
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectorSnapshot {
+    pub version: u32,
+    pub taken_at: DateTime<Utc>,
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbols: Vec<String>,  // 購読中の symbol
+    pub timeframes: Vec<u32>,
+    pub candles: CandleBuilderSnapshot,
+}
+
+impl CollectorSnapshot {
+    pub const VERSION: u32 = 1;
+
+    pub fn new(exchange: &str, market_type: MarketType, symbols: Vec<String>, timeframes: Vec<u32>, candles: CandleBuilderSnapshot) -> Self {
+        Self {
+            version: Self::VERSION,
+            taken_at: Utc::now(),
+            exchange: exchange.to_string(),
+            market_type,
+            symbols,
+            timeframes,
+            candles,
+        }
+    }
+
+    /// 書き込み途中のファイルを読まないように一時ファイルに書いてから置き換える
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let snapshot: Self = serde_json::from_slice(&std::fs::read(path)?)
+            .map_err(|e| anyhow::anyhow!("Invalid snapshot {}: {}", path.display(), e))?;
+        if snapshot.version > Self::VERSION {
+            return Err(anyhow::anyhow!("Snapshot version {} is newer than supported ({})", snapshot.version, Self::VERSION));
+        }
+        Ok(snapshot)
+    }
+
+    /// 別の取引所・市場のスナップショットを読み込まない
+    pub fn check(&self, exchange: &str, market_type: &MarketType) -> anyhow::Result<()> {
+        if self.exchange != exchange || &self.market_type != market_type {
+            return Err(anyhow::anyhow!(
+                "Snapshot is for {} {}, not {} {}", self.exchange, self.market_type, exchange, market_type
+            ));
+        }
+        Ok(())
+    }
+
+    /// 指定した symbol にスナップショット時の購読を加える (順序は指定を優先)
+    pub fn merge_symbols(&self, mut symbols: Vec<String>) -> Vec<String> {
+        for symbol in &self.symbols {
+            if !symbols.contains(symbol) {
+                tracing::info!("Restoring subscription: {}", symbol);
+                symbols.push(symbol.clone());
+            }
+        }
+        symbols
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} @ {} | symbols={} timeframes={:?} open_candles={}",
+            self.exchange, self.market_type, self.taken_at.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.symbols.join(","), self.timeframes, self.candles.buffers.len()
+        )
+    }
+}
+
+/// シグナルでスナップショットを書き出す
+/// SIGUSR1: 書き出して収集を続ける, SIGTERM / SIGINT: 集計を止めて書き出してから終了する (計画的な再起動)
+#[cfg(unix)]
+pub async fn serve(
+    exchange: String,
+    market_type: MarketType,
+    symbols: Vec<String>,
+    timeframes: Vec<u32>,
+    control: SnapshotControl,
+    path: PathBuf,
+) -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut usr1 = signal(SignalKind::user_defined1())?;
+    let mut term = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    loop {
+        let stop = tokio::select! {
+            _ = usr1.recv() => false,
+            _ = term.recv() => true,
+            _ = interrupt.recv() => true,
+        };
+        let candles = control.snapshot(stop).await?;
+        let snapshot = CollectorSnapshot::new(&exchange, market_type.clone(), symbols.clone(), timeframes.clone(), candles);
+        match snapshot.save(&path) {
+            Ok(()) => ops_events::record(OpsEventKind::Snapshot, format!("{} ({})", path.display(), snapshot.summary())),
+            Err(e) => tracing::error!("Failed to write snapshot {}: {}", path.display(), e),
+        }
+        if stop {
+            // 出力済みのイベントを書き込む時間を置いてから終了する
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            std::process::exit(0);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn serve(
+    _exchange: String,
+    _market_type: MarketType,
+    _symbols: Vec<String>,
+    _timeframes: Vec<u32>,
+    _control: SnapshotControl,
+    _path: PathBuf,
+) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("Snapshots are triggered by unix signals and are not supported on this platform"))
+}
@@ -0,0 +1,215 @@
+use crate::models::{market_event::MarketEvent, trade::{Side, Trade}, trade_candle::TradeCandle};
+use super::storage_sink::StorageSink;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, Transaction};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+/// 書き込みを待たせておけるイベント数 (これを超えるとパイプラインが待つ)
+const QUEUE_CAPACITY: usize = 100_000;
+/// 1 つのトランザクションにまとめる最大件数
+const MAX_BATCH: usize = 5_000;
+
+/// 時刻は UNIX ミリ秒. 同じ足は revision の大きい行で上書きする
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS candles (
+    timestamp INTEGER NOT NULL,
+    exchange TEXT NOT NULL,
+    market_type TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    period_seconds INTEGER NOT NULL,
+    open REAL, high REAL, low REAL, close REAL,
+    ask_price REAL, ask_volume REAL NOT NULL, ask_notional REAL NOT NULL, ask_count INTEGER NOT NULL,
+    bid_price REAL, bid_volume REAL NOT NULL, bid_notional REAL NOT NULL, bid_count INTEGER NOT NULL,
+    cvd REAL NOT NULL, realized_vol REAL, direction_changes INTEGER NOT NULL,
+    warmup INTEGER NOT NULL, revision INTEGER NOT NULL,
+    PRIMARY KEY (exchange, market_type, symbol, period_seconds, timestamp)
+);
+CREATE INDEX IF NOT EXISTS candles_symbol_timestamp ON candles (symbol, timestamp);
+CREATE TABLE IF NOT EXISTS trades (
+    timestamp INTEGER NOT NULL,
+    received_at INTEGER NOT NULL,
+    exchange TEXT NOT NULL,
+    market_type TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    trade_id TEXT NOT NULL,
+    price REAL NOT NULL,
+    quantity REAL NOT NULL,
+    side TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS trades_symbol_timestamp ON trades (symbol, timestamp);
+";
+
+const INSERT_CANDLE: &str = "
+INSERT INTO candles (
+    timestamp, exchange, market_type, symbol, period_seconds, open, high, low, close,
+    ask_price, ask_volume, ask_notional, ask_count, bid_price, bid_volume, bid_notional, bid_count,
+    cvd, realized_vol, direction_changes, warmup, revision
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)
+ON CONFLICT (exchange, market_type, symbol, period_seconds, timestamp) DO UPDATE SET
+    open = excluded.open, high = excluded.high, low = excluded.low, close = excluded.close,
+    ask_price = excluded.ask_price, ask_volume = excluded.ask_volume, ask_notional = excluded.ask_notional, ask_count = excluded.ask_count,
+    bid_price = excluded.bid_price, bid_volume = excluded.bid_volume, bid_notional = excluded.bid_notional, bid_count = excluded.bid_count,
+    cvd = excluded.cvd, realized_vol = excluded.realized_vol, direction_changes = excluded.direction_changes,
+    warmup = excluded.warmup, revision = excluded.revision
+WHERE excluded.revision >= candles.revision
+";
+
+const INSERT_TRADE: &str = "
+INSERT INTO trades (timestamp, received_at, exchange, market_type, symbol, trade_id, price, quantity, side)
+VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+";
+
+/// 月ごとのファイル ({dir}/kkcrypto-YYYY-MM.sqlite)
+pub fn month_path(dir: &Path, time: DateTime<Utc>) -> PathBuf {
+    dir.join(format!("kkcrypto-{}.sqlite", time.format("%Y-%m")))
+}
+
+/// WAL モードで開き, テーブルとインデックスを作成する (なければ)
+pub fn open_database(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    connection.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))?;
+    connection.execute_batch("PRAGMA synchronous = NORMAL;")?;
+    connection.execute_batch(SCHEMA)?;
+    Ok(connection)
+}
+
+fn insert_candle(transaction: &Transaction, candle: &TradeCandle) -> rusqlite::Result<usize> {
+    transaction.prepare_cached(INSERT_CANDLE)?.execute(params![
+        candle.timestamp.timestamp_millis(), candle.exchange, candle.market_type.as_str(), candle.symbol, candle.period_seconds,
+        candle.open, candle.high, candle.low, candle.close,
+        candle.ask_price, candle.ask_volume, candle.ask_notional, candle.ask_count,
+        candle.bid_price, candle.bid_volume, candle.bid_notional, candle.bid_count,
+        candle.cvd, candle.realized_vol, candle.direction_changes, candle.warmup, candle.revision,
+    ])
+}
+
+fn insert_trade(transaction: &Transaction, trade: &Trade) -> rusqlite::Result<usize> {
+    let side = match trade.side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    };
+    transaction.prepare_cached(INSERT_TRADE)?.execute(params![
+        trade.timestamp.timestamp_millis(), trade.received_at.timestamp_millis(), trade.exchange, trade.market_type.as_str(),
+        trade.symbol, trade.trade_id, trade.price, trade.quantity, side,
+    ])
+}
+
+/// 月ごとの SQLite ファイルに足・約定を書く
+pub struct SqliteStore {
+    dir: PathBuf,
+    connections: HashMap<PathBuf, Connection>,
+}
+
+impl SqliteStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, connections: HashMap::new() }
+    }
+
+    /// 足・約定を月ごとに 1 つのトランザクションで書き, 書いた件数を返す (それ以外は無視)
+    pub fn write(&mut self, events: &[MarketEvent]) -> rusqlite::Result<usize> {
+        let mut by_month: HashMap<PathBuf, Vec<&MarketEvent>> = HashMap::new();
+        for event in events {
+            if matches!(event, MarketEvent::Candle(_) | MarketEvent::Trade(_)) {
+                by_month.entry(month_path(&self.dir, event.timestamp())).or_default().push(event);
+            }
+        }
+        let mut written = 0;
+        for (path, events) in by_month {
+            if !self.connections.contains_key(&path) {
+                info!("Opening SQLite database {}", path.display());
+                let connection = open_database(&path)?;
+                self.connections.insert(path.clone(), connection);
+            }
+            let connection = self.connections.get_mut(&path).unwrap();
+            let transaction = connection.transaction()?;
+            for event in events {
+                written += match event {
+                    MarketEvent::Candle(candle) => insert_candle(&transaction, candle)?,
+                    MarketEvent::Trade(trade) => insert_trade(&transaction, trade)?,
+                    _ => 0,
+                };
+            }
+            transaction.commit()?;
+        }
+        // 前の月のファイルは遅れて届く足のために 1 つだけ残す
+        if self.connections.len() > 2 {
+            let mut paths: Vec<PathBuf> = self.connections.keys().cloned().collect();
+            paths.sort();
+            for path in &paths[..paths.len() - 2] {
+                self.connections.remove(path);
+            }
+        }
+        Ok(written)
+    }
+
+    /// 書き込みのスレッドに送るシンクを返す
+    pub fn start(self, trades: bool) -> SqliteStage {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        std::thread::Builder::new()
+            .name("sqlite-writer".to_string())
+            .spawn(move || write(self, receiver))
+            .expect("Failed to start the SQLite writer thread");
+        SqliteStage { trades, sender }
+    }
+}
+
+/// SqliteStore の書き込みのスレッドに送るシンク
+#[derive(Clone)]
+pub struct SqliteStage {
+    trades: bool,
+    sender: mpsc::Sender<MarketEvent>,
+}
+
+impl SqliteStage {
+    async fn send(&self, event: MarketEvent) -> anyhow::Result<()> {
+        self.sender.send(event).await.map_err(|_| anyhow::anyhow!("SQLite writer stopped"))
+    }
+}
+
+#[async_trait]
+impl StorageSink for SqliteStage {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn stores_trades(&self) -> bool {
+        self.trades
+    }
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()> {
+        self.send(MarketEvent::Candle(candle.clone())).await
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.send(MarketEvent::Trade(trade.clone())).await
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("SQLite writer stopped"));
+        }
+        Ok(())
+    }
+}
+
+/// 溜まっている分をまとめて書く (rusqlite はブロックするので専用のスレッドで動かす)
+fn write(mut store: SqliteStore, mut receiver: mpsc::Receiver<MarketEvent>) {
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    while let Some(event) = receiver.blocking_recv() {
+        batch.push(event);
+        while batch.len() < MAX_BATCH {
+            match receiver.try_recv() {
+                Ok(event) => batch.push(event),
+                Err(_) => break,
+            }
+        }
+        if let Err(e) = store.write(&batch) {
+            error!("Failed to write {} events to SQLite: {}", batch.len(), e);
+        }
+        batch.clear();
+    }
+}
@@ -0,0 +1,43 @@
+/// 同じ base のステーブルコイン建てペア (BTCUSDT, BTCUSDC, BTCFDUSD ...) を 1 つの論理シンボル ({BASE}-USD) にまとめる
+/// 流動性がペア間で移動しても 1 本の系列 (約定の出来高加重平均) として扱うため
+#[derive(Debug, Clone)]
+pub struct StablecoinMerge {
+    quotes: Vec<String>,  // 長い順 (FDUSD を USD より先に判定する)
+}
+
+impl StablecoinMerge {
+    pub const DEFAULT_QUOTES: &'static str = "USDT,USDC,FDUSD,BUSD";
+
+    /// 書式: "USDT,USDC,FDUSD,BUSD"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut quotes: Vec<String> = spec
+            .split(',')
+            .map(|q| q.trim().to_uppercase())
+            .filter(|q| !q.is_empty())
+            .collect();
+        if quotes.len() < 2 {
+            return Err(anyhow::anyhow!("At least two quote assets are required to merge: {:?}", spec));
+        }
+        if let Some(q) = quotes.iter().find(|q| !q.chars().all(|c| c.is_ascii_alphanumeric())) {
+            return Err(anyhow::anyhow!("Invalid quote asset: {}", q));
+        }
+        quotes.sort_by_key(|q| std::cmp::Reverse(q.len()));
+        quotes.dedup();
+        Ok(Self { quotes })
+    }
+
+    /// symbol が対象のステーブルコイン建てなら論理シンボル (e.g. BTCUSDT -> BTC-USD, eth_usdc -> ETH-USD)
+    pub fn logical_symbol(&self, symbol: &str) -> Option<String> {
+        let upper = symbol.to_uppercase();
+        for quote in &self.quotes {
+            let Some(base) = upper.strip_suffix(quote.as_str()) else {
+                continue;
+            };
+            let base = base.trim_end_matches(['_', '-', '/']);
+            if !base.is_empty() {
+                return Some(format!("{}-USD", base));
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,238 @@
+use crate::models::market_event::MarketEvent;
+use crate::models::trade::Trade;
+use super::notify::Notifier;
+use super::ops_events::{self, OpsEventKind};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::fmt;
+use tokio::sync::mpsc;
+use tracing::error;
+
+const GAP_EWMA_ALPHA: f64 = 0.05;  // 約定間隔の平均の更新率 (直近 40 件程度を重視)
+const CHECK_INTERVAL_SECONDS: u64 = 5;
+
+/// 何秒止まったら異常とみなすか
+/// 銘柄ごとの普段の約定間隔の factor 倍, ただし min_silence 未満では判定しない (閑散な時間帯の誤検知を避ける)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StaleFeedConfig {
+    pub min_silence: Duration,
+    pub factor: f64,
+}
+
+impl StaleFeedConfig {
+    pub fn new(min_silence: Duration, factor: f64) -> Self {
+        Self { min_silence, factor: factor.max(1.0) }
+    }
+}
+
+/// 止まった理由の推定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleKind {
+    NoTrades,      // 購読してから 1 件も約定がない (symbol の誤りなど)
+    Subscription,  // 他の symbol は普段通り届いているのにこの symbol だけ止まった (購読が切れた可能性)
+    Feed,          // 全ての symbol が止まった (接続の問題, watchdog で再接続される)
+}
+
+impl StaleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoTrades => "no_trades",
+            Self::Subscription => "subscription",
+            Self::Feed => "feed",
+        }
+    }
+}
+
+/// 状態が変わった symbol の通知
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedAlert {
+    Stale { symbol: String, kind: StaleKind, silence: Duration, threshold: Duration },
+    Resumed { symbol: String, silence: Duration },
+}
+
+impl FeedAlert {
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Stale { symbol, .. } | Self::Resumed { symbol, .. } => symbol,
+        }
+    }
+}
+
+impl fmt::Display for FeedAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stale { symbol, kind, silence, threshold } => write!(
+                f, "{} stale ({}): no trade for {}s (threshold {}s)",
+                symbol, kind.as_str(), silence.num_seconds(), threshold.num_seconds()
+            ),
+            Self::Resumed { symbol, silence } => write!(f, "{} resumed after {}s", symbol, silence.num_seconds()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SymbolActivity {
+    last_trade: Option<DateTime<Utc>>,
+    mean_gap_seconds: Option<f64>,  // 約定間隔の EWMA (普段の活発さ)
+    stale: bool,
+}
+
+/// symbol ごとの最後の約定の受信時刻を追い, 普段の約定間隔に比べて長く止まった symbol を知らせる
+/// 受信時刻で測るので, 取引所の時刻のずれや REST 補完の古い約定には影響されない
+pub struct StaleFeedMonitor {
+    config: StaleFeedConfig,
+    label: String,  // 表示用の取引所名 (e.g. BYBIT)
+    started_at: DateTime<Utc>,
+    symbols: HashMap<String, SymbolActivity>,
+    notifier: Option<Notifier>,
+}
+
+impl StaleFeedMonitor {
+    /// symbols は購読した symbol (1 件も約定が来ない symbol も検知する)
+    pub fn new(exchange: &str, symbols: &[String], config: StaleFeedConfig) -> Self {
+        Self {
+            config,
+            label: exchange.to_uppercase(),
+            started_at: Utc::now(),
+            symbols: symbols.iter().map(|symbol| (symbol.clone(), SymbolActivity::default())).collect(),
+            notifier: None,
+        }
+    }
+
+    /// 監視の起点 (購読した時刻)
+    pub fn with_started_at(mut self, started_at: DateTime<Utc>) -> Self {
+        self.started_at = started_at;
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = (!notifier.is_empty()).then_some(notifier);
+        self
+    }
+
+    /// 約定を記録する (止まっていた symbol なら再開を返す)
+    pub fn record(&mut self, trade: &Trade) -> Option<FeedAlert> {
+        let received_at = trade.received_at;
+        let activity = self.symbols.entry(trade.symbol.clone()).or_default();
+        let silence = activity.last_trade.map(|last| received_at - last);
+        if let Some(gap) = silence.filter(|gap| *gap >= Duration::zero()) {
+            // 止まっていた間隔は普段の活発さに含めない
+            if !activity.stale {
+                let gap_seconds = gap.num_milliseconds() as f64 / 1000.0;
+                activity.mean_gap_seconds = Some(match activity.mean_gap_seconds {
+                    Some(mean) => mean + GAP_EWMA_ALPHA * (gap_seconds - mean),
+                    None => gap_seconds,
+                });
+            }
+        }
+        activity.last_trade = Some(activity.last_trade.map_or(received_at, |last| last.max(received_at)));
+        if !activity.stale {
+            return None;
+        }
+        activity.stale = false;
+        Some(FeedAlert::Resumed {
+            symbol: trade.symbol.clone(),
+            silence: silence.unwrap_or_else(|| received_at - self.started_at),
+        })
+    }
+
+    /// symbol の普段の約定間隔から決めた閾値
+    fn threshold(&self, activity: &SymbolActivity) -> Duration {
+        let baseline = activity
+            .mean_gap_seconds
+            .map(|mean| Duration::milliseconds((mean * self.config.factor * 1000.0) as i64))
+            .unwrap_or_else(Duration::zero);
+        baseline.max(self.config.min_silence)
+    }
+
+    /// now の時点で新たに閾値を超えて止まった symbol を返す
+    pub fn check(&mut self, now: DateTime<Utc>) -> Vec<FeedAlert> {
+        let over: Vec<(String, Duration, Duration)> = self
+            .symbols
+            .iter()
+            .map(|(symbol, activity)| {
+                let silence = now - activity.last_trade.unwrap_or(self.started_at);
+                (symbol.clone(), silence, self.threshold(activity))
+            })
+            .filter(|(_, silence, threshold)| silence > threshold)
+            .collect();
+        // 閾値を超えていない symbol が残っていれば接続は生きている
+        let feed_down = over.len() == self.symbols.len();
+
+        let mut alerts = Vec::new();
+        for (symbol, silence, threshold) in over {
+            let activity = self.symbols.get_mut(&symbol).unwrap();
+            if activity.stale {
+                continue;
+            }
+            activity.stale = true;
+            let kind = if activity.last_trade.is_none() {
+                StaleKind::NoTrades
+            } else if feed_down {
+                StaleKind::Feed
+            } else {
+                StaleKind::Subscription
+            };
+            alerts.push(FeedAlert::Stale { symbol, kind, silence, threshold });
+        }
+        alerts.sort_by(|a, b| a.symbol().cmp(b.symbol()));
+        alerts
+    }
+
+    /// 現在止まっている symbol の数
+    pub fn stale_count(&self) -> usize {
+        self.symbols.values().filter(|activity| activity.stale).count()
+    }
+
+    fn report(&self, alert: &FeedAlert) {
+        match alert {
+            FeedAlert::Stale { symbol, kind, silence, threshold } => {
+                tracing::warn!(exchange = %self.label, symbol = %symbol, kind = kind.as_str(),
+                    silence_secs = silence.num_seconds(), threshold_secs = threshold.num_seconds(), "Stale feed");
+                ops_events::record(OpsEventKind::StaleFeed, alert.to_string());
+            }
+            FeedAlert::Resumed { symbol, silence } => {
+                tracing::info!(exchange = %self.label, symbol = %symbol, silence_secs = silence.num_seconds(), "Feed resumed");
+                ops_events::record(OpsEventKind::FeedResumed, alert.to_string());
+            }
+        }
+        tracing::info!("[{}-FEED] {} ({}/{} symbols stale)", self.label, alert, self.stale_count(), self.symbols.len());
+        if let Some(notifier) = self.notifier.clone() {
+            // 通知の遅延でパイプラインを止めない
+            let text = format!("[{}] {}", self.label, alert);
+            tokio::spawn(async move {
+                if let Err(e) = notifier.send(&text).await {
+                    error!("{}", e);
+                }
+            });
+        }
+    }
+
+    /// 約定の受信を記録しながら全てのイベントを後段にそのまま流す
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECONDS));
+        loop {
+            let event = tokio::select! {
+                event = receiver.recv() => match event {
+                    Some(event) => event,
+                    None => break,
+                },
+                _ = ticker.tick() => {
+                    for alert in self.check(Utc::now()) {
+                        self.report(&alert);
+                    }
+                    continue;
+                }
+            };
+            if let MarketEvent::Trade(trade) = &event {
+                if let Some(alert) = self.record(trade) {
+                    self.report(&alert);
+                }
+            }
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+}
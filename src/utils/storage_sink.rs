@@ -0,0 +1,231 @@
+use crate::models::{market_event::MarketEvent, trade::Trade, trade_candle::TradeCandle};
+use async_trait::async_trait;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// シンクごとに書き込みを待たせておけるイベント数 (これを超えるとパイプラインが待つ)
+pub const SINK_QUEUE_CAPACITY: usize = 10_000;
+/// 1 回の flush までにまとめて書く最大件数
+const MAX_BATCH: usize = 1_000;
+const HEALTHCHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 足・約定の書き込み先 (MongoDB, Parquet, ClickHouse, Redis など)
+/// SinkFanout がシンクごとのキューとタスクから呼ぶので, 1 つのシンクが遅れても失敗しても他のシンクには影響しない
+#[async_trait]
+pub trait StorageSink: Send + Sync {
+    /// ログ・状態に出す名前 (e.g. clickhouse)
+    fn name(&self) -> &'static str;
+
+    /// 約定も書くか (false なら write_trade は呼ばれない)
+    fn stores_trades(&self) -> bool;
+
+    async fn write_candle(&self, candle: &TradeCandle) -> anyhow::Result<()>;
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()>;
+
+    /// 受け取った分を書き出す (まとめて書くシンクのみ. キューが空になったとき (flush_interval があればその間隔) と終了時に呼ぶ)
+    async fn flush(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// flush を呼ぶ間隔 (None ならキューが空になるたびに呼ぶ)
+    fn flush_interval(&self) -> Option<Duration> {
+        None
+    }
+
+    /// 受け取った分を溜めて flush で書くシンクが, 実際に書き込めた件数
+    /// (None なら write_candle / write_trade が成功した件数を書き込んだ数とする)
+    fn written(&self) -> Option<u64> {
+        None
+    }
+
+    /// 書き込める状態か (定期的に呼ぶ)
+    async fn healthcheck(&self) -> anyhow::Result<()>;
+}
+
+/// シンクごとの書き込みの状況
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SinkStatus {
+    pub name: String,
+    pub healthy: bool,
+    pub written: u64,
+    pub failed: u64,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    write_failing: bool,  // 直近の書き込み・flush が失敗した
+    #[serde(skip)]
+    check_failing: bool,  // 直近の healthcheck が失敗した
+}
+
+impl SinkStatus {
+    fn new(name: &str) -> Self {
+        Self { name: name.to_string(), healthy: true, ..Default::default() }
+    }
+
+    /// 1 件の書き込みの結果を数える (buffered なら成功は受け取っただけなので数えず, flush 後に sync_written で合わせる)
+    fn record_write(&mut self, result: &anyhow::Result<()>, buffered: bool) {
+        match result {
+            Ok(()) if !buffered => self.written += 1,
+            Ok(()) => {}
+            Err(_) => self.failed += 1,
+        }
+        self.record(result, false);
+    }
+
+    /// 溜めて書くシンクの書き込めた件数を written に反映する
+    fn sync_written(&mut self, sink: &dyn StorageSink) {
+        if let Some(written) = sink.written() {
+            self.written = written;
+        }
+    }
+
+    /// 書き込み・flush の失敗 (check なら healthcheck) の結果を記録し, 障害の開始・復旧をログに出す
+    /// healthy は直近の書き込みと healthcheck の両方が成功していること
+    fn record(&mut self, result: &anyhow::Result<()>, check: bool) {
+        let failing = if check { &mut self.check_failing } else { &mut self.write_failing };
+        *failing = result.is_err();
+        if let Err(e) = result {
+            self.last_error = Some(e.to_string());
+        }
+        let healthy = !self.write_failing && !self.check_failing;
+        if healthy != self.healthy {
+            match result {
+                Err(e) => warn!("Storage sink {} is failing: {}", self.name, e),
+                Ok(()) => info!("Storage sink {} recovered", self.name),
+            }
+            self.healthy = healthy;
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SinkQueue {
+    trades: bool,
+    sender: mpsc::Sender<MarketEvent>,
+    status: Arc<Mutex<SinkStatus>>,
+    task: Arc<Mutex<Option<JoinHandle<()>>>>,  // 書き込みのタスク (finish で待つ)
+}
+
+/// 約定・足を全てのシンクに配るステージ (clone して約定側・足側の両方に挟む)
+/// シンクごとにキューと書き込みのタスクを持ち, 失敗はそのシンクの状況に記録するだけでパイプラインは止めない
+#[derive(Clone, Default)]
+pub struct SinkFanout {
+    sinks: Vec<SinkQueue>,
+}
+
+impl SinkFanout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// シンクを加え, その書き込みのタスクを起動する
+    pub fn with_sink(mut self, sink: Arc<dyn StorageSink>) -> Self {
+        let (sender, receiver) = mpsc::channel(SINK_QUEUE_CAPACITY);
+        let status = Arc::new(Mutex::new(SinkStatus::new(sink.name())));
+        let trades = sink.stores_trades();
+        let task = tokio::spawn(write(sink, receiver, status.clone()));
+        self.sinks.push(SinkQueue { trades, sender, status, task: Arc::new(Mutex::new(Some(task))) });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// シンクの名前
+    pub fn names(&self) -> Vec<String> {
+        self.sinks.iter().map(|sink| sink.status.lock().unwrap().name.clone()).collect()
+    }
+
+    /// シンクごとの書き込みの状況
+    pub fn statuses(&self) -> Vec<SinkStatus> {
+        self.sinks.iter().map(|sink| sink.status.lock().unwrap().clone()).collect()
+    }
+
+    /// 各シンクのキューを閉じ, 残りを書き終えるまで待つ (backfill などの一括処理の最後に, run が全て終わってから呼ぶ)
+    pub async fn finish(self) {
+        let tasks: Vec<_> = self.sinks.into_iter().filter_map(|sink| sink.task.lock().unwrap().take()).collect();
+        for task in tasks {
+            if let Err(e) = task.await {
+                error!("Storage sink writer failed: {}", e);
+            }
+        }
+    }
+
+    /// 足と約定 (約定を書くシンクのみ) を各シンクのキューに送りながら後段にそのまま流す
+    pub async fn run(self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        while let Some(event) = receiver.recv().await {
+            let trade = match &event {
+                MarketEvent::Candle(_) => Some(false),
+                MarketEvent::Trade(_) => Some(true),
+                _ => None,
+            };
+            if let Some(trade) = trade {
+                for sink in self.sinks.iter().filter(|sink| !trade || sink.trades) {
+                    if sink.sender.send(event.clone()).await.is_err() {
+                        error!("Storage sink {} writer stopped", sink.status.lock().unwrap().name);
+                    }
+                }
+            }
+            let kind = event.kind();
+            if let Err(e) = sender.send(event).await {
+                error!("Failed to forward {}: {}", kind, e);
+            }
+        }
+    }
+}
+
+/// 1 つのシンクの書き込みのタスク. 溜まっている分を書き, キューが空になったら (flush_interval があればその間隔で) flush する
+async fn write(sink: Arc<dyn StorageSink>, mut receiver: mpsc::Receiver<MarketEvent>, status: Arc<Mutex<SinkStatus>>) {
+    let mut batch = Vec::with_capacity(MAX_BATCH);
+    let mut healthcheck = tokio::time::interval(HEALTHCHECK_INTERVAL);
+    healthcheck.tick().await;
+    let flush_interval = sink.flush_interval();
+    let buffered = sink.written().is_some();
+    let mut flush = tokio::time::interval(flush_interval.unwrap_or(HEALTHCHECK_INTERVAL));
+    loop {
+        tokio::select! {
+            received = receiver.recv_many(&mut batch, MAX_BATCH) => {
+                if received == 0 {
+                    break;
+                }
+                for event in batch.drain(..) {
+                    let result = match &event {
+                        MarketEvent::Candle(candle) => sink.write_candle(candle).await,
+                        MarketEvent::Trade(trade) => sink.write_trade(trade).await,
+                        _ => continue,
+                    };
+                    status.lock().unwrap().record_write(&result, buffered);
+                }
+                if flush_interval.is_none() && receiver.is_empty() {
+                    let result = sink.flush().await;
+                    if result.is_err() {
+                        status.lock().unwrap().record(&result, false);
+                    }
+                }
+                status.lock().unwrap().sync_written(sink.as_ref());
+            }
+            _ = flush.tick(), if flush_interval.is_some() => {
+                let result = sink.flush().await;
+                let mut status = status.lock().unwrap();
+                if result.is_err() {
+                    status.record(&result, false);
+                }
+                status.sync_written(sink.as_ref());
+            }
+            _ = healthcheck.tick() => {
+                let result = sink.healthcheck().await;
+                status.lock().unwrap().record(&result, true);
+            }
+        }
+    }
+    // 残りを書き出してから終了する
+    if let Err(e) = sink.flush().await {
+        error!("Failed to flush storage sink {}: {}", sink.name(), e);
+    }
+    status.lock().unwrap().sync_written(sink.as_ref());
+}
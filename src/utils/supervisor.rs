@@ -0,0 +1,132 @@
+use super::ops_events::{self, OpsEventKind};
+use futures::FutureExt;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// restart_window の間にこれを超えて再起動が必要になったら諦める (壊れた状態で再起動を繰り返さない)
+pub const DEFAULT_MAX_RESTARTS: usize = 5;
+pub const DEFAULT_RESTART_WINDOW: Duration = Duration::from_secs(600);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// panic の payload の文字列 (panic!("...") / expect などのメッセージ)
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// future を実行し, panic したらそのメッセージを Err で返す
+pub async fn catch_panic<F: Future>(future: F) -> Result<F::Output, String> {
+    AssertUnwindSafe(future).catch_unwind().await.map_err(|payload| panic_message(&*payload))
+}
+
+/// 1 つのコンポーネント (ローソク足の集計, DB 書き込み, 取引所の接続) の panic・予期しない終了を捕まえて再起動する
+/// 再起動の間隔は 1 秒から倍々に延ばし (最大 30 秒), window の間に max_restarts 回を超えたら諦める
+pub struct Supervisor {
+    name: String,
+    max_restarts: usize,
+    window: Duration,
+    restarts: VecDeque<Instant>,  // window 内の再起動の時刻
+}
+
+impl Supervisor {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            window: DEFAULT_RESTART_WINDOW,
+            restarts: VecDeque::new(),
+        }
+    }
+
+    pub fn with_limit(mut self, max_restarts: usize, window: Duration) -> Self {
+        self.max_restarts = max_restarts;
+        self.window = window;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 次の再起動までの待ち時間 (window 内の再起動の回数で倍々にする)
+    pub fn backoff(&self) -> Duration {
+        let exponent = self.restarts.len().saturating_sub(1).min(5) as u32;
+        (Duration::from_secs(1) * 2u32.pow(exponent)).min(MAX_BACKOFF)
+    }
+
+    /// 失敗を記録し, 再起動してよければ待ってから true を返す (上限を超えたら false)
+    pub async fn restart(&mut self, cause: &str) -> bool {
+        let now = Instant::now();
+        while self.restarts.front().is_some_and(|at| now.duration_since(*at) > self.window) {
+            self.restarts.pop_front();
+        }
+        if self.restarts.len() >= self.max_restarts {
+            tracing::error!(component = %self.name, "{} failed {} times within {}s, giving up: {}",
+                self.name, self.restarts.len() + 1, self.window.as_secs(), cause);
+            ops_events::record(OpsEventKind::TaskFailed, format!("{}: {}", self.name, cause));
+            return false;
+        }
+        self.restarts.push_back(now);
+        let backoff = self.backoff();
+        tracing::error!(component = %self.name, "{} failed: {}; restarting in {}s", self.name, cause, backoff.as_secs());
+        ops_events::record(OpsEventKind::TaskRestart, format!("{}: {}", self.name, cause));
+        tokio::time::sleep(backoff).await;
+        true
+    }
+
+    /// receiver から受け取るパイプラインの段を start で起動し, panic したら同じ receiver につなぎ直して起動し直す
+    /// 段には中継用のチャネルを渡す (止まった段に渡せなかったイベントは次の段に渡す. 処理中と中継中のイベント (最大 2 件) は失われる)
+    /// 段が自分で終了したら (receiver が閉じた, スナップショットの後に止めたなど) 戻る
+    /// 再起動の上限を超えたら collector を終了する (外部の再起動に任せる)
+    pub async fn run_stage<T, F, Fut>(mut self, mut receiver: mpsc::Receiver<T>, mut start: F)
+    where
+        T: Send + 'static,
+        F: FnMut(mpsc::Receiver<T>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let mut pending = None;
+        loop {
+            let (relay_tx, relay_rx) = mpsc::channel(1);
+            let mut task = tokio::spawn(start(relay_rx));
+            let joined = loop {
+                let event = match pending.take() {
+                    Some(event) => event,
+                    None => tokio::select! {
+                        event = receiver.recv() => match event {
+                            Some(event) => event,
+                            None => {
+                                drop(relay_tx);
+                                break (&mut task).await;
+                            }
+                        },
+                        joined = &mut task => break joined,
+                    },
+                };
+                if let Err(mpsc::error::SendError(event)) = relay_tx.send(event).await {
+                    pending = Some(event);
+                    break (&mut task).await;
+                }
+            };
+            let cause = match joined {
+                Ok(()) => {
+                    tracing::info!("{} stopped", self.name);
+                    return;
+                }
+                Err(e) if e.is_panic() => format!("panicked: {}", panic_message(&*e.into_panic())),
+                Err(e) => e.to_string(),
+            };
+            if !self.restart(&cause).await {
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -35,6 +35,36 @@ impl SymbolManager {
     pub fn get_symbol_id(&self, exchange: &str, symbol: &str, market_type: &str) -> Option<i32> {
         self.symbol_map.get(&(exchange.to_string(), symbol.to_string(), market_type.to_string())).copied()
     }
+
+    /// 登録されている全ての symbol (symbol_id, exchange, symbol, market_type) を symbol_id 順に返す
+    pub fn entries(&self) -> Vec<(i32, &str, &str, &str)> {
+        let mut entries: Vec<_> = self
+            .symbol_map
+            .iter()
+            .map(|((exchange, symbol, market_type), id)| (*id, exchange.as_str(), symbol.as_str(), market_type.as_str()))
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    /// "bybit:linear:BTCUSDT" の symbol_id
+    pub fn resolve(&self, spec: &str) -> Result<i32> {
+        let (exchange, market_type, symbol) = parse_symbol_spec(spec)?;
+        let market_type = crate::models::market_type::MarketType::parse(&market_type)?;
+        self.get_symbol_id(&exchange, &symbol, market_type.as_str())
+            .ok_or_else(|| anyhow::anyhow!("{} {} ({}) is not in master.csv", exchange, symbol, market_type.as_str()))
+    }
+}
+
+/// "bybit:linear:BTCUSDT" を (exchange, market_type, symbol) にする
+pub fn parse_symbol_spec(spec: &str) -> Result<(String, String, String)> {
+    let mut parts = spec.trim().splitn(3, ':');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(exchange), Some(market_type), Some(symbol)) if !exchange.is_empty() && !market_type.is_empty() && !symbol.is_empty() => {
+            Ok((exchange.to_lowercase(), market_type.to_lowercase(), symbol.to_string()))
+        }
+        _ => Err(anyhow::anyhow!("Invalid symbol: {}. Use <exchange>:<market_type>:<symbol>, e.g. bybit:linear:BTCUSDT", spec)),
+    }
 }
 
 // グローバルインスタンス
@@ -0,0 +1,177 @@
+use crate::models::market_event::MarketEvent;
+use crate::models::trade::{Side, Trade};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::error;
+
+/// symbol ごとの間引き方法
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThrottleRule {
+    Conflate { window_ms: i64 },  // 同じ向きの約定を約定時刻の window_ms 区間ごとに 1 件 (VWAP, 数量は合計) にまとめる
+    Sample { every: u32 },        // every 件に 1 件だけ残す (数量は every 倍して出来高を保つ)
+}
+
+impl ThrottleRule {
+    /// 書式: "50ms" (conflation), "1/10" (sampling)
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        if let Some(window) = spec.strip_suffix("ms") {
+            let window_ms: i64 = window.parse().map_err(|_| anyhow::anyhow!("Invalid conflation window: {}", spec))?;
+            if window_ms <= 0 {
+                return Err(anyhow::anyhow!("Conflation window must be positive: {}", spec));
+            }
+            return Ok(Self::Conflate { window_ms });
+        }
+        if let Some(every) = spec.strip_prefix("1/") {
+            let every: u32 = every.parse().map_err(|_| anyhow::anyhow!("Invalid sampling rate: {}", spec))?;
+            if every == 0 {
+                return Err(anyhow::anyhow!("Sampling rate must be positive: {}", spec));
+            }
+            return Ok(Self::Sample { every });
+        }
+        Err(anyhow::anyhow!("Invalid throttle rule: {}. Use <N>ms (conflate) or 1/<N> (sample)", spec))
+    }
+}
+
+/// 取引所ごとの間引き設定 (symbol 別, * は既定)
+#[derive(Debug, Clone, Default)]
+pub struct ThrottleConfig {
+    rules: HashMap<String, ThrottleRule>,
+    default: Option<ThrottleRule>,
+}
+
+impl ThrottleConfig {
+    /// 書式: "BTCUSDT=50ms,ETHUSDT=1/10,*=20ms"
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (symbol, rule) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid throttle entry: {}. Use SYMBOL=RULE", entry))?;
+            let rule = ThrottleRule::parse(rule)?;
+            match symbol.trim() {
+                "*" => config.default = Some(rule),
+                symbol => {
+                    config.rules.insert(symbol.to_string(), rule);
+                }
+            }
+        }
+        if config.rules.is_empty() && config.default.is_none() {
+            return Err(anyhow::anyhow!("Empty throttle config"));
+        }
+        Ok(config)
+    }
+
+    pub fn rule(&self, symbol: &str) -> Option<ThrottleRule> {
+        self.rules.get(symbol).copied().or(self.default)
+    }
+}
+
+#[derive(Debug)]
+struct PendingTrade {
+    bucket: i64,
+    trade: Trade,
+    notional: f64,
+}
+
+/// 約定を TradeCandleBuilder に渡す前に間引く
+/// 出力は入力 (約定時刻) だけで決まるので, 同じ約定列からは同じ結果になる
+pub struct TradeThrottle {
+    config: ThrottleConfig,
+    pending: HashMap<(String, bool), PendingTrade>,  // (symbol, is_buy) -> 集約中の約定
+    counters: HashMap<String, u64>,
+}
+
+impl TradeThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            config,
+            pending: HashMap::new(),
+            counters: HashMap::new(),
+        }
+    }
+
+    /// 約定を 1 件受け取り, 確定した約定を返す
+    pub fn push(&mut self, trade: Trade) -> Vec<Trade> {
+        match self.config.rule(&trade.symbol) {
+            None => vec![trade],
+            Some(ThrottleRule::Sample { every }) => {
+                let counter = self.counters.entry(trade.symbol.clone()).or_default();
+                let keep = counter.is_multiple_of(every as u64);
+                *counter += 1;
+                if !keep {
+                    return Vec::new();
+                }
+                let quantity = trade.quantity * every as f64;
+                vec![Trade { quantity, ..trade }]
+            }
+            Some(ThrottleRule::Conflate { window_ms }) => {
+                let bucket = trade.timestamp.timestamp_millis().div_euclid(window_ms);
+                let key = (trade.symbol.clone(), matches!(trade.side, Side::Buy));
+                let mut emitted = Vec::new();
+                if let Some(pending) = self.pending.get_mut(&key) {
+                    if pending.bucket == bucket {
+                        pending.notional += trade.price * trade.quantity;
+                        let quantity = pending.trade.quantity + trade.quantity;
+                        pending.trade = Trade {
+                            id: pending.trade.id,
+                            price: if quantity > 0.0 { pending.notional / quantity } else { trade.price },
+                            quantity,
+                            ..trade
+                        };
+                        return emitted;
+                    }
+                    emitted.push(self.pending.remove(&key).unwrap().trade);
+                }
+                let notional = trade.price * trade.quantity;
+                self.pending.insert(key, PendingTrade { bucket, trade, notional });
+                emitted
+            }
+        }
+    }
+
+    /// 区間が now より前に閉じた集約中の約定を返す (約定が途切れた symbol の取り残しを防ぐ)
+    pub fn flush_due(&mut self, now: DateTime<Utc>) -> Vec<Trade> {
+        let now_ms = now.timestamp_millis();
+        let due: Vec<(String, bool)> = self
+            .pending
+            .iter()
+            .filter(|((symbol, _), pending)| match self.config.rule(symbol) {
+                Some(ThrottleRule::Conflate { window_ms }) => (pending.bucket + 1) * window_ms <= now_ms,
+                _ => true,
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut trades: Vec<Trade> = due.iter().filter_map(|key| self.pending.remove(key)).map(|p| p.trade).collect();
+        trades.sort_by_key(|t| t.timestamp);
+        trades
+    }
+
+    /// MarketEvent のうち約定だけを間引いて後段に流す
+    pub async fn run(mut self, mut receiver: mpsc::Receiver<MarketEvent>, sender: mpsc::Sender<MarketEvent>) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_millis(10));
+        loop {
+            let events: Vec<MarketEvent> = tokio::select! {
+                event = receiver.recv() => match event {
+                    Some(MarketEvent::Trade(trade)) => self.push(trade).into_iter().map(MarketEvent::from).collect(),
+                    Some(event) => vec![event],
+                    None => break,
+                },
+                _ = ticker.tick() => self.flush_due(Utc::now()).into_iter().map(MarketEvent::from).collect(),
+            };
+            for event in events {
+                let kind = event.kind();
+                if let Err(e) = sender.send(event).await {
+                    error!("Failed to send {}: {}", kind, e);
+                }
+            }
+        }
+        // 入力が閉じたら集約中の約定を全て出す
+        for trade in self.flush_due(DateTime::<Utc>::MAX_UTC) {
+            if let Err(e) = sender.send(MarketEvent::Trade(trade)).await {
+                error!("Failed to send trade: {}", e);
+            }
+        }
+    }
+}
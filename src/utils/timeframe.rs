@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+
+/// 時間枠の指定 ("60", "1s", "90s", "5m", "4h", "1d") を秒に変換する
+/// 数字だけなら秒として扱う
+pub fn parse(spec: &str) -> anyhow::Result<u32> {
+    let spec = spec.trim();
+    let invalid = || anyhow::anyhow!("Invalid timeframe: {} (use seconds or Ns/Nm/Nh/Nd, e.g., 1,60,5m,1h)", spec);
+    let (value, unit) = match spec.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => spec.split_at(index),
+        None => (spec, "s"),
+    };
+    let value: u32 = value.parse().map_err(|_| invalid())?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(invalid()),
+    };
+    value.checked_mul(multiplier).filter(|&seconds| seconds > 0).ok_or_else(invalid)
+}
+
+/// カンマ区切りの時間枠のリスト
+pub fn parse_list(spec: &str) -> anyhow::Result<Vec<u32>> {
+    let timeframes = spec
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(parse)
+        .collect::<anyhow::Result<Vec<u32>>>()?;
+    if timeframes.is_empty() {
+        return Err(anyhow::anyhow!("Empty timeframe list"));
+    }
+    Ok(timeframes)
+}
+
+/// 割り切れる最大の単位で表した時間枠 (60 -> "1m", 90 -> "90s", 5400 -> "90m")
+pub fn label(seconds: u32) -> String {
+    match seconds {
+        s if s > 0 && s.is_multiple_of(86400) => format!("{}d", s / 86400),
+        s if s > 0 && s.is_multiple_of(3600) => format!("{}h", s / 3600),
+        s if s > 0 && s.is_multiple_of(60) => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// 足の境界の時差 (秒). 境界は (UNIX 時刻 + offset) が時間枠で割り切れる時刻になる
+/// "utc" / "funding" (00/08/16 UTC, UNIX 時刻の境界と同じ), "jst" (UTC+9), "utc+9", "utc-5", "utc+5:30"
+/// 日足は offset の時差の 0 時に, 8 時間足は 0/8/16 時に区切られる. offset で割り切れる短い時間枠は変わらない
+pub fn parse_session_offset(spec: &str) -> anyhow::Result<i64> {
+    let spec = spec.trim().to_ascii_lowercase();
+    let invalid = || anyhow::anyhow!("Invalid session: {} (use utc, funding, jst or utc+H[:MM], e.g., utc+9, utc-5, utc+5:30)", spec);
+    match spec.as_str() {
+        "utc" | "funding" => return Ok(0),
+        "jst" => return Ok(9 * 3600),
+        _ => {}
+    }
+    let rest = spec.strip_prefix("utc").ok_or_else(invalid)?;
+    let (sign, rest) = match rest.split_at_checked(1) {
+        Some(("+", rest)) => (1, rest),
+        Some(("-", rest)) => (-1, rest),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = rest.split_once(':').unwrap_or((rest, "0"));
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes >= 60 {
+        return Err(invalid());
+    }
+    Ok(sign * (hours as i64 * 3600 + minutes as i64 * 60))
+}
+
+/// timestamp を含む足の終端 (切り上げ). offset は parse_session_offset の値
+pub fn bucket_end(timestamp: &DateTime<Utc>, timeframe_seconds: u32, offset_seconds: i64) -> DateTime<Utc> {
+    let period = timeframe_seconds as i64;
+    let shifted = timestamp.timestamp() + offset_seconds;
+    DateTime::from_timestamp(shifted.div_euclid(period) * period + period - offset_seconds, 0).unwrap()
+}
@@ -0,0 +1,284 @@
+use crate::db::Database;
+use crate::models::{market_type::MarketType, trade::{Side, Trade}, trade_candle::TradeCandle};
+use super::storage_sink::StorageSink;
+use async_trait::async_trait;
+use chrono::{DateTime, DurationRound, Utc};
+use mongodb::bson::{doc, spec::BinarySubtype, Binary, Document};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// 約定のまとまりを書き込むコレクション
+pub const TRADE_BLOB_COLLECTION: &str = "trade_blobs";
+/// 書き込みを待たせておける約定数 (これを超えるとパイプラインが待つ)
+const QUEUE_CAPACITY: usize = 100_000;
+/// 分が終わってから遅れて届く約定を待つ時間
+const SEAL_GRACE: Duration = Duration::from_secs(5);
+const SEAL_INTERVAL: Duration = Duration::from_secs(5);
+const ZSTD_LEVEL: i32 = 3;
+/// バイナリの先頭 (形式が変わったら数字を上げる)
+const MAGIC: &[u8; 4] = b"KKT1";
+
+/// 約定のまとまりの圧縮
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeCodec {
+    Zstd,
+    None,
+}
+
+impl TradeCodec {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "zstd" => Ok(Self::Zstd),
+            "none" => Ok(Self::None),
+            s => Err(anyhow::anyhow!("Invalid trade codec: {}. Use zstd or none", s)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            Self::None => "none",
+        }
+    }
+}
+
+/// 1 つの symbol の約定を列ごとに並べたバイナリにする (約定時刻の順. 時刻は差分, 受信・配信時刻は約定時刻との差)
+/// MAGIC, 件数 (u32), 約定時刻, 受信時刻, 配信時刻 (なければ i64::MIN) (i64), 価格, 数量 (f64), 売買 (u8), 約定 ID (u16 の長さ + UTF-8) の順でリトルエンディアン
+pub fn encode(trades: &[Trade], codec: TradeCodec) -> anyhow::Result<Vec<u8>> {
+    let mut trades: Vec<&Trade> = trades.iter().collect();
+    trades.sort_by_key(|trade| trade.timestamp);
+    let mut raw = Vec::with_capacity(8 + trades.len() * 48);
+    raw.extend_from_slice(MAGIC);
+    raw.extend_from_slice(&(trades.len() as u32).to_le_bytes());
+    let mut previous = 0;
+    for trade in &trades {
+        let time = trade.timestamp.timestamp_micros();
+        raw.extend_from_slice(&(time - previous).to_le_bytes());
+        previous = time;
+    }
+    for trade in &trades {
+        raw.extend_from_slice(&(trade.received_at - trade.timestamp).num_microseconds().unwrap_or(0).to_le_bytes());
+    }
+    for trade in &trades {
+        let offset = trade.gateway_timestamp.map_or(i64::MIN, |gateway| (gateway - trade.timestamp).num_microseconds().unwrap_or(0));
+        raw.extend_from_slice(&offset.to_le_bytes());
+    }
+    for trade in &trades {
+        raw.extend_from_slice(&trade.price.to_le_bytes());
+    }
+    for trade in &trades {
+        raw.extend_from_slice(&trade.quantity.to_le_bytes());
+    }
+    for trade in &trades {
+        raw.push(match trade.side {
+            Side::Buy => 0,
+            Side::Sell => 1,
+        });
+    }
+    for trade in &trades {
+        let id = trade.trade_id.as_bytes();
+        let len = u16::try_from(id.len()).map_err(|_| anyhow::anyhow!("Trade id too long: {}", trade.trade_id))?;
+        raw.extend_from_slice(&len.to_le_bytes());
+        raw.extend_from_slice(id);
+    }
+    match codec {
+        TradeCodec::Zstd => Ok(zstd::encode_all(raw.as_slice(), ZSTD_LEVEL)?),
+        TradeCodec::None => Ok(raw),
+    }
+}
+
+/// バイナリの読み出し位置
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self.bytes.get(self.position..self.position + len).ok_or_else(|| anyhow::anyhow!("Truncated trade blob"))?;
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn i64(&mut self) -> anyhow::Result<i64> {
+        Ok(i64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f64(&mut self) -> anyhow::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+}
+
+/// encode の逆. 約定時刻の順の約定を返す
+pub fn decode(bytes: &[u8], codec: TradeCodec, exchange: &str, market_type: &MarketType, symbol: &str) -> anyhow::Result<Vec<Trade>> {
+    let raw = match codec {
+        TradeCodec::Zstd => zstd::decode_all(bytes)?,
+        TradeCodec::None => bytes.to_vec(),
+    };
+    let mut reader = Reader { bytes: &raw, position: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err(anyhow::anyhow!("Not a trade blob"));
+    }
+    let count = u32::from_le_bytes(reader.take(4)?.try_into()?) as usize;
+    let mut trades = Vec::with_capacity(count);
+    let mut time = 0;
+    for _ in 0..count {
+        time += reader.i64()?;
+        let timestamp = DateTime::from_timestamp_micros(time).ok_or_else(|| anyhow::anyhow!("Invalid trade time in blob"))?;
+        trades.push(Trade::new(exchange.to_string(), market_type.clone(), symbol.to_string(), String::new(), 0.0, 0.0, Side::Buy, timestamp));
+    }
+    for trade in &mut trades {
+        trade.received_at = trade.timestamp + chrono::Duration::microseconds(reader.i64()?);
+    }
+    for trade in &mut trades {
+        let offset = reader.i64()?;
+        trade.gateway_timestamp = (offset != i64::MIN).then(|| trade.timestamp + chrono::Duration::microseconds(offset));
+    }
+    for trade in &mut trades {
+        trade.price = reader.f64()?;
+    }
+    for trade in &mut trades {
+        trade.quantity = reader.f64()?;
+    }
+    for trade in &mut trades {
+        trade.side = if reader.take(1)?[0] == 0 { Side::Buy } else { Side::Sell };
+    }
+    for trade in &mut trades {
+        let len = u16::from_le_bytes(reader.take(2)?.try_into()?) as usize;
+        trade.trade_id = String::from_utf8(reader.take(len)?.to_vec())?;
+    }
+    Ok(trades)
+}
+
+/// trade_blobs のドキュメント (1 つの symbol の 1 分の約定)
+/// unixtime は分の開始, metadata は足と同じ (ym, symbol_id)
+pub fn to_document(trades: &[Trade], minute: DateTime<Utc>, symbol_id: i32, codec: TradeCodec) -> anyhow::Result<Document> {
+    let data = encode(trades, codec)?;
+    Ok(doc! {
+        "unixtime": mongodb::bson::DateTime::from_millis(minute.timestamp_millis()),
+        "metadata": {
+            "ym": minute.format("%Y%m").to_string().parse::<i32>().unwrap_or(0),
+            "symbol": symbol_id,
+        },
+        "count": trades.len() as i32,
+        "codec": codec.as_str(),
+        "data": Binary { subtype: BinarySubtype::Generic, bytes: data },
+    })
+}
+
+/// trade_blobs のドキュメントの約定
+pub fn from_document(doc: &Document, exchange: &str, market_type: &MarketType, symbol: &str) -> anyhow::Result<Vec<Trade>> {
+    let codec = TradeCodec::parse(doc.get_str("codec")?)?;
+    decode(&doc.get_binary_generic("data").map_err(|e| anyhow::anyhow!("Invalid trade blob: {}", e))?[..], codec, exchange, market_type, symbol)
+}
+
+/// 約定を CSV にする (時刻は UTC の ISO 8601, 配信時刻がなければ空)
+pub fn write_csv<W: std::io::Write>(writer: &mut W, trades: &[Trade]) -> anyhow::Result<()> {
+    let time = |time: DateTime<Utc>| time.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string();
+    writeln!(writer, "timestamp,received_at,gateway_timestamp,trade_id,price,quantity,side")?;
+    for trade in trades {
+        writeln!(
+            writer, "{},{},{},{},{},{},{}",
+            time(trade.timestamp), time(trade.received_at), trade.gateway_timestamp.map(time).unwrap_or_default(),
+            trade.trade_id, trade.price, trade.quantity,
+            match trade.side {
+                Side::Buy => "buy",
+                Side::Sell => "sell",
+            }
+        )?;
+    }
+    Ok(())
+}
+
+/// 約定を symbol・分ごとにまとめて圧縮し, MongoDB の trade_blobs に書き込むシンク
+/// 分が終わって SEAL_GRACE 経ったら書き込み, それより遅れた約定 (backfill など) は同じ分の別のドキュメントになる
+pub struct TradeBlobSink {
+    sender: mpsc::Sender<Trade>,
+}
+
+impl TradeBlobSink {
+    pub fn start(db: Arc<Database>, codec: TradeCodec) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        tokio::spawn(write(db, codec, receiver));
+        Self { sender }
+    }
+}
+
+#[async_trait]
+impl StorageSink for TradeBlobSink {
+    fn name(&self) -> &'static str {
+        "mongodb-trades"
+    }
+
+    fn stores_trades(&self) -> bool {
+        true
+    }
+
+    /// 足は MongoCandleSink が書き込む
+    async fn write_candle(&self, _candle: &TradeCandle) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn write_trade(&self, trade: &Trade) -> anyhow::Result<()> {
+        self.sender.send(trade.clone()).await.map_err(|_| anyhow::anyhow!("Trade blob writer stopped"))
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        if self.sender.is_closed() {
+            return Err(anyhow::anyhow!("Trade blob writer stopped"));
+        }
+        Ok(())
+    }
+}
+
+/// (exchange, market_type, symbol, 分の開始) -> 約定
+type Buckets = HashMap<(String, MarketType, String, DateTime<Utc>), Vec<Trade>>;
+
+async fn write(db: Arc<Database>, codec: TradeCodec, mut receiver: mpsc::Receiver<Trade>) {
+    let mut buckets = Buckets::new();
+    let mut ticker = tokio::time::interval(SEAL_INTERVAL);
+    loop {
+        tokio::select! {
+            trade = receiver.recv() => match trade {
+                Some(trade) => {
+                    let minute = trade.timestamp.duration_trunc(chrono::Duration::minutes(1)).unwrap_or(trade.timestamp);
+                    buckets.entry((trade.exchange.clone(), trade.market_type.clone(), trade.symbol.clone(), minute)).or_default().push(trade);
+                }
+                None => break,
+            },
+            _ = ticker.tick() => {
+                let sealed = Utc::now() - chrono::Duration::minutes(1) - chrono::Duration::from_std(SEAL_GRACE).unwrap_or_default();
+                let keys: Vec<_> = buckets.keys().filter(|(_, _, _, minute)| *minute <= sealed).cloned().collect();
+                for key in keys {
+                    if let Some(trades) = buckets.remove(&key) {
+                        write_bucket(&db, codec, &key, &trades).await;
+                    }
+                }
+            }
+        }
+    }
+    // 残りを書き出してから終了する
+    for (key, trades) in buckets {
+        write_bucket(&db, codec, &key, &trades).await;
+    }
+}
+
+async fn write_bucket(db: &Database, codec: TradeCodec, (exchange, market_type, symbol, minute): &(String, MarketType, String, DateTime<Utc>), trades: &[Trade]) {
+    use crate::utils::symbol_manager::SYMBOL_MANAGER;
+
+    let Some(symbol_id) = SYMBOL_MANAGER.get_symbol_id(exchange, symbol, market_type.as_str()) else {
+        error!("{} {} ({}) is not in master.csv; dropped {} trades", exchange, symbol, market_type, trades.len());
+        return;
+    };
+    let result = match to_document(trades, *minute, symbol_id, codec) {
+        Ok(doc) => db.insert_trade_blob(symbol, doc).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => debug!("Stored {} {} trades of {}", trades.len(), symbol, minute.format("%H:%M")),
+        Err(e) => error!("Failed to store {} {} trades of {}: {}", trades.len(), symbol, minute, e),
+    }
+}
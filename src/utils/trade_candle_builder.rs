@@ -1,39 +1,225 @@
-use crate::models::{trade::{Trade, Side}, trade_candle::TradeCandle, market_type::MarketType};
+use crate::models::{trade::{Trade, Side, TimestampSource}, trade_candle::{TradeCandle, TradeSizeStats}, market_event::MarketEvent, market_type::MarketType};
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
-use tokio::sync::mpsc;
-use tokio::time::interval;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 use tracing::error;
+use super::audit::{self, AuditReport};
+use super::candle_cache::CandleCache;
+use super::latency::MAX_LIVE_LATENCY_MS;
+use super::quality::QualityTracker;
+use super::stablecoin::StablecoinMerge;
+use super::timeframe;
 
-#[derive(Debug)]
+const DEFAULT_MAX_TRADES_PER_BUFFER: usize = 10_000;  // 中央値の標本として保持する約定サイズの上限
+
+/// 足の中の約定価格の経路 (高値・安値, realized volatility, 向きの変化)
+/// 約定は到着順に扱うので, 遅延約定の訂正では経路が実際の順序と異なることがある
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PricePath {
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    #[serde(default)]
+    pub first_price: Option<f64>,
+    pub last_price: Option<f64>,
+    #[serde(default)]
+    pub first_move: i32,          // 最初の価格変化の向き (足を連結するときに境界の向きの変化を数える)
+    pub last_move: i32,           // 直前の価格変化の向き (1 / -1, 変化がまだなければ 0)
+    pub squared_returns: f64,     // Σ ln(p_t / p_t-1)^2
+    pub direction_changes: i32,
+}
+
+impl PricePath {
+    fn update(&mut self, price: f64) {
+        if price <= 0.0 || !price.is_finite() {
+            return;
+        }
+        self.high = Some(self.high.map_or(price, |high| high.max(price)));
+        self.low = Some(self.low.map_or(price, |low| low.min(price)));
+        if let Some(last) = self.last_price {
+            self.squared_returns += (price / last).ln().powi(2);
+            let direction = if price > last { 1 } else if price < last { -1 } else { 0 };
+            if direction != 0 {
+                if self.last_move != 0 && direction != self.last_move {
+                    self.direction_changes += 1;
+                }
+                if self.first_move == 0 {
+                    self.first_move = direction;
+                }
+                self.last_move = direction;
+            }
+        }
+        self.first_price.get_or_insert(price);
+        self.last_price = Some(price);
+    }
+
+    /// 直後の足の経路を連結する (約定を順に update した場合と同じ結果になる)
+    fn merge(&mut self, next: &PricePath) {
+        let (Some(first), Some(last)) = (next.first_price, next.last_price) else {
+            return;
+        };
+        if self.last_price.is_none() {
+            *self = next.clone();
+            return;
+        }
+        // 境界をまたぐ 1 約定分のリターンと向き
+        self.update(first);
+        self.squared_returns += next.squared_returns;
+        self.direction_changes += next.direction_changes;
+        if next.first_move != 0 {
+            if self.last_move != 0 && next.first_move != self.last_move {
+                self.direction_changes += 1;
+            }
+            if self.first_move == 0 {
+                self.first_move = next.first_move;
+            }
+            self.last_move = next.last_move;
+        }
+        if let (Some(high), Some(low)) = (next.high, next.low) {
+            self.high = Some(self.high.map_or(high, |h| h.max(high)));
+            self.low = Some(self.low.map_or(low, |l| l.min(low)));
+        }
+        self.last_price = Some(last);
+    }
+
+    fn realized_vol(&self) -> Option<f64> {
+        self.last_price.map(|_| self.squared_returns.sqrt())
+    }
+}
+
+/// 足の中の約定サイズ (約定代金) の集計
+/// 最大値とヒストグラムは全約定から, 中央値は上限までの標本から求める (上限を超えたら 1 つおきに間引き, 以後の標本の間隔を倍にする)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeSizes {
+    pub max: Option<f64>,
+    pub histogram: [i32; 5],
+    pub sample: Vec<f64>,
+    pub stride: u64,  // sample に入れる約定の間隔 (0 は 1 と同じ)
+    pub seen: u64,
+}
+
+impl TradeSizes {
+    /// 標本を間引いたら true
+    fn push(&mut self, size: f64, max_samples: usize) -> bool {
+        self.max = Some(self.max.map_or(size, |max| max.max(size)));
+        self.histogram[TradeSizeStats::bucket(size)] += 1;
+        if self.seen.is_multiple_of(self.stride.max(1)) {
+            self.sample.push(size);
+        }
+        self.seen += 1;
+        self.thin(max_samples)
+    }
+
+    fn merge(&mut self, next: &TradeSizes, max_samples: usize) -> bool {
+        if let Some(max) = next.max {
+            self.max = Some(self.max.map_or(max, |m| m.max(max)));
+        }
+        for (count, next_count) in self.histogram.iter_mut().zip(next.histogram) {
+            *count += next_count;
+        }
+        self.sample.extend_from_slice(&next.sample);
+        self.seen += next.seen;
+        self.stride = self.stride.max(next.stride);
+        self.thin(max_samples)
+    }
+
+    fn thin(&mut self, max_samples: usize) -> bool {
+        let mut thinned = false;
+        while max_samples > 0 && self.sample.len() > max_samples {
+            let mut index = 0;
+            self.sample.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.stride = self.stride.max(1) * 2;
+            thinned = true;
+        }
+        thinned
+    }
+
+    fn stats(&self) -> Option<TradeSizeStats> {
+        let mut stats = TradeSizeStats::from_sizes(&self.sample)?;
+        stats.max = self.max.unwrap_or(stats.max);
+        stats.histogram = self.histogram;
+        Some(stats)
+    }
+}
+
+/// 足の中の約定の遅延 (受信時刻 - 約定時刻) の合計 (REST 補完などの古い約定は除く)
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LatencySum {
+    pub sum_ms: f64,
+    pub count: u64,
+}
+
+impl LatencySum {
+    fn push(&mut self, trade: &Trade) {
+        let latency = trade.latency_ms();
+        if latency <= MAX_LIVE_LATENCY_MS {
+            self.sum_ms += latency;
+            self.count += 1;
+        }
+    }
+
+    fn merge(&mut self, next: &LatencySum) {
+        self.sum_ms += next.sum_ms;
+        self.count += next.count;
+    }
+
+    fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum_ms / self.count as f64)
+    }
+}
+
+#[derive(Debug, Clone)]
 struct TradeCandleBuffer {
     // Ask側データ (売り注文側の約定)
     ask_price: Option<f64>,  // 加重平均価格 (VWAP)
     ask_volume: f64,
+    ask_notional: f64,  // quote 通貨建ての約定代金 (Trade::notional の合計)
     ask_count: i32,
     
     // Bid側データ (買い注文側の約定)
     bid_price: Option<f64>,  // 加重平均価格 (VWAP)
     bid_volume: f64,
+    bid_notional: f64,
     bid_count: i32,
     
-    timestamp: DateTime<Utc>,
+    sizes: TradeSizes,
+    path: PricePath,
+    latency: LatencySum,
+    timestamp: DateTime<Utc>,  // 足の終端 (unixtime)
+    received_at: DateTime<Utc>,  // 最後に含めた約定の受信時刻
 }
 
 impl TradeCandleBuffer {
-    fn new(timestamp: DateTime<Utc>) -> Self {
+    fn new(timestamp: DateTime<Utc>, received_at: DateTime<Utc>) -> Self {
         Self {
             ask_price: None,
             ask_volume: 0.0,
+            ask_notional: 0.0,
             ask_count: 0,
             bid_price: None,
             bid_volume: 0.0,
+            bid_notional: 0.0,
             bid_count: 0,
+            sizes: TradeSizes::default(),
+            path: PricePath::default(),
+            latency: LatencySum::default(),
             timestamp,
+            received_at,
         }
     }
 
-    fn update(&mut self, trade: &Trade) {
+    /// 標本を間引いたら true
+    fn update(&mut self, trade: &Trade, max_samples: usize) -> bool {
+        self.received_at = self.received_at.max(trade.received_at);
+        self.latency.push(trade);
+        let thinned = self.sizes.push(trade.notional(), max_samples);
+        self.path.update(trade.price);
         match trade.side {
             Side::Sell => {
                 // Bid側 (売り約定)
@@ -46,6 +232,7 @@ impl TradeCandleBuffer {
                 }
                 
                 self.bid_volume = new_total_volume;
+                self.bid_notional += trade.notional();
                 self.bid_count += 1;
             }
             Side::Buy => {
@@ -59,170 +246,1095 @@ impl TradeCandleBuffer {
                 }
                 
                 self.ask_volume = new_total_volume;
+                self.ask_notional += trade.notional();
                 self.ask_count += 1;
             }
         }
+        thinned
     }
 
-    fn to_trade_candle(&self, exchange: String, market_type: MarketType, symbol: String, period_seconds: i32) -> TradeCandle {
-        // タイムスタンプを時間枠の開始時刻に正規化（切り上げ）
-        let seconds_since_epoch = self.timestamp.timestamp();
-        let candle_start = (seconds_since_epoch / period_seconds as i64) * period_seconds as i64 + period_seconds as i64;
-        let normalized_timestamp = DateTime::from_timestamp(candle_start, 0).unwrap();
-        
+    /// 同じ系列の直後の足を集計に加える (小さい時間枠の足から大きい時間枠の足を作る)
+    /// 標本を間引いたら true
+    fn merge(&mut self, next: &TradeCandleBuffer, max_samples: usize) -> bool {
+        let vwap = |price: Option<f64>, volume: f64, next_price: Option<f64>, next_volume: f64| {
+            let total = volume + next_volume;
+            if total > 0.0 {
+                Some((price.unwrap_or(0.0) * volume + next_price.unwrap_or(0.0) * next_volume) / total)
+            } else {
+                price.or(next_price)
+            }
+        };
+        self.ask_price = vwap(self.ask_price, self.ask_volume, next.ask_price, next.ask_volume);
+        self.ask_volume += next.ask_volume;
+        self.ask_notional += next.ask_notional;
+        self.ask_count += next.ask_count;
+        self.bid_price = vwap(self.bid_price, self.bid_volume, next.bid_price, next.bid_volume);
+        self.bid_volume += next.bid_volume;
+        self.bid_notional += next.bid_notional;
+        self.bid_count += next.bid_count;
+        self.path.merge(&next.path);
+        self.latency.merge(&next.latency);
+        self.received_at = self.received_at.max(next.received_at);
+        self.sizes.merge(&next.sizes, max_samples)
+    }
+
+    fn to_trade_candle(&self, exchange: String, market_type: MarketType, symbol: String, period_seconds: i32, timestamp_source: TimestampSource) -> TradeCandle {
         TradeCandle {
             id: uuid::Uuid::new_v4(),
             exchange,
             market_type,
             symbol,
-            timestamp: normalized_timestamp,
+            timestamp: self.timestamp,
             period_seconds,
             ask_price: self.ask_price,
             ask_volume: self.ask_volume,
+            ask_notional: self.ask_notional,
             ask_count: self.ask_count,
             bid_price: self.bid_price,
             bid_volume: self.bid_volume,
+            bid_notional: self.bid_notional,
             bid_count: self.bid_count,
+            warmup: false,
+            revision: 0,
+            trade_size: self.sizes.stats(),
+            open: self.path.first_price,
+            high: self.path.high,
+            low: self.path.low,
+            close: self.path.last_price,
+            realized_vol: self.path.realized_vol(),
+            direction_changes: self.path.direction_changes,
+            cvd: 0.0,  // 出力時に TradeCandleBuilder が積み上げる
+            timestamp_source,
+            received_at: Some(self.received_at),
+            latency_ms: self.latency.mean(),
         }
     }
 }
 
+/// 集計中の足 1 本 (計画的な再起動で引き継ぐ)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandleBufferSnapshot {
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub timeframe: u32,
+    pub candle_end: DateTime<Utc>,
+    pub ask_price: Option<f64>,
+    pub ask_volume: f64,
+    #[serde(default)]
+    pub ask_notional: f64,
+    pub ask_count: i32,
+    pub bid_price: Option<f64>,
+    pub bid_volume: f64,
+    #[serde(default)]
+    pub bid_notional: f64,
+    pub bid_count: i32,
+    #[serde(default)]
+    pub trade_sizes: TradeSizes,
+    #[serde(default)]
+    pub path: PricePath,
+    #[serde(default)]
+    pub latency: LatencySum,
+    pub received_at: DateTime<Utc>,
+}
+
+/// 直前の足の価格 (emit_empty で引き継ぐ)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastPriceSnapshot {
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub timeframe: u32,
+    pub ask_price: Option<f64>,
+    pub bid_price: Option<f64>,
+}
+
+/// 出力済みの足までの CVD
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CvdSnapshot {
+    pub exchange: String,
+    pub market_type: MarketType,
+    pub symbol: String,
+    pub timeframe: u32,
+    pub cvd: f64,
+}
+
+/// TradeCandleBuilder の実行中の状態
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CandleBuilderSnapshot {
+    pub buffers: Vec<CandleBufferSnapshot>,
+    pub flushed_until: HashMap<u32, DateTime<Utc>>,
+    #[serde(default)]
+    pub last_prices: Vec<LastPriceSnapshot>,
+    #[serde(default)]
+    pub cvd: Vec<CvdSnapshot>,
+}
+
+type SnapshotRequest = (bool, oneshot::Sender<CandleBuilderSnapshot>);
+
+/// 実行中の TradeCandleBuilder から状態を取り出すためのハンドル
+/// TradeCandleBuilder::with_snapshot_control で再起動した builder につなぎ直せる
+#[derive(Debug, Clone)]
+pub struct SnapshotControl {
+    sender: Arc<Mutex<mpsc::Sender<SnapshotRequest>>>,
+}
+
+impl Default for SnapshotControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnapshotControl {
+    /// まだ builder につながっていないハンドル
+    pub fn new() -> Self {
+        let (sender, _) = mpsc::channel(1);
+        Self { sender: Arc::new(Mutex::new(sender)) }
+    }
+
+    /// 集計中の足を出力せずに状態を取り出す
+    /// stop = true なら以後の出力を止めて終了する (スナップショットの後に同じ足を出力して再起動後に重複させない)
+    pub async fn snapshot(&self, stop: bool) -> anyhow::Result<CandleBuilderSnapshot> {
+        let (reply, receiver) = oneshot::channel();
+        let sender = self.sender.lock().unwrap().clone();
+        sender
+            .send((stop, reply))
+            .await
+            .map_err(|_| anyhow::anyhow!("TradeCandleBuilder is not running"))?;
+        receiver.await.map_err(|_| anyhow::anyhow!("TradeCandleBuilder stopped before the snapshot"))
+    }
+}
+
+/// 実行中の TradeCandleBuilder に監査を依頼するハンドル
+/// TradeCandleBuilder::with_audit_control で再起動した builder につなぎ直せる
+#[derive(Debug, Clone)]
+pub struct AuditControl {
+    sender: Arc<Mutex<mpsc::Sender<oneshot::Sender<AuditReport>>>>,
+}
+
+impl Default for AuditControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditControl {
+    /// まだ builder につながっていないハンドル
+    pub fn new() -> Self {
+        let (sender, _) = mpsc::channel(1);
+        Self { sender: Arc::new(Mutex::new(sender)) }
+    }
+
+    /// 保持している出力済みの足を約定から計算し直して比べる
+    pub async fn audit(&self) -> anyhow::Result<AuditReport> {
+        let (reply, receiver) = oneshot::channel();
+        let sender = self.sender.lock().unwrap().clone();
+        sender
+            .send(reply)
+            .await
+            .map_err(|_| anyhow::anyhow!("TradeCandleBuilder is not running"))?;
+        receiver.await.map_err(|_| anyhow::anyhow!("TradeCandleBuilder stopped before the audit"))
+    }
+}
+
+/// 監査用に保持する約定と出力した足
+#[derive(Debug)]
+struct AuditLog {
+    capacity: usize,  // 保持する出力済みの足の数
+    trades: HashMap<BufferKey, Vec<Trade>>,  // 集計中 (と訂正できる出力済み) の足に加えた約定
+    emitted: VecDeque<(TradeCandle, Vec<Trade>)>,
+}
+
+impl AuditLog {
+    fn push(&mut self, candle: TradeCandle, trades: Vec<Trade>) {
+        self.emitted.push_back((candle, trades));
+        while self.emitted.len() > self.capacity {
+            self.emitted.pop_front();
+        }
+    }
+}
+
+/// 出力済みの足 (猶予期間後に届いた遅延約定で訂正する)
+#[derive(Debug)]
+struct FlushedCandle {
+    buffer: TradeCandleBuffer,
+    revision: u32,
+    dirty: bool,  // 出力後に遅延約定を加えた
+    delta: f64,  // 最後に出力した delta と cvd (訂正で差分を CVD に反映する)
+    cvd: f64,
+}
+
+type BufferKey = (String, MarketType, String, u32, DateTime<Utc>);  // (exchange, market_type, symbol, timeframe, 足の終端)
+type SeriesKey = (String, MarketType, String, u32);  // (exchange, market_type, symbol, timeframe)
+type SymbolKey = (String, MarketType, String);  // (exchange, market_type, symbol)
+
+/// ウォームアップ期間中の足の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmupMode {
+    Discard,
+    Flag,  // warmup: true を付けて保存する
+}
+
+impl WarmupMode {
+    pub fn parse(mode: &str) -> anyhow::Result<Self> {
+        match mode.trim() {
+            "discard" => Ok(WarmupMode::Discard),
+            "flag" => Ok(WarmupMode::Flag),
+            m => Err(anyhow::anyhow!("Invalid warmup mode: {}. Use discard or flag", m)),
+        }
+    }
+}
+
+/// ウォームアップ期間の終了時刻 (接続・再接続時に restart() で延長する)
+#[derive(Debug, Clone)]
+pub struct WarmupHandle {
+    until_ms: Arc<AtomicI64>,
+    period: std::time::Duration,
+}
+
+impl WarmupHandle {
+    fn new(period: std::time::Duration) -> Self {
+        Self {
+            until_ms: Arc::new(AtomicI64::new(0)),
+            period,
+        }
+    }
+
+    pub fn restart(&self) {
+        let until = Utc::now().timestamp_millis() + self.period.as_millis() as i64;
+        self.until_ms.store(until, Ordering::Relaxed);
+    }
+
+    /// 足の開始時刻がウォームアップ終了より前なら部分的な集計の可能性がある
+    fn contains(&self, candle: &TradeCandle) -> bool {
+        let candle_start_ms = (candle.timestamp.timestamp() - candle.period_seconds as i64) * 1000;
+        candle_start_ms < self.until_ms.load(Ordering::Relaxed)
+    }
+}
+
+/// 集計中のバッファの指標 (実行中に別タスクから参照する)
+#[derive(Debug, Clone, Default)]
+pub struct BufferMetrics {
+    active: Arc<AtomicU64>,
+    peak: Arc<AtomicU64>,
+    evicted: Arc<AtomicU64>,  // 上限を超えて捨てたバッファの数
+    thinned: Arc<AtomicU64>,  // 約定サイズの標本を間引いた回数
+}
+
+impl BufferMetrics {
+    pub fn active(&self) -> u64 {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    pub fn evicted(&self) -> u64 {
+        self.evicted.load(Ordering::Relaxed)
+    }
+
+    pub fn thinned(&self) -> u64 {
+        self.thinned.load(Ordering::Relaxed)
+    }
+
+    fn observe(&self, active: usize) {
+        self.active.store(active as u64, Ordering::Relaxed);
+        self.peak.fetch_max(active as u64, Ordering::Relaxed);
+    }
+}
+
+impl std::fmt::Display for BufferMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "active:{} peak:{} evicted:{} thinned:{}", self.active(), self.peak(), self.evicted(), self.thinned())
+    }
+}
+
+/// 実行中の TradeCandleBuilder への時間枠の追加・削除
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeframeCommand {
+    Add(u32),
+    Remove(u32),  // 削除時は集計中の足を出力してから止める
+}
+
+/// 実行中の TradeCandleBuilder の時間枠を変更するためのハンドル
+#[derive(Debug, Clone)]
+pub struct TimeframeControl {
+    sender: mpsc::Sender<TimeframeCommand>,
+}
+
+impl TimeframeControl {
+    pub async fn add(&self, timeframe: u32) -> anyhow::Result<()> {
+        self.send(TimeframeCommand::Add(timeframe)).await
+    }
+
+    pub async fn remove(&self, timeframe: u32) -> anyhow::Result<()> {
+        self.send(TimeframeCommand::Remove(timeframe)).await
+    }
+
+    pub async fn send(&self, command: TimeframeCommand) -> anyhow::Result<()> {
+        if let TimeframeCommand::Add(0) = command {
+            return Err(anyhow::anyhow!("Timeframe must be at least 1 second"));
+        }
+        self.sender
+            .send(command)
+            .await
+            .map_err(|_| anyhow::anyhow!("TradeCandleBuilder is not running"))
+    }
+}
+
+/// MarketEvent::Trade を時間枠ごとのローソク足 (MarketEvent::Candle) に集計する
+/// 約定は最小の時間枠の足にだけ加え, その倍数の時間枠の足は出力した最小の時間枠の足を連結して作る
+/// (約定 1 件あたりの処理が時間枠の数によらず, 1s と 1m の値が必ず一致する. 倍数でない時間枠は約定から直接集計する)
+/// 約定以外のイベントはそのまま後段に流す
 pub struct TradeCandleBuilder {
-    trade_receiver: mpsc::Receiver<Trade>,
-    candle_sender: mpsc::Sender<TradeCandle>,
+    event_receiver: mpsc::Receiver<MarketEvent>,
+    event_sender: mpsc::Sender<MarketEvent>,
     timeframes: Vec<u32>, // 時間枠のリスト (秒単位)
-    buffers: HashMap<(String, MarketType, String, u32), TradeCandleBuffer>, // (exchange, market_type, symbol, timeframe) -> buffer
+    cascade_base: Option<u32>, // 約定を直接集計する最小の時間枠 (起動時に決め, 削除されたら連結をやめる)
+    buffers: HashMap<BufferKey, TradeCandleBuffer>,
+    flushed_until: HashMap<u32, DateTime<Utc>>, // 時間枠ごとの出力済みの足の終端
+    grace: Option<chrono::Duration>,
+    session_offset: i64,  // 足の境界の時差 (秒, utils::timeframe::parse_session_offset)
+    flushed: HashMap<BufferKey, FlushedCandle>, // 直前の境界で出力した足 (grace 指定時のみ)
+    emit_empty: bool,
+    last_prices: HashMap<SeriesKey, (Option<f64>, Option<f64>)>, // 最後の (ask_price, bid_price)
+    cvd: HashMap<SeriesKey, f64>,  // 出力した足の delta の累積 (symbol ごとに 1 値なので make_room では消さない)
+    quality: Option<Arc<Mutex<QualityTracker>>>,
+    timestamp_source: TimestampSource,
+    latency: bool,  // 足に約定の平均の遅延を付ける
+    stablecoin_merge: Option<StablecoinMerge>,
+    cache: Option<Arc<Mutex<CandleCache>>>,
+    warmup: Option<(WarmupMode, WarmupHandle)>,
+    max_buffers: usize,  // 0 は無制限
+    max_trades_per_buffer: usize,
+    metrics: BufferMetrics,
+    last_used: HashMap<SymbolKey, u64>,  // symbol ごとの最後に約定を加えた順番 (LRU)
+    use_counter: u64,
+    audit: Option<AuditLog>,
+    audit_receiver: Option<mpsc::Receiver<oneshot::Sender<AuditReport>>>,
+    control_receiver: Option<mpsc::Receiver<TimeframeCommand>>,
+    snapshot_receiver: Option<mpsc::Receiver<(bool, oneshot::Sender<CandleBuilderSnapshot>)>>,
+    timers: HashMap<u32, JoinHandle<()>>,
 }
 
 impl TradeCandleBuilder {
     pub fn new(
-        trade_receiver: mpsc::Receiver<Trade>,
-        candle_sender: mpsc::Sender<TradeCandle>,
+        event_receiver: mpsc::Receiver<MarketEvent>,
+        event_sender: mpsc::Sender<MarketEvent>,
         timeframes: Vec<u32>,
     ) -> Self {
         Self {
-            trade_receiver,
-            candle_sender,
+            event_receiver,
+            event_sender,
+            cascade_base: timeframes.iter().copied().min(),
             timeframes,
             buffers: HashMap::new(),
+            flushed_until: HashMap::new(),
+            grace: None,
+            session_offset: 0,
+            flushed: HashMap::new(),
+            emit_empty: false,
+            last_prices: HashMap::new(),
+            cvd: HashMap::new(),
+            quality: None,
+            timestamp_source: TimestampSource::Exchange,
+            latency: false,
+            stablecoin_merge: None,
+            cache: None,
+            warmup: None,
+            max_buffers: 0,
+            max_trades_per_buffer: DEFAULT_MAX_TRADES_PER_BUFFER,
+            metrics: BufferMetrics::default(),
+            last_used: HashMap::new(),
+            use_counter: 0,
+            audit: None,
+            audit_receiver: None,
+            control_receiver: None,
+            snapshot_receiver: None,
+            timers: HashMap::new(),
+        }
+    }
+
+    /// 再起動せずに時間枠を追加・削除するためのハンドルを作成する
+    pub fn timeframe_control(&mut self) -> TimeframeControl {
+        let (sender, receiver) = mpsc::channel(16);
+        self.control_receiver = Some(receiver);
+        TimeframeControl { sender }
+    }
+
+    /// 実行中の状態 (集計中の足など) を取り出すためのハンドルを作成する
+    pub fn snapshot_control(&mut self) -> SnapshotControl {
+        let control = SnapshotControl::new();
+        self.attach_snapshot_control(&control);
+        control
+    }
+
+    /// 既存のハンドルをこの builder につなぐ (以前の builder へのリクエストは届かなくなる)
+    pub fn with_snapshot_control(mut self, control: &SnapshotControl) -> Self {
+        self.attach_snapshot_control(control);
+        self
+    }
+
+    fn attach_snapshot_control(&mut self, control: &SnapshotControl) {
+        let (sender, receiver) = mpsc::channel(4);
+        self.snapshot_receiver = Some(receiver);
+        *control.sender.lock().unwrap() = sender;
+    }
+
+    /// スナップショットの集計中の足を引き継ぐ (有効でない時間枠の足は捨てる)
+    /// 再起動中に終端を過ぎた足は次のタイマーで出力される (停止中の約定は含まれない)
+    pub fn with_snapshot(mut self, snapshot: CandleBuilderSnapshot) -> Self {
+        for buffer in snapshot.buffers {
+            if !self.timeframes.contains(&buffer.timeframe) {
+                tracing::warn!("Dropping restored {}s candle for {} (timeframe not active)", buffer.timeframe, buffer.symbol);
+                continue;
+            }
+            let key = (buffer.exchange, buffer.market_type, buffer.symbol, buffer.timeframe, buffer.candle_end);
+            self.buffers.insert(key, TradeCandleBuffer {
+                ask_price: buffer.ask_price,
+                ask_volume: buffer.ask_volume,
+                ask_notional: buffer.ask_notional,
+                ask_count: buffer.ask_count,
+                bid_price: buffer.bid_price,
+                bid_volume: buffer.bid_volume,
+                bid_notional: buffer.bid_notional,
+                bid_count: buffer.bid_count,
+                sizes: buffer.trade_sizes,
+                path: buffer.path,
+                latency: buffer.latency,
+                timestamp: buffer.candle_end,
+                received_at: buffer.received_at,
+            });
+        }
+        for (timeframe, until) in snapshot.flushed_until {
+            if self.timeframes.contains(&timeframe) {
+                self.flushed_until.insert(timeframe, until);
+            }
+        }
+        for last in snapshot.last_prices {
+            self.last_prices.insert((last.exchange, last.market_type, last.symbol, last.timeframe), (last.ask_price, last.bid_price));
+        }
+        for series in snapshot.cvd {
+            self.cvd.insert((series.exchange, series.market_type, series.symbol, series.timeframe), series.cvd);
         }
+        tracing::info!("Restored {} open candles", self.buffers.len());
+        self
+    }
+
+    fn snapshot(&self) -> CandleBuilderSnapshot {
+        CandleBuilderSnapshot {
+            buffers: self
+                .buffers
+                .iter()
+                .map(|((exchange, market_type, symbol, timeframe, candle_end), buffer)| CandleBufferSnapshot {
+                    exchange: exchange.clone(),
+                    market_type: market_type.clone(),
+                    symbol: symbol.clone(),
+                    timeframe: *timeframe,
+                    candle_end: *candle_end,
+                    ask_price: buffer.ask_price,
+                    ask_volume: buffer.ask_volume,
+                    ask_notional: buffer.ask_notional,
+                    ask_count: buffer.ask_count,
+                    bid_price: buffer.bid_price,
+                    bid_volume: buffer.bid_volume,
+                    bid_notional: buffer.bid_notional,
+                    bid_count: buffer.bid_count,
+                    trade_sizes: buffer.sizes.clone(),
+                    path: buffer.path.clone(),
+                    latency: buffer.latency,
+                    received_at: buffer.received_at,
+                })
+                .collect(),
+            flushed_until: self.flushed_until.clone(),
+            last_prices: self
+                .last_prices
+                .iter()
+                .map(|((exchange, market_type, symbol, timeframe), &(ask_price, bid_price))| LastPriceSnapshot {
+                    exchange: exchange.clone(),
+                    market_type: market_type.clone(),
+                    symbol: symbol.clone(),
+                    timeframe: *timeframe,
+                    ask_price,
+                    bid_price,
+                })
+                .collect(),
+            cvd: self
+                .cvd
+                .iter()
+                .map(|((exchange, market_type, symbol, timeframe), &cvd)| CvdSnapshot {
+                    exchange: exchange.clone(),
+                    market_type: market_type.clone(),
+                    symbol: symbol.clone(),
+                    timeframe: *timeframe,
+                    cvd,
+                })
+                .collect(),
+        }
+    }
+
+    /// 接続から period 秒以内に始まった足 (開始直後の部分的な足, 再送された約定の集中) を破棄またはフラグ付けする
+    /// period が 0 でも接続時刻をまたぐ足は対象になる
+    pub fn with_warmup(mut self, period: std::time::Duration, mode: WarmupMode) -> Self {
+        self.warmup = Some((mode, WarmupHandle::new(period)));
+        self
+    }
+
+    pub fn warmup_handle(&self) -> Option<WarmupHandle> {
+        self.warmup.as_ref().map(|(_, handle)| handle.clone())
+    }
+
+    /// 集計中のバッファ数と, 1 つの足で約定サイズの標本として保持する約定数の上限 (0 は無制限)
+    /// バッファ数が上限に達したら, 最も長く約定のない symbol の集計中の足を捨てる
+    pub fn with_buffer_limits(mut self, max_buffers: usize, max_trades_per_buffer: usize) -> Self {
+        self.max_buffers = max_buffers;
+        self.max_trades_per_buffer = max_trades_per_buffer;
+        self
+    }
+
+    pub fn buffer_metrics(&self) -> BufferMetrics {
+        self.metrics.clone()
+    }
+
+    /// 既存のカウンタに集計する (再起動した builder でも同じ値を積み上げる)
+    pub fn with_buffer_metrics(mut self, metrics: BufferMetrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// 直近 candles 本の出力済みの足の約定を保持し, AuditControl::audit で計算し直して出力した値と比べる
+    /// 集計中の足の約定も保持するので, 長い時間枠ではその分メモリを使う
+    pub fn with_audit(mut self, candles: usize) -> Self {
+        self.audit = Some(AuditLog { capacity: candles, trades: HashMap::new(), emitted: VecDeque::new() });
+        self
+    }
+
+    pub fn audit_control(&mut self) -> AuditControl {
+        let control = AuditControl::new();
+        self.attach_audit_control(&control);
+        control
+    }
+
+    /// 既存のハンドルをこの builder につなぐ
+    pub fn with_audit_control(mut self, control: &AuditControl) -> Self {
+        self.attach_audit_control(control);
+        self
+    }
+
+    fn attach_audit_control(&mut self, control: &AuditControl) {
+        let (sender, receiver) = mpsc::channel(4);
+        self.audit_receiver = Some(receiver);
+        *control.sender.lock().unwrap() = sender;
+    }
+
+    fn audit_report(&self) -> AuditReport {
+        let mut report = AuditReport { taken_at: Utc::now(), checked: 0, mismatches: Vec::new() };
+        let Some(audit) = &self.audit else {
+            return report;
+        };
+        for (candle, trades) in &audit.emitted {
+            let Some(first) = trades.first() else {
+                continue;
+            };
+            let mut buffer = TradeCandleBuffer::new(candle.timestamp, first.received_at);
+            for trade in trades {
+                buffer.update(trade, 0);
+            }
+            let recomputed = buffer.to_trade_candle(candle.exchange.clone(), candle.market_type.clone(), candle.symbol.clone(), candle.period_seconds, self.timestamp_source);
+            report.checked += 1;
+            report.mismatches.extend(audit::compare(candle, &recomputed));
+        }
+        report
+    }
+
+    /// 受信した約定と出力したローソク足を品質レポート用に記録する
+    pub fn with_quality(mut self, quality: Arc<Mutex<QualityTracker>>) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// 足の時刻に使うタイムスタンプ (既定は約定時刻)
+    pub fn with_timestamp_source(mut self, timestamp_source: TimestampSource) -> Self {
+        self.timestamp_source = timestamp_source;
+        self
+    }
+
+    /// 足に含めた約定の平均の遅延 (受信時刻 - 約定時刻, latency_ms) を付けて出力する
+    pub fn with_latency(mut self) -> Self {
+        self.latency = true;
+        self
+    }
+
+    /// ステーブルコイン建てペアの約定を {BASE}-USD の系列にも集計する (元のペアの足も出力する)
+    pub fn with_stablecoin_merge(mut self, merge: StablecoinMerge) -> Self {
+        self.stablecoin_merge = Some(merge);
+        self
+    }
+
+    /// 足の境界から grace だけ待ってから出力する (約定時刻が境界の直前の約定の到着を待つ)
+    /// grace を過ぎて届いた約定は, 次の境界までなら該当する足を訂正して revision を上げた足を出力する
+    pub fn with_grace(mut self, grace: std::time::Duration) -> Self {
+        self.grace = Some(chrono::Duration::from_std(grace).unwrap_or_default());
+        self
+    }
+
+    /// 足の境界を UNIX 時刻から offset_seconds だけずらす (UTC+9 なら日足は日本時間の 0 時で区切る)
+    /// offset で割り切れる時間枠 (UTC+9 の 1 時間足以下など) の境界は変わらない
+    pub fn with_session_offset(mut self, offset_seconds: i64) -> Self {
+        self.session_offset = offset_seconds;
+        self
+    }
+
+    /// 約定のなかった期間も出来高・件数 0 の足を出力する (価格は直前の足の値を引き継ぐ)
+    /// 一度も約定のない symbol は引き継ぐ価格がないので出力しない
+    pub fn with_emit_empty(mut self) -> Self {
+        self.emit_empty = true;
+        self
+    }
+
+    /// 出力したローソク足をメモリ上のキャッシュにも保持する
+    pub fn with_cache(mut self, cache: Arc<Mutex<CandleCache>>) -> Self {
+        self.cache = Some(cache);
+        self
     }
 
     pub async fn start(mut self) {
         tracing::info!("TradeCandleBuilder started with timeframes: {:?}", self.timeframes);
         
+        if let Some((mode, handle)) = &self.warmup {
+            tracing::info!("Warm-up {:?} for {:?}", mode, handle.period);
+            handle.restart();
+        }
+        
         // 各時間枠用のタスクを作成
         let (trigger_sender, mut trigger_receiver) = mpsc::channel::<u32>(100);
         
         // 各時間枠に対してタイマータスクを起動
-        for &timeframe in &self.timeframes {
-            let sender = trigger_sender.clone();
-            tokio::spawn(async move {
-                let mut interval = interval(std::time::Duration::from_secs(timeframe as u64));
-                tracing::debug!("Timer task started for {}s timeframe", timeframe);
-                loop {
-                    interval.tick().await;
-                    tracing::debug!("Timer tick for {}s timeframe", timeframe);
-                    if sender.send(timeframe).await.is_err() {
-                        tracing::error!("Timer task for {}s timeframe failed to send", timeframe);
-                        break;
-                    }
-                }
-            });
+        for timeframe in self.timeframes.clone() {
+            self.spawn_timer(timeframe, trigger_sender.clone());
         }
         
+        let mut control_receiver = self.control_receiver.take();
+        let mut snapshot_receiver = self.snapshot_receiver.take();
+        let mut audit_receiver = self.audit_receiver.take();
+        
         loop {
             tokio::select! {
-                Some(trade) = self.trade_receiver.recv() => {
-                    self.process_trade(trade);
-                }
+                Some(event) = self.event_receiver.recv() => match event {
+                    MarketEvent::Trade(trade) => self.process_trade(trade),
+                    event => {
+                        let kind = event.kind();
+                        if let Err(e) = self.event_sender.send(event).await {
+                            error!("Failed to forward {}: {}", kind, e);
+                        }
+                    }
+                },
                 Some(timeframe) = trigger_receiver.recv() => {
                     tracing::debug!("Received timer trigger for {}s timeframe", timeframe);
-                    self.flush_candles_for_timeframe(timeframe).await;
+                    // 境界 (+ grace) を過ぎた足 (終端 <= 直前の境界) を出力する
+                    let now = Utc::now() - self.grace.unwrap_or_default();
+                    // 連結する時間枠は, 同じ境界のタイマーの順序によらず最小の時間枠の足を先に出力して連結しておく
+                    if let Some(base) = self.cascade_base.filter(|_| self.is_cascaded(timeframe)) {
+                        let base_boundary = self.bucket_end(&now, base) - chrono::Duration::seconds(base as i64);
+                        self.flush_candles_for_timeframe(base, base_boundary).await;
+                    }
+                    let boundary = self.bucket_end(&now, timeframe) - chrono::Duration::seconds(timeframe as i64);
+                    self.flush_candles_for_timeframe(timeframe, boundary).await;
+                }
+                Some(command) = async { control_receiver.as_mut()?.recv().await } => {
+                    self.apply_timeframe_command(command, &trigger_sender).await;
+                }
+                Some(reply) = async { audit_receiver.as_mut()?.recv().await } => {
+                    let _ = reply.send(self.audit_report());
+                }
+                Some((stop, reply)) = async { snapshot_receiver.as_mut()?.recv().await } => {
+                    let _ = reply.send(self.snapshot());
+                    if stop {
+                        tracing::info!("TradeCandleBuilder stopped after snapshot");
+                        break;
+                    }
+                }
+            }
+        }
+        for (_, handle) in self.timers.drain() {
+            handle.abort();
+        }
+    }
+
+    /// 履歴の約定 (古い順) を集計するバッチモード. タイマーではなく約定時刻が境界を越えたら足を出力する
+    /// receiver が閉じたら終端が until 以前の足を全て出力し, until より後に終わる (約定が揃っていない) 足は捨てる
+    pub async fn run_batch(mut self, until: DateTime<Utc>) {
+        tracing::info!("TradeCandleBuilder (batch) started with timeframes: {:?}", self.timeframes);
+        let mut timeframes = self.timeframes.clone();
+        // 連結する時間枠より先に最小の時間枠を出力する
+        timeframes.sort();
+        let mut boundaries: HashMap<u32, DateTime<Utc>> = HashMap::new();
+        while let Some(event) = self.event_receiver.recv().await {
+            let trade = match event {
+                MarketEvent::Trade(trade) => trade,
+                event => {
+                    let kind = event.kind();
+                    if let Err(e) = self.event_sender.send(event).await {
+                        error!("Failed to forward {}: {}", kind, e);
+                    }
+                    continue;
+                }
+            };
+            let now = trade.timestamp_for(self.timestamp_source);
+            for &timeframe in &timeframes {
+                let boundary = self.bucket_end(&now, timeframe) - chrono::Duration::seconds(timeframe as i64);
+                if boundaries.get(&timeframe).is_none_or(|last| *last < boundary) {
+                    self.flush_candles_for_timeframe(timeframe, boundary).await;
+                    boundaries.insert(timeframe, boundary);
                 }
             }
+            self.process_trade(trade);
+        }
+        for &timeframe in &timeframes {
+            self.flush_candles_for_timeframe(timeframe, until).await;
+        }
+        tracing::info!("TradeCandleBuilder (batch) finished; {} incomplete candles after {} discarded", self.buffers.len(), until);
+    }
+
+    /// 時刻の境界 (:00 など, session_offset だけずらした UNIX 時刻で割り切れる時刻) ごとに発火するタイマー
+    /// 起動時刻からの interval ではなく毎回次の境界までの時間を計算する (時計のずれを蓄積しない)
+    fn spawn_timer(&mut self, timeframe: u32, sender: mpsc::Sender<u32>) {
+        // grace だけ遅らせ, session_offset だけずらした時刻で境界を計算する
+        let shift_ms = self.grace.map_or(0, |grace| grace.num_milliseconds()) - self.session_offset * 1000;
+        let handle = tokio::spawn(async move {
+            tracing::debug!("Timer task started for {}s timeframe", timeframe);
+            let period_ms = timeframe as i64 * 1000;
+            loop {
+                let now_ms = Utc::now().timestamp_millis() - shift_ms;
+                let next_ms = (now_ms / period_ms + 1) * period_ms;
+                tokio::time::sleep(std::time::Duration::from_millis((next_ms - now_ms) as u64)).await;
+                tracing::debug!("Timer tick for {}s timeframe", timeframe);
+                if sender.send(timeframe).await.is_err() {
+                    tracing::error!("Timer task for {}s timeframe failed to send", timeframe);
+                    break;
+                }
+            }
+        });
+        self.timers.insert(timeframe, handle);
+    }
+
+    async fn apply_timeframe_command(&mut self, command: TimeframeCommand, trigger_sender: &mpsc::Sender<u32>) {
+        match command {
+            TimeframeCommand::Add(timeframe) => {
+                if self.timeframes.contains(&timeframe) {
+                    tracing::warn!("Timeframe {}s is already active", timeframe);
+                    return;
+                }
+                self.timeframes.push(timeframe);
+                self.spawn_timer(timeframe, trigger_sender.clone());
+                tracing::info!("Added {}s timeframe: {:?}", timeframe, self.timeframes);
+            }
+            TimeframeCommand::Remove(timeframe) => {
+                if !self.timeframes.contains(&timeframe) {
+                    tracing::warn!("Timeframe {}s is not active", timeframe);
+                    return;
+                }
+                if let Some(handle) = self.timers.remove(&timeframe) {
+                    handle.abort();
+                }
+                // 集計中の足を出力してから時間枠を外す
+                self.flush_candles_for_timeframe(timeframe, DateTime::<Utc>::MAX_UTC).await;
+                self.flushed_until.remove(&timeframe);
+                self.last_prices.retain(|key, _| key.3 != timeframe);
+                self.cvd.retain(|key, _| key.3 != timeframe);
+                self.timeframes.retain(|&tf| tf != timeframe);
+                if self.cascade_base == Some(timeframe) {
+                    // 以後は全ての時間枠を約定から直接集計する
+                    tracing::info!("Cascading from {}s disabled", timeframe);
+                    self.cascade_base = None;
+                }
+                tracing::info!("Removed {}s timeframe: {:?}", timeframe, self.timeframes);
+            }
         }
     }
 
     fn process_trade(&mut self, trade: Trade) {
-        // 各時間枠に対して処理
-        for &timeframe in &self.timeframes {
-            let key = (
-                trade.exchange.clone(), 
-                trade.market_type.clone(), 
-                trade.symbol.clone(),
-                timeframe
-            );
-            
-            // バッファが存在しない場合は作成、存在する場合は更新のみ
-            self.buffers
-                .entry(key.clone())
-                .and_modify(|buffer| {
-                    buffer.update(&trade);
-                })
-                .or_insert_with(|| {
-                    tracing::debug!("Creating new buffer for {} {} {}s", 
-                        trade.exchange, trade.symbol, timeframe);
-                    let mut buffer = TradeCandleBuffer::new(trade.timestamp);
-                    buffer.update(&trade);
-                    buffer
-                });
+        if let Some(quality) = &self.quality {
+            quality.lock().unwrap().record_trade(&trade);
+        }
+        
+        let merged = self
+            .stablecoin_merge
+            .as_ref()
+            .and_then(|merge| merge.logical_symbol(&trade.symbol))
+            .map(|symbol| Trade { symbol, ..trade.clone() });
+        self.add_to_buffers(trade);
+        if let Some(merged) = merged {
+            self.add_to_buffers(merged);
+        }
+    }
+
+    /// 最小の時間枠の足から連結して作る時間枠か
+    fn is_cascaded(&self, timeframe: u32) -> bool {
+        self.cascade_base.is_some_and(|base| timeframe != base && timeframe.is_multiple_of(base))
+    }
+
+    fn add_to_buffers(&mut self, trade: Trade) {
+        let timestamp = trade.timestamp_for(self.timestamp_source);
+        // 最小の時間枠の集計中の足に入った約定は, その足を出力するときに大きい時間枠に連結される
+        // 出力済みの足に入った (または捨てた) 遅延約定は, 大きい時間枠にも直接加える
+        let rolled_up = match self.cascade_base {
+            Some(base) => self.add_to_timeframe(&trade, timestamp, base),
+            None => false,
+        };
+        let timeframes: Vec<u32> = self
+            .timeframes
+            .iter()
+            .copied()
+            .filter(|&tf| Some(tf) != self.cascade_base && !(rolled_up && self.is_cascaded(tf)))
+            .collect();
+        for timeframe in timeframes {
+            self.add_to_timeframe(&trade, timestamp, timeframe);
+        }
+    }
+
+    /// 約定を 1 つの時間枠の足に加える (集計中の足に入れたら true, 出力済みの足の訂正や破棄なら false)
+    fn add_to_timeframe(&mut self, trade: &Trade, timestamp: DateTime<Utc>, timeframe: u32) -> bool {
+        let max_samples = self.max_trades_per_buffer;
+        // 約定時刻で足を決める
+        // 出力済みの足に届いた遅延約定は, 重複した足を出さないように集計中の最も古い足に入れる
+        let mut candle_end = self.bucket_end(&timestamp, timeframe);
+        if let Some(&flushed_until) = self.flushed_until.get(&timeframe) {
+            if candle_end <= flushed_until && self.grace.is_some() {
+                let key = (trade.exchange.clone(), trade.market_type.clone(), trade.symbol.clone(), timeframe, candle_end);
+                match self.flushed.get_mut(&key) {
+                    Some(flushed) => {
+                        if flushed.buffer.update(trade, max_samples) {
+                            self.metrics.thinned.fetch_add(1, Ordering::Relaxed);
+                        }
+                        flushed.dirty = true;
+                        if let Some(audit) = &mut self.audit {
+                            audit.trades.entry(key).or_default().push(trade.clone());
+                        }
+                    }
+                    None => tracing::warn!("Dropping late trade for {}s candle: {} {} @ {}", timeframe, trade.exchange, trade.symbol, timestamp),
+                }
+                return false;
+            }
+            if candle_end <= flushed_until {
+                tracing::debug!("Late trade for flushed {}s candle: {} {} @ {}", timeframe, trade.exchange, trade.symbol, timestamp);
+                candle_end = flushed_until + chrono::Duration::seconds(timeframe as i64);
+            }
+        }
+        let key = (
+            trade.exchange.clone(), 
+            trade.market_type.clone(), 
+            trade.symbol.clone(),
+            timeframe,
+            candle_end,
+        );
+        
+        self.use_counter += 1;
+        let symbol_key = (trade.exchange.clone(), trade.market_type.clone(), trade.symbol.clone());
+        self.last_used.insert(symbol_key.clone(), self.use_counter);
+        if !self.buffers.contains_key(&key) {
+            self.make_room(&symbol_key);
+        }
+        
+        if let Some(audit) = &mut self.audit {
+            audit.trades.entry(key.clone()).or_default().push(trade.clone());
+        }
+        
+        // バッファが存在しない場合は作成、存在する場合は更新のみ
+        let thinned = match self.buffers.entry(key) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut().update(trade, max_samples),
+            std::collections::hash_map::Entry::Vacant(entry) => {
+                tracing::debug!("Creating new buffer for {} {} {}s", 
+                    trade.exchange, trade.symbol, timeframe);
+                let mut buffer = TradeCandleBuffer::new(candle_end, trade.received_at);
+                let thinned = buffer.update(trade, max_samples);
+                entry.insert(buffer);
+                thinned
+            }
+        };
+        if thinned {
+            self.metrics.thinned.fetch_add(1, Ordering::Relaxed);
         }
+        self.metrics.observe(self.buffers.len());
+        true
     }
 
-    fn get_candle_timestamp(&self, timestamp: &DateTime<Utc>, timeframe_seconds: u32) -> DateTime<Utc> {
-        let seconds_since_epoch = timestamp.timestamp();
-        let candle_start = (seconds_since_epoch / timeframe_seconds as i64) * timeframe_seconds as i64 + timeframe_seconds as i64;
-        DateTime::from_timestamp(candle_start, 0).unwrap()
+    /// バッファ数が上限に達していたら, keep 以外で最も長く約定のない symbol の集計中の足を捨てる
+    fn make_room(&mut self, keep: &SymbolKey) {
+        if self.max_buffers == 0 {
+            return;
+        }
+        while self.buffers.len() >= self.max_buffers {
+            let Some(victim) = self
+                .last_used
+                .iter()
+                .filter(|(symbol_key, _)| *symbol_key != keep)
+                .min_by_key(|(_, &used)| used)
+                .map(|(symbol_key, _)| symbol_key.clone())
+            else {
+                break;
+            };
+            let is_victim = |exchange: &String, market_type: &MarketType, symbol: &String| {
+                *exchange == victim.0 && *market_type == victim.1 && *symbol == victim.2
+            };
+            let before = self.buffers.len();
+            self.buffers.retain(|key, _| !is_victim(&key.0, &key.1, &key.2));
+            self.flushed.retain(|key, _| !is_victim(&key.0, &key.1, &key.2));
+            self.last_prices.retain(|key, _| !is_victim(&key.0, &key.1, &key.2));
+            if let Some(audit) = &mut self.audit {
+                audit.trades.retain(|key, _| !is_victim(&key.0, &key.1, &key.2));
+            }
+            self.last_used.remove(&victim);
+            let evicted = before - self.buffers.len();
+            if evicted > 0 {
+                self.metrics.evicted.fetch_add(evicted as u64, Ordering::Relaxed);
+                tracing::warn!("Evicted {} open candles of least recently traded {} {} (limit {} buffers)",
+                    evicted, victim.0, victim.2, self.max_buffers);
+            }
+        }
     }
 
-    async fn flush_candles_for_timeframe(&mut self, timeframe: u32) {
-        let current_time = Utc::now();
-        let candle_timestamp = self.get_candle_timestamp(&current_time, timeframe);
+    /// timestamp を含む足の終端 (切り上げ, session_offset の時差で区切る)
+    fn bucket_end(&self, timestamp: &DateTime<Utc>, timeframe_seconds: u32) -> DateTime<Utc> {
+        timeframe::bucket_end(timestamp, timeframe_seconds, self.session_offset)
+    }
+
+    /// 終端が until 以前の足を出力する
+    async fn flush_candles_for_timeframe(&mut self, timeframe: u32, until: DateTime<Utc>) {
+        tracing::debug!("Flushing {}s candles until {}", timeframe, until.format("%H:%M:%S"));
+        
+        // 前回出力後に遅延約定が届いた足の訂正を出力する (訂正できるのは次の境界まで)
+        let timestamp_source = self.timestamp_source;
+        let latency = self.latency;
+        let cvd = &mut self.cvd;
+        let corrections: Vec<(BufferKey, TradeCandle)> = self
+            .flushed
+            .iter_mut()
+            .filter(|(key, flushed)| key.3 == timeframe && flushed.dirty)
+            .map(|(key, flushed)| {
+                flushed.revision += 1;
+                flushed.dirty = false;
+                let (exchange, market_type, symbol, tf, _) = key;
+                let mut candle = flushed.buffer.to_trade_candle(exchange.clone(), market_type.clone(), symbol.clone(), *tf as i32, timestamp_source);
+                candle.revision = flushed.revision;
+                if !latency {
+                    candle.latency_ms = None;
+                }
+                // 訂正は次の足の出力より前なので, 差分を足せば以後の足の CVD も訂正後の値になる
+                let change = candle.delta() - flushed.delta;
+                *cvd.entry((exchange.clone(), market_type.clone(), symbol.clone(), *tf)).or_default() += change;
+                flushed.delta = candle.delta();
+                flushed.cvd += change;
+                candle.cvd = flushed.cvd;
+                (key.clone(), candle)
+            })
+            .collect();
+        self.flushed.retain(|key, _| key.3 != timeframe);
+        if let Some(audit) = &mut self.audit {
+            for (key, candle) in &corrections {
+                let trades = audit.trades.get(key).cloned().unwrap_or_default();
+                audit.push(candle.clone(), trades);
+            }
+            let (buffers, flushed) = (&self.buffers, &self.flushed);
+            audit.trades.retain(|key, _| key.3 != timeframe || buffers.contains_key(key) || flushed.contains_key(key));
+        }
+        for (_, candle) in corrections {
+            tracing::debug!("Sending {}s correction: {} {} @ {} (revision {})",
+                timeframe, candle.exchange, candle.symbol, candle.timestamp.format("%H:%M:%S"), candle.revision);
+            if let Some(cache) = &self.cache {
+                cache.lock().unwrap().push(&candle);
+            }
+            if let Err(e) = self.event_sender.send(MarketEvent::Candle(candle)).await {
+                error!("Failed to send trade candle correction: {}", e);
+            }
+        }
         
-        tracing::debug!("Flushing {}s candles at {} (candle_timestamp: {})", 
-            timeframe, 
-            current_time.format("%H:%M:%S.%3f"),
-            candle_timestamp.format("%H:%M:%S"));
+        // 約定のなかった足を直前の価格で埋める
+        if self.emit_empty && until < DateTime::<Utc>::MAX_UTC {
+            self.fill_empty_buffers(timeframe, until);
+        }
         
         // 該当する時間枠のバッファを収集して送信
         let mut buffers_to_remove = Vec::new();
+        let mut sent = HashMap::new();  // 出力した足の (delta, cvd)
+        let mut audited = HashMap::new();
         let mut found_buffers = 0;
         let mut sent_candles = 0;
         
-        for ((exchange, market_type, symbol, tf), buffer) in &self.buffers {
-            if *tf == timeframe {
-                found_buffers += 1;
-                tracing::debug!("Found buffer for {}s: {} {} (ask_cnt:{}, bid_cnt:{})", 
-                    timeframe, exchange, symbol, buffer.ask_count, buffer.bid_count);
+        // CVD を積み上げるため, 同じ symbol の複数の足は古い順に出力する
+        let mut due: Vec<(&BufferKey, &TradeCandleBuffer)> = self.buffers.iter().filter(|(key, _)| key.3 == timeframe && key.4 <= until).collect();
+        due.sort_by_key(|(key, _)| key.4);
+        for ((exchange, market_type, symbol, tf, candle_end), buffer) in due {
+            found_buffers += 1;
+            tracing::debug!("Found buffer for {}s: {} {} (ask_cnt:{}, bid_cnt:{})", 
+                timeframe, exchange, symbol, buffer.ask_count, buffer.bid_count);
+            
+            // バッファにデータがある場合のみ送信 (emit_empty では価格を引き継いだ空の足も送信)
+            let has_trades = buffer.ask_count > 0 || buffer.bid_count > 0;
+            if has_trades || self.emit_empty {
+                let mut candle = buffer.to_trade_candle(
+                    exchange.clone(), 
+                    market_type.clone(), 
+                    symbol.clone(),
+                    timeframe as i32,
+                    self.timestamp_source,
+                );
+                if !self.latency {
+                    candle.latency_ms = None;
+                }
+                
+                tracing::debug!("Sending {}s candle: {} {} @ {} (ask_cnt:{}, bid_cnt:{})", 
+                    timeframe, exchange, symbol, 
+                    candle_end.format("%H:%M:%S"),
+                    buffer.ask_count, buffer.bid_count);
                 
-                // バッファにデータがある場合のみ送信
-                if buffer.ask_count > 0 || buffer.bid_count > 0 {
-                    let candle = buffer.to_trade_candle(
-                        exchange.clone(), 
-                        market_type.clone(), 
-                        symbol.clone(),
-                        timeframe as i32
-                    );
-                    
-                    tracing::debug!("Sending {}s candle: {} {} @ {} (ask_cnt:{}, bid_cnt:{})", 
-                        timeframe, exchange, symbol, 
-                        candle_timestamp.format("%H:%M:%S"),
-                        buffer.ask_count, buffer.bid_count);
-                    
-                    if let Err(e) = self.candle_sender.send(candle).await {
-                        error!("Failed to send trade candle: {}", e);
-                    } else {
-                        sent_candles += 1;
+                if let Some((mode, handle)) = &self.warmup {
+                    if handle.contains(&candle) {
+                        match mode {
+                            WarmupMode::Discard => {
+                                tracing::debug!("Discarding warm-up {}s candle: {} {}", timeframe, exchange, symbol);
+                                buffers_to_remove.push((exchange.clone(), market_type.clone(), symbol.clone(), *tf, *candle_end));
+                                continue;
+                            }
+                            WarmupMode::Flag => candle.warmup = true,
+                        }
                     }
-                } else {
-                    tracing::debug!("Skipping empty buffer for {}s: {} {}", 
-                        timeframe, exchange, symbol);
                 }
                 
-                // このバッファを削除対象に追加
-                buffers_to_remove.push((exchange.clone(), market_type.clone(), symbol.clone(), *tf));
+                if has_trades {
+                    if let Some(quality) = &self.quality {
+                        quality.lock().unwrap().record_candle(&candle);
+                    }
+                }
+                if self.emit_empty {
+                    let last = self.last_prices.entry((exchange.clone(), market_type.clone(), symbol.clone(), *tf)).or_default();
+                    *last = (candle.ask_price.or(last.0), candle.bid_price.or(last.1));
+                }
+                let cvd = self.cvd.entry((exchange.clone(), market_type.clone(), symbol.clone(), *tf)).or_default();
+                *cvd += candle.delta();
+                candle.cvd = *cvd;
+                if let Some(cache) = &self.cache {
+                    cache.lock().unwrap().push(&candle);
+                }
+                if self.audit.is_some() && has_trades {
+                    audited.insert((exchange.clone(), market_type.clone(), symbol.clone(), *tf, *candle_end), candle.clone());
+                }
+                
+                let emitted = (candle.delta(), candle.cvd);
+                if let Err(e) = self.event_sender.send(MarketEvent::Candle(candle)).await {
+                    error!("Failed to send trade candle: {}", e);
+                } else {
+                    sent_candles += 1;
+                    sent.insert((exchange.clone(), market_type.clone(), symbol.clone(), *tf, *candle_end), emitted);
+                }
+            } else {
+                tracing::debug!("Skipping empty buffer for {}s: {} {}", 
+                    timeframe, exchange, symbol);
             }
+            
+            // このバッファを削除対象に追加
+            buffers_to_remove.push((exchange.clone(), market_type.clone(), symbol.clone(), *tf, *candle_end));
         }
         
         tracing::debug!("Flush {}s summary: found {} buffers, sent {} candles, removing {} buffers", 
@@ -230,7 +1342,81 @@ impl TradeCandleBuilder {
         
         // 送信したバッファをクリア
         for key in &buffers_to_remove {
-            self.buffers.remove(key);
+            let Some(buffer) = self.buffers.remove(key) else {
+                continue;
+            };
+            if self.cascade_base == Some(timeframe) && (buffer.ask_count > 0 || buffer.bid_count > 0) {
+                self.roll_up(key, &buffer);
+            }
+            let emitted = sent.get(key).copied().filter(|_| self.grace.is_some() && until < DateTime::<Utc>::MAX_UTC);
+            let keep_flushed = emitted.is_some();
+            if let Some(audit) = &mut self.audit {
+                // 訂正できる間は約定を残す
+                let trades = if keep_flushed { audit.trades.get(key).cloned() } else { audit.trades.remove(key) };
+                if let Some(candle) = audited.remove(key) {
+                    audit.push(candle, trades.unwrap_or_default());
+                }
+            }
+            if let Some((delta, cvd)) = emitted {
+                self.flushed.insert(key.clone(), FlushedCandle { buffer, revision: 0, dirty: false, delta, cvd });
+            }
         }
+        if until < DateTime::<Utc>::MAX_UTC {
+            self.flushed_until.insert(timeframe, until);
+        }
+        self.metrics.observe(self.buffers.len());
     }
-}
\ No newline at end of file
+
+    /// 出力した最小の時間枠の足を, それを含む大きい時間枠の集計中の足に加える
+    fn roll_up(&mut self, base_key: &BufferKey, buffer: &TradeCandleBuffer) {
+        let (exchange, market_type, symbol, base, base_end) = base_key;
+        let start = *base_end - chrono::Duration::seconds(*base as i64);
+        let timeframes: Vec<u32> = self.timeframes.iter().copied().filter(|&tf| self.is_cascaded(tf)).collect();
+        for timeframe in timeframes {
+            let candle_end = self.bucket_end(&start, timeframe);
+            if self.flushed_until.get(&timeframe).is_some_and(|&until| candle_end <= until) {
+                tracing::warn!("Dropping {}s candle roll-up into flushed {}s candle: {} {} @ {}", base, timeframe, exchange, symbol, candle_end);
+                continue;
+            }
+            let key = (exchange.clone(), market_type.clone(), symbol.clone(), timeframe, candle_end);
+            if !self.buffers.contains_key(&key) {
+                self.make_room(&(exchange.clone(), market_type.clone(), symbol.clone()));
+            }
+            if let Some(audit) = &mut self.audit {
+                let trades = audit.trades.get(base_key).cloned().unwrap_or_default();
+                audit.trades.entry(key.clone()).or_default().extend(trades);
+            }
+            let thinned = self
+                .buffers
+                .entry(key)
+                .or_insert_with(|| TradeCandleBuffer::new(candle_end, buffer.received_at))
+                .merge(buffer, self.max_trades_per_buffer);
+            if thinned {
+                self.metrics.thinned.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// 約定のあった symbol のうち, 前回の出力から until までに足のない区間に空のバッファを作る
+    fn fill_empty_buffers(&mut self, timeframe: u32, until: DateTime<Utc>) {
+        let period = chrono::Duration::seconds(timeframe as i64);
+        let first = self.flushed_until.get(&timeframe).map_or(until, |&flushed_until| flushed_until + period);
+        for ((exchange, market_type, symbol, tf), &(ask_price, bid_price)) in &self.last_prices {
+            if *tf != timeframe {
+                continue;
+            }
+            let mut candle_end = first;
+            while candle_end <= until {
+                let key = (exchange.clone(), market_type.clone(), symbol.clone(), timeframe, candle_end);
+                self.buffers.entry(key).or_insert_with(|| {
+                    tracing::debug!("Creating empty {}s buffer for {} {} @ {}", timeframe, exchange, symbol, candle_end.format("%H:%M:%S"));
+                    let mut buffer = TradeCandleBuffer::new(candle_end, candle_end);
+                    buffer.ask_price = ask_price;
+                    buffer.bid_price = bid_price;
+                    buffer
+                });
+                candle_end += period;
+            }
+        }
+    }
+}
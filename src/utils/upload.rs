@@ -0,0 +1,88 @@
+use crate::models::trade_candle::TradeCandle;
+use super::candle_frame;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+/// アップロードする期間の単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UploadPeriod {
+    Hour,
+    Day,
+}
+
+impl UploadPeriod {
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        match spec.trim().to_lowercase().as_str() {
+            "hour" => Ok(Self::Hour),
+            "day" => Ok(Self::Day),
+            s => Err(anyhow::anyhow!("Invalid period: {}. Use hour or day", s)),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Hour => Duration::hours(1),
+            Self::Day => Duration::days(1),
+        }
+    }
+
+    /// time を含む期間の開始 (UTC)
+    pub fn floor(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        time.duration_trunc(self.duration()).unwrap_or(time)
+    }
+
+    /// 期間ごとのファイルの既定の置き場所
+    pub fn default_layout(&self) -> &'static str {
+        match self {
+            Self::Hour => "{exchange}/{market}/{symbol}/{timeframe}/{date}/{hour}.parquet",
+            Self::Day => "{exchange}/{market}/{symbol}/{timeframe}/{year}/{month}/{date}.parquet",
+        }
+    }
+}
+
+/// 期間 [start, start + period) のファイルの場所 (prefix からの相対パス)
+/// {exchange} {market} {symbol} {timeframe} (e.g. 1m) {date} (YYYY-MM-DD) {year} {month} {day} {hour} を置き換える
+pub fn object_path(layout: &str, exchange: &str, market: &str, symbol: &str, timeframe: &str, start: DateTime<Utc>) -> String {
+    layout
+        .replace("{exchange}", exchange)
+        .replace("{market}", market)
+        .replace("{symbol}", symbol)
+        .replace("{timeframe}", timeframe)
+        .replace("{date}", &start.format("%Y-%m-%d").to_string())
+        .replace("{year}", &start.format("%Y").to_string())
+        .replace("{month}", &start.format("%m").to_string())
+        .replace("{day}", &start.format("%d").to_string())
+        .replace("{hour}", &start.format("%H").to_string())
+}
+
+/// next_start から, 終わってから delay 経った期間の開始時刻
+pub fn completed_periods(next_start: DateTime<Utc>, now: DateTime<Utc>, delay: Duration, period: UploadPeriod) -> Vec<DateTime<Utc>> {
+    let mut starts = Vec::new();
+    let mut start = next_start;
+    while start + period.duration() + delay <= now {
+        starts.push(start);
+        start += period.duration();
+    }
+    starts
+}
+
+/// 同じ時刻の足は最新の revision だけを残し, 時刻順に並べる
+pub fn latest_revisions(candles: Vec<TradeCandle>) -> Vec<TradeCandle> {
+    let mut latest: BTreeMap<_, TradeCandle> = BTreeMap::new();
+    for candle in candles {
+        if latest.get(&candle.timestamp).is_none_or(|kept| kept.revision < candle.revision) {
+            latest.insert(candle.timestamp, candle);
+        }
+    }
+    latest.into_values().collect()
+}
+
+/// 足を全ての列の Parquet (メモリ上) にする
+pub fn parquet_bytes(candles: &[TradeCandle], compression: ParquetCompression) -> anyhow::Result<Vec<u8>> {
+    let columns: Vec<String> = candle_frame::COLUMNS.iter().map(|c| c.to_string()).collect();
+    let mut df = candle_frame::to_dataframe(candles, &columns)?;
+    let mut bytes = Vec::new();
+    ParquetWriter::new(&mut bytes).with_compression(compression).finish(&mut df)?;
+    Ok(bytes)
+}
@@ -0,0 +1,87 @@
+use crate::models::trade_candle::TradeCandle;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// DB に書き込めなかったローソク足を溜めるディスク上のキュー (1 行 1 足の JSONL, 追記のみ)
+/// 前回のプロセスが残したファイルも開いたときに引き継ぎ, DB の復旧後に古い順に書き直す
+#[derive(Debug)]
+pub struct WriteAheadQueue {
+    path: PathBuf,
+    pending: usize,
+}
+
+impl WriteAheadQueue {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut queue = Self { path: path.to_path_buf(), pending: 0 };
+        if path.exists() {
+            // 書き込み途中で落ちた行を除いて書き直す (続けて追記した行が壊れないように)
+            queue.remove_front(0)?;
+            if queue.pending > 0 {
+                tracing::warn!("{} candles are queued in {} from a previous run", queue.pending, path.display());
+            }
+        }
+        Ok(queue)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// 1 足を追記してディスクに書き出す (プロセスが落ちても失わないように)
+    pub fn push(&mut self, candle: &TradeCandle) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(candle)?;
+        line.push(b'\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(&line)?;
+        file.sync_data()?;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// 溜まっている足を古い順に返す (書き込み途中で落ちた行は読み飛ばす)
+    pub fn load(&self) -> anyhow::Result<Vec<TradeCandle>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut candles = Vec::new();
+        for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(candle) => candles.push(candle),
+                Err(e) => tracing::warn!("Skipping broken line {} of {}: {}", number + 1, self.path.display(), e),
+            }
+        }
+        Ok(candles)
+    }
+
+    /// 書き込めた先頭の written 足を取り除く (残りは一時ファイルに書いてから置き換える)
+    pub fn remove_front(&mut self, written: usize) -> anyhow::Result<()> {
+        let rest: Vec<TradeCandle> = self.load()?.into_iter().skip(written).collect();
+        if rest.is_empty() {
+            if self.path.exists() {
+                std::fs::remove_file(&self.path)?;
+            }
+        } else {
+            let mut text = Vec::new();
+            for candle in &rest {
+                text.extend(serde_json::to_vec(candle)?);
+                text.push(b'\n');
+            }
+            let tmp = self.path.with_extension("tmp");
+            std::fs::write(&tmp, text)?;
+            std::fs::rename(&tmp, &self.path)?;
+        }
+        self.pending = rest.len();
+        Ok(())
+    }
+}
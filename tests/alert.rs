@@ -0,0 +1,58 @@
+mod common;
+
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::alert::{AlertCondition, AlertEngine, Watchlist};
+
+fn candle(symbol: &str, seconds: i64, price: f64, volume: f64) -> TradeCandle {
+    let mut candle = common::candle(symbol, common::at(seconds), 60);
+    candle.ask_price = Some(price);
+    candle.ask_volume = volume;
+    candle.ask_count = 1;
+    candle
+}
+
+#[test]
+fn parse_watchlist() {
+    let watchlist = Watchlist::parse("BTCUSDT:price>70000, ETHUSDT:move>2%/5m, *:volume>3x/20").unwrap();
+    let conditions: Vec<_> = watchlist.rules.iter().map(|r| (r.symbol.as_str(), r.condition.clone())).collect();
+    assert_eq!(conditions, vec![
+        ("BTCUSDT", AlertCondition::CrossAbove(70000.0)),
+        ("ETHUSDT", AlertCondition::Move { percent: 2.0, window: chrono::Duration::minutes(5) }),
+        ("*", AlertCondition::VolumeSpike { multiple: 3.0, lookback: 20 }),
+    ]);
+    assert!(Watchlist::parse("BTCUSDT").is_err());
+    assert!(Watchlist::parse("BTCUSDT:move>2%/5d").is_err());
+    assert!(Watchlist::parse("BTCUSDT:volume>3x/0").is_err());
+    assert!(Watchlist::parse("").is_err());
+}
+
+#[test]
+fn cross_fires_once_per_crossing() {
+    let mut engine = AlertEngine::new(Watchlist::parse("BTCUSDT:price>100,BTCUSDT:price<90").unwrap(), 60, "bybit");
+    let fired: Vec<usize> = [95.0, 101.0, 105.0, 99.0, 101.0, 89.0]
+        .iter()
+        .enumerate()
+        .map(|(i, &price)| engine.evaluate(&candle("BTCUSDT", i as i64 * 60, price, 1.0)).len())
+        .collect();
+    assert_eq!(fired, vec![0, 1, 0, 0, 1, 1]);
+    // 他の symbol や時間枠の足は評価しない
+    assert!(engine.evaluate(&candle("ETHUSDT", 0, 1000.0, 1.0)).is_empty());
+    let mut other = candle("BTCUSDT", 600, 80.0, 1.0);
+    other.period_seconds = 1;
+    assert!(engine.evaluate(&other).is_empty());
+}
+
+#[test]
+fn move_and_volume_spike() {
+    let mut engine = AlertEngine::new(Watchlist::parse("*:move>2%/3m,*:volume>3x/3").unwrap(), 60, "bybit");
+    for i in 0..4 {
+        assert!(engine.evaluate(&candle("ETHUSDT", i * 60, 100.0, 1.0)).is_empty());
+    }
+    // 3 分前の 100 から +3%, 出来高は平均の 4 倍
+    let alerts = engine.evaluate(&candle("ETHUSDT", 240, 103.0, 4.0));
+    assert_eq!(alerts.len(), 2);
+    assert!(alerts[0].message.starts_with("moved +3.00%"));
+    assert!(alerts[1].message.starts_with("volume 4.0000 is 4.0x"));
+    // move は window の間は再発火しない
+    assert!(engine.evaluate(&candle("ETHUSDT", 300, 104.0, 1.0)).is_empty());
+}
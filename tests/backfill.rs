@@ -0,0 +1,142 @@
+mod common;
+
+use chrono::DateTime;
+use kkcrypto::exchanges::{binance::BinanceClient, bybit::BybitClient};
+use kkcrypto::models::{market_event::MarketEvent, market_type::MarketType, trade::{Side, Trade}};
+use kkcrypto::utils::backfill::{LastTrades, SequenceGap};
+use kkcrypto::utils::trade_candle_builder::TradeCandleBuilder;
+use tokio::sync::mpsc;
+
+fn trade(trade_id: &str, timestamp_ms: i64) -> Trade {
+    common::trade("BTCUSDT", trade_id, DateTime::from_timestamp_millis(timestamp_ms).unwrap())
+}
+
+#[test]
+fn missed_keeps_trades_after_the_last_seen_one_in_time_order() {
+    let mut last_trades = LastTrades::new();
+    assert!(last_trades.is_empty());
+    last_trades.record(&trade("b", 2_000));
+    // 遅れて届いた古い約定では起点を戻さない
+    last_trades.record(&trade("a", 1_000));
+    assert_eq!(last_trades.get("BTCUSDT").unwrap().trade_id, "b");
+
+    let fetched = vec![trade("e", 4_000), trade("c", 2_000), trade("b", 2_000), trade("a", 1_000), trade("d", 3_000)];
+    let missed: Vec<String> = last_trades.missed("BTCUSDT", fetched).into_iter().map(|t| t.trade_id).collect();
+    assert_eq!(missed, vec!["c", "d", "e"]);
+    // 起点のない銘柄は全て返す
+    assert_eq!(last_trades.missed("ETHUSDT", vec![trade("x", 1)]).len(), 1);
+}
+
+#[test]
+fn bybit_recent_trades_are_returned_oldest_first() {
+    let text = r#"{"retCode":0,"retMsg":"OK","result":{"category":"linear","list":[
+        {"execId":"2","symbol":"BTCUSDT","price":"101.5","size":"0.2","side":"Sell","time":"1700000001000","isBlockTrade":false},
+        {"execId":"1","symbol":"BTCUSDT","price":"101.0","size":"0.1","side":"Buy","time":"1700000000000","isBlockTrade":true}
+    ]},"time":1700000002000}"#;
+    let trades = BybitClient::parse_recent_trades(text, &MarketType::Linear).unwrap();
+    assert_eq!(trades.iter().map(|t| t.trade_id.as_str()).collect::<Vec<_>>(), vec!["1", "2"]);
+    assert!(matches!(trades[0].side, Side::Buy));
+    assert!(matches!(trades[1].side, Side::Sell));
+    assert_eq!(trades[1].price, 101.5);
+    assert_eq!(trades[1].quantity, 0.2);
+    assert_eq!(trades[0].timestamp.timestamp_millis(), 1_700_000_000_000);
+
+    let error = r#"{"retCode":10001,"retMsg":"params error","result":{},"time":1700000002000}"#;
+    assert!(BybitClient::parse_recent_trades(error, &MarketType::Linear).is_err());
+}
+
+#[test]
+fn binance_rest_agg_trades_match_the_stream_side() {
+    let stream = r#"{"e":"aggTrade","E":1700000000100,"s":"BTCUSDT","a":42,"p":"100.0","q":"0.5","f":1,"l":2,"T":1700000000000,"m":true}"#;
+    let rest = r#"[{"a":42,"p":"100.0","q":"0.5","f":1,"l":2,"T":1700000000000,"m":true},{"a":43,"p":"99.0","q":"1.5","f":3,"l":3,"T":1700000000500,"m":false}]"#;
+    let live = BinanceClient::parse_trades(stream, &MarketType::Linear).unwrap();
+    let backfilled = BinanceClient::parse_agg_trades(rest, "BTCUSDT", &MarketType::Linear).unwrap();
+    assert_eq!(backfilled.len(), 2);
+    assert_eq!(backfilled[0].trade_id, live[0].trade_id);
+    assert_eq!(backfilled[0].symbol, live[0].symbol);
+    assert_eq!(format!("{:?}", backfilled[0].side), format!("{:?}", live[0].side));
+    assert_eq!(backfilled[0].timestamp, live[0].timestamp);
+    assert!(matches!(backfilled[1].side, Side::Sell));
+    assert_eq!(backfilled[1].quantity, 1.5);
+}
+
+#[test]
+fn sequence_gap_reports_the_missing_agg_trade_ids() {
+    let mut last_trades = LastTrades::new();
+    // 起点がなければ判定しない
+    assert_eq!(last_trades.sequence_gap(&trade("10", 1_000)), None);
+    last_trades.record(&trade("10", 1_000));
+
+    assert_eq!(last_trades.sequence_gap(&trade("11", 1_100)), None);
+    let gap = last_trades.sequence_gap(&trade("15", 1_500)).unwrap();
+    assert_eq!(gap, SequenceGap { symbol: "BTCUSDT".to_string(), from: 11, to: 14 });
+    assert_eq!(gap.missing(), 4);
+    // 重複・数値でない ID は欠番にしない
+    assert_eq!(last_trades.sequence_gap(&trade("9", 900)), None);
+    assert_eq!(last_trades.sequence_gap(&trade("abc", 1_600)), None);
+
+    assert!(last_trades.is_after_last(&trade("11", 1_100)));
+    assert!(!last_trades.is_after_last(&trade("10", 1_000)));
+    let mut other = trade("1", 1);
+    other.symbol = "ETHUSDT".to_string();
+    assert!(last_trades.is_after_last(&other));
+}
+
+#[test]
+fn binance_archive_csv_matches_the_rest_agg_trades() {
+    // 先物はヘッダー行あり, 現物はなく 2025 年からマイクロ秒
+    let futures = "agg_trade_id,price,quantity,first_trade_id,last_trade_id,transact_time,is_buyer_maker\n42,100.0,0.5,1,2,1700000000000,true\n43,99.0,1.5,3,3,1700000000500,false\n";
+    let spot = "42,100.0,0.5,1,2,1700000000000000,True,True\n";
+    let rest = r#"[{"a":42,"p":"100.0","q":"0.5","f":1,"l":2,"T":1700000000000,"m":true}]"#;
+    let archived = BinanceClient::parse_agg_trades_csv(futures, "BTCUSDT", &MarketType::Linear).unwrap();
+    let fetched = BinanceClient::parse_agg_trades(rest, "BTCUSDT", &MarketType::Linear).unwrap();
+    assert_eq!(archived.len(), 2);
+    assert_eq!(archived[0].trade_id, fetched[0].trade_id);
+    assert_eq!(archived[0].timestamp, fetched[0].timestamp);
+    assert_eq!(format!("{:?}", archived[0].side), format!("{:?}", fetched[0].side));
+    assert!(matches!(archived[1].side, Side::Sell));
+
+    let archived = BinanceClient::parse_agg_trades_csv(spot, "BTCUSDT", &MarketType::Spot).unwrap();
+    assert_eq!(archived[0].timestamp, fetched[0].timestamp);
+    assert!(matches!(archived[0].side, Side::Buy));
+}
+
+#[test]
+fn bybit_archive_csv_is_read_by_header_and_sorted() {
+    let futures = "timestamp,symbol,side,size,price,tickDirection,trdMatchID,grossValue,homeNotional,foreignNotional\n\
+        1700000001.5,BTCUSDT,Sell,0.2,101.5,MinusTick,b,2.03e+09,0.2,20.3\n\
+        1700000000.25,BTCUSDT,Buy,0.1,101.0,PlusTick,a,1.01e+09,0.1,10.1\n";
+    let trades = BybitClient::parse_trade_archive_csv(futures, "BTCUSDT", &MarketType::Linear).unwrap();
+    assert_eq!(trades.iter().map(|t| t.trade_id.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+    assert_eq!(trades[0].timestamp.timestamp_millis(), 1_700_000_000_250);
+    assert!(matches!(trades[1].side, Side::Sell));
+    assert_eq!(trades[1].quantity, 0.2);
+
+    let spot = "id,timestamp,price,volume,side\n1,1700000000250,101.0,0.1,buy\n";
+    let trades = BybitClient::parse_trade_archive_csv(spot, "BTCUSDT", &MarketType::Spot).unwrap();
+    assert_eq!(trades[0].timestamp.timestamp_millis(), 1_700_000_000_250);
+    assert!(BybitClient::parse_trade_archive_csv("time,price\n", "BTCUSDT", &MarketType::Spot).is_err());
+}
+
+#[tokio::test]
+async fn batch_builder_follows_trade_time_and_drops_candles_after_until() {
+    let base = 1_700_000_040_000;  // 分の境界
+    let (trade_tx, trade_rx) = mpsc::channel(10);
+    let (candle_tx, mut candle_rx) = mpsc::channel(10);
+    let builder = tokio::spawn(
+        TradeCandleBuilder::new(trade_rx, candle_tx, vec![1, 60]).run_batch(DateTime::from_timestamp_millis(base + 60_000).unwrap()),
+    );
+    for (id, offset) in [("1", 500), ("2", 1_200), ("3", 61_000)] {
+        trade_tx.send(MarketEvent::Trade(trade(id, base + offset))).await.unwrap();
+    }
+    drop(trade_tx);
+    builder.await.unwrap();
+
+    let mut candles = Vec::new();
+    while let Some(MarketEvent::Candle(candle)) = candle_rx.recv().await {
+        candles.push((candle.period_seconds, candle.timestamp.timestamp_millis() - base, candle.ask_count + candle.bid_count));
+    }
+    candles.sort();
+    // 3 件目の約定の足は until より後に終わるので出力しない
+    assert_eq!(candles, vec![(1, 1_000, 1), (1, 2_000, 1), (60, 60_000, 2)]);
+}
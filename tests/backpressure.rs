@@ -0,0 +1,71 @@
+mod common;
+
+use chrono::Utc;
+use kkcrypto::models::market_event::MarketEvent;
+use kkcrypto::utils::backpressure::{BackpressurePolicy, BackpressureQueue};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn trade(trade_id: &str) -> MarketEvent {
+    MarketEvent::Trade(common::trade("BTCUSDT", trade_id, Utc::now()))
+}
+
+fn trade_id(event: &MarketEvent) -> &str {
+    match event {
+        MarketEvent::Trade(trade) => &trade.trade_id,
+        _ => panic!("expected a trade"),
+    }
+}
+
+#[test]
+fn parses_policies() {
+    assert_eq!(BackpressurePolicy::parse("block").unwrap(), BackpressurePolicy::Block);
+    assert_eq!(BackpressurePolicy::parse("drop-oldest").unwrap(), BackpressurePolicy::DropOldest);
+    assert_eq!(BackpressurePolicy::parse("drop-newest").unwrap(), BackpressurePolicy::DropNewest);
+    assert!(BackpressurePolicy::parse("drop").is_err());
+}
+
+#[test]
+fn drop_oldest_keeps_the_latest_events() {
+    let mut queue = BackpressureQueue::new(BackpressurePolicy::DropOldest, 2);
+    assert!(queue.push(trade("1")).is_none());
+    assert!(queue.push(trade("2")).is_none());
+    assert_eq!(trade_id(&queue.push(trade("3")).unwrap()), "1");
+    assert_eq!(trade_id(&queue.pop().unwrap()), "2");
+    assert_eq!(trade_id(&queue.pop().unwrap()), "3");
+    let metrics = queue.metrics();
+    assert_eq!((metrics.dropped_trades(), metrics.dropped_other(), metrics.peak(), metrics.queued()), (1, 0, 2, 0));
+}
+
+#[test]
+fn drop_newest_keeps_the_queued_events() {
+    let mut queue = BackpressureQueue::new(BackpressurePolicy::DropNewest, 2);
+    queue.push(trade("1"));
+    queue.push(trade("2"));
+    assert_eq!(trade_id(&queue.push(trade("3")).unwrap()), "3");
+    assert_eq!(trade_id(&queue.pop().unwrap()), "1");
+    assert_eq!(queue.metrics().to_string(), "queued:1 peak:2 dropped_trades:1 dropped_other:0");
+}
+
+#[tokio::test]
+async fn keeps_receiving_while_the_pipeline_is_stalled() {
+    let (event_tx, event_rx) = mpsc::channel::<MarketEvent>(1);
+    let (queued_tx, mut queued_rx) = mpsc::channel::<MarketEvent>(1);
+    let queue = BackpressureQueue::new(BackpressurePolicy::DropOldest, 3);
+    let metrics = queue.metrics();
+    tokio::spawn(queue.run(event_rx, queued_tx));
+
+    // 後段が受け取らなくても送信は待たされない
+    for id in 1..=10 {
+        tokio::time::timeout(Duration::from_secs(1), event_tx.send(trade(&id.to_string()))).await.unwrap().unwrap();
+    }
+    drop(event_tx);
+
+    let mut received = Vec::new();
+    while let Some(event) = queued_rx.recv().await {
+        received.push(trade_id(&event).to_string());
+    }
+    // 後段のチャネルに入った分のほかは最新の 3 件だけ残る
+    assert!(received.ends_with(&["8".to_string(), "9".to_string(), "10".to_string()]));
+    assert_eq!(metrics.dropped_trades(), 10 - received.len() as u64);
+}
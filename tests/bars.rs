@@ -0,0 +1,116 @@
+mod common;
+
+use kkcrypto::models::{bar::BarType, imbalance_bar::ImbalanceKind, market_type::MarketType, trade::{Side, Trade}};
+use kkcrypto::utils::bar_builder::BarBuilder;
+use kkcrypto::utils::imbalance_bar_builder::{ImbalanceBarBuilder, ImbalanceBarConfig};
+use kkcrypto::utils::renko_builder::{BrickConfig, BrickKind, BrickSize, RenkoBuilder};
+
+fn trade(market_type: MarketType, ms: i64, price: f64, quantity: f64, side: Side) -> Trade {
+    Trade { market_type, price, quantity, side, ..common::trade("BTCUSDT", &ms.to_string(), common::at_ms(ms)) }
+}
+
+#[test]
+fn parse_bar_types() {
+    let bar_types = BarType::parse_list("tick:500, volume:0.5,dollar:1000000").unwrap();
+    assert_eq!(bar_types, vec![BarType::Tick(500), BarType::Volume(0.5), BarType::Dollar(1_000_000.0)]);
+    assert_eq!(bar_types.iter().map(BarType::collection_name).collect::<Vec<_>>(), vec!["bars_tick_500", "bars_volume_0_5", "bars_dollar_1000000"]);
+    assert!(BarType::parse("tick:0").is_err());
+    assert!(BarType::parse("time:60").is_err());
+    assert!(BarType::parse("volume").is_err());
+}
+
+#[test]
+fn tick_and_volume_bars() {
+    let mut builder = BarBuilder::new(vec![BarType::Tick(3), BarType::Volume(2.0)]);
+    let trades = [
+        trade(MarketType::Linear, 0, 100.0, 0.5, Side::Buy),
+        trade(MarketType::Linear, 10, 102.0, 1.0, Side::Sell),
+        trade(MarketType::Linear, 20, 99.0, 1.0, Side::Buy),  // 3 約定目, 出来高 2.5 で両方閉じる
+        trade(MarketType::Linear, 30, 101.0, 1.0, Side::Buy),
+    ];
+    let closed: Vec<_> = trades.iter().map(|t| builder.push(t)).collect();
+    assert_eq!(closed.iter().map(Vec::len).collect::<Vec<_>>(), vec![0, 0, 2, 0]);
+    let tick = &closed[2][0];
+    assert_eq!(tick.bar_type, BarType::Tick(3));
+    assert_eq!((tick.open, tick.high, tick.low, tick.close), (100.0, 102.0, 99.0, 99.0));
+    assert_eq!((tick.count, tick.volume, tick.buy_volume, tick.sell_volume), (3, 2.5, 1.5, 1.0));
+    assert_eq!((tick.timestamp - tick.start).num_milliseconds(), 20);
+}
+
+#[test]
+fn dollar_bars_use_usd_quantity_for_inverse() {
+    let mut builder = BarBuilder::new(vec![BarType::Dollar(1000.0)]);
+    assert!(builder.push(&trade(MarketType::Linear, 0, 100.0, 5.0, Side::Buy)).is_empty());
+    assert_eq!(builder.push(&trade(MarketType::Linear, 1, 100.0, 5.0, Side::Buy)).len(), 1);
+    // Inverse の数量は USD 建て
+    assert!(builder.push(&trade(MarketType::Inverse, 2, 60000.0, 999.0, Side::Buy)).is_empty());
+    assert_eq!(builder.push(&trade(MarketType::Inverse, 3, 60000.0, 1.0, Side::Sell))[0].notional, 1000.0);
+}
+
+#[test]
+fn imbalance_bars_close_on_signed_flow() {
+    let config = ImbalanceBarConfig::parse("tick:4").unwrap();
+    assert_eq!((config.kind, config.initial_ticks, config.span), (ImbalanceKind::Tick, 4.0, 10));
+    assert_eq!(ImbalanceBarConfig::parse("volume:200/20").unwrap().span, 20);
+    assert!(ImbalanceBarConfig::parse("tick:0").is_err());
+    assert!(ImbalanceBarConfig::parse("dollar:100").is_err());
+
+    let mut builder = ImbalanceBarBuilder::new(vec![config]);
+    // 最初の足: 買いのみなので期待不均衡 1 x E[T] 4 で閉じる
+    let closed: Vec<_> = (0..4).flat_map(|i| builder.push(&trade(MarketType::Linear, i, 100.0, 1.0, Side::Buy))).collect();
+    assert_eq!(closed.len(), 1);
+    assert_eq!((closed[0].count, closed[0].imbalance, closed[0].threshold), (4, 4.0, 4.0));
+
+    // 売り買いが交互なら不均衡は溜まらない
+    for i in 0..20 {
+        let side = if i % 2 == 0 { Side::Buy } else { Side::Sell };
+        assert!(builder.push(&trade(MarketType::Linear, 10 + i, 100.0, 1.0, side)).is_empty());
+    }
+    // 売りが続くと閉じ, 累積不均衡は負
+    let closed: Vec<_> = (0..10).flat_map(|i| builder.push(&trade(MarketType::Linear, 40 + i, 99.0, 1.0, Side::Sell))).collect();
+    assert_eq!(closed.len(), 1);
+    assert!(closed[0].imbalance <= -closed[0].threshold);
+}
+
+#[test]
+fn parse_brick_configs() {
+    let configs = BrickConfig::parse_list("renko:100, range:0.5,renko:atr14,range:atr20/300").unwrap();
+    assert_eq!(configs[0], BrickConfig { kind: BrickKind::Renko, size: BrickSize::Fixed(100.0) });
+    assert_eq!(configs[2].size, BrickSize::Atr { period: 14, timeframe: 60 });
+    assert_eq!(configs.iter().map(BrickConfig::name).collect::<Vec<_>>(), vec!["renko_100", "range_0_5", "renko_atr14", "range_atr20_300"]);
+    assert!(BrickConfig::parse("renko:0").is_err());
+    assert!(BrickConfig::parse("renko:atr0").is_err());
+    assert!(BrickConfig::parse("kagi:10").is_err());
+}
+
+#[test]
+fn renko_bricks_follow_trend_and_reverse_on_two_sizes() {
+    let mut builder = RenkoBuilder::new(vec![BrickConfig::parse("renko:10").unwrap()]);
+    assert!(builder.push(&trade(MarketType::Linear, 0, 100.0, 1.0, Side::Buy)).is_empty());
+    // 1 約定で 2 ブリック, 出来高は最初のブリックに入る
+    let bricks = builder.push(&trade(MarketType::Linear, 10, 125.0, 2.0, Side::Buy));
+    assert_eq!(bricks.iter().map(|b| (b.open, b.close, b.direction)).collect::<Vec<_>>(), vec![(100.0, 110.0, 1), (110.0, 120.0, 1)]);
+    assert_eq!((bricks[0].volume, bricks[0].count, bricks[1].volume, bricks[1].count), (3.0, 2, 0.0, 0));
+    assert_eq!(bricks[0].name, "renko_10");
+    // 反転は直前のブリックの終値から 2 x size
+    assert!(builder.push(&trade(MarketType::Linear, 20, 105.0, 1.0, Side::Sell)).is_empty());
+    let bricks = builder.push(&trade(MarketType::Linear, 30, 99.0, 1.0, Side::Sell));
+    assert_eq!(bricks.iter().map(|b| (b.open, b.close, b.direction)).collect::<Vec<_>>(), vec![(110.0, 100.0, -1)]);
+    assert_eq!((bricks[0].high, bricks[0].low, bricks[0].count), (105.0, 99.0, 2));
+}
+
+#[test]
+fn range_bars_use_atr_once_warmed_up() {
+    let mut builder = RenkoBuilder::new(vec![BrickConfig::parse("range:atr2").unwrap()]);
+    // 1 分足の true range が 2 本揃うまではブリックを作らない
+    for (ms, price) in [(0, 100.0), (30_000, 104.0), (60_000, 102.0)] {
+        assert!(builder.push(&trade(MarketType::Linear, ms, price, 1.0, Side::Buy)).is_empty());
+    }
+    // TR = 4, 2 -> ATR 3
+    assert!(builder.push(&trade(MarketType::Linear, 120_000, 106.0, 1.0, Side::Buy)).is_empty());
+    let bricks = builder.push(&trade(MarketType::Linear, 121_000, 109.0, 1.0, Side::Buy));
+    assert_eq!(bricks.len(), 1);
+    let brick = &bricks[0];
+    assert_eq!((brick.open, brick.close, brick.size, brick.direction, brick.count), (106.0, 109.0, 3.0, 1, 2));
+    assert_eq!(brick.name, "range_atr2");
+}
@@ -0,0 +1,71 @@
+use chrono::{DateTime, Duration};
+use kkcrypto::utils::beta::{regress, to_documents, BetaParams, SymbolBeta};
+use kkcrypto::utils::price_window::PricePoint;
+use kkcrypto::utils::resample::FillPolicy;
+use kkcrypto::utils::rolling_correlation::RollingCorrelation;
+use mongodb::bson::Bson;
+
+#[test]
+fn regresses_on_the_benchmark() {
+    let benchmark = [0.01, -0.02, 0.015, 0.0, -0.005];
+    // 2 倍に動き, 定数がずれても beta は 2, 決定係数は 1
+    let values: Vec<f64> = benchmark.iter().map(|r| 2.0 * r + 0.001).collect();
+    let (beta, r_squared) = regress(&benchmark, &values);
+    assert!((beta.unwrap() - 2.0).abs() < 1e-12);
+    assert!((r_squared.unwrap() - 1.0).abs() < 1e-12);
+    assert_eq!(regress(&[0.01, 0.01], &[0.02, 0.03]), (None, None));
+    assert_eq!(regress(&[0.01], &[0.02]), (None, None));
+}
+
+#[test]
+fn rolling_betas_match_regression() {
+    let start = 1_717_200_000_000;
+    let mut rolling = RollingCorrelation::new(1, Duration::seconds(60), FillPolicy::Ffill { limit: None }).unwrap().with_returns(1);
+    let price = |symbol_id: i32, t: f64| match symbol_id {
+        1 => 67_000.0 * (1.0 + (t * 0.7).sin() * 0.01),
+        6 => 3_500.0 * (1.0 + (t * 0.7).sin() * 0.015 + (t * 0.3).cos() * 0.002),
+        _ => 150.0 * (1.0 + (t * 1.3).cos() * 0.02),
+    };
+    let mut prices: Vec<(i32, Vec<f64>)> = vec![(1, Vec::new()), (6, Vec::new()), (9, Vec::new())];
+    for second in 1..=40 {
+        for (symbol_id, series) in prices.iter_mut() {
+            let value = price(*symbol_id, second as f64);
+            series.push(value);
+            rolling.insert(&PricePoint { timestamp_ms: start + second * 1000, symbol_id: *symbol_id, price: value });
+        }
+        rolling.advance(start + second * 1000);
+    }
+    let returns = |series: &[f64]| -> Vec<f64> { series.windows(2).map(|w| (w[1] / w[0]).ln()).collect() };
+    let benchmark = returns(&prices[1].1);
+    // benchmark が symbol_b (6) のペアも symbol_a のペアも symbol を benchmark で回帰する
+    let betas = rolling.betas(6);
+    assert_eq!(betas.iter().map(|beta| beta.symbol_id).collect::<Vec<_>>(), vec![1, 9]);
+    for (beta, (_, series)) in betas.iter().zip([&prices[0], &prices[2]]) {
+        let (expected, r_squared) = regress(&benchmark, &returns(series));
+        assert_eq!((beta.benchmark_id, beta.count), (6, 39));
+        assert!((beta.beta.unwrap() - expected.unwrap()).abs() < 1e-9);
+        assert!((beta.r_squared.unwrap() - r_squared.unwrap()).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn one_document_per_symbol() {
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let betas = [
+        SymbolBeta { symbol_id: 6, benchmark_id: 1, count: 359, beta: Some(1.25), r_squared: Some(0.64) },
+        SymbolBeta { symbol_id: 7, benchmark_id: 1, count: 3, beta: None, r_squared: None },
+    ];
+    let params = BetaParams { window_minutes: 30, interval_seconds: 5, returns: 1 };
+    let docs = to_documents(timestamp, &params, &betas);
+    assert_eq!(docs.len(), 2);
+    let metadata = docs[0].get_document("metadata").unwrap();
+    assert_eq!(metadata.get_i32("ym").unwrap(), 202406);
+    assert_eq!((metadata.get_i32("symbol").unwrap(), metadata.get_i32("benchmark").unwrap()), (6, 1));
+    assert_eq!(metadata.get_i32("window").unwrap(), 30);
+    assert_eq!(metadata.get_i64("interval").unwrap(), 5);
+    assert_eq!(metadata.get_i32("returns").unwrap(), 1);
+    assert_eq!(docs[0].get_f64("beta").unwrap(), 1.25);
+    assert_eq!(docs[0].get_f64("r2").unwrap(), 0.64);
+    assert_eq!(docs[0].get_i64("count").unwrap(), 359);
+    assert_eq!(docs[1].get("beta"), Some(&Bson::Null));
+}
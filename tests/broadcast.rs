@@ -0,0 +1,107 @@
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade};
+use kkcrypto::utils::broadcast::{self, BroadcastFilter, BroadcastMessage, ClientCommand, EventBroadcaster};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+fn trade(exchange: &str, symbol: &str) -> MarketEvent {
+    MarketEvent::Trade(Trade { exchange: exchange.to_string(), ..common::trade(symbol, "1", common::at(0)) })
+}
+
+fn candle(symbol: &str) -> MarketEvent {
+    MarketEvent::Candle(common::candle(symbol, common::at(60), 60))
+}
+
+fn command(json: &str) -> ClientCommand {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn message_wraps_trades_and_candles_only() {
+    let message = BroadcastMessage::from_event(&trade("bybit", "BTCUSDT")).unwrap();
+    assert_eq!(message.kind, "trade");
+    assert_eq!(message.symbol, "BTCUSDT");
+    let json: serde_json::Value = serde_json::from_str(&message.json).unwrap();
+    assert_eq!(json["type"], "trade");
+    assert_eq!(json["data"]["price"], 100.0);
+
+    let message = BroadcastMessage::from_event(&candle("ETHUSDT")).unwrap();
+    let json: serde_json::Value = serde_json::from_str(&message.json).unwrap();
+    assert_eq!(json["type"], "candle");
+    assert_eq!(json["data"]["period_seconds"], 60);
+}
+
+#[test]
+fn filter_from_query() {
+    let filter = BroadcastFilter::from_query("symbols=BTCUSDT,ETHUSDT&types=candle&exchanges=Bybit").unwrap();
+    assert!(filter.matches(&BroadcastMessage::from_event(&candle("BTCUSDT")).unwrap()));
+    assert!(!filter.matches(&BroadcastMessage::from_event(&candle("SOLUSDT")).unwrap()));
+    assert!(!filter.matches(&BroadcastMessage::from_event(&trade("bybit", "BTCUSDT")).unwrap()));
+
+    // 指定がなければ全て
+    let filter = BroadcastFilter::from_query("").unwrap();
+    assert!(filter.matches(&BroadcastMessage::from_event(&trade("binance", "SOLUSDT")).unwrap()));
+
+    assert!(BroadcastFilter::from_query("types=orderbook").is_err());
+    assert!(BroadcastFilter::from_query("symbol=BTCUSDT").is_err());
+}
+
+#[test]
+fn filter_subscribe_and_unsubscribe() {
+    let mut filter = BroadcastFilter::from_query("symbols=BTCUSDT").unwrap();
+    let eth = BroadcastMessage::from_event(&trade("bybit", "ETHUSDT")).unwrap();
+    assert!(!filter.matches(&eth));
+
+    filter.apply(&command(r#"{"op":"subscribe","symbols":["ETHUSDT"]}"#)).unwrap();
+    assert!(filter.matches(&eth));
+
+    // BTCUSDT も ETHUSDT も外すと全てに戻る
+    filter.apply(&command(r#"{"op":"unsubscribe","symbols":["BTCUSDT","ETHUSDT"]}"#)).unwrap();
+    assert!(filter.symbols.is_empty());
+    assert!(filter.matches(&eth));
+
+    assert!(filter.apply(&command(r#"{"op":"list"}"#)).is_err());
+    assert!(filter.apply(&command(r#"{"op":"subscribe","types":["bar"]}"#)).is_err());
+}
+
+#[tokio::test]
+async fn stage_forwards_and_publishes() {
+    let broadcaster = EventBroadcaster::new(10);
+    let mut subscriber = broadcaster.subscribe();
+    let (in_tx, in_rx) = mpsc::channel(10);
+    let (out_tx, mut out_rx) = mpsc::channel(10);
+    tokio::spawn(broadcaster.run(in_rx, out_tx));
+
+    in_tx.send(trade("bybit", "BTCUSDT")).await.unwrap();
+    assert!(matches!(out_rx.recv().await, Some(MarketEvent::Trade(_))));
+    assert_eq!(subscriber.recv().await.unwrap().kind, "trade");
+}
+
+#[tokio::test]
+async fn websocket_client_receives_filtered_events() {
+    let broadcaster = EventBroadcaster::new(10);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(broadcast::serve(listener, broadcaster.clone()));
+
+    let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}/?symbols=ETHUSDT", addr)).await.unwrap();
+    // 購読の変更への応答を待ってから流す (接続が受け付けられたことも確かめる)
+    client.send(Message::Text(r#"{"op":"subscribe","types":["candle"]}"#.to_string())).await.unwrap();
+    let reply: serde_json::Value = match client.next().await.unwrap().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("unexpected message: {:?}", other),
+    };
+    assert_eq!(reply["type"], "subscribed");
+
+    broadcaster.publish(&candle("BTCUSDT"));
+    broadcaster.publish(&trade("bybit", "ETHUSDT"));
+    broadcaster.publish(&candle("ETHUSDT"));
+    let message: serde_json::Value = match client.next().await.unwrap().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("unexpected message: {:?}", other),
+    };
+    assert_eq!(message["type"], "candle");
+    assert_eq!(message["data"]["symbol"], "ETHUSDT");
+}
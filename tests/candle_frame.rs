@@ -0,0 +1,57 @@
+mod common;
+
+use common::at;
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::candle_frame::{self, FrameFormat};
+use polars::prelude::*;
+
+// 終端 end_seconds の 60 秒足
+fn candle(symbol: &str, end_seconds: i64, ask: Option<(f64, f64)>) -> TradeCandle {
+    let mut candle = common::candle(symbol, at(end_seconds), 60);
+    if let Some((price, volume)) = ask {
+        candle.ask_price = Some(price);
+        candle.ask_volume = volume;
+        candle.ask_notional = price * volume;
+        candle.ask_count = 1;
+    }
+    candle
+}
+
+#[test]
+fn parse_columns() {
+    assert_eq!(candle_frame::parse_columns(None).unwrap().len(), candle_frame::COLUMNS.len());
+    assert_eq!(candle_frame::parse_columns(Some("timestamp, vwap")).unwrap(), vec!["timestamp", "vwap"]);
+    assert!(candle_frame::parse_columns(Some("timestamp,price")).is_err());
+    assert!(candle_frame::parse_columns(Some(",")).is_err());
+    assert_eq!(FrameFormat::parse("Parquet"), Some(FrameFormat::Parquet));
+    assert_eq!(FrameFormat::parse("ccxt"), None);
+}
+
+#[test]
+fn dataframe_from_candles() {
+    let candles = vec![candle("BTCUSDT", 60, Some((100.0, 2.0))), candle("ETHUSDT", 60, None)];
+    let columns = candle_frame::parse_columns(None).unwrap();
+    let df = candle_frame::to_dataframe(&candles, &columns).unwrap();
+    assert_eq!(df.shape(), (2, candle_frame::COLUMNS.len()));
+    assert_eq!(df.column("timestamp").unwrap().dtype(), &DataType::Datetime(TimeUnit::Milliseconds, Some(TimeZone::UTC)));
+    let vwap = df.column("vwap").unwrap().f64().unwrap();
+    assert_eq!(vwap.get(0), Some(100.0));
+    assert_eq!(vwap.get(1), None);
+    assert_eq!(df.column("symbol").unwrap().str().unwrap().get(1), Some("ETHUSDT"));
+}
+
+#[test]
+fn write_csv_and_parquet() {
+    let candles = vec![candle("BTCUSDT", 60, Some((100.0, 2.0))), candle("BTCUSDT", 120, Some((101.0, 1.0)))];
+    let columns = candle_frame::parse_columns(Some("symbol,ask_volume,ask_count")).unwrap();
+    let mut df = candle_frame::to_dataframe(&candles, &columns).unwrap();
+
+    let mut csv = Vec::new();
+    candle_frame::write_frame(&mut csv, &mut df, FrameFormat::Csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap(), "symbol,ask_volume,ask_count\nBTCUSDT,2.0,1\nBTCUSDT,1.0,1\n");
+
+    let mut parquet = Vec::new();
+    candle_frame::write_frame(&mut parquet, &mut df, FrameFormat::Parquet).unwrap();
+    let read = ParquetReader::new(std::io::Cursor::new(parquet)).finish().unwrap();
+    assert!(read.equals(&df));
+}
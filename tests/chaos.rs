@@ -0,0 +1,62 @@
+#![cfg(feature = "chaos")]
+
+use kkcrypto::utils::chaos::{ChaosAction, ChaosConfig, ChaosInjector};
+use tokio_tungstenite::tungstenite::Message;
+
+const TRADE_FRAME: &str = r#"{"topic":"publicTrade.BTCUSDT","data":[{"s":"BTCUSDT","p":"65000.1","v":"0.01","S":"Buy","T":1717200000000,"i":"abc"}]}"#;
+
+#[test]
+fn parse_spec() {
+    let config = ChaosConfig::parse("disconnect=0.001,delay=0.01,max_delay_ms=250,malform=0.02,seed=7").unwrap();
+    assert_eq!(config.disconnect_rate, 0.001);
+    assert_eq!(config.delay_rate, 0.01);
+    assert_eq!(config.max_delay_ms, 250);
+    assert_eq!(config.malform_rate, 0.02);
+    assert_eq!(config.seed, Some(7));
+
+    assert!(ChaosConfig::parse("disconnect=1.5").is_err());
+    assert!(ChaosConfig::parse("unknown=0.1").is_err());
+}
+
+#[test]
+fn same_seed_same_actions() {
+    let config = ChaosConfig::parse("disconnect=0.1,delay=0.2,malform=0.2,seed=42").unwrap();
+    let mut a = ChaosInjector::new(config.clone());
+    let mut b = ChaosInjector::new(config);
+    let actions_a: Vec<ChaosAction> = (0..1000).map(|_| a.next_action()).collect();
+    let actions_b: Vec<ChaosAction> = (0..1000).map(|_| b.next_action()).collect();
+    assert_eq!(actions_a, actions_b);
+
+    // 全ての種類の障害が発生していること
+    assert!(actions_a.contains(&ChaosAction::Disconnect));
+    assert!(actions_a.contains(&ChaosAction::Malform));
+    assert!(actions_a.contains(&ChaosAction::Pass));
+    assert!(actions_a.iter().any(|a| matches!(a, ChaosAction::Delay(_))));
+}
+
+#[tokio::test]
+async fn zero_rates_pass_through() {
+    let mut injector = ChaosInjector::new(ChaosConfig::parse("seed=1").unwrap());
+    for _ in 0..100 {
+        let msg = injector.apply(Message::Text(TRADE_FRAME.to_string())).await.unwrap();
+        assert_eq!(msg, Message::Text(TRADE_FRAME.to_string()));
+    }
+}
+
+#[tokio::test]
+async fn full_disconnect_rate_always_fails() {
+    let mut injector = ChaosInjector::new(ChaosConfig::parse("disconnect=1,seed=1").unwrap());
+    for _ in 0..10 {
+        assert!(injector.apply(Message::Text(TRADE_FRAME.to_string())).await.is_err());
+    }
+}
+
+#[test]
+fn malformed_frame_is_not_valid_json() {
+    match ChaosInjector::malform(Message::Text(TRADE_FRAME.to_string())) {
+        Message::Text(text) => {
+            assert!(serde_json::from_str::<serde_json::Value>(&text).is_err());
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
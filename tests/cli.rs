@@ -0,0 +1,41 @@
+use clap::Parser;
+use kkcrypto::cli::common::{self, MarketArgs};
+use kkcrypto::cli::Cli;
+use kkcrypto::models::market_type::MarketType;
+
+#[test]
+fn parses_comma_separated_symbols() {
+    assert_eq!(common::parse_symbols("BTCUSDT, ETHUSDT,,"), vec!["BTCUSDT", "ETHUSDT"]);
+    assert!(common::parse_symbols("").is_empty());
+}
+
+#[test]
+fn market_type_must_be_one_supported_flag() {
+    let market = |spot, linear, inverse| MarketArgs { spot, linear, inverse };
+    let supported = [MarketType::Spot, MarketType::Linear];
+    assert_eq!(market(false, true, false).market_type("Hyperliquid", &supported).unwrap(), MarketType::Linear);
+    assert!(market(false, false, true).market_type("Hyperliquid", &supported).is_err());
+    assert!(market(true, true, false).market_type("Hyperliquid", &supported).is_err());
+    assert!(market(false, false, false).market_type("Hyperliquid", &supported).is_err());
+}
+
+#[test]
+fn subcommands_take_the_same_options_as_the_binaries() {
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "bybit", "--linear", "-t", "1,5", "--symbols", "BTCUSDT", "--update"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "config", "--config", "collector.toml"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "symbols", "-e", "bybit", "-m", "linear", "BTC", "--join"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "bybit", "--linear"]).is_err()); // --symbols is required
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "kraken"]).is_err());
+    assert!(Cli::try_parse_from(["kkcrypto", "correlate", "-w", "5,30,240", "--symbol-ids", "1,6,7", "--min-coverage", "0.8"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "correlate", "--output", "csv", "--output-file", "matrix.csv"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "correlate", "--returns", "--pca", "3"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "correlate", "--cluster", "0.5", "--linkage", "complete"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "correlate", "--alert", "1/6<0.5x3@30"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "binance", "--spot", "--symbols", "BTCUSDT,ETHUSDT", "--tui"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "config", "--config", "collector.toml", "--tui"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "collect", "bybit", "--linear", "--symbols", "BTCUSDT", "--log-format", "json", "--log-dir", "/var/log/kkcrypto", "--log-retention-days", "30"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "correlate", "--log-format", "json"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "pairs", "-e", "bybit", "-p", "ETHUSDT/BTCUSDT", "--log-format", "json", "--log-dir", "/var/log/kkcrypto"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "quality", "--log-format", "json"]).is_ok());
+    assert!(Cli::try_parse_from(["kkcrypto", "admin", "--log-format", "json", "show", "snapshot.json"]).is_ok());
+}
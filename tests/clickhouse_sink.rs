@@ -0,0 +1,53 @@
+mod common;
+
+use kkcrypto::models::{market_type::MarketType, trade::{Side, Trade}};
+use kkcrypto::utils::clickhouse_sink::{candle_row, candles_table_sql, trade_row, trades_table_sql, RowBuffer};
+
+#[test]
+fn candle_row_matches_table_columns() {
+    let mut candle = common::candle("BTCUSDT", common::at_ms(60_250), 60);
+    candle.close = Some(67000.5);
+    candle.revision = 1;
+    let row = candle_row(&candle);
+    assert_eq!(row["timestamp"], "2024-06-01 00:01:00.250");
+    assert_eq!(row["market_type"], "linear");
+    assert_eq!(row["close"], 67000.5);
+    assert!(row["open"].is_null());
+    assert_eq!(row["revision"], 1);
+
+    // 行の全ての列がテーブルにある
+    let sql = candles_table_sql("kkcrypto");
+    for column in row.as_object().unwrap().keys() {
+        assert!(sql.contains(&format!("\n    {} ", column)), "{} is not in the candles table", column);
+    }
+}
+
+#[test]
+fn trade_row_matches_table_columns() {
+    let trade = Trade {
+        exchange: "binance".to_string(),
+        market_type: MarketType::Spot,
+        price: 3000.0,
+        quantity: 0.5,
+        side: Side::Sell,
+        ..common::trade("ETHUSDT", "42", common::at_ms(60_250))
+    };
+    let row = trade_row(&trade);
+    assert_eq!(row["side"], "sell");
+    assert_eq!(row["trade_id"], "42");
+    let sql = trades_table_sql("kkcrypto");
+    for column in row.as_object().unwrap().keys() {
+        assert!(sql.contains(&format!("\n    {} ", column)), "{} is not in the trades table", column);
+    }
+}
+
+#[test]
+fn row_buffer_takes_json_lines() {
+    let mut buffer = RowBuffer::new("candles");
+    assert!(buffer.is_empty());
+    buffer.push(&serde_json::json!({ "a": 1 }));
+    buffer.push(&serde_json::json!({ "a": 2 }));
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(buffer.take(), "{\"a\":1}\n{\"a\":2}\n");
+    assert!(buffer.is_empty());
+}
@@ -0,0 +1,58 @@
+use chrono::DateTime;
+use kkcrypto::utils::clustering::{cluster, Linkage, Merge};
+use kkcrypto::utils::correlation_matrix::CorrelationMatrix;
+use kkcrypto::utils::rolling_correlation::PairCorrelation;
+
+/// L1 (1, 6, 9) と memecoin (20, 21) の 2 つのグループ
+fn matrix() -> CorrelationMatrix {
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let correlation = |a: i32, b: i32| match (a, b) {
+        (1, 6) => Some(0.9),
+        (1, 9) | (6, 9) => Some(0.8),
+        (20, 21) => Some(0.7),
+        (_, 21) => None,
+        _ => Some(0.2),
+    };
+    let symbols = [1, 6, 9, 20, 21];
+    let pairs: Vec<PairCorrelation> = symbols
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &a)| symbols[i + 1..].iter().map(move |&b| PairCorrelation { symbol_a: a, symbol_b: b, count: 360, correlation: correlation(a, b) }))
+        .collect();
+    CorrelationMatrix::from_pairs(timestamp, 30, &pairs)
+}
+
+#[test]
+fn parses_linkage() {
+    assert_eq!(Linkage::parse("Average").unwrap(), Linkage::Average);
+    assert_eq!(Linkage::parse("complete").unwrap().as_str(), "complete");
+    assert!(Linkage::parse("ward").is_err());
+}
+
+#[test]
+fn merges_closest_clusters_first() {
+    let dendrogram = cluster(&matrix(), Linkage::Average).unwrap();
+    assert_eq!(dendrogram.merges.len(), 4);
+    let Merge { left, right, distance, size } = dendrogram.merges[0];
+    assert_eq!((left, right, size), (0, 1, 2));
+    assert!((distance - 0.1).abs() < 1e-12);
+    assert_eq!((dendrogram.merges[1].left, dendrogram.merges[1].right), (2, 5));
+    assert!((dendrogram.merges[1].distance - 0.2).abs() < 1e-12);
+    assert_eq!((dendrogram.merges[2].left, dendrogram.merges[2].right), (3, 4));
+    assert!((dendrogram.merges[2].distance - 0.3).abs() < 1e-12);
+    // 計算できないペア (21 と L1) は距離 1: (0.8 * 3 + 1.0 * 3) / 6
+    assert!((dendrogram.merges[3].distance - 0.9).abs() < 1e-12);
+    assert!(dendrogram.merges.windows(2).all(|w| w[0].distance <= w[1].distance));
+    assert_eq!(dendrogram.order(), vec![9, 1, 6, 20, 21]);
+}
+
+#[test]
+fn cuts_at_distance() {
+    let dendrogram = cluster(&matrix(), Linkage::Average).unwrap();
+    assert_eq!(dendrogram.cut(0.5), vec![vec![9, 1, 6], vec![20, 21]]);
+    assert_eq!(dendrogram.cut(0.15), vec![vec![9], vec![1, 6], vec![20], vec![21]]);
+    assert_eq!(dendrogram.cut(2.0).len(), 1);
+    // single linkage は最も近い組で merge する
+    let single = cluster(&matrix(), Linkage::Single).unwrap();
+    assert!((single.merges[3].distance - 0.8).abs() < 1e-12);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use chrono::DateTime;
+use kkcrypto::models::{market_event::MarketEvent, market_type::MarketType};
+use kkcrypto::utils::collector_config::{CandleTimeframeFilter, CollectorConfig};
+
+const TOML: &str = r#"
+timeframes = "1,1m"
+
+[[feeds]]
+exchange = "bybit"
+market_type = "linear"
+symbols = ["BTCUSDT", "ETHUSDT"]
+symbols_per_connection = 100
+
+[[feeds]]
+exchange = "hyperliquid"
+market_type = "linear"
+symbols = ["BTC"]
+timeframes = "1m,5m"
+"#;
+
+const YAML: &str = r#"
+timeframes: "1,1m"
+feeds:
+  - exchange: bybit
+    market_type: linear
+    symbols: [BTCUSDT, ETHUSDT]
+    symbols_per_connection: 100
+  - exchange: hyperliquid
+    market_type: linear
+    symbols: [BTC]
+    timeframes: "1m,5m"
+"#;
+
+fn candle(exchange: &str, period_seconds: i32) -> MarketEvent {
+    let timestamp = DateTime::from_timestamp(1_700_000_100, 0).unwrap();
+    let mut candle = common::candle("BTC", timestamp, period_seconds);
+    candle.exchange = exchange.to_string();
+    MarketEvent::Candle(candle)
+}
+
+#[test]
+fn toml_and_yaml_describe_the_same_feeds() {
+    for config in [CollectorConfig::from_toml(TOML).unwrap(), CollectorConfig::from_yaml(YAML).unwrap()] {
+        assert_eq!(config.feeds.len(), 2);
+        assert_eq!(config.feeds[0].market_type().unwrap(), MarketType::Linear);
+        assert_eq!(config.feeds[0].symbols_per_connection, 100);
+        assert_eq!(config.feeds[0].timeframes(&config.timeframes).unwrap(), vec![1, 60]);
+        assert_eq!(config.feeds[1].timeframes(&config.timeframes).unwrap(), vec![60, 300]);
+        assert_eq!(config.all_timeframes().unwrap(), vec![1, 60, 300]);
+        assert!(!config.update);
+        assert_eq!((config.batch_size, config.batch_ms), (500, 1000));
+    }
+}
+
+#[test]
+fn rejects_unknown_markets_duplicates_and_typos() {
+    let invalid = [
+        "[[feeds]]\nexchange = \"bitstamp\"\nmarket_type = \"linear\"\nsymbols = [\"BTCEUR\"]\n",
+        "[[feeds]]\nexchange = \"kraken\"\nmarket_type = \"spot\"\nsymbols = [\"XBTUSD\"]\n",
+        "[[feeds]]\nexchange = \"bybit\"\nmarket_type = \"spot\"\nsymbols = []\n",
+        "[[feeds]]\nexchange = \"bybit\"\nmarket_type = \"spot\"\nsymbol = [\"BTCUSDT\"]\n",
+        "[[feeds]]\nexchange = \"phemex\"\nmarket_type = \"spot\"\nsymbols = [\"sBTCUSDT\"]\ntestnet = true\n",
+        "[[feeds]]\nexchange = \"bybit\"\nmarket_type = \"spot\"\nsymbols = [\"BTCUSDT\"]\n[[feeds]]\nexchange = \"bybit\"\nmarket_type = \"spot\"\nsymbols = [\"ETHUSDT\"]\n",
+        "feeds = []\n",
+        "[[feeds]]\nexchange = \"bybit\"\nmarket_type = \"spot\"\nsymbols = [\"BTCUSDT\"]\n[retention]\nkeep = { 1m = \"soon\" }\n",
+    ];
+    for text in invalid {
+        assert!(CollectorConfig::from_toml(text).is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn filter_drops_timeframes_the_feed_did_not_ask_for() {
+    let config = CollectorConfig::from_toml(TOML).unwrap();
+    let filter = CandleTimeframeFilter::new(&config).unwrap();
+    assert!(filter.keeps(&candle("bybit", 1)));
+    assert!(!filter.keeps(&candle("bybit", 300)));
+    assert!(!filter.keeps(&candle("hyperliquid", 1)));
+    assert!(filter.keeps(&candle("hyperliquid", 300)));
+}
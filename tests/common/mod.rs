@@ -0,0 +1,30 @@
+//! 結合テストで共有する約定・ローソク足の fixture
+//! 各テストファイルから `mod common;` で読み込むので、使わない関数があっても警告にしない
+#![allow(dead_code)]
+
+use chrono::{DateTime, Utc};
+use kkcrypto::models::{market_type::MarketType, trade::{Side, Trade}, trade_candle::TradeCandle};
+
+/// fixture の基準時刻 (2024-06-01 00:00:00 UTC)
+pub const BASE_SECONDS: i64 = 1_717_200_000;
+
+/// 基準時刻から seconds 秒後
+pub fn at(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(BASE_SECONDS + seconds, 0).unwrap()
+}
+
+/// 基準時刻から millis ミリ秒後
+pub fn at_ms(millis: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(BASE_SECONDS * 1000 + millis).unwrap()
+}
+
+/// bybit linear の買い約定 (価格 100, 数量 1)
+/// 他の値は `Trade { side: Side::Sell, ..common::trade(..) }` のように上書きする
+pub fn trade(symbol: &str, trade_id: &str, timestamp: DateTime<Utc>) -> Trade {
+    Trade::new("bybit".to_string(), MarketType::Linear, symbol.to_string(), trade_id.to_string(), 100.0, 1.0, Side::Buy, timestamp)
+}
+
+/// bybit linear の空のローソク足 (timestamp は足の終わり)
+pub fn candle(symbol: &str, timestamp: DateTime<Utc>, period_seconds: i32) -> TradeCandle {
+    TradeCandle::new("bybit".to_string(), MarketType::Linear, symbol.to_string(), timestamp, period_seconds)
+}
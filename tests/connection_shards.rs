@@ -0,0 +1,35 @@
+use kkcrypto::exchanges::binance::BinanceClient;
+use kkcrypto::models::{market_type::MarketType, ExchangeClient};
+use kkcrypto::utils::connection_shards::split_symbols;
+use tokio::sync::mpsc;
+
+fn symbols(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("SYM{}USDT", i)).collect()
+}
+
+#[test]
+fn symbols_are_split_evenly_in_order() {
+    let shards = split_symbols(&symbols(300), 200);
+    assert_eq!(shards.iter().map(Vec::len).collect::<Vec<_>>(), vec![150, 150]);
+    assert_eq!(shards[0][0], "SYM0USDT");
+    assert_eq!(shards[1][0], "SYM150USDT");
+    assert_eq!(shards.concat(), symbols(300));
+
+    assert_eq!(split_symbols(&symbols(7), 3).iter().map(Vec::len).collect::<Vec<_>>(), vec![3, 2, 2]);
+    assert_eq!(split_symbols(&symbols(200), 200).len(), 1);
+    // 0 は 1 接続
+    assert_eq!(split_symbols(&symbols(300), 0).len(), 1);
+    assert!(split_symbols(&[], 10).is_empty());
+}
+
+#[test]
+fn binance_symbols_per_connection_follow_the_stream_limit() {
+    let (tx, _rx) = mpsc::channel(1);
+    let client = BinanceClient::new(tx.clone(), 100);
+    assert_eq!(client.streams_per_symbol(), 1);
+    let client = BinanceClient::new(tx, 100).with_quotes().with_mark_prices();
+    assert_eq!(client.streams_per_symbol(), 3);
+    let per_connection = client.max_symbols_per_connection(&MarketType::Linear);
+    assert_eq!(per_connection, 66);
+    assert_eq!(split_symbols(&symbols(300), per_connection).len(), 5);
+}
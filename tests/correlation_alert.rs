@@ -0,0 +1,70 @@
+use chrono::DateTime;
+use kkcrypto::utils::correlation_alert::{parse_rules, CorrelationAlerts, CorrelationCondition, CorrelationRule};
+use kkcrypto::utils::rolling_correlation::PairCorrelation;
+
+fn resolve(symbol: &str) -> anyhow::Result<i32> {
+    match symbol {
+        "bybit:linear:BTCUSDT" => Ok(1),
+        "bybit:linear:ETHUSDT" => Ok(6),
+        _ => symbol.parse().map_err(|_| anyhow::anyhow!("Unknown symbol: {}", symbol)),
+    }
+}
+
+fn pairs(correlation: Option<f64>) -> Vec<PairCorrelation> {
+    vec![
+        PairCorrelation { symbol_a: 1, symbol_b: 6, count: 360, correlation },
+        PairCorrelation { symbol_a: 1, symbol_b: 9, count: 360, correlation: Some(0.9) },
+    ]
+}
+
+#[test]
+fn parses_rules() {
+    let rule = CorrelationRule::parse("bybit:linear:BTCUSDT/bybit:linear:ETHUSDT<0.5x3@30", resolve).unwrap();
+    assert_eq!((rule.symbol_a, rule.symbol_b), (1, 6));
+    assert_eq!(rule.condition, CorrelationCondition::Below(0.5));
+    assert_eq!((rule.intervals, rule.window_minutes), (3, Some(30)));
+    let rules = parse_rules("6/9>0.95, 1/9<-0.2", resolve).unwrap();
+    assert_eq!(rules[0].condition, CorrelationCondition::Above(0.95));
+    assert_eq!((rules[1].condition, rules[1].intervals, rules[1].window_minutes), (CorrelationCondition::Below(-0.2), 1, None));
+    for spec in ["1/6", "1<0.5", "1/1<0.5", "1/6<abc", "1/6<0.5x0", "1/6<0.5@0", "1/SOL<0.5"] {
+        assert!(CorrelationRule::parse(spec, resolve).is_err(), "{}", spec);
+    }
+    assert!(parse_rules(" , ", resolve).is_err());
+}
+
+#[test]
+fn fires_once_after_consecutive_intervals() {
+    let rules = parse_rules("6/1<0.5x3", resolve).unwrap();
+    let mut alerts = CorrelationAlerts::new(rules);
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let mut fired = Vec::new();
+    for correlation in [Some(0.4), Some(0.45), Some(0.6), Some(0.3), None, Some(0.2), Some(0.1), Some(0.05), Some(0.7)] {
+        fired.push(alerts.evaluate(timestamp, 30, &pairs(correlation)));
+    }
+    // 0.6 で数え直し, 計算できない回は数えない
+    assert!(fired[..6].iter().all(Vec::is_empty));
+    assert_eq!(fired[6].len(), 1);
+    let alert = &fired[6][0];
+    assert_eq!((alert.symbol_a, alert.symbol_b, alert.window_minutes, alert.resolved), (6, 1, 30, false));
+    assert_eq!(alert.recent, vec![0.3, 0.2, 0.1]);
+    assert!(alert.to_string().starts_with("corr(6, 1) 0.1000 below 0.5 for 3 intervals [0.3000, 0.2000, 0.1000] (30m window)"));
+    assert!(fired[7].is_empty());
+    assert_eq!(fired[8].len(), 1);
+    assert!(fired[8][0].resolved);
+}
+
+#[test]
+fn counts_each_window_separately() {
+    let rules = parse_rules("1/6<0.5x2, 1/9>0.8@240", resolve).unwrap();
+    let mut alerts = CorrelationAlerts::new(rules);
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    assert!(alerts.evaluate(timestamp, 5, &pairs(Some(0.1))).is_empty());
+    assert!(alerts.evaluate(timestamp, 240, &pairs(Some(0.9))).iter().all(|alert| alert.symbol_b == 9));
+    let fired = alerts.evaluate(timestamp, 5, &pairs(Some(0.2)));
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].window_minutes, 5);
+    // 240 分の窓は 5 分の窓とは別に 2 回続けて満たすまで発火しない
+    assert!(alerts.evaluate(timestamp, 240, &pairs(Some(0.3))).is_empty());
+    let fired = alerts.evaluate(timestamp, 240, &pairs(Some(0.3)));
+    assert_eq!(fired.iter().map(|alert| alert.rule.as_str()).collect::<Vec<_>>(), vec!["1/6<0.5x2"]);
+}
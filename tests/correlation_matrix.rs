@@ -0,0 +1,57 @@
+use chrono::DateTime;
+use kkcrypto::utils::correlation_matrix::{CorrelationMatrix, MatrixFormat};
+use kkcrypto::utils::rolling_correlation::PairCorrelation;
+
+fn matrix() -> CorrelationMatrix {
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let pairs = [
+        PairCorrelation { symbol_a: 1, symbol_b: 6, count: 360, correlation: Some(0.75) },
+        PairCorrelation { symbol_a: 1, symbol_b: 9, count: 360, correlation: Some(-0.5) },
+        PairCorrelation { symbol_a: 6, symbol_b: 9, count: 3, correlation: None },
+    ];
+    CorrelationMatrix::from_pairs(timestamp, 30, &pairs)
+}
+
+#[test]
+fn parses_format() {
+    assert_eq!(MatrixFormat::parse("JSON").unwrap(), MatrixFormat::Json);
+    assert_eq!(MatrixFormat::parse("csv").unwrap().as_str(), "csv");
+    assert!(MatrixFormat::parse("xml").is_err());
+}
+
+#[test]
+fn symmetric_with_unit_diagonal() {
+    let matrix = matrix();
+    assert_eq!(matrix.symbols, vec![1, 6, 9]);
+    assert_eq!(matrix.values[0], vec![Some(1.0), Some(0.75), Some(-0.5)]);
+    assert_eq!((matrix.get(6, 1), matrix.get(9, 1)), (Some(0.75), Some(-0.5)));
+    assert_eq!((matrix.get(6, 9), matrix.get(9, 9), matrix.get(2, 1)), (None, Some(1.0), None));
+}
+
+#[test]
+fn writes_json_line() {
+    let mut out = Vec::new();
+    matrix().write(MatrixFormat::Json, &mut out).unwrap();
+    let line = String::from_utf8(out).unwrap();
+    assert_eq!(line.lines().count(), 1);
+    let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(value["timestamp"], "2024-06-01T00:00:05.000Z");
+    assert_eq!(value["window"], 30);
+    assert_eq!(value["symbols"], serde_json::json!([1, 6, 9]));
+    assert_eq!(value["matrix"][2], serde_json::json!([-0.5, null, 1.0]));
+}
+
+#[test]
+fn writes_csv_rows() {
+    let mut out = Vec::new();
+    matrix().write(MatrixFormat::Csv, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+timestamp,window,symbol,1,6,9
+2024-06-01T00:00:05.000Z,30,1,1,0.75,-0.5
+2024-06-01T00:00:05.000Z,30,6,0.75,1,
+2024-06-01T00:00:05.000Z,30,9,-0.5,,1
+");
+    let mut out = Vec::new();
+    matrix().write(MatrixFormat::Table, &mut out).unwrap();
+    assert!(out.is_empty());
+}
@@ -0,0 +1,49 @@
+use chrono::DateTime;
+use kkcrypto::utils::correlation_store::{to_documents, to_ewma_documents, CorrelationParams};
+use kkcrypto::utils::rank_correlation::CorrelationMethod;
+use kkcrypto::utils::rolling_correlation::PairCorrelation;
+use mongodb::bson::Bson;
+
+#[test]
+fn one_document_per_pair() {
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let pairs = [
+        PairCorrelation { symbol_a: 1, symbol_b: 6, count: 360, correlation: Some(0.8125) },
+        PairCorrelation { symbol_a: 1, symbol_b: 7, count: 12, correlation: None },
+    ];
+    let params = CorrelationParams { window_minutes: 30, interval_seconds: 5, returns: None, method: CorrelationMethod::Pearson };
+    let docs = to_documents(timestamp, &params, &pairs);
+    assert_eq!(docs.len(), 2);
+
+    let metadata = docs[0].get_document("metadata").unwrap();
+    assert_eq!(metadata.get_i32("ym").unwrap(), 202406);
+    assert_eq!((metadata.get_i32("symbol_a").unwrap(), metadata.get_i32("symbol_b").unwrap()), (1, 6));
+    assert_eq!(metadata.get_i32("window").unwrap(), 30);
+    assert_eq!(metadata.get_i64("interval").unwrap(), 5);
+    assert_eq!(metadata.get_i32("returns").unwrap(), 0);
+    assert_eq!(metadata.get_str("method").unwrap(), "pearson");
+    assert_eq!(docs[0].get_datetime("unixtime").unwrap().timestamp_millis(), timestamp.timestamp_millis());
+    assert_eq!(docs[0].get_f64("coefficient").unwrap(), 0.8125);
+    assert_eq!(docs[0].get_i64("count").unwrap(), 360);
+    // 計算できなかったペアも null で残す
+    assert_eq!(docs[1].get("coefficient"), Some(&Bson::Null));
+
+    let params = CorrelationParams { returns: Some(12), ..params };
+    let docs = to_documents(timestamp, &params, &pairs);
+    assert_eq!(docs[0].get_document("metadata").unwrap().get_i32("returns").unwrap(), 12);
+}
+
+#[test]
+fn ewma_documents_carry_half_life() {
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let pairs = [PairCorrelation { symbol_a: 1, symbol_b: 6, count: 900, correlation: Some(-0.25) }];
+    let params = CorrelationParams { window_minutes: 30, interval_seconds: 5, returns: Some(1), method: CorrelationMethod::Spearman };
+    let docs = to_ewma_documents(timestamp, &params, 300, &pairs);
+    let metadata = docs[0].get_document("metadata").unwrap();
+    assert_eq!(metadata.get_str("method").unwrap(), "ewma");
+    assert_eq!(metadata.get_i64("half_life").unwrap(), 300);
+    assert_eq!(metadata.get_i32("returns").unwrap(), 1);
+    assert_eq!(docs[0].get_f64("coefficient").unwrap(), -0.25);
+    // 通常の相関には half_life を入れない
+    assert!(!to_documents(timestamp, &params, &pairs)[0].get_document("metadata").unwrap().contains_key("half_life"));
+}
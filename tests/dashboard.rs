@@ -0,0 +1,120 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use kkcrypto::cli::common::{self as cli, LogArgs, DEFAULT_LOG_RETENTION_DAYS};
+use kkcrypto::db::Database;
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade};
+use kkcrypto::utils::dashboard::{Dashboard, TRADE_WINDOW_SECONDS};
+use kkcrypto::utils::ops_events::{OpsEvent, OpsEventKind};
+use std::io::Write;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+fn trade(exchange: &str, symbol: &str, price: f64, quantity: f64) -> MarketEvent {
+    MarketEvent::Trade(Trade { exchange: exchange.to_string(), price, quantity, ..common::trade(symbol, "1", Utc::now()) })
+}
+
+fn candle(symbol: &str, timestamp: DateTime<Utc>, period_seconds: i32, warmup: bool) -> MarketEvent {
+    let mut candle = common::candle(symbol, timestamp, period_seconds);
+    candle.warmup = warmup;
+    MarketEvent::Candle(candle)
+}
+
+fn ops(kind: OpsEventKind, detail: &str, timestamp: DateTime<Utc>) -> OpsEvent {
+    OpsEvent { timestamp, exchange: "bybit".to_string(), market_type: "linear".to_string(), kind, detail: detail.to_string() }
+}
+
+#[tokio::test]
+async fn snapshot_rows() {
+    let db = Arc::new(Database::new("", false).await.unwrap());
+    let dashboard = Dashboard::new("bybit").with_database(db);
+    dashboard.track(&["BTCUSDT".to_string(), "SOLUSDT".to_string()]);
+    let now = Utc::now();
+    let minute = common::at(0);
+
+    // 窓より古い約定は出来高に入らない
+    dashboard.observe(&trade("bybit", "BTCUSDT", 67000.0, 0.5), now - Duration::seconds(TRADE_WINDOW_SECONDS + 5));
+    dashboard.observe(&trade("bybit", "BTCUSDT", 67010.0, 0.25), now - Duration::seconds(3));
+    dashboard.observe(&trade("bybit", "BTCUSDT", 67020.0, 0.25), now - Duration::seconds(1));
+    dashboard.observe(&candle("BTCUSDT", minute, 60, false), now - Duration::seconds(10));
+    dashboard.observe(&candle("BTCUSDT", minute, 1, false), now - Duration::seconds(2));
+    dashboard.observe(&candle("SOLUSDT", minute, 60, true), now);
+    dashboard.record_writer(3, 1);
+
+    let snapshot = dashboard.snapshot(now);
+    assert_eq!(snapshot.symbols.iter().map(|row| row.symbol.as_str()).collect::<Vec<_>>(), vec!["BTCUSDT", "SOLUSDT"]);
+    let btc = &snapshot.symbols[0];
+    assert_eq!(btc.last_price, Some(67020.0));
+    assert_eq!(btc.volume, 0.5);
+    assert_eq!(btc.trades_per_sec, 2.0 / TRADE_WINDOW_SECONDS as f64);
+    assert_eq!(btc.last_trade_age_secs, Some(1.0));
+    // 1 秒足は 2 秒出ていないので遅れている
+    assert_eq!(btc.candles.iter().map(|candle| (candle.period_seconds, candle.late)).collect::<Vec<_>>(), vec![(1, true), (60, false)]);
+    // warmup の足は数えない
+    let sol = &snapshot.symbols[1];
+    assert_eq!((sol.last_price, sol.last_trade_age_secs), (None, None));
+    assert!(sol.candles.is_empty());
+    assert_eq!((snapshot.pending_candles, snapshot.queued_candles), (3, 1));
+    assert_eq!(snapshot.database, Some((false, true)));
+}
+
+#[test]
+fn qualified_rows() {
+    let dashboard = Dashboard::new("collector").qualified();
+    dashboard.track(&["bybit:linear:BTCUSDT".to_string()]);
+    let now = Utc::now();
+    dashboard.observe(&trade("bybit", "BTCUSDT", 67000.0, 1.0), now);
+    dashboard.observe(&trade("binance", "BTCUSDT", 67001.0, 1.0), now);
+    let snapshot = dashboard.snapshot(now);
+    assert_eq!(
+        snapshot.symbols.iter().map(|row| (row.symbol.as_str(), row.last_price)).collect::<Vec<_>>(),
+        vec![("binance:linear:BTCUSDT", Some(67001.0)), ("bybit:linear:BTCUSDT", Some(67000.0))]
+    );
+}
+
+#[test]
+fn connections_and_logs() {
+    let dashboard = Dashboard::new("bybit");
+    let now = Utc::now();
+    assert_eq!(dashboard.snapshot(now).open_connections, 0);
+    dashboard.record_ops(&ops(OpsEventKind::Connect, "wss://stream.bybit.com/v5/public/linear", now));
+    dashboard.record_ops(&ops(OpsEventKind::Connect, "wss://stream.bybit.com/v5/public/linear", now));
+    dashboard.record_ops(&ops(OpsEventKind::Disconnect, "watchdog: no message for 60s", now));
+    for i in 0..300 {
+        dashboard.log(format!("line {}", i));
+    }
+
+    let snapshot = dashboard.snapshot(now);
+    assert_eq!((snapshot.open_connections, snapshot.connects, snapshot.disconnects), (1, 2, 1));
+    assert_eq!(snapshot.last_disconnect.map(|(_, reason)| reason).as_deref(), Some("watchdog: no message for 60s"));
+    assert_eq!(snapshot.database, None);
+    // 古い行から捨てる
+    assert_eq!(snapshot.logs.len(), 200);
+    assert_eq!(snapshot.logs.last().map(String::as_str), Some("line 299"));
+
+    // tracing の出力は行ごとにログに入る
+    let dashboard = Dashboard::new("bybit");
+    let mut writer = dashboard.log_writer();
+    writer.write_all(b"INFO kkcrypto: first\nINFO kkcrypto: second\n\n").unwrap();
+    assert_eq!(dashboard.snapshot(now).logs, vec!["INFO kkcrypto: first", "INFO kkcrypto: second"]);
+}
+
+#[tokio::test]
+async fn ops_events_reach_the_log_pane() {
+    // --tui と同じく tracing のログをダッシュボードのログ欄に出す
+    let dashboard = Dashboard::new("bybit");
+    let logs = LogArgs { log_format: "text".to_string(), log_dir: None, log_retention_days: DEFAULT_LOG_RETENTION_DAYS };
+    let _guard = tracing::subscriber::set_default(logs.subscriber("bybit-linear", BoxMakeWriter::new(dashboard.log_writer()), false).unwrap());
+    let db = Arc::new(Database::new("", false).await.unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    tx.send(ops(OpsEventKind::Connect, "wss://example", Utc::now())).unwrap();
+    drop(tx);
+    cli::write_ops_events("BYBIT".to_string(), rx, db, Some(dashboard.clone()), None).await;
+
+    let snapshot = dashboard.snapshot(Utc::now());
+    assert_eq!(snapshot.connects, 1);
+    let lines = snapshot.logs.iter().filter(|line| line.contains("[BYBIT-OPS] linear connect @")).collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].ends_with("| wss://example"));
+}
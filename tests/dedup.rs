@@ -0,0 +1,82 @@
+mod common;
+
+use chrono::DateTime;
+use kkcrypto::exchanges::{hyperliquid::HyperliquidClient, phemex::PhemexClient};
+use kkcrypto::models::{market_event::MarketEvent, market_type::MarketType, trade::Trade};
+use kkcrypto::utils::dedup::TradeDedup;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+fn trade(exchange: &str, symbol: &str, trade_id: &str) -> Trade {
+    Trade { exchange: exchange.to_string(), ..common::trade(symbol, trade_id, DateTime::from_timestamp_millis(1_700_000_000_000).unwrap()) }
+}
+
+#[test]
+fn duplicates_are_keyed_by_exchange_symbol_and_trade_id() {
+    let mut dedup = TradeDedup::new(10);
+    assert!(dedup.push(&trade("bybit", "BTCUSDT", "1")));
+    assert!(!dedup.push(&trade("bybit", "BTCUSDT", "1")));
+    // 同じ trade_id でも取引所・銘柄が違えば別の約定
+    assert!(dedup.push(&trade("binance", "BTCUSDT", "1")));
+    assert!(dedup.push(&trade("bybit", "ETHUSDT", "1")));
+    assert_eq!(dedup.len(), 3);
+    assert_eq!(dedup.duplicates(), 1);
+}
+
+#[test]
+fn least_recently_seen_keys_are_forgotten_first() {
+    let mut dedup = TradeDedup::new(2);
+    assert!(dedup.push(&trade("bybit", "BTCUSDT", "1")));
+    assert!(dedup.push(&trade("bybit", "BTCUSDT", "2")));
+    // 重複で 1 が最近見たものになり, 3 で 2 が追い出される
+    assert!(!dedup.push(&trade("bybit", "BTCUSDT", "1")));
+    assert!(dedup.push(&trade("bybit", "BTCUSDT", "3")));
+    assert_eq!(dedup.len(), 2);
+    assert!(!dedup.push(&trade("bybit", "BTCUSDT", "1")));
+    assert!(dedup.push(&trade("bybit", "BTCUSDT", "2")));
+}
+
+#[test]
+fn hyperliquid_fills_sharing_a_hash_are_kept() {
+    // 同じ注文の約定はトランザクションの hash を共有し, tid だけが異なる
+    let text = r#"{"channel":"trades","data":[{"coin":"BTC","side":"B","px":"67515.0","sz":"0.01","time":1717200000140,"hash":"0xabc","tid":1},{"coin":"BTC","side":"B","px":"67515.5","sz":"0.02","time":1717200000140,"hash":"0xabc","tid":2}]}"#;
+    let trades = HyperliquidClient::parse_trades(text, &MarketType::Linear).unwrap();
+    let mut dedup = TradeDedup::new(10);
+    assert_eq!(trades.len(), 2);
+    assert!(trades.iter().all(|trade| dedup.push(trade)));
+    // 再送された約定は落とす
+    assert!(!dedup.push(&trades[0]));
+}
+
+#[test]
+fn phemex_fills_sharing_a_timestamp_are_kept() {
+    // Phemex には約定 ID がないので, 別メッセージで同じ ns タイムスタンプの約定が届いても重複扱いしない
+    let first = r#"{"sequence":1,"symbol":"BTCUSDT","trades_p":[[1717200001452000000,"Buy","67518.3","0.125"]],"type":"incremental"}"#;
+    let second = r#"{"sequence":2,"symbol":"BTCUSDT","trades_p":[[1717200001452000000,"Sell","67518.2","0.5"]],"type":"incremental"}"#;
+    let mut dedup = TradeDedup::new(10);
+    for text in [first, second] {
+        for trade in PhemexClient::parse_trades(text, &MarketType::Linear, &HashMap::new()).unwrap() {
+            assert!(dedup.push(&trade));
+        }
+    }
+    assert_eq!(dedup.duplicates(), 0);
+}
+
+#[tokio::test]
+async fn run_drops_duplicate_trades() {
+    let (input_tx, input_rx) = mpsc::channel(10);
+    let (output_tx, mut output_rx) = mpsc::channel(10);
+    tokio::spawn(TradeDedup::new(100).run(input_rx, output_tx));
+    for trade_id in ["1", "2", "1", "3", "2"] {
+        input_tx.send(MarketEvent::Trade(trade("bybit", "BTCUSDT", trade_id))).await.unwrap();
+    }
+    drop(input_tx);
+
+    let mut trade_ids = Vec::new();
+    while let Some(event) = output_rx.recv().await {
+        if let MarketEvent::Trade(trade) = event {
+            trade_ids.push(trade.trade_id);
+        }
+    }
+    assert_eq!(trade_ids, vec!["1", "2", "3"]);
+}
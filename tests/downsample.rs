@@ -0,0 +1,82 @@
+mod common;
+
+use chrono::DateTime;
+use kkcrypto::models::{market_event::MarketEvent, trade::{Side, Trade}, trade_candle::TradeCandle};
+use kkcrypto::utils::downsample;
+use kkcrypto::utils::trade_candle_builder::TradeCandleBuilder;
+use tokio::sync::mpsc;
+
+const BASE: i64 = 1_700_000_040_000;  // 分の境界
+
+fn trade(trade_id: usize, offset_ms: i64, price: f64, quantity: f64, side: Side) -> Trade {
+    Trade { price, quantity, side, ..common::trade("BTCUSDT", &trade_id.to_string(), DateTime::from_timestamp_millis(BASE + offset_ms).unwrap()) }
+}
+
+/// 約定から 1s と 60s の足を作る
+async fn build(trades: Vec<Trade>) -> (Vec<TradeCandle>, Vec<TradeCandle>) {
+    let (trade_tx, trade_rx) = mpsc::channel(100);
+    let (candle_tx, mut candle_rx) = mpsc::channel(100);
+    let until = DateTime::from_timestamp_millis(BASE + 120_000).unwrap();
+    let builder = tokio::spawn(TradeCandleBuilder::new(trade_rx, candle_tx, vec![1, 60]).run_batch(until));
+    for trade in trades {
+        trade_tx.send(MarketEvent::Trade(trade)).await.unwrap();
+    }
+    drop(trade_tx);
+    builder.await.unwrap();
+    let (mut seconds, mut minutes) = (Vec::new(), Vec::new());
+    while let Some(MarketEvent::Candle(candle)) = candle_rx.recv().await {
+        if candle.period_seconds == 1 { seconds.push(candle) } else { minutes.push(candle) }
+    }
+    seconds.sort_by_key(|candle| candle.timestamp);
+    minutes.sort_by_key(|candle| candle.timestamp);
+    (seconds, minutes)
+}
+
+#[tokio::test]
+async fn downsampled_candles_match_candles_built_from_trades() {
+    let trades = vec![
+        trade(1, 100, 100.0, 1.0, Side::Buy),
+        trade(2, 300, 101.0, 2.0, Side::Sell),
+        trade(3, 1_500, 99.5, 0.5, Side::Buy),
+        trade(4, 30_200, 102.0, 1.5, Side::Buy),
+        trade(5, 30_900, 101.5, 3.0, Side::Sell),
+        trade(6, 65_000, 103.0, 1.0, Side::Buy),
+    ];
+    let (seconds, minutes) = build(trades).await;
+    let start = DateTime::from_timestamp_millis(BASE).unwrap();
+    let end = DateTime::from_timestamp_millis(BASE + 120_000).unwrap();
+    let derived = downsample::downsample(&seconds, 60, 0, start, end);
+
+    assert_eq!(derived.len(), minutes.len());
+    for (derived, built) in derived.iter().zip(&minutes) {
+        assert_eq!(derived.timestamp, built.timestamp);
+        assert_eq!((derived.ask_count, derived.bid_count), (built.ask_count, built.bid_count));
+        assert!((derived.ask_volume - built.ask_volume).abs() < 1e-9);
+        assert!((derived.bid_notional - built.bid_notional).abs() < 1e-9);
+        assert!((derived.ask_price.unwrap() - built.ask_price.unwrap()).abs() < 1e-9);
+        assert_eq!((derived.open, derived.high, derived.low, derived.close), (built.open, built.high, built.low, built.close));
+        assert!((derived.realized_vol.unwrap() - built.realized_vol.unwrap()).abs() < 1e-12);
+        assert_eq!(derived.trade_size.as_ref().unwrap().histogram, built.trade_size.as_ref().unwrap().histogram);
+    }
+}
+
+#[test]
+fn downsample_uses_the_latest_revision_and_only_whole_buckets() {
+    let candle = |offset_ms: i64, revision: u32, volume: f64| {
+        let mut candle = common::candle("BTCUSDT", DateTime::from_timestamp_millis(BASE + offset_ms).unwrap(), 1);
+        candle.ask_price = Some(100.0);
+        candle.ask_volume = volume;
+        candle.ask_count = 1;
+        candle.revision = revision;
+        candle
+    };
+    let candles = vec![candle(1_000, 0, 1.0), candle(1_000, 1, 2.0), candle(60_000, 0, 4.0), candle(61_000, 0, 8.0)];
+    let start = DateTime::from_timestamp_millis(BASE).unwrap();
+    // 2 本目の足 (60s - 120s) は end までに終わらないので出力しない
+    let end = DateTime::from_timestamp_millis(BASE + 90_000).unwrap();
+    let derived = downsample::downsample(&candles, 60, 0, start, end);
+    assert_eq!(derived.len(), 1);
+    assert_eq!(derived[0].timestamp.timestamp_millis(), BASE + 60_000);
+    assert_eq!(derived[0].ask_volume, 6.0);
+    assert_eq!(derived[0].ask_count, 2);
+}
@@ -0,0 +1,98 @@
+use chrono::Duration;
+use kkcrypto::utils::ewma::EwmaCorrelation;
+use kkcrypto::utils::price_window::PricePoint;
+use kkcrypto::utils::rank_correlation::pearson;
+use kkcrypto::utils::resample::FillPolicy;
+use kkcrypto::utils::rolling_correlation::RollingCorrelation;
+use std::collections::BTreeMap;
+
+fn row(values: &[(i32, f64)]) -> BTreeMap<i32, f64> {
+    values.iter().copied().collect()
+}
+
+#[test]
+fn half_life_halves_the_weight() {
+    let ewma = EwmaCorrelation::new(10.0, false);
+    assert!((ewma.lambda().powi(10) - 0.5).abs() < 1e-12);
+}
+
+#[test]
+fn long_half_life_matches_flat_statistics() {
+    // 半減期が十分長ければ重みはほぼ同じ
+    let mut ewma = EwmaCorrelation::new(1e12, false);
+    let x = [1.0, 2.0, 4.0, 8.0, 3.0];
+    let y = [2.0, 1.0, 5.0, 9.0, 2.5];
+    for (a, b) in x.iter().zip(&y) {
+        ewma.update(&row(&[(1, *a), (6, *b)]));
+    }
+    let pairs = ewma.correlations();
+    assert_eq!(pairs.len(), 1);
+    assert_eq!((pairs[0].symbol_a, pairs[0].symbol_b, pairs[0].count), (1, 6, 5));
+    assert!((pairs[0].correlation.unwrap() - pearson(&x, &y).unwrap()).abs() < 1e-9);
+    // 値が収益率ならボラティリティはそのままの標準偏差 (母分散)
+    let mean = x.iter().sum::<f64>() / 5.0;
+    let std = (x.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / 5.0).sqrt();
+    let volatility = ewma.volatilities().into_iter().find(|volatility| volatility.symbol_id == 1).unwrap();
+    assert!((volatility.volatility.unwrap() - std).abs() < 1e-9);
+    let cov = x.iter().zip(&y).map(|(a, b)| (a - mean) * (b - y.iter().sum::<f64>() / 5.0)).sum::<f64>() / 5.0;
+    assert!((ewma.covariance(6, 1).unwrap() - cov).abs() < 1e-9);
+}
+
+#[test]
+fn reacts_to_regime_shift() {
+    // 100 バケット同じ向きに動いたあと 20 バケット逆に動く
+    let x: Vec<f64> = (0..120).map(|i| (i as f64 * 0.9).sin()).collect();
+    let y: Vec<f64> = x.iter().enumerate().map(|(i, a)| if i < 100 { *a } else { -a }).collect();
+    let mut ewma = EwmaCorrelation::new(5.0, false);
+    for (a, b) in x.iter().zip(&y) {
+        ewma.update(&row(&[(1, *a), (2, *b)]));
+    }
+    assert!(pearson(&x, &y).unwrap() > 0.5);
+    assert!(ewma.correlations()[0].correlation.unwrap() < -0.5);
+}
+
+#[test]
+fn price_volatility_uses_log_returns() {
+    let mut ewma = EwmaCorrelation::new(20.0, true);
+    // 毎バケット 1% ずつ上がる価格は収益率が一定 -> ボラティリティ 0
+    for i in 0..50 {
+        ewma.update(&row(&[(1, 100.0 * 1.01f64.powi(i)), (2, 50.0 * if i % 2 == 0 { 1.0 } else { 1.02 })]));
+    }
+    let volatilities = ewma.volatilities();
+    assert!(volatilities[0].volatility.unwrap() < 1e-9);
+    assert_eq!(volatilities[0].count, 49);
+    // 交互に ±ln(1.02) -> 標準偏差はほぼ ln(1.02)
+    assert!((volatilities[1].volatility.unwrap() - 1.02f64.ln()).abs() < 1e-3);
+}
+
+#[test]
+fn missing_values_skip_pairs() {
+    let mut ewma = EwmaCorrelation::new(5.0, false);
+    ewma.update(&row(&[(1, 1.0), (2, 2.0), (3, 3.0)]));
+    ewma.update(&row(&[(1, 2.0), (2, 1.0)]));
+    ewma.update(&row(&[(1, 3.0), (2, 5.0), (3, 1.0)]));
+    let counts: Vec<(i32, i32, usize)> = ewma.correlations().iter().map(|pair| (pair.symbol_a, pair.symbol_b, pair.count)).collect();
+    assert_eq!(counts, vec![(1, 2, 3), (1, 3, 2), (2, 3, 2)]);
+    ewma.reset();
+    assert!(ewma.correlations().is_empty());
+    assert!(ewma.volatilities().is_empty());
+}
+
+#[test]
+fn rolling_feeds_ewma_beyond_the_window() {
+    let start = 1_717_200_000_000;
+    let mut rolling = RollingCorrelation::new(1, Duration::seconds(10), FillPolicy::Ffill { limit: None }).unwrap().with_ewma(5.0);
+    for second in 1..=30 {
+        let t = second as f64;
+        rolling.insert(&PricePoint { timestamp_ms: start + second * 1000, symbol_id: 1, price: 100.0 + (t * 0.7).sin() });
+        rolling.insert(&PricePoint { timestamp_ms: start + second * 1000, symbol_id: 2, price: 50.0 + (t * 0.7).sin() * 0.5 });
+        rolling.advance(start + second * 1000);
+    }
+    assert_eq!(rolling.len(), 10);
+    // EWMA は窓から出たバケットも重みを減らして使う
+    let ewma = rolling.ewma().unwrap();
+    let pairs = ewma.correlations();
+    assert_eq!(pairs[0].count, 30);
+    assert!((pairs[0].correlation.unwrap() - 1.0).abs() < 1e-9);
+    assert_eq!(ewma.volatilities().len(), 2);
+}
@@ -0,0 +1,128 @@
+// 記録済みペイロードに対するパーサーのゴールデンテスト
+// tests/fixtures/golden/{exchange}/{market}_{case}.payload.json を正規化し,
+// 同名の .golden.json と完全一致することを確認する.
+// UPDATE_GOLDEN=1 cargo test --test golden でゴールデンファイルを再生成する.
+
+use anyhow::Result;
+use kkcrypto::{
+    exchanges::{
+        backpack::BackpackClient, binance::BinanceClient, bitstamp::BitstampClient, bybit::BybitClient, hyperliquid::HyperliquidClient,
+        phemex::{PhemexClient, PhemexScale},
+    },
+    models::{market_type::MarketType, trade::Trade},
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FIXTURE_DIR: &str = "tests/fixtures/golden";
+
+fn parse(exchange: &str, text: &str, market_type: &MarketType) -> Result<Vec<Trade>> {
+    match exchange {
+        "bybit" => BybitClient::parse_trades(text, market_type),
+        "binance" => BinanceClient::parse_trades(text, market_type),
+        "hyperliquid" => HyperliquidClient::parse_trades(text, market_type),
+        "bitstamp" => BitstampClient::parse_trades(text, market_type),
+        "backpack" => BackpackClient::parse_trades(text, market_type),
+        "phemex" => PhemexClient::parse_trades(text, market_type, &phemex_scales()),
+        _ => panic!("No parser for exchange: {}", exchange),
+    }
+}
+
+// /public/products の記録値 (sBTCUSDT: priceScale=8, BTC valueScale=8 / BTCUSD: priceScale=4)
+fn phemex_scales() -> HashMap<String, PhemexScale> {
+    HashMap::from([
+        ("sBTCUSDT".to_string(), PhemexScale { price_scale: 8, qty_scale: 8 }),
+        ("BTCUSD".to_string(), PhemexScale { price_scale: 4, qty_scale: 0 }),
+    ])
+}
+
+fn market_type_from_case(case: &str) -> MarketType {
+    match case.split('_').next() {
+        Some("spot") => MarketType::Spot,
+        Some("linear") => MarketType::Linear,
+        Some("inverse") => MarketType::Inverse,
+        _ => panic!("Case name must start with spot_/linear_/inverse_: {}", case),
+    }
+}
+
+// Trade.id はランダムな UUID, received_at は受信時刻なので比較対象から外す
+fn normalize(trades: &[Trade]) -> Value {
+    let mut value = serde_json::to_value(trades).unwrap();
+    for trade in value.as_array_mut().unwrap() {
+        let trade = trade.as_object_mut().unwrap();
+        trade.remove("id");
+        trade.remove("received_at");
+    }
+    value
+}
+
+fn payload_files(exchange: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(Path::new(FIXTURE_DIR).join(exchange))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.to_string_lossy().ends_with(".payload.json"))
+        .collect();
+    files.sort();
+    files
+}
+
+fn check_exchange(exchange: &str) {
+    let update = std::env::var("UPDATE_GOLDEN").is_ok();
+    let files = payload_files(exchange);
+    assert!(!files.is_empty(), "No fixtures for {}", exchange);
+
+    for payload_path in files {
+        let file_name = payload_path.file_name().unwrap().to_string_lossy().to_string();
+        let case = file_name.trim_end_matches(".payload.json");
+        let golden_path = payload_path.with_file_name(format!("{}.golden.json", case));
+
+        let text = fs::read_to_string(&payload_path).unwrap();
+        let trades = parse(exchange, &text, &market_type_from_case(case))
+            .unwrap_or_else(|e| panic!("{}/{}: parse failed: {}", exchange, case, e));
+        let actual = normalize(&trades);
+
+        if update {
+            fs::write(&golden_path, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+            continue;
+        }
+
+        let expected: Value = serde_json::from_str(
+            &fs::read_to_string(&golden_path)
+                .unwrap_or_else(|_| panic!("Missing golden file: {}", golden_path.display())),
+        )
+        .unwrap();
+        assert_eq!(actual, expected, "{}/{}: normalized trades differ from golden", exchange, case);
+    }
+}
+
+#[test]
+fn bybit_golden() {
+    check_exchange("bybit");
+}
+
+#[test]
+fn binance_golden() {
+    check_exchange("binance");
+}
+
+#[test]
+fn hyperliquid_golden() {
+    check_exchange("hyperliquid");
+}
+
+#[test]
+fn bitstamp_golden() {
+    check_exchange("bitstamp");
+}
+
+#[test]
+fn phemex_golden() {
+    check_exchange("phemex");
+}
+
+#[test]
+fn backpack_golden() {
+    check_exchange("backpack");
+}
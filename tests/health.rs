@@ -0,0 +1,70 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use kkcrypto::db::Database;
+use kkcrypto::models::market_event::MarketEvent;
+use kkcrypto::utils::health::{HealthState, MAX_SILENCE_SECONDS};
+use kkcrypto::utils::ops_events::{OpsEvent, OpsEventKind};
+use kkcrypto::utils::trade_candle_builder::BufferMetrics;
+use std::sync::Arc;
+
+fn trade(symbol: &str) -> MarketEvent {
+    MarketEvent::Trade(common::trade(symbol, "1", Utc::now()))
+}
+
+fn ops(kind: OpsEventKind, detail: &str, timestamp: DateTime<Utc>) -> OpsEvent {
+    OpsEvent { timestamp, exchange: "bybit".to_string(), market_type: "linear".to_string(), kind, detail: detail.to_string() }
+}
+
+#[tokio::test]
+async fn status_reports_connections_and_message_ages() {
+    let db = Arc::new(Database::new("", false).await.unwrap());
+    let health = HealthState::new().with_database(db).with_buffer_metrics(BufferMetrics::default());
+    let now = Utc::now();
+
+    // 接続前は ready でない
+    let status = health.status(now);
+    assert!(!status.ready);
+    assert_eq!(status.reasons, vec!["no open exchange connection"]);
+
+    health.record_ops(&ops(OpsEventKind::Connect, "wss://stream.bybit.com/v5/public/linear", now));
+    health.record_ops(&ops(OpsEventKind::Connect, "wss://stream.bybit.com/v5/public/linear", now));
+    health.record_ops(&ops(OpsEventKind::Disconnect, "watchdog: no message for 60s", now));
+    health.record_ops(&ops(OpsEventKind::Subscribe, "BTCUSDT", now));
+    health.observe(&trade("BTCUSDT"), now - Duration::seconds(10));
+    health.observe(&trade("ETHUSDT"), now - Duration::seconds(2));
+
+    let status = health.status(now);
+    assert!(status.ready, "{:?}", status.reasons);
+    assert_eq!((status.connections.open, status.connections.connects, status.connections.disconnects), (1, 2, 1));
+    assert_eq!(status.connections.last_disconnect_reason.as_deref(), Some("watchdog: no message for 60s"));
+    let bybit = &status.exchanges["bybit"];
+    assert_eq!(bybit.last_message_age_secs, 2.0);
+    assert_eq!(bybit.symbols["BTCUSDT"], 10.0);
+    let database = status.database.unwrap();
+    assert!(!database.enabled && database.healthy);
+    assert_eq!(status.buffers.unwrap().active, 0);
+
+    // 全ての symbol が止まると ready でなくなる
+    let status = health.status(now + Duration::seconds(MAX_SILENCE_SECONDS));
+    assert!(!status.ready);
+    assert!(status.reasons[0].starts_with("no message for"));
+}
+
+#[test]
+fn respond_serves_healthz_and_status() {
+    let health = HealthState::new();
+    let now = Utc::now();
+    let (code, body) = health.respond("GET /healthz HTTP/1.1", now);
+    assert_eq!(code, 503);
+    assert!(body.contains("no open exchange connection"));
+
+    health.record_ops(&ops(OpsEventKind::Connect, "wss://example", now));
+    assert_eq!(health.respond("GET /healthz HTTP/1.1", now).0, 200);
+    let (code, body) = health.respond("GET /status?pretty HTTP/1.1", now);
+    assert_eq!(code, 200);
+    let status: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(status["connections"]["open"], 1);
+    assert_eq!(health.respond("GET /metrics HTTP/1.1", now).0, 404);
+    assert_eq!(health.respond("POST /healthz HTTP/1.1", now).0, 405);
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::heikin_ashi::HeikinAshiBuilder;
+
+fn candle(minute: i64, period_seconds: i32, ohlc: Option<(f64, f64, f64, f64)>) -> TradeCandle {
+    let mut candle = common::candle("BTCUSDT", common::at(minute * 60), period_seconds);
+    if let Some((open, high, low, close)) = ohlc {
+        (candle.open, candle.high, candle.low, candle.close) = (Some(open), Some(high), Some(low), Some(close));
+    }
+    candle
+}
+
+#[test]
+fn parse_heikin_ashi_timeframes() {
+    assert!(HeikinAshiBuilder::parse("60,300", &[1, 60, 300]).is_ok());
+    assert!(HeikinAshiBuilder::parse("*", &[60]).is_ok());
+    assert!(HeikinAshiBuilder::parse("900", &[60]).is_err());
+    assert!(HeikinAshiBuilder::parse("1m,5m", &[60, 300]).is_ok());
+    assert!(HeikinAshiBuilder::parse("1x", &[60]).is_err());
+}
+
+#[test]
+fn heikin_ashi_follows_previous_candle() {
+    let mut builder = HeikinAshiBuilder::new(vec![60]);
+    let first = builder.push(&candle(1, 60, Some((100.0, 110.0, 90.0, 104.0)))).unwrap();
+    assert_eq!((first.open, first.close, first.high, first.low), (102.0, 101.0, 110.0, 90.0));
+    assert_eq!(first.collection_name().as_deref(), Some("ha_candles_1m"));
+
+    let second = builder.push(&candle(2, 60, Some((104.0, 106.0, 100.0, 102.0)))).unwrap();
+    assert_eq!((second.open, second.close), (101.5, 103.0));
+    assert_eq!((second.high, second.low), (106.0, 100.0));
+
+    // 最新の足の訂正は 1 本前の平均足から作り直す
+    let mut corrected = candle(2, 60, Some((104.0, 108.0, 100.0, 108.0)));
+    corrected.revision = 1;
+    let corrected = builder.push(&corrected).unwrap();
+    assert_eq!((corrected.open, corrected.close, corrected.revision), (101.5, 105.0, 1));
+
+    // 対象外の時間枠, 約定のない足, 古い足は作らない
+    assert!(builder.push(&candle(3, 300, Some((1.0, 1.0, 1.0, 1.0)))).is_none());
+    assert!(builder.push(&candle(3, 60, None)).is_none());
+    assert!(builder.push(&candle(1, 60, Some((1.0, 1.0, 1.0, 1.0)))).is_none());
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use chrono::Utc;
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::index::{IndexBuilder, IndexDefinition, INDEX_EXCHANGE};
+
+fn candle(exchange: &str, symbol: &str, timestamp: chrono::DateTime<Utc>, open: f64, close: f64, notional: f64) -> TradeCandle {
+    let mut candle = common::candle(symbol, timestamp, 60);
+    candle.exchange = exchange.to_string();
+    let price = (open + close) / 2.0;
+    candle.ask_price = Some(price);
+    candle.ask_volume = notional / price;
+    candle.ask_notional = notional;
+    candle.ask_count = 1;
+    candle.open = Some(open);
+    candle.high = Some(open.max(close));
+    candle.low = Some(open.min(close));
+    candle.close = Some(close);
+    candle
+}
+
+#[test]
+fn parse_index_definitions() {
+    let definition = IndexDefinition::parse("BTC70-ETH30 = 0.7*bybit:linear:BTCUSDT + 0.3*Binance:linear:ETHUSDT").unwrap();
+    assert_eq!(definition.name, "BTC70-ETH30");
+    assert_eq!(definition.constituents.len(), 2);
+    assert_eq!((definition.constituents[1].exchange.as_str(), definition.constituents[1].weight), ("binance", 0.3));
+    for spec in [
+        "0.7*bybit:linear:BTCUSDT",
+        "X = 0.7*bybit:BTCUSDT",
+        "X = -1*bybit:linear:BTCUSDT",
+        "X = 1*bybit:perp:BTCUSDT",
+        "X = 1*bybit:linear:BTCUSDT + 2*bybit:linear:BTCUSDT",
+    ] {
+        assert!(IndexDefinition::parse(spec).is_err(), "{}", spec);
+    }
+}
+
+#[test]
+fn compose_weighted_candles_holding_idle_constituents() {
+    let mut builder = IndexBuilder::new(vec![IndexDefinition::parse("BTC70-ETH30 = 0.7*bybit:linear:BTCUSDT + 0.3*binance:linear:ETHUSDT").unwrap()]);
+    let t0 = chrono::DateTime::from_timestamp(1_767_225_600, 0).unwrap();
+    let t1 = t0 + chrono::Duration::seconds(60);
+
+    // ETHUSDT に約定がまだないので出力しない
+    assert!(builder.compose(t0, 60, &[candle("bybit", "BTCUSDT", t0, 100.0, 110.0, 1000.0)]).is_empty());
+
+    let composites = builder.compose(t1, 60, &[
+        candle("bybit", "BTCUSDT", t1, 110.0, 120.0, 1000.0),
+        candle("binance", "ETHUSDT", t1, 10.0, 20.0, 500.0),
+    ]);
+    let index = &composites[0];
+    assert_eq!((index.exchange.as_str(), index.symbol.as_str(), index.timestamp), (INDEX_EXCHANGE, "BTC70-ETH30", t1));
+    assert!((index.vwap().unwrap() - (0.7 * 115.0 + 0.3 * 15.0)).abs() < 1e-9);
+    assert!((index.open.unwrap() - (0.7 * 110.0 + 0.3 * 10.0)).abs() < 1e-9);
+    assert!((index.close.unwrap() - (0.7 * 120.0 + 0.3 * 20.0)).abs() < 1e-9);
+    assert_eq!((index.ask_notional, index.ask_count), (1500.0, 2));
+
+    // 約定のない BTCUSDT は直前の終値で横ばい
+    let t2 = t1 + chrono::Duration::seconds(60);
+    let index = builder.compose(t2, 60, &[candle("binance", "ETHUSDT", t2, 20.0, 30.0, 100.0)]).remove(0);
+    assert!((index.close.unwrap() - (0.7 * 120.0 + 0.3 * 30.0)).abs() < 1e-9);
+    assert!((index.high.unwrap() - (0.7 * 120.0 + 0.3 * 30.0)).abs() < 1e-9);
+    let doc = index.to_timeseries_document();
+    assert!(doc.contains_key("close") && !doc.contains_key("realized_vol"));
+}
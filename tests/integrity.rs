@@ -0,0 +1,119 @@
+mod common;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::integrity::{self, GapScanner, IssueKind};
+
+const BASE: i64 = 1_700_000_040;  // 分の境界
+
+fn time(offset_seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(BASE + offset_seconds, 0).unwrap()
+}
+
+/// 終端が BASE + offset_seconds の 60s 足 (買い約定 1 件)
+fn candle(offset_seconds: i64) -> TradeCandle {
+    let mut candle = common::candle("BTCUSDT", time(offset_seconds), 60);
+    candle.ask_price = Some(100.0);
+    candle.ask_volume = 1.0;
+    candle.ask_notional = 100.0;
+    candle.ask_count = 1;
+    (candle.open, candle.high, candle.low, candle.close) = (Some(100.0), Some(100.0), Some(100.0), Some(100.0));
+    candle
+}
+
+fn scanner(min_gap_seconds: i64) -> GapScanner {
+    GapScanner::new("bybit", "linear", "BTCUSDT", 60, 0, time(0), Duration::seconds(min_gap_seconds))
+}
+
+#[test]
+fn reports_gaps_of_at_least_min_gap() {
+    let mut scanner = scanner(180);
+    // 120 の 1 本と末尾 540, 600 の 2 本の欠けは短いので報告せず, 240..=420 の 4 本だけを報告する
+    for offset in [60, 180, 480] {
+        scanner.push(&candle(offset));
+    }
+    let (summary, issues) = scanner.finish(time(600));
+    assert_eq!(summary.candles, 3);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::Missing);
+    assert_eq!((issues[0].from, issues[0].to, issues[0].count), (time(240), time(420), 4));
+    assert_eq!(summary.missing, 4);
+
+    let mut scanner = scanner(60);
+    scanner.push(&candle(60));
+    let (summary, issues) = scanner.finish(time(200));
+    // 終端が end を過ぎる 240 の足は数えない
+    assert_eq!(summary.missing, 2);
+    assert_eq!((issues[0].from, issues[0].to), (time(120), time(180)));
+}
+
+#[test]
+fn reports_duplicates_and_invalid_values() {
+    let mut scanner = scanner(60);
+    scanner.push(&candle(60));
+    scanner.push(&candle(60));
+    scanner.push(&candle(60));
+    let mut negative = candle(120);
+    negative.bid_volume = -1.0;
+    scanner.push(&negative);
+    let mut off_boundary = candle(150);
+    off_boundary.timestamp = time(150);
+    scanner.push(&off_boundary);
+    let (summary, issues) = scanner.finish(time(180));
+    assert_eq!(summary.duplicates, 3);
+    assert_eq!(summary.invalid, 2);
+    assert_eq!(summary.missing, 0);
+    assert_eq!(issues[0].kind, IssueKind::Duplicate);
+    assert_eq!(issues[0].count, 3);
+    assert!(issues[1].detail.contains("negative bid"));
+    assert!(issues[2].detail.contains("boundary"));
+}
+
+#[test]
+fn invalid_reason() {
+    assert_eq!(integrity::invalid_reason(&candle(60)), None);
+
+    // --emit-empty の足は価格だけを引き継ぐ
+    let mut empty = common::candle("BTCUSDT", time(60), 60);
+    empty.ask_price = Some(100.0);
+    empty.bid_price = Some(99.0);
+    assert_eq!(integrity::invalid_reason(&empty), None);
+
+    let mut without_trades = candle(60);
+    without_trades.bid_price = Some(99.0);
+    assert!(integrity::invalid_reason(&without_trades).unwrap().contains("without bid trades"));
+
+    let mut outside = candle(60);
+    outside.close = Some(101.0);
+    assert!(integrity::invalid_reason(&outside).unwrap().contains("close outside"));
+
+    let mut zero_price = candle(60);
+    zero_price.ask_price = Some(0.0);
+    assert!(integrity::invalid_reason(&zero_price).is_some());
+}
+
+#[test]
+fn backfill_days_merges_ranges() {
+    let day = |d: u32| NaiveDate::from_ymd_opt(2026, 1, d).unwrap();
+    let at = |d: u32, h: u32| day(d).and_hms_opt(h, 0, 0).unwrap().and_utc();
+    let mut scanner = GapScanner::new("bybit", "linear", "BTCUSDT", 3600, 0, at(1, 0), Duration::seconds(3600));
+    // 1 日 01:00 .. 2 日 23:00 の足と 3 日 02:00, 03:00 の足が欠けている
+    scanner.push(&{
+        let mut c = candle(0);
+        c.timestamp = at(3, 0);
+        c.period_seconds = 3600;
+        c
+    });
+    scanner.push(&{
+        let mut c = candle(0);
+        c.timestamp = at(3, 1);
+        c.period_seconds = 3600;
+        c
+    });
+    let (_, issues) = scanner.finish(at(3, 3));
+    assert_eq!(issues.len(), 2);
+    let days = integrity::backfill_days(&issues);
+    // 1 日と 2 日, 3 日の取り直しは続いているので 1 つにまとめる
+    assert_eq!(days.len(), 1);
+    assert_eq!((days[0].1, days[0].2), (day(1), day(4)));
+}
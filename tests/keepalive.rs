@@ -0,0 +1,57 @@
+use kkcrypto::utils::keepalive::{self, Keepalive, KeepaliveAction, WatchdogTimeout};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+#[tokio::test]
+async fn keepalive_sends_ping_every_period() {
+    let ping = Message::Text(r#"{"op":"ping"}"#.to_string());
+    let mut keepalive = Keepalive::new().with_ping(Duration::from_millis(30), ping.clone());
+    let started = Instant::now();
+    for _ in 0..3 {
+        assert_eq!(keepalive.next().await, KeepaliveAction::Ping(ping.clone()));
+    }
+    assert!(started.elapsed() >= Duration::from_millis(90));
+}
+
+#[tokio::test]
+async fn watchdog_times_out_without_frames() {
+    let mut keepalive = Keepalive::new().with_watchdog(Some(Duration::from_millis(50)));
+    let started = Instant::now();
+    assert_eq!(keepalive.next().await, KeepaliveAction::Timeout(Duration::from_millis(50)));
+    assert!(started.elapsed() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn received_frames_defer_the_watchdog() {
+    let ping = Message::Ping(Vec::new());
+    let mut keepalive = Keepalive::new()
+        .with_ping(Duration::from_millis(20), ping.clone())
+        .with_watchdog(Some(Duration::from_millis(80)));
+    // pong が返ってくる間は ping だけが続く
+    for _ in 0..8 {
+        assert_eq!(keepalive.next().await, KeepaliveAction::Ping(ping.clone()));
+        keepalive.received();
+    }
+    // pong が途絶えると watchdog が切れる
+    let timed_out = loop {
+        if let KeepaliveAction::Timeout(timeout) = keepalive.next().await {
+            break timeout;
+        }
+    };
+    assert_eq!(timed_out, Duration::from_millis(80));
+}
+
+#[test]
+fn protocol_pings_are_answered_with_the_same_payload() {
+    assert_eq!(keepalive::pong(&Message::Ping(vec![1, 2, 3])), Some(Message::Pong(vec![1, 2, 3])));
+    assert_eq!(keepalive::pong(&Message::Text("{}".to_string())), None);
+    assert_eq!(keepalive::pong(&Message::Pong(Vec::new())), None);
+}
+
+#[test]
+fn watchdog_timeout_is_distinguishable_from_other_errors() {
+    let error = anyhow::Error::from(WatchdogTimeout(Duration::from_secs(60)));
+    assert!(error.is::<WatchdogTimeout>());
+    assert_eq!(error.to_string(), "watchdog: no message for 60s");
+    assert!(!anyhow::anyhow!("stream closed").is::<WatchdogTimeout>());
+}
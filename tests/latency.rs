@@ -0,0 +1,76 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade};
+use kkcrypto::utils::latency::{percentile, LatencyMetrics};
+use kkcrypto::utils::trade_candle_builder::TradeCandleBuilder;
+use tokio::sync::mpsc;
+
+fn trade(trade_id: &str, timestamp: DateTime<Utc>, latency_ms: i64) -> Trade {
+    Trade {
+        exchange: "hyperliquid".to_string(),
+        received_at: timestamp + Duration::milliseconds(latency_ms),
+        ..common::trade("BTC", trade_id, timestamp)
+    }
+}
+
+#[test]
+fn percentile_uses_nearest_rank() {
+    let sorted: Vec<f64> = (1..=100).map(f64::from).collect();
+    assert_eq!(percentile(&sorted, 0.5), 50.0);
+    assert_eq!(percentile(&sorted, 0.99), 99.0);
+    assert_eq!(percentile(&sorted, 1.0), 100.0);
+    assert_eq!(percentile(&sorted[..1], 0.99), 1.0);
+    assert!(percentile(&[], 0.5).is_nan());
+}
+
+#[test]
+fn summary_covers_the_rolling_window_only() {
+    let metrics = LatencyMetrics::new(Duration::seconds(60));
+    let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+    assert_eq!(metrics.summary(start), None);
+
+    // 窓から外れる古い約定
+    assert!(metrics.record(&trade("0", start, 5_000)));
+    for i in 1..=100 {
+        assert!(metrics.record(&trade(&i.to_string(), start + Duration::seconds(60), i)));
+    }
+    // REST で補完した古い約定は遅延に含めない
+    assert!(!metrics.record(&trade("backfill", start, 600_000)));
+
+    let summary = metrics.summary(start + Duration::seconds(70)).unwrap();
+    assert_eq!(summary.count, 100);
+    assert_eq!((summary.min_ms, summary.p50_ms, summary.p99_ms, summary.max_ms), (1.0, 50.0, 99.0, 100.0));
+    assert_eq!(summary.to_string(), "trades:100 min:1ms p50:50ms p99:99ms max:100ms");
+
+    // ローカルの時計が遅れていれば負になる
+    let skewed = LatencyMetrics::new(Duration::seconds(60));
+    skewed.record(&trade("1", start, -250));
+    assert_eq!(skewed.summary(start).unwrap().p50_ms, -250.0);
+    assert_eq!(metrics.summary(start + Duration::seconds(200)), None);
+}
+
+#[tokio::test]
+async fn candles_carry_mean_latency_when_enabled() {
+    for enabled in [false, true] {
+        let (event_tx, event_rx) = mpsc::channel(16);
+        let (output_tx, mut output_rx) = mpsc::channel(16);
+        let mut builder = TradeCandleBuilder::new(event_rx, output_tx, vec![1]);
+        if enabled {
+            builder = builder.with_latency();
+        }
+        tokio::spawn(builder.start());
+        let timestamp = Utc::now() - Duration::seconds(5);
+        for (i, latency_ms) in [100, 300].into_iter().enumerate() {
+            event_tx.send(MarketEvent::Trade(trade(&i.to_string(), timestamp, latency_ms))).await.unwrap();
+        }
+        // 終端を過ぎた足は次の 1 秒の境界で出力される
+        let candle = match tokio::time::timeout(std::time::Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+            MarketEvent::Candle(candle) => candle,
+            other => panic!("unexpected {}", other.kind()),
+        };
+        assert_eq!(candle.ask_count, 2);
+        assert_eq!(candle.latency_ms, enabled.then_some(200.0));
+        assert_eq!(candle.to_timeseries_document().get_f64("latency_ms").ok(), enabled.then_some(200.0));
+    }
+}
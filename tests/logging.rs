@@ -0,0 +1,44 @@
+use kkcrypto::cli::common::{LogArgs, LogFormat};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+
+fn log_args(format: &str, dir: Option<std::path::PathBuf>, retention_days: usize) -> LogArgs {
+    LogArgs { log_format: format.to_string(), log_dir: dir, log_retention_days: retention_days }
+}
+
+#[test]
+fn parses_log_format() {
+    assert_eq!(LogFormat::parse("JSON").unwrap(), LogFormat::Json);
+    assert_eq!(LogFormat::parse("text").unwrap(), LogFormat::Text);
+    assert!(LogFormat::parse("logfmt").is_err());
+    // subscriber を設定する前にエラーになる
+    assert!(log_args("logfmt", None, 14).init_with("bybit-linear", BoxMakeWriter::new(std::io::sink), false).is_err());
+    assert!(log_args("json", Some(std::env::temp_dir()), 0).init_with("bybit-linear", BoxMakeWriter::new(std::io::sink), false).is_err());
+}
+
+#[test]
+fn writes_json_lines_to_the_log_dir() {
+    let dir = std::env::temp_dir().join(format!("kkcrypto_logs_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let logs = log_args("json", Some(dir.clone()), 3);
+    logs.init_with("bybit-linear", BoxMakeWriter::new(std::io::sink), false).unwrap();
+    tracing::info!(target: "kkcrypto::logging", symbol = "BTCUSDT", "[BYBIT-OPS] linear connect");
+    tracing::debug!(target: "kkcrypto::logging", "filtered out by kkcrypto=info");
+    // subscriber は 1 つだけ
+    assert!(logs.init_with("bybit-linear", BoxMakeWriter::new(std::io::sink), false).is_err());
+
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().path()).collect();
+    assert_eq!(files.len(), 1);
+    let name = files[0].file_name().unwrap().to_string_lossy().to_string();
+    assert!(name.starts_with("bybit-linear.") && name.ends_with(".log"), "{}", name);
+    let content = std::fs::read_to_string(&files[0]).unwrap();
+    let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    if std::env::var("RUST_LOG").is_err() {
+        assert_eq!(lines.len(), 1);
+    }
+    assert_eq!(lines[0]["level"], "INFO");
+    assert_eq!(lines[0]["target"], "kkcrypto::logging");
+    assert_eq!(lines[0]["message"], "[BYBIT-OPS] linear connect");
+    assert_eq!(lines[0]["symbol"], "BTCUSDT");
+    assert!(lines[0]["timestamp"].is_string());
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,346 @@
+use chrono::Utc;
+use kkcrypto::models::{
+    market_event::MarketEvent,
+    market_type::MarketType,
+    quote::Quote,
+    trade::{Side, Trade},
+    trade_candle::{TradeCandle, TradeSizeStats},
+};
+use kkcrypto::utils::{audit, trade_candle_builder::TradeCandleBuilder};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn start_builder() -> (mpsc::Sender<MarketEvent>, mpsc::Receiver<MarketEvent>) {
+    let (event_tx, event_rx) = mpsc::channel(16);
+    let (output_tx, output_rx) = mpsc::channel(16);
+    tokio::spawn(TradeCandleBuilder::new(event_rx, output_tx, vec![1]).start());
+    (event_tx, output_rx)
+}
+
+#[tokio::test]
+async fn non_trade_events_pass_through_builder() {
+    let (event_tx, mut output_rx) = start_builder();
+    let quote = Quote::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), 100.0, 1.0, 101.0, 2.0, Utc::now());
+    event_tx.send(quote.into()).await.unwrap();
+
+    let event = tokio::time::timeout(Duration::from_secs(1), output_rx.recv()).await.unwrap().unwrap();
+    match event {
+        MarketEvent::Quote(q) => assert_eq!((q.symbol.as_str(), q.bid_price, q.ask_price), ("BTCUSDT", 100.0, 101.0)),
+        other => panic!("unexpected {}", other.kind()),
+    }
+}
+
+#[tokio::test]
+async fn trades_become_candles() {
+    let (event_tx, mut output_rx) = start_builder();
+    for (price, side) in [(100.0, Side::Buy), (102.0, Side::Buy), (99.0, Side::Sell)] {
+        let trade = Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), price.to_string(), price, 1.0, side, Utc::now());
+        event_tx.send(trade.into()).await.unwrap();
+    }
+
+    // 1 秒足のタイマーで出力される (約定そのものは後段に流れない)
+    // タイマーと送信のタイミング次第で 2 本に分かれることがあるため件数の合計で確認する
+    let mut counts = 0;
+    while counts < 3 {
+        let event = tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap();
+        match event {
+            MarketEvent::Candle(candle) => {
+                assert_eq!(candle.period_seconds, 1);
+                // 足の時刻は約定時刻を含む区間の終端 (秒の境界)
+                assert_eq!(candle.timestamp.timestamp_subsec_nanos(), 0);
+                assert!(candle.timestamp <= Utc::now());
+                counts += candle.ask_count + candle.bid_count;
+            }
+            other => panic!("unexpected {}", other.kind()),
+        }
+    }
+    assert_eq!(counts, 3);
+}
+
+#[tokio::test]
+async fn late_trade_emits_correction() {
+    let (event_tx, event_rx) = mpsc::channel(16);
+    let (output_tx, mut output_rx) = mpsc::channel(16);
+    let builder = TradeCandleBuilder::new(event_rx, output_tx, vec![1]).with_grace(Duration::from_millis(300));
+    tokio::spawn(builder.start());
+    let trade = |price: f64, timestamp| Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), price.to_string(), price, 1.0, Side::Buy, timestamp);
+
+    event_tx.send(trade(100.0, Utc::now()).into()).await.unwrap();
+    let first = match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+        MarketEvent::Candle(candle) => candle,
+        other => panic!("unexpected {}", other.kind()),
+    };
+    assert_eq!((first.revision, first.ask_count + first.bid_count), (0, 1));
+
+    // 出力済みの足に含まれる時刻の約定が遅れて届くと, 次の境界で revision を上げた足が出る
+    event_tx.send(trade(101.0, first.timestamp - chrono::Duration::milliseconds(500)).into()).await.unwrap();
+    let corrected = match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+        MarketEvent::Candle(candle) => candle,
+        other => panic!("unexpected {}", other.kind()),
+    };
+    assert_eq!(corrected.timestamp, first.timestamp);
+    assert_eq!((corrected.revision, corrected.ask_count + corrected.bid_count), (1, 2));
+}
+
+#[tokio::test]
+async fn emit_empty_carries_last_price() {
+    let (event_tx, event_rx) = mpsc::channel(16);
+    let (output_tx, mut output_rx) = mpsc::channel(16);
+    tokio::spawn(TradeCandleBuilder::new(event_rx, output_tx, vec![1]).with_emit_empty().start());
+    let trade = Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), "1".to_string(), 100.0, 1.0, Side::Buy, Utc::now());
+    event_tx.send(trade.into()).await.unwrap();
+
+    let mut candles = Vec::new();
+    while candles.len() < 3 {
+        match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+            MarketEvent::Candle(candle) => candles.push(candle),
+            other => panic!("unexpected {}", other.kind()),
+        }
+    }
+    assert_eq!((candles[0].ask_count, candles[0].ask_price), (1, Some(100.0)));
+    // 約定のない秒も価格を引き継いだ出来高 0 の足が連続して出る
+    for (prev, candle) in candles.iter().zip(&candles[1..]) {
+        assert_eq!(candle.timestamp - prev.timestamp, chrono::Duration::seconds(1));
+        assert_eq!((candle.ask_count, candle.bid_count, candle.ask_volume), (0, 0, 0.0));
+        assert_eq!((candle.ask_price, candle.bid_price), (Some(100.0), None));
+    }
+}
+
+#[tokio::test]
+async fn candles_sum_quote_notional_per_side() {
+    let (event_tx, mut output_rx) = start_builder();
+    let now = Utc::now();
+    for (market_type, symbol, price, quantity, side) in [
+        (MarketType::Linear, "BTCUSDT", 100.0, 2.0, Side::Buy),
+        (MarketType::Linear, "BTCUSDT", 110.0, 1.0, Side::Buy),
+        (MarketType::Linear, "BTCUSDT", 90.0, 1.0, Side::Sell),
+        (MarketType::Inverse, "BTCUSD", 60000.0, 500.0, Side::Sell),  // Inverse の数量は USD 建て
+    ] {
+        let trade = Trade::new("bybit".to_string(), market_type, symbol.to_string(), price.to_string(), price, quantity, side, now);
+        event_tx.send(trade.into()).await.unwrap();
+    }
+
+    let mut notional = std::collections::HashMap::new();
+    while notional.len() < 2 {
+        match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+            MarketEvent::Candle(candle) => {
+                let doc = candle.to_timeseries_document();
+                notional.insert(candle.symbol.clone(), (doc.get_f64("ask_notional").unwrap(), doc.get_f64("bid_notional").unwrap()));
+            }
+            other => panic!("unexpected {}", other.kind()),
+        }
+    }
+    assert_eq!(notional["BTCUSDT"], (310.0, 90.0));
+    assert_eq!(notional["BTCUSD"], (0.0, 500.0));
+}
+
+#[test]
+fn trade_size_stats_round_trip_through_document() {
+    assert_eq!(TradeSizeStats::from_sizes(&[]), None);
+    let stats = TradeSizeStats::from_sizes(&[500.0, 2_000_000.0, 1_000.0, 50_000.0]).unwrap();
+    assert_eq!((stats.max, stats.median, stats.histogram), (2_000_000.0, 25_500.0, [1, 1, 1, 0, 1]));
+    assert_eq!(TradeSizeStats::from_sizes(&[3.0, 1.0, 2.0]).unwrap().median, 2.0);
+
+    let mut candle = TradeCandle::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), Utc::now(), 60);
+    candle.trade_size = Some(stats.clone());
+    let doc = candle.to_timeseries_document();
+    assert_eq!(doc.get_document("size_hist").unwrap().get_i32("ge_1m").unwrap(), 1);
+    let restored = TradeCandle::from_timeseries_document(&doc, "bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), 60).unwrap();
+    assert_eq!(restored.trade_size, Some(stats));
+    // 約定のない足には書き込まない
+    let empty = TradeCandle::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), Utc::now(), 60);
+    assert!(!empty.to_timeseries_document().contains_key("trade_size"));
+}
+
+#[tokio::test]
+async fn candles_record_price_path() {
+    let (event_tx, mut output_rx) = start_builder();
+    let now = Utc::now();
+    for (i, price) in [100.0, 101.0, 101.0, 100.0, 102.0].into_iter().enumerate() {
+        let trade = Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), i.to_string(), price, 1.0, Side::Buy, now);
+        event_tx.send(trade.into()).await.unwrap();
+    }
+
+    let candle = match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+        MarketEvent::Candle(candle) => candle,
+        other => panic!("unexpected {}", other.kind()),
+    };
+    assert_eq!((candle.high, candle.low, candle.range()), (Some(102.0), Some(100.0), Some(2.0)));
+    // 上昇 -> 下落 -> 上昇 (変化なしの約定は数えない)
+    assert_eq!(candle.direction_changes, 2);
+    let expected = ((101.0f64 / 100.0).ln().powi(2) + (100.0f64 / 101.0).ln().powi(2) + (102.0f64 / 100.0).ln().powi(2)).sqrt();
+    assert!((candle.realized_vol.unwrap() - expected).abs() < 1e-12);
+    let doc = candle.to_timeseries_document();
+    assert_eq!((doc.get_f64("range").unwrap(), doc.get_i32("direction_changes").unwrap()), (2.0, 2));
+}
+
+#[tokio::test]
+async fn larger_timeframes_roll_up_from_smallest() {
+    let (event_tx, event_rx) = mpsc::channel(64);
+    let (output_tx, mut output_rx) = mpsc::channel(64);
+    tokio::spawn(TradeCandleBuilder::new(event_rx, output_tx, vec![1, 2]).start());
+
+    let mut sent = Vec::new();
+    for (i, price) in [100.0, 101.0, 99.5, 102.0, 101.0, 103.0, 100.5, 104.0, 102.5, 101.5].into_iter().enumerate() {
+        let timestamp = Utc::now();
+        let side = if i % 3 == 0 { Side::Sell } else { Side::Buy };
+        let trade = Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), i.to_string(), price, 1.0 + i as f64, side, timestamp);
+        event_tx.send(trade.into()).await.unwrap();
+        sent.push((timestamp, price));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+    }
+
+    let mut candles = Vec::new();
+    while let Ok(Some(event)) = tokio::time::timeout(Duration::from_millis(2500), output_rx.recv()).await {
+        if let MarketEvent::Candle(candle) = event {
+            candles.push(candle);
+        }
+    }
+    let (small, large): (Vec<_>, Vec<_>) = candles.into_iter().partition(|c| c.period_seconds == 1);
+    assert!(!large.is_empty());
+    for candle in &large {
+        let start = candle.timestamp - chrono::Duration::seconds(2);
+        let parts: Vec<_> = small.iter().filter(|c| c.timestamp > start && c.timestamp <= candle.timestamp).collect();
+        // 1s の足の合計と 2s の足が一致する
+        assert_eq!(candle.ask_count + candle.bid_count, parts.iter().map(|c| c.ask_count + c.bid_count).sum::<i32>());
+        assert!((candle.ask_notional + candle.bid_notional - parts.iter().map(|c| c.ask_notional + c.bid_notional).sum::<f64>()).abs() < 1e-9);
+        assert_eq!(candle.high, parts.iter().filter_map(|c| c.high).reduce(f64::max));
+        assert_eq!(candle.low, parts.iter().filter_map(|c| c.low).reduce(f64::min));
+        // 境界をまたぐリターンも含めて, 約定を順に集計した場合と同じ値になる
+        let prices: Vec<f64> = sent.iter().filter(|(t, _)| *t >= start && *t < candle.timestamp).map(|(_, p)| *p).collect();
+        let expected = prices.windows(2).map(|w| (w[1] / w[0]).ln().powi(2)).sum::<f64>().sqrt();
+        assert!((candle.realized_vol.unwrap() - expected).abs() < 1e-12);
+        let trade_size = candle.trade_size.as_ref().unwrap();
+        assert_eq!(trade_size.histogram.iter().sum::<i32>(), candle.ask_count + candle.bid_count);
+    }
+}
+
+#[tokio::test]
+async fn buffer_limits_evict_least_recently_traded_symbol() {
+    let (event_tx, event_rx) = mpsc::channel(64);
+    let (output_tx, _output_rx) = mpsc::channel(64);
+    let mut builder = TradeCandleBuilder::new(event_rx, output_tx, vec![60]).with_buffer_limits(2, 4);
+    let metrics = builder.buffer_metrics();
+    let control = builder.snapshot_control();
+    let handle = tokio::spawn(builder.start());
+    // 出力されない先の時刻に固定する
+    let timestamp = Utc::now() + chrono::Duration::hours(1);
+    let trade = |symbol: &str, i: usize| Trade::new("bybit".to_string(), MarketType::Linear, symbol.to_string(), i.to_string(), 100.0, 1.0 + i as f64, Side::Buy, timestamp);
+
+    for (i, symbol) in ["BTCUSDT", "ETHUSDT", "BTCUSDT", "SOLUSDT"].into_iter().enumerate() {
+        event_tx.send(trade(symbol, i).into()).await.unwrap();
+    }
+    for i in 0..10 {
+        event_tx.send(trade("SOLUSDT", i).into()).await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let snapshot = control.snapshot(true).await.unwrap();
+    handle.await.unwrap();
+
+    // ETHUSDT が最も長く約定がない
+    let mut symbols: Vec<_> = snapshot.buffers.iter().map(|b| b.symbol.as_str()).collect();
+    symbols.sort();
+    assert_eq!(symbols, vec!["BTCUSDT", "SOLUSDT"]);
+    assert_eq!((metrics.active(), metrics.peak(), metrics.evicted()), (2, 2, 1));
+    // 標本は間引いても, ヒストグラムは全約定を数える
+    let sol = snapshot.buffers.iter().find(|b| b.symbol == "SOLUSDT").unwrap();
+    assert!(sol.trade_sizes.sample.len() <= 4);
+    assert_eq!(sol.trade_sizes.histogram.iter().sum::<i32>(), 11);
+    assert!(metrics.thinned() > 0);
+}
+
+#[tokio::test]
+async fn audit_recomputes_emitted_candles() {
+    let (event_tx, event_rx) = mpsc::channel(64);
+    let (output_tx, mut output_rx) = mpsc::channel(64);
+    let mut builder = TradeCandleBuilder::new(event_rx, output_tx, vec![1, 2]).with_grace(Duration::from_millis(300)).with_audit(10);
+    let control = builder.audit_control();
+    tokio::spawn(builder.start());
+    let trade = |i: usize, price: f64, timestamp| {
+        let side = if i.is_multiple_of(2) { Side::Sell } else { Side::Buy };
+        Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), i.to_string(), price, 1.0 + i as f64, side, timestamp)
+    };
+
+    for (i, price) in [100.0, 101.0, 99.5, 102.0, 101.0, 103.0].into_iter().enumerate() {
+        event_tx.send(trade(i, price, Utc::now()).into()).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+    }
+    let first = match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+        MarketEvent::Candle(candle) => candle,
+        other => panic!("unexpected {}", other.kind()),
+    };
+    // 訂正された足も, 遅れて届いた約定を含めて計算し直される
+    event_tx.send(trade(6, 98.0, first.timestamp - chrono::Duration::milliseconds(500)).into()).await.unwrap();
+    while let Ok(Some(_)) = tokio::time::timeout(Duration::from_millis(2500), output_rx.recv()).await {}
+
+    let report = control.audit().await.unwrap();
+    assert!(report.checked > 0);
+    assert!(report.mismatches.is_empty(), "{:?}", report.mismatches);
+}
+
+#[test]
+fn audit_compare_reports_differing_fields() {
+    let mut emitted = TradeCandle::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), Utc::now(), 60);
+    emitted.ask_count = 2;
+    emitted.ask_volume = 3.0;
+    emitted.ask_price = Some(100.0);
+    let mut recomputed = emitted.clone();
+    // 浮動小数点の誤差は許容する
+    recomputed.ask_volume = 3.0 + 1e-13;
+    assert!(audit::compare(&emitted, &recomputed).is_empty());
+
+    recomputed.ask_price = Some(100.5);
+    let mismatches = audit::compare(&emitted, &recomputed);
+    assert_eq!(mismatches.len(), 1);
+    assert_eq!(mismatches[0].field, "ask_price");
+}
+
+#[tokio::test]
+async fn session_offset_aligns_daily_candles() {
+    let (event_tx, event_rx) = mpsc::channel(16);
+    let (output_tx, _output_rx) = mpsc::channel(16);
+    let mut builder = TradeCandleBuilder::new(event_rx, output_tx, vec![86400]).with_session_offset(9 * 3600);
+    let control = builder.snapshot_control();
+    let handle = tokio::spawn(builder.start());
+    let timestamp = Utc::now() + chrono::Duration::days(2);
+    event_tx.send(Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), "1".to_string(), 100.0, 1.0, Side::Buy, timestamp).into()).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let snapshot = control.snapshot(true).await.unwrap();
+    handle.await.unwrap();
+
+    // 日足は日本時間の 0 時 (15:00 UTC) に終わる
+    let daily = &snapshot.buffers[0];
+    assert_eq!(daily.candle_end.timestamp() % 86400, 15 * 3600);
+    assert!(daily.candle_end > timestamp && daily.candle_end - timestamp <= chrono::Duration::days(1));
+}
+
+#[tokio::test]
+async fn candles_accumulate_cvd_including_corrections() {
+    let (event_tx, event_rx) = mpsc::channel(16);
+    let (output_tx, mut output_rx) = mpsc::channel(16);
+    let builder = TradeCandleBuilder::new(event_rx, output_tx, vec![1]).with_grace(Duration::from_millis(300));
+    tokio::spawn(builder.start());
+    let trade = |quantity: f64, side, timestamp| Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), quantity.to_string(), 100.0, quantity, side, timestamp);
+    let mut next_candle = async || match tokio::time::timeout(Duration::from_secs(3), output_rx.recv()).await.unwrap().unwrap() {
+        MarketEvent::Candle(candle) => candle,
+        other => panic!("unexpected {}", other.kind()),
+    };
+
+    event_tx.send(trade(3.0, Side::Buy, Utc::now()).into()).await.unwrap();
+    event_tx.send(trade(1.0, Side::Sell, Utc::now()).into()).await.unwrap();
+    let first = next_candle().await;
+    assert_eq!((first.delta(), first.cvd, first.taker_buy_ratio()), (2.0, 2.0, Some(0.75)));
+
+    // 遅れて届いた売りで 1 本目を訂正すると, CVD の差分が次の足にも引き継がれる
+    event_tx.send(trade(1.5, Side::Sell, first.timestamp - chrono::Duration::milliseconds(500)).into()).await.unwrap();
+    event_tx.send(trade(1.0, Side::Sell, Utc::now()).into()).await.unwrap();
+    let corrected = next_candle().await;
+    assert_eq!((corrected.revision, corrected.delta(), corrected.cvd), (1, 0.5, 0.5));
+    let second = next_candle().await;
+    assert_eq!((second.delta(), second.cvd), (-1.0, -0.5));
+
+    let doc = second.to_timeseries_document();
+    assert_eq!((doc.get_f64("delta").unwrap(), doc.get_f64("cvd").unwrap(), doc.get_f64("taker_buy_ratio").unwrap()), (-1.0, -0.5, 0.0));
+    let restored = TradeCandle::from_timeseries_document(&doc, "bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), 1).unwrap();
+    assert_eq!(restored.cvd, -0.5);
+}
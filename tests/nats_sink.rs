@@ -0,0 +1,32 @@
+mod common;
+
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade};
+use kkcrypto::utils::nats_sink::NatsRecord;
+
+#[test]
+fn trade_subject_and_message_id() {
+    let trade = Trade {
+        exchange: "hyperliquid".to_string(),
+        price: 0.01,
+        quantity: 100.0,
+        ..common::trade("kPEPE.X", "7", common::at(60))
+    };
+    let record = NatsRecord::from_event(&MarketEvent::Trade(trade)).unwrap();
+    // subject の区切りになる . は _ にする
+    assert_eq!(record.subject, "trades.hyperliquid.kPEPE_X");
+    assert_eq!(record.message_id, "hyperliquid:linear:kPEPE.X:7");
+    let data: serde_json::Value = serde_json::from_str(&record.payload).unwrap();
+    assert_eq!(data["symbol"], "kPEPE.X");
+}
+
+#[test]
+fn candle_revisions_have_distinct_message_ids() {
+    let mut candle = common::candle("BTCUSDT", common::at(60), 3600);
+    let first = NatsRecord::from_event(&MarketEvent::Candle(candle.clone())).unwrap();
+    assert_eq!(first.subject, "candles.1h.BTCUSDT");
+
+    // 再送は同じ id (重複排除される), 修正した足は別の id
+    assert_eq!(NatsRecord::from_event(&MarketEvent::Candle(candle.clone())).unwrap().message_id, first.message_id);
+    candle.revision += 1;
+    assert_ne!(NatsRecord::from_event(&MarketEvent::Candle(candle)).unwrap().message_id, first.message_id);
+}
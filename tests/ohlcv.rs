@@ -0,0 +1,55 @@
+mod common;
+
+use common::at;
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::ohlcv::{write_csv, OhlcvBar, OhlcvFormat};
+
+// 終端 end_seconds の 1 秒足
+fn candle(end_seconds: i64, ask: Option<(f64, f64)>, bid: Option<(f64, f64)>) -> TradeCandle {
+    let mut candle = common::candle("BTCUSDT", at(end_seconds), 1);
+    if let Some((price, volume)) = ask {
+        candle.ask_price = Some(price);
+        candle.ask_volume = volume;
+        candle.ask_count = 1;
+    }
+    if let Some((price, volume)) = bid {
+        candle.bid_price = Some(price);
+        candle.bid_volume = volume;
+        candle.bid_count = 1;
+    }
+    candle
+}
+
+#[test]
+fn bars_from_vwap_candles() {
+    let mut corrected = candle(3, Some((104.0, 1.0)), None);
+    corrected.revision = 1;
+    let candles = vec![
+        candle(1, Some((101.0, 1.0)), Some((99.0, 1.0))),
+        candle(2, None, None),  // 約定なしは無視する
+        candle(3, Some((103.0, 1.0)), None),
+        corrected,  // 訂正された足を使う
+        candle(6, None, Some((98.0, 2.0))),  // 次の 5 秒足
+    ];
+    let bars = OhlcvBar::from_candles(&candles, 5);
+    assert_eq!(bars.len(), 2);
+    assert_eq!(bars[0], OhlcvBar { timestamp: at(0), open: 100.0, high: 104.0, low: 99.0, close: 104.0, volume: 3.0 });
+    assert_eq!(bars[1], OhlcvBar { timestamp: at(5), open: 98.0, high: 98.0, low: 98.0, close: 98.0, volume: 2.0 });
+}
+
+#[test]
+fn csv_formats() {
+    let bars = vec![OhlcvBar { timestamp: at(0), open: 100.0, high: 104.0, low: 99.5, close: 104.0, volume: 3.0 }];
+    let render = |format| {
+        let mut out = Vec::new();
+        write_csv(&mut out, &bars, format).unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(render(OhlcvFormat::Ccxt), "timestamp,open,high,low,close,volume\n1717200000000,100,104,99.5,104,3\n");
+    assert_eq!(render(OhlcvFormat::TradingView), "time,open,high,low,close,Volume\n1717200000,100,104,99.5,104,3\n");
+    assert_eq!(
+        render(OhlcvFormat::parse("backtrader").unwrap()),
+        "datetime,open,high,low,close,volume,openinterest\n2024-06-01 00:00:00,100,104,99.5,104,3,0\n"
+    );
+    assert!(OhlcvFormat::parse("excel").is_err());
+}
@@ -0,0 +1,99 @@
+use chrono::DateTime;
+use kkcrypto::utils::pair_signal_store::to_document;
+use kkcrypto::utils::pair_spread::{dickey_fuller, ols, parse_pairs, spread_stats, SpreadSignal, ZScoreTrigger};
+
+/// 決まった順の擬似乱数 (-0.5..0.5)
+fn noise(seed: &mut u64) -> f64 {
+    *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+    (*seed >> 11) as f64 / (1u64 << 53) as f64 - 0.5
+}
+
+#[test]
+fn parses_pairs() {
+    let pairs = parse_pairs("ETHUSDT/BTCUSDT, SOLUSDT/ETHUSDT").unwrap();
+    assert_eq!(pairs, vec![("ETHUSDT".to_string(), "BTCUSDT".to_string()), ("SOLUSDT".to_string(), "ETHUSDT".to_string())]);
+    assert!(parse_pairs("ETHUSDT").is_err());
+    assert!(parse_pairs("ETHUSDT/ETHUSDT").is_err());
+    assert!(parse_pairs(" , ").is_err());
+}
+
+#[test]
+fn ols_fits_a_line() {
+    let x = [1.0, 2.0, 3.0, 4.0];
+    let y = [3.0, 5.0, 7.0, 9.0];
+    let (intercept, slope) = ols(&x, &y).unwrap();
+    assert!((intercept - 1.0).abs() < 1e-12);
+    assert!((slope - 2.0).abs() < 1e-12);
+    assert!(ols(&[1.0, 1.0], &[2.0, 3.0]).is_none());
+}
+
+#[test]
+fn cointegrated_pair() {
+    // ln y = 0.2 + 1.5 ln x + 平均に戻る残差 (AR(1), 係数 0.5)
+    let mut seed = 7;
+    let (mut log_x, mut residual) = (10.0, 0.0);
+    let (mut x, mut y) = (Vec::new(), Vec::new());
+    for _ in 0..500 {
+        log_x += noise(&mut seed) * 0.01;
+        residual = 0.5 * residual + noise(&mut seed) * 0.002;
+        x.push(f64::exp(log_x));
+        y.push(f64::exp(0.2 + 1.5 * log_x + residual));
+    }
+    let stats = spread_stats(&x, &y).unwrap();
+    assert_eq!(stats.count, 500);
+    assert!((stats.hedge_ratio - 1.5).abs() < 0.05);
+    assert!(stats.adf.unwrap() < -3.90);
+    assert_eq!(stats.cointegration_level(), Some(0.01));
+    assert!(stats.zscore.unwrap().abs() < 5.0);
+    // 価格が 0 以下なら計算しない
+    assert!(spread_stats(&[1.0, 0.0, 2.0], &[1.0, 2.0, 3.0]).is_none());
+}
+
+#[test]
+fn random_walk_residuals_do_not_revert() {
+    let mut seed = 11;
+    let mut walk = vec![0.0];
+    for _ in 0..500 {
+        walk.push(walk.last().unwrap() + 0.1 + noise(&mut seed) * 0.01);
+    }
+    // 一方向に離れていく残差は平均に戻らない
+    assert!(dickey_fuller(&walk).unwrap() > -3.04);
+    assert!(dickey_fuller(&[1.0, 2.0]).is_none());
+}
+
+#[test]
+fn trigger_reports_crossings_only() {
+    let mut trigger = ZScoreTrigger::new(2.0, 0.5).unwrap();
+    let signals: Vec<Option<SpreadSignal>> = [0.3, 1.9, 2.1, 2.5, 1.0, 0.4, 0.2, -2.2, 2.3, -0.1]
+        .iter()
+        .map(|z| trigger.update(*z))
+        .collect();
+    assert_eq!(signals, vec![
+        None,
+        None,
+        Some(SpreadSignal::EnterShort),
+        None,
+        None,
+        Some(SpreadSignal::Exit),
+        None,
+        Some(SpreadSignal::EnterLong),
+        Some(SpreadSignal::EnterShort),
+        Some(SpreadSignal::Exit),
+    ]);
+    assert!(ZScoreTrigger::new(1.0, 1.0).is_err());
+}
+
+#[test]
+fn signal_document() {
+    let timestamp = DateTime::from_timestamp(1_717_200_060, 0).unwrap();
+    let stats = spread_stats(&[100.0, 101.0, 103.0, 102.0, 104.0], &[10.0, 10.2, 10.3, 10.1, 10.6]).unwrap();
+    let doc = to_document(timestamp, 6, 1, 240, 60, SpreadSignal::EnterShort, &stats);
+    let metadata = doc.get_document("metadata").unwrap();
+    assert_eq!(metadata.get_i32("ym").unwrap(), 202406);
+    assert_eq!((metadata.get_i32("symbol_a").unwrap(), metadata.get_i32("symbol_b").unwrap()), (6, 1));
+    assert_eq!(metadata.get_i32("window").unwrap(), 240);
+    assert_eq!(metadata.get_i64("interval").unwrap(), 60);
+    assert_eq!(doc.get_str("signal").unwrap(), "enter_short");
+    assert_eq!(doc.get_f64("hedge_ratio").unwrap(), stats.hedge_ratio);
+    assert_eq!(doc.get_i64("count").unwrap(), 5);
+}
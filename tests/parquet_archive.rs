@@ -0,0 +1,45 @@
+mod common;
+
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade};
+use kkcrypto::utils::parquet_archive::{parse_compression, trades_dataframe, ParquetArchive};
+use polars::prelude::*;
+
+fn trade(symbol: &str, seconds: i64) -> Trade {
+    common::trade(symbol, &seconds.to_string(), common::at(seconds))
+}
+
+#[test]
+fn builds_trades_dataframe() {
+    let df = trades_dataframe(&[trade("BTCUSDT", 1), trade("BTCUSDT", 2)]).unwrap();
+    assert_eq!(df.height(), 2);
+    assert_eq!(df.column("side").unwrap().str().unwrap().get(0), Some("buy"));
+    assert!(matches!(df.column("timestamp").unwrap().dtype(), DataType::Datetime(TimeUnit::Milliseconds, _)));
+}
+
+#[test]
+fn rotate_writes_date_partitioned_files() {
+    let dir = std::env::temp_dir().join(format!("kkcrypto_archive_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut archive = ParquetArchive::new(dir.clone(), parse_compression("zstd").unwrap());
+    archive.push(MarketEvent::Trade(trade("BTCUSDT", 1)));
+    archive.push(MarketEvent::Trade(trade("BTCUSDT", 2)));
+    // 翌日の足は別のディレクトリ
+    archive.push(MarketEvent::Candle(common::candle("BTCUSDT", common::at(86400), 60)));
+    assert_eq!(archive.pending_rows(), 3);
+
+    let mut written = archive.rotate(common::at(3600));
+    written.sort();
+    assert_eq!(written, vec![
+        dir.join("exchange=bybit/symbol=BTCUSDT/date=2024-06-01/trades-linear-010000.parquet"),
+        dir.join("exchange=bybit/symbol=BTCUSDT/date=2024-06-02/candles-linear-010000.parquet"),
+    ]);
+    assert_eq!(archive.pending_rows(), 0);
+    let df = ParquetReader::new(std::fs::File::open(&written[0]).unwrap()).finish().unwrap();
+    assert_eq!(df.height(), 2);
+
+    // 同じ秒にもう一度書いても上書きしない
+    archive.push(MarketEvent::Trade(trade("BTCUSDT", 3)));
+    let again = archive.rotate(common::at(3600));
+    assert_eq!(again, vec![dir.join("exchange=bybit/symbol=BTCUSDT/date=2024-06-01/trades-linear-010000-1.parquet")]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
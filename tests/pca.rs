@@ -0,0 +1,57 @@
+use chrono::DateTime;
+use kkcrypto::utils::correlation_matrix::CorrelationMatrix;
+use kkcrypto::utils::pca::{principal_components, symmetric_eigen};
+use kkcrypto::utils::rolling_correlation::PairCorrelation;
+
+fn pair(symbol_a: i32, symbol_b: i32, correlation: Option<f64>) -> PairCorrelation {
+    PairCorrelation { symbol_a, symbol_b, count: 360, correlation }
+}
+
+#[test]
+fn eigen_decomposes_symmetric_matrix() {
+    let matrix = vec![vec![1.0, 0.8, 0.6], vec![0.8, 1.0, 0.7], vec![0.6, 0.7, 1.0]];
+    let eigen = symmetric_eigen(&matrix);
+    // 固有値は大きい順で合計は対角の和
+    assert!(eigen.windows(2).all(|w| w[0].0 >= w[1].0));
+    assert!((eigen.iter().map(|(value, _)| value).sum::<f64>() - 3.0).abs() < 1e-9);
+    for (value, vector) in &eigen {
+        assert!((vector.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-9);
+        for (row, x) in matrix.iter().zip(vector) {
+            let product: f64 = row.iter().zip(vector).map(|(a, b)| a * b).sum();
+            assert!((product - value * x).abs() < 1e-9);
+        }
+    }
+}
+
+#[test]
+fn one_factor_market() {
+    // 全てのペアの相関が rho なら第 1 主成分は 1 + (n - 1) rho で loadings は全て 1 / sqrt(n)
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let pairs: Vec<PairCorrelation> = [(1, 6), (1, 9), (1, 12), (6, 9), (6, 12), (9, 12)].iter().map(|&(a, b)| pair(a, b, Some(0.7))).collect();
+    let pca = principal_components(&CorrelationMatrix::from_pairs(timestamp, 30, &pairs), 2).unwrap();
+    assert_eq!(pca.symbols, vec![1, 6, 9, 12]);
+    assert_eq!(pca.components.len(), 2);
+    assert!((pca.components[0].eigenvalue - 3.1).abs() < 1e-9);
+    assert!((pca.components[0].explained - 0.775).abs() < 1e-9);
+    assert!(pca.components[0].loadings.iter().all(|(_, loading)| (loading - 0.5).abs() < 1e-9));
+    assert!((pca.components[1].explained - 0.075).abs() < 1e-9);
+    assert!((pca.cumulative_explained() - 0.85).abs() < 1e-9);
+}
+
+#[test]
+fn drops_symbols_with_missing_pairs() {
+    let timestamp = DateTime::from_timestamp(1_717_200_005, 0).unwrap();
+    let pairs = [
+        pair(1, 6, Some(0.9)),
+        pair(1, 9, None),
+        pair(6, 9, None),
+        pair(1, 12, Some(0.5)),
+        pair(6, 12, Some(0.4)),
+        pair(9, 12, Some(0.3)),
+    ];
+    let pca = principal_components(&CorrelationMatrix::from_pairs(timestamp, 30, &pairs), 3).unwrap();
+    assert_eq!((&pca.symbols, &pca.dropped), (&vec![1, 6, 12], &vec![9]));
+    assert_eq!(pca.components.len(), 3);
+    assert!((pca.cumulative_explained() - 1.0).abs() < 1e-9);
+    assert!(principal_components(&CorrelationMatrix::from_pairs(timestamp, 30, &[pair(1, 6, None)]), 1).is_none());
+}
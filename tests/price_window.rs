@@ -0,0 +1,54 @@
+use chrono::DateTime;
+use kkcrypto::utils::price_window::{price_point, PricePoint, PriceWindow};
+use kkcrypto::utils::resample::{resample_long, FillPolicy, TimeGrid};
+use mongodb::bson::doc;
+
+fn at(seconds: i64) -> i64 {
+    (1_717_200_000 + seconds) * 1000
+}
+
+fn point(seconds: i64, symbol_id: i32, price: f64) -> PricePoint {
+    PricePoint { timestamp_ms: at(seconds), symbol_id, price }
+}
+
+#[test]
+fn price_point_uses_mid_price() {
+    let candle = |ask: Option<f64>, bid: Option<f64>| doc! {
+        "unixtime": mongodb::bson::DateTime::from_millis(at(1)),
+        "metadata": { "ym": 202406, "symbol": 6 },
+        "ask_price": ask,
+        "bid_price": bid,
+    };
+    assert_eq!(price_point(&candle(Some(101.0), Some(99.0))), Some(point(1, 6, 100.0)));
+    assert_eq!(price_point(&candle(Some(101.0), None)), Some(point(1, 6, 101.0)));
+    assert_eq!(price_point(&candle(None, Some(99.0))), Some(point(1, 6, 99.0)));
+    // 約定のない足 (価格が null) は使わない
+    assert_eq!(price_point(&candle(None, None)), None);
+}
+
+#[test]
+fn append_and_trim() {
+    let mut window = PriceWindow::new();
+    assert!(window.is_empty());
+    window.replace(&[point(2, 1, 10.0), point(1, 1, 9.0), point(1, 2, 20.0)]).unwrap();
+    window.append(&[point(3, 1, 11.0), point(3, 2, 21.0)]).unwrap();
+    assert_eq!(window.len(), 5);
+
+    window.trim(at(2)).unwrap();
+    assert_eq!(window.len(), 3);
+    let timestamps: Vec<i64> = window.frame().column("timestamp").unwrap().i64().unwrap().into_no_null_iter().collect();
+    assert_eq!(timestamps, vec![at(2), at(3), at(3)]);
+}
+
+#[test]
+fn rewritten_candle_replaces_earlier_price() {
+    let mut window = PriceWindow::new();
+    window.replace(&[point(1, 1, 10.0), point(2, 1, 11.0)]).unwrap();
+    // 足の upsert (同じ symbol・時刻) が後から届く
+    window.append(&[point(2, 1, 12.0)]).unwrap();
+
+    let grid = TimeGrid::aligned(DateTime::from_timestamp(1_717_200_000, 0).unwrap(), DateTime::from_timestamp(1_717_200_002, 0).unwrap(), 1);
+    let df = resample_long(window.frame(), &grid, FillPolicy::None).unwrap();
+    let prices: Vec<Option<f64>> = df.column("symbol_1").unwrap().f64().unwrap().into_iter().collect();
+    assert_eq!(prices, vec![Some(10.0), Some(12.0)]);
+}
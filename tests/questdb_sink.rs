@@ -0,0 +1,28 @@
+mod common;
+
+use kkcrypto::models::trade::Trade;
+use kkcrypto::utils::questdb_sink::{candle_line, trade_line};
+
+#[test]
+fn trade_line_escapes_string_fields() {
+    let mut trade = Trade { price: 67000.5, quantity: 0.25, ..common::trade("BTCUSDT", "a\"1", common::at(60)) };
+    trade.received_at = common::at(60) + chrono::Duration::milliseconds(15);
+    assert_eq!(
+        trade_line(&trade),
+        "trades,exchange=bybit,market_type=linear,symbol=BTCUSDT,side=buy trade_id=\"a\\\"1\",price=67000.5,quantity=0.25,received_at=1717200060015000t 1717200060000000000\n"
+    );
+}
+
+#[test]
+fn candle_line_leaves_empty_prices_null() {
+    let mut candle = common::candle("BTC USDT", common::at(60), 300);
+    let line = candle_line(&candle);
+    // 空白はタグでエスケープする
+    assert!(line.starts_with("candles,exchange=bybit,market_type=linear,symbol=BTC\\ USDT,timeframe=5m period_seconds=300i,ask_volume=0.0,"));
+    assert!(!line.contains("open="));
+    assert!(line.ends_with(",warmup=false,revision=0i 1717200060000000000\n"));
+
+    (candle.open, candle.close) = (Some(100.0), Some(101.0));
+    let line = candle_line(&candle);
+    assert!(line.contains(" period_seconds=300i,open=100.0,close=101.0,ask_volume="));
+}
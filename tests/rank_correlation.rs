@@ -0,0 +1,82 @@
+use chrono::Duration;
+use kkcrypto::utils::price_window::PricePoint;
+use kkcrypto::utils::rank_correlation::{kendall, pearson, ranks, spearman, CorrelationMethod};
+use kkcrypto::utils::resample::FillPolicy;
+use kkcrypto::utils::rolling_correlation::RollingCorrelation;
+
+fn approx(left: Option<f64>, right: f64) -> bool {
+    left.is_some_and(|left| (left - right).abs() < 1e-9)
+}
+
+/// 定義どおりの O(n^2) の tau-b
+fn kendall_naive(x: &[f64], y: &[f64]) -> f64 {
+    let (mut score, mut untied_x, mut untied_y) = (0.0, 0.0, 0.0);
+    for i in 0..x.len() {
+        for j in i + 1..x.len() {
+            let (dx, dy) = ((x[i] - x[j]).signum(), (y[i] - y[j]).signum());
+            let (dx, dy) = (if x[i] == x[j] { 0.0 } else { dx }, if y[i] == y[j] { 0.0 } else { dy });
+            score += dx * dy;
+            untied_x += dx.abs();
+            untied_y += dy.abs();
+        }
+    }
+    score / (untied_x * untied_y).sqrt()
+}
+
+#[test]
+fn average_ranks_for_ties() {
+    assert_eq!(ranks(&[10.0, 30.0, 20.0, 30.0]), vec![1.0, 3.5, 2.0, 3.5]);
+}
+
+#[test]
+fn monotonic_is_one_for_ranks() {
+    let x: Vec<f64> = (1..=20).map(|i| i as f64).collect();
+    let y: Vec<f64> = x.iter().map(|v| v.powi(5)).collect();
+    assert!(pearson(&x, &y).unwrap() < 0.95);
+    assert!(approx(spearman(&x, &y), 1.0));
+    assert!(approx(kendall(&x, &y), 1.0));
+    let reversed: Vec<f64> = y.iter().rev().copied().collect();
+    assert!(approx(kendall(&x, &reversed), -1.0));
+}
+
+#[test]
+fn kendall_matches_definition_with_ties() {
+    let x = [1.0, 2.0, 2.0, 3.0, 5.0, 4.0, 4.0, 7.0, 6.0, 1.0];
+    let y = [2.0, 1.0, 3.0, 3.0, 6.0, 4.0, 5.0, 5.0, 9.0, 0.5];
+    assert!(approx(kendall(&x, &y), kendall_naive(&x, &y)));
+    // 片方が一定なら計算できない
+    assert_eq!(kendall(&x, &[1.0; 10]), None);
+    assert_eq!(spearman(&[1.0], &[2.0]), None);
+}
+
+#[test]
+fn outlier_moves_pearson_not_ranks() {
+    let x: Vec<f64> = (1..=30).map(|i| i as f64).collect();
+    let mut y = x.clone();
+    y[29] = -1000.0;  // 1 本だけの暴落
+    assert!(pearson(&x, &y).unwrap() < 0.0);
+    assert!(spearman(&x, &y).unwrap() > 0.7);
+    assert!(kendall(&x, &y).unwrap() > 0.7);
+}
+
+#[test]
+fn method_parse() {
+    assert_eq!(CorrelationMethod::parse("Spearman").unwrap(), CorrelationMethod::Spearman);
+    assert_eq!(CorrelationMethod::parse("kendall").unwrap().as_str(), "kendall");
+    assert!(CorrelationMethod::parse("distance").is_err());
+}
+
+#[test]
+fn rolling_window_rank_correlation() {
+    let start = 1_717_200_000_000;
+    let mut rolling = RollingCorrelation::new(1, Duration::seconds(30), FillPolicy::None).unwrap();
+    for i in 1..=50i64 {
+        rolling.insert(&PricePoint { timestamp_ms: start + i * 1000, symbol_id: 1, price: i as f64 });
+        rolling.insert(&PricePoint { timestamp_ms: start + i * 1000, symbol_id: 2, price: (i as f64).exp() });
+        rolling.advance(start + i * 1000);
+    }
+    let pairs = rolling.correlations_by(CorrelationMethod::Kendall);
+    assert_eq!(pairs[0].count, 30);
+    assert!(approx(pairs[0].correlation, 1.0));
+    assert!(approx(rolling.correlations_by(CorrelationMethod::Spearman)[0].correlation, 1.0));
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use kkcrypto::models::{market_event::MarketEvent, market_type::MarketType, trade::{Side, Trade}};
+use kkcrypto::utils::redis_sink::RedisRecord;
+
+#[test]
+fn candles_go_to_per_symbol_streams() {
+    let mut candle = common::candle("BTCUSDT", common::at(60), 60);
+    candle.revision = 2;
+    let Some(RedisRecord::Stream { key, fields }) = RedisRecord::from_event("kkcrypto", &MarketEvent::Candle(candle)) else {
+        panic!("candle should be a stream record");
+    };
+    assert_eq!(key, "kkcrypto:candles:bybit:linear:BTCUSDT");
+    assert_eq!(fields[0], ("period", "60".to_string()));
+    assert_eq!(fields[1], ("revision", "2".to_string()));
+    let data: serde_json::Value = serde_json::from_str(&fields[2].1).unwrap();
+    assert_eq!(data["symbol"], "BTCUSDT");
+}
+
+#[test]
+fn trades_go_to_per_symbol_channels() {
+    let trade = Trade {
+        exchange: "binance".to_string(),
+        market_type: MarketType::Spot,
+        price: 3000.0,
+        quantity: 0.5,
+        side: Side::Sell,
+        ..common::trade("ETHUSDT", "42", common::at(60))
+    };
+    let Some(RedisRecord::Publish { channel, message }) = RedisRecord::from_event("md", &MarketEvent::Trade(trade)) else {
+        panic!("trade should be a publish record");
+    };
+    assert_eq!(channel, "md:trades:binance:spot:ETHUSDT");
+    let data: serde_json::Value = serde_json::from_str(&message).unwrap();
+    assert_eq!(data["trade_id"], "42");
+    assert_eq!(data["price"], 3000.0);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use chrono::Duration;
+use common::BASE_SECONDS;
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade};
+use kkcrypto::utils::replay::{self, ReplayClock};
+use tokio::sync::mpsc;
+
+fn trade(offset_seconds: i64) -> Trade {
+    common::trade("BTCUSDT", &offset_seconds.to_string(), common::at(offset_seconds))
+}
+
+#[test]
+fn delay_follows_speed() {
+    let origin = common::at(0);
+    let at = |seconds: i64| origin + Duration::seconds(seconds);
+    let secs = std::time::Duration::from_secs;
+
+    let clock = ReplayClock::new(origin, 1.0).unwrap();
+    assert_eq!(clock.delay(at(10), secs(0)), secs(10));
+    assert_eq!(clock.delay(at(10), secs(4)), secs(6));
+    // 遅れているときは待たない
+    assert_eq!(clock.delay(at(10), secs(12)), secs(0));
+    assert_eq!(clock.delay(at(-5), secs(0)), secs(0));
+
+    let clock = ReplayClock::new(origin, 60.0).unwrap();
+    assert_eq!(clock.delay(at(3600), secs(0)), secs(60));
+
+    let clock = ReplayClock::new(origin, 0.0).unwrap();
+    assert_eq!(clock.delay(at(3600), secs(0)), secs(0));
+
+    assert!(ReplayClock::new(origin, -1.0).is_err());
+}
+
+#[test]
+fn rebase_offset_keeps_boundaries() {
+    let start = common::at(0);
+    let now = start + Duration::seconds(86_400 + 3_725);
+    assert_eq!(replay::rebase_offset(start, now, 60), Duration::seconds(86_400 + 3_720));
+    assert_eq!(replay::rebase_offset(start, now, 3600), Duration::seconds(86_400 + 3_600));
+    assert_eq!(replay::rebase_offset(start, now, 1), Duration::seconds(86_400 + 3_725));
+}
+
+#[tokio::test]
+async fn replay_shifts_events_in_order() {
+    let origin = common::at(0);
+    let clock = ReplayClock::new(origin, 0.0).unwrap();
+    let candle = common::candle("BTCUSDT", origin + Duration::seconds(60), 60);
+    let events = vec![
+        MarketEvent::Trade(trade(1)),
+        MarketEvent::Trade(trade(30)),
+        MarketEvent::Candle(candle),
+    ];
+    let (tx, mut rx) = mpsc::channel(10);
+    let offset = Duration::days(1);
+    assert_eq!(replay::replay(events, &clock, offset, &tx).await.unwrap(), 3);
+    drop(tx);
+
+    let mut received = Vec::new();
+    while let Some(event) = rx.recv().await {
+        received.push(event);
+    }
+    let times: Vec<i64> = received.iter().map(|event| event.timestamp().timestamp() - BASE_SECONDS).collect();
+    assert_eq!(times, vec![86_401, 86_430, 86_460]);
+}
@@ -0,0 +1,65 @@
+use chrono::DateTime;
+use kkcrypto::utils::resample::{resample_long, resample_series, FillPolicy, TimeGrid};
+use polars::prelude::*;
+
+fn grid() -> TimeGrid {
+    // 10:00:00 より後の 1 秒足 5 本 (…:01 - …:05)
+    TimeGrid::aligned(
+        DateTime::from_timestamp(1_717_200_000, 0).unwrap(),
+        DateTime::from_timestamp(1_717_200_005, 500_000_000).unwrap(),
+        1,
+    )
+}
+
+fn at(seconds: i64) -> i64 {
+    (1_717_200_000 + seconds) * 1000
+}
+
+#[test]
+fn grid_alignment_and_bucketing() {
+    let grid = grid();
+    assert_eq!(grid.len, 5);
+    assert_eq!(grid.timestamps()[0], at(1));
+    assert_eq!(grid.bucket(at(1)), Some(0));
+    assert_eq!(grid.bucket(at(1) - 999), Some(0));
+    assert_eq!(grid.bucket(at(1) + 1), Some(1));
+    assert_eq!(grid.bucket(at(0)), None);
+    assert_eq!(grid.bucket(at(6)), None);
+}
+
+#[test]
+fn fill_policies() {
+    let grid = grid();
+    let points = [(at(1), 1.0), (at(2) - 500, 9.0), (at(2), 2.0), (at(5), 5.0)];
+
+    assert_eq!(resample_series(&points, &grid, FillPolicy::None), vec![Some(1.0), Some(2.0), None, None, Some(5.0)]);
+    assert_eq!(
+        resample_series(&points, &grid, FillPolicy::parse("ffill").unwrap()),
+        vec![Some(1.0), Some(2.0), Some(2.0), Some(2.0), Some(5.0)]
+    );
+    assert_eq!(
+        resample_series(&points, &grid, FillPolicy::parse("ffill:1").unwrap()),
+        vec![Some(1.0), Some(2.0), Some(2.0), None, Some(5.0)]
+    );
+    assert_eq!(
+        resample_series(&points, &grid, FillPolicy::Interpolate),
+        vec![Some(1.0), Some(2.0), Some(3.0), Some(4.0), Some(5.0)]
+    );
+    assert!(FillPolicy::parse("bfill").is_err());
+}
+
+#[test]
+fn long_to_wide_with_drop() {
+    let df = DataFrame::new(vec![
+        Series::new("timestamp".into(), vec![at(1), at(2), at(3), at(2), at(3)]).into(),
+        Series::new("symbol_id".into(), vec![1, 1, 1, 2, 2]).into(),
+        Series::new("price".into(), vec![10.0, 11.0, 12.0, 20.0, 21.0]).into(),
+    ])
+    .unwrap();
+
+    let wide = resample_long(&df, &grid(), FillPolicy::Drop).unwrap();
+    assert_eq!(wide.get_column_names(), vec!["timestamp", "symbol_1", "symbol_2"]);
+    assert_eq!(wide.height(), 2);
+    assert_eq!(wide.column("timestamp").unwrap().i64().unwrap().get(0), Some(at(2)));
+    assert_eq!(wide.column("symbol_2").unwrap().f64().unwrap().get(1), Some(21.0));
+}
@@ -0,0 +1,30 @@
+use kkcrypto::utils::retention::{parse_keep, RetentionConfig, RetentionMode, RetentionPolicy};
+use std::time::Duration;
+
+fn config(text: &str) -> RetentionConfig {
+    toml::from_str(text).unwrap()
+}
+
+#[test]
+fn policies_are_sorted_by_timeframe() {
+    let retention = config(r#"keep = { 1h = "forever", 1s = "7d", 60 = "90d" }"#);
+    assert_eq!(retention.mode().unwrap(), RetentionMode::Ttl);
+    assert_eq!(retention.policies().unwrap(), vec![
+        RetentionPolicy { period_seconds: 1, keep: Some(Duration::from_secs(7 * 86400)) },
+        RetentionPolicy { period_seconds: 60, keep: Some(Duration::from_secs(90 * 86400)) },
+        RetentionPolicy { period_seconds: 3600, keep: None },
+    ]);
+    assert_eq!(parse_keep("12h").unwrap(), Some(Duration::from_secs(12 * 3600)));
+}
+
+#[test]
+fn rejects_invalid_retention() {
+    // 同じ時間枠を 2 通りに書いた
+    assert!(config(r#"keep = { 1m = "1d", 60 = "2d" }"#).validate().is_err());
+    assert!(config(r#"keep = { 1m = "a while" }"#).validate().is_err());
+    assert!(config(r#"mode = "archive"
+keep = { 1m = "1d" }"#).validate().is_err());
+    assert!(config(r#"mode = "prune"
+interval_secs = 0
+keep = { 1m = "1d" }"#).validate().is_err());
+}
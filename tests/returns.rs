@@ -0,0 +1,70 @@
+use chrono::Duration;
+use kkcrypto::utils::price_window::PricePoint;
+use kkcrypto::utils::resample::FillPolicy;
+use kkcrypto::utils::returns::{log_returns, log_returns_frame, ReturnTransform};
+use kkcrypto::utils::rolling_correlation::RollingCorrelation;
+use polars::prelude::*;
+use std::collections::BTreeMap;
+
+fn close(left: Option<f64>, right: f64) -> bool {
+    left.is_some_and(|left| (left - right).abs() < 1e-12)
+}
+
+#[test]
+fn log_returns_with_horizon() {
+    let values = [Some(100.0), Some(110.0), None, Some(121.0), Some(0.0)];
+    let one = log_returns(&values, 1);
+    assert_eq!(one[0], None);
+    assert!(close(one[1], (1.1f64).ln()));
+    // 欠損・0 以下の価格は収益率にしない
+    assert_eq!((one[2], one[3], one[4]), (None, None, None));
+
+    let two = log_returns(&values, 2);
+    assert!(close(two[3], (1.1f64).ln()));
+    assert_eq!(two[2], None);
+}
+
+#[test]
+fn frame_keeps_timestamp() {
+    let df = DataFrame::new(vec![
+        Series::new("timestamp".into(), vec![1000i64, 2000, 3000]).into(),
+        Series::new("symbol_1".into(), vec![Some(1.0), Some(2.0), Some(4.0)]).into(),
+    ])
+    .unwrap();
+    let returns = log_returns_frame(&df, 1).unwrap();
+    assert_eq!(returns.column("timestamp").unwrap().i64().unwrap().get(2), Some(3000));
+    let values: Vec<Option<f64>> = returns.column("symbol_1").unwrap().f64().unwrap().into_iter().collect();
+    assert_eq!(values[0], None);
+    assert!(close(values[1], 2f64.ln()) && close(values[2], 2f64.ln()));
+}
+
+#[test]
+fn transform_rows() {
+    let mut transform = ReturnTransform::new(1);
+    let row = |values: &[(i32, f64)]| values.iter().copied().collect::<BTreeMap<i32, f64>>();
+    assert!(transform.apply(&row(&[(1, 100.0)])).is_empty());
+    let returns = transform.apply(&row(&[(1, 110.0), (2, 50.0)]));
+    assert_eq!(returns.keys().copied().collect::<Vec<_>>(), vec![1]);
+    assert!(close(returns.get(&1).copied(), (1.1f64).ln()));
+    // 値のないバケットを挟むと次の収益率は出ない
+    assert!(!transform.apply(&row(&[(2, 55.0)])).contains_key(&1));
+    assert!(!transform.apply(&row(&[(1, 120.0), (2, 60.0)])).contains_key(&1));
+}
+
+#[test]
+fn trending_prices_have_uncorrelated_returns() {
+    // 両方とも上昇トレンドだが, 1 秒ごとの動きは逆向き
+    let start = 1_717_200_000_000;
+    let price = |i: i64, sign: f64| 100.0 * (0.001 * i as f64 + sign * 0.0005 * (i % 2) as f64).exp();
+    let mut levels = RollingCorrelation::new(1, Duration::seconds(60), FillPolicy::None).unwrap();
+    let mut returns = RollingCorrelation::new(1, Duration::seconds(60), FillPolicy::None).unwrap().with_returns(1);
+    for i in 1..=120 {
+        for rolling in [&mut levels, &mut returns] {
+            rolling.insert(&PricePoint { timestamp_ms: start + i * 1000, symbol_id: 1, price: price(i, 1.0) });
+            rolling.insert(&PricePoint { timestamp_ms: start + i * 1000, symbol_id: 2, price: price(i, -1.0) });
+            rolling.advance(start + i * 1000);
+        }
+    }
+    assert!(levels.correlations()[0].correlation.unwrap() > 0.9);
+    assert!(returns.correlations()[0].correlation.unwrap() < -0.9);
+}
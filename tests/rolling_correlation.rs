@@ -0,0 +1,127 @@
+use chrono::Duration;
+use kkcrypto::utils::price_window::PricePoint;
+use kkcrypto::utils::resample::FillPolicy;
+use kkcrypto::utils::rolling_correlation::{PairStats, RollingCorrelation};
+
+const START: i64 = 1_717_200_000_000;
+
+fn point(second: i64, symbol_id: i32, price: f64) -> PricePoint {
+    PricePoint { timestamp_ms: START + second * 1000, symbol_id, price }
+}
+
+fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let (mx, my) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+    let cov: f64 = x.iter().zip(y).map(|(a, b)| (a - mx) * (b - my)).sum();
+    let vx: f64 = x.iter().map(|a| (a - mx).powi(2)).sum();
+    let vy: f64 = y.iter().map(|b| (b - my).powi(2)).sum();
+    cov / (vx * vy).sqrt()
+}
+
+fn prices(i: i64) -> (f64, f64) {
+    let t = i as f64;
+    (67_000.0 + (t * 0.7).sin() * 50.0 + t, 3_500.0 + (t * 0.3).cos() * 5.0 + (t * 0.7).sin() * 2.0)
+}
+
+#[test]
+fn pair_stats_add_and_remove() {
+    let mut stats = PairStats::default();
+    let x = [1.0, 2.0, 4.0, 8.0, 3.0];
+    let y = [2.0, 1.0, 5.0, 9.0, 2.5];
+    for (a, b) in x.iter().zip(&y) {
+        stats.add(*a, *b);
+    }
+    assert!((stats.correlation().unwrap() - pearson(&x, &y)).abs() < 1e-12);
+    // 先頭の 2 点を外すと残りの相関
+    stats.remove(x[0], y[0]);
+    stats.remove(x[1], y[1]);
+    assert_eq!(stats.count, 3);
+    assert!((stats.correlation().unwrap() - pearson(&x[2..], &y[2..])).abs() < 1e-9);
+    stats.remove(x[2], y[2]);
+    stats.remove(x[3], y[3]);
+    assert_eq!(stats.correlation(), None);
+}
+
+#[test]
+fn sliding_window_matches_full_computation() {
+    // 1 秒足, 60 秒の窓
+    let mut rolling = RollingCorrelation::new(1, Duration::seconds(60), FillPolicy::Ffill { limit: None }).unwrap();
+    for i in 1..=200 {
+        let (a, b) = prices(i);
+        rolling.insert(&point(i, 1, a));
+        rolling.insert(&point(i, 2, b));
+        rolling.advance(START + i * 1000);
+    }
+    assert_eq!(rolling.len(), 60);
+    let (x, y): (Vec<f64>, Vec<f64>) = (141..=200).map(prices).unzip();
+    let pairs = rolling.correlations();
+    assert_eq!(pairs.len(), 1);
+    assert_eq!((pairs[0].symbol_a, pairs[0].symbol_b, pairs[0].count), (1, 2, 60));
+    assert!((pairs[0].correlation.unwrap() - pearson(&x, &y)).abs() < 1e-9);
+}
+
+#[test]
+fn pending_buckets_wait_for_advance() {
+    let mut rolling = RollingCorrelation::new(5, Duration::minutes(1), FillPolicy::None).unwrap();
+    rolling.insert(&point(3, 1, 1.0));
+    rolling.insert(&point(4, 1, 2.0));  // 同じバケットは後の値
+    rolling.insert(&point(4, 2, 5.0));
+    assert_eq!(rolling.advance(START), 0);
+    assert!(rolling.is_empty());
+    assert_eq!(rolling.advance(START + 5000), 1);
+    assert_eq!(rolling.symbols(), vec![1, 2]);
+    // 確定したバケットに遅れて届いた価格は使わない
+    rolling.insert(&point(2, 1, 100.0));
+    assert_eq!(rolling.resume_ms(), Some(START + 5001));
+    assert_eq!(rolling.advance(START + 9999), 0);
+}
+
+#[test]
+fn fill_policies() {
+    // symbol 2 は 1 秒目だけ
+    let run = |policy: FillPolicy| {
+        let mut rolling = RollingCorrelation::new(1, Duration::seconds(60), policy).unwrap();
+        rolling.insert(&point(1, 2, 10.0));
+        for i in 1..=10 {
+            rolling.insert(&point(i, 1, i as f64));
+        }
+        rolling.advance(START + 10_000);
+        rolling.correlations()[0].count
+    };
+    assert_eq!(run(FillPolicy::Ffill { limit: None }), 10);
+    assert_eq!(run(FillPolicy::Ffill { limit: Some(3) }), 4);
+    assert_eq!(run(FillPolicy::None), 1);
+    assert_eq!(run(FillPolicy::Drop), 1);
+    assert!(RollingCorrelation::new(1, Duration::seconds(60), FillPolicy::Interpolate).is_err());
+}
+
+#[test]
+fn coverage_counts_filled_buckets_in_window() {
+    // symbol 2 は 1 秒目だけ, 3 バケットまで埋める
+    let mut rolling = RollingCorrelation::new(1, Duration::seconds(10), FillPolicy::Ffill { limit: Some(3) }).unwrap();
+    rolling.insert(&point(1, 2, 10.0));
+    for i in 1..=10 {
+        rolling.insert(&point(i, 1, i as f64));
+        rolling.advance(START + i * 1000);
+    }
+    let coverage = rolling.coverage();
+    assert_eq!(coverage[&1], 1.0);
+    assert!((coverage[&2] - 0.4).abs() < 1e-12);
+    // 窓から出たバケットは数えない
+    for i in 11..=13 {
+        rolling.insert(&point(i, 1, i as f64));
+        rolling.advance(START + i * 1000);
+    }
+    assert!((rolling.coverage()[&2] - 0.1).abs() < 1e-12);
+}
+
+#[test]
+fn long_gap_skips_to_window() {
+    let mut rolling = RollingCorrelation::new(1, Duration::seconds(10), FillPolicy::Ffill { limit: None }).unwrap();
+    rolling.insert(&point(1, 1, 1.0));
+    rolling.insert(&point(1, 2, 2.0));
+    rolling.advance(START + 1000);
+    // 1 時間後まで進めても窓のバケット数だけ入れる
+    assert_eq!(rolling.advance(START + 3_600_000), 10);
+    assert_eq!(rolling.len(), 10);
+}
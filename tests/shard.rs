@@ -0,0 +1,113 @@
+use chrono::DateTime;
+use kkcrypto::db::{candle_granularity, candle_key, shard_index, shard_urls, Database, DEFAULT_BATCH_SIZE};
+use kkcrypto::models::{market_type::MarketType, trade_candle::TradeCandle};
+use mongodb::options::TimeseriesGranularity;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn shard_index_is_stable_and_spread() {
+    // 既存データの配置が変わらないように値を固定する
+    assert_eq!(shard_index("BTCUSDT", 1), 0);
+    assert_eq!(shard_index("BTCUSDT", 3), 0);
+    assert_eq!(shard_index("SOLUSDT", 3), 2);
+    let symbols: Vec<String> = (0..300).map(|i| format!("SYM{}USDT", i)).collect();
+    let mut counts = [0; 3];
+    for symbol in &symbols {
+        counts[shard_index(symbol, 3)] += 1;
+    }
+    assert!(counts.iter().all(|&c| c > 60), "{:?}", counts);
+}
+
+#[test]
+fn parse_shard_urls() {
+    assert_eq!(
+        shard_urls(Some("mongodb://a:27017/trade, ,mongodb://b:27017/trade_b")),
+        vec!["mongodb://a:27017/trade".to_string(), "mongodb://b:27017/trade_b".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn batched_candle_writes_skip_unsupported_periods() {
+    let db = Database::new("", false).await.unwrap();
+    assert_eq!(db.batch_size(), DEFAULT_BATCH_SIZE);
+    let db = db.with_batching(0, Duration::ZERO);
+    assert_eq!(db.batch_size(), 1);
+    assert!(db.batch_latency() > Duration::ZERO);
+
+    let timestamp = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+    let candles: Vec<TradeCandle> = [60, 0, 60]
+        .into_iter()
+        .map(|period| TradeCandle::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), timestamp, period))
+        .collect();
+    db.insert_trade_candles(&candles).await.unwrap();
+    assert!(db.is_healthy());
+}
+
+#[test]
+fn candle_collections_use_schema_granularity() {
+    // schema.mongo.js: candles_1m は seconds, ha_candles_5m は minutes
+    assert_eq!(candle_granularity(1), TimeseriesGranularity::Seconds);
+    assert_eq!(candle_granularity(60), TimeseriesGranularity::Seconds);
+    assert_eq!(candle_granularity(300), TimeseriesGranularity::Minutes);
+    assert_eq!(candle_granularity(3600), TimeseriesGranularity::Minutes);
+    assert_eq!(candle_granularity(14400), TimeseriesGranularity::Hours);
+}
+
+#[tokio::test]
+async fn candles_are_keyed_by_symbol_and_time() {
+    let timestamp = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+    let mut candle = TradeCandle::new("bybit".to_string(), MarketType::Spot, "BTCUSDT".to_string(), timestamp, 60);
+    let key = candle_key(&candle.to_timeseries_document()).unwrap();
+    // 訂正した revision も同じキー
+    candle.revision = 1;
+    candle.ask_volume = 1.0;
+    assert_eq!(candle_key(&candle.to_timeseries_document()).unwrap(), key);
+    assert_eq!(key, (6, 1_700_000_040_000));
+
+    // master.csv にない symbol は symbol_id 0 の足を上書きしないように書き込まない (エラーにはせず落とす)
+    let db = Database::new("", false).await.unwrap();
+    db.insert_trade_candle(&candle).await.unwrap();
+    let unknown = TradeCandle::new("bybit".to_string(), MarketType::Spot, "NOPEUSDT".to_string(), timestamp, 60);
+    db.insert_trade_candle(&unknown).await.unwrap();
+}
+
+/// tracing の出力を溜める writer
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn unlisted_symbols_are_warned_once() {
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt().with_writer(move || writer.clone()).with_ansi(false).finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let db = Database::new("", false).await.unwrap();
+    let timestamp = DateTime::from_timestamp(1_700_000_040, 0).unwrap();
+    let candles: Vec<TradeCandle> = ["BTCUSDT", "NOPEUSDT", "NOPEUSDT"]
+        .into_iter()
+        .map(|symbol| TradeCandle::new("bybit".to_string(), MarketType::Linear, symbol.to_string(), timestamp, 60))
+        .collect();
+    // バッチ・1 本ずつの書き込みのたびには警告しない
+    for _ in 0..3 {
+        db.insert_trade_candles(&candles).await.unwrap();
+        db.insert_trade_candle(&candles[1]).await.unwrap();
+    }
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    let warnings: Vec<&str> = logs.lines().filter(|line| line.contains("not in master.csv")).collect();
+    assert_eq!(warnings.len(), 1, "{}", logs);
+    assert!(warnings[0].contains("WARN") && warnings[0].contains("NOPEUSDT"));
+}
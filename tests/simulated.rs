@@ -0,0 +1,162 @@
+#![cfg(feature = "execution")]
+
+mod common;
+
+use chrono::Duration;
+use common::at_ms;
+use kkcrypto::execution::{
+    order::{CancelRequest, OrderRequest, OrderSide, TimeInForce},
+    risk::{RiskGuard, RiskLimits, RiskViolation},
+    simulated::{SimulatedConfig, SimulatedExchange, SlippageModel},
+    ExecutionClient,
+};
+use kkcrypto::models::{market_type::MarketType, trade::Trade};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+const SYMBOL: &str = "BTCUSDT";
+
+fn trade(ms: i64, price: f64) -> Trade {
+    Trade { price, ..common::trade(SYMBOL, &ms.to_string(), at_ms(ms)) }
+}
+
+fn exchange(config: SimulatedConfig) -> SimulatedExchange {
+    let exchange = SimulatedExchange::new(config);
+    exchange.on_trade(&trade(0, 100.0));
+    exchange
+}
+
+#[tokio::test]
+async fn market_order_fills_after_latency_with_slippage() {
+    let exchange = exchange(SimulatedConfig {
+        latency: Duration::milliseconds(50),
+        slippage: SlippageModel::FixedBps(10.0),
+        ..Default::default()
+    });
+    exchange.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 2.0)).await.unwrap();
+
+    // 遅延中の約定では約定しない
+    assert!(exchange.on_trade(&trade(10, 101.0)).is_empty());
+
+    let fills = exchange.on_trade(&trade(60, 102.0));
+    assert_eq!(fills.len(), 1);
+    assert!((fills[0].price - 102.0 * 1.001).abs() < 1e-9);
+    assert_eq!(fills[0].quantity, 2.0);
+    assert_eq!(fills[0].timestamp, at_ms(60));
+    assert!(exchange.open_orders(&MarketType::Linear, None).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn limit_order_fills_when_price_reaches_limit() {
+    let exchange = exchange(SimulatedConfig::default());
+    exchange.place_order(&OrderRequest::limit(MarketType::Linear, SYMBOL, OrderSide::Buy, 1.0, 99.0)).await.unwrap();
+
+    assert!(exchange.on_trade(&trade(10, 99.5)).is_empty());
+    let fills = exchange.on_trade(&trade(20, 98.5));
+    assert_eq!(fills.len(), 1);
+    assert_eq!(fills[0].price, 99.0);
+}
+
+#[tokio::test]
+async fn post_only_and_cancel() {
+    let exchange = exchange(SimulatedConfig::default());
+
+    let mut crossing = OrderRequest::limit(MarketType::Linear, SYMBOL, OrderSide::Buy, 1.0, 101.0);
+    crossing.time_in_force = TimeInForce::PostOnly;
+    assert!(exchange.place_order(&crossing).await.is_err());
+
+    let ack = exchange.place_order(&OrderRequest::limit(MarketType::Linear, SYMBOL, OrderSide::Sell, 1.0, 105.0)).await.unwrap();
+    assert_eq!(exchange.open_orders(&MarketType::Linear, Some(SYMBOL)).await.unwrap().len(), 1);
+    exchange
+        .cancel_order(&CancelRequest { market_type: MarketType::Linear, symbol: SYMBOL.to_string(), order_id: ack.order_id })
+        .await
+        .unwrap();
+    assert!(exchange.on_trade(&trade(10, 106.0)).is_empty());
+}
+
+#[tokio::test]
+async fn positions_and_realized_pnl() {
+    let exchange = exchange(SimulatedConfig {
+        fee_rate: 0.001,
+        initial_balance: 1000.0,
+        ..Default::default()
+    });
+
+    exchange.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 2.0)).await.unwrap();
+    exchange.on_trade(&trade(10, 100.0));
+    exchange.on_trade(&trade(20, 110.0));
+
+    let positions = exchange.positions(&MarketType::Linear).await.unwrap();
+    assert_eq!(positions.len(), 1);
+    assert_eq!(positions[0].size, 2.0);
+    assert_eq!(positions[0].entry_price, Some(100.0));
+    assert!((positions[0].unrealized_pnl - 20.0).abs() < 1e-9);
+
+    // 3 売ってドテン: 2 決済 (+20) して 1 ショート
+    exchange.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Sell, 3.0)).await.unwrap();
+    exchange.on_trade(&trade(30, 110.0));
+
+    let positions = exchange.positions(&MarketType::Linear).await.unwrap();
+    assert_eq!(positions[0].size, -1.0);
+    assert_eq!(positions[0].entry_price, Some(110.0));
+
+    let fees = 100.0 * 2.0 * 0.001 + 110.0 * 3.0 * 0.001;
+    assert!((exchange.cash() - (1000.0 + 20.0 - fees)).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn run_streams_fills() {
+    let exchange = Arc::new(exchange(SimulatedConfig::default()));
+    exchange.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Sell, 1.0)).await.unwrap();
+
+    let (trade_tx, trade_rx) = mpsc::channel(10);
+    let (fill_tx, mut fill_rx) = mpsc::channel(10);
+    let handle = tokio::spawn(exchange.clone().run(trade_rx, fill_tx));
+
+    trade_tx.send(trade(10, 100.5)).await.unwrap();
+    let fill = fill_rx.recv().await.unwrap();
+    assert_eq!(fill.side, OrderSide::Sell);
+    assert_eq!(fill.price, 100.5);
+
+    drop(trade_tx);
+    handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn risk_guard_rejects_over_limit_orders() {
+    let limits = RiskLimits::parse("max_notional=500,max_position=3,collar=0.02").unwrap();
+    let guard = RiskGuard::new(Box::new(exchange(SimulatedConfig::default())), limits);
+
+    // 参照価格がなければ拒否
+    let err = guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 1.0)).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RiskViolation>(), Some(RiskViolation::NoReferencePrice { .. })));
+
+    guard.set_reference_price(SYMBOL, 100.0);
+    guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 1.0)).await.unwrap();
+
+    let err = guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 6.0)).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RiskViolation>(), Some(RiskViolation::OrderNotional { .. })));
+
+    let err = guard.place_order(&OrderRequest::limit(MarketType::Linear, SYMBOL, OrderSide::Buy, 1.0, 90.0)).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RiskViolation>(), Some(RiskViolation::PriceCollar { .. })));
+
+    guard.kill_switch().store(true, std::sync::atomic::Ordering::SeqCst);
+    let err = guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Sell, 1.0)).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RiskViolation>(), Some(RiskViolation::KillSwitch)));
+}
+
+#[tokio::test]
+async fn risk_guard_limits_position() {
+    let exchange = Arc::new(exchange(SimulatedConfig::default()));
+    let limits = RiskLimits::parse("max_position.BTCUSDT=2").unwrap();
+    let guard = RiskGuard::new(Box::new(exchange.clone()), limits);
+
+    guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 2.0)).await.unwrap();
+    exchange.on_trade(&trade(10, 100.0));
+
+    let err = guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Buy, 0.5)).await.unwrap_err();
+    assert!(matches!(err.downcast_ref::<RiskViolation>(), Some(RiskViolation::Position { .. })));
+
+    // ポジションを減らす注文は通す
+    guard.place_order(&OrderRequest::market(MarketType::Linear, SYMBOL, OrderSide::Sell, 1.0)).await.unwrap();
+}
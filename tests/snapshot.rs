@@ -0,0 +1,48 @@
+use chrono::Utc;
+use kkcrypto::models::{market_event::MarketEvent, market_type::MarketType, trade::{Side, Trade}};
+use kkcrypto::utils::snapshot::CollectorSnapshot;
+use kkcrypto::utils::trade_candle_builder::{CandleBuilderSnapshot, TradeCandleBuilder};
+use tokio::sync::mpsc;
+
+// 60 秒足の builder を起動して約定を送り, 集計中の足を取り出す
+async fn snapshot_after(trades: Vec<Trade>, restore: Option<CandleBuilderSnapshot>) -> CandleBuilderSnapshot {
+    let (event_tx, event_rx) = mpsc::channel::<MarketEvent>(16);
+    let (output_tx, _output_rx) = mpsc::channel(16);
+    let mut builder = TradeCandleBuilder::new(event_rx, output_tx, vec![60]);
+    if let Some(snapshot) = restore {
+        builder = builder.with_snapshot(snapshot);
+    }
+    let control = builder.snapshot_control();
+    let handle = tokio::spawn(builder.start());
+    for trade in trades {
+        event_tx.send(trade.into()).await.unwrap();
+    }
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let snapshot = control.snapshot(true).await.unwrap();
+    handle.await.unwrap();  // stop = true で builder が終了する
+    snapshot
+}
+
+#[tokio::test]
+async fn open_candles_survive_restart() {
+    // 分の境界をまたいで別の足にならないよう, 出力されない先の時刻に固定する
+    let timestamp = Utc::now() + chrono::Duration::hours(1);
+    let trade = |price: f64, side| Trade::new("bybit".to_string(), MarketType::Linear, "BTCUSDT".to_string(), price.to_string(), price, 1.0, side, timestamp);
+    let before = snapshot_after(vec![trade(100.0, Side::Buy), trade(99.0, Side::Sell)], None).await;
+    assert_eq!(before.buffers.len(), 1);
+
+    // ファイル経由で引き継ぎ, 再起動後の約定は同じ足に加わる
+    let path = std::env::temp_dir().join(format!("kkcrypto_snapshot_{}.json", std::process::id()));
+    CollectorSnapshot::new("bybit", MarketType::Linear, vec!["BTCUSDT".to_string()], vec![60], before).save(&path).unwrap();
+    let loaded = CollectorSnapshot::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(loaded.check("bybit", &MarketType::Linear).is_ok());
+    assert!(loaded.check("binance", &MarketType::Linear).is_err());
+    assert_eq!(loaded.merge_symbols(vec!["ETHUSDT".to_string()]), vec!["ETHUSDT", "BTCUSDT"]);
+
+    let after = snapshot_after(vec![trade(101.0, Side::Buy)], Some(loaded.candles)).await;
+    assert_eq!(after.buffers.len(), 1);
+    let buffer = &after.buffers[0];
+    assert_eq!((buffer.ask_count, buffer.bid_count, buffer.ask_volume), (2, 1, 2.0));
+    assert_eq!(buffer.ask_price, Some(100.5));
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::at;
+use kkcrypto::models::{market_event::MarketEvent, trade::{Side, Trade}};
+use kkcrypto::utils::sqlite_sink::{month_path, open_database, SqliteStore};
+
+fn candle(close: f64, revision: u32) -> MarketEvent {
+    let mut candle = common::candle("BTCUSDT", at(60), 60);
+    candle.close = Some(close);
+    candle.revision = revision;
+    MarketEvent::Candle(candle)
+}
+
+#[test]
+fn monthly_files_and_revision_upserts() {
+    let dir = std::env::temp_dir().join(format!("kkcrypto_sqlite_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut store = SqliteStore::new(dir.clone());
+    let trade = Trade { side: Side::Sell, ..common::trade("BTCUSDT", "1", at(-86400)) };
+
+    // 前の月 (5/31) の約定は別のファイル
+    assert_eq!(store.write(&[candle(100.0, 0), MarketEvent::Trade(trade), candle(101.0, 1)]).unwrap(), 3);
+    // 古い revision では上書きしない
+    store.write(&[candle(99.0, 0)]).unwrap();
+
+    let june = open_database(&month_path(&dir, at(0))).unwrap();
+    let (count, close, revision): (i64, f64, u32) = june
+        .query_row("SELECT COUNT(*), MAX(close), MAX(revision) FROM candles", [], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap();
+    assert_eq!((count, close, revision), (1, 101.0, 1));
+    let mode: String = june.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+    assert_eq!(mode, "wal");
+
+    let may = open_database(&month_path(&dir, at(-86400))).unwrap();
+    let side: String = may.query_row("SELECT side FROM trades", [], |row| row.get(0)).unwrap();
+    assert_eq!(side, "sell");
+    drop((june, may, store));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use kkcrypto::models::trade::Trade;
+use kkcrypto::utils::stale_feed::{FeedAlert, StaleFeedConfig, StaleFeedMonitor, StaleKind};
+
+fn at(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+}
+
+fn trade(symbol: &str, seconds: i64) -> Trade {
+    Trade { received_at: at(seconds), ..common::trade(symbol, &seconds.to_string(), at(seconds)) }
+}
+
+fn monitor(symbols: &[&str]) -> StaleFeedMonitor {
+    let symbols: Vec<String> = symbols.iter().map(|s| s.to_string()).collect();
+    StaleFeedMonitor::new("bybit", &symbols, StaleFeedConfig::new(Duration::seconds(30), 10.0)).with_started_at(at(0))
+}
+
+#[test]
+fn quiet_symbols_get_a_longer_threshold_than_active_ones() {
+    let mut monitor = monitor(&["BTCUSDT", "XRPUSDT"]);
+    // BTCUSDT は 1 秒ごと, XRPUSDT は 10 秒ごとに約定する
+    for s in 0..=100 {
+        monitor.record(&trade("BTCUSDT", s));
+        if s % 10 == 0 {
+            monitor.record(&trade("XRPUSDT", s));
+        }
+    }
+    // 40 秒止まると BTCUSDT (閾値 30 秒) だけ異常, XRPUSDT (閾値 100 秒) は閑散の範囲
+    for s in 101..=140 {
+        if s % 10 == 0 {
+            monitor.record(&trade("XRPUSDT", s));
+        }
+    }
+    let alerts = monitor.check(at(140));
+    assert_eq!(alerts.len(), 1);
+    match &alerts[0] {
+        FeedAlert::Stale { symbol, kind, silence, threshold } => {
+            assert_eq!(symbol, "BTCUSDT");
+            assert_eq!(*kind, StaleKind::Subscription);
+            assert_eq!(*silence, Duration::seconds(40));
+            assert_eq!(*threshold, Duration::seconds(30));
+        }
+        alert => panic!("unexpected {:?}", alert),
+    }
+    // 同じ停止は 1 度だけ知らせる
+    assert!(monitor.check(at(145)).is_empty());
+    assert_eq!(monitor.stale_count(), 1);
+
+    assert_eq!(monitor.record(&trade("BTCUSDT", 150)), Some(FeedAlert::Resumed { symbol: "BTCUSDT".to_string(), silence: Duration::seconds(50) }));
+    assert_eq!(monitor.stale_count(), 0);
+}
+
+#[test]
+fn all_symbols_silent_is_a_feed_problem_and_unseen_symbols_are_flagged() {
+    let mut monitor = monitor(&["BTCUSDT", "TYPOUSDT"]);
+    for s in 0..=10 {
+        monitor.record(&trade("BTCUSDT", s));
+    }
+    let alerts = monitor.check(at(60));
+    let kinds: Vec<(String, StaleKind)> = alerts
+        .iter()
+        .map(|alert| match alert {
+            FeedAlert::Stale { symbol, kind, .. } => (symbol.clone(), *kind),
+            alert => panic!("unexpected {:?}", alert),
+        })
+        .collect();
+    assert_eq!(kinds, vec![("BTCUSDT".to_string(), StaleKind::Feed), ("TYPOUSDT".to_string(), StaleKind::NoTrades)]);
+}
@@ -0,0 +1,186 @@
+mod common;
+
+use async_trait::async_trait;
+use kkcrypto::db::Database;
+use kkcrypto::models::{market_event::MarketEvent, trade::Trade, trade_candle::TradeCandle};
+use kkcrypto::utils::mongo_sink::MongoCandleSink;
+use kkcrypto::utils::storage_sink::{SinkFanout, StorageSink};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 書いたイベントの種類を記録するシンク (fail なら全て失敗する)
+struct RecordingSink {
+    name: &'static str,
+    trades: bool,
+    fail: bool,
+    written: Mutex<Vec<&'static str>>,
+}
+
+impl RecordingSink {
+    fn new(name: &'static str, trades: bool, fail: bool) -> Arc<Self> {
+        Arc::new(Self { name, trades, fail, written: Mutex::new(Vec::new()) })
+    }
+
+    fn record(&self, kind: &'static str) -> anyhow::Result<()> {
+        if self.fail {
+            return Err(anyhow::anyhow!("{} is down", self.name));
+        }
+        self.written.lock().unwrap().push(kind);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StorageSink for RecordingSink {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn stores_trades(&self) -> bool {
+        self.trades
+    }
+
+    async fn write_candle(&self, _candle: &TradeCandle) -> anyhow::Result<()> {
+        self.record("candle")
+    }
+
+    async fn write_trade(&self, _trade: &Trade) -> anyhow::Result<()> {
+        self.record("trade")
+    }
+
+    async fn healthcheck(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn trade() -> MarketEvent {
+    MarketEvent::Trade(common::trade("BTCUSDT", "1", common::at(60)))
+}
+
+fn candle() -> MarketEvent {
+    MarketEvent::Candle(common::candle("BTCUSDT", common::at(60), 60))
+}
+
+#[tokio::test]
+async fn fanout_writes_each_sink_independently() {
+    let candles_only = RecordingSink::new("candles_only", false, false);
+    let all = RecordingSink::new("all", true, false);
+    let failing = RecordingSink::new("failing", true, true);
+    let fanout = SinkFanout::new().with_sink(candles_only.clone()).with_sink(all.clone()).with_sink(failing.clone());
+    assert_eq!(fanout.names(), vec!["candles_only", "all", "failing"]);
+
+    let (input_tx, input_rx) = mpsc::channel(10);
+    let (output_tx, mut output_rx) = mpsc::channel(10);
+    tokio::spawn(fanout.clone().run(input_rx, output_tx));
+    input_tx.send(trade()).await.unwrap();
+    input_tx.send(candle()).await.unwrap();
+
+    // 全てのイベントが後段に流れる
+    assert_eq!(output_rx.recv().await.unwrap().kind(), "trade");
+    assert_eq!(output_rx.recv().await.unwrap().kind(), "candle");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(*candles_only.written.lock().unwrap(), vec!["candle"]);
+    assert_eq!(*all.written.lock().unwrap(), vec!["trade", "candle"]);
+    let statuses = fanout.statuses();
+    assert!(statuses[1].healthy);
+    assert_eq!(statuses[1].written, 2);
+    assert!(!statuses[2].healthy);
+    assert_eq!(statuses[2].failed, 2);
+    assert_eq!(statuses[2].last_error.as_deref(), Some("failing is down"));
+}
+
+#[tokio::test]
+async fn mongo_candle_sink_with_dummy_database() {
+    let db = Arc::new(Database::new("", false).await.unwrap());
+    // --update なしなら MongoDB のシンクは加えない
+    assert!(MongoCandleSink::fanout(&db, "bybit").is_empty());
+
+    let sink = MongoCandleSink::new(db, "bybit");
+    assert_eq!(sink.name(), "mongodb");
+    assert!(!sink.stores_trades());
+    assert!(sink.healthcheck().await.is_ok());
+    if let MarketEvent::Candle(candle) = candle() {
+        assert!(sink.write_candle(&candle).await.is_ok());
+    }
+    // batch_size に届くまでは溜めるだけなので, flush で書けるまで書き込み数に数えない
+    assert_eq!(sink.written(), Some(0));
+    assert!(sink.flush().await.is_ok());
+    assert_eq!(sink.written(), Some(1));
+}
+
+#[tokio::test]
+async fn buffered_candles_are_not_counted_until_flushed() {
+    let db = Database::new("", false).await.unwrap();
+    let db = Arc::new(db.with_batching(10, Duration::from_millis(500)));
+    let fanout = SinkFanout::new().with_sink(Arc::new(MongoCandleSink::new(db, "bybit")));
+    let (input_tx, input_rx) = mpsc::channel(10);
+    let (output_tx, mut output_rx) = mpsc::channel(10);
+    tokio::spawn(fanout.clone().run(input_rx, output_tx));
+    for _ in 0..3 {
+        input_tx.send(candle()).await.unwrap();
+        output_rx.recv().await.unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(fanout.statuses()[0].written, 0);
+
+    // batch_latency ごとの flush で書けた分だけ数える
+    tokio::time::timeout(Duration::from_secs(3), async {
+        while fanout.statuses()[0].written < 3 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("flushed candles were not counted");
+    assert_eq!(fanout.statuses()[0].written, 3);
+}
+
+#[tokio::test]
+async fn finish_waits_for_the_queued_events() {
+    let all = RecordingSink::new("all", true, false);
+    let fanout = SinkFanout::new().with_sink(all.clone());
+    let (input_tx, input_rx) = mpsc::channel(10);
+    let (output_tx, mut output_rx) = mpsc::channel(10);
+    let stage = tokio::spawn(fanout.clone().run(input_rx, output_tx));
+    input_tx.send(candle()).await.unwrap();
+    drop(input_tx);
+    assert_eq!(output_rx.recv().await.unwrap().kind(), "candle");
+    stage.await.unwrap();
+
+    fanout.finish().await;
+    assert_eq!(*all.written.lock().unwrap(), vec!["candle"]);
+}
+
+#[tokio::test]
+async fn failing_mongodb_does_not_stall_the_other_sinks() {
+    // 接続できない MongoDB (書き込みは再試行を含めて十数秒かかって失敗する)
+    let db = Database::connect("mongodb://127.0.0.1:1/?serverSelectionTimeoutMS=100&connectTimeoutMS=100").await.unwrap();
+    let db = Arc::new(db.with_batching(1, Duration::from_millis(10)));
+    let all = RecordingSink::new("all", true, false);
+    let fanout = SinkFanout::new().with_sink(Arc::new(MongoCandleSink::new(db, "bybit"))).with_sink(all.clone());
+    assert_eq!(fanout.names(), vec!["mongodb", "all"]);
+
+    let (input_tx, input_rx) = mpsc::channel(10);
+    let (output_tx, mut output_rx) = mpsc::channel(10);
+    tokio::spawn(fanout.clone().run(input_rx, output_tx));
+    tokio::spawn(async move {
+        for _ in 0..100 {
+            input_tx.send(candle()).await.unwrap();
+            input_tx.send(trade()).await.unwrap();
+        }
+    });
+
+    // MongoDB の書き込みが終わる前に, 後段と他のシンクには全て届く
+    tokio::time::timeout(Duration::from_secs(2), async {
+        for _ in 0..200 {
+            output_rx.recv().await.unwrap();
+        }
+        while all.written.lock().unwrap().len() < 200 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("the other sinks were stalled by MongoDB");
+    assert_eq!(fanout.statuses()[1].written, 200);
+}
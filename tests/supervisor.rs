@@ -0,0 +1,84 @@
+use kkcrypto::utils::supervisor::{catch_panic, Supervisor};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn catch_panic_returns_the_panic_message() {
+    assert_eq!(catch_panic(async { 1 }).await, Ok(1));
+    let result = catch_panic(async {
+        let values: Vec<u32> = Vec::new();
+        values.first().copied().expect("no values")
+    })
+    .await;
+    assert_eq!(result, Err("no values".to_string()));
+}
+
+#[tokio::test]
+async fn backoff_doubles_with_each_restart_in_the_window() {
+    let mut supervisor = Supervisor::new("test").with_limit(3, Duration::from_secs(600));
+    assert_eq!(supervisor.backoff(), Duration::from_secs(1));
+    assert!(supervisor.restart("first").await);
+    assert_eq!(supervisor.backoff(), Duration::from_secs(1));
+    assert!(supervisor.restart("second").await);
+    assert_eq!(supervisor.backoff(), Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn restarts_a_panicked_stage_on_the_same_receiver() {
+    let (event_tx, event_rx) = mpsc::channel::<u32>(10);
+    let (output_tx, mut output_rx) = mpsc::channel::<u32>(10);
+    let starts = Arc::new(AtomicUsize::new(0));
+
+    let counter = starts.clone();
+    let stage = tokio::spawn(Supervisor::new("doubler").run_stage(event_rx, move |mut receiver: mpsc::Receiver<u32>| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        let output_tx = output_tx.clone();
+        async move {
+            while let Some(value) = receiver.recv().await {
+                if value == 0 {
+                    panic!("zero");
+                }
+                output_tx.send(value * 2).await.unwrap();
+            }
+        }
+    }));
+
+    // panic した段の中継チャネルに残ったイベントは失われるので, 再起動してから送る
+    event_tx.send(1).await.unwrap();
+    event_tx.send(0).await.unwrap();
+    while starts.load(Ordering::SeqCst) < 2 {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    event_tx.send(2).await.unwrap();
+    event_tx.send(3).await.unwrap();
+    drop(event_tx);
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(tokio::time::timeout(Duration::from_secs(5), output_rx.recv()).await.unwrap().unwrap());
+    }
+    assert_eq!(received, vec![2, 4, 6]);
+    tokio::time::timeout(Duration::from_secs(5), stage).await.unwrap().unwrap();
+    assert_eq!(starts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn returns_when_the_stage_stops_by_itself() {
+    let (event_tx, event_rx) = mpsc::channel::<u32>(10);
+    let starts = Arc::new(AtomicUsize::new(0));
+
+    let counter = starts.clone();
+    let stage = tokio::spawn(Supervisor::new("one shot").run_stage(event_rx, move |mut receiver: mpsc::Receiver<u32>| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            receiver.recv().await;
+        }
+    }));
+
+    event_tx.send(1).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), stage).await.unwrap().unwrap();
+    assert_eq!(starts.load(Ordering::SeqCst), 1);
+    drop(event_tx);
+}
@@ -0,0 +1,8 @@
+use kkcrypto::utils::symbol_manager::parse_symbol_spec;
+
+#[test]
+fn parses_symbol_spec() {
+    assert_eq!(parse_symbol_spec("Bybit:linear:BTCUSDT").unwrap(), ("bybit".to_string(), "linear".to_string(), "BTCUSDT".to_string()));
+    assert!(parse_symbol_spec("BTCUSDT").is_err());
+    assert!(parse_symbol_spec("bybit::BTCUSDT").is_err());
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::at_ms;
+use kkcrypto::models::trade::{Side, Trade};
+use kkcrypto::utils::throttle::{ThrottleConfig, ThrottleRule, TradeThrottle};
+
+fn trade(symbol: &str, ms: i64, price: f64, quantity: f64, side: Side) -> Trade {
+    Trade { price, quantity, side, ..common::trade(symbol, &ms.to_string(), at_ms(ms)) }
+}
+
+#[test]
+fn parse_spec() {
+    let config = ThrottleConfig::parse("BTCUSDT=50ms, ETHUSDT=1/10, *=20ms").unwrap();
+    assert_eq!(config.rule("BTCUSDT"), Some(ThrottleRule::Conflate { window_ms: 50 }));
+    assert_eq!(config.rule("ETHUSDT"), Some(ThrottleRule::Sample { every: 10 }));
+    assert_eq!(config.rule("XRPUSDT"), Some(ThrottleRule::Conflate { window_ms: 20 }));
+    assert_eq!(ThrottleConfig::parse("BTCUSDT=50ms").unwrap().rule("ETHUSDT"), None);
+
+    assert!(ThrottleConfig::parse("BTCUSDT=0ms").is_err());
+    assert!(ThrottleConfig::parse("BTCUSDT=1/0").is_err());
+    assert!(ThrottleConfig::parse("BTCUSDT").is_err());
+    assert!(ThrottleConfig::parse("").is_err());
+}
+
+#[test]
+fn conflate_within_window_per_side() {
+    let mut throttle = TradeThrottle::new(ThrottleConfig::parse("*=50ms").unwrap());
+    assert!(throttle.push(trade("BTCUSDT", 0, 100.0, 1.0, Side::Buy)).is_empty());
+    assert!(throttle.push(trade("BTCUSDT", 10, 103.0, 2.0, Side::Buy)).is_empty());
+    assert!(throttle.push(trade("BTCUSDT", 20, 90.0, 1.0, Side::Sell)).is_empty());
+
+    // 次の区間の約定で前の区間が確定する
+    let emitted = throttle.push(trade("BTCUSDT", 60, 104.0, 1.0, Side::Buy));
+    assert_eq!(emitted.len(), 1);
+    assert!((emitted[0].price - 102.0).abs() < 1e-9);
+    assert_eq!(emitted[0].quantity, 3.0);
+    assert_eq!(emitted[0].trade_id, "10");
+    assert_eq!(emitted[0].timestamp, at_ms(10));
+
+    // 区間が閉じた売りは時刻経過で確定し, 区間内の買いは残る
+    let flushed = throttle.flush_due(at_ms(55));
+    assert_eq!(flushed.len(), 1);
+    assert!(matches!(flushed[0].side, Side::Sell));
+    assert_eq!(throttle.flush_due(at_ms(100)).len(), 1);
+    assert!(throttle.flush_due(at_ms(1000)).is_empty());
+}
+
+#[test]
+fn sample_keeps_every_nth_and_scales_quantity() {
+    let mut throttle = TradeThrottle::new(ThrottleConfig::parse("ETHUSDT=1/3").unwrap());
+    let kept: Vec<Trade> = (0..7)
+        .flat_map(|i| throttle.push(trade("ETHUSDT", i, 10.0, 0.5, Side::Buy)))
+        .collect();
+    assert_eq!(kept.iter().map(|t| t.trade_id.as_str()).collect::<Vec<_>>(), vec!["0", "3", "6"]);
+    assert!(kept.iter().all(|t| t.quantity == 1.5));
+
+    // 設定のない symbol はそのまま通す
+    assert_eq!(throttle.push(trade("BTCUSDT", 0, 100.0, 1.0, Side::Buy)).len(), 1);
+}
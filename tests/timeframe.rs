@@ -0,0 +1,57 @@
+use kkcrypto::db::candle_collection_name;
+use kkcrypto::utils::timeframe;
+
+#[test]
+fn parse_timeframe_specs() {
+    assert_eq!(timeframe::parse("60").unwrap(), 60);
+    assert_eq!(timeframe::parse("90s").unwrap(), 90);
+    assert_eq!(timeframe::parse("5m").unwrap(), 300);
+    assert_eq!(timeframe::parse("4h").unwrap(), 14400);
+    assert_eq!(timeframe::parse("2d").unwrap(), 172800);
+    assert_eq!(timeframe::parse_list("1, 5m,1h").unwrap(), vec![1, 300, 3600]);
+    for spec in ["", "0", "0m", "5x", "m", "1.5m", "-1", "50000d"] {
+        assert!(timeframe::parse(spec).is_err(), "{}", spec);
+    }
+    assert!(timeframe::parse_list(" , ").is_err());
+}
+
+#[test]
+fn collection_names_follow_the_largest_whole_unit() {
+    assert_eq!(timeframe::label(90), "90s");
+    assert_eq!(timeframe::label(5400), "90m");
+    assert_eq!(candle_collection_name(1).as_deref(), Some("candles_1s"));
+    assert_eq!(candle_collection_name(60).as_deref(), Some("candles_1m"));
+    assert_eq!(candle_collection_name(900).as_deref(), Some("candles_15m"));
+    assert_eq!(candle_collection_name(14400).as_deref(), Some("candles_4h"));
+    assert_eq!(candle_collection_name(86400).as_deref(), Some("candles_1d"));
+    assert_eq!(candle_collection_name(0), None);
+}
+
+#[test]
+fn parse_session_offsets() {
+    assert_eq!(timeframe::parse_session_offset("utc").unwrap(), 0);
+    assert_eq!(timeframe::parse_session_offset("funding").unwrap(), 0);
+    assert_eq!(timeframe::parse_session_offset("JST").unwrap(), 9 * 3600);
+    assert_eq!(timeframe::parse_session_offset("utc+9").unwrap(), 9 * 3600);
+    assert_eq!(timeframe::parse_session_offset("utc-5").unwrap(), -5 * 3600);
+    assert_eq!(timeframe::parse_session_offset("utc+5:30").unwrap(), 5 * 3600 + 1800);
+    for spec in ["", "utc9", "utc+", "utc+15", "utc+5:60", "utc+-3", "est"] {
+        assert!(timeframe::parse_session_offset(spec).is_err(), "{}", spec);
+    }
+}
+
+#[test]
+fn session_offset_moves_daily_and_8h_boundaries() {
+    let at = |s: &str| chrono::DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&chrono::Utc);
+    let timestamp = at("2026-01-01T20:30:00Z");
+    assert_eq!(timeframe::bucket_end(&timestamp, 86400, 0), at("2026-01-02T00:00:00Z"));
+    assert_eq!(timeframe::bucket_end(&timestamp, 28800, 0), at("2026-01-02T00:00:00Z"));
+    // 日本時間の 0 時 (15:00 UTC) で区切る
+    let jst = 9 * 3600;
+    assert_eq!(timeframe::bucket_end(&timestamp, 86400, jst), at("2026-01-02T15:00:00Z"));
+    assert_eq!(timeframe::bucket_end(&at("2026-01-01T14:59:59Z"), 86400, jst), at("2026-01-01T15:00:00Z"));
+    assert_eq!(timeframe::bucket_end(&timestamp, 28800, jst), at("2026-01-01T23:00:00Z"));
+    // 時差で割り切れる時間枠は変わらない
+    assert_eq!(timeframe::bucket_end(&timestamp, 3600, jst), at("2026-01-01T21:00:00Z"));
+    assert_eq!(timeframe::bucket_end(&timestamp, 86400, -5 * 3600), at("2026-01-02T05:00:00Z"));
+}
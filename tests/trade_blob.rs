@@ -0,0 +1,101 @@
+mod common;
+
+use common::at_ms;
+use kkcrypto::models::{market_type::MarketType, trade::{Side, Trade}};
+use kkcrypto::utils::trade_blob::{decode, encode, from_document, to_document, write_csv, TradeCodec};
+
+fn trade(id: usize, millis: i64, side: Side) -> Trade {
+    Trade {
+        price: 67_000.5 + id as f64 * 0.5,
+        quantity: 0.001 * (id % 7 + 1) as f64,
+        side,
+        gateway_timestamp: (id % 2 == 0).then(|| at_ms(millis + 3)),
+        received_at: at_ms(millis + 25),
+        ..common::trade("BTCUSDT", &format!("t-{}", id), at_ms(millis))
+    }
+}
+
+fn trades(count: usize) -> Vec<Trade> {
+    (0..count).map(|i| trade(i, i as i64 * 40, if i % 3 == 0 { Side::Sell } else { Side::Buy })).collect()
+}
+
+fn assert_same(left: &[Trade], right: &[Trade]) {
+    assert_eq!(left.len(), right.len());
+    for (left, right) in left.iter().zip(right) {
+        assert_eq!(left.trade_id, right.trade_id);
+        assert_eq!(left.timestamp, right.timestamp);
+        assert_eq!(left.received_at, right.received_at);
+        assert_eq!(left.gateway_timestamp, right.gateway_timestamp);
+        assert_eq!(left.price, right.price);
+        assert_eq!(left.quantity, right.quantity);
+        assert_eq!(matches!(left.side, Side::Buy), matches!(right.side, Side::Buy));
+        assert_eq!(left.symbol, right.symbol);
+    }
+}
+
+#[test]
+fn encode_decode_roundtrip() {
+    let original = trades(50);
+    for codec in [TradeCodec::Zstd, TradeCodec::None] {
+        let bytes = encode(&original, codec).unwrap();
+        let decoded = decode(&bytes, codec, "bybit", &MarketType::Linear, "BTCUSDT").unwrap();
+        assert_same(&original, &decoded);
+    }
+    // 空のまとまりも読める
+    let bytes = encode(&[], TradeCodec::Zstd).unwrap();
+    assert!(decode(&bytes, TradeCodec::Zstd, "bybit", &MarketType::Linear, "BTCUSDT").unwrap().is_empty());
+}
+
+#[test]
+fn decode_sorts_by_trade_time() {
+    let mut original = trades(3);
+    original.reverse();
+    let decoded = decode(&encode(&original, TradeCodec::None).unwrap(), TradeCodec::None, "bybit", &MarketType::Linear, "BTCUSDT").unwrap();
+    assert_eq!(decoded.iter().map(|trade| trade.trade_id.as_str()).collect::<Vec<_>>(), vec!["t-0", "t-1", "t-2"]);
+}
+
+#[test]
+fn zstd_is_smaller() {
+    let original = trades(2000);
+    let none = encode(&original, TradeCodec::None).unwrap();
+    let zstd = encode(&original, TradeCodec::Zstd).unwrap();
+    assert!(zstd.len() * 3 < none.len(), "zstd {} bytes, none {} bytes", zstd.len(), none.len());
+}
+
+#[test]
+fn decode_rejects_garbage() {
+    assert!(decode(b"not a blob", TradeCodec::None, "bybit", &MarketType::Linear, "BTCUSDT").is_err());
+    let bytes = encode(&trades(10), TradeCodec::None).unwrap();
+    assert!(decode(&bytes[..bytes.len() - 1], TradeCodec::None, "bybit", &MarketType::Linear, "BTCUSDT").is_err());
+}
+
+#[test]
+fn document_roundtrip() {
+    let original = trades(20);
+    let minute = at_ms(0);
+    let doc = to_document(&original, minute, 6, TradeCodec::Zstd).unwrap();
+    assert_eq!(doc.get_i32("count").unwrap(), 20);
+    assert_eq!(doc.get_str("codec").unwrap(), "zstd");
+    assert_eq!(doc.get_document("metadata").unwrap().get_i32("symbol").unwrap(), 6);
+    assert_eq!(doc.get_document("metadata").unwrap().get_i32("ym").unwrap(), 202406);
+    assert_eq!(doc.get_datetime("unixtime").unwrap().timestamp_millis(), minute.timestamp_millis());
+    assert_same(&original, &from_document(&doc, "bybit", &MarketType::Linear, "BTCUSDT").unwrap());
+}
+
+#[test]
+fn writes_csv_rows() {
+    let mut out = Vec::new();
+    write_csv(&mut out, &trades(2)).unwrap();
+    let csv = String::from_utf8(out).unwrap();
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines[0], "timestamp,received_at,gateway_timestamp,trade_id,price,quantity,side");
+    assert_eq!(lines[1], "2024-06-01T00:00:00.000000Z,2024-06-01T00:00:00.025000Z,2024-06-01T00:00:00.003000Z,t-0,67000.5,0.001,sell");
+    assert_eq!(lines[2], "2024-06-01T00:00:00.040000Z,2024-06-01T00:00:00.065000Z,,t-1,67001,0.002,buy");
+}
+
+#[test]
+fn parse_codec() {
+    assert_eq!(TradeCodec::parse("ZSTD").unwrap(), TradeCodec::Zstd);
+    assert_eq!(TradeCodec::parse("none").unwrap(), TradeCodec::None);
+    assert!(TradeCodec::parse("gzip").is_err());
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use chrono::Duration;
+use common::at;
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::parquet_archive::parse_compression;
+use kkcrypto::utils::upload::{completed_periods, latest_revisions, object_path, parquet_bytes, UploadPeriod};
+use polars::prelude::*;
+
+fn candle(seconds: i64, revision: u32) -> TradeCandle {
+    let mut candle = common::candle("BTCUSDT", at(seconds), 60);
+    candle.revision = revision;
+    candle
+}
+
+#[test]
+fn period_parse_and_floor() {
+    assert_eq!(UploadPeriod::parse("Day").unwrap(), UploadPeriod::Day);
+    assert_eq!(UploadPeriod::parse("hour").unwrap(), UploadPeriod::Hour);
+    assert!(UploadPeriod::parse("week").is_err());
+    assert_eq!(UploadPeriod::Day.floor(at(5 * 3600 + 7)), at(0));
+    assert_eq!(UploadPeriod::Hour.floor(at(5 * 3600 + 7)), at(5 * 3600));
+}
+
+#[test]
+fn object_path_follows_layout() {
+    let path = object_path(UploadPeriod::Day.default_layout(), "bybit", "linear", "BTCUSDT", "1m", at(0));
+    assert_eq!(path, "bybit/linear/BTCUSDT/1m/2024/06/2024-06-01.parquet");
+    let path = object_path(UploadPeriod::Hour.default_layout(), "bybit", "linear", "BTCUSDT", "1s", at(5 * 3600));
+    assert_eq!(path, "bybit/linear/BTCUSDT/1s/2024-06-01/05.parquet");
+}
+
+#[test]
+fn completed_periods_wait_for_delay() {
+    let delay = Duration::minutes(5);
+    // 01:04 は 00:00 - 01:00 が終わって 5 分経っていない
+    assert!(completed_periods(at(0), at(3600 + 240), delay, UploadPeriod::Hour).is_empty());
+    assert_eq!(completed_periods(at(0), at(3 * 3600 + 300), delay, UploadPeriod::Hour), vec![at(0), at(3600), at(2 * 3600)]);
+}
+
+#[test]
+fn keeps_latest_revisions() {
+    let candles = latest_revisions(vec![candle(120, 0), candle(60, 1), candle(60, 0), candle(120, 2)]);
+    let kept: Vec<_> = candles.iter().map(|c| (c.timestamp, c.revision)).collect();
+    assert_eq!(kept, vec![(at(60), 1), (at(120), 2)]);
+}
+
+#[test]
+fn parquet_bytes_round_trip() {
+    let bytes = parquet_bytes(&[candle(60, 0), candle(120, 0)], parse_compression("zstd").unwrap()).unwrap();
+    let df = ParquetReader::new(std::io::Cursor::new(bytes)).finish().unwrap();
+    assert_eq!(df.height(), 2);
+    assert_eq!(df.column("symbol").unwrap().str().unwrap().get(0), Some("BTCUSDT"));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use chrono::DateTime;
+use kkcrypto::models::trade_candle::TradeCandle;
+use kkcrypto::utils::write_ahead::WriteAheadQueue;
+use std::io::Write;
+
+fn candle(minute: i64, revision: u32) -> TradeCandle {
+    let mut candle = common::candle("BTCUSDT", DateTime::from_timestamp(1_700_000_000 + minute * 60, 0).unwrap(), 60);
+    candle.revision = revision;
+    candle
+}
+
+#[test]
+fn queued_candles_survive_reopen_and_are_removed_in_order() {
+    let path = std::env::temp_dir().join(format!("kkcrypto_write_ahead_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut queue = WriteAheadQueue::open(&path).unwrap();
+    assert!(queue.is_empty());
+    assert!(queue.load().unwrap().is_empty());
+    queue.push(&candle(0, 0)).unwrap();
+    queue.push(&candle(0, 1)).unwrap();
+    queue.push(&candle(1, 0)).unwrap();
+    // 書き込み途中で落ちた行は読み飛ばす
+    std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"id\":").unwrap();
+
+    // 再起動後も残っている
+    let mut queue = WriteAheadQueue::open(&path).unwrap();
+    assert_eq!(queue.len(), 3);
+    let loaded = queue.load().unwrap();
+    assert_eq!(loaded.iter().map(|c| c.revision).collect::<Vec<_>>(), vec![0, 1, 0]);
+    assert_eq!(loaded[2].timestamp, candle(1, 0).timestamp);
+
+    queue.remove_front(2).unwrap();
+    assert_eq!(queue.len(), 1);
+    assert_eq!(queue.load().unwrap()[0].timestamp, candle(1, 0).timestamp);
+    queue.remove_front(1).unwrap();
+    assert!(queue.is_empty());
+    assert!(!path.exists());
+}